@@ -0,0 +1,463 @@
+use std::collections::HashMap;
+
+use crate::bytecode::{self, Instr, Program, Reg};
+use crate::ir::analysis::AnalysisManager;
+use crate::ir::passes::{DeadFunctionElimination, Pass, TailCallElimination};
+use crate::ir::{self, BlockId, InstKind, Terminator, Type, ValueId};
+use crate::syntax::ast::Literal;
+
+use super::{Backend, BackendError, BackendOptions, BackendResult, resolve_callee};
+
+/// Backend that compiles the SSA module into register bytecode for
+/// [`bytecode::Vm`].
+///
+/// Every SSA value owns one register, so no allocation pass is needed; phis
+/// become moves on the incoming edges, staged through scratch registers when
+/// an edge both reads and writes the same phi registers.
+#[derive(Debug, Default, Clone)]
+pub struct BytecodeBackend;
+
+impl Backend for BytecodeBackend {
+    type Output = Program;
+
+    fn compile(
+        &self,
+        module: &ir::Module,
+        _options: &BackendOptions,
+    ) -> BackendResult<Self::Output> {
+        let mut module = module.clone();
+        let mut analyses = AnalysisManager::new();
+        DeadFunctionElimination::library().run(&mut module, &mut analyses);
+        TailCallElimination.run(&mut module, &mut analyses);
+
+        let mut strings = Strings::default();
+        let mut functions = Vec::with_capacity(module.functions.len());
+        for function in &module.functions {
+            functions.push(FunctionCompiler::compile(&module, &mut strings, function)?);
+        }
+        Ok(Program {
+            name: module.name.join("::"),
+            strings: strings.values,
+            functions,
+        })
+    }
+}
+
+/// Deduplicated string table.
+#[derive(Default)]
+struct Strings {
+    values: Vec<String>,
+    indices: HashMap<String, u32>,
+}
+
+impl Strings {
+    fn intern(&mut self, value: &str) -> u32 {
+        if let Some(index) = self.indices.get(value) {
+            return *index;
+        }
+        let index = self.values.len() as u32;
+        self.values.push(value.to_string());
+        self.indices.insert(value.to_string(), index);
+        index
+    }
+}
+
+/// Runtime operation behind `method` on a `capability` token. Methods keep
+/// their own names except `io.println`, the console's `write_line`.
+fn runtime_operation<'m>(capability: &str, method: &'m str) -> &'m str {
+    match (capability, method) {
+        ("io", "println") => "write_line",
+        _ => method,
+    }
+}
+
+struct FunctionCompiler<'a> {
+    module: &'a ir::Module,
+    strings: &'a mut Strings,
+    function: &'a ir::Function,
+    registers: HashMap<ValueId, Reg>,
+    /// Scratch registers live above the value registers and are reused by
+    /// every edge.
+    scratch_base: Reg,
+    scratch_count: u32,
+    code: Vec<Instr>,
+    block_starts: HashMap<BlockId, u32>,
+    /// Jumps whose target is a block that may not have been emitted yet.
+    fixups: Vec<(usize, BlockId)>,
+}
+
+impl<'a> FunctionCompiler<'a> {
+    fn compile(
+        module: &'a ir::Module,
+        strings: &'a mut Strings,
+        function: &'a ir::Function,
+    ) -> BackendResult<bytecode::Function> {
+        let mut registers = HashMap::new();
+        for param in &function.params {
+            let reg = registers.len() as Reg;
+            registers.insert(param.value, reg);
+        }
+        for inst in function.blocks.iter().flat_map(|block| &block.instructions) {
+            let reg = registers.len() as Reg;
+            registers.insert(inst.id, reg);
+        }
+        let scratch_base = registers.len() as Reg;
+        let mut compiler = FunctionCompiler {
+            module,
+            strings,
+            function,
+            registers,
+            scratch_base,
+            scratch_count: 0,
+            code: Vec::new(),
+            block_starts: HashMap::new(),
+            fixups: Vec::new(),
+        };
+        for (index, block) in function.blocks.iter().enumerate() {
+            compiler
+                .block_starts
+                .insert(block.id, compiler.code.len() as u32);
+            let next = function.blocks.get(index + 1).map(|next| next.id);
+            compiler.compile_block(block, next)?;
+        }
+        for (pc, block) in std::mem::take(&mut compiler.fixups) {
+            let Some(start) = compiler.block_starts.get(&block).copied() else {
+                return Err(BackendError::Internal(format!(
+                    "function '{}' jumps to missing block {}",
+                    function.name, block.0
+                )));
+            };
+            match &mut compiler.code[pc] {
+                Instr::Jump { target } | Instr::JumpUnless { target, .. } => *target = start,
+                _ => unreachable!("fixups only point at jumps"),
+            }
+        }
+        Ok(bytecode::Function {
+            name: function.name.clone(),
+            arity: function.params.len() as u32,
+            registers: scratch_base + compiler.scratch_count,
+            capabilities: function
+                .effect_row
+                .iter()
+                .map(|effect| module.effect_name(*effect).to_string())
+                .collect(),
+            code: compiler.code,
+        })
+    }
+
+    fn reg(&self, value: ValueId) -> BackendResult<Reg> {
+        self.registers.get(&value).copied().ok_or_else(|| {
+            BackendError::Internal(format!(
+                "function '{}' uses undefined value %{}",
+                self.function.name,
+                value.index()
+            ))
+        })
+    }
+
+    fn regs(&self, values: &[ValueId]) -> BackendResult<Vec<Reg>> {
+        values.iter().map(|value| self.reg(*value)).collect()
+    }
+
+    fn compile_block(
+        &mut self,
+        block: &ir::BasicBlock,
+        next: Option<BlockId>,
+    ) -> BackendResult<()> {
+        for inst in &block.instructions {
+            self.compile_instruction(inst)?;
+        }
+        match &block.terminator {
+            Terminator::Return(value) => {
+                let src = value.map(|value| self.reg(value)).transpose()?;
+                self.code.push(Instr::Return { src });
+            }
+            Terminator::Jump(target) => {
+                self.emit_edge_moves(block.id, *target)?;
+                if next != Some(*target) {
+                    self.emit_jump(*target);
+                }
+            }
+            Terminator::Branch {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                let cond = self.reg(*condition)?;
+                let else_moves = self.edge_moves(block.id, *else_block)?;
+                if else_moves.is_empty() {
+                    self.fixups.push((self.code.len(), *else_block));
+                    self.code.push(Instr::JumpUnless { cond, target: 0 });
+                    self.emit_edge_moves(block.id, *then_block)?;
+                    if next != Some(*then_block) {
+                        self.emit_jump(*then_block);
+                    }
+                } else {
+                    let skip = self.code.len();
+                    self.code.push(Instr::JumpUnless { cond, target: 0 });
+                    self.emit_edge_moves(block.id, *then_block)?;
+                    self.emit_jump(*then_block);
+                    let else_start = self.code.len() as u32;
+                    if let Instr::JumpUnless { target, .. } = &mut self.code[skip] {
+                        *target = else_start;
+                    }
+                    self.emit_moves(else_moves);
+                    if next != Some(*else_block) {
+                        self.emit_jump(*else_block);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_jump(&mut self, target: BlockId) {
+        self.fixups.push((self.code.len(), target));
+        self.code.push(Instr::Jump { target: 0 });
+    }
+
+    /// Phi assignments `(dst, src)` performed when control moves from `from`
+    /// to `to`.
+    fn edge_moves(&self, from: BlockId, to: BlockId) -> BackendResult<Vec<(Reg, Reg)>> {
+        let Some(block) = self.function.blocks.iter().find(|block| block.id == to) else {
+            return Err(BackendError::Internal(format!(
+                "function '{}' branches to missing block {}",
+                self.function.name, to.0
+            )));
+        };
+        let mut moves = Vec::new();
+        for inst in &block.instructions {
+            let InstKind::Phi { incomings } = &inst.kind else {
+                continue;
+            };
+            let Some((_, value)) = incomings.iter().find(|(pred, _)| *pred == from) else {
+                return Err(BackendError::Internal(format!(
+                    "phi %{} in '{}' has no incoming value from block {}",
+                    inst.id.index(),
+                    self.function.name,
+                    from.0
+                )));
+            };
+            let (dst, src) = (self.reg(inst.id)?, self.reg(*value)?);
+            if dst != src {
+                moves.push((dst, src));
+            }
+        }
+        Ok(moves)
+    }
+
+    fn emit_edge_moves(&mut self, from: BlockId, to: BlockId) -> BackendResult<()> {
+        let moves = self.edge_moves(from, to)?;
+        self.emit_moves(moves);
+        Ok(())
+    }
+
+    /// Emits the parallel assignment `moves`. When a destination is also a
+    /// source (a loop swapping two phis), every source is first copied into
+    /// scratch registers so no move clobbers a value still to be read.
+    fn emit_moves(&mut self, moves: Vec<(Reg, Reg)>) {
+        let conflicts = moves
+            .iter()
+            .any(|(dst, _)| moves.iter().any(|(_, src)| src == dst));
+        if !conflicts {
+            self.code
+                .extend(moves.into_iter().map(|(dst, src)| Instr::Move { dst, src }));
+            return;
+        }
+        self.scratch_count = self.scratch_count.max(moves.len() as u32);
+        for (offset, (_, src)) in moves.iter().enumerate() {
+            self.code.push(Instr::Move {
+                dst: self.scratch_base + offset as Reg,
+                src: *src,
+            });
+        }
+        for (offset, (dst, _)) in moves.iter().enumerate() {
+            self.code.push(Instr::Move {
+                dst: *dst,
+                src: self.scratch_base + offset as Reg,
+            });
+        }
+    }
+
+    fn compile_instruction(&mut self, inst: &ir::Instruction) -> BackendResult<()> {
+        let dst = self.reg(inst.id)?;
+        let instr = match &inst.kind {
+            InstKind::Literal(literal) => match literal {
+                Literal::Int(value) => Instr::Int { dst, value: *value },
+                Literal::Float(value) => Instr::Float { dst, value: *value },
+                Literal::Bool(value) => Instr::Bool { dst, value: *value },
+                Literal::String(value) => Instr::Str {
+                    dst,
+                    index: self.strings.intern(value),
+                },
+                Literal::Unit => Instr::Unit { dst },
+            },
+            InstKind::Binary { op, lhs, rhs } => Instr::Binary {
+                op: *op,
+                dst,
+                lhs: self.reg(*lhs)?,
+                rhs: self.reg(*rhs)?,
+            },
+            InstKind::Call { func, args } => self.compile_call(dst, func, args)?,
+            InstKind::Record { fields, .. } => {
+                let Type::Record(record) = self.module.type_of(inst.ty) else {
+                    return Err(BackendError::Internal(
+                        "record literal lowered with non-record type".into(),
+                    ));
+                };
+                for (name, _) in fields {
+                    if record.field(name).is_none() {
+                        return Err(BackendError::Unsupported(format!(
+                            "record literal references unknown field '{}'",
+                            name
+                        )));
+                    }
+                }
+                let mut slots = Vec::with_capacity(record.fields.len());
+                for field in &record.fields {
+                    let Some((_, value)) = fields.iter().find(|(name, _)| name == &field.name)
+                    else {
+                        let record_name = record.name.as_deref().unwrap_or("<anonymous record>");
+                        return Err(BackendError::Unsupported(format!(
+                            "record literal for '{record_name}' is missing field '{}'",
+                            field.name
+                        )));
+                    };
+                    slots.push(self.reg(*value)?);
+                }
+                Instr::Record { dst, fields: slots }
+            }
+            InstKind::Field { base, name } => {
+                let base_ty = self.value_type(*base)?;
+                let Type::Record(record) = self.module.type_of(base_ty) else {
+                    return Err(BackendError::unsupported(format!(
+                        "field '{name}' read from a value that is not a record"
+                    )));
+                };
+                let Some(slot) = record.fields.iter().position(|field| &field.name == name) else {
+                    return Err(BackendError::unsupported(format!(
+                        "record has no field '{name}'"
+                    )));
+                };
+                Instr::Field {
+                    dst,
+                    base: self.reg(*base)?,
+                    slot: slot as u32,
+                }
+            }
+            InstKind::Convert { value } => {
+                let src = self.reg(*value)?;
+                let from = self.module.type_of(self.value_type(*value)?);
+                match (from, self.module.type_of(inst.ty)) {
+                    (Type::Int, Type::Float) => Instr::IntToFloat { dst, src },
+                    (Type::Float, Type::Int) => Instr::FloatToInt { dst, src },
+                    (from, to) if from == to => Instr::Move { dst, src },
+                    (from, to) => {
+                        return Err(BackendError::unsupported(format!(
+                            "conversion from {} to {} cannot be lowered to bytecode",
+                            super::type_label(from),
+                            super::type_label(to)
+                        )));
+                    }
+                }
+            }
+            InstKind::Path(path) => {
+                return Err(BackendError::Unsupported(format!(
+                    "path expression '{}' cannot be lowered to bytecode",
+                    path.segments.join("::")
+                )));
+            }
+            // Phis are written by the moves on their incoming edges.
+            InstKind::Phi { .. } => return Ok(()),
+            InstKind::List { elements } => Instr::List {
+                dst,
+                elements: self.regs(elements)?,
+            },
+            InstKind::ListLen { list } => Instr::ListLen {
+                dst,
+                list: self.reg(*list)?,
+            },
+            InstKind::ListIndex { list, index } => Instr::ListIndex {
+                dst,
+                list: self.reg(*list)?,
+                index: self.reg(*index)?,
+            },
+            InstKind::ListPush { list, value } => Instr::ListPush {
+                dst,
+                list: self.reg(*list)?,
+                value: self.reg(*value)?,
+            },
+            // VM values own their data; there is nothing to count.
+            InstKind::Retain { .. } | InstKind::Release { .. } => Instr::Unit { dst },
+            InstKind::Variant { .. }
+            | InstKind::IsVariant { .. }
+            | InstKind::Payload { .. }
+            | InstKind::Trap { .. } => {
+                return Err(BackendError::Unsupported(
+                    "sum types cannot be lowered to bytecode".into(),
+                ));
+            }
+        };
+        self.code.push(instr);
+        Ok(())
+    }
+
+    fn value_type(&self, value: ValueId) -> BackendResult<ir::TypeId> {
+        if let Some(param) = self.function.params.iter().find(|p| p.value == value) {
+            return Ok(param.ty);
+        }
+        self.function
+            .blocks
+            .iter()
+            .flat_map(|block| &block.instructions)
+            .find(|inst| inst.id == value)
+            .map(|inst| inst.ty)
+            .ok_or_else(|| {
+                BackendError::Internal(format!(
+                    "function '{}' uses undefined value %{}",
+                    self.function.name,
+                    value.index()
+                ))
+            })
+    }
+
+    /// The capability a method call's receiver stands for: a token whose
+    /// type names a runtime provider, such as `fs: Fs`, which the VM
+    /// resolves through `runtime::Runtime` by the lowercased name.
+    fn receiver_capability(&self, args: &[ValueId]) -> BackendResult<Option<String>> {
+        let Some(receiver) = args.first() else {
+            return Ok(None);
+        };
+        Ok(match self.module.type_of(self.value_type(*receiver)?) {
+            Type::Named(name) => Some(name.to_ascii_lowercase()),
+            _ => None,
+        })
+    }
+
+    fn compile_call(
+        &mut self,
+        dst: Reg,
+        func: &ir::FuncRef,
+        args: &[ValueId],
+    ) -> BackendResult<Instr> {
+        if let ir::FuncRef::Method(name) = func
+            && let Some(capability) = self.receiver_capability(args)?
+        {
+            // Method calls pass the capability token first; the payload,
+            // when there is one, follows it.
+            let payload = args.get(1).map(|value| self.reg(*value)).transpose()?;
+            return Ok(Instr::Invoke {
+                dst,
+                capability: self.strings.intern(&capability),
+                operation: self.strings.intern(runtime_operation(&capability, name)),
+                payload,
+            });
+        }
+        let (index, _) = resolve_callee(self.module, func, args.len(), "to bytecode")?;
+        Ok(Instr::Call {
+            dst,
+            function: index as u32,
+            args: self.regs(args)?,
+        })
+    }
+}
@@ -0,0 +1,327 @@
+//! Content-addressed cache of native build outputs.
+//!
+//! An entry is named by its [`CacheKey`]: a 128-bit FNV-1a hash of the
+//! module in the binary IR format ([`ir::serialize::to_bytes`]), the backend
+//! options that shape the generated code, the kind of artifact, the Mica
+//! compiler and [`native::RUNTIME_VERSION`], and the C compiler's identity
+//! and flags. An unchanged program therefore
+//! finds its executable in the cache and skips both C generation and `cc`.
+//!
+//! Entries are written under a temporary name and renamed into place, so
+//! concurrent builds of one program never observe a partial file. The
+//! `stats` file holds the hit and miss counters, replaced the same way on
+//! every lookup; concurrent lookups may lose a count but never corrupt it.
+//!
+//! A hit refreshes its entry's modification time, and every store evicts by
+//! [`CacheLimits`]: entries unused for longer than `max_age` go first, then
+//! the least recently used until the cache fits in `max_bytes`.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::ir;
+
+use super::{BackendError, BackendOptions, BackendResult, native};
+
+/// Environment variable naming the cache directory when none is configured.
+pub const CACHE_DIR_ENV: &str = "MICA_CACHE_DIR";
+
+const STATS_FILE: &str = "stats";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey(u128);
+
+impl CacheKey {
+    /// The key of the `kind` artifact the native backend builds from
+    /// `module` with `options`.
+    pub fn new(module: &ir::Module, options: &BackendOptions, kind: &str) -> Self {
+        let mut hasher = Fnv128::new();
+        hasher.write_field(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.write_field(&native::RUNTIME_VERSION.to_le_bytes());
+        hasher.write_field(native::compiler_identity().as_bytes());
+        hasher.write_field(native::CC_FLAGS.join(" ").as_bytes());
+        hasher.write_field(kind.as_bytes());
+        hasher.write_field(&ir::serialize::to_bytes(module));
+        hasher.write_field(&[
+            options.optimize as u8,
+            options.debug_info as u8,
+            options.library as u8,
+        ]);
+        hasher.write_field(options.target_triple.as_deref().unwrap_or("").as_bytes());
+        // Source files only reach the output through `#line` directives.
+        if options.debug_info {
+            for (module, file) in &options.source_files {
+                hasher.write_field(module.as_bytes());
+                hasher.write_field(file.to_string_lossy().as_bytes());
+            }
+        }
+        CacheKey(hasher.finish())
+    }
+
+    /// The key as 32 lowercase hex digits, which also names its entry.
+    pub fn hex(&self) -> String {
+        format!("{:032x}", self.0)
+    }
+}
+
+/// FNV-1a over 128 bits, which unlike `std`'s hashers is stable across
+/// Rust releases and platforms.
+struct Fnv128(u128);
+
+impl Fnv128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    fn new() -> Self {
+        Fnv128(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u128::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Writes `bytes` after their length, so adjacent fields cannot run
+    /// into each other.
+    fn write_field(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+    }
+
+    fn finish(&self) -> u128 {
+        self.0
+    }
+}
+
+/// Hits and misses recorded by lookups, and the entries currently stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub entries: usize,
+}
+
+/// Bounds the cache keeps to after each store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheLimits {
+    /// Total size of the stored entries.
+    pub max_bytes: u64,
+    /// Time since an entry was last built or used.
+    pub max_age: Duration,
+}
+
+impl Default for CacheLimits {
+    /// 512 MiB of entries, each kept for up to 30 days.
+    fn default() -> Self {
+        CacheLimits {
+            max_bytes: 512 * 1024 * 1024,
+            max_age: Duration::from_secs(30 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// The outcome of [`BuildCache::get_or_build`].
+#[derive(Debug, Clone)]
+pub struct CacheLookup {
+    pub path: PathBuf,
+    pub hit: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BuildCache {
+    dir: PathBuf,
+    limits: CacheLimits,
+}
+
+impl BuildCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        BuildCache {
+            dir: dir.into(),
+            limits: CacheLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: CacheLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The directory named by `MICA_CACHE_DIR`, or `mica-cache` in the
+    /// system temporary directory.
+    pub fn default_dir() -> PathBuf {
+        match std::env::var_os(CACHE_DIR_ENV) {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => std::env::temp_dir().join("mica-cache"),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Where the entry for `key` is stored.
+    pub fn path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(key.hex())
+    }
+
+    /// Whether an entry for `key` exists. Unlike [`get_or_build`], this does
+    /// not count as a lookup.
+    ///
+    /// [`get_or_build`]: BuildCache::get_or_build
+    pub fn contains(&self, key: &CacheKey) -> bool {
+        self.path(key).is_file()
+    }
+
+    /// The entry for `key`, calling `build` to write it on a miss.
+    pub fn get_or_build<F>(&self, key: &CacheKey, build: F) -> BackendResult<CacheLookup>
+    where
+        F: FnOnce(&Path) -> BackendResult<()>,
+    {
+        let path = self.path(key);
+        if path.is_file() {
+            // The modification time doubles as the last use for eviction.
+            if let Ok(entry) = File::open(&path) {
+                entry.set_modified(SystemTime::now()).ok();
+            }
+            self.record(true);
+            return Ok(CacheLookup { path, hit: true });
+        }
+
+        fs::create_dir_all(&self.dir).map_err(|err| {
+            BackendError::Internal(format!(
+                "failed to create build cache {}: {err}",
+                self.dir.display()
+            ))
+        })?;
+        let partial = self.temporary(&key.hex());
+        if let Err(err) = build(&partial) {
+            fs::remove_file(&partial).ok();
+            return Err(err);
+        }
+        fs::rename(&partial, &path).map_err(|err| {
+            fs::remove_file(&partial).ok();
+            BackendError::Internal(format!("failed to store {}: {err}", path.display()))
+        })?;
+        self.record(false);
+        self.evict(&path);
+        Ok(CacheLookup { path, hit: false })
+    }
+
+    /// The lookup counters and the number of stored entries.
+    pub fn stats(&self) -> CacheStats {
+        let (hits, misses) = self.counters();
+        CacheStats {
+            hits,
+            misses,
+            entries: self.entries().len(),
+        }
+    }
+
+    /// Removes entries past [`CacheLimits`], oldest first, and temporary
+    /// files left by builds that died before their rename. `keep` is the
+    /// entry just stored, which survives even when it alone is too large.
+    fn evict(&self, keep: &Path) {
+        let now = SystemTime::now();
+        let expired = |modified: SystemTime| {
+            now.duration_since(modified)
+                .is_ok_and(|age| age > self.limits.max_age)
+        };
+        if let Ok(files) = fs::read_dir(&self.dir) {
+            for file in files.flatten() {
+                let stale = file.file_name().to_string_lossy().ends_with(".tmp")
+                    && file
+                        .metadata()
+                        .and_then(|metadata| metadata.modified())
+                        .is_ok_and(expired);
+                if stale {
+                    fs::remove_file(file.path()).ok();
+                }
+            }
+        }
+
+        let mut entries = self.entries();
+        entries.sort_by_key(|entry| entry.modified);
+        let mut total: u64 = entries.iter().map(|entry| entry.len).sum();
+        for entry in entries {
+            if entry.path == keep {
+                continue;
+            }
+            if (expired(entry.modified) || total > self.limits.max_bytes)
+                && fs::remove_file(&entry.path).is_ok()
+            {
+                total -= entry.len;
+            }
+        }
+    }
+
+    /// Every stored entry: the files named by a key's 32 hex digits.
+    fn entries(&self) -> Vec<Entry> {
+        let Ok(files) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        files
+            .flatten()
+            .filter(|file| {
+                let name = file.file_name();
+                let name = name.to_string_lossy();
+                name.len() == 32 && name.chars().all(|ch| ch.is_ascii_hexdigit())
+            })
+            .filter_map(|file| {
+                let metadata = file.metadata().ok()?;
+                Some(Entry {
+                    path: file.path(),
+                    len: metadata.len(),
+                    modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+                })
+            })
+            .collect()
+    }
+
+    /// The hit and miss counters, stored as two decimal numbers.
+    fn counters(&self) -> (usize, usize) {
+        let Ok(text) = fs::read_to_string(self.dir.join(STATS_FILE)) else {
+            return (0, 0);
+        };
+        let mut counts = text
+            .split_whitespace()
+            .map(|count| count.parse().unwrap_or(0));
+        (counts.next().unwrap_or(0), counts.next().unwrap_or(0))
+    }
+
+    /// Counts a lookup by replacing the counters file; failures only lose
+    /// the count.
+    fn record(&self, hit: bool) {
+        let (mut hits, mut misses) = self.counters();
+        if hit {
+            hits += 1;
+        } else {
+            misses += 1;
+        }
+        let partial = self.temporary(STATS_FILE);
+        if fs::write(&partial, format!("{hits} {misses}\n")).is_err()
+            || fs::rename(&partial, self.dir.join(STATS_FILE)).is_err()
+        {
+            fs::remove_file(&partial).ok();
+        }
+    }
+
+    /// A file name for `name` unique to this process and moment, to be
+    /// renamed into place once complete.
+    fn temporary(&self, name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        self.dir
+            .join(format!("{name}.{}-{nanos}.tmp", std::process::id()))
+    }
+}
+
+struct Entry {
+    path: PathBuf,
+    len: u64,
+    modified: SystemTime,
+}
@@ -0,0 +1,214 @@
+use std::fmt::Write;
+
+use crate::ir::analysis::{EffectSummaries, analyze_function_purity};
+use crate::ir::{self, Terminator};
+
+use super::text::{format_instruction, format_terminator};
+use super::{Backend, BackendOptions, BackendResult};
+
+/// Fill colour of pure blocks and functions.
+const PURE_FILL: &str = "#d9f2d9";
+/// Fill colour of blocks and functions with effects.
+const EFFECTFUL_FILL: &str = "#f9d6d5";
+
+/// Renders the control-flow graphs of a module as Graphviz DOT.
+#[derive(Debug, Default)]
+pub struct DotBackend;
+
+impl Backend for DotBackend {
+    type Output = String;
+
+    fn compile(
+        &self,
+        module: &ir::Module,
+        _options: &BackendOptions,
+    ) -> BackendResult<Self::Output> {
+        Ok(render_module(module))
+    }
+}
+
+/// Renders every function as a cluster of basic blocks.
+///
+/// Each block node lists its instructions and terminator in the textual IR
+/// syntax. Blocks `PurityReport` finds pure are filled green, effectful ones
+/// red, and branch edges are labelled `true` and `false`.
+pub fn render_module(module: &ir::Module) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph {} {{", quote(&module.name.join("."))).unwrap();
+    writeln!(
+        out,
+        "  node [shape=box, style=filled, fontname=\"monospace\"];"
+    )
+    .unwrap();
+    for function in &module.functions {
+        render_function(&mut out, module, function);
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+fn render_function(out: &mut String, module: &ir::Module, function: &ir::Function) {
+    let purity = analyze_function_purity(function);
+    writeln!(
+        out,
+        "  subgraph {} {{",
+        quote(&format!("cluster_{}", function.name))
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    label={};",
+        quote(&format!("fn {}", function.name))
+    )
+    .unwrap();
+    for block in &function.blocks {
+        let mut label = format!("bb{}:\\l", block.id.index());
+        for inst in &block.instructions {
+            label.push_str(&escape(&format_instruction(module, inst)));
+            label.push_str("\\l");
+        }
+        label.push_str(&escape(&format_terminator(block)));
+        label.push_str("\\l");
+        let fill = if purity.is_block_pure(block.id) {
+            PURE_FILL
+        } else {
+            EFFECTFUL_FILL
+        };
+        writeln!(
+            out,
+            "    {} [label=\"{}\", fillcolor=\"{}\"];",
+            block_node(function, block.id),
+            label,
+            fill
+        )
+        .unwrap();
+    }
+    for block in &function.blocks {
+        let from = block_node(function, block.id);
+        match &block.terminator {
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => {
+                let then_node = block_node(function, *then_block);
+                let else_node = block_node(function, *else_block);
+                writeln!(out, "    {} -> {} [label=\"true\"];", from, then_node).unwrap();
+                writeln!(out, "    {} -> {} [label=\"false\"];", from, else_node).unwrap();
+            }
+            Terminator::Jump(target) => {
+                writeln!(out, "    {} -> {};", from, block_node(function, *target)).unwrap();
+            }
+            Terminator::Return(_) => {}
+        }
+    }
+    writeln!(out, "  }}").unwrap();
+}
+
+/// Renders the module call graph from its [`EffectSummaries`].
+///
+/// Functions are filled by transitive purity and labelled with their
+/// effects. Recursive components are grouped into dashed clusters, and calls
+/// whose target is not a module function point at dashed placeholder nodes.
+pub fn render_call_graph(module: &ir::Module) -> String {
+    let summaries = EffectSummaries::new(module);
+    let mut out = String::new();
+    writeln!(out, "digraph {} {{", quote(&module.name.join("."))).unwrap();
+    writeln!(out, "  node [shape=ellipse, style=filled];").unwrap();
+    for (index, members) in summaries.sccs.iter().enumerate() {
+        let recursive = members
+            .first()
+            .and_then(|name| summaries.get(name))
+            .is_some_and(|summary| summary.recursive);
+        if recursive && members.len() > 1 {
+            writeln!(
+                out,
+                "  subgraph {} {{",
+                quote(&format!("cluster_scc{}", index))
+            )
+            .unwrap();
+            writeln!(out, "    style=dashed;").unwrap();
+            for member in members {
+                writeln!(out, "    {};", quote(member)).unwrap();
+            }
+            writeln!(out, "  }}").unwrap();
+        }
+    }
+    for summary in &summaries.functions {
+        let mut label = escape(&summary.name);
+        if !summary.effects.is_empty() {
+            write!(label, "\\n!{{{}}}", escape(&summary.effects.join(", "))).unwrap();
+        }
+        let fill = if summary.pure {
+            PURE_FILL
+        } else {
+            EFFECTFUL_FILL
+        };
+        writeln!(
+            out,
+            "  {} [label=\"{}\", fillcolor=\"{}\"];",
+            quote(&summary.name),
+            label,
+            fill
+        )
+        .unwrap();
+    }
+    let mut unresolved: Vec<&str> = Vec::new();
+    for summary in &summaries.functions {
+        for callee in &summary.callees {
+            writeln!(out, "  {} -> {};", quote(&summary.name), quote(callee)).unwrap();
+        }
+        for target in &summary.unresolved_calls {
+            if !unresolved.contains(&target.as_str()) {
+                unresolved.push(target);
+            }
+            writeln!(
+                out,
+                "  {} -> {} [style=dashed];",
+                quote(&summary.name),
+                unresolved_node(target)
+            )
+            .unwrap();
+        }
+    }
+    for target in unresolved {
+        writeln!(
+            out,
+            "  {} [label=\"{}\", style=dashed];",
+            unresolved_node(target),
+            escape(target)
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+fn block_node(function: &ir::Function, block: ir::BlockId) -> String {
+    quote(&format!("{}.bb{}", function.name, block.index()))
+}
+
+/// Unresolved targets get their own namespace so a method named like a
+/// module function cannot merge with it.
+fn unresolved_node(target: &str) -> String {
+    quote(&format!("?{}", target))
+}
+
+fn quote(id: &str) -> String {
+    format!("\"{}\"", escape(id))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
@@ -1,14 +1,17 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Write;
 
+use crate::ir::analysis::AnalysisManager;
+use crate::ir::passes::{Pass, TailCallElimination};
 use crate::ir::{self, InstKind, Terminator, Type, TypeId, ValueId};
+use crate::syntax::ast::{BinaryOp, Literal};
 
 use super::{Backend, BackendError, BackendOptions, BackendResult};
 
-/// Scaffolding backend that translates the typed SSA module into a
-/// lightweight LLVM-flavoured IR string. The intent is to expose a stable
-/// contract for future native code generation work without pulling in LLVM
-/// as a dependency yet.
+/// Backend that translates the typed SSA module into textual LLVM IR with
+/// opaque pointers. The output is meant for `llvm-as`/`llc`; lists, strings
+/// and capabilities are calls into `mica_*` runtime functions the module
+/// declares but does not define.
 #[derive(Debug, Default, Clone)]
 pub struct LlvmBackend {
     /// Preferred target triple for the emitted module. When `None`, the
@@ -17,9 +20,8 @@ pub struct LlvmBackend {
     pub target_triple: Option<String>,
 }
 
-/// Result of the LLVM backend scaffolding. The IR is stored verbatim so
-/// downstream tooling can persist it to disk or feed it into the real LLVM
-/// toolchain when it lands.
+/// Result of the LLVM backend. The IR is stored verbatim so downstream
+/// tooling can persist it to disk or feed it into the LLVM toolchain.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LlvmModule {
     pub ir: String,
@@ -44,7 +46,11 @@ impl Backend for LlvmBackend {
             .target_triple
             .clone()
             .or_else(|| options.target_triple.clone());
-        let renderer = ModuleRenderer::new(module, triple.clone());
+        // Self tail calls become loops; the remaining tail calls are marked
+        // on the calls themselves.
+        let mut module = module.clone();
+        TailCallElimination.run(&mut module, &mut AnalysisManager::new());
+        let renderer = ModuleRenderer::new(&module, triple.clone(), options);
         let ir = renderer.render()?;
         Ok(LlvmModule {
             ir,
@@ -53,24 +59,79 @@ impl Backend for LlvmBackend {
     }
 }
 
+/// Capability operations a method call maps to, mirroring the C backend's
+/// runtime shims: the runtime symbol, its return type and its parameters.
+fn runtime_method(name: &str) -> Option<(&'static str, &'static str, &'static [&'static str])> {
+    match name {
+        "println" | "write_line" => Some(("mica_runtime_io_write_line", "void", &["ptr"])),
+        "now_millis" => Some(("mica_runtime_time_now_millis", "i64", &[])),
+        _ => None,
+    }
+}
+
+/// Fixed metadata slots used when emitting debug info.
+const DEBUG_COMPILE_UNIT: usize = 0;
+const DEBUG_FILE: usize = 1;
+const DEBUG_SUBROUTINE_TYPE: usize = 2;
+
 struct ModuleRenderer<'m> {
     module: &'m ir::Module,
     target_triple: Option<String>,
+    options: &'m BackendOptions,
+    debug_info: bool,
     string_literals: Vec<String>,
     string_map: HashMap<String, usize>,
+    /// `declare` lines for every runtime or external function called, keyed
+    /// by symbol.
+    declarations: BTreeMap<String, String>,
+    /// Globals that path expressions reference outside the module.
+    external_globals: BTreeMap<String, String>,
+    /// Numbered metadata nodes, `!0` first.
+    metadata: Vec<String>,
+    /// `DIFile` node of each source file.
+    files: HashMap<String, usize>,
+    locations: HashMap<(u32, u32, usize), usize>,
 }
 
 impl<'m> ModuleRenderer<'m> {
-    fn new(module: &'m ir::Module, target_triple: Option<String>) -> Self {
+    fn new(
+        module: &'m ir::Module,
+        target_triple: Option<String>,
+        options: &'m BackendOptions,
+    ) -> Self {
         ModuleRenderer {
             module,
             target_triple,
+            options,
+            debug_info: options.debug_info,
             string_literals: Vec::new(),
             string_map: HashMap::new(),
+            declarations: BTreeMap::new(),
+            external_globals: BTreeMap::new(),
+            metadata: Vec::new(),
+            files: HashMap::new(),
+            locations: HashMap::new(),
         }
     }
 
     fn render(mut self) -> BackendResult<String> {
+        if self.debug_info {
+            let file = self.options.source_file(self.module, "");
+            self.files.insert(file.clone(), DEBUG_FILE);
+            self.metadata = vec![
+                format!(
+                    "distinct !DICompileUnit(language: DW_LANG_C, file: !{DEBUG_FILE}, producer: \"mica\", isOptimized: false, runtimeVersion: 0, emissionKind: LineTablesOnly)"
+                ),
+                format!(
+                    "!DIFile(filename: \"{}\", directory: \".\")",
+                    escape_string(&file)
+                ),
+                "!DISubroutineType(types: !{})".to_string(),
+                "!{i32 2, !\"Debug Info Version\", i32 3}".to_string(),
+                "!{i32 7, !\"Dwarf Version\", i32 4}".to_string(),
+            ];
+        }
+
         let mut functions = String::new();
         for function in &self.module.functions {
             self.render_function(&mut functions, function)?;
@@ -83,7 +144,12 @@ impl<'m> ModuleRenderer<'m> {
         if let Some(triple) = &self.target_triple {
             writeln!(out, "target triple = \"{}\"", triple).unwrap();
         }
-        writeln!(out, "target datalayout = \"{}\"", default_data_layout()).unwrap();
+        writeln!(
+            out,
+            "target datalayout = \"{}\"",
+            data_layout(self.target_triple.as_deref())
+        )
+        .unwrap();
         writeln!(out).unwrap();
 
         let mut type_defs = String::new();
@@ -101,12 +167,29 @@ impl<'m> ModuleRenderer<'m> {
             )
             .unwrap();
         }
+        for (symbol, ty) in &self.external_globals {
+            writeln!(out, "@{} = external global {}", symbol, ty).unwrap();
+        }
+        if !self.string_literals.is_empty() || !self.external_globals.is_empty() {
+            writeln!(out).unwrap();
+        }
 
-        if !self.string_literals.is_empty() {
+        for declaration in self.declarations.values() {
+            writeln!(out, "{}", declaration).unwrap();
+        }
+        if !self.declarations.is_empty() {
             writeln!(out).unwrap();
         }
 
         out.push_str(&functions);
+
+        if self.debug_info {
+            writeln!(out, "!llvm.dbg.cu = !{{!{DEBUG_COMPILE_UNIT}}}").unwrap();
+            writeln!(out, "!llvm.module.flags = !{{!3, !4}}").unwrap();
+            for (index, node) in self.metadata.iter().enumerate() {
+                writeln!(out, "!{} = {}", index, node).unwrap();
+            }
+        }
         Ok(out)
     }
 
@@ -122,7 +205,7 @@ impl<'m> ModuleRenderer<'m> {
                 } else {
                     let mut parts = Vec::with_capacity(record.fields.len());
                     for field in &record.fields {
-                        parts.push(format_type(self.module, field.ty));
+                        parts.push(storage_type(self.module, field.ty));
                     }
                     format!("{{ {} }}", parts.join(", "))
                 };
@@ -141,27 +224,109 @@ impl<'m> ModuleRenderer<'m> {
         }
     }
 
+    fn declare(&mut self, symbol: &str, ret: &str, params: &[String]) {
+        self.declarations
+            .entry(symbol.to_string())
+            .or_insert_with(|| format!("declare {} @{}({})", ret, symbol, params.join(", ")));
+    }
+
+    fn declare_list_runtime(&mut self) {
+        self.declare("mica_list_new", "ptr", &["i64".into(), "i64".into()]);
+        self.declare("mica_list_push", "void", &["ptr".into(), "ptr".into()]);
+        self.declare("mica_list_len", "i64", &["ptr".into()]);
+        self.declare("mica_list_at", "ptr", &["ptr".into(), "i64".into()]);
+    }
+
+    fn add_metadata(&mut self, node: String) -> usize {
+        self.metadata.push(node);
+        self.metadata.len() - 1
+    }
+
+    fn file(&mut self, file: String) -> usize {
+        if let Some(index) = self.files.get(&file) {
+            return *index;
+        }
+        let index = self.add_metadata(format!(
+            "!DIFile(filename: \"{}\", directory: \".\")",
+            escape_string(&file)
+        ));
+        self.files.insert(file, index);
+        index
+    }
+
+    fn location(&mut self, line: u32, column: u32, scope: usize) -> usize {
+        if let Some(index) = self.locations.get(&(line, column, scope)) {
+            return *index;
+        }
+        let index = self.add_metadata(format!(
+            "!DILocation(line: {line}, column: {column}, scope: !{scope})"
+        ));
+        self.locations.insert((line, column, scope), index);
+        index
+    }
+
+    /// The `!dbg` attachment for code lowered from `provenance`, falling back
+    /// to the function's own line for synthesized code.
+    fn debug_suffix(
+        &mut self,
+        provenance: Option<&ir::Provenance>,
+        context: &RenderContext<'_, '_>,
+    ) -> String {
+        let Some((scope, line)) = context.debug_scope else {
+            return String::new();
+        };
+        let (line, column) = provenance.map_or((line, 0), |provenance| {
+            (provenance.span.line, provenance.span.column)
+        });
+        format!(", !dbg !{}", self.location(line, column, scope))
+    }
+
     fn render_function(&mut self, out: &mut String, function: &ir::Function) -> BackendResult<()> {
         let ret_ty = format_type(self.module, function.ret_type);
-        let mut params = Vec::with_capacity(function.params.len());
         let purity = ir::analysis::analyze_function_purity(function);
-        let mut context = RenderContext::new(self.module, Some(&purity));
+        let mut context = RenderContext::new(self.module, function, Some(&purity))?;
+        context.tail_calls = self.tail_call_markers(function);
 
+        let mut params = Vec::with_capacity(function.params.len());
         for param in &function.params {
-            context.value_types.insert(param.value, param.ty);
-            params.push(format!(
-                "{} %{}",
-                format_type(self.module, param.ty),
-                param.name
+            let ty = format_type(self.module, param.ty);
+            if ty != "void" {
+                params.push(format!("{} {}", ty, context.names[&param.value]));
+            }
+        }
+
+        let mut debug = String::new();
+        if self.debug_info {
+            let line = function
+                .blocks
+                .iter()
+                .flat_map(|block| {
+                    block
+                        .instructions
+                        .iter()
+                        .map(|inst| inst.provenance.as_ref())
+                        .chain([block.terminator_provenance.as_ref()])
+                })
+                .flatten()
+                .map(|provenance| provenance.span.line)
+                .min()
+                .unwrap_or(0);
+            let file = self.file(self.options.source_file(self.module, &function.name));
+            let scope = self.add_metadata(format!(
+                "distinct !DISubprogram(name: \"{}\", scope: !{file}, file: !{file}, line: {line}, type: !{DEBUG_SUBROUTINE_TYPE}, scopeLine: {line}, spFlags: DISPFlagDefinition, unit: !{DEBUG_COMPILE_UNIT})",
+                escape_string(&function.name)
             ));
+            context.debug_scope = Some((scope, line));
+            debug = format!(" !dbg !{scope}");
         }
 
         writeln!(
             out,
-            "define {} @{}({}) {{",
+            "define {} @{}({}){} {{",
             ret_ty,
-            function.name,
-            params.join(", ")
+            sanitize_symbol(&function.name),
+            params.join(", "),
+            debug
         )
         .unwrap();
 
@@ -189,19 +354,51 @@ impl<'m> ModuleRenderer<'m> {
             writeln!(out, "  ; effects: {}", names.join(", ")).unwrap();
         }
 
-        for block in &function.blocks {
-            self.render_block(out, block, &mut context)?;
+        for (index, block) in function.blocks.iter().enumerate() {
+            self.render_block(out, block, index == 0, &context)?;
         }
 
         writeln!(out, "}}").unwrap();
         Ok(())
     }
 
+    /// `musttail` for calls whose result `function` returns directly when
+    /// the callee is a module function with the same signature, which LLVM
+    /// then guarantees to lower as a jump; `tail` for other returned calls.
+    fn tail_call_markers(&self, function: &ir::Function) -> HashMap<ValueId, &'static str> {
+        let signature = |function: &ir::Function| {
+            let params: Vec<String> = function
+                .params
+                .iter()
+                .map(|param| format_type(self.module, param.ty))
+                .collect();
+            (format_type(self.module, function.ret_type), params)
+        };
+        let caller = signature(function);
+        let mut markers = HashMap::new();
+        for block in &function.blocks {
+            let (Some((path, _)), Some(call)) = (block.tail_call(), block.instructions.last())
+            else {
+                continue;
+            };
+            let name = path.segments.join("::");
+            let guaranteed = self
+                .module
+                .functions
+                .iter()
+                .find(|callee| callee.name == name)
+                .is_some_and(|callee| signature(callee) == caller);
+            markers.insert(call.id, if guaranteed { "musttail" } else { "tail" });
+        }
+        markers
+    }
+
     fn render_block(
         &mut self,
         out: &mut String,
         block: &ir::BasicBlock,
-        context: &mut RenderContext<'_, '_>,
+        entry: bool,
+        context: &RenderContext<'_, '_>,
     ) -> BackendResult<()> {
         writeln!(out, "bb{}:", block.id.index()).unwrap();
         if let Some(purity) = context
@@ -214,88 +411,188 @@ impl<'m> ModuleRenderer<'m> {
             };
             writeln!(out, "  ; block purity: {}", label).unwrap();
         }
+        // List elements are passed by address; one slot per element type,
+        // allocated once in the entry block, serves every push.
+        if entry {
+            let suffix = self.debug_suffix(None, context);
+            for (ty, slot) in &context.slots {
+                writeln!(out, "  {} = alloca {}{}", slot, ty, suffix).unwrap();
+            }
+        }
         for inst in &block.instructions {
-            context.value_types.insert(inst.id, inst.ty);
-            if let Some(line) = self.render_instruction(inst, context)? {
+            let lines = self.render_instruction(inst, context)?;
+            let suffix = self.debug_suffix(inst.provenance.as_ref(), context);
+            let count = lines.len();
+            for (index, mut line) in lines.into_iter().enumerate() {
+                line.push_str(&suffix);
+                if index + 1 == count && !inst.effects.is_empty() {
+                    let names: Vec<_> = inst
+                        .effects
+                        .iter()
+                        .map(|id| self.module.effect_name(*id).to_string())
+                        .collect();
+                    line.push_str(&format!("  ; effects: {}", names.join(", ")));
+                }
                 writeln!(out, "{}", line).unwrap();
             }
         }
-        self.render_terminator(out, &block.terminator, context);
+        let line = render_terminator(&block.terminator, context)?;
+        let suffix = self.debug_suffix(block.terminator_provenance.as_ref(), context);
+        writeln!(out, "{}{}", line, suffix).unwrap();
         Ok(())
     }
 
     fn render_instruction(
         &mut self,
         inst: &ir::Instruction,
-        context: &mut RenderContext<'_, '_>,
-    ) -> BackendResult<Option<String>> {
-        let mut line = match &inst.kind {
+        context: &RenderContext<'_, '_>,
+    ) -> BackendResult<Vec<String>> {
+        match &inst.kind {
             InstKind::Literal(literal) => Ok(self.render_literal(inst, literal, context)),
-            InstKind::Binary { op, lhs, rhs } => {
-                Ok(Some(render_binary(inst, *op, *lhs, *rhs, context)))
+            InstKind::Binary { op, lhs, rhs } => self.render_binary(inst, *op, *lhs, *rhs, context),
+            InstKind::Call { func, args } => self.render_call(inst, func, args, context),
+            InstKind::Record { fields, .. } => self.render_record_literal(inst, fields, context),
+            InstKind::Path(path) => Ok(self.render_path(inst, path, context)),
+            InstKind::Field { base, name } => render_field(inst, *base, name, context),
+            InstKind::Convert { value } => render_convert(inst, *value, context),
+            InstKind::Phi { incomings } => render_phi(inst, incomings, context),
+            InstKind::List { elements } => self.render_list(inst, elements, context),
+            InstKind::ListLen { list } => {
+                self.declare_list_runtime();
+                Ok(vec![format!(
+                    "  {} = call i64 @mica_list_len(ptr {})",
+                    context.result(inst.id),
+                    context.operand(*list)?
+                )])
             }
-            InstKind::Call { func, args } => Ok(Some(self.render_call(inst, func, args, context))),
-            InstKind::Record { fields, .. } => {
-                self.render_record_literal(inst, fields, context).map(Some)
+            InstKind::ListIndex { list, index } => {
+                self.declare_list_runtime();
+                let slot = temporary(inst.id, "ptr");
+                let mut lines = vec![format!(
+                    "  {slot} = call ptr @mica_list_at(ptr {}, i64 {})",
+                    context.operand(*list)?,
+                    context.operand(*index)?
+                )];
+                if !context.is_unit(inst.id) {
+                    lines.push(format!(
+                        "  {} = load {}, ptr {slot}",
+                        context.result(inst.id),
+                        format_type(self.module, context.value_type(inst.id))
+                    ));
+                }
+                Ok(lines)
             }
-            InstKind::Path(path) => Ok(Some(render_path(inst, path))),
-            InstKind::Phi { incomings } => Ok(Some(render_phi(inst, incomings, context))),
-        }?;
-
-        if !inst.effects.is_empty() {
-            let names: Vec<_> = inst
-                .effects
-                .iter()
-                .map(|id| self.module.effect_name(*id).to_string())
-                .collect();
-            if let Some(line_str) = &mut line {
-                line_str.push_str(&format!("  ; effects: {}", names.join(", ")));
+            InstKind::ListPush { list, value } => {
+                self.declare_list_runtime();
+                render_list_push(*list, *value, context)
             }
+            // Lists and strings are never freed here, so counts are moot.
+            InstKind::Retain { .. } | InstKind::Release { .. } => Ok(Vec::new()),
+            InstKind::Variant { .. }
+            | InstKind::IsVariant { .. }
+            | InstKind::Payload { .. }
+            | InstKind::Trap { .. } => Err(BackendError::Unsupported(
+                "sum types cannot be lowered to LLVM IR".into(),
+            )),
         }
-
-        Ok(line)
     }
 
     fn render_literal(
         &mut self,
         inst: &ir::Instruction,
-        literal: &crate::syntax::ast::Literal,
-        context: &mut RenderContext<'_, '_>,
-    ) -> Option<String> {
-        let id = inst.id.index();
-        match literal {
-            crate::syntax::ast::Literal::Int(value) => Some(format!(
-                "  %{} = add {} 0, {}",
-                id,
+        literal: &Literal,
+        context: &RenderContext<'_, '_>,
+    ) -> Vec<String> {
+        let result = context.result(inst.id);
+        let line = match literal {
+            Literal::Int(value) => format!(
+                "  {} = add {} 0, {}",
+                result,
                 format_type(context.module, inst.ty),
                 value
-            )),
-            crate::syntax::ast::Literal::Float(value) => Some(format!(
-                "  %{} = fadd {} 0.0, {:.6e}",
-                id,
+            ),
+            // Hexadecimal keeps every bit of the double.
+            Literal::Float(value) => format!(
+                "  {} = fadd {} 0.0, 0x{:016X}",
+                result,
                 format_type(context.module, inst.ty),
-                value
-            )),
-            crate::syntax::ast::Literal::Bool(value) => Some(format!(
-                "  %{} = or {} false, {}",
-                id,
+                value.to_bits()
+            ),
+            Literal::Bool(value) => format!(
+                "  {} = or {} false, {}",
+                result,
                 format_type(context.module, inst.ty),
                 if *value { "true" } else { "false" }
-            )),
-            crate::syntax::ast::Literal::String(value) => {
+            ),
+            Literal::String(value) => {
                 let symbol = self.intern_string(value);
-                Some(format!(
-                    "  %{} = getelementptr inbounds ([{} x i8], ptr @{}, i32 0, i32 0)",
-                    id,
+                format!(
+                    "  {} = getelementptr inbounds [{} x i8], ptr @{}, i32 0, i32 0",
+                    result,
                     value.len() + 1,
                     symbol
-                ))
-            }
-            crate::syntax::ast::Literal::Unit => {
-                context.unit_values.insert(inst.id);
-                None
+                )
             }
+            Literal::Unit => return Vec::new(),
+        };
+        vec![line]
+    }
+
+    fn render_binary(
+        &mut self,
+        inst: &ir::Instruction,
+        op: BinaryOp,
+        lhs: ValueId,
+        rhs: ValueId,
+        context: &RenderContext<'_, '_>,
+    ) -> BackendResult<Vec<String>> {
+        let result = context.result(inst.id);
+        let lhs_type = context.value_type(lhs);
+        let lhs_ty = context.module.type_of(lhs_type);
+        if matches!(lhs_ty, Type::Unit) {
+            // Unit values are all equal.
+            let equal = matches!(op, BinaryOp::Eq | BinaryOp::Le | BinaryOp::Ge);
+            return Ok(vec![format!("  {result} = or i1 false, {equal}")]);
+        }
+        let ty_name = format_type(context.module, lhs_type);
+        let rhs_ty = format_type(context.module, context.value_type(rhs));
+        let (lhs, rhs) = (context.operand(lhs)?, context.operand(rhs)?);
+        if matches!(lhs_ty, Type::String) && matches!(op, BinaryOp::Eq | BinaryOp::Ne) {
+            self.declare("strcmp", "i32", &["ptr".into(), "ptr".into()]);
+            let order = temporary(inst.id, "cmp");
+            let pred = if op == BinaryOp::Eq { "eq" } else { "ne" };
+            return Ok(vec![
+                format!("  {order} = call i32 @strcmp(ptr {lhs}, ptr {rhs})"),
+                format!("  {result} = icmp {pred} i32 {order}, 0"),
+            ]);
+        }
+        if ty_name != rhs_ty {
+            return Err(BackendError::unsupported(format!(
+                "binary operator '{op}' applied to {ty_name} and {rhs_ty}"
+            )));
+        }
+        let comparison = matches!(op, BinaryOp::Eq | BinaryOp::Ne);
+        if ty_name == "ptr" && !comparison || ty_name.starts_with(['%', '{']) {
+            return Err(BackendError::unsupported(format!(
+                "binary operator '{op}' requires a known scalar type"
+            )));
         }
+        let line = match op {
+            BinaryOp::Add => binary_arith("add", "fadd", &ty_name, &lhs, &rhs, &result),
+            BinaryOp::Sub => binary_arith("sub", "fsub", &ty_name, &lhs, &rhs, &result),
+            BinaryOp::Mul => binary_arith("mul", "fmul", &ty_name, &lhs, &rhs, &result),
+            BinaryOp::Div => binary_arith("sdiv", "fdiv", &ty_name, &lhs, &rhs, &result),
+            BinaryOp::Mod => binary_arith("srem", "frem", &ty_name, &lhs, &rhs, &result),
+            BinaryOp::And => format!("  {result} = and {ty_name} {lhs}, {rhs}"),
+            BinaryOp::Or => format!("  {result} = or {ty_name} {lhs}, {rhs}"),
+            BinaryOp::Eq => render_cmp("eq", "oeq", &ty_name, &lhs, &rhs, &result),
+            BinaryOp::Ne => render_cmp("ne", "one", &ty_name, &lhs, &rhs, &result),
+            BinaryOp::Lt => render_cmp("slt", "olt", &ty_name, &lhs, &rhs, &result),
+            BinaryOp::Le => render_cmp("sle", "ole", &ty_name, &lhs, &rhs, &result),
+            BinaryOp::Gt => render_cmp("sgt", "ogt", &ty_name, &lhs, &rhs, &result),
+            BinaryOp::Ge => render_cmp("sge", "oge", &ty_name, &lhs, &rhs, &result),
+        };
+        Ok(vec![line])
     }
 
     fn render_call(
@@ -304,160 +601,215 @@ impl<'m> ModuleRenderer<'m> {
         func: &ir::FuncRef,
         args: &[ValueId],
         context: &RenderContext<'_, '_>,
-    ) -> String {
-        let ret_ty = format_type(context.module, inst.ty);
-        let callee = match func {
-            ir::FuncRef::Function(path) => {
-                format!("@{}", sanitize_symbol(&path.segments.join("::")))
+    ) -> BackendResult<Vec<String>> {
+        let call = match context.tail_calls.get(&inst.id) {
+            Some(marker) => format!("{} call", marker),
+            None => "call".to_string(),
+        };
+        let name = match func {
+            ir::FuncRef::Method(name) => {
+                if let Some((symbol, ret, params)) = runtime_method(name) {
+                    return self
+                        .render_runtime_call(inst, name, symbol, ret, params, args, context);
+                }
+                name.clone()
             }
-            ir::FuncRef::Method(name) => format!("@{}", sanitize_symbol(name)),
+            ir::FuncRef::Function(path) => path.segments.join("::"),
         };
-        let formatted_args: Vec<String> = args
-            .iter()
-            .map(|arg| {
-                let ty = context
-                    .value_types
-                    .get(arg)
-                    .copied()
-                    .unwrap_or_else(|| context.module.unknown_type());
-                format!("{} %{}", format_type(context.module, ty), arg.index())
-            })
-            .collect();
-        if ret_ty == "void" {
-            format!(
-                "  call {} {}({})",
-                ret_ty,
-                callee,
-                formatted_args.join(", ")
-            )
-        } else {
-            format!(
-                "  %{} = call {} {}({})",
-                inst.id.index(),
-                ret_ty,
-                callee,
-                formatted_args.join(", ")
-            )
+
+        // Like the C backend, methods dispatch to module functions by name;
+        // anything else is an external function declared from this call.
+        let symbol = sanitize_symbol(&name);
+        let (ret_ty, param_tys) = match self.module.functions.iter().find(|f| f.name == name) {
+            Some(callee) => {
+                if callee.params.len() != args.len() {
+                    return Err(BackendError::unsupported(format!(
+                        "call to '{name}' passes {} arguments but it takes {}",
+                        args.len(),
+                        callee.params.len()
+                    )));
+                }
+                let params = callee
+                    .params
+                    .iter()
+                    .map(|param| format_type(self.module, param.ty))
+                    .collect::<Vec<_>>();
+                (format_type(self.module, callee.ret_type), params)
+            }
+            None => {
+                let params = args
+                    .iter()
+                    .map(|arg| format_type(self.module, context.value_type(*arg)))
+                    .collect::<Vec<_>>();
+                let ret = format_type(self.module, inst.ty);
+                let declared: Vec<String> =
+                    params.iter().filter(|ty| *ty != "void").cloned().collect();
+                self.declare(&symbol, &ret, &declared);
+                (ret, params)
+            }
+        };
+
+        let mut formatted_args = Vec::with_capacity(args.len());
+        for (arg, ty) in args.iter().zip(&param_tys) {
+            if ty == "void" {
+                continue;
+            }
+            let arg_ty = format_type(self.module, context.value_type(*arg));
+            if !context.is_unit(*arg) && &arg_ty != ty {
+                return Err(BackendError::unsupported(format!(
+                    "call to '{name}' passes {arg_ty} where {ty} is expected"
+                )));
+            }
+            formatted_args.push(format!("{} {}", ty, context.operand(*arg)?));
+        }
+        let call = format!(
+            "{} {} @{}({})",
+            call,
+            ret_ty,
+            symbol,
+            formatted_args.join(", ")
+        );
+        Ok(define_result(inst, &ret_ty, call, context))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_runtime_call(
+        &mut self,
+        inst: &ir::Instruction,
+        name: &str,
+        symbol: &str,
+        ret: &str,
+        params: &[&str],
+        args: &[ValueId],
+        context: &RenderContext<'_, '_>,
+    ) -> BackendResult<Vec<String>> {
+        let params: Vec<String> = params.iter().map(|ty| ty.to_string()).collect();
+        self.declare(symbol, ret, &params);
+        // Method calls pass the capability token first; the payload, when
+        // the operation takes one, follows it.
+        let payload = &args[args.len().min(1)..];
+        if payload.len() < params.len() {
+            return Err(BackendError::Internal(format!(
+                "method '{name}' expected value argument"
+            )));
         }
+        let mut formatted_args = Vec::with_capacity(params.len());
+        for (arg, ty) in payload.iter().zip(&params) {
+            formatted_args.push(format!("{} {}", ty, context.operand(*arg)?));
+        }
+        let call = format!("call {} @{}({})", ret, symbol, formatted_args.join(", "));
+        Ok(define_result(inst, ret, call, context))
     }
 
     fn render_record_literal(
         &mut self,
         inst: &ir::Instruction,
         fields: &[(String, ValueId)],
-        _context: &mut RenderContext<'_, '_>,
-    ) -> BackendResult<String> {
+        context: &RenderContext<'_, '_>,
+    ) -> BackendResult<Vec<String>> {
         let ty_name = format_type(self.module, inst.ty);
-        match self.module.type_of(inst.ty) {
-            Type::Record(record) => {
-                if record.fields.is_empty() {
-                    return Ok(format!("  %{} = {} undef", inst.id.index(), ty_name));
-                }
-                let mut acc = String::from("undef");
-                let mut expr = String::new();
-                let mut seen = HashSet::new();
-                for (name, _) in fields.iter() {
-                    if !seen.insert(name) {
-                        return Err(BackendError::unsupported(format!(
-                            "duplicate field '{}' in record literal",
-                            name
-                        )));
-                    }
-                }
-                for (name, _) in fields.iter() {
-                    if record.field(name).is_none() {
-                        return Err(BackendError::unsupported(format!(
-                            "record literal field '{}' not present in type {}",
-                            name, ty_name
-                        )));
-                    }
-                }
-                for (index, field) in record.fields.iter().enumerate() {
-                    if let Some((_, value)) = fields.iter().find(|(name, _)| name == &field.name) {
-                        let field_ty = format_type(self.module, field.ty);
-                        expr = format!(
-                            "insertvalue {} {}, {} %{}, {}",
-                            ty_name,
-                            acc,
-                            field_ty,
-                            value.index(),
-                            index
-                        );
-                        if index + 1 != record.fields.len() {
-                            acc = format!("({})", expr);
-                        }
-                    } else {
-                        return Err(BackendError::unsupported(format!(
-                            "record literal missing field '{}' for type {}",
-                            field.name, ty_name
-                        )));
-                    }
-                }
-                Ok(if expr.is_empty() {
-                    format!("  %{} = {} undef", inst.id.index(), ty_name)
-                } else {
-                    format!("  %{} = {}", inst.id.index(), expr)
-                })
-            }
-            _ => Err(BackendError::unsupported(format!(
+        let Type::Record(record) = self.module.type_of(inst.ty) else {
+            return Err(BackendError::unsupported(format!(
                 "record literal for type '{}' requires a concrete record layout",
                 ty_name
-            ))),
+            )));
+        };
+        let mut seen = HashSet::new();
+        for (name, _) in fields.iter() {
+            if !seen.insert(name) {
+                return Err(BackendError::unsupported(format!(
+                    "duplicate field '{}' in record literal",
+                    name
+                )));
+            }
+        }
+        for (name, _) in fields.iter() {
+            if record.field(name).is_none() {
+                return Err(BackendError::unsupported(format!(
+                    "record literal field '{}' not present in type {}",
+                    name, ty_name
+                )));
+            }
         }
+        let result = context.result(inst.id);
+        if record.fields.is_empty() {
+            return Ok(vec![constant(&result, &ty_name, "zeroinitializer")]);
+        }
+        // Each field is inserted into the aggregate built so far.
+        let mut lines = Vec::with_capacity(record.fields.len());
+        let mut acc = String::from("undef");
+        for (index, field) in record.fields.iter().enumerate() {
+            let Some((_, value)) = fields.iter().find(|(name, _)| name == &field.name) else {
+                return Err(BackendError::unsupported(format!(
+                    "record literal missing field '{}' for type {}",
+                    field.name, ty_name
+                )));
+            };
+            let target = if index + 1 == record.fields.len() {
+                result.clone()
+            } else {
+                temporary(inst.id, &format!("f{index}"))
+            };
+            lines.push(format!(
+                "  {} = insertvalue {} {}, {} {}, {}",
+                target,
+                ty_name,
+                acc,
+                storage_type(self.module, field.ty),
+                context.operand(*value)?,
+                index
+            ));
+            acc = target;
+        }
+        Ok(lines)
     }
 
-    fn render_terminator(
-        &self,
-        out: &mut String,
-        terminator: &Terminator,
+    fn render_path(
+        &mut self,
+        inst: &ir::Instruction,
+        path: &crate::syntax::ast::Path,
         context: &RenderContext<'_, '_>,
-    ) {
-        match terminator {
-            Terminator::Return(Some(value)) => {
-                if context.unit_values.contains(value) {
-                    writeln!(out, "  ret void").unwrap();
-                    return;
-                }
-                let ty = context
-                    .value_types
-                    .get(value)
-                    .copied()
-                    .unwrap_or_else(|| context.module.unknown_type());
-                writeln!(
-                    out,
-                    "  ret {} %{}",
-                    format_type(context.module, ty),
-                    value.index()
-                )
-                .unwrap();
-            }
-            Terminator::Return(None) => {
-                writeln!(out, "  ret void").unwrap();
-            }
-            Terminator::Branch {
-                condition,
-                then_block,
-                else_block,
-            } => {
-                let ty = context
-                    .value_types
-                    .get(condition)
-                    .copied()
-                    .unwrap_or_else(|| context.module.unknown_type());
-                writeln!(
-                    out,
-                    "  br {} %{}, label %bb{}, label %bb{}",
-                    format_type(context.module, ty),
-                    condition.index(),
-                    then_block.index(),
-                    else_block.index()
-                )
-                .unwrap();
-            }
-            Terminator::Jump(target) => {
-                writeln!(out, "  br label %bb{}", target.index()).unwrap();
-            }
+    ) -> Vec<String> {
+        let name = path.segments.join("::");
+        let symbol = sanitize_symbol(&name);
+        let result = context.result(inst.id);
+        let ty = format_type(self.module, inst.ty);
+        if self.module.functions.iter().any(|f| f.name == name) {
+            return vec![format!("  {result} = bitcast ptr @{symbol} to ptr")];
+        }
+        // Anything else is a global the linker provides, read as a value of
+        // the path's type.
+        if context.is_unit(inst.id) {
+            return Vec::new();
+        }
+        self.external_globals
+            .entry(symbol.clone())
+            .or_insert_with(|| ty.clone());
+        vec![format!("  {result} = load {ty}, ptr @{symbol}")]
+    }
+
+    fn render_list(
+        &mut self,
+        inst: &ir::Instruction,
+        elements: &[ValueId],
+        context: &RenderContext<'_, '_>,
+    ) -> BackendResult<Vec<String>> {
+        self.declare_list_runtime();
+        let element_size = match self.module.type_of(inst.ty) {
+            Type::List(element) => self.module.types.size_of(*element),
+            _ => 8,
+        };
+        let list = context.result(inst.id);
+        let mut lines = vec![format!(
+            "  {} = call ptr @mica_list_new(i64 {}, i64 {})",
+            list,
+            element_size,
+            elements.len()
+        )];
+        for element in elements {
+            lines.extend(push_lines(&list, *element, context)?);
         }
+        Ok(lines)
     }
 
     fn intern_string(&mut self, value: &str) -> String {
@@ -474,166 +826,407 @@ impl<'m> ModuleRenderer<'m> {
 struct RenderContext<'m, 'p> {
     module: &'m ir::Module,
     value_types: HashMap<ValueId, TypeId>,
+    /// LLVM name of every value that has one: parameters keep their source
+    /// names and instruction results are numbered in definition order.
+    names: HashMap<ValueId, String>,
+    /// Values of type `Unit`, which have no LLVM representation.
     unit_values: HashSet<ValueId>,
+    /// Marker (`tail` or `musttail`) of each call in tail position.
+    tail_calls: HashMap<ValueId, &'static str>,
     purity: Option<&'p ir::analysis::PurityReport>,
+    /// Entry-block stack slot for each list element type pushed.
+    slots: BTreeMap<String, String>,
+    /// `DISubprogram` node and line of the function, with debug info on.
+    debug_scope: Option<(usize, u32)>,
+    ret_type: TypeId,
 }
 
 impl<'m, 'p> RenderContext<'m, 'p> {
-    fn new(module: &'m ir::Module, purity: Option<&'p ir::analysis::PurityReport>) -> Self {
-        RenderContext {
+    fn new(
+        module: &'m ir::Module,
+        function: &ir::Function,
+        purity: Option<&'p ir::analysis::PurityReport>,
+    ) -> BackendResult<Self> {
+        let mut context = RenderContext {
             module,
             value_types: HashMap::new(),
+            names: HashMap::new(),
             unit_values: HashSet::new(),
+            tail_calls: HashMap::new(),
             purity,
+            slots: BTreeMap::new(),
+            debug_scope: None,
+            ret_type: function.ret_type,
+        };
+
+        let mut taken: HashSet<String> = function
+            .blocks
+            .iter()
+            .map(|block| format!("bb{}", block.id.index()))
+            .collect();
+        for (index, param) in function.params.iter().enumerate() {
+            context.value_types.insert(param.value, param.ty);
+            if matches!(module.type_of(param.ty), Type::Unit) {
+                context.unit_values.insert(param.value);
+                continue;
+            }
+            let mut name = sanitize_local(&param.name);
+            if !taken.insert(name.clone()) {
+                name = format!("{name}.{index}");
+                taken.insert(name.clone());
+            }
+            context.names.insert(param.value, format!("%{name}"));
+        }
+
+        let mut next = 0;
+        for inst in function.blocks.iter().flat_map(|block| &block.instructions) {
+            let ty = refine_type(module, inst, &context.value_types);
+            if context.value_types.insert(inst.id, ty).is_some() {
+                return Err(BackendError::Internal(format!(
+                    "function '{}' defines %{} twice",
+                    function.name,
+                    inst.id.index()
+                )));
+            }
+            let unit = matches!(module.type_of(ty), Type::Unit)
+                || matches!(
+                    inst.kind,
+                    InstKind::ListPush { .. }
+                        | InstKind::Retain { .. }
+                        | InstKind::Release { .. }
+                        | InstKind::Literal(Literal::Unit)
+                );
+            if unit {
+                context.unit_values.insert(inst.id);
+            } else {
+                context.names.insert(inst.id, format!("%{next}"));
+                next += 1;
+            }
+        }
+
+        for inst in function.blocks.iter().flat_map(|block| &block.instructions) {
+            let pushed = match &inst.kind {
+                InstKind::List { elements } => elements.clone(),
+                InstKind::ListPush { value, .. } => vec![*value],
+                _ => continue,
+            };
+            for value in pushed {
+                let ty = storage_type(module, context.value_type(value));
+                let slot = format!("%slot.{}", context.slots.len());
+                context.slots.entry(ty).or_insert(slot);
+            }
+        }
+        Ok(context)
+    }
+
+    fn value_type(&self, value: ValueId) -> TypeId {
+        self.value_types
+            .get(&value)
+            .copied()
+            .unwrap_or_else(|| self.module.unknown_type())
+    }
+
+    fn is_unit(&self, value: ValueId) -> bool {
+        self.unit_values.contains(&value)
+    }
+
+    /// Name an instruction defines; only asked of values that have one.
+    fn result(&self, value: ValueId) -> String {
+        self.names
+            .get(&value)
+            .cloned()
+            .unwrap_or_else(|| temporary(value, "unit"))
+    }
+
+    /// A value used as an operand. Unit values read as `zeroinitializer`
+    /// wherever a typed operand is needed.
+    fn operand(&self, value: ValueId) -> BackendResult<String> {
+        if let Some(name) = self.names.get(&value) {
+            return Ok(name.clone());
         }
+        if self.is_unit(value) {
+            return Ok("zeroinitializer".to_string());
+        }
+        Err(BackendError::Internal(format!(
+            "use of undefined value %{}",
+            value.index()
+        )))
     }
 }
 
-fn render_binary(
+/// The type of `inst`'s result. Where the IR left it unknown, a call to a
+/// module function, a record field, a list element or arithmetic still has
+/// the type the callee, record, list or left operand provides.
+fn refine_type(
+    module: &ir::Module,
     inst: &ir::Instruction,
-    op: crate::syntax::ast::BinaryOp,
-    lhs: ValueId,
-    rhs: ValueId,
+    value_types: &HashMap<ValueId, TypeId>,
+) -> TypeId {
+    if !matches!(module.type_of(inst.ty), Type::Unknown) {
+        return inst.ty;
+    }
+    let known = |value: &ValueId| value_types.get(value).map(|ty| module.type_of(*ty));
+    let refined = match &inst.kind {
+        InstKind::Call { func, .. } => {
+            let name = match func {
+                // The clock's reading is an `Int`, when the module has one.
+                ir::FuncRef::Method(name) if name == "now_millis" => {
+                    return module
+                        .types
+                        .entries()
+                        .find(|(_, ty)| matches!(ty, Type::Int))
+                        .map_or(inst.ty, |(id, _)| id);
+                }
+                ir::FuncRef::Method(name) if runtime_method(name).is_some() => None,
+                ir::FuncRef::Method(name) => Some(name.clone()),
+                ir::FuncRef::Function(path) => Some(path.segments.join("::")),
+            };
+            name.and_then(|name| module.functions.iter().find(|f| f.name == name))
+                .map(|callee| callee.ret_type)
+        }
+        InstKind::Field { base, name } => match known(base) {
+            Some(Type::Record(record)) => record.field(name).map(|field| field.ty),
+            _ => None,
+        },
+        InstKind::Binary {
+            op: BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod,
+            lhs,
+            ..
+        } => value_types.get(lhs).copied(),
+        InstKind::ListIndex { list, .. } => match known(list) {
+            Some(Type::List(element)) => Some(*element),
+            _ => None,
+        },
+        _ => None,
+    };
+    refined.unwrap_or(inst.ty)
+}
+
+/// Named helper values an instruction needs besides its result, such as
+/// `%t4.ptr`; the dot keeps them apart from parameter names.
+fn temporary(value: ValueId, suffix: &str) -> String {
+    format!("%t{}.{}", value.index(), suffix)
+}
+
+/// Defines `value` as the constant `constant`.
+fn constant(result: &str, ty: &str, constant: &str) -> String {
+    format!("  {result} = select i1 true, {ty} {constant}, {ty} {constant}")
+}
+
+/// The lines for a call producing `ret`: the call defines the instruction's
+/// result directly. A unit-typed call's value is discarded into a temporary,
+/// and when the IR expects a value from a `void` call, it gets a zero.
+fn define_result(
+    inst: &ir::Instruction,
+    ret: &str,
+    call: String,
     context: &RenderContext<'_, '_>,
-) -> String {
-    let result_id = inst.id.index();
-    let lhs_ty = context
-        .value_types
-        .get(&lhs)
-        .copied()
-        .unwrap_or_else(|| context.module.unknown_type());
-    let ty_name = format_type(context.module, lhs_ty);
-    match op {
-        crate::syntax::ast::BinaryOp::Add => {
-            binary_arith("add", "fadd", ty_name, lhs, rhs, result_id)
-        }
-        crate::syntax::ast::BinaryOp::Sub => {
-            binary_arith("sub", "fsub", ty_name, lhs, rhs, result_id)
-        }
-        crate::syntax::ast::BinaryOp::Mul => {
-            binary_arith("mul", "fmul", ty_name, lhs, rhs, result_id)
-        }
-        crate::syntax::ast::BinaryOp::Div => {
-            binary_arith("sdiv", "fdiv", ty_name, lhs, rhs, result_id)
-        }
-        crate::syntax::ast::BinaryOp::Mod => {
-            binary_arith("srem", "frem", ty_name, lhs, rhs, result_id)
-        }
-        crate::syntax::ast::BinaryOp::And => format!(
-            "  %{} = and {} %{}, %{}",
-            result_id,
-            ty_name,
-            lhs.index(),
-            rhs.index()
-        ),
-        crate::syntax::ast::BinaryOp::Or => format!(
-            "  %{} = or {} %{}, %{}",
-            result_id,
-            ty_name,
-            lhs.index(),
-            rhs.index()
-        ),
-        crate::syntax::ast::BinaryOp::Eq => render_cmp("eq", "oeq", ty_name, lhs, rhs, result_id),
-        crate::syntax::ast::BinaryOp::Ne => render_cmp("ne", "one", ty_name, lhs, rhs, result_id),
-        crate::syntax::ast::BinaryOp::Lt => render_cmp("slt", "olt", ty_name, lhs, rhs, result_id),
-        crate::syntax::ast::BinaryOp::Le => render_cmp("sle", "ole", ty_name, lhs, rhs, result_id),
-        crate::syntax::ast::BinaryOp::Gt => render_cmp("sgt", "ogt", ty_name, lhs, rhs, result_id),
-        crate::syntax::ast::BinaryOp::Ge => render_cmp("sge", "oge", ty_name, lhs, rhs, result_id),
+) -> Vec<String> {
+    let result = context.result(inst.id);
+    match (context.is_unit(inst.id), ret == "void") {
+        (true, true) => vec![format!("  {call}")],
+        (true, false) => vec![format!("  {} = {call}", temporary(inst.id, "unused"))],
+        (false, false) => vec![format!("  {result} = {call}")],
+        (false, true) => vec![
+            format!("  {call}"),
+            constant(
+                &result,
+                &format_type(context.module, context.value_type(inst.id)),
+                "zeroinitializer",
+            ),
+        ],
     }
 }
 
 fn binary_arith(
     int_op: &str,
     float_op: &str,
-    ty_name: String,
-    lhs: ValueId,
-    rhs: ValueId,
-    result_id: u32,
+    ty_name: &str,
+    lhs: &str,
+    rhs: &str,
+    result: &str,
 ) -> String {
     if ty_name == "double" {
-        format!(
-            "  %{} = {} double %{}, %{}",
-            result_id,
-            float_op,
-            lhs.index(),
-            rhs.index()
-        )
+        format!("  {result} = {float_op} double {lhs}, {rhs}")
     } else {
-        format!(
-            "  %{} = {} {} %{}, %{}",
-            result_id,
-            int_op,
-            ty_name,
-            lhs.index(),
-            rhs.index()
-        )
+        format!("  {result} = {int_op} {ty_name} {lhs}, {rhs}")
     }
 }
 
 fn render_cmp(
     int_pred: &str,
     float_pred: &str,
-    ty_name: String,
-    lhs: ValueId,
-    rhs: ValueId,
-    result_id: u32,
+    ty_name: &str,
+    lhs: &str,
+    rhs: &str,
+    result: &str,
 ) -> String {
     if ty_name == "double" {
-        format!(
-            "  %{} = fcmp {} double %{}, %{}",
-            result_id,
-            float_pred,
-            lhs.index(),
-            rhs.index()
-        )
+        format!("  {result} = fcmp {float_pred} double {lhs}, {rhs}")
     } else {
-        let cmp_ty = if ty_name == "void" {
-            "i1".to_string()
-        } else {
-            ty_name
-        };
-        format!(
-            "  %{} = icmp {} {} %{}, %{}",
-            result_id,
-            int_pred,
-            cmp_ty,
-            lhs.index(),
-            rhs.index()
-        )
+        format!("  {result} = icmp {int_pred} {ty_name} {lhs}, {rhs}")
+    }
+}
+
+fn render_terminator(
+    terminator: &Terminator,
+    context: &RenderContext<'_, '_>,
+) -> BackendResult<String> {
+    let ret_ty = format_type(context.module, context.ret_type);
+    Ok(match terminator {
+        // The function's type decides the return; unit values and missing
+        // ones become zero in functions that return something.
+        Terminator::Return(_) if ret_ty == "void" => "  ret void".to_string(),
+        Terminator::Return(Some(value)) => {
+            let value_ty = format_type(context.module, context.value_type(*value));
+            if !context.is_unit(*value) && value_ty != ret_ty {
+                return Err(BackendError::unsupported(format!(
+                    "function returning {ret_ty} returns a {value_ty} value"
+                )));
+            }
+            format!("  ret {} {}", ret_ty, context.operand(*value)?)
+        }
+        Terminator::Return(None) => format!("  ret {} zeroinitializer", ret_ty),
+        Terminator::Branch {
+            condition,
+            then_block,
+            else_block,
+        } => format!(
+            "  br i1 {}, label %bb{}, label %bb{}",
+            context.operand(*condition)?,
+            then_block.index(),
+            else_block.index()
+        ),
+        Terminator::Jump(target) => format!("  br label %bb{}", target.index()),
+    })
+}
+
+/// Copies `value` into its element slot and passes the slot's address to the
+/// runtime, which copies it into the list.
+fn push_lines(
+    list: &str,
+    value: ValueId,
+    context: &RenderContext<'_, '_>,
+) -> BackendResult<Vec<String>> {
+    let ty = storage_type(context.module, context.value_type(value));
+    let slot = &context.slots[&ty];
+    Ok(vec![
+        format!("  store {ty} {}, ptr {slot}", context.operand(value)?),
+        format!("  call void @mica_list_push(ptr {list}, ptr {slot})"),
+    ])
+}
+
+fn render_list_push(
+    list: ValueId,
+    value: ValueId,
+    context: &RenderContext<'_, '_>,
+) -> BackendResult<Vec<String>> {
+    push_lines(&context.operand(list)?, value, context)
+}
+
+fn render_field(
+    inst: &ir::Instruction,
+    base: ValueId,
+    name: &str,
+    context: &RenderContext<'_, '_>,
+) -> BackendResult<Vec<String>> {
+    let base_ty = context.value_type(base);
+    let Type::Record(record) = context.module.type_of(base_ty) else {
+        return Err(BackendError::unsupported(format!(
+            "field access '.{}' requires a concrete record layout",
+            name
+        )));
+    };
+    let Some(index) = record.fields.iter().position(|field| field.name == name) else {
+        return Err(BackendError::unsupported(format!(
+            "record {} has no field '{}'",
+            format_type(context.module, base_ty),
+            name
+        )));
+    };
+    if context.is_unit(inst.id) {
+        return Ok(Vec::new());
     }
+    Ok(vec![format!(
+        "  {} = extractvalue {} {}, {}",
+        context.result(inst.id),
+        format_type(context.module, base_ty),
+        context.operand(base)?,
+        index
+    )])
 }
 
-fn render_path(inst: &ir::Instruction, path: &crate::syntax::ast::Path) -> String {
-    let symbol = sanitize_symbol(&path.segments.join("::"));
-    format!("  %{} = bitcast ptr @{} to ptr", inst.id.index(), symbol)
+fn render_convert(
+    inst: &ir::Instruction,
+    value: ValueId,
+    context: &RenderContext<'_, '_>,
+) -> BackendResult<Vec<String>> {
+    let from = context.value_type(value);
+    let opcode = match (
+        context.module.type_of(from),
+        context.module.type_of(inst.ty),
+    ) {
+        (Type::Int, Type::Float) => "sitofp",
+        (Type::Float, Type::Int) => "fptosi",
+        _ => {
+            return Err(BackendError::unsupported(format!(
+                "conversion from {} to {} cannot be lowered to LLVM IR",
+                format_type(context.module, from),
+                format_type(context.module, inst.ty)
+            )));
+        }
+    };
+    Ok(vec![format!(
+        "  {} = {opcode} {} {} to {}",
+        context.result(inst.id),
+        format_type(context.module, from),
+        context.operand(value)?,
+        format_type(context.module, inst.ty)
+    )])
 }
 
 fn render_phi(
     inst: &ir::Instruction,
     incomings: &[(crate::ir::BlockId, ValueId)],
     context: &RenderContext<'_, '_>,
-) -> String {
+) -> BackendResult<Vec<String>> {
+    if context.is_unit(inst.id) {
+        return Ok(Vec::new());
+    }
     let ty = format_type(context.module, inst.ty);
     let mut parts = Vec::new();
     for (block, value) in incomings {
-        parts.push(format!("[ %{}, %bb{} ]", value.index(), block.index()));
+        let value_ty = format_type(context.module, context.value_type(*value));
+        if !context.is_unit(*value) && value_ty != ty {
+            return Err(BackendError::unsupported(format!(
+                "phi of type {ty} merges a {value_ty} value"
+            )));
+        }
+        parts.push(format!(
+            "[ {}, %bb{} ]",
+            context.operand(*value)?,
+            block.index()
+        ));
     }
-    format!("  %{} = phi {} {}", inst.id.index(), ty, parts.join(", "))
+    Ok(vec![format!(
+        "  {} = phi {} {}",
+        context.result(inst.id),
+        ty,
+        parts.join(", ")
+    )])
 }
 
+/// Escapes `value` byte by byte for a `c"..."` constant.
 fn escape_string(value: &str) -> String {
-    value
-        .chars()
-        .flat_map(|ch| match ch {
-            '\\' => Some("\\5C".to_string()),
-            '"' => Some("\\22".to_string()),
-            '\n' => Some("\\0A".to_string()),
-            '\r' => Some("\\0D".to_string()),
-            '\t' => Some("\\09".to_string()),
-            c if c.is_ascii_graphic() || c == ' ' => Some(c.to_string()),
-            other => Some(format!("\\{:02X}", other as u32)),
-        })
-        .collect()
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'\\' | b'"' => write!(out, "\\{:02X}", byte).unwrap(),
+            b' '..=b'~' => out.push(byte as char),
+            other => write!(out, "\\{:02X}", other).unwrap(),
+        }
+    }
+    out
 }
 
 fn format_type(module: &ir::Module, ty: TypeId) -> String {
@@ -644,6 +1237,7 @@ fn format_type(module: &ir::Module, ty: TypeId) -> String {
         Type::Bool => "i1".to_string(),
         Type::String => "ptr".to_string(),
         Type::Named(_name) => "ptr".to_string(),
+        Type::List(_) | Type::Sum(_) => "ptr".to_string(),
         Type::Record(record) => {
             if let Some(name) = &record.name {
                 format!("%{}", record_symbol(name))
@@ -652,7 +1246,7 @@ fn format_type(module: &ir::Module, ty: TypeId) -> String {
             } else {
                 let mut parts = Vec::with_capacity(record.fields.len());
                 for field in &record.fields {
-                    parts.push(format_type(module, field.ty));
+                    parts.push(storage_type(module, field.ty));
                 }
                 format!("{{ {} }}", parts.join(", "))
             }
@@ -661,10 +1255,29 @@ fn format_type(module: &ir::Module, ty: TypeId) -> String {
     }
 }
 
+/// Like [`format_type`], but `Unit` stored in a record field or list slot
+/// is the empty struct, since `void` cannot be stored.
+fn storage_type(module: &ir::Module, ty: TypeId) -> String {
+    match module.type_of(ty) {
+        Type::Unit => "{}".to_string(),
+        _ => format_type(module, ty),
+    }
+}
+
 fn sanitize_symbol(name: &str) -> String {
     name.replace(['.', ':'], "_")
 }
 
+/// Parameter names must not look like numbered values.
+fn sanitize_local(name: &str) -> String {
+    let name = sanitize_symbol(name);
+    if name.starts_with(|ch: char| ch.is_ascii_digit()) || name.is_empty() {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
 fn record_symbol(name: &str) -> String {
     format!("record.{}", sanitize_symbol(name))
 }
@@ -672,3 +1285,16 @@ fn record_symbol(name: &str) -> String {
 pub(crate) fn default_data_layout() -> &'static str {
     "e-m:e-p:64:64-i64:64-f64:64-n8:16:32:64-S128"
 }
+
+/// The data layout LLVM uses for `triple`'s architecture, or the generic
+/// 64-bit layout when there is no triple or it is not one we know.
+fn data_layout(triple: Option<&str>) -> &'static str {
+    let arch = triple.and_then(|triple| triple.split('-').next());
+    match arch {
+        Some("x86_64") => "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128",
+        Some("aarch64") => "e-m:e-i8:8:32-i16:16:32-i64:64-i128:128-n32:64-S128",
+        Some("wasm32") => "e-m:e-p:32:32-i64:64-n32:64-S128",
+        Some("riscv64") => "e-m:e-p:64:64-i64:64-i128:128-n32:64-S128",
+        _ => default_data_layout(),
+    }
+}
@@ -1,19 +1,94 @@
+use std::collections::BTreeMap;
 use std::fmt;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use crate::ir;
 
+pub mod bytecode;
+pub mod cache;
+pub mod dot;
 pub mod llvm;
 pub mod native;
 pub mod text;
+pub mod wasm;
+pub mod x86_64;
 
 #[derive(Debug, Clone, Default)]
 pub struct BackendOptions {
     pub optimize: bool,
     pub debug_info: bool,
     pub target_triple: Option<String>,
+    /// Keep `pub` functions as entry points next to `main` when pruning
+    /// unreachable code, as a library build needs.
+    pub library: bool,
+    /// Source file of each module, keyed by its dotted path, for the line
+    /// information `debug_info` adds.
+    pub source_files: BTreeMap<String, PathBuf>,
+}
+
+impl BackendOptions {
+    /// The file holding the source of `function`, as named in provenance:
+    /// linked modules qualify their functions with the module path, and
+    /// unqualified names belong to `module` itself. Modules without a
+    /// registered file fall back to their path with a `.mica` extension.
+    pub fn source_file(&self, module: &ir::Module, function: &str) -> String {
+        let path = match function.rsplit_once("::") {
+            Some((prefix, _)) => prefix.replace("::", "."),
+            None => module.name.join("."),
+        };
+        match self.source_files.get(&path) {
+            Some(file) => file.display().to_string(),
+            None => format!("{}.mica", path.replace('.', "/")),
+        }
+    }
+}
+
+/// Short name of `ty` for diagnostics about values a backend cannot lower.
+pub(crate) fn type_label(ty: &ir::Type) -> &'static str {
+    match ty {
+        ir::Type::Unit => "Unit",
+        ir::Type::Int => "Int",
+        ir::Type::Float => "Float",
+        ir::Type::Bool => "Bool",
+        ir::Type::String => "String",
+        ir::Type::Named(_) => "named",
+        ir::Type::Record(_) => "record",
+        ir::Type::Sum(_) => "sum",
+        ir::Type::List(_) => "list",
+        ir::Type::Unknown => "untyped",
+    }
+}
+
+/// The module function a call to `func` with `arg_count` arguments runs,
+/// with its index. Like the C backend, methods the backend does not handle
+/// itself dispatch to module functions by name. `lowered` completes the
+/// error message, as in "cannot be lowered to bytecode".
+pub(crate) fn resolve_callee<'m>(
+    module: &'m ir::Module,
+    func: &ir::FuncRef,
+    arg_count: usize,
+    lowered: &str,
+) -> BackendResult<(usize, &'m ir::Function)> {
+    let name = match func {
+        ir::FuncRef::Method(name) => name.clone(),
+        ir::FuncRef::Function(path) => path.segments.join("::"),
+    };
+    let Some(index) = module.functions.iter().position(|f| f.name == name) else {
+        return Err(BackendError::unsupported(format!(
+            "call to '{name}' cannot be lowered {lowered}"
+        )));
+    };
+    let callee = &module.functions[index];
+    if callee.params.len() != arg_count {
+        return Err(BackendError::unsupported(format!(
+            "call to '{name}' passes {arg_count} arguments but it takes {}",
+            callee.params.len()
+        )));
+    }
+    Ok((index, callee))
 }
 
 pub trait Backend {
@@ -52,11 +127,18 @@ impl fmt::Display for BackendError {
 
 impl std::error::Error for BackendError {}
 
+/// Compiles `module` with `backend`, first running the IR optimisation
+/// pipeline over a copy of it when `options.optimize` is set.
 pub fn run<B: Backend>(
     backend: &B,
     module: &ir::Module,
     options: &BackendOptions,
 ) -> BackendResult<B::Output> {
+    if options.optimize {
+        let mut optimized = module.clone();
+        ir::passes::optimize(&mut optimized);
+        return backend.compile(&optimized, options);
+    }
     backend.compile(module, options)
 }
 
@@ -168,7 +250,7 @@ where
                     let module = &modules[index];
                     let dispatch_offset = start.elapsed();
                     let module_start = Instant::now();
-                    match backend.compile(module, &options) {
+                    match run(backend, module, &options) {
                         Ok(result) => {
                             let duration = module_start.elapsed();
                             records.lock().unwrap()[index] = Some(ModuleRecord {
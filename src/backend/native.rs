@@ -3,14 +3,80 @@ use std::fmt::Write as _;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::ir::analysis::{AnalysisManager, EffectSummaries};
+use crate::ir::passes::{
+    DeadFunctionElimination, Pass, ReferenceCounting, TailCallElimination, is_managed,
+};
 use crate::ir::{self, InstKind, Terminator, Type, ValueId};
+use crate::syntax::ast::{BinaryOp, Literal};
 
 use super::{Backend, BackendError, BackendOptions, BackendResult};
 
+/// The C names of the module's records (`record_*`) and sum types
+/// (`sum_*`).
 type RecordNameMap = HashMap<ir::TypeId, String>;
 
+/// Mutually recursive functions whose tail calls to each other return to a
+/// trampoline instead of growing the C stack.
+///
+/// Each member's body becomes `mica_tail{index}_{name}`, which stores the
+/// arguments of a sibling tail call in the shared `mica_tail{index}` frame
+/// and returns; the member's public function loops until no call is pending.
+struct TailGroup {
+    index: usize,
+    members: Vec<String>,
+    arities: Vec<usize>,
+}
+
+impl TailGroup {
+    fn frame_type(&self) -> String {
+        format!("mica_tail{}", self.index)
+    }
+
+    fn body_name(&self, member: usize) -> String {
+        format!(
+            "mica_tail{}_{}",
+            self.index,
+            mangle_name(&self.members[member])
+        )
+    }
+
+    fn member(&self, name: &str) -> Option<usize> {
+        self.members.iter().position(|member| member == name)
+    }
+
+    /// The member `block` tail-calls and the arguments it passes.
+    fn sibling_call<'b>(&self, block: &'b ir::BasicBlock) -> Option<(usize, &'b [ValueId])> {
+        let (path, args) = block.tail_call()?;
+        let member = self.member(&path.segments.join("::"))?;
+        (self.arities[member] == args.len()).then_some((member, args))
+    }
+}
+
+/// Version of the C runtime and code generation. Build cache keys include
+/// it, so it must be bumped whenever the emitted C changes for the same
+/// module; otherwise cached executables of the old output are reused.
+pub const RUNTIME_VERSION: u32 = 1;
+
+/// Flags every `cc` invocation starts with; `-g` follows for debug builds.
+pub const CC_FLAGS: [&str; 2] = ["-std=c11", "-O2"];
+
+/// What `cc --version` prints, or nothing when `cc` cannot be run. Build
+/// cache keys include it, so switching compilers rebuilds.
+pub fn compiler_identity() -> &'static str {
+    static IDENTITY: OnceLock<String> = OnceLock::new();
+    IDENTITY.get_or_init(|| {
+        Command::new("cc")
+            .arg("--version")
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default()
+    })
+}
+
 /// Backend that lowers the typed SSA module into portable C code and relies on
 /// the host C compiler to produce machine code. This approach keeps the
 /// backend dependency-free while still emitting native executables.
@@ -18,12 +84,18 @@ type RecordNameMap = HashMap<ir::TypeId, String>;
 pub struct NativeBackend;
 
 /// Native artifact produced by the backend. The generated C source is exposed
-/// for inspection and can be compiled into an executable through helper
-/// methods.
+/// for inspection and can be compiled into an executable or, for library
+/// builds, a shared or static library through helper methods.
 #[derive(Debug, Clone)]
 pub struct NativeArtifact {
     pub c_source: String,
     pub module_name: String,
+    /// Compile with `-g`, so the `#line` directives in the source reach the
+    /// debugger.
+    pub debug_info: bool,
+    /// For library builds, the `mica.h` header declaring the exported
+    /// functions and the records they use.
+    pub header: Option<String>,
 }
 
 impl NativeArtifact {
@@ -35,37 +107,82 @@ impl NativeArtifact {
         Ok(path.to_path_buf())
     }
 
+    /// Writes the header of a library build to the provided path.
+    pub fn write_header<P: AsRef<Path>>(&self, path: P) -> BackendResult<PathBuf> {
+        let path = path.as_ref();
+        let header = self.header.as_ref().ok_or_else(|| {
+            BackendError::Internal("only library builds have a header".to_string())
+        })?;
+        fs::write(path, header)
+            .map_err(|err| BackendError::Internal(format!("failed to write header: {err}")))?;
+        Ok(path.to_path_buf())
+    }
+
     /// Compiles the generated C source into an executable at `out_path` using
     /// the system C compiler.
     pub fn link_executable<P: AsRef<Path>>(&self, out_path: P) -> BackendResult<PathBuf> {
         let out_path = out_path.as_ref();
+        self.compile_c(&[], out_path)?;
+        Ok(out_path.to_path_buf())
+    }
+
+    /// Compiles the generated C source into a shared library at `out_path`.
+    pub fn link_shared_library<P: AsRef<Path>>(&self, out_path: P) -> BackendResult<PathBuf> {
+        let out_path = out_path.as_ref();
+        self.compile_c(&["-shared", "-fPIC"], out_path)?;
+        Ok(out_path.to_path_buf())
+    }
+
+    /// Compiles the generated C source into an object file and archives it
+    /// as a static library at `out_path`.
+    pub fn link_static_library<P: AsRef<Path>>(&self, out_path: P) -> BackendResult<PathBuf> {
+        let out_path = out_path.as_ref();
+        let object_path = temp_path("mica", "o");
+        self.compile_c(&["-c", "-fPIC"], &object_path)?;
+        // `ar` adds to an existing archive rather than replacing it.
+        fs::remove_file(out_path).ok();
+        let mut command = Command::new("ar");
+        command.arg("rcs").arg(out_path).arg(&object_path);
+        let archived = run_tool(command, "ar");
+        fs::remove_file(&object_path).ok();
+        archived?;
+        Ok(out_path.to_path_buf())
+    }
+
+    /// Runs `cc` over the generated source with `flags`, writing `out_path`.
+    fn compile_c(&self, flags: &[&str], out_path: &Path) -> BackendResult<()> {
         let c_path = temp_path("mica", "c");
         self.write_source(&c_path)?;
 
-        let output = Command::new("cc")
-            .arg(&c_path)
-            .arg("-std=c11")
-            .arg("-O2")
-            .arg("-o")
-            .arg(out_path)
-            .output()
-            .map_err(|err| BackendError::Internal(format!("failed to invoke cc: {err}")))?;
+        let mut command = Command::new("cc");
+        command.arg(&c_path).args(CC_FLAGS).args(flags);
+        if self.debug_info {
+            command.arg("-g");
+        }
+        command.arg("-o").arg(out_path);
+        let compiled = run_tool(command, "cc");
 
         fs::remove_file(&c_path).ok();
+        compiled
+    }
+}
 
-        if !output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(BackendError::Internal(format!(
-                "cc exited with status {}. stdout: {} stderr: {}",
-                output.status,
-                stdout.trim(),
-                stderr.trim()
-            )));
-        }
-
-        Ok(out_path.to_path_buf())
+/// Runs an external build tool, reporting its output when it fails.
+fn run_tool(mut command: Command, tool: &str) -> BackendResult<()> {
+    let output = command
+        .output()
+        .map_err(|err| BackendError::Internal(format!("failed to invoke {tool}: {err}")))?;
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackendError::Internal(format!(
+            "{tool} exited with status {}. stdout: {} stderr: {}",
+            output.status,
+            stdout.trim(),
+            stderr.trim()
+        )));
     }
+    Ok(())
 }
 
 impl Backend for NativeBackend {
@@ -74,18 +191,108 @@ impl Backend for NativeBackend {
     fn compile(
         &self,
         module: &ir::Module,
-        _options: &BackendOptions,
+        options: &BackendOptions,
     ) -> BackendResult<Self::Output> {
-        let c_source = generate_c_source(module)?;
+        // `cc` only needs what the entry points can reach.
+        let mut module = module.clone();
+        let shaking = DeadFunctionElimination {
+            keep_public: options.library,
+        };
+        let mut analyses = AnalysisManager::new();
+        shaking.run(&mut module, &mut analyses);
+        // Self tail calls become loops; sibling ones go through trampolines.
+        TailCallElimination.run(&mut module, &mut analyses);
+        let exports = if options.library {
+            wrap_string_exports(&mut module)
+        } else {
+            Vec::new()
+        };
+        type_runtime_results(&mut module);
+        // Strings and lists are freed once their last reference goes.
+        ReferenceCounting.run(&mut module, &mut analyses);
+        let c_source = generate_c_source(&module, options, &exports)?;
+        let header = if options.library {
+            Some(generate_c_header(&module, &exports)?)
+        } else {
+            None
+        };
         Ok(NativeArtifact {
             c_source,
             module_name: module.name.join("_"),
+            debug_info: options.debug_info,
+            header,
         })
     }
 }
 
-fn generate_c_source(module: &ir::Module) -> BackendResult<String> {
+/// Points the C compiler, and through it the debugger, back at the Mica
+/// source each function and statement was lowered from.
+struct LineDirectives<'a> {
+    module: &'a ir::Module,
+    options: &'a BackendOptions,
+}
+
+impl LineDirectives<'_> {
+    fn emit(&self, out: &mut String, function: &str, line: u32) {
+        // `#line 0` is not valid C; synthesized code keeps the last line.
+        if line == 0 {
+            return;
+        }
+        let file = self.options.source_file(self.module, function);
+        let file = file.replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(out, "#line {line} \"{file}\"").unwrap();
+    }
+
+    fn emit_provenance(&self, out: &mut String, provenance: Option<&ir::Provenance>) {
+        if let Some(provenance) = provenance {
+            self.emit(out, &provenance.function, provenance.span.line);
+        }
+    }
+
+    /// Places the function's signature on its first source line.
+    fn emit_function(&self, out: &mut String, function: &ir::Function) {
+        self.emit(out, &function.name, first_line(function));
+    }
+}
+
+/// The earliest source line of `function`'s body, or zero without
+/// provenance.
+fn first_line(function: &ir::Function) -> u32 {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block| {
+            block
+                .instructions
+                .iter()
+                .map(|inst| inst.provenance.as_ref())
+                .chain([block.terminator_provenance.as_ref()])
+        })
+        .flatten()
+        .map(|provenance| provenance.span.line)
+        .min()
+        .unwrap_or(0)
+}
+
+fn generate_c_source(
+    module: &ir::Module,
+    options: &BackendOptions,
+    exports: &[StringExport],
+) -> BackendResult<String> {
+    let lines = options
+        .debug_info
+        .then_some(LineDirectives { module, options });
+    let lines = lines.as_ref();
+    let capabilities = collect_capabilities(module);
     let mut out = String::new();
+    let declares = |name: &str| capabilities.iter().any(|cap| cap == name);
+    // `env` and `process` need POSIX beyond `-std=c11`.
+    if declares("env") || declares("process") {
+        writeln!(out, "#define _POSIX_C_SOURCE 200809L").unwrap();
+    }
+    writeln!(out, "#include <ctype.h>").unwrap();
+    writeln!(out, "#include <errno.h>").unwrap();
+    writeln!(out, "#include <stdarg.h>").unwrap();
     writeln!(out, "#include <stdbool.h>").unwrap();
     writeln!(out, "#include <stdint.h>").unwrap();
     writeln!(out, "#include <stddef.h>").unwrap();
@@ -93,39 +300,441 @@ fn generate_c_source(module: &ir::Module) -> BackendResult<String> {
     writeln!(out, "#include <stdio.h>").unwrap();
     writeln!(out, "#include <string.h>").unwrap();
     writeln!(out, "#include <time.h>").unwrap();
+    if declares("process") {
+        writeln!(out, "#include <fcntl.h>").unwrap();
+        writeln!(out, "#include <spawn.h>").unwrap();
+        writeln!(out, "#include <sys/wait.h>").unwrap();
+    }
     writeln!(out).unwrap();
 
     let record_names = collect_record_names(module);
-    let capabilities = collect_capabilities(module);
+    emit_string_support(&mut out);
     emit_runtime_support(&mut out, &capabilities)?;
+    if uses_lists(module) {
+        emit_list_support(&mut out);
+    }
+    emit_sum_declarations(&mut out, module, &record_names);
     emit_record_definitions(&mut out, module, &record_names)?;
+    emit_sum_definitions(&mut out, module, &record_names);
+    emit_record_refcounts(&mut out, module, &record_names);
+    let tail_groups = collect_tail_groups(module);
+    for group in &tail_groups {
+        emit_tail_frame(&mut out, module, group, &record_names);
+    }
 
     // Emit prototypes to allow mutual recursion.
     for function in &module.functions {
         writeln!(
             out,
-            "{};",
+            "{}{};",
+            linkage(options, function.is_public),
             function_signature(module, function, &record_names)?
         )
         .unwrap();
+        if let Some((group, member)) = tail_group_of(&tail_groups, &function.name) {
+            writeln!(
+                out,
+                "{}{};",
+                linkage(options, false),
+                tail_body_signature(module, group, member, function, &record_names)
+            )
+            .unwrap();
+        }
     }
     writeln!(out).unwrap();
 
     for function in &module.functions {
+        if let Some(lines) = lines {
+            lines.emit_function(&mut out, function);
+        }
         writeln!(
             out,
-            "{} {{",
+            "{}{} {{",
+            linkage(options, function.is_public),
             function_signature(module, function, &record_names)?
         )
         .unwrap();
-        emit_function_body(&mut out, module, function, &record_names)?;
+        let Some((group, member)) = tail_group_of(&tail_groups, &function.name) else {
+            emit_function_body(&mut out, module, function, None, &record_names, lines)?;
+            writeln!(out, "}}").unwrap();
+            writeln!(out).unwrap();
+            continue;
+        };
+        emit_trampoline(&mut out, module, group, member, function, &record_names);
+        writeln!(out, "}}").unwrap();
+        writeln!(out).unwrap();
+        if let Some(lines) = lines {
+            lines.emit_function(&mut out, function);
+        }
+        writeln!(
+            out,
+            "{}{} {{",
+            linkage(options, false),
+            tail_body_signature(module, group, member, function, &record_names)
+        )
+        .unwrap();
+        emit_function_body(
+            &mut out,
+            module,
+            function,
+            Some(group),
+            &record_names,
+            lines,
+        )?;
         writeln!(out, "}}").unwrap();
         writeln!(out).unwrap();
     }
 
+    for export in exports {
+        emit_string_export(&mut out, module, export, &record_names)?;
+    }
+    if options.library {
+        for (kind, param) in library_releases(module) {
+            writeln!(
+                out,
+                "void {}({param}) {{\n  mica_{kind}_release(value);\n}}\n",
+                release_symbol(module, kind)
+            )
+            .unwrap();
+        }
+    }
+
     Ok(out)
 }
 
+/// Library builds export only their `pub` functions; everything else gets
+/// internal linkage, so it cannot clash with the symbols of the program the
+/// library is linked into.
+fn linkage(options: &BackendOptions, exported: bool) -> &'static str {
+    if options.library && !exported {
+        "static "
+    } else {
+        ""
+    }
+}
+
+/// The `mica.h` header of a library build: the records of the module in C
+/// layout, opaque declarations of the reference-counted runtime types, the
+/// release functions for what C callers receive, and prototypes of the
+/// `pub` functions.
+fn generate_c_header(module: &ir::Module, exports: &[StringExport]) -> BackendResult<String> {
+    let record_names = collect_record_names(module);
+    let mut out = String::new();
+    writeln!(
+        out,
+        "/* Generated by mica from module {}. */",
+        module.name.join(".")
+    )
+    .unwrap();
+    writeln!(out, "#ifndef MICA_H").unwrap();
+    writeln!(out, "#define MICA_H").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#include <stdbool.h>").unwrap();
+    writeln!(out, "#include <stdint.h>").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#ifdef __cplusplus").unwrap();
+    writeln!(out, "extern \"C\" {{").unwrap();
+    writeln!(out, "#endif").unwrap();
+    writeln!(out).unwrap();
+
+    // Strings are `const char *`; lists and sum values are only handed
+    // back and forth, so their layout stays private to the library.
+    let mut opaque = Vec::new();
+    if uses_lists(module) {
+        opaque.push("mica_list".to_string());
+    }
+    opaque.extend(
+        sum_entries(module, &record_names)
+            .into_iter()
+            .map(|(_, name)| name),
+    );
+    for name in &opaque {
+        writeln!(out, "typedef struct {name} {name};").unwrap();
+    }
+    if !opaque.is_empty() {
+        writeln!(out).unwrap();
+    }
+    emit_record_definitions(&mut out, module, &record_names)?;
+
+    // Strings and lists returned to C belong to the caller, which releases
+    // them; string arguments are copied, so callers keep their own.
+    for (kind, param) in library_releases(module) {
+        writeln!(out, "void {}({param});", release_symbol(module, kind)).unwrap();
+    }
+    writeln!(out).unwrap();
+
+    for function in &module.functions {
+        let name = match exports
+            .iter()
+            .find(|export| export.implementation == function.name)
+        {
+            Some(export) => &export.name,
+            None if function.is_public => &function.name,
+            None => continue,
+        };
+        writeln!(
+            out,
+            "{};",
+            named_signature(module, function, name, &record_names)?
+        )
+        .unwrap();
+    }
+    writeln!(out).unwrap();
+    writeln!(out, "#ifdef __cplusplus").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out, "#endif").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#endif").unwrap();
+    Ok(out)
+}
+
+/// Retain and release functions for records holding strings, lists or sum
+/// values, which visit every such field, and for sum types, which release
+/// the payload of the variant they hold; plus the slot callback lists of
+/// them use.
+fn emit_record_refcounts(out: &mut String, module: &ir::Module, record_names: &RecordNameMap) {
+    let mut entries: Vec<_> = record_names
+        .iter()
+        .filter(|(id, _)| is_managed(&module.types, **id))
+        .collect();
+    if entries.is_empty() {
+        return;
+    }
+    entries.sort_by_key(|(id, _)| id.index());
+
+    // Nested records and sums may be declared in any order.
+    for (type_id, name) in &entries {
+        let c_type = c_type_value(module, **type_id, record_names);
+        writeln!(out, "static void mica_retain_{name}({c_type} value);").unwrap();
+        writeln!(out, "static void mica_release_{name}({c_type} value);").unwrap();
+    }
+    writeln!(out).unwrap();
+    for (type_id, name) in entries {
+        let c_type = c_type_value(module, *type_id, record_names);
+        match module.type_of(*type_id) {
+            Type::Record(record) => {
+                for action in ["retain", "release"] {
+                    writeln!(out, "static void mica_{action}_{name}({c_type} value) {{").unwrap();
+                    for field in &record.fields {
+                        if let Some(call) = refcount_call(module, field.ty, record_names, action) {
+                            writeln!(out, "  {call}(value.{});", sanitize_identifier(&field.name))
+                                .unwrap();
+                        }
+                    }
+                    writeln!(out, "}}").unwrap();
+                    writeln!(out).unwrap();
+                }
+            }
+            Type::Sum(sum) => emit_sum_refcounts(out, module, sum, name, record_names),
+            _ => continue,
+        }
+        writeln!(out, "static void mica_release_slot_{name}(void *slot) {{").unwrap();
+        writeln!(out, "  mica_release_{name}(*({c_type} *)slot);").unwrap();
+        writeln!(out, "}}").unwrap();
+        writeln!(out).unwrap();
+    }
+}
+
+/// Sum values are counted like strings, and nullary variants are shared
+/// immortal instances. Releasing the last reference switches on the tag to
+/// release the payload fields of the variant held.
+fn emit_sum_refcounts(
+    out: &mut String,
+    module: &ir::Module,
+    sum: &ir::SumType,
+    name: &str,
+    record_names: &RecordNameMap,
+) {
+    writeln!(out, "static void mica_retain_{name}({name} *value) {{").unwrap();
+    writeln!(out, "  if (value && value->rc > 0) {{").unwrap();
+    writeln!(out, "    value->rc += 1;").unwrap();
+    writeln!(out, "  }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "static void mica_release_{name}({name} *value) {{").unwrap();
+    writeln!(out, "  if (!value || value->rc < 0) {{").unwrap();
+    writeln!(out, "    return;").unwrap();
+    writeln!(out, "  }}").unwrap();
+    writeln!(out, "  value->rc -= 1;").unwrap();
+    writeln!(out, "  if (value->rc != 0) {{").unwrap();
+    writeln!(out, "    return;").unwrap();
+    writeln!(out, "  }}").unwrap();
+    writeln!(out, "  switch (value->tag) {{").unwrap();
+    for variant in &sum.variants {
+        let releases: Vec<String> = variant
+            .fields
+            .iter()
+            .enumerate()
+            .filter_map(|(index, field)| {
+                refcount_call(module, *field, record_names, "release").map(|call| {
+                    format!(
+                        "{call}(value->as.{}.f{index});",
+                        sanitize_identifier(&variant.name)
+                    )
+                })
+            })
+            .collect();
+        if releases.is_empty() {
+            continue;
+        }
+        writeln!(out, "  case {}:", sum_tag(name, &variant.name)).unwrap();
+        for release in releases {
+            writeln!(out, "    {release}").unwrap();
+        }
+        writeln!(out, "    break;").unwrap();
+    }
+    writeln!(out, "  default:").unwrap();
+    writeln!(out, "    break;").unwrap();
+    writeln!(out, "  }}").unwrap();
+    writeln!(out, "  free(value);").unwrap();
+    writeln!(out, "  MICA_RUNTIME_LIVE_OBJECTS -= 1;").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+}
+
+/// Sum types in ID order, with their C names.
+fn sum_entries<'m>(
+    module: &'m ir::Module,
+    record_names: &RecordNameMap,
+) -> Vec<(&'m ir::SumType, String)> {
+    let mut entries: Vec<_> = module
+        .types
+        .entries()
+        .filter_map(|(id, ty)| match ty {
+            Type::Sum(sum) => Some((id, sum)),
+            _ => None,
+        })
+        .collect();
+    entries.sort_by_key(|(id, _)| id.index());
+    entries
+        .into_iter()
+        .map(|(id, sum)| (sum, record_names[&id].clone()))
+        .collect()
+}
+
+/// Forward declarations and tag constants for every sum type, so records
+/// and other sums can hold pointers to them.
+fn emit_sum_declarations(out: &mut String, module: &ir::Module, record_names: &RecordNameMap) {
+    let sums = sum_entries(module, record_names);
+    if sums.is_empty() {
+        return;
+    }
+    for (sum, name) in &sums {
+        writeln!(out, "typedef struct {name} {name};").unwrap();
+        let tags: Vec<String> = sum
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(tag, variant)| format!("{} = {tag}", sum_tag(name, &variant.name)))
+            .collect();
+        writeln!(out, "enum {{ {} }};", tags.join(", ")).unwrap();
+    }
+    writeln!(out).unwrap();
+    out.push_str("static void *mica_sum_alloc(size_t size) {\n");
+    out.push_str("  void *value = malloc(size);\n");
+    out.push_str("  if (!value) {\n");
+    out.push_str("    fprintf(stderr, \"error: out of memory while allocating a variant\\n\");\n");
+    out.push_str("    exit(70);\n");
+    out.push_str("  }\n");
+    out.push_str("  MICA_RUNTIME_LIVE_OBJECTS += 1;\n");
+    out.push_str("  return value;\n");
+    out.push_str("}\n\n");
+}
+
+/// A sum type is a tagged union: the count, the tag, and a struct of
+/// payload fields `f0`, `f1`, ... per variant that has any. Each variant
+/// with a payload gets a constructor; nullary variants are static
+/// instances with an immortal count.
+fn emit_sum_definitions(out: &mut String, module: &ir::Module, record_names: &RecordNameMap) {
+    for (sum, name) in sum_entries(module, record_names) {
+        writeln!(out, "struct {name} {{").unwrap();
+        writeln!(out, "  int64_t rc;").unwrap();
+        writeln!(out, "  int64_t tag;").unwrap();
+        if sum
+            .variants
+            .iter()
+            .any(|variant| !variant.fields.is_empty())
+        {
+            writeln!(out, "  union {{").unwrap();
+            for variant in sum.variants.iter().filter(|v| !v.fields.is_empty()) {
+                let fields: Vec<String> = variant
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(index, field)| {
+                        format!("{} f{index};", c_type_value(module, *field, record_names))
+                    })
+                    .collect();
+                writeln!(
+                    out,
+                    "    struct {{ {} }} {};",
+                    fields.join(" "),
+                    sanitize_identifier(&variant.name)
+                )
+                .unwrap();
+            }
+            writeln!(out, "  }} as;").unwrap();
+        }
+        writeln!(out, "}};").unwrap();
+        writeln!(out).unwrap();
+
+        for variant in &sum.variants {
+            let tag = sum_tag(&name, &variant.name);
+            if variant.fields.is_empty() {
+                writeln!(
+                    out,
+                    "static {name} mica_{tag} = {{ .rc = -1, .tag = {tag} }};"
+                )
+                .unwrap();
+                continue;
+            }
+            let params: Vec<String> = variant
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    format!("{} f{index}", c_type_value(module, *field, record_names))
+                })
+                .collect();
+            writeln!(
+                out,
+                "static {name} *mica_new_{tag}({}) {{",
+                params.join(", ")
+            )
+            .unwrap();
+            writeln!(out, "  {name} *value = mica_sum_alloc(sizeof({name}));").unwrap();
+            writeln!(out, "  value->rc = 1;").unwrap();
+            writeln!(out, "  value->tag = {tag};").unwrap();
+            let member = sanitize_identifier(&variant.name);
+            for index in 0..variant.fields.len() {
+                writeln!(out, "  value->as.{member}.f{index} = f{index};").unwrap();
+            }
+            writeln!(out, "  return value;").unwrap();
+            writeln!(out, "}}").unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+}
+
+/// The tag constant of `variant` in the sum type named `sum` in C.
+fn sum_tag(sum: &str, variant: &str) -> String {
+    format!("{sum}_{}", sanitize_identifier(variant))
+}
+
+/// The sum type `ty` denotes; a payload naming its own sum refers to it by
+/// name.
+fn sum_type(module: &ir::Module, ty: ir::TypeId) -> Option<ir::TypeId> {
+    match module.type_of(ty) {
+        Type::Sum(_) => Some(ty),
+        Type::Named(name) => module
+            .types
+            .lookup_named(name)
+            .filter(|id| matches!(module.type_of(*id), Type::Sum(_))),
+        _ => None,
+    }
+}
+
 fn emit_record_definitions(
     out: &mut String,
     module: &ir::Module,
@@ -158,6 +767,16 @@ fn function_signature(
     module: &ir::Module,
     function: &ir::Function,
     record_names: &RecordNameMap,
+) -> BackendResult<String> {
+    named_signature(module, function, &function.name, record_names)
+}
+
+/// The C signature of `function` under the C name of `name`.
+fn named_signature(
+    module: &ir::Module,
+    function: &ir::Function,
+    name: &str,
+    record_names: &RecordNameMap,
 ) -> BackendResult<String> {
     let mut signature = String::new();
 
@@ -168,7 +787,7 @@ fn function_signature(
         c_type_return(module, function.ret_type, record_names)
     };
 
-    write!(signature, "{} {}(", ret_type, mangle_name(&function.name)).unwrap();
+    write!(signature, "{} {}(", ret_type, mangle_name(name)).unwrap();
 
     for (index, param) in function.params.iter().enumerate() {
         if index > 0 {
@@ -191,7 +810,205 @@ fn function_signature(
     Ok(signature)
 }
 
+/// Groups the recursive call-graph components whose members tail-call each
+/// other. `main` and components mixing C return types are left out, since
+/// the trampoline returns whatever the last member called returns.
+fn collect_tail_groups(module: &ir::Module) -> Vec<TailGroup> {
+    let summaries = EffectSummaries::new(module);
+    let mut groups: Vec<TailGroup> = Vec::new();
+    for scc in summaries.sccs.iter().filter(|scc| scc.len() > 1) {
+        let members: Option<Vec<&ir::Function>> = scc
+            .iter()
+            .map(|name| module.functions.iter().find(|f| &f.name == name))
+            .collect();
+        let Some(members) = members else {
+            continue;
+        };
+        let ret_type = members[0].ret_type;
+        if members
+            .iter()
+            .any(|member| member.name == "main" || member.ret_type != ret_type)
+        {
+            continue;
+        }
+        let group = TailGroup {
+            index: groups.len(),
+            members: scc.clone(),
+            arities: members.iter().map(|member| member.params.len()).collect(),
+        };
+        let has_sibling_calls = members
+            .iter()
+            .flat_map(|member| &member.blocks)
+            .any(|block| group.sibling_call(block).is_some());
+        if has_sibling_calls {
+            groups.push(group);
+        }
+    }
+    groups
+}
+
+fn tail_group_of<'g>(groups: &'g [TailGroup], name: &str) -> Option<(&'g TailGroup, usize)> {
+    groups
+        .iter()
+        .find_map(|group| group.member(name).map(|member| (group, member)))
+}
+
+/// Declares the frame holding the pending call of `group`: `next` is the
+/// member to call plus one, or zero once a member has returned.
+fn emit_tail_frame(
+    out: &mut String,
+    module: &ir::Module,
+    group: &TailGroup,
+    record_names: &RecordNameMap,
+) {
+    let frame = group.frame_type();
+    writeln!(out, "typedef struct {frame} {{").unwrap();
+    writeln!(out, "  int next;").unwrap();
+    for (member, name) in group.members.iter().enumerate() {
+        let Some(function) = module.functions.iter().find(|f| &f.name == name) else {
+            continue;
+        };
+        for (index, param) in function.params.iter().enumerate() {
+            writeln!(
+                out,
+                "  {} f{}_arg{};",
+                c_type_value(module, param.ty, record_names),
+                member,
+                index
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "}} {frame};").unwrap();
+    writeln!(out).unwrap();
+}
+
+fn tail_body_signature(
+    module: &ir::Module,
+    group: &TailGroup,
+    member: usize,
+    function: &ir::Function,
+    record_names: &RecordNameMap,
+) -> String {
+    let mut signature = format!(
+        "static {} {}({} *tail",
+        c_type_return(module, function.ret_type, record_names),
+        group.body_name(member),
+        group.frame_type()
+    );
+    for (index, param) in function.params.iter().enumerate() {
+        write!(
+            signature,
+            ", {} arg{}",
+            c_type_value(module, param.ty, record_names),
+            index
+        )
+        .unwrap();
+    }
+    signature.push(')');
+    signature
+}
+
+/// Emits the public entry of a tail group member: it calls the member's
+/// body, then keeps calling whichever body the last one left pending.
+fn emit_trampoline(
+    out: &mut String,
+    module: &ir::Module,
+    group: &TailGroup,
+    member: usize,
+    function: &ir::Function,
+    record_names: &RecordNameMap,
+) {
+    let returns_value = !matches!(module.type_of(function.ret_type), Type::Unit);
+    let result = if returns_value { "result = " } else { "" };
+    let call = |member: usize, args: Vec<String>| {
+        let mut call_args = vec!["&tail".to_string()];
+        call_args.extend(args);
+        format!("{}({})", group.body_name(member), call_args.join(", "))
+    };
+
+    writeln!(out, "  {} tail;", group.frame_type()).unwrap();
+    writeln!(out, "  tail.next = 0;").unwrap();
+    let first = call(
+        member,
+        (0..function.params.len())
+            .map(|index| format!("arg{}", index))
+            .collect(),
+    );
+    if returns_value {
+        writeln!(
+            out,
+            "  {} result = {};",
+            c_type_value(module, function.ret_type, record_names),
+            first
+        )
+        .unwrap();
+    } else {
+        writeln!(out, "  {};", first).unwrap();
+    }
+    writeln!(out, "  while (tail.next != 0) {{").unwrap();
+    writeln!(out, "    int next = tail.next;").unwrap();
+    writeln!(out, "    tail.next = 0;").unwrap();
+    writeln!(out, "    switch (next) {{").unwrap();
+    for (target, arity) in group.arities.iter().enumerate() {
+        let args = (0..*arity)
+            .map(|index| format!("tail.f{}_arg{}", target, index))
+            .collect();
+        writeln!(out, "    case {}:", target + 1).unwrap();
+        writeln!(out, "      {}{};", result, call(target, args)).unwrap();
+        writeln!(out, "      break;").unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "  }}").unwrap();
+    if returns_value {
+        writeln!(out, "  return result;").unwrap();
+    }
+}
+
 fn emit_function_body(
+    out: &mut String,
+    module: &ir::Module,
+    function: &ir::Function,
+    tail: Option<&TailGroup>,
+    record_names: &RecordNameMap,
+    lines: Option<&LineDirectives>,
+) -> BackendResult<()> {
+    let mut prologue = String::new();
+    emit_prologue(&mut prologue, module, function, record_names)?;
+    // With line directives, every prologue statement is pinned to the
+    // function's line rather than to whatever lines follow it.
+    match lines {
+        Some(lines) => {
+            let line = first_line(function);
+            for statement in prologue.lines() {
+                lines.emit(out, &function.name, line);
+                writeln!(out, "{statement}").unwrap();
+            }
+        }
+        None => out.push_str(&prologue),
+    }
+
+    // C scoping requires every value to be declared textually before its
+    // uses; emitting blocks in reverse postorder places each definition ahead
+    // of the blocks it dominates.
+    for block in reverse_postorder(function) {
+        emit_block(out, module, function, block, tail, record_names, lines)?;
+    }
+
+    match default_return(module, function.ret_type, record_names) {
+        Some(expr) => {
+            writeln!(out, "  return {};", expr).unwrap();
+        }
+        None => {
+            writeln!(out, "  return;").unwrap();
+        }
+    }
+    Ok(())
+}
+
+/// Declares the parameters and phi results and checks capabilities before
+/// jumping to the entry block.
+fn emit_prologue(
     out: &mut String,
     module: &ir::Module,
     function: &ir::Function,
@@ -212,6 +1029,23 @@ fn emit_function_body(
         .unwrap();
     }
 
+    // Phi results are assigned on the incoming edges, which may appear before
+    // the phi's own block, so they are declared up front.
+    for block in &function.blocks {
+        for inst in &block.instructions {
+            if matches!(inst.kind, InstKind::Phi { .. }) {
+                writeln!(
+                    out,
+                    "  {} {} = {};",
+                    c_type_value(module, inst.ty, record_names),
+                    value_name(inst.id),
+                    default_value(module, inst.ty, record_names)
+                )
+                .unwrap();
+            }
+        }
+    }
+
     emit_runtime_capability_guards(out, module, function)?;
 
     if function.params.is_empty() {
@@ -220,19 +1054,6 @@ fn emit_function_body(
     }
 
     writeln!(out, "  goto block0;").unwrap();
-
-    for block in &function.blocks {
-        emit_block(out, module, function, block, record_names)?;
-    }
-
-    match default_return(module, function.ret_type, record_names) {
-        Some(expr) => {
-            writeln!(out, "  return {};", expr).unwrap();
-        }
-        None => {
-            writeln!(out, "  return;").unwrap();
-        }
-    }
     Ok(())
 }
 
@@ -241,72 +1062,135 @@ fn emit_block(
     module: &ir::Module,
     function: &ir::Function,
     block: &ir::BasicBlock,
+    tail: Option<&TailGroup>,
     record_names: &RecordNameMap,
+    lines: Option<&LineDirectives>,
 ) -> BackendResult<()> {
+    if let Some(lines) = lines {
+        // The label and block bookkeeping belong to the block's first line.
+        let first = block
+            .instructions
+            .iter()
+            .find_map(|inst| inst.provenance.as_ref())
+            .or(block.terminator_provenance.as_ref());
+        lines.emit_provenance(out, first);
+    }
     writeln!(out, "block{}:", block.id.index()).unwrap();
     writeln!(out, "  current_block = {};", block.id.index()).unwrap();
 
-    for inst in &block.instructions {
-        if let InstKind::Phi { .. } = &inst.kind {
-            emit_phi(out, module, inst, record_names)?;
-        }
-    }
-
-    for inst in &block.instructions {
+    let sibling_call = tail.and_then(|group| group.sibling_call(block));
+    let instructions = match sibling_call {
+        Some(_) => &block.instructions[..block.instructions.len() - 1],
+        None => &block.instructions[..],
+    };
+    for inst in instructions {
         if matches!(inst.kind, InstKind::Phi { .. }) {
             continue;
         }
-        emit_instruction(out, module, inst, record_names)?;
+        if let Some(lines) = lines {
+            lines.emit_provenance(out, inst.provenance.as_ref());
+        }
+        emit_instruction(out, module, function, inst, record_names)?;
     }
 
-    emit_terminator(out, module, function, block, record_names)?;
+    let Some((target, args)) = sibling_call else {
+        if function.name == "main" && matches!(block.terminator, Terminator::Return(_)) {
+            writeln!(out, "  mica_runtime_check_leaks();").unwrap();
+        }
+        if let Some(lines) = lines {
+            lines.emit_provenance(out, block.terminator_provenance.as_ref());
+        }
+        return emit_terminator(out, module, function, block, record_names);
+    };
+    if let Some(lines) = lines {
+        let call = block.instructions.last();
+        lines.emit_provenance(out, call.and_then(|call| call.provenance.as_ref()));
+    }
+    // Leave the call to the trampoline so the stack unwinds first.
+    for (index, arg) in args.iter().enumerate() {
+        writeln!(
+            out,
+            "  tail->f{}_arg{} = {};",
+            target,
+            index,
+            value_name(*arg)
+        )
+        .unwrap();
+    }
+    writeln!(out, "  tail->next = {};", target + 1).unwrap();
+    match default_return(module, function.ret_type, record_names) {
+        Some(expr) => writeln!(out, "  return {};", expr).unwrap(),
+        None => writeln!(out, "  return;").unwrap(),
+    }
     Ok(())
 }
 
-fn emit_phi(
+/// Assigns the phi results of `target` for the edge leaving `from`. All
+/// incoming values are read into temporaries first so that phis feeding each
+/// other across a back edge observe the values from the previous iteration.
+fn emit_phi_copies(
     out: &mut String,
     module: &ir::Module,
-    inst: &ir::Instruction,
+    function: &ir::Function,
+    from: ir::BlockId,
+    target: ir::BlockId,
+    indent: &str,
     record_names: &RecordNameMap,
-) -> BackendResult<()> {
-    let ty = inst.ty;
-    let var = value_name(inst.id);
-    writeln!(out, "  {} {};", c_type_value(module, ty, record_names), var).unwrap();
-
-    if let InstKind::Phi { incomings } = &inst.kind {
-        writeln!(out, "  switch (prev_block) {{").unwrap();
-        for (block, value) in incomings {
-            writeln!(
-                out,
-                "    case {}: {} = {}; break;",
-                block.index(),
-                var,
-                value_name(*value)
-            )
-            .unwrap();
-        }
+) {
+    let Some(block) = function.blocks.iter().find(|block| block.id == target) else {
+        return;
+    };
+    let copies: Vec<_> = block
+        .instructions
+        .iter()
+        .filter_map(|inst| match &inst.kind {
+            InstKind::Phi { incomings } => incomings
+                .iter()
+                .find(|(pred, _)| *pred == from)
+                .map(|(_, value)| (inst, *value)),
+            _ => None,
+        })
+        .collect();
+    if copies.is_empty() {
+        return;
+    }
+    writeln!(out, "{indent}{{").unwrap();
+    for (index, (inst, value)) in copies.iter().enumerate() {
         writeln!(
             out,
-            "    default: {} = {}; break;",
-            var,
-            default_value(module, ty, record_names)
+            "{indent}  {} phi_tmp{} = {};",
+            c_type_value(module, inst.ty, record_names),
+            index,
+            value_name(*value)
         )
         .unwrap();
-        writeln!(out, "  }}").unwrap();
     }
-
-    Ok(())
+    for (index, (inst, _)) in copies.iter().enumerate() {
+        writeln!(out, "{indent}  {} = phi_tmp{};", value_name(inst.id), index).unwrap();
+    }
+    writeln!(out, "{indent}}}").unwrap();
 }
 
 fn emit_instruction(
     out: &mut String,
     module: &ir::Module,
+    function: &ir::Function,
     inst: &ir::Instruction,
     record_names: &RecordNameMap,
 ) -> BackendResult<()> {
     let ty = inst.ty;
     let var = value_name(inst.id);
     match &inst.kind {
+        InstKind::Literal(Literal::String(text)) => {
+            // Literals carry an immortal count so they pass for heap strings.
+            let text = format!("\"{}\"", escape_string(text));
+            writeln!(
+                out,
+                "  static struct {{ int64_t rc; char data[sizeof({text})]; }} {var}_text = {{ -1, {text} }};"
+            )
+            .unwrap();
+            writeln!(out, "  const char *{var} = {var}_text.data;").unwrap();
+        }
         InstKind::Literal(lit) => {
             writeln!(
                 out,
@@ -317,6 +1201,31 @@ fn emit_instruction(
             )
             .unwrap();
         }
+        InstKind::Binary { op, lhs, rhs }
+            if value_type(function, *lhs)
+                .is_some_and(|lhs| matches!(module.type_of(lhs), Type::String)) =>
+        {
+            if *op == BinaryOp::Add {
+                writeln!(
+                    out,
+                    "  const char *{var} = mica_string_concat({}, {});",
+                    value_name(*lhs),
+                    value_name(*rhs)
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "  {} {} = strcmp({}, {}) {} 0;",
+                    c_type_value(module, ty, record_names),
+                    var,
+                    value_name(*lhs),
+                    value_name(*rhs),
+                    op
+                )
+                .unwrap();
+            }
+        }
         InstKind::Binary { op, lhs, rhs } => {
             writeln!(
                 out,
@@ -329,9 +1238,13 @@ fn emit_instruction(
             )
             .unwrap();
         }
+        InstKind::Convert { value } => {
+            let c_ty = c_type_value(module, ty, record_names);
+            writeln!(out, "  {c_ty} {var} = ({c_ty}){};", value_name(*value)).unwrap();
+        }
         InstKind::Call { func, args } => {
             if let ir::FuncRef::Method(name) = func
-                && emit_runtime_method(out, module, name, args, ty, &var, record_names)?
+                && emit_runtime_method(out, module, function, name, args, ty, &var, record_names)?
             {
                 return Ok(());
             }
@@ -409,17 +1322,245 @@ fn emit_instruction(
                 .unwrap();
             }
         }
+        InstKind::Field { base, name } => {
+            writeln!(
+                out,
+                "  {} {} = {}.{};",
+                c_type_value(module, ty, record_names),
+                var,
+                value_name(*base),
+                sanitize_identifier(name)
+            )
+            .unwrap();
+        }
         InstKind::Path(path) => {
             return Err(BackendError::Unsupported(format!(
                 "path expression '{}' cannot be lowered by the native backend yet",
                 path.segments.join("::")
             )));
         }
-        InstKind::Phi { .. } => {}
+        InstKind::Phi { .. } => {}
+        InstKind::List { elements } => {
+            let element_ty = list_element_c_type(module, ty, record_names)?;
+            writeln!(
+                out,
+                "  mica_list *{} = mica_list_new(sizeof({}), {});",
+                var,
+                element_ty,
+                elements.len()
+            )
+            .unwrap();
+            if let Type::List(element) = module.type_of(ty)
+                && let Some(release) = release_slot(module, *element, record_names)
+            {
+                writeln!(out, "  {var}->release = {release};").unwrap();
+            }
+            for element in elements {
+                writeln!(out, "  mica_list_push({}, &{});", var, value_name(*element)).unwrap();
+            }
+        }
+        InstKind::ListLen { list } => {
+            writeln!(
+                out,
+                "  {} {} = mica_list_len({});",
+                c_type_value(module, ty, record_names),
+                var,
+                value_name(*list)
+            )
+            .unwrap();
+        }
+        InstKind::ListIndex { list, index } => {
+            let element_ty = c_type_value(module, ty, record_names);
+            writeln!(
+                out,
+                "  {} {} = *({} *)mica_list_at({}, {});",
+                element_ty,
+                var,
+                element_ty,
+                value_name(*list),
+                value_name(*index)
+            )
+            .unwrap();
+        }
+        InstKind::ListPush { list, value } => {
+            writeln!(
+                out,
+                "  mica_list_push({}, &{});",
+                value_name(*list),
+                value_name(*value)
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "  {} {} = 0;",
+                c_type_value(module, ty, record_names),
+                var
+            )
+            .unwrap();
+        }
+        InstKind::Variant { variant, payload } => {
+            let (sum, name) = sum_type(module, ty)
+                .and_then(|id| Some((module.types.sum(id)?, &record_names[&id])))
+                .ok_or_else(|| {
+                    BackendError::Internal(format!("variant '{variant}' lowered with non-sum type"))
+                })?;
+            let tag = sum_tag(name, variant);
+            let Some((_, declared)) = sum.variant(variant) else {
+                return Err(BackendError::Internal(format!(
+                    "'{}' has no variant '{variant}'",
+                    sum.name
+                )));
+            };
+            let c_type = c_type_value(module, ty, record_names);
+            if payload.is_empty() {
+                writeln!(out, "  {c_type} {var} = &mica_{tag};").unwrap();
+                return Ok(());
+            }
+            for (value, field) in payload.iter().zip(&declared.fields) {
+                // Type parameters are erased to 64-bit slots.
+                let erased = matches!(module.type_of(*field), Type::Unknown);
+                let fits = value_type(function, *value).is_none_or(|value_ty| {
+                    matches!(
+                        module.type_of(value_ty),
+                        Type::Int | Type::Bool | Type::Unit | Type::Unknown
+                    )
+                });
+                if erased && !fits {
+                    return Err(BackendError::Unsupported(format!(
+                        "generic payload of '{}::{variant}' holds a value the native backend cannot erase",
+                        sum.name
+                    )));
+                }
+            }
+            let args: Vec<String> = payload.iter().map(|value| value_name(*value)).collect();
+            writeln!(
+                out,
+                "  {c_type} {var} = mica_new_{tag}({});",
+                args.join(", ")
+            )
+            .unwrap();
+        }
+        InstKind::IsVariant { value, variant } => {
+            let name = value_type(function, *value)
+                .and_then(|value_ty| sum_type(module, value_ty))
+                .map(|id| &record_names[&id])
+                .ok_or_else(|| {
+                    BackendError::Internal(format!("variant test of non-sum %{}", value.index()))
+                })?;
+            writeln!(
+                out,
+                "  bool {var} = {}->tag == {};",
+                value_name(*value),
+                sum_tag(name, variant)
+            )
+            .unwrap();
+        }
+        InstKind::Payload {
+            value,
+            variant,
+            index,
+        } => {
+            writeln!(
+                out,
+                "  {} {var} = {}->as.{}.f{index};",
+                c_type_value(module, ty, record_names),
+                value_name(*value),
+                sanitize_identifier(variant)
+            )
+            .unwrap();
+        }
+        InstKind::Trap { message } => {
+            writeln!(out, "  mica_runtime_trap(\"{}\");", escape_string(message)).unwrap();
+            writeln!(
+                out,
+                "  {} {} = 0;",
+                c_type_value(module, ty, record_names),
+                var
+            )
+            .unwrap();
+        }
+        InstKind::Retain { value } | InstKind::Release { value } => {
+            let action = match inst.kind {
+                InstKind::Retain { .. } => "retain",
+                _ => "release",
+            };
+            let value_ty = value_type(function, *value).ok_or_else(|| {
+                BackendError::Internal(format!("{action} of undefined value %{}", value.index()))
+            })?;
+            if let Some(call) = refcount_call(module, value_ty, record_names, action) {
+                writeln!(out, "  {call}({});", value_name(*value)).unwrap();
+            }
+        }
     }
     Ok(())
 }
 
+/// The type of `value` in `function`, from its parameter or definition.
+fn value_type(function: &ir::Function, value: ValueId) -> Option<ir::TypeId> {
+    function
+        .params
+        .iter()
+        .find(|param| param.value == value)
+        .map(|param| param.ty)
+        .or_else(|| {
+            function
+                .blocks
+                .iter()
+                .flat_map(|block| &block.instructions)
+                .find(|inst| inst.id == value)
+                .map(|inst| inst.ty)
+        })
+}
+
+/// The runtime function that retains or releases a value of type `ty`, or
+/// `None` when the type holds no counted references.
+fn refcount_call(
+    module: &ir::Module,
+    ty: ir::TypeId,
+    record_names: &RecordNameMap,
+    action: &str,
+) -> Option<String> {
+    match module.type_of(ty) {
+        Type::String => Some(format!("mica_string_{action}")),
+        Type::List(_) => Some(format!("mica_list_{action}")),
+        Type::Record(_) if is_managed(&module.types, ty) => Some(format!(
+            "mica_{action}_{}",
+            c_type_value(module, ty, record_names)
+        )),
+        _ => sum_type(module, ty).map(|id| format!("mica_{action}_{}", record_names[&id])),
+    }
+}
+
+/// The callback a list of `element`s releases each slot with when freed.
+fn release_slot(
+    module: &ir::Module,
+    element: ir::TypeId,
+    record_names: &RecordNameMap,
+) -> Option<String> {
+    match module.type_of(element) {
+        Type::String => Some("mica_string_release_slot".into()),
+        Type::List(_) => Some("mica_list_release_slot".into()),
+        Type::Record(_) if is_managed(&module.types, element) => Some(format!(
+            "mica_release_slot_{}",
+            c_type_value(module, element, record_names)
+        )),
+        _ => sum_type(module, element).map(|id| format!("mica_release_slot_{}", record_names[&id])),
+    }
+}
+
+fn list_element_c_type(
+    module: &ir::Module,
+    ty: ir::TypeId,
+    record_names: &RecordNameMap,
+) -> BackendResult<String> {
+    match module.type_of(ty) {
+        Type::List(element) => Ok(c_type_value(module, *element, record_names)),
+        _ => Err(BackendError::Internal(
+            "list literal lowered with non-list type".into(),
+        )),
+    }
+}
+
 fn emit_terminator(
     out: &mut String,
     module: &ir::Module,
@@ -444,6 +1585,7 @@ fn emit_terminator(
             }
         },
         Terminator::Jump(target) => {
+            emit_phi_copies(out, module, function, block.id, *target, "  ", record_names);
             writeln!(out, "  prev_block = current_block;").unwrap();
             writeln!(out, "  current_block = {};", target.index()).unwrap();
             writeln!(out, "  goto block{};", target.index()).unwrap();
@@ -455,9 +1597,27 @@ fn emit_terminator(
         } => {
             writeln!(out, "  prev_block = current_block;").unwrap();
             writeln!(out, "  if ({}) {{", value_name(*condition)).unwrap();
+            emit_phi_copies(
+                out,
+                module,
+                function,
+                block.id,
+                *then_block,
+                "    ",
+                record_names,
+            );
             writeln!(out, "    current_block = {};", then_block.index()).unwrap();
             writeln!(out, "    goto block{};", then_block.index()).unwrap();
             writeln!(out, "  }} else {{").unwrap();
+            emit_phi_copies(
+                out,
+                module,
+                function,
+                block.id,
+                *else_block,
+                "    ",
+                record_names,
+            );
             writeln!(out, "    current_block = {};", else_block.index()).unwrap();
             writeln!(out, "    goto block{};", else_block.index()).unwrap();
             writeln!(out, "  }}").unwrap();
@@ -466,12 +1626,53 @@ fn emit_terminator(
     Ok(())
 }
 
+fn reverse_postorder(function: &ir::Function) -> Vec<&ir::BasicBlock> {
+    let Some(entry) = function.blocks.first() else {
+        return Vec::new();
+    };
+    let by_id: HashMap<ir::BlockId, &ir::BasicBlock> = function
+        .blocks
+        .iter()
+        .map(|block| (block.id, block))
+        .collect();
+    let mut order = Vec::with_capacity(function.blocks.len());
+    let mut visited = std::collections::HashSet::new();
+    let mut stack = vec![(entry, 0usize)];
+    visited.insert(entry.id);
+    while let Some((block, next)) = stack.pop() {
+        let succs = block.terminator.successors();
+        if let Some(succ) = succs.get(next) {
+            stack.push((block, next + 1));
+            if let Some(succ_block) = by_id.get(succ)
+                && visited.insert(*succ)
+            {
+                stack.push((succ_block, 0));
+            }
+        } else {
+            order.push(block);
+        }
+    }
+    order.reverse();
+    // Unreachable blocks keep their original relative order at the end.
+    for block in &function.blocks {
+        if !visited.contains(&block.id) {
+            order.push(block);
+        }
+    }
+    order
+}
+
 fn c_type_value(module: &ir::Module, ty: ir::TypeId, record_names: &RecordNameMap) -> String {
+    if let Some(sum) = sum_type(module, ty) {
+        return format!("{} *", record_names[&sum]);
+    }
     match module.type_of(ty) {
         Type::Int | Type::Named(_) | Type::Unknown | Type::Unit => "int64_t".into(),
         Type::String => "const char *".into(),
         Type::Float => "double".into(),
         Type::Bool => "bool".into(),
+        Type::List(_) => "mica_list *".into(),
+        Type::Sum(_) => unreachable!("sum types are pointers"),
         Type::Record(_) => record_names
             .get(&ty)
             .cloned()
@@ -490,8 +1691,9 @@ fn default_value(module: &ir::Module, ty: ir::TypeId, record_names: &RecordNameM
     match module.type_of(ty) {
         Type::Bool => "false".into(),
         Type::Float => "0.0".into(),
-        Type::String => "NULL".into(),
+        Type::String | Type::List(_) | Type::Sum(_) => "NULL".into(),
         Type::Record(_) => format!("({}){{0}}", c_type_value(module, ty, record_names)),
+        _ if sum_type(module, ty).is_some() => "NULL".into(),
         _ => "0".into(),
     }
 }
@@ -505,8 +1707,9 @@ fn default_return(
         Type::Unit => None,
         Type::Float => Some("0.0".into()),
         Type::Bool => Some("false".into()),
-        Type::String => Some("NULL".into()),
+        Type::String | Type::List(_) | Type::Sum(_) => Some("NULL".into()),
         Type::Record(_) => Some(format!("({}){{0}}", c_type_value(module, ty, record_names))),
+        _ if sum_type(module, ty).is_some() => Some("NULL".into()),
         _ => Some("0".into()),
     }
 }
@@ -586,9 +1789,11 @@ fn emit_runtime_capability_guards(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn emit_runtime_method(
     out: &mut String,
     module: &ir::Module,
+    function: &ir::Function,
     name: &str,
     args: &[ir::ValueId],
     ty: ir::TypeId,
@@ -641,10 +1846,335 @@ fn emit_runtime_method(
             .unwrap();
             Ok(true)
         }
-        _ => Ok(false),
+        _ => {
+            let capability = args
+                .first()
+                .and_then(|receiver| receiver_capability(module, function, *receiver));
+            let Some(capability) = capability else {
+                return Ok(false);
+            };
+            writeln!(out, "  mica_runtime_initialize();").unwrap();
+            writeln!(
+                out,
+                "  mica_runtime_require_capability(\"{}\");",
+                escape_string(capability)
+            )
+            .unwrap();
+            let Some((symbol, result)) = runtime_operation(capability, name) else {
+                // Providers reject operations they do not know at run time.
+                writeln!(
+                    out,
+                    "  mica_runtime_provider_failure(\"{}\", \"unsupported operation '%s'\", \"{}\");",
+                    escape_string(capability),
+                    escape_string(name)
+                )
+                .unwrap();
+                writeln!(
+                    out,
+                    "  {} {} = {};",
+                    c_type_value(module, ty, record_names),
+                    var,
+                    default_value(module, ty, record_names)
+                )
+                .unwrap();
+                return Ok(true);
+            };
+            if args.len() < 2 {
+                return Err(BackendError::Internal(format!(
+                    "method '{name}' expected value argument"
+                )));
+            }
+            let c_type = c_type_value(module, ty, record_names);
+            let call = format!("{symbol}({})", value_name(args[1]));
+            match result {
+                Type::Unit => {
+                    writeln!(out, "  {call};").unwrap();
+                    writeln!(out, "  {c_type} {var} = 0;").unwrap();
+                }
+                _ => writeln!(out, "  {c_type} {var} = {call};").unwrap(),
+            }
+            Ok(true)
+        }
+    }
+}
+
+/// Capabilities the embedded C runtime provides, named like the Rust
+/// runtime's default providers.
+const NATIVE_PROVIDERS: [&str; 6] = ["io", "time", "fs", "env", "process", "net"];
+
+/// The capability a method receiver stands for: a parameter whose type names
+/// one of the native providers, such as `fs: Fs`.
+fn receiver_capability(
+    module: &ir::Module,
+    function: &ir::Function,
+    receiver: ValueId,
+) -> Option<&'static str> {
+    let param = function
+        .params
+        .iter()
+        .find(|param| param.value == receiver)?;
+    let Type::Named(name) = module.type_of(param.ty) else {
+        return None;
+    };
+    NATIVE_PROVIDERS
+        .into_iter()
+        .find(|provider| provider.eq_ignore_ascii_case(name))
+}
+
+/// Runtime shims behind the string-payload capability operations: the C
+/// function and the type it returns.
+fn runtime_operation(capability: &str, operation: &str) -> Option<(&'static str, Type)> {
+    match (capability, operation) {
+        ("fs", "read_to_string") => Some(("mica_runtime_fs_read_to_string", Type::String)),
+        ("fs", "write_string") => Some(("mica_runtime_fs_write_string", Type::Unit)),
+        ("env", "get") => Some(("mica_runtime_env_get", Type::String)),
+        ("env", "set") => Some(("mica_runtime_env_set", Type::Unit)),
+        ("env", "unset") => Some(("mica_runtime_env_unset", Type::Unit)),
+        ("process", "spawn") => Some(("mica_runtime_process_spawn", Type::Int)),
+        ("net", "fetch") => Some(("mica_runtime_net_fetch", Type::String)),
+        _ => None,
+    }
+}
+
+/// A `pub` function of a library build that takes strings. C callers pass
+/// plain `const char *` strings, which have no reference count in front of
+/// them, so the function is renamed to `implementation` and exported
+/// through a wrapper under `name` that copies them into Mica strings.
+struct StringExport {
+    name: String,
+    implementation: String,
+}
+
+/// Renames the `pub` functions with string parameters, and every call to
+/// them, leaving the implementations private to the library.
+fn wrap_string_exports(module: &mut ir::Module) -> Vec<StringExport> {
+    let mut exports = Vec::new();
+    for function in &mut module.functions {
+        let takes_strings = function
+            .params
+            .iter()
+            .any(|param| matches!(module.types.get(param.ty), Type::String));
+        if !function.is_public || !takes_strings {
+            continue;
+        }
+        let implementation = format!("{}__mica_impl", function.name);
+        exports.push(StringExport {
+            name: std::mem::replace(&mut function.name, implementation.clone()),
+            implementation,
+        });
+        function.is_public = false;
+    }
+    for inst in module
+        .functions
+        .iter_mut()
+        .flat_map(|function| &mut function.blocks)
+        .flat_map(|block| &mut block.instructions)
+    {
+        let path = match &mut inst.kind {
+            InstKind::Call {
+                func: ir::FuncRef::Function(path),
+                ..
+            }
+            | InstKind::Path(path) => path,
+            _ => continue,
+        };
+        let name = path.segments.join("::");
+        if let Some(export) = exports.iter().find(|export| export.name == name) {
+            path.segments = export
+                .implementation
+                .split("::")
+                .map(String::from)
+                .collect();
+        }
+    }
+    exports
+}
+
+/// The counted runtime types a library hands to C, with the C parameter
+/// that releases one of them.
+fn library_releases(module: &ir::Module) -> Vec<(&'static str, &'static str)> {
+    let mut releases = vec![("string", "const char *value")];
+    if uses_lists(module) {
+        releases.push(("list", "mica_list *value"));
+    }
+    releases
+}
+
+/// The exported release function for `kind` values, prefixed with the
+/// library's module path so several Mica libraries can share a program.
+fn release_symbol(module: &ir::Module, kind: &str) -> String {
+    format!("{}_{kind}_release", mangle_name(&module.name.join("_")))
+}
+
+/// The exported wrapper of `export`: copies each string argument, calls the
+/// implementation, and releases the copies again.
+fn emit_string_export(
+    out: &mut String,
+    module: &ir::Module,
+    export: &StringExport,
+    record_names: &RecordNameMap,
+) -> BackendResult<()> {
+    let function = module
+        .functions
+        .iter()
+        .find(|function| function.name == export.implementation)
+        .ok_or_else(|| {
+            BackendError::Internal(format!("export '{}' lost its function", export.name))
+        })?;
+    writeln!(
+        out,
+        "{} {{",
+        named_signature(module, function, &export.name, record_names)?
+    )
+    .unwrap();
+    let mut args = Vec::with_capacity(function.params.len());
+    let mut copies = Vec::new();
+    for (index, param) in function.params.iter().enumerate() {
+        if matches!(module.type_of(param.ty), Type::String) {
+            writeln!(
+                out,
+                "  const char *copy{index} = mica_string_from(arg{index}, strlen(arg{index}));"
+            )
+            .unwrap();
+            args.push(format!("copy{index}"));
+            copies.push(index);
+        } else {
+            args.push(format!("arg{index}"));
+        }
+    }
+    let call = format!("{}({})", mangle_name(&function.name), args.join(", "));
+    let returns = !matches!(module.type_of(function.ret_type), Type::Unit);
+    if returns {
+        writeln!(
+            out,
+            "  {} result = {call};",
+            c_type_value(module, function.ret_type, record_names)
+        )
+        .unwrap();
+    } else {
+        writeln!(out, "  {call};").unwrap();
+    }
+    for index in copies {
+        writeln!(out, "  mica_string_release(copy{index});").unwrap();
+    }
+    if returns {
+        writeln!(out, "  return result;").unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+    Ok(())
+}
+
+/// Capability calls are untyped in the IR; gives their results the types
+/// the runtime returns so strings they produce are counted like any other.
+fn type_runtime_results(module: &mut ir::Module) {
+    let mut results = Vec::new();
+    for (index, function) in module.functions.iter().enumerate() {
+        for inst in function.blocks.iter().flat_map(|block| &block.instructions) {
+            let InstKind::Call {
+                func: ir::FuncRef::Method(name),
+                args,
+            } = &inst.kind
+            else {
+                continue;
+            };
+            if !matches!(module.type_of(inst.ty), Type::Unknown) {
+                continue;
+            }
+            let result = match name.as_str() {
+                "now_millis" => Some(Type::Int),
+                _ => args
+                    .first()
+                    .and_then(|receiver| receiver_capability(module, function, *receiver))
+                    .and_then(|capability| runtime_operation(capability, name))
+                    .map(|(_, ty)| ty),
+            };
+            if let Some(ty) = result {
+                results.push((index, inst.id, ty));
+            }
+        }
+    }
+    for (index, id, ty) in results {
+        let ty = module.types.intern(ty);
+        let inst = module.functions[index]
+            .blocks
+            .iter_mut()
+            .flat_map(|block| &mut block.instructions)
+            .find(|inst| inst.id == id)
+            .expect("typed call");
+        inst.ty = ty;
     }
 }
 
+/// Heap strings share one layout with literals: a count in front of the
+/// characters, so `const char *` values can be printed and compared
+/// directly. Literals count `-1` and are never freed. Heap strings and lists
+/// count towards `MICA_RUNTIME_LIVE_OBJECTS`, which `main` checks on return
+/// when `MICA_LEAK_CHECK` is set.
+fn emit_string_support(out: &mut String) {
+    out.push_str("typedef struct mica_string {\n");
+    out.push_str("  int64_t rc;\n");
+    out.push_str("  char data[];\n");
+    out.push_str("} mica_string;\n\n");
+    out.push_str("static int64_t MICA_RUNTIME_LIVE_OBJECTS = 0;\n\n");
+    out.push_str("static void mica_runtime_check_leaks(void) {\n");
+    out.push_str("  if (MICA_RUNTIME_LIVE_OBJECTS != 0 && getenv(\"MICA_LEAK_CHECK\")) {\n");
+    out.push_str("    fprintf(stderr, \"error: %lld heap objects leaked\\n\", (long long)MICA_RUNTIME_LIVE_OBJECTS);\n");
+    out.push_str("    exit(70);\n");
+    out.push_str("  }\n");
+    out.push_str("}\n\n");
+    out.push_str("static void mica_runtime_trap(const char *message) {\n");
+    out.push_str("  fprintf(stderr, \"error: %s\\n\", message);\n");
+    out.push_str("  exit(70);\n");
+    out.push_str("}\n\n");
+    out.push_str("static char *mica_string_new(size_t len) {\n");
+    out.push_str("  mica_string *string = malloc(sizeof(mica_string) + len + 1);\n");
+    out.push_str("  if (!string) {\n");
+    out.push_str("    fprintf(stderr, \"error: out of memory while allocating a string\\n\");\n");
+    out.push_str("    exit(70);\n");
+    out.push_str("  }\n");
+    out.push_str("  string->rc = 1;\n");
+    out.push_str("  string->data[len] = '\\0';\n");
+    out.push_str("  MICA_RUNTIME_LIVE_OBJECTS += 1;\n");
+    out.push_str("  return string->data;\n");
+    out.push_str("}\n\n");
+    out.push_str("static const char *mica_string_from(const char *text, size_t len) {\n");
+    out.push_str("  char *string = mica_string_new(len);\n");
+    out.push_str("  memcpy(string, text, len);\n");
+    out.push_str("  return string;\n");
+    out.push_str("}\n\n");
+    out.push_str("static mica_string *mica_string_header(const char *text) {\n");
+    out.push_str("  return (mica_string *)(text - offsetof(mica_string, data));\n");
+    out.push_str("}\n\n");
+    out.push_str("static void mica_string_retain(const char *text) {\n");
+    out.push_str("  if (text && mica_string_header(text)->rc > 0) {\n");
+    out.push_str("    mica_string_header(text)->rc += 1;\n");
+    out.push_str("  }\n");
+    out.push_str("}\n\n");
+    out.push_str("static void mica_string_release(const char *text) {\n");
+    out.push_str("  if (!text || mica_string_header(text)->rc < 0) {\n");
+    out.push_str("    return;\n");
+    out.push_str("  }\n");
+    out.push_str("  mica_string *string = mica_string_header(text);\n");
+    out.push_str("  string->rc -= 1;\n");
+    out.push_str("  if (string->rc == 0) {\n");
+    out.push_str("    free(string);\n");
+    out.push_str("    MICA_RUNTIME_LIVE_OBJECTS -= 1;\n");
+    out.push_str("  }\n");
+    out.push_str("}\n\n");
+    out.push_str("static void mica_string_release_slot(void *slot) {\n");
+    out.push_str("  mica_string_release(*(const char **)slot);\n");
+    out.push_str("}\n\n");
+    out.push_str("static const char *mica_string_concat(const char *lhs, const char *rhs) {\n");
+    out.push_str("  size_t lhs_len = strlen(lhs);\n");
+    out.push_str("  size_t rhs_len = strlen(rhs);\n");
+    out.push_str("  char *result = mica_string_new(lhs_len + rhs_len);\n");
+    out.push_str("  memcpy(result, lhs, lhs_len);\n");
+    out.push_str("  memcpy(result + lhs_len, rhs, rhs_len);\n");
+    out.push_str("  return result;\n");
+    out.push_str("}\n\n");
+}
+
 fn emit_runtime_support(out: &mut String, capabilities: &[String]) -> BackendResult<()> {
     if capabilities.is_empty() {
         out.push_str("static const size_t MICA_RUNTIME_CAPABILITY_COUNT = 0;\n");
@@ -684,17 +2214,13 @@ fn emit_runtime_support(out: &mut String, capabilities: &[String]) -> BackendRes
     if capabilities.is_empty() {
         out.push_str("  (void)MICA_RUNTIME_CAPABILITY_NAMES;\n");
     } else {
-        if capabilities.iter().any(|cap| cap == "io") {
-            out.push_str(&format!(
-                "  mica_runtime_mark_capability(\"{}\");\n",
-                escape_string("io")
-            ));
-        }
-        if capabilities.iter().any(|cap| cap == "time") {
-            out.push_str(&format!(
-                "  mica_runtime_mark_capability(\"{}\");\n",
-                escape_string("time")
-            ));
+        for provider in NATIVE_PROVIDERS {
+            if capabilities.iter().any(|cap| cap == provider) {
+                out.push_str(&format!(
+                    "  mica_runtime_mark_capability(\"{}\");\n",
+                    escape_string(provider)
+                ));
+            }
         }
     }
     out.push_str("}\n\n");
@@ -729,12 +2255,15 @@ fn emit_runtime_support(out: &mut String, capabilities: &[String]) -> BackendRes
     out.push_str("  exit(74);\n");
     out.push_str("}\n\n");
 
+    out.push_str("_Noreturn static void mica_runtime_provider_failure(const char *capability, const char *format, ...) {\n");
+    out.push_str("  va_list args;\n");
     out.push_str(
-        "static void mica_runtime_provider_failure(const char *capability, const char *message) {\n",
-    );
-    out.push_str(
-        "  fprintf(stderr, \"error: capability provider '%s' reported an error: %s\\n\", capability, message);\n",
+        "  fprintf(stderr, \"error: capability provider '%s' reported an error: \", capability);\n",
     );
+    out.push_str("  va_start(args, format);\n");
+    out.push_str("  vfprintf(stderr, format, args);\n");
+    out.push_str("  va_end(args);\n");
+    out.push_str("  fprintf(stderr, \"\\n\");\n");
     out.push_str("  exit(74);\n");
     out.push_str("}\n\n");
 
@@ -759,9 +2288,357 @@ fn emit_runtime_support(out: &mut String, capabilities: &[String]) -> BackendRes
     out.push_str("  return (int64_t)ts.tv_sec * 1000 + (int64_t)(ts.tv_nsec / 1000000);\n");
     out.push_str("}\n\n");
 
+    let declares = |name: &str| capabilities.iter().any(|cap| cap == name);
+    if ["fs", "env", "process", "net"].into_iter().any(declares) {
+        emit_provider_helpers(out);
+    }
+    if declares("fs") {
+        emit_fs_support(out);
+    }
+    if declares("env") {
+        emit_env_support(out);
+    }
+    if declares("process") {
+        emit_process_support(out);
+    }
+    if declares("net") {
+        emit_net_support(out);
+    }
+
     Ok(())
 }
 
+/// Shared pieces of the string-payload providers: payload checks, the
+/// `KEY=VALUE` split the Rust providers use, and OS errors rendered like
+/// `std::io::Error` ("No such file or directory (os error 2)").
+fn emit_provider_helpers(out: &mut String) {
+    out.push_str("static void mica_runtime_out_of_memory(void) {\n");
+    out.push_str("  fprintf(stderr, \"error: out of memory\\n\");\n");
+    out.push_str("  exit(70);\n");
+    out.push_str("}\n\n");
+    out.push_str("static char *mica_runtime_copy_string(const char *text, size_t len) {\n");
+    out.push_str("  char *copy = malloc(len + 1);\n");
+    out.push_str("  if (!copy) {\n");
+    out.push_str("    mica_runtime_out_of_memory();\n");
+    out.push_str("  }\n");
+    out.push_str("  memcpy(copy, text, len);\n");
+    out.push_str("  copy[len] = '\\0';\n");
+    out.push_str("  return copy;\n");
+    out.push_str("}\n\n");
+    out.push_str("static const char *mica_runtime_os_error(int code) {\n");
+    out.push_str("  static char message[256];\n");
+    out.push_str(
+        "  snprintf(message, sizeof message, \"%s (os error %d)\", strerror(code), code);\n",
+    );
+    out.push_str("  return message;\n");
+    out.push_str("}\n\n");
+    out.push_str("static const char *mica_runtime_payload(const char *capability, const char *operation, const char *payload) {\n");
+    out.push_str("  if (!payload) {\n");
+    out.push_str("    mica_runtime_provider_failure(capability, \"%s expects a string payload\", operation);\n");
+    out.push_str("  }\n");
+    out.push_str("  return payload;\n");
+    out.push_str("}\n\n");
+    out.push_str(
+        "static char *mica_runtime_split_assignment(const char *payload, const char **value) {\n",
+    );
+    out.push_str("  const char *separator = strchr(payload, '=');\n");
+    out.push_str("  const char *start = payload;\n");
+    out.push_str("  const char *end = separator ? separator : payload + strlen(payload);\n");
+    out.push_str("  while (start < end && isspace((unsigned char)*start)) {\n");
+    out.push_str("    ++start;\n");
+    out.push_str("  }\n");
+    out.push_str("  while (end > start && isspace((unsigned char)end[-1])) {\n");
+    out.push_str("    --end;\n");
+    out.push_str("  }\n");
+    out.push_str("  *value = separator ? separator + 1 : NULL;\n");
+    out.push_str("  return mica_runtime_copy_string(start, (size_t)(end - start));\n");
+    out.push_str("}\n\n");
+}
+
+/// `fs.read_to_string(path)` and `fs.write_string("path=contents")`.
+fn emit_fs_support(out: &mut String) {
+    out.push_str("static const char *mica_runtime_fs_read_to_string(const char *path) {\n");
+    out.push_str("  path = mica_runtime_payload(\"fs\", \"read_to_string\", path);\n");
+    out.push_str("  FILE *file = fopen(path, \"rb\");\n");
+    out.push_str("  if (!file) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"fs\", \"failed to read '%s': %s\", path, mica_runtime_os_error(errno));\n");
+    out.push_str("  }\n");
+    out.push_str("  size_t len = 0;\n");
+    out.push_str("  size_t cap = 4096;\n");
+    out.push_str("  char *contents = malloc(cap);\n");
+    out.push_str("  if (!contents) {\n");
+    out.push_str("    mica_runtime_out_of_memory();\n");
+    out.push_str("  }\n");
+    out.push_str("  size_t count;\n");
+    out.push_str("  while ((count = fread(contents + len, 1, cap - len - 1, file)) > 0) {\n");
+    out.push_str("    len += count;\n");
+    out.push_str("    if (len + 1 == cap) {\n");
+    out.push_str("      cap *= 2;\n");
+    out.push_str("      char *grown = realloc(contents, cap);\n");
+    out.push_str("      if (!grown) {\n");
+    out.push_str("        mica_runtime_out_of_memory();\n");
+    out.push_str("      }\n");
+    out.push_str("      contents = grown;\n");
+    out.push_str("    }\n");
+    out.push_str("  }\n");
+    out.push_str("  if (ferror(file)) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"fs\", \"failed to read '%s': %s\", path, mica_runtime_os_error(errno));\n");
+    out.push_str("  }\n");
+    out.push_str("  fclose(file);\n");
+    out.push_str("  const char *result = mica_string_from(contents, len);\n");
+    out.push_str("  free(contents);\n");
+    out.push_str("  return result;\n");
+    out.push_str("}\n\n");
+    out.push_str("static void mica_runtime_fs_write_string(const char *payload) {\n");
+    out.push_str("  const char *contents;\n");
+    out.push_str("  char *path = mica_runtime_split_assignment(mica_runtime_payload(\"fs\", \"write_string\", payload), &contents);\n");
+    out.push_str("  if (!*path) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"fs\", \"write_string payload must be 'path=contents'\");\n");
+    out.push_str("  }\n");
+    out.push_str("  if (!contents) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"fs\", \"write_string payload must include file contents\");\n");
+    out.push_str("  }\n");
+    out.push_str("  size_t len = strlen(contents);\n");
+    out.push_str("  FILE *file = fopen(path, \"wb\");\n");
+    out.push_str("  if (!file || fwrite(contents, 1, len, file) != len || fclose(file) != 0) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"fs\", \"failed to write '%s': %s\", path, mica_runtime_os_error(errno));\n");
+    out.push_str("  }\n");
+    out.push_str("  free(path);\n");
+    out.push_str("}\n\n");
+}
+
+/// `env.get(key)`, `env.set("KEY=VALUE")` and `env.unset(key)` on the
+/// process environment.
+fn emit_env_support(out: &mut String) {
+    out.push_str("static const char *mica_runtime_env_get(const char *key) {\n");
+    out.push_str("  key = mica_runtime_payload(\"env\", \"get\", key);\n");
+    out.push_str("  const char *value = getenv(key);\n");
+    out.push_str("  if (!value) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"env\", \"environment variable '%s' is not set\", key);\n");
+    out.push_str("  }\n");
+    out.push_str("  return mica_string_from(value, strlen(value));\n");
+    out.push_str("}\n\n");
+    out.push_str("static void mica_runtime_env_set(const char *payload) {\n");
+    out.push_str("  const char *value;\n");
+    out.push_str("  char *key = mica_runtime_split_assignment(mica_runtime_payload(\"env\", \"set\", payload), &value);\n");
+    out.push_str("  if (!*key) {\n");
+    out.push_str(
+        "    mica_runtime_provider_failure(\"env\", \"set payload must be 'KEY=VALUE'\");\n",
+    );
+    out.push_str("  }\n");
+    out.push_str("  if (!value) {\n");
+    out.push_str(
+        "    mica_runtime_provider_failure(\"env\", \"set payload must include a value\");\n",
+    );
+    out.push_str("  }\n");
+    out.push_str("  if (setenv(key, value, 1) != 0) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"env\", \"failed to set '%s': %s\", key, mica_runtime_os_error(errno));\n");
+    out.push_str("  }\n");
+    out.push_str("  free(key);\n");
+    out.push_str("}\n\n");
+    out.push_str("static void mica_runtime_env_unset(const char *key) {\n");
+    out.push_str("  key = mica_runtime_payload(\"env\", \"unset\", key);\n");
+    out.push_str("  if (unsetenv(key) != 0) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"env\", \"failed to unset '%s': %s\", key, mica_runtime_os_error(errno));\n");
+    out.push_str("  }\n");
+    out.push_str("}\n\n");
+}
+
+/// `process.spawn(cmdline)` splits the command line like the Rust provider
+/// (quotes and backslash escapes), runs it with its output discarded, and
+/// returns the exit code.
+fn emit_process_support(out: &mut String) {
+    out.push_str("extern char **environ;\n\n");
+    out.push_str("static char **mica_runtime_process_parse(const char *payload) {\n");
+    out.push_str("  size_t len = strlen(payload);\n");
+    out.push_str("  char **argv = calloc(len / 2 + 2, sizeof(char *));\n");
+    out.push_str("  char *current = malloc(len + 1);\n");
+    out.push_str("  if (!argv || !current) {\n");
+    out.push_str("    mica_runtime_out_of_memory();\n");
+    out.push_str("  }\n");
+    out.push_str("  size_t used = 0;\n");
+    out.push_str("  size_t count = 0;\n");
+    out.push_str("  char quote = '\\0';\n");
+    out.push_str("  for (const char *cursor = payload; *cursor; ++cursor) {\n");
+    out.push_str("    char ch = *cursor;\n");
+    out.push_str("    if (ch == '\\'' || ch == '\"') {\n");
+    out.push_str("      if (!quote) {\n");
+    out.push_str("        quote = ch;\n");
+    out.push_str("      } else if (ch == quote) {\n");
+    out.push_str("        quote = '\\0';\n");
+    out.push_str("      } else {\n");
+    out.push_str("        current[used++] = ch;\n");
+    out.push_str("      }\n");
+    out.push_str("    } else if (ch == '\\\\') {\n");
+    out.push_str("      if (cursor[1]) {\n");
+    out.push_str("        current[used++] = *++cursor;\n");
+    out.push_str("      }\n");
+    out.push_str("    } else if (isspace((unsigned char)ch) && !quote) {\n");
+    out.push_str("      if (used > 0) {\n");
+    out.push_str("        argv[count++] = mica_runtime_copy_string(current, used);\n");
+    out.push_str("        used = 0;\n");
+    out.push_str("      }\n");
+    out.push_str("    } else {\n");
+    out.push_str("      current[used++] = ch;\n");
+    out.push_str("    }\n");
+    out.push_str("  }\n");
+    out.push_str("  if (quote) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"process\", \"unterminated quote in process payload\");\n");
+    out.push_str("  }\n");
+    out.push_str("  if (used > 0) {\n");
+    out.push_str("    argv[count++] = mica_runtime_copy_string(current, used);\n");
+    out.push_str("  }\n");
+    out.push_str("  free(current);\n");
+    out.push_str("  if (count == 0) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"process\", \"spawn requires a command to execute\");\n");
+    out.push_str("  }\n");
+    out.push_str("  return argv;\n");
+    out.push_str("}\n\n");
+    out.push_str("static int64_t mica_runtime_process_spawn(const char *payload) {\n");
+    out.push_str("  char **argv = mica_runtime_process_parse(mica_runtime_payload(\"process\", \"spawn\", payload));\n");
+    out.push_str("  posix_spawn_file_actions_t actions;\n");
+    out.push_str("  posix_spawn_file_actions_init(&actions);\n");
+    out.push_str("  posix_spawn_file_actions_addopen(&actions, 0, \"/dev/null\", O_RDONLY, 0);\n");
+    out.push_str("  posix_spawn_file_actions_addopen(&actions, 1, \"/dev/null\", O_WRONLY, 0);\n");
+    out.push_str("  posix_spawn_file_actions_addopen(&actions, 2, \"/dev/null\", O_WRONLY, 0);\n");
+    out.push_str("  pid_t pid;\n");
+    out.push_str("  int code = posix_spawnp(&pid, argv[0], &actions, NULL, argv, environ);\n");
+    out.push_str("  posix_spawn_file_actions_destroy(&actions);\n");
+    out.push_str("  if (code != 0) {\n");
+    out.push_str("    mica_runtime_provider_failure(\"process\", \"failed to spawn '%s': %s\", argv[0], mica_runtime_os_error(code));\n");
+    out.push_str("  }\n");
+    out.push_str("  int status;\n");
+    out.push_str("  while (waitpid(pid, &status, 0) < 0) {\n");
+    out.push_str("    if (errno != EINTR) {\n");
+    out.push_str("      mica_runtime_provider_failure(\"process\", \"failed to spawn '%s': %s\", argv[0], mica_runtime_os_error(errno));\n");
+    out.push_str("    }\n");
+    out.push_str("  }\n");
+    out.push_str("  for (char **arg = argv; *arg; ++arg) {\n");
+    out.push_str("    free(*arg);\n");
+    out.push_str("  }\n");
+    out.push_str("  free(argv);\n");
+    out.push_str("  if (!WIFEXITED(status)) {\n");
+    out.push_str(
+        "    mica_runtime_provider_failure(\"process\", \"process exited without status code\");\n",
+    );
+    out.push_str("  }\n");
+    out.push_str("  return WEXITSTATUS(status);\n");
+    out.push_str("}\n\n");
+}
+
+/// `net.fetch(key)` only serves fixtures registered with the Rust runtime, so
+/// native executables report every key as missing.
+fn emit_net_support(out: &mut String) {
+    out.push_str("static const char *mica_runtime_net_fetch(const char *key) {\n");
+    out.push_str("  key = mica_runtime_payload(\"net\", \"fetch\", key);\n");
+    out.push_str(
+        "  mica_runtime_provider_failure(\"net\", \"no fixture registered for '%s'\", key);\n",
+    );
+    out.push_str("}\n\n");
+}
+
+/// Growable, heap-allocated arrays backing `[T]`. Elements are stored inline
+/// and copied by value; every access is bounds-checked and an out-of-range
+/// index aborts the program. A list is freed with its last reference,
+/// releasing its elements through `release` when they hold references too.
+fn emit_list_support(out: &mut String) {
+    out.push_str("typedef struct mica_list {\n");
+    out.push_str("  int64_t rc;\n");
+    out.push_str("  int64_t len;\n");
+    out.push_str("  int64_t cap;\n");
+    out.push_str("  size_t elem_size;\n");
+    out.push_str("  unsigned char *data;\n");
+    out.push_str("  void (*release)(void *);\n");
+    out.push_str("} mica_list;\n\n");
+
+    out.push_str("static void mica_list_out_of_memory(void) {\n");
+    out.push_str("  fprintf(stderr, \"error: out of memory while growing list\\n\");\n");
+    out.push_str("  exit(70);\n");
+    out.push_str("}\n\n");
+
+    out.push_str("static mica_list *mica_list_new(size_t elem_size, int64_t cap) {\n");
+    out.push_str("  mica_list *list = malloc(sizeof(mica_list));\n");
+    out.push_str("  if (!list) {\n");
+    out.push_str("    mica_list_out_of_memory();\n");
+    out.push_str("  }\n");
+    out.push_str("  list->rc = 1;\n");
+    out.push_str("  list->len = 0;\n");
+    out.push_str("  list->cap = cap > 0 ? cap : 4;\n");
+    out.push_str("  list->elem_size = elem_size > 0 ? elem_size : 1;\n");
+    out.push_str("  list->data = malloc((size_t)list->cap * list->elem_size);\n");
+    out.push_str("  if (!list->data) {\n");
+    out.push_str("    mica_list_out_of_memory();\n");
+    out.push_str("  }\n");
+    out.push_str("  list->release = NULL;\n");
+    out.push_str("  MICA_RUNTIME_LIVE_OBJECTS += 1;\n");
+    out.push_str("  return list;\n");
+    out.push_str("}\n\n");
+
+    out.push_str("static void mica_list_push(mica_list *list, const void *value) {\n");
+    out.push_str("  if (list->len == list->cap) {\n");
+    out.push_str("    int64_t cap = list->cap * 2;\n");
+    out.push_str("    unsigned char *data = realloc(list->data, (size_t)cap * list->elem_size);\n");
+    out.push_str("    if (!data) {\n");
+    out.push_str("      mica_list_out_of_memory();\n");
+    out.push_str("    }\n");
+    out.push_str("    list->data = data;\n");
+    out.push_str("    list->cap = cap;\n");
+    out.push_str("  }\n");
+    out.push_str(
+        "  memcpy(list->data + (size_t)list->len * list->elem_size, value, list->elem_size);\n",
+    );
+    out.push_str("  list->len += 1;\n");
+    out.push_str("}\n\n");
+
+    out.push_str("static int64_t mica_list_len(const mica_list *list) {\n");
+    out.push_str("  return list ? list->len : 0;\n");
+    out.push_str("}\n\n");
+
+    out.push_str("static void *mica_list_at(mica_list *list, int64_t index) {\n");
+    out.push_str("  int64_t len = mica_list_len(list);\n");
+    out.push_str("  if (index < 0 || index >= len) {\n");
+    out.push_str(
+        "    fprintf(stderr, \"error: list index %lld out of bounds for length %lld\\n\", (long long)index, (long long)len);\n",
+    );
+    out.push_str("    exit(70);\n");
+    out.push_str("  }\n");
+    out.push_str("  return list->data + (size_t)index * list->elem_size;\n");
+    out.push_str("}\n\n");
+
+    out.push_str("static void mica_list_retain(mica_list *list) {\n");
+    out.push_str("  if (list) {\n");
+    out.push_str("    list->rc += 1;\n");
+    out.push_str("  }\n");
+    out.push_str("}\n\n");
+    out.push_str("static void mica_list_release(mica_list *list) {\n");
+    out.push_str("  if (!list) {\n");
+    out.push_str("    return;\n");
+    out.push_str("  }\n");
+    out.push_str("  list->rc -= 1;\n");
+    out.push_str("  if (list->rc > 0) {\n");
+    out.push_str("    return;\n");
+    out.push_str("  }\n");
+    out.push_str("  if (list->release) {\n");
+    out.push_str("    for (int64_t i = 0; i < list->len; ++i) {\n");
+    out.push_str("      list->release(list->data + (size_t)i * list->elem_size);\n");
+    out.push_str("    }\n");
+    out.push_str("  }\n");
+    out.push_str("  free(list->data);\n");
+    out.push_str("  free(list);\n");
+    out.push_str("  MICA_RUNTIME_LIVE_OBJECTS -= 1;\n");
+    out.push_str("}\n\n");
+    out.push_str("static void mica_list_release_slot(void *slot) {\n");
+    out.push_str("  mica_list_release(*(mica_list **)slot);\n");
+    out.push_str("}\n\n");
+}
+
+fn uses_lists(module: &ir::Module) -> bool {
+    module
+        .types
+        .entries()
+        .any(|(_, ty)| matches!(ty, Type::List(_)))
+}
+
 fn collect_capabilities(module: &ir::Module) -> Vec<String> {
     let mut caps = BTreeSet::new();
     for function in &module.functions {
@@ -783,6 +2660,9 @@ fn collect_record_names(module: &ir::Module) -> RecordNameMap {
                 .unwrap_or_else(|| format!("anon_{}", id.index()));
             names.insert(id, format!("record_{}", base));
         }
+        if let Type::Sum(sum) = ty {
+            names.insert(id, format!("sum_{}", sanitize_identifier(&sum.name)));
+        }
     }
     names
 }
@@ -19,17 +19,69 @@ impl Backend for TextBackend {
     }
 }
 
+/// Renders `module` in the textual IR format that `ir::parse` reads back.
+///
+/// Named record layouts, sum types and the effect table are printed as `type` and
+/// `effects` lines after the module header so the output is self-contained.
 pub fn render_module(module: &ir::Module) -> String {
+    render(module, false)
+}
+
+/// Like [`render_module`], with a `; function line:column node` comment
+/// before every instruction and terminator that has provenance.
+pub fn render_module_with_provenance(module: &ir::Module) -> String {
+    render(module, true)
+}
+
+fn render(module: &ir::Module, provenance: bool) -> String {
     let mut out = String::new();
     writeln!(out, "module {}", module.name.join(".")).unwrap();
+    for (_, ty) in module.types.entries() {
+        if let Type::Record(record) = ty
+            && let Some(name) = &record.name
+        {
+            writeln!(
+                out,
+                "type {} = {}",
+                name,
+                format_record_fields(module, record)
+            )
+            .unwrap();
+        }
+        if let Type::Sum(sum) = ty {
+            writeln!(
+                out,
+                "type {} = {}",
+                sum.name,
+                format_sum_variants(module, sum)
+            )
+            .unwrap();
+        }
+    }
+    let effects: Vec<&str> = module
+        .effects
+        .entries()
+        .map(|(_, name)| name.as_str())
+        .collect();
+    if !effects.is_empty() {
+        writeln!(out, "effects {}", effects.join(", ")).unwrap();
+    }
     for function in &module.functions {
         writeln!(out).unwrap();
-        render_function(&mut out, module, function);
+        render_function(&mut out, module, function, provenance);
     }
     out
 }
 
-fn render_function(out: &mut String, module: &ir::Module, function: &ir::Function) {
+fn render_function(
+    out: &mut String,
+    module: &ir::Module,
+    function: &ir::Function,
+    provenance: bool,
+) {
+    if function.is_public {
+        write!(out, "pub ").unwrap();
+    }
     write!(out, "fn {}(", function.name).unwrap();
     for (index, param) in function.params.iter().enumerate() {
         if index > 0 {
@@ -55,20 +107,52 @@ fn render_function(out: &mut String, module: &ir::Module, function: &ir::Functio
     for block in &function.blocks {
         writeln!(out, "  block {}:", block.id.index()).unwrap();
         for inst in &block.instructions {
-            let ty = module.type_of(inst.ty);
-            writeln!(
-                out,
-                "    %{} = {} : {}",
-                inst.id.index(),
-                format_inst(inst),
-                format_type(module, ty)
-            )
-            .unwrap();
+            if provenance {
+                write_provenance(out, inst.provenance.as_ref());
+            }
+            writeln!(out, "    {}", format_instruction(module, inst)).unwrap();
+        }
+        if provenance {
+            write_provenance(out, block.terminator_provenance.as_ref());
         }
         writeln!(out, "    {}", format_terminator(block)).unwrap();
     }
 }
 
+/// Comment lines keep the output readable by `ir::parse`.
+fn write_provenance(out: &mut String, provenance: Option<&ir::Provenance>) {
+    if let Some(provenance) = provenance {
+        writeln!(
+            out,
+            "    ; {} {} {}",
+            provenance.function,
+            provenance.span,
+            provenance.node.as_str()
+        )
+        .unwrap();
+    }
+}
+
+/// One instruction as `%id = kind : Type`, followed by its effect row.
+pub(super) fn format_instruction(module: &ir::Module, inst: &ir::Instruction) -> String {
+    let ty = module.type_of(inst.ty);
+    let mut line = format!(
+        "%{} = {} : {}",
+        inst.id.index(),
+        format_inst(inst),
+        format_type(module, ty)
+    );
+    if !inst.effects.is_empty() {
+        let names: Vec<_> = inst
+            .effects
+            .iter()
+            .map(|id| module.effect_name(*id))
+            .collect();
+        write!(line, " !{{{}}}", names.join(", ")).unwrap();
+    }
+    line
+}
+
 fn format_inst(inst: &ir::Instruction) -> String {
     match &inst.kind {
         InstKind::Literal(lit) => format_literal(lit),
@@ -76,7 +160,7 @@ fn format_inst(inst: &ir::Instruction) -> String {
         InstKind::Call { func, args } => {
             let name = match func {
                 ir::FuncRef::Function(path) => path.segments.join("::"),
-                ir::FuncRef::Method(name) => name.clone(),
+                ir::FuncRef::Method(name) => format!(".{}", name),
             };
             let mut parts = Vec::with_capacity(args.len());
             for arg in args {
@@ -99,6 +183,8 @@ fn format_inst(inst: &ir::Instruction) -> String {
             }
         }
         InstKind::Path(path) => format!("path {}", path.segments.join("::")),
+        InstKind::Field { base, name } => format!("field %{}.{}", base.index(), name),
+        InstKind::Convert { value } => format!("convert %{}", value.index()),
         InstKind::Phi { incomings } => {
             let mut parts = Vec::new();
             for (block, value) in incomings {
@@ -106,10 +192,43 @@ fn format_inst(inst: &ir::Instruction) -> String {
             }
             format!("phi {{ {} }}", parts.join(", "))
         }
+        InstKind::List { elements } => {
+            let parts: Vec<String> = elements
+                .iter()
+                .map(|value| format!("%{}", value.index()))
+                .collect();
+            format!("list [{}]", parts.join(", "))
+        }
+        InstKind::ListLen { list } => format!("len %{}", list.index()),
+        InstKind::ListIndex { list, index } => {
+            format!("index %{}, %{}", list.index(), index.index())
+        }
+        InstKind::ListPush { list, value } => {
+            format!("push %{}, %{}", list.index(), value.index())
+        }
+        InstKind::Retain { value } => format!("retain %{}", value.index()),
+        InstKind::Release { value } => format!("release %{}", value.index()),
+        InstKind::Variant { variant, payload } if payload.is_empty() => {
+            format!("variant {}", variant)
+        }
+        InstKind::Variant { variant, payload } => {
+            let parts: Vec<String> = payload
+                .iter()
+                .map(|value| format!("%{}", value.index()))
+                .collect();
+            format!("variant {}({})", variant, parts.join(", "))
+        }
+        InstKind::IsVariant { value, variant } => format!("is %{}, {}", value.index(), variant),
+        InstKind::Payload {
+            value,
+            variant,
+            index,
+        } => format!("payload %{}, {}, {}", value.index(), variant, index),
+        InstKind::Trap { message } => format!("trap {:?}", message),
     }
 }
 
-fn format_terminator(block: &ir::BasicBlock) -> String {
+pub(super) fn format_terminator(block: &ir::BasicBlock) -> String {
     match &block.terminator {
         Terminator::Return(Some(value)) => {
             format!("return %{}", value.index())
@@ -132,9 +251,11 @@ fn format_terminator(block: &ir::BasicBlock) -> String {
 fn format_literal(literal: &crate::syntax::ast::Literal) -> String {
     match literal {
         crate::syntax::ast::Literal::Int(value) => value.to_string(),
-        crate::syntax::ast::Literal::Float(value) => value.to_string(),
+        // Debug formatting keeps a decimal point so floats never read back
+        // as integers.
+        crate::syntax::ast::Literal::Float(value) => format!("{:?}", value),
         crate::syntax::ast::Literal::Bool(value) => value.to_string(),
-        crate::syntax::ast::Literal::String(value) => format!("\"{}\"", value),
+        crate::syntax::ast::Literal::String(value) => format!("{:?}", value),
         crate::syntax::ast::Literal::Unit => "()".to_string(),
     }
 }
@@ -150,17 +271,43 @@ fn format_type(module: &ir::Module, ty: &Type) -> String {
         Type::Record(record) => {
             if let Some(name) = &record.name {
                 name.clone()
-            } else if record.fields.is_empty() {
-                "{}".to_string()
             } else {
-                let mut parts = Vec::with_capacity(record.fields.len());
-                for field in &record.fields {
-                    let field_ty = module.type_of(field.ty);
-                    parts.push(format!("{}: {}", field.name, format_type(module, field_ty)));
-                }
-                format!("{{ {} }}", parts.join(", "))
+                format_record_fields(module, record)
             }
         }
+        Type::Sum(sum) => sum.name.clone(),
+        Type::List(element) => format!("[{}]", format_type(module, module.type_of(*element))),
         Type::Unknown => "_".to_string(),
     }
 }
+
+fn format_sum_variants(module: &ir::Module, sum: &ir::SumType) -> String {
+    let variants: Vec<String> = sum
+        .variants
+        .iter()
+        .map(|variant| {
+            if variant.fields.is_empty() {
+                return variant.name.clone();
+            }
+            let fields: Vec<String> = variant
+                .fields
+                .iter()
+                .map(|field| format_type(module, module.type_of(*field)))
+                .collect();
+            format!("{}({})", variant.name, fields.join(", "))
+        })
+        .collect();
+    variants.join(" | ")
+}
+
+fn format_record_fields(module: &ir::Module, record: &ir::RecordType) -> String {
+    if record.fields.is_empty() {
+        return "{}".to_string();
+    }
+    let mut parts = Vec::with_capacity(record.fields.len());
+    for field in &record.fields {
+        let field_ty = module.type_of(field.ty);
+        parts.push(format!("{}: {}", field.name, format_type(module, field_ty)));
+    }
+    format!("{{ {} }}", parts.join(", "))
+}
@@ -0,0 +1,1599 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ir::analysis::AnalysisManager;
+use crate::ir::passes::{DeadFunctionElimination, Pass, TailCallElimination};
+use crate::ir::{self, BlockId, InstKind, Terminator, Type, TypeId, ValueId};
+use crate::syntax::ast::{BinaryOp, Literal};
+
+use super::{Backend, BackendError, BackendOptions, BackendResult, resolve_callee, type_label};
+
+/// Import module the host provides capability operations under.
+pub const RUNTIME_IMPORT_MODULE: &str = "mica_runtime";
+
+/// Address of the first string literal; the bytes below it stay zero so a
+/// null pointer never aliases live data.
+const DATA_BASE: u32 = 8;
+
+/// Every record field and list element occupies one slot of this size.
+const SLOT_SIZE: u32 = 8;
+
+const PAGE_SIZE: u32 = 65536;
+
+/// Backend that encodes the typed SSA module as a WebAssembly module.
+///
+/// Records, lists and strings live in a single exported linear memory:
+/// string literals are data segments, and records and lists come from a bump
+/// allocator that grows the memory on demand. Control flow is a dispatch
+/// loop over the IR blocks, and capability methods call functions imported
+/// from [`RUNTIME_IMPORT_MODULE`].
+#[derive(Debug, Default, Clone)]
+pub struct WasmBackend;
+
+/// WebAssembly artifact produced by the backend: the binary module plus its
+/// text form for inspection.
+#[derive(Debug, Clone)]
+pub struct WasmModule {
+    pub bytes: Vec<u8>,
+    pub wat: String,
+    pub module_name: String,
+}
+
+impl WasmModule {
+    /// Writes the binary module to the provided path.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> BackendResult<PathBuf> {
+        let path = path.as_ref();
+        fs::write(path, &self.bytes)
+            .map_err(|err| BackendError::Internal(format!("failed to write module: {err}")))?;
+        Ok(path.to_path_buf())
+    }
+}
+
+impl Backend for WasmBackend {
+    type Output = WasmModule;
+
+    fn compile(
+        &self,
+        module: &ir::Module,
+        _options: &BackendOptions,
+    ) -> BackendResult<Self::Output> {
+        // Exports are the only way in, so only they and their callees stay.
+        let mut module = module.clone();
+        let mut analyses = AnalysisManager::new();
+        DeadFunctionElimination::library().run(&mut module, &mut analyses);
+        TailCallElimination.run(&mut module, &mut analyses);
+
+        let layout = Layout::new(&module);
+        let mut functions = Vec::with_capacity(module.functions.len());
+        for function in &module.functions {
+            functions.push(FunctionCompiler::compile(&module, &layout, function)?);
+        }
+        for helper in &layout.helpers {
+            functions.push(helper.function(&layout));
+        }
+
+        let encoded = EncodedModule {
+            name: module.name.join("::"),
+            layout: &layout,
+            functions: &functions,
+        };
+        Ok(WasmModule {
+            bytes: encoded.binary(),
+            wat: encoded.text(),
+            module_name: module.name.join("_"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ValType {
+    I32,
+    I64,
+    F64,
+}
+
+impl ValType {
+    fn of(module: &ir::Module, ty: TypeId) -> ValType {
+        match module.type_of(ty) {
+            Type::Int | Type::Named(_) | Type::Unknown => ValType::I64,
+            Type::Float => ValType::F64,
+            Type::Unit
+            | Type::Bool
+            | Type::String
+            | Type::Record(_)
+            | Type::Sum(_)
+            | Type::List(_) => ValType::I32,
+        }
+    }
+
+    /// Result type of a function returning `ty`; unit results are dropped.
+    fn result_of(module: &ir::Module, ty: TypeId) -> Option<ValType> {
+        (!matches!(module.type_of(ty), Type::Unit)).then(|| ValType::of(module, ty))
+    }
+
+    fn zero(self) -> Op {
+        match self {
+            ValType::I32 => Op::I32Const(0),
+            ValType::I64 => Op::I64Const(0),
+            ValType::F64 => Op::F64Const(0.0),
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            ValType::I32 => 0x7f,
+            ValType::I64 => 0x7e,
+            ValType::F64 => 0x7c,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+            ValType::F64 => "f64",
+        }
+    }
+}
+
+/// One WebAssembly instruction, shared by the binary encoder and the text
+/// printer. Label operands are relative depths as in the binary format.
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    /// An instruction without immediates: its text name and opcode.
+    Plain(&'static str, u8),
+    Load(ValType, u32),
+    Load8U(u32),
+    Store(ValType, u32),
+    I32Const(i32),
+    I64Const(i64),
+    F64Const(f64),
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
+    Call(u32),
+    Block,
+    Loop,
+    End,
+    Br(u32),
+    BrIf(u32),
+    BrTable(Vec<u32>, u32),
+    Return,
+    Unreachable,
+    MemorySize,
+    MemoryGrow,
+}
+
+const I32_EQZ: Op = Op::Plain("i32.eqz", 0x45);
+const I32_NE: Op = Op::Plain("i32.ne", 0x47);
+const I32_LT_U: Op = Op::Plain("i32.lt_u", 0x49);
+const I32_GT_U: Op = Op::Plain("i32.gt_u", 0x4b);
+const I32_LE_U: Op = Op::Plain("i32.le_u", 0x4d);
+const I32_GE_U: Op = Op::Plain("i32.ge_u", 0x4f);
+const I32_ADD: Op = Op::Plain("i32.add", 0x6a);
+const I32_SUB: Op = Op::Plain("i32.sub", 0x6b);
+const I32_AND: Op = Op::Plain("i32.and", 0x71);
+const I32_SHL: Op = Op::Plain("i32.shl", 0x74);
+const I32_SHR_U: Op = Op::Plain("i32.shr_u", 0x76);
+const I32_WRAP_I64: Op = Op::Plain("i32.wrap_i64", 0xa7);
+const I64_EXTEND_I32_U: Op = Op::Plain("i64.extend_i32_u", 0xad);
+const I64_LT_U: Op = Op::Plain("i64.lt_u", 0x54);
+const F64_CONVERT_I64_S: Op = Op::Plain("f64.convert_i64_s", 0xb9);
+const I64_TRUNC_F64_S: Op = Op::Plain("i64.trunc_f64_s", 0xb0);
+const DROP: Op = Op::Plain("drop", 0x1a);
+const SELECT: Op = Op::Plain("select", 0x1b);
+
+/// Index of the `$heap` global holding the allocator's next free address.
+const HEAP_GLOBAL: u32 = 0;
+
+/// Capability operations imported from the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Import {
+    /// `io_write_line(ptr, len)` prints the UTF-8 bytes at `ptr`.
+    WriteLine,
+    /// `time_now_millis() -> i64`.
+    NowMillis,
+}
+
+impl Import {
+    fn for_method(name: &str) -> Option<Import> {
+        match name {
+            "println" | "write_line" => Some(Import::WriteLine),
+            "now_millis" => Some(Import::NowMillis),
+            _ => None,
+        }
+    }
+
+    fn field(self) -> &'static str {
+        match self {
+            Import::WriteLine => "io_write_line",
+            Import::NowMillis => "time_now_millis",
+        }
+    }
+
+    fn signature(self) -> Signature {
+        match self {
+            Import::WriteLine => (vec![ValType::I32, ValType::I32], None),
+            Import::NowMillis => (Vec::new(), Some(ValType::I64)),
+        }
+    }
+}
+
+/// Runtime support functions appended after the module's own functions.
+///
+/// A list is a 12-byte header `{len: i32, cap: i32, data: i32}` whose data
+/// points at `cap` element slots; the header never moves, so lists stay
+/// shared handles when pushing reallocates the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Helper {
+    /// `mica_alloc(size) -> ptr` bumps `$heap`, growing memory as needed.
+    Alloc,
+    /// `mica_list_new(cap) -> list` allocates an empty list.
+    ListNew,
+    /// `mica_list_slot(list) -> ptr` appends an element slot and returns it.
+    ListSlot,
+    /// `mica_list_at(list, index) -> ptr` traps when `index` is out of range.
+    ListAt,
+    /// `mica_str_eq(a, b) -> bool` compares two strings bytewise.
+    StrEq,
+}
+
+impl Helper {
+    fn name(self) -> &'static str {
+        match self {
+            Helper::Alloc => "mica_alloc",
+            Helper::ListNew => "mica_list_new",
+            Helper::ListSlot => "mica_list_slot",
+            Helper::ListAt => "mica_list_at",
+            Helper::StrEq => "mica_str_eq",
+        }
+    }
+
+    fn function(self, layout: &Layout) -> WasmFunction {
+        use ValType::{I32, I64};
+        let alloc = || layout.helper_index(Helper::Alloc);
+        let (params, locals, body): (&[NamedLocal], &[NamedLocal], Vec<Op>) = match self {
+            Helper::Alloc => (&[("size", I32)], &[("ptr", I32)], alloc_body()),
+            Helper::ListNew => (&[("cap", I32)], &[("list", I32)], list_new_body(alloc())),
+            Helper::ListSlot => (
+                &[("list", I32)],
+                &[("data", I32), ("offset", I32)],
+                list_slot_body(alloc()),
+            ),
+            Helper::ListAt => (&[("list", I32), ("index", I64)], &[], list_at_body()),
+            Helper::StrEq => (
+                &[("a", I32), ("b", I32)],
+                &[("len", I32), ("i", I32)],
+                str_eq_body(),
+            ),
+        };
+        let named = |entries: &[NamedLocal]| {
+            entries
+                .iter()
+                .map(|(name, ty)| (name.to_string(), *ty))
+                .collect()
+        };
+        WasmFunction {
+            name: self.name().to_string(),
+            params: named(params),
+            result: Some(I32),
+            locals: named(locals),
+            body,
+            export: false,
+        }
+    }
+}
+
+// Helper bodies. Locals are numbered parameters first, as in `Helper::function`.
+
+fn alloc_body() -> Vec<Op> {
+    let (size, ptr) = (0, 1);
+    let memory_end = [Op::MemorySize, Op::I32Const(16), I32_SHL];
+    let mut body = vec![
+        Op::GlobalGet(HEAP_GLOBAL),
+        Op::LocalSet(ptr),
+        Op::LocalGet(ptr),
+        Op::LocalGet(size),
+        I32_ADD,
+        Op::I32Const(7),
+        I32_ADD,
+        Op::I32Const(-8),
+        I32_AND,
+        Op::GlobalSet(HEAP_GLOBAL),
+        Op::Block,
+        Op::GlobalGet(HEAP_GLOBAL),
+    ];
+    body.extend(memory_end.clone());
+    body.extend([I32_LE_U, Op::BrIf(0), Op::GlobalGet(HEAP_GLOBAL)]);
+    body.extend(memory_end);
+    body.extend([
+        I32_SUB,
+        Op::I32Const(PAGE_SIZE as i32 - 1),
+        I32_ADD,
+        Op::I32Const(16),
+        I32_SHR_U,
+        Op::MemoryGrow,
+        Op::I32Const(-1),
+        I32_NE,
+        Op::BrIf(0),
+        Op::Unreachable,
+        Op::End,
+        Op::LocalGet(ptr),
+    ]);
+    body
+}
+
+fn list_new_body(alloc: u32) -> Vec<Op> {
+    let (cap, list) = (0, 1);
+    vec![
+        Op::I32Const(12),
+        Op::Call(alloc),
+        Op::LocalSet(list),
+        // cap = max(cap, 4)
+        Op::LocalGet(cap),
+        Op::I32Const(4),
+        Op::LocalGet(cap),
+        Op::I32Const(4),
+        I32_GT_U,
+        SELECT,
+        Op::LocalSet(cap),
+        Op::LocalGet(list),
+        Op::LocalGet(cap),
+        Op::Store(ValType::I32, 4),
+        Op::LocalGet(list),
+        Op::LocalGet(cap),
+        Op::I32Const(3),
+        I32_SHL,
+        Op::Call(alloc),
+        Op::Store(ValType::I32, 8),
+        Op::LocalGet(list),
+    ]
+}
+
+fn list_slot_body(alloc: u32) -> Vec<Op> {
+    let (list, data, offset) = (0, 1, 2);
+    vec![
+        Op::Block,
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 0),
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 4),
+        I32_LT_U,
+        Op::BrIf(0),
+        // Full: copy the slots into twice the capacity.
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 4),
+        Op::I32Const(4),
+        I32_SHL,
+        Op::Call(alloc),
+        Op::LocalSet(data),
+        Op::Block,
+        Op::Loop,
+        Op::LocalGet(offset),
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 0),
+        Op::I32Const(3),
+        I32_SHL,
+        I32_GE_U,
+        Op::BrIf(1),
+        Op::LocalGet(data),
+        Op::LocalGet(offset),
+        I32_ADD,
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 8),
+        Op::LocalGet(offset),
+        I32_ADD,
+        Op::Load(ValType::I64, 0),
+        Op::Store(ValType::I64, 0),
+        Op::LocalGet(offset),
+        Op::I32Const(SLOT_SIZE as i32),
+        I32_ADD,
+        Op::LocalSet(offset),
+        Op::Br(0),
+        Op::End,
+        Op::End,
+        Op::LocalGet(list),
+        Op::LocalGet(data),
+        Op::Store(ValType::I32, 8),
+        Op::LocalGet(list),
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 4),
+        Op::I32Const(1),
+        I32_SHL,
+        Op::Store(ValType::I32, 4),
+        Op::End,
+        // slot = data + len * 8, then len += 1
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 8),
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 0),
+        Op::I32Const(3),
+        I32_SHL,
+        I32_ADD,
+        Op::LocalGet(list),
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 0),
+        Op::I32Const(1),
+        I32_ADD,
+        Op::Store(ValType::I32, 0),
+    ]
+}
+
+fn list_at_body() -> Vec<Op> {
+    let (list, index) = (0, 1);
+    vec![
+        // An unsigned compare also rejects negative indices.
+        Op::Block,
+        Op::LocalGet(index),
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 0),
+        I64_EXTEND_I32_U,
+        I64_LT_U,
+        Op::BrIf(0),
+        Op::Unreachable,
+        Op::End,
+        Op::LocalGet(list),
+        Op::Load(ValType::I32, 8),
+        Op::LocalGet(index),
+        I32_WRAP_I64,
+        Op::I32Const(3),
+        I32_SHL,
+        I32_ADD,
+    ]
+}
+
+fn str_eq_body() -> Vec<Op> {
+    let (a, b, len, i) = (0, 1, 2, 3);
+    vec![
+        Op::Block,
+        Op::LocalGet(a),
+        Op::Load(ValType::I32, 0),
+        Op::LocalTee(len),
+        Op::LocalGet(b),
+        Op::Load(ValType::I32, 0),
+        I32_NE,
+        Op::BrIf(0),
+        Op::Loop,
+        Op::Block,
+        Op::LocalGet(i),
+        Op::LocalGet(len),
+        I32_LT_U,
+        Op::BrIf(0),
+        Op::I32Const(1),
+        Op::Return,
+        Op::End,
+        Op::LocalGet(a),
+        Op::LocalGet(i),
+        I32_ADD,
+        Op::Load8U(4),
+        Op::LocalGet(b),
+        Op::LocalGet(i),
+        I32_ADD,
+        Op::Load8U(4),
+        I32_NE,
+        Op::BrIf(1),
+        Op::LocalGet(i),
+        Op::I32Const(1),
+        I32_ADD,
+        Op::LocalSet(i),
+        Op::Br(0),
+        Op::End,
+        Op::End,
+        Op::I32Const(0),
+    ]
+}
+
+type Signature = (Vec<ValType>, Option<ValType>);
+
+type NamedLocal = (&'static str, ValType);
+
+/// Module-wide index spaces and the static data segment.
+struct Layout {
+    imports: Vec<Import>,
+    functions: HashMap<String, u32>,
+    helpers: Vec<Helper>,
+    /// Address of each string literal's `[len: i32][bytes]` blob.
+    strings: HashMap<String, u32>,
+    data: Vec<u8>,
+    heap_base: u32,
+    exports: Vec<String>,
+}
+
+impl Layout {
+    fn new(module: &ir::Module) -> Self {
+        let mut imports = BTreeSet::new();
+        let mut helpers = BTreeSet::new();
+        let mut strings = HashMap::new();
+        let mut data = Vec::new();
+        for function in &module.functions {
+            let types = value_types(function);
+            for inst in function.blocks.iter().flat_map(|block| &block.instructions) {
+                match &inst.kind {
+                    InstKind::Literal(Literal::String(value)) if !strings.contains_key(value) => {
+                        strings.insert(value.clone(), DATA_BASE + data.len() as u32);
+                        data.extend((value.len() as u32).to_le_bytes());
+                        data.extend(value.as_bytes());
+                        data.resize(data.len().next_multiple_of(4), 0);
+                    }
+                    InstKind::Call {
+                        func: ir::FuncRef::Method(name),
+                        ..
+                    } => imports.extend(Import::for_method(name)),
+                    InstKind::Record { .. } => {
+                        helpers.insert(Helper::Alloc);
+                    }
+                    InstKind::List { .. } | InstKind::ListPush { .. } => {
+                        helpers.extend([Helper::Alloc, Helper::ListNew, Helper::ListSlot]);
+                    }
+                    InstKind::ListIndex { .. } => {
+                        helpers.insert(Helper::ListAt);
+                    }
+                    InstKind::Binary {
+                        op: BinaryOp::Eq | BinaryOp::Ne,
+                        lhs,
+                        ..
+                    } if types
+                        .get(lhs)
+                        .is_some_and(|ty| matches!(module.type_of(*ty), Type::String)) =>
+                    {
+                        helpers.insert(Helper::StrEq);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let imports: Vec<Import> = imports.into_iter().collect();
+        let functions = module
+            .functions
+            .iter()
+            .enumerate()
+            .map(|(index, function)| (function.name.clone(), (imports.len() + index) as u32))
+            .collect();
+
+        // Like dead function elimination, a module without entry points
+        // exposes everything.
+        let mut exports: Vec<String> = module
+            .functions
+            .iter()
+            .filter(|function| function.name == "main" || function.is_public)
+            .map(|function| function.name.clone())
+            .collect();
+        if exports.is_empty() {
+            exports = module.functions.iter().map(|f| f.name.clone()).collect();
+        }
+
+        let heap_base = (DATA_BASE + data.len() as u32).next_multiple_of(SLOT_SIZE);
+        Layout {
+            imports,
+            functions,
+            helpers: helpers.into_iter().collect(),
+            strings,
+            data,
+            heap_base,
+            exports,
+        }
+    }
+
+    fn import_index(&self, import: Import) -> u32 {
+        self.imports
+            .iter()
+            .position(|candidate| *candidate == import)
+            .expect("import collected by layout") as u32
+    }
+
+    fn helper_index(&self, helper: Helper) -> u32 {
+        let position = self
+            .helpers
+            .iter()
+            .position(|candidate| *candidate == helper)
+            .expect("helper collected by layout");
+        (self.imports.len() + self.functions.len() + position) as u32
+    }
+
+    fn memory_pages(&self) -> u32 {
+        self.heap_base.div_ceil(PAGE_SIZE).max(1)
+    }
+}
+
+fn value_types(function: &ir::Function) -> HashMap<ValueId, TypeId> {
+    let params = function.params.iter().map(|param| (param.value, param.ty));
+    let insts = function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .map(|inst| (inst.id, inst.ty));
+    params.chain(insts).collect()
+}
+
+/// A function ready for encoding.
+struct WasmFunction {
+    name: String,
+    params: Vec<(String, ValType)>,
+    result: Option<ValType>,
+    locals: Vec<(String, ValType)>,
+    body: Vec<Op>,
+    export: bool,
+}
+
+impl WasmFunction {
+    fn signature(&self) -> Signature {
+        (self.params.iter().map(|(_, ty)| *ty).collect(), self.result)
+    }
+
+    fn local_name(&self, index: u32) -> &str {
+        let index = index as usize;
+        match self.params.get(index) {
+            Some((name, _)) => name,
+            None => &self.locals[index - self.params.len()].0,
+        }
+    }
+}
+
+/// Lowers one IR function into a dispatch loop.
+///
+/// The body is `loop (block … (block (br_table $pc)) code0) … codeN) end`:
+/// leaving the innermost `n - 1 - i` blocks lands on block `i`'s code, and
+/// every terminator stores its target's position in `$pc` and branches back
+/// to the loop. Phi nodes are locals assigned on the incoming edges.
+struct FunctionCompiler<'a> {
+    module: &'a ir::Module,
+    layout: &'a Layout,
+    function: &'a ir::Function,
+    values: HashMap<ValueId, (u32, TypeId)>,
+    positions: HashMap<BlockId, u32>,
+    pc: u32,
+    code: Vec<Op>,
+}
+
+impl<'a> FunctionCompiler<'a> {
+    fn compile(
+        module: &'a ir::Module,
+        layout: &'a Layout,
+        function: &'a ir::Function,
+    ) -> BackendResult<WasmFunction> {
+        let mut values = HashMap::new();
+        let mut params = Vec::with_capacity(function.params.len());
+        for (index, param) in function.params.iter().enumerate() {
+            values.insert(param.value, (index as u32, param.ty));
+            params.push((
+                format!("v{}", param.value.index()),
+                ValType::of(module, param.ty),
+            ));
+        }
+        let mut locals = Vec::new();
+        for inst in function.blocks.iter().flat_map(|block| &block.instructions) {
+            values.insert(inst.id, ((params.len() + locals.len()) as u32, inst.ty));
+            locals.push((
+                format!("v{}", inst.id.index()),
+                ValType::of(module, inst.ty),
+            ));
+        }
+        let pc = (params.len() + locals.len()) as u32;
+        locals.push(("pc".to_string(), ValType::I32));
+
+        let mut compiler = FunctionCompiler {
+            module,
+            layout,
+            function,
+            values,
+            positions: function
+                .blocks
+                .iter()
+                .enumerate()
+                .map(|(index, block)| (block.id, index as u32))
+                .collect(),
+            pc,
+            code: Vec::new(),
+        };
+        compiler.emit_body()?;
+
+        Ok(WasmFunction {
+            name: function.name.clone(),
+            params,
+            result: ValType::result_of(module, function.ret_type),
+            locals,
+            body: compiler.code,
+            export: layout.exports.contains(&function.name),
+        })
+    }
+
+    fn emit_body(&mut self) -> BackendResult<()> {
+        let count = self.function.blocks.len() as u32;
+        if count == 0 {
+            self.code.push(Op::Unreachable);
+            return Ok(());
+        }
+        if let [block] = self.function.blocks.as_slice()
+            && matches!(block.terminator, Terminator::Return(_))
+        {
+            // Straight-line code needs no dispatch.
+            for inst in &block.instructions {
+                self.emit_instruction(inst)?;
+            }
+            return self.emit_terminator(block, 0);
+        }
+        self.code.push(Op::Loop);
+        self.code.extend((0..count).map(|_| Op::Block));
+        self.code.push(Op::LocalGet(self.pc));
+        self.code.push(Op::BrTable((0..count).collect(), 0));
+        for (index, block) in self.function.blocks.iter().enumerate() {
+            self.code.push(Op::End);
+            let loop_depth = count - 1 - index as u32;
+            for inst in &block.instructions {
+                self.emit_instruction(inst)?;
+            }
+            self.emit_terminator(block, loop_depth)?;
+        }
+        self.code.push(Op::End);
+        self.code.push(Op::Unreachable);
+        Ok(())
+    }
+
+    fn value(&self, id: ValueId) -> BackendResult<(u32, TypeId)> {
+        self.values.get(&id).copied().ok_or_else(|| {
+            BackendError::internal(format!(
+                "function '{}' uses undefined value %{}",
+                self.function.name,
+                id.index()
+            ))
+        })
+    }
+
+    fn value_type(&self, id: ValueId) -> BackendResult<ValType> {
+        let (_, ty) = self.value(id)?;
+        Ok(ValType::of(self.module, ty))
+    }
+
+    /// Pushes `id`, which must have the wasm type `expected`.
+    fn get(&mut self, id: ValueId, expected: ValType) -> BackendResult<()> {
+        let (local, _) = self.value(id)?;
+        let actual = self.value_type(id)?;
+        if actual != expected {
+            return Err(BackendError::unsupported(format!(
+                "value %{} in '{}' is {} where {} is expected",
+                id.index(),
+                self.function.name,
+                actual.name(),
+                expected.name()
+            )));
+        }
+        self.code.push(Op::LocalGet(local));
+        Ok(())
+    }
+
+    /// Pops a `produced` value into the local of `id`.
+    fn set(&mut self, id: ValueId, produced: ValType) -> BackendResult<()> {
+        let (local, _) = self.value(id)?;
+        let declared = self.value_type(id)?;
+        if declared != produced {
+            return Err(BackendError::unsupported(format!(
+                "value %{} in '{}' is declared {} but computes {}",
+                id.index(),
+                self.function.name,
+                declared.name(),
+                produced.name()
+            )));
+        }
+        self.code.push(Op::LocalSet(local));
+        Ok(())
+    }
+
+    /// Fills the local of a unit-valued `id` with zero of its declared type,
+    /// which may be untyped for calls whose result the checker never saw.
+    fn set_unit(&mut self, id: ValueId) -> BackendResult<()> {
+        let declared = self.value_type(id)?;
+        self.code.push(declared.zero());
+        self.set(id, declared)
+    }
+
+    fn emit_instruction(&mut self, inst: &ir::Instruction) -> BackendResult<()> {
+        match &inst.kind {
+            InstKind::Literal(lit) => {
+                let produced = match lit {
+                    Literal::Int(value) => {
+                        self.code.push(Op::I64Const(*value));
+                        ValType::I64
+                    }
+                    Literal::Float(value) => {
+                        self.code.push(Op::F64Const(*value));
+                        ValType::F64
+                    }
+                    Literal::Bool(value) => {
+                        self.code.push(Op::I32Const(i32::from(*value)));
+                        ValType::I32
+                    }
+                    Literal::String(value) => {
+                        self.code
+                            .push(Op::I32Const(self.layout.strings[value] as i32));
+                        ValType::I32
+                    }
+                    Literal::Unit => {
+                        self.code.push(Op::I32Const(0));
+                        ValType::I32
+                    }
+                };
+                self.set(inst.id, produced)
+            }
+            InstKind::Binary { op, lhs, rhs } => self.emit_binary(inst.id, *op, *lhs, *rhs),
+            InstKind::Call { func, args } => self.emit_call(inst.id, func, args),
+            InstKind::Record { fields, .. } => {
+                let Type::Record(record) = self.module.type_of(inst.ty) else {
+                    return Err(BackendError::Internal(
+                        "record literal lowered with non-record type".into(),
+                    ));
+                };
+                let (record_local, _) = self.value(inst.id)?;
+                self.code.push(Op::I32Const(
+                    (record.fields.len() as u32 * SLOT_SIZE) as i32,
+                ));
+                self.code
+                    .push(Op::Call(self.layout.helper_index(Helper::Alloc)));
+                self.set(inst.id, ValType::I32)?;
+                for (slot, field) in record.fields.iter().enumerate() {
+                    let Some((_, value)) = fields.iter().find(|(name, _)| name == &field.name)
+                    else {
+                        let record_name = record.name.as_deref().unwrap_or("<anonymous record>");
+                        return Err(BackendError::Unsupported(format!(
+                            "record literal for '{record_name}' is missing field '{}'",
+                            field.name
+                        )));
+                    };
+                    let ty = ValType::of(self.module, field.ty);
+                    self.code.push(Op::LocalGet(record_local));
+                    self.get(*value, ty)?;
+                    self.code.push(Op::Store(ty, slot as u32 * SLOT_SIZE));
+                }
+                for (name, _) in fields {
+                    if record.field(name).is_none() {
+                        return Err(BackendError::Unsupported(format!(
+                            "record literal references unknown field '{}'",
+                            name
+                        )));
+                    }
+                }
+                Ok(())
+            }
+            InstKind::Field { base, name } => {
+                let (_, base_ty) = self.value(*base)?;
+                let Type::Record(record) = self.module.type_of(base_ty) else {
+                    return Err(BackendError::unsupported(format!(
+                        "field '{name}' read from a value that is not a record"
+                    )));
+                };
+                let Some(slot) = record.fields.iter().position(|field| &field.name == name) else {
+                    return Err(BackendError::unsupported(format!(
+                        "record has no field '{name}'"
+                    )));
+                };
+                let ty = ValType::of(self.module, record.fields[slot].ty);
+                self.get(*base, ValType::I32)?;
+                self.code.push(Op::Load(ty, slot as u32 * SLOT_SIZE));
+                self.set(inst.id, ty)
+            }
+            InstKind::Convert { value } => {
+                let (_, from) = self.value(*value)?;
+                let (op, from, to) = match (self.module.type_of(from), self.module.type_of(inst.ty))
+                {
+                    (Type::Int, Type::Float) => (F64_CONVERT_I64_S, ValType::I64, ValType::F64),
+                    (Type::Float, Type::Int) => (I64_TRUNC_F64_S, ValType::F64, ValType::I64),
+                    (from, to) => {
+                        return Err(BackendError::unsupported(format!(
+                            "conversion from {} to {} cannot be lowered by the wasm backend",
+                            super::type_label(from),
+                            super::type_label(to)
+                        )));
+                    }
+                };
+                self.get(*value, from)?;
+                self.code.push(op);
+                self.set(inst.id, to)
+            }
+            InstKind::Path(path) => Err(BackendError::Unsupported(format!(
+                "path expression '{}' cannot be lowered by the wasm backend",
+                path.segments.join("::")
+            ))),
+            InstKind::Phi { .. } => Ok(()),
+            InstKind::List { elements } => {
+                let element = self.list_element_type(inst.ty)?;
+                let (list_local, _) = self.value(inst.id)?;
+                self.code.push(Op::I32Const(elements.len() as i32));
+                self.code
+                    .push(Op::Call(self.layout.helper_index(Helper::ListNew)));
+                self.set(inst.id, ValType::I32)?;
+                for value in elements {
+                    self.code.push(Op::LocalGet(list_local));
+                    self.emit_append(*value, element)?;
+                }
+                Ok(())
+            }
+            InstKind::ListLen { list } => {
+                self.get(*list, ValType::I32)?;
+                self.code.push(Op::Load(ValType::I32, 0));
+                self.code.push(I64_EXTEND_I32_U);
+                self.set(inst.id, ValType::I64)
+            }
+            InstKind::ListIndex { list, index } => {
+                let (_, list_ty) = self.value(*list)?;
+                let element = self.list_element_type(list_ty)?;
+                self.get(*list, ValType::I32)?;
+                self.get(*index, ValType::I64)?;
+                self.code
+                    .push(Op::Call(self.layout.helper_index(Helper::ListAt)));
+                self.code.push(Op::Load(element, 0));
+                self.set(inst.id, element)
+            }
+            InstKind::ListPush { list, value } => {
+                let (_, list_ty) = self.value(*list)?;
+                let element = self.list_element_type(list_ty)?;
+                self.get(*list, ValType::I32)?;
+                self.emit_append(*value, element)?;
+                self.set_unit(inst.id)
+            }
+            // The bump allocator never frees, so counts are moot.
+            InstKind::Retain { .. } | InstKind::Release { .. } => self.set_unit(inst.id),
+            InstKind::Variant { .. }
+            | InstKind::IsVariant { .. }
+            | InstKind::Payload { .. }
+            | InstKind::Trap { .. } => Err(BackendError::Unsupported(
+                "sum types cannot be lowered to WebAssembly".into(),
+            )),
+        }
+    }
+
+    fn list_element_type(&self, ty: TypeId) -> BackendResult<ValType> {
+        match self.module.type_of(ty) {
+            Type::List(element) => Ok(ValType::of(self.module, *element)),
+            _ => Err(BackendError::Internal(
+                "list operation lowered with non-list type".into(),
+            )),
+        }
+    }
+
+    /// Appends `value` to the list on top of the stack.
+    fn emit_append(&mut self, value: ValueId, element: ValType) -> BackendResult<()> {
+        self.code
+            .push(Op::Call(self.layout.helper_index(Helper::ListSlot)));
+        self.get(value, element)?;
+        self.code.push(Op::Store(element, 0));
+        Ok(())
+    }
+
+    fn emit_binary(
+        &mut self,
+        id: ValueId,
+        op: BinaryOp,
+        lhs: ValueId,
+        rhs: ValueId,
+    ) -> BackendResult<()> {
+        let (_, lhs_ty) = self.value(lhs)?;
+        let operand = ValType::of(self.module, lhs_ty);
+        let unsupported = || {
+            Err(BackendError::unsupported(format!(
+                "operator '{op}' on {} values",
+                type_label(self.module.type_of(lhs_ty))
+            )))
+        };
+        let (name, opcode, produced) = match (self.module.type_of(lhs_ty), op) {
+            (Type::String, BinaryOp::Eq | BinaryOp::Ne) => {
+                self.get(lhs, ValType::I32)?;
+                self.get(rhs, ValType::I32)?;
+                self.code
+                    .push(Op::Call(self.layout.helper_index(Helper::StrEq)));
+                if op == BinaryOp::Ne {
+                    self.code.push(I32_EQZ);
+                }
+                return self.set(id, ValType::I32);
+            }
+            (Type::Bool, _) => match op {
+                BinaryOp::Eq => ("i32.eq", 0x46, ValType::I32),
+                BinaryOp::Ne => ("i32.ne", 0x47, ValType::I32),
+                BinaryOp::And => ("i32.and", 0x71, ValType::I32),
+                BinaryOp::Or => ("i32.or", 0x72, ValType::I32),
+                _ => return unsupported(),
+            },
+            (Type::Float, _) => match op {
+                BinaryOp::Add => ("f64.add", 0xa0, ValType::F64),
+                BinaryOp::Sub => ("f64.sub", 0xa1, ValType::F64),
+                BinaryOp::Mul => ("f64.mul", 0xa2, ValType::F64),
+                BinaryOp::Div => ("f64.div", 0xa3, ValType::F64),
+                BinaryOp::Eq => ("f64.eq", 0x61, ValType::I32),
+                BinaryOp::Ne => ("f64.ne", 0x62, ValType::I32),
+                BinaryOp::Lt => ("f64.lt", 0x63, ValType::I32),
+                BinaryOp::Gt => ("f64.gt", 0x64, ValType::I32),
+                BinaryOp::Le => ("f64.le", 0x65, ValType::I32),
+                BinaryOp::Ge => ("f64.ge", 0x66, ValType::I32),
+                BinaryOp::Mod | BinaryOp::And | BinaryOp::Or => return unsupported(),
+            },
+            (_, _) if operand == ValType::I64 => match op {
+                BinaryOp::Add => ("i64.add", 0x7c, ValType::I64),
+                BinaryOp::Sub => ("i64.sub", 0x7d, ValType::I64),
+                BinaryOp::Mul => ("i64.mul", 0x7e, ValType::I64),
+                BinaryOp::Div => ("i64.div_s", 0x7f, ValType::I64),
+                BinaryOp::Mod => ("i64.rem_s", 0x81, ValType::I64),
+                BinaryOp::Eq => ("i64.eq", 0x51, ValType::I32),
+                BinaryOp::Ne => ("i64.ne", 0x52, ValType::I32),
+                BinaryOp::Lt => ("i64.lt_s", 0x53, ValType::I32),
+                BinaryOp::Gt => ("i64.gt_s", 0x55, ValType::I32),
+                BinaryOp::Le => ("i64.le_s", 0x57, ValType::I32),
+                BinaryOp::Ge => ("i64.ge_s", 0x59, ValType::I32),
+                BinaryOp::And | BinaryOp::Or => return unsupported(),
+            },
+            _ => return unsupported(),
+        };
+        self.get(lhs, operand)?;
+        self.get(rhs, operand)?;
+        self.code.push(Op::Plain(name, opcode));
+        self.set(id, produced)
+    }
+
+    fn emit_call(
+        &mut self,
+        id: ValueId,
+        func: &ir::FuncRef,
+        args: &[ValueId],
+    ) -> BackendResult<()> {
+        if let ir::FuncRef::Method(name) = func
+            && let Some(import) = Import::for_method(name)
+        {
+            return self.emit_runtime_call(id, name, import, args);
+        }
+        let (_, callee) = resolve_callee(self.module, func, args.len(), "by the wasm backend")?;
+        for (arg, param) in args.iter().zip(&callee.params) {
+            self.get(*arg, ValType::of(self.module, param.ty))?;
+        }
+        self.code
+            .push(Op::Call(self.layout.functions[&callee.name]));
+        let (_, declared) = self.value(id)?;
+        match ValType::result_of(self.module, callee.ret_type) {
+            // The caller treats the result as unit, so it is discarded.
+            Some(_) if matches!(self.module.type_of(declared), Type::Unit) => {
+                self.code.push(DROP);
+                self.set_unit(id)
+            }
+            Some(result) => self.set(id, result),
+            None => self.set_unit(id),
+        }
+    }
+
+    fn emit_runtime_call(
+        &mut self,
+        id: ValueId,
+        name: &str,
+        import: Import,
+        args: &[ValueId],
+    ) -> BackendResult<()> {
+        let index = self.layout.import_index(import);
+        match import {
+            Import::WriteLine => {
+                let Some(value) = args.get(1) else {
+                    return Err(BackendError::Internal(format!(
+                        "method '{name}' expected value argument"
+                    )));
+                };
+                let (local, ty) = self.value(*value)?;
+                if !matches!(self.module.type_of(ty), Type::String) {
+                    return Err(BackendError::unsupported(format!(
+                        "method '{name}' can only print strings in the wasm backend"
+                    )));
+                }
+                // The import takes the bytes after the length prefix.
+                self.code.extend([
+                    Op::LocalGet(local),
+                    Op::I32Const(4),
+                    I32_ADD,
+                    Op::LocalGet(local),
+                    Op::Load(ValType::I32, 0),
+                    Op::Call(index),
+                ]);
+                self.set_unit(id)
+            }
+            Import::NowMillis => {
+                self.code.push(Op::Call(index));
+                self.set(id, ValType::I64)
+            }
+        }
+    }
+
+    fn emit_terminator(&mut self, block: &ir::BasicBlock, loop_depth: u32) -> BackendResult<()> {
+        match &block.terminator {
+            Terminator::Return(value) => {
+                let result = ValType::result_of(self.module, self.function.ret_type);
+                match (value, result) {
+                    (Some(value), Some(result)) => self.get(*value, result)?,
+                    (None, Some(result)) => self.code.push(result.zero()),
+                    (_, None) => {}
+                }
+                self.code.push(Op::Return);
+            }
+            Terminator::Jump(target) => self.emit_edge(block.id, *target, loop_depth)?,
+            Terminator::Branch {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                self.code.push(Op::Block);
+                self.get(*condition, ValType::I32)?;
+                self.code.push(I32_EQZ);
+                self.code.push(Op::BrIf(0));
+                self.emit_edge(block.id, *then_block, loop_depth + 1)?;
+                self.code.push(Op::End);
+                self.emit_edge(block.id, *else_block, loop_depth)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Assigns the phis of `to` for the edge from `from`, then re-enters the
+    /// dispatch loop at `to`. All incoming values are read before any phi is
+    /// written, so phis that feed each other swap correctly.
+    fn emit_edge(&mut self, from: BlockId, to: BlockId, loop_depth: u32) -> BackendResult<()> {
+        let Some(target) = self.function.blocks.iter().find(|block| block.id == to) else {
+            return Err(BackendError::internal(format!(
+                "function '{}' jumps to missing block {}",
+                self.function.name,
+                to.index()
+            )));
+        };
+        let mut copies = Vec::new();
+        for inst in &target.instructions {
+            let InstKind::Phi { incomings } = &inst.kind else {
+                continue;
+            };
+            if let Some((_, value)) = incomings.iter().find(|(pred, _)| *pred == from) {
+                let ty = self.value_type(inst.id)?;
+                self.get(*value, ty)?;
+                copies.push(inst.id);
+            }
+        }
+        for phi in copies.into_iter().rev() {
+            let ty = self.value_type(phi)?;
+            self.set(phi, ty)?;
+        }
+        self.code.push(Op::I32Const(self.positions[&to] as i32));
+        self.code.push(Op::LocalSet(self.pc));
+        self.code.push(Op::Br(loop_depth));
+        Ok(())
+    }
+}
+
+/// The module's functions in index order (imports excluded), with the
+/// layout describing everything else.
+struct EncodedModule<'a> {
+    name: String,
+    layout: &'a Layout,
+    functions: &'a [WasmFunction],
+}
+
+impl EncodedModule<'_> {
+    fn uses_heap(&self) -> bool {
+        self.layout.helpers.contains(&Helper::Alloc)
+    }
+
+    fn types(&self) -> Vec<Signature> {
+        let mut types: Vec<Signature> = Vec::new();
+        let signatures = self
+            .layout
+            .imports
+            .iter()
+            .map(|import| import.signature())
+            .chain(self.functions.iter().map(WasmFunction::signature));
+        for signature in signatures {
+            if !types.contains(&signature) {
+                types.push(signature);
+            }
+        }
+        types
+    }
+
+    fn type_index(types: &[Signature], signature: &Signature) -> u32 {
+        types
+            .iter()
+            .position(|candidate| candidate == signature)
+            .expect("signature collected") as u32
+    }
+
+    fn function_name(&self, index: u32) -> String {
+        let imports = self.layout.imports.len();
+        match (index as usize).checked_sub(imports) {
+            None => format!(
+                "{RUNTIME_IMPORT_MODULE}.{}",
+                self.layout.imports[index as usize].field()
+            ),
+            Some(position) => self.functions[position].name.clone(),
+        }
+    }
+
+    fn binary(&self) -> Vec<u8> {
+        let mut out = b"\0asm".to_vec();
+        out.extend(1u32.to_le_bytes());
+
+        let types = self.types();
+        push_section(
+            &mut out,
+            1,
+            types.iter().map(|(params, result)| {
+                let mut entry = vec![0x60];
+                push_u32(&mut entry, params.len() as u32);
+                entry.extend(params.iter().map(|ty| ty.code()));
+                push_u32(&mut entry, u32::from(result.is_some()));
+                entry.extend(result.iter().map(|ty| ty.code()));
+                entry
+            }),
+        );
+        push_section(
+            &mut out,
+            2,
+            self.layout.imports.iter().map(|import| {
+                let mut entry = Vec::new();
+                push_name(&mut entry, RUNTIME_IMPORT_MODULE);
+                push_name(&mut entry, import.field());
+                entry.push(0x00);
+                push_u32(&mut entry, Self::type_index(&types, &import.signature()));
+                entry
+            }),
+        );
+        push_section(
+            &mut out,
+            3,
+            self.functions.iter().map(|function| {
+                let mut entry = Vec::new();
+                push_u32(&mut entry, Self::type_index(&types, &function.signature()));
+                entry
+            }),
+        );
+        push_section(
+            &mut out,
+            5,
+            [{
+                let mut entry = vec![0x00];
+                push_u32(&mut entry, self.layout.memory_pages());
+                entry
+            }],
+        );
+        if self.uses_heap() {
+            push_section(
+                &mut out,
+                6,
+                [{
+                    let mut entry = vec![ValType::I32.code(), 0x01];
+                    encode_op(&mut entry, &Op::I32Const(self.layout.heap_base as i32));
+                    entry.push(0x0b);
+                    entry
+                }],
+            );
+        }
+        let imports = self.layout.imports.len() as u32;
+        let exports = std::iter::once({
+            let mut entry = Vec::new();
+            push_name(&mut entry, "memory");
+            entry.extend([0x02, 0x00]);
+            entry
+        })
+        .chain(
+            self.functions
+                .iter()
+                .enumerate()
+                .filter(|(_, function)| function.export)
+                .map(|(position, function)| {
+                    let mut entry = Vec::new();
+                    push_name(&mut entry, &function.name);
+                    entry.push(0x00);
+                    push_u32(&mut entry, imports + position as u32);
+                    entry
+                }),
+        );
+        push_section(&mut out, 7, exports);
+        push_section(
+            &mut out,
+            10,
+            self.functions.iter().map(|function| {
+                let mut body = Vec::new();
+                let mut runs: Vec<(u32, ValType)> = Vec::new();
+                for (_, ty) in &function.locals {
+                    match runs.last_mut() {
+                        Some((count, last)) if last == ty => *count += 1,
+                        _ => runs.push((1, *ty)),
+                    }
+                }
+                push_u32(&mut body, runs.len() as u32);
+                for (count, ty) in runs {
+                    push_u32(&mut body, count);
+                    body.push(ty.code());
+                }
+                for op in &function.body {
+                    encode_op(&mut body, op);
+                }
+                body.push(0x0b);
+                let mut entry = Vec::new();
+                push_u32(&mut entry, body.len() as u32);
+                entry.extend(body);
+                entry
+            }),
+        );
+        if !self.layout.data.is_empty() {
+            push_section(
+                &mut out,
+                11,
+                [{
+                    let mut entry = vec![0x00];
+                    encode_op(&mut entry, &Op::I32Const(DATA_BASE as i32));
+                    entry.push(0x0b);
+                    push_u32(&mut entry, self.layout.data.len() as u32);
+                    entry.extend(&self.layout.data);
+                    entry
+                }],
+            );
+        }
+        out
+    }
+
+    fn text(&self) -> String {
+        let mut out = String::new();
+        if self.name.is_empty() {
+            writeln!(out, "(module").unwrap();
+        } else {
+            writeln!(out, "(module ${}", self.name).unwrap();
+        }
+        for import in &self.layout.imports {
+            let (params, result) = import.signature();
+            writeln!(
+                out,
+                "  (import \"{RUNTIME_IMPORT_MODULE}\" \"{field}\" (func ${RUNTIME_IMPORT_MODULE}.{field}{}))",
+                text_signature(&params, result),
+                field = import.field()
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            "  (memory (export \"memory\") {})",
+            self.layout.memory_pages()
+        )
+        .unwrap();
+        if self.uses_heap() {
+            writeln!(
+                out,
+                "  (global $heap (mut i32) (i32.const {}))",
+                self.layout.heap_base
+            )
+            .unwrap();
+        }
+        if !self.layout.data.is_empty() {
+            writeln!(
+                out,
+                "  (data (i32.const {DATA_BASE}) \"{}\")",
+                escape_bytes(&self.layout.data)
+            )
+            .unwrap();
+        }
+        for function in self.functions {
+            write!(out, "  (func ${}", function.name).unwrap();
+            if function.export {
+                write!(out, " (export \"{}\")", function.name).unwrap();
+            }
+            for (name, ty) in &function.params {
+                write!(out, " (param ${name} {})", ty.name()).unwrap();
+            }
+            if let Some(result) = function.result {
+                write!(out, " (result {})", result.name()).unwrap();
+            }
+            writeln!(out).unwrap();
+            for (name, ty) in &function.locals {
+                writeln!(out, "    (local ${name} {})", ty.name()).unwrap();
+            }
+            let mut depth = 2;
+            for op in &function.body {
+                if *op == Op::End {
+                    depth -= 1;
+                }
+                writeln!(out, "{}{}", "  ".repeat(depth), self.text_op(function, op)).unwrap();
+                if matches!(op, Op::Block | Op::Loop) {
+                    depth += 1;
+                }
+            }
+            writeln!(out, "  )").unwrap();
+        }
+        writeln!(out, ")").unwrap();
+        out
+    }
+
+    fn text_op(&self, function: &WasmFunction, op: &Op) -> String {
+        match op {
+            Op::Plain(name, _) => name.to_string(),
+            Op::Load(ty, offset) => with_offset(&format!("{}.load", ty.name()), *offset),
+            Op::Load8U(offset) => with_offset("i32.load8_u", *offset),
+            Op::Store(ty, offset) => with_offset(&format!("{}.store", ty.name()), *offset),
+            Op::I32Const(value) => format!("i32.const {value}"),
+            Op::I64Const(value) => format!("i64.const {value}"),
+            Op::F64Const(value) => format!("f64.const {}", text_float(*value)),
+            Op::LocalGet(index) => format!("local.get ${}", function.local_name(*index)),
+            Op::LocalSet(index) => format!("local.set ${}", function.local_name(*index)),
+            Op::LocalTee(index) => format!("local.tee ${}", function.local_name(*index)),
+            Op::GlobalGet(_) => "global.get $heap".to_string(),
+            Op::GlobalSet(_) => "global.set $heap".to_string(),
+            Op::Call(callee) => format!("call ${}", self.function_name(*callee)),
+            Op::Block => "block".to_string(),
+            Op::Loop => "loop".to_string(),
+            Op::End => "end".to_string(),
+            Op::Br(depth) => format!("br {depth}"),
+            Op::BrIf(depth) => format!("br_if {depth}"),
+            Op::BrTable(labels, default) => {
+                let mut text = "br_table".to_string();
+                for label in labels.iter().chain([default]) {
+                    write!(text, " {label}").unwrap();
+                }
+                text
+            }
+            Op::Return => "return".to_string(),
+            Op::Unreachable => "unreachable".to_string(),
+            Op::MemorySize => "memory.size".to_string(),
+            Op::MemoryGrow => "memory.grow".to_string(),
+        }
+    }
+}
+
+fn encode_op(out: &mut Vec<u8>, op: &Op) {
+    match op {
+        Op::Plain(_, opcode) => out.push(*opcode),
+        Op::Load(ty, offset) => {
+            let (opcode, align) = match ty {
+                ValType::I32 => (0x28, 2),
+                ValType::I64 => (0x29, 3),
+                ValType::F64 => (0x2b, 3),
+            };
+            push_memarg(out, opcode, align, *offset);
+        }
+        Op::Load8U(offset) => push_memarg(out, 0x2d, 0, *offset),
+        Op::Store(ty, offset) => {
+            let (opcode, align) = match ty {
+                ValType::I32 => (0x36, 2),
+                ValType::I64 => (0x37, 3),
+                ValType::F64 => (0x39, 3),
+            };
+            push_memarg(out, opcode, align, *offset);
+        }
+        Op::I32Const(value) => {
+            out.push(0x41);
+            push_i64(out, i64::from(*value));
+        }
+        Op::I64Const(value) => {
+            out.push(0x42);
+            push_i64(out, *value);
+        }
+        Op::F64Const(value) => {
+            out.push(0x44);
+            out.extend(value.to_le_bytes());
+        }
+        Op::LocalGet(index) => push_indexed(out, 0x20, *index),
+        Op::LocalSet(index) => push_indexed(out, 0x21, *index),
+        Op::LocalTee(index) => push_indexed(out, 0x22, *index),
+        Op::GlobalGet(index) => push_indexed(out, 0x23, *index),
+        Op::GlobalSet(index) => push_indexed(out, 0x24, *index),
+        Op::Call(index) => push_indexed(out, 0x10, *index),
+        Op::Block => out.extend([0x02, 0x40]),
+        Op::Loop => out.extend([0x03, 0x40]),
+        Op::End => out.push(0x0b),
+        Op::Br(depth) => push_indexed(out, 0x0c, *depth),
+        Op::BrIf(depth) => push_indexed(out, 0x0d, *depth),
+        Op::BrTable(labels, default) => {
+            out.push(0x0e);
+            push_u32(out, labels.len() as u32);
+            for label in labels {
+                push_u32(out, *label);
+            }
+            push_u32(out, *default);
+        }
+        Op::Return => out.push(0x0f),
+        Op::Unreachable => out.push(0x00),
+        Op::MemorySize => out.extend([0x3f, 0x00]),
+        Op::MemoryGrow => out.extend([0x40, 0x00]),
+    }
+}
+
+fn push_section<I: IntoIterator<Item = Vec<u8>>>(out: &mut Vec<u8>, id: u8, entries: I) {
+    let entries: Vec<Vec<u8>> = entries.into_iter().collect();
+    if entries.is_empty() {
+        return;
+    }
+    let mut content = Vec::new();
+    push_u32(&mut content, entries.len() as u32);
+    for entry in entries {
+        content.extend(entry);
+    }
+    out.push(id);
+    push_u32(out, content.len() as u32);
+    out.extend(content);
+}
+
+fn push_indexed(out: &mut Vec<u8>, opcode: u8, index: u32) {
+    out.push(opcode);
+    push_u32(out, index);
+}
+
+fn push_memarg(out: &mut Vec<u8>, opcode: u8, align: u32, offset: u32) {
+    out.push(opcode);
+    push_u32(out, align);
+    push_u32(out, offset);
+}
+
+fn push_name(out: &mut Vec<u8>, name: &str) {
+    push_u32(out, name.len() as u32);
+    out.extend(name.as_bytes());
+}
+
+/// Unsigned LEB128.
+fn push_u32(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Signed LEB128.
+fn push_i64(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let sign_clear = byte & 0x40 == 0;
+        if (value == 0 && sign_clear) || (value == -1 && !sign_clear) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn text_signature(params: &[ValType], result: Option<ValType>) -> String {
+    let mut text = String::new();
+    if !params.is_empty() {
+        text.push_str(" (param");
+        for param in params {
+            write!(text, " {}", param.name()).unwrap();
+        }
+        text.push(')');
+    }
+    if let Some(result) = result {
+        write!(text, " (result {})", result.name()).unwrap();
+    }
+    text
+}
+
+fn with_offset(name: &str, offset: u32) -> String {
+    if offset == 0 {
+        name.to_string()
+    } else {
+        format!("{name} offset={offset}")
+    }
+}
+
+fn text_float(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        format!("{value:?}")
+    }
+}
+
+fn escape_bytes(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' => write!(text, "\\{:02x}", byte).unwrap(),
+            0x20..=0x7e => text.push(*byte as char),
+            _ => write!(text, "\\{:02x}", byte).unwrap(),
+        }
+    }
+    text
+}
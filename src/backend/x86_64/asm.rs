@@ -0,0 +1,369 @@
+//! Encoder for the small subset of x86-64 the code generator emits.
+//!
+//! Every instruction works on full 64-bit registers, and memory operands are
+//! always `[base + disp32]` or RIP-relative. Jumps to local labels are
+//! patched when the label is bound; references to other symbols become
+//! [`Reloc`]s that the ELF writer either emits or resolves.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reg {
+    Rax = 0,
+    Rcx,
+    Rdx,
+    Rbx,
+    Rsp,
+    Rbp,
+    Rsi,
+    Rdi,
+    R8,
+    R9,
+    R10,
+    R11,
+    R12,
+    R13,
+    R14,
+    R15,
+}
+
+impl Reg {
+    fn low(self) -> u8 {
+        self as u8 & 7
+    }
+
+    fn ext(self) -> bool {
+        self as u8 >= 8
+    }
+}
+
+/// Condition codes, numbered as in the `Jcc`/`SETcc` opcodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cond {
+    /// Unsigned `>=`.
+    Ae = 0x3,
+    E = 0x4,
+    Ne = 0x5,
+    /// Unsigned `>`.
+    A = 0x7,
+    L = 0xC,
+    Ge = 0xD,
+    Le = 0xE,
+    G = 0xF,
+}
+
+/// Two-operand ALU instructions in their `r/m64, r64` form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alu {
+    Add = 0x01,
+    Or = 0x09,
+    And = 0x21,
+    Sub = 0x29,
+    Xor = 0x31,
+    Cmp = 0x39,
+    Test = 0x85,
+}
+
+impl Alu {
+    /// The `/digit` of the `81 /digit id` immediate form.
+    fn digit(self) -> u8 {
+        match self {
+            Alu::Add => 0,
+            Alu::Or => 1,
+            Alu::And => 4,
+            Alu::Sub => 5,
+            Alu::Xor => 6,
+            Alu::Cmp => 7,
+            Alu::Test => unreachable!("test has no 81 form"),
+        }
+    }
+}
+
+/// What a relocated 32-bit field points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symbol {
+    /// Entry of the code symbol with this index.
+    Code(usize),
+    /// Offset into `.rodata`.
+    Rodata(u32),
+    /// Offset into `.bss`.
+    Bss(u32),
+}
+
+/// A PC-relative 32-bit field at `offset` in `.text` that must hold
+/// `symbol + addend - offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reloc {
+    pub offset: usize,
+    pub symbol: Symbol,
+    pub addend: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Label(usize);
+
+#[derive(Debug, Default)]
+pub struct Assembler {
+    pub code: Vec<u8>,
+    pub relocs: Vec<Reloc>,
+    labels: Vec<Option<usize>>,
+    /// rel32 fields waiting for their label to be bound.
+    pending: Vec<(usize, Label)>,
+}
+
+impl Assembler {
+    pub fn offset(&self) -> usize {
+        self.code.len()
+    }
+
+    pub fn label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    pub fn bind(&mut self, label: Label) {
+        let here = self.code.len();
+        self.labels[label.0] = Some(here);
+        self.pending.retain(|(field, pending)| {
+            if *pending != label {
+                return true;
+            }
+            let rel = here as i64 - (*field as i64 + 4);
+            self.code[*field..*field + 4].copy_from_slice(&(rel as i32).to_le_bytes());
+            false
+        });
+    }
+
+    /// Whether every referenced label has been bound.
+    pub fn is_resolved(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Pads with `int3` up to a multiple of `align`.
+    pub fn align(&mut self, align: usize) {
+        while !self.code.len().is_multiple_of(align) {
+            self.code.push(0xCC);
+        }
+    }
+
+    fn rex(&mut self, r: Reg, b: Reg) {
+        self.code
+            .push(0x48 | (u8::from(r.ext()) << 2) | u8::from(b.ext()));
+    }
+
+    fn modrm_reg(&mut self, reg: u8, rm: Reg) {
+        self.code.push(0xC0 | (reg << 3) | rm.low());
+    }
+
+    fn modrm_mem(&mut self, reg: u8, base: Reg, disp: i32) {
+        self.code.push(0x80 | (reg << 3) | base.low());
+        if base.low() == Reg::Rsp.low() {
+            self.code.push(0x24);
+        }
+        self.code.extend_from_slice(&disp.to_le_bytes());
+    }
+
+    /// RIP-relative operand whose displacement is relocated to `symbol`.
+    fn modrm_rip(&mut self, reg: u8, symbol: Symbol) {
+        self.code.push(0x05 | (reg << 3));
+        self.reloc32(symbol, 0);
+    }
+
+    fn reloc32(&mut self, symbol: Symbol, addend: i64) {
+        // Every relocated field ends its instruction, so the CPU measures
+        // from four bytes past the field.
+        self.relocs.push(Reloc {
+            offset: self.code.len(),
+            symbol,
+            addend: addend - 4,
+        });
+        self.code.extend_from_slice(&[0; 4]);
+    }
+
+    pub fn mov(&mut self, dst: Reg, src: Reg) {
+        if dst != src {
+            self.alu_raw(0x89, dst, src);
+        }
+    }
+
+    pub fn mov_imm(&mut self, dst: Reg, value: i64) {
+        if let Ok(value) = i32::try_from(value) {
+            self.rex(Reg::Rax, dst);
+            self.code.push(0xC7);
+            self.modrm_reg(0, dst);
+            self.code.extend_from_slice(&value.to_le_bytes());
+        } else {
+            self.rex(Reg::Rax, dst);
+            self.code.push(0xB8 + dst.low());
+            self.code.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    /// `dst = [base + disp]`
+    pub fn load(&mut self, dst: Reg, base: Reg, disp: i32) {
+        self.rex(dst, base);
+        self.code.push(0x8B);
+        self.modrm_mem(dst.low(), base, disp);
+    }
+
+    /// `[base + disp] = src`
+    pub fn store(&mut self, base: Reg, disp: i32, src: Reg) {
+        self.rex(src, base);
+        self.code.push(0x89);
+        self.modrm_mem(src.low(), base, disp);
+    }
+
+    /// Zero-extends the byte at `[base + disp]` into `dst`.
+    pub fn load_byte(&mut self, dst: Reg, base: Reg, disp: i32) {
+        self.rex(dst, base);
+        self.code.extend_from_slice(&[0x0F, 0xB6]);
+        self.modrm_mem(dst.low(), base, disp);
+    }
+
+    pub fn lea(&mut self, dst: Reg, base: Reg, disp: i32) {
+        self.rex(dst, base);
+        self.code.push(0x8D);
+        self.modrm_mem(dst.low(), base, disp);
+    }
+
+    pub fn lea_symbol(&mut self, dst: Reg, symbol: Symbol) {
+        self.rex(dst, Reg::Rax);
+        self.code.push(0x8D);
+        self.modrm_rip(dst.low(), symbol);
+    }
+
+    pub fn load_symbol(&mut self, dst: Reg, symbol: Symbol) {
+        self.rex(dst, Reg::Rax);
+        self.code.push(0x8B);
+        self.modrm_rip(dst.low(), symbol);
+    }
+
+    pub fn store_symbol(&mut self, symbol: Symbol, src: Reg) {
+        self.rex(src, Reg::Rax);
+        self.code.push(0x89);
+        self.modrm_rip(src.low(), symbol);
+    }
+
+    fn alu_raw(&mut self, opcode: u8, dst: Reg, src: Reg) {
+        self.rex(src, dst);
+        self.code.push(opcode);
+        self.modrm_reg(src.low(), dst);
+    }
+
+    /// `dst = dst <op> src`, or just the flags for `cmp` and `test`.
+    pub fn alu(&mut self, op: Alu, dst: Reg, src: Reg) {
+        self.alu_raw(op as u8, dst, src);
+    }
+
+    pub fn alu_imm(&mut self, op: Alu, dst: Reg, value: i32) {
+        self.rex(Reg::Rax, dst);
+        self.code.push(0x81);
+        self.modrm_reg(op.digit(), dst);
+        self.code.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn imul(&mut self, dst: Reg, src: Reg) {
+        self.rex(dst, src);
+        self.code.extend_from_slice(&[0x0F, 0xAF]);
+        self.modrm_reg(dst.low(), src);
+    }
+
+    /// Signed `rdx:rax / divisor`, leaving the quotient in `rax` and the
+    /// remainder in `rdx`. Sign-extends `rax` first.
+    pub fn idiv(&mut self, divisor: Reg) {
+        self.code.extend_from_slice(&[0x48, 0x99]);
+        self.rex(Reg::Rax, divisor);
+        self.code.push(0xF7);
+        self.modrm_reg(7, divisor);
+    }
+
+    /// Unsigned `rdx:rax / divisor`; the caller zeroes `rdx`.
+    pub fn div(&mut self, divisor: Reg) {
+        self.rex(Reg::Rax, divisor);
+        self.code.push(0xF7);
+        self.modrm_reg(6, divisor);
+    }
+
+    /// `[base + disp] = low byte of src`
+    pub fn store_byte(&mut self, base: Reg, disp: i32, src: Reg) {
+        // A REX prefix selects `sil`/`dil` rather than `dh`/`bh`.
+        self.code
+            .push(0x40 | (u8::from(src.ext()) << 2) | u8::from(base.ext()));
+        self.code.push(0x88);
+        self.modrm_mem(src.low(), base, disp);
+    }
+
+    pub fn store_byte_imm(&mut self, base: Reg, disp: i32, value: u8) {
+        self.code.push(0x40 | u8::from(base.ext()));
+        self.code.push(0xC6);
+        self.modrm_mem(0, base, disp);
+        self.code.push(value);
+    }
+
+    pub fn shl(&mut self, dst: Reg, amount: u8) {
+        self.rex(Reg::Rax, dst);
+        self.code.push(0xC1);
+        self.modrm_reg(4, dst);
+        self.code.push(amount);
+    }
+
+    /// `rax = cond ? 1 : 0` from the current flags.
+    pub fn set_rax(&mut self, cond: Cond) {
+        self.code
+            .extend_from_slice(&[0x0F, 0x90 + cond as u8, 0xC0]);
+        self.code.extend_from_slice(&[0x48, 0x0F, 0xB6, 0xC0]);
+    }
+
+    pub fn push(&mut self, reg: Reg) {
+        if reg.ext() {
+            self.code.push(0x41);
+        }
+        self.code.push(0x50 + reg.low());
+    }
+
+    pub fn pop(&mut self, reg: Reg) {
+        if reg.ext() {
+            self.code.push(0x41);
+        }
+        self.code.push(0x58 + reg.low());
+    }
+
+    pub fn jmp(&mut self, label: Label) {
+        self.code.push(0xE9);
+        self.label32(label);
+    }
+
+    pub fn jcc(&mut self, cond: Cond, label: Label) {
+        self.code.extend_from_slice(&[0x0F, 0x80 + cond as u8]);
+        self.label32(label);
+    }
+
+    fn label32(&mut self, label: Label) {
+        let field = self.code.len();
+        self.code.extend_from_slice(&[0; 4]);
+        match self.labels[label.0] {
+            Some(target) => {
+                let rel = target as i64 - (field as i64 + 4);
+                self.code[field..field + 4].copy_from_slice(&(rel as i32).to_le_bytes());
+            }
+            None => self.pending.push((field, label)),
+        }
+    }
+
+    pub fn call(&mut self, symbol: Symbol) {
+        self.code.push(0xE8);
+        self.reloc32(symbol, 0);
+    }
+
+    /// Tail-jumps to another code symbol.
+    pub fn jmp_symbol(&mut self, symbol: Symbol) {
+        self.code.push(0xE9);
+        self.reloc32(symbol, 0);
+    }
+
+    pub fn ret(&mut self) {
+        self.code.push(0xC3);
+    }
+
+    pub fn syscall(&mut self) {
+        self.code.extend_from_slice(&[0x0F, 0x05]);
+    }
+}
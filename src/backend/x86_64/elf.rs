@@ -0,0 +1,435 @@
+//! ELF64 writers for the generated code: a relocatable object for external
+//! linkers and a static executable linked here.
+
+use super::asm::{Reloc, Symbol};
+
+/// Load address of the executable's first segment.
+const BASE_ADDRESS: u64 = 0x40_0000;
+const PAGE_SIZE: u64 = 0x1000;
+
+const ELF_HEADER_SIZE: usize = 64;
+const PROGRAM_HEADER_SIZE: usize = 56;
+const SECTION_HEADER_SIZE: usize = 64;
+const SYMBOL_SIZE: usize = 24;
+const RELA_SIZE: usize = 24;
+
+const ET_REL: u16 = 1;
+const ET_EXEC: u16 = 2;
+const EM_X86_64: u16 = 62;
+
+const PT_LOAD: u32 = 1;
+const PT_GNU_STACK: u32 = 0x6474_E551;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+const SHF_WRITE: u64 = 1;
+const SHF_ALLOC: u64 = 2;
+const SHF_EXECINSTR: u64 = 4;
+const SHF_INFO_LINK: u64 = 0x40;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+
+const R_X86_64_PC32: u32 = 2;
+const R_X86_64_PLT32: u32 = 4;
+
+/// Section indices of the object file, in header order.
+const TEXT_INDEX: u16 = 1;
+const RODATA_INDEX: u16 = 2;
+const BSS_INDEX: u16 = 3;
+
+/// A function or runtime routine in `.text`.
+#[derive(Debug, Clone)]
+pub struct CodeSymbol {
+    pub name: String,
+    pub offset: usize,
+    pub size: usize,
+    /// Visible to other objects; everything else stays local.
+    pub global: bool,
+}
+
+/// Sections and symbols shared by both output forms.
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub text: Vec<u8>,
+    pub rodata: Vec<u8>,
+    pub bss_size: u64,
+    pub symbols: Vec<CodeSymbol>,
+    pub relocs: Vec<Reloc>,
+    /// Index into `symbols` of `_start`.
+    pub entry: usize,
+}
+
+fn align(value: usize, to: usize) -> usize {
+    value.div_ceil(to) * to
+}
+
+fn pad_to(bytes: &mut Vec<u8>, len: usize) {
+    bytes.resize(len, 0);
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn i64(&mut self, value: i64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+struct Header {
+    kind: u16,
+    entry: u64,
+    phoff: u64,
+    shoff: u64,
+    phnum: u16,
+    shnum: u16,
+    shstrndx: u16,
+}
+
+fn write_header(out: &mut Writer, header: Header) {
+    out.0
+        .extend_from_slice(&[0x7F, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    out.u16(header.kind);
+    out.u16(EM_X86_64);
+    out.u32(1);
+    out.u64(header.entry);
+    out.u64(header.phoff);
+    out.u64(header.shoff);
+    out.u32(0);
+    out.u16(ELF_HEADER_SIZE as u16);
+    out.u16(if header.phnum > 0 {
+        PROGRAM_HEADER_SIZE as u16
+    } else {
+        0
+    });
+    out.u16(header.phnum);
+    out.u16(SECTION_HEADER_SIZE as u16);
+    out.u16(header.shnum);
+    out.u16(header.shstrndx);
+}
+
+#[derive(Default)]
+struct StringTable(Vec<u8>);
+
+impl StringTable {
+    fn new() -> Self {
+        StringTable(vec![0])
+    }
+
+    fn add(&mut self, name: &str) -> u32 {
+        let offset = self.0.len() as u32;
+        self.0.extend_from_slice(name.as_bytes());
+        self.0.push(0);
+        offset
+    }
+}
+
+struct Section {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    offset: usize,
+    size: usize,
+    link: u32,
+    info: u32,
+    align: u64,
+    entsize: u64,
+}
+
+/// Writes a relocatable object. Calls reference function symbols through
+/// `R_X86_64_PLT32` and data references `.rodata`/`.bss` through
+/// `R_X86_64_PC32`, so a system linker can place the sections freely.
+pub fn object(image: &Image) -> Vec<u8> {
+    let mut strtab = StringTable::new();
+    let mut symtab = Writer(vec![0; SYMBOL_SIZE]);
+    let mut symbol = |name: u32, info: u8, shndx: u16, value: u64, size: u64| {
+        symtab.u32(name);
+        symtab.u8(info);
+        symtab.u8(0);
+        symtab.u16(shndx);
+        symtab.u64(value);
+        symtab.u64(size);
+    };
+    for shndx in [TEXT_INDEX, RODATA_INDEX, BSS_INDEX] {
+        symbol(0, (STB_LOCAL << 4) | STT_SECTION, shndx, 0, 0);
+    }
+    // Locals must precede globals in the symbol table.
+    let mut order: Vec<usize> = (0..image.symbols.len()).collect();
+    order.sort_by_key(|index| image.symbols[*index].global);
+    let mut elf_index = vec![0u32; image.symbols.len()];
+    let mut first_global = 4 + image.symbols.len() as u32;
+    for (position, index) in order.iter().enumerate() {
+        let code = &image.symbols[*index];
+        let index_in_table = 4 + position as u32;
+        elf_index[*index] = index_in_table;
+        let bind = if code.global {
+            first_global = first_global.min(index_in_table);
+            STB_GLOBAL
+        } else {
+            STB_LOCAL
+        };
+        symbol(
+            strtab.add(&code.name),
+            (bind << 4) | STT_FUNC,
+            TEXT_INDEX,
+            code.offset as u64,
+            code.size as u64,
+        );
+    }
+
+    let mut rela = Writer(Vec::new());
+    for reloc in &image.relocs {
+        let (sym, kind, addend) = match reloc.symbol {
+            Symbol::Code(index) => (elf_index[index], R_X86_64_PLT32, reloc.addend),
+            Symbol::Rodata(offset) => (
+                RODATA_INDEX as u32,
+                R_X86_64_PC32,
+                offset as i64 + reloc.addend,
+            ),
+            Symbol::Bss(offset) => (
+                BSS_INDEX as u32,
+                R_X86_64_PC32,
+                offset as i64 + reloc.addend,
+            ),
+        };
+        rela.u64(reloc.offset as u64);
+        rela.u64(((sym as u64) << 32) | kind as u64);
+        rela.i64(addend);
+    }
+
+    let mut shstrtab = StringTable::new();
+    let names = [
+        ".text",
+        ".rodata",
+        ".bss",
+        ".symtab",
+        ".strtab",
+        ".rela.text",
+        ".note.GNU-stack",
+        ".shstrtab",
+    ]
+    .map(|name| shstrtab.add(name));
+
+    let mut out = Writer(vec![0; ELF_HEADER_SIZE]);
+    let place = |out: &mut Writer, bytes: &[u8], alignment: usize| {
+        let offset = align(out.0.len(), alignment);
+        pad_to(&mut out.0, offset);
+        out.0.extend_from_slice(bytes);
+        offset
+    };
+    let text = place(&mut out, &image.text, 16);
+    let rodata = place(&mut out, &image.rodata, 8);
+    let symtab_offset = place(&mut out, &symtab.0, 8);
+    let strtab_offset = place(&mut out, &strtab.0, 1);
+    let rela_offset = place(&mut out, &rela.0, 8);
+    let shstrtab_offset = place(&mut out, &shstrtab.0, 1);
+    let sections = [
+        Section {
+            name: names[0],
+            kind: SHT_PROGBITS,
+            flags: SHF_ALLOC | SHF_EXECINSTR,
+            offset: text,
+            size: image.text.len(),
+            link: 0,
+            info: 0,
+            align: 16,
+            entsize: 0,
+        },
+        Section {
+            name: names[1],
+            kind: SHT_PROGBITS,
+            flags: SHF_ALLOC,
+            offset: rodata,
+            size: image.rodata.len(),
+            link: 0,
+            info: 0,
+            align: 8,
+            entsize: 0,
+        },
+        Section {
+            name: names[2],
+            kind: SHT_NOBITS,
+            flags: SHF_ALLOC | SHF_WRITE,
+            offset: rodata + image.rodata.len(),
+            size: image.bss_size as usize,
+            link: 0,
+            info: 0,
+            align: 16,
+            entsize: 0,
+        },
+        Section {
+            name: names[3],
+            kind: SHT_SYMTAB,
+            flags: 0,
+            offset: symtab_offset,
+            size: symtab.0.len(),
+            link: 5,
+            info: first_global,
+            align: 8,
+            entsize: SYMBOL_SIZE as u64,
+        },
+        Section {
+            name: names[4],
+            kind: SHT_STRTAB,
+            flags: 0,
+            offset: strtab_offset,
+            size: strtab.0.len(),
+            link: 0,
+            info: 0,
+            align: 1,
+            entsize: 0,
+        },
+        Section {
+            name: names[5],
+            kind: SHT_RELA,
+            flags: SHF_INFO_LINK,
+            offset: rela_offset,
+            size: rela.0.len(),
+            link: 4,
+            info: TEXT_INDEX as u32,
+            align: 8,
+            entsize: RELA_SIZE as u64,
+        },
+        // Marks the stack non-executable for the system linker.
+        Section {
+            name: names[6],
+            kind: SHT_PROGBITS,
+            flags: 0,
+            offset: shstrtab_offset,
+            size: 0,
+            link: 0,
+            info: 0,
+            align: 1,
+            entsize: 0,
+        },
+        Section {
+            name: names[7],
+            kind: SHT_STRTAB,
+            flags: 0,
+            offset: shstrtab_offset,
+            size: shstrtab.0.len(),
+            link: 0,
+            info: 0,
+            align: 1,
+            entsize: 0,
+        },
+    ];
+
+    let shoff = align(out.0.len(), 8);
+    pad_to(&mut out.0, shoff);
+    out.0.extend_from_slice(&[0; SECTION_HEADER_SIZE]);
+    for section in &sections {
+        out.u32(section.name);
+        out.u32(section.kind);
+        out.u64(section.flags);
+        out.u64(0);
+        out.u64(section.offset as u64);
+        out.u64(section.size as u64);
+        out.u32(section.link);
+        out.u32(section.info);
+        out.u64(section.align);
+        out.u64(section.entsize);
+    }
+
+    let mut header = Writer(Vec::with_capacity(ELF_HEADER_SIZE));
+    write_header(
+        &mut header,
+        Header {
+            kind: ET_REL,
+            entry: 0,
+            phoff: 0,
+            shoff: shoff as u64,
+            phnum: 0,
+            shnum: sections.len() as u16 + 1,
+            shstrndx: sections.len() as u16,
+        },
+    );
+    out.0[..ELF_HEADER_SIZE].copy_from_slice(&header.0);
+    out.0
+}
+
+/// Writes a static executable: one read-execute segment holding the headers,
+/// code and read-only data, and one zero-filled read-write segment for
+/// `.bss`. Relocations are resolved against those fixed addresses.
+pub fn executable(image: &Image) -> Vec<u8> {
+    let phnum = 3;
+    let headers = ELF_HEADER_SIZE + phnum * PROGRAM_HEADER_SIZE;
+    let text_offset = align(headers, 16);
+    let rodata_offset = align(text_offset + image.text.len(), 8);
+    let file_size = rodata_offset + image.rodata.len();
+    let bss_address = (BASE_ADDRESS + file_size as u64).div_ceil(PAGE_SIZE) * PAGE_SIZE;
+
+    let text_address = BASE_ADDRESS + text_offset as u64;
+    let address_of = |symbol: Symbol| match symbol {
+        Symbol::Code(index) => text_address + image.symbols[index].offset as u64,
+        Symbol::Rodata(offset) => BASE_ADDRESS + rodata_offset as u64 + offset as u64,
+        Symbol::Bss(offset) => bss_address + offset as u64,
+    };
+
+    let mut text = image.text.clone();
+    for reloc in &image.relocs {
+        let place = text_address + reloc.offset as u64;
+        let value = address_of(reloc.symbol) as i64 + reloc.addend - place as i64;
+        let value = i32::try_from(value).expect("executable fits in 2 GiB");
+        text[reloc.offset..reloc.offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    let mut out = Writer(Vec::with_capacity(file_size));
+    write_header(
+        &mut out,
+        Header {
+            kind: ET_EXEC,
+            entry: address_of(Symbol::Code(image.entry)),
+            phoff: ELF_HEADER_SIZE as u64,
+            shoff: 0,
+            phnum: phnum as u16,
+            shnum: 0,
+            shstrndx: 0,
+        },
+    );
+    let mut segment = |kind: u32, flags: u32, address: u64, file: u64, memory: u64| {
+        out.u32(kind);
+        out.u32(flags);
+        out.u64(0);
+        out.u64(address);
+        out.u64(address);
+        out.u64(file);
+        out.u64(memory);
+        out.u64(PAGE_SIZE);
+    };
+    segment(
+        PT_LOAD,
+        PF_R | PF_X,
+        BASE_ADDRESS,
+        file_size as u64,
+        file_size as u64,
+    );
+    segment(PT_LOAD, PF_R | PF_W, bss_address, 0, image.bss_size);
+    segment(PT_GNU_STACK, PF_R | PF_W, 0, 0, 0);
+
+    pad_to(&mut out.0, text_offset);
+    out.0.extend_from_slice(&text);
+    pad_to(&mut out.0, rodata_offset);
+    out.0.extend_from_slice(&image.rodata);
+    out.0
+}
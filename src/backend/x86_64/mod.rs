@@ -0,0 +1,805 @@
+//! Self-contained x86-64 backend: compiles the SSA module straight to
+//! machine code and packages it as an ELF relocatable object or a static
+//! Linux executable, with no C compiler or C library involved.
+
+mod asm;
+mod elf;
+mod regalloc;
+mod runtime;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ir::analysis::AnalysisManager;
+use crate::ir::passes::{DeadFunctionElimination, Pass, TailCallElimination};
+use crate::ir::{self, BlockId, InstKind, Terminator, Type, TypeId, ValueId};
+use crate::syntax::ast::{BinaryOp, Literal};
+
+use super::{Backend, BackendError, BackendOptions, BackendResult, resolve_callee, type_label};
+use asm::{Alu, Assembler, Cond, Label, Reg, Symbol};
+use elf::{CodeSymbol, Image};
+use regalloc::{Allocation, Loc};
+use runtime::{Entry, Messages, Routine, RuntimeEmitter};
+
+/// SysV integer argument registers.
+const ARG_REGS: [Reg; 6] = [Reg::Rdi, Reg::Rsi, Reg::Rdx, Reg::Rcx, Reg::R8, Reg::R9];
+
+/// Bytes between `rbp` and the first spill slot: the callee-saved registers
+/// pushed by every prologue.
+const SAVED_BYTES: i32 = 8 * regalloc::ALLOCATABLE.len() as i32;
+
+/// Backend that generates x86-64 machine code for Linux.
+///
+/// Values live in callee-saved registers picked by a linear-scan allocator
+/// or in stack slots, every value is one 64-bit word, and calls follow the
+/// SysV convention. A small runtime written against raw system calls is
+/// emitted into every object, so the executable is fully static.
+#[derive(Debug, Default, Clone)]
+pub struct X86_64Backend;
+
+/// Output of [`X86_64Backend`].
+#[derive(Debug, Clone)]
+pub struct ElfArtifact {
+    /// Relocatable object defining `_start`, `main` and the public functions.
+    pub object: Vec<u8>,
+    /// Static executable, present when the module has a `main`.
+    pub executable: Option<Vec<u8>>,
+    pub module_name: String,
+}
+
+impl ElfArtifact {
+    /// Writes the relocatable object to the provided path.
+    pub fn write_object<P: AsRef<Path>>(&self, path: P) -> BackendResult<PathBuf> {
+        let path = path.as_ref();
+        fs::write(path, &self.object)
+            .map_err(|err| BackendError::Internal(format!("failed to write object: {err}")))?;
+        Ok(path.to_path_buf())
+    }
+
+    /// Writes the executable to `out_path` and marks it executable.
+    pub fn link_executable<P: AsRef<Path>>(&self, out_path: P) -> BackendResult<PathBuf> {
+        let out_path = out_path.as_ref();
+        let Some(executable) = &self.executable else {
+            return Err(BackendError::unsupported(format!(
+                "module '{}' has no 'main' function to link",
+                self.module_name
+            )));
+        };
+        fs::write(out_path, executable)
+            .map_err(|err| BackendError::Internal(format!("failed to write executable: {err}")))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(out_path, fs::Permissions::from_mode(0o755)).map_err(|err| {
+                BackendError::Internal(format!("failed to mark executable: {err}"))
+            })?;
+        }
+        Ok(out_path.to_path_buf())
+    }
+}
+
+impl Backend for X86_64Backend {
+    type Output = ElfArtifact;
+
+    fn compile(
+        &self,
+        module: &ir::Module,
+        options: &BackendOptions,
+    ) -> BackendResult<Self::Output> {
+        if let Some(triple) = &options.target_triple
+            && !triple.starts_with("x86_64")
+        {
+            return Err(BackendError::unsupported(format!(
+                "the x86-64 backend cannot target '{triple}'"
+            )));
+        }
+        let mut module = module.clone();
+        let shaking = DeadFunctionElimination {
+            keep_public: options.library,
+        };
+        let mut analyses = AnalysisManager::new();
+        shaking.run(&mut module, &mut analyses);
+        TailCallElimination.run(&mut module, &mut analyses);
+
+        let image = generate(&module)?;
+        Ok(ElfArtifact {
+            object: elf::object(&image),
+            executable: module
+                .functions
+                .iter()
+                .any(|function| function.name == "main")
+                .then(|| elf::executable(&image)),
+            module_name: module.name.join("_"),
+        })
+    }
+}
+
+/// Code symbols are the module's functions followed by the runtime routines.
+fn routine_symbol(module: &ir::Module, routine: Routine) -> Symbol {
+    let index = Routine::ALL
+        .iter()
+        .position(|candidate| *candidate == routine)
+        .expect("every routine is listed");
+    Symbol::Code(module.functions.len() + index)
+}
+
+fn symbol_name(name: &str) -> String {
+    name.replace("::", "_")
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+        .collect()
+}
+
+fn generate(module: &ir::Module) -> BackendResult<Image> {
+    let mut asm = Assembler::default();
+    let mut rodata = Rodata::default();
+    let mut symbols = Vec::new();
+
+    for function in &module.functions {
+        asm.align(16);
+        let offset = asm.offset();
+        FunctionCodegen::emit(module, function, &mut asm, &mut rodata)?;
+        symbols.push(CodeSymbol {
+            name: symbol_name(&function.name),
+            offset,
+            size: asm.offset() - offset,
+            global: function.name == "main" || function.is_public,
+        });
+    }
+
+    let entry = module
+        .functions
+        .iter()
+        .position(|function| function.name == "main")
+        .map(|index| {
+            let main = &module.functions[index];
+            Entry {
+                main: Symbol::Code(index),
+                arity: main.params.len(),
+                returns_status: matches!(module.type_of(main.ret_type), Type::Int),
+            }
+        });
+    let messages = Messages {
+        newline: rodata.intern(runtime::NEWLINE),
+        error_prefix: rodata.intern(runtime::ERROR_PREFIX),
+        out_of_memory: rodata.intern(runtime::OUT_OF_MEMORY),
+        index_prefix: rodata.intern(runtime::INDEX_PREFIX),
+        index_middle: rodata.intern(runtime::INDEX_MIDDLE),
+    };
+    let symbol = |routine| routine_symbol(module, routine);
+    for routine in Routine::ALL {
+        asm.align(16);
+        let offset = asm.offset();
+        RuntimeEmitter {
+            asm: &mut asm,
+            symbol: &symbol,
+            messages,
+        }
+        .emit(routine, entry);
+        symbols.push(CodeSymbol {
+            name: routine.name().to_string(),
+            offset,
+            size: asm.offset() - offset,
+            global: routine == Routine::Start,
+        });
+    }
+    if !asm.is_resolved() {
+        return Err(BackendError::internal(
+            "x86-64 code references an unbound label",
+        ));
+    }
+
+    let Symbol::Code(entry) = routine_symbol(module, Routine::Start) else {
+        unreachable!("routines are code symbols");
+    };
+    Ok(Image {
+        text: asm.code,
+        rodata: rodata.bytes,
+        bss_size: runtime::BSS_SIZE as u64,
+        symbols,
+        relocs: asm.relocs,
+        entry,
+    })
+}
+
+/// Deduplicated `.rodata` strings, each an 8-byte length followed by its
+/// bytes like every runtime string.
+#[derive(Default)]
+struct Rodata {
+    bytes: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl Rodata {
+    fn intern(&mut self, value: &str) -> Symbol {
+        if let Some(offset) = self.offsets.get(value) {
+            return Symbol::Rodata(*offset);
+        }
+        self.bytes.resize(self.bytes.len().next_multiple_of(8), 0);
+        let offset = self.bytes.len() as u32;
+        self.bytes
+            .extend_from_slice(&(value.len() as u64).to_le_bytes());
+        self.bytes.extend_from_slice(value.as_bytes());
+        self.offsets.insert(value.to_string(), offset);
+        Symbol::Rodata(offset)
+    }
+}
+
+/// Methods the built-in runtime implements: console output and the clock.
+fn runtime_method(name: &str) -> bool {
+    matches!(name, "println" | "write_line" | "now_millis")
+}
+
+struct FunctionCodegen<'a> {
+    module: &'a ir::Module,
+    function: &'a ir::Function,
+    asm: &'a mut Assembler,
+    rodata: &'a mut Rodata,
+    allocation: Allocation,
+    types: HashMap<ValueId, TypeId>,
+    labels: HashMap<BlockId, Label>,
+    /// First of the slots edge moves stage values through.
+    scratch_base: u32,
+}
+
+impl<'a> FunctionCodegen<'a> {
+    fn emit(
+        module: &'a ir::Module,
+        function: &'a ir::Function,
+        asm: &'a mut Assembler,
+        rodata: &'a mut Rodata,
+    ) -> BackendResult<()> {
+        if function.params.len() > ARG_REGS.len() {
+            return Err(BackendError::unsupported(format!(
+                "function '{}' takes {} parameters; the x86-64 backend passes at most {}",
+                function.name,
+                function.params.len(),
+                ARG_REGS.len()
+            )));
+        }
+        let mut types: HashMap<ValueId, TypeId> = function
+            .params
+            .iter()
+            .map(|param| (param.value, param.ty))
+            .collect();
+        for inst in function.blocks.iter().flat_map(|block| &block.instructions) {
+            types.insert(inst.id, inst.ty);
+        }
+        if types
+            .values()
+            .chain([&function.ret_type])
+            .any(|ty| matches!(module.type_of(*ty), Type::Float))
+        {
+            return Err(BackendError::unsupported(format!(
+                "function '{}' uses Float values, which the x86-64 backend does not support",
+                function.name
+            )));
+        }
+
+        let allocation = regalloc::allocate(function);
+        let scratch_base = allocation.slots;
+        let scratch = function
+            .blocks
+            .iter()
+            .map(|block| {
+                block
+                    .instructions
+                    .iter()
+                    .filter(|inst| matches!(inst.kind, InstKind::Phi { .. }))
+                    .count() as u32
+            })
+            .max()
+            .unwrap_or(0);
+        let labels = function
+            .blocks
+            .iter()
+            .map(|block| (block.id, asm.label()))
+            .collect();
+        let mut codegen = FunctionCodegen {
+            module,
+            function,
+            asm,
+            rodata,
+            allocation,
+            types,
+            labels,
+            scratch_base,
+        };
+        codegen.prologue(scratch_base + scratch)?;
+        for (index, block) in function.blocks.iter().enumerate() {
+            codegen.asm.bind(codegen.labels[&block.id]);
+            let next = function.blocks.get(index + 1).map(|next| next.id);
+            codegen.emit_block(block, next)?;
+        }
+        Ok(())
+    }
+
+    /// Saves `rbp` and the allocatable registers, reserves `slots` words
+    /// keeping `rsp` 16-byte aligned, and moves the parameters home.
+    fn prologue(&mut self, slots: u32) -> BackendResult<()> {
+        self.asm.push(Reg::Rbp);
+        self.asm.mov(Reg::Rbp, Reg::Rsp);
+        for reg in regalloc::ALLOCATABLE {
+            self.asm.push(reg);
+        }
+        // Five pushes leave `rsp` 8 bytes off alignment.
+        let frame = if slots % 2 == 1 { slots } else { slots + 1 };
+        self.asm.alu_imm(Alu::Sub, Reg::Rsp, 8 * frame as i32);
+        for (param, reg) in self.function.params.iter().zip(ARG_REGS) {
+            self.store(param.value, reg)?;
+        }
+        Ok(())
+    }
+
+    /// Restores the caller's registers and frame, leaving `rsp` at the
+    /// return address.
+    fn unwind(&mut self) {
+        self.asm.lea(Reg::Rsp, Reg::Rbp, -SAVED_BYTES);
+        for reg in regalloc::ALLOCATABLE.iter().rev() {
+            self.asm.pop(*reg);
+        }
+        self.asm.pop(Reg::Rbp);
+    }
+
+    fn loc(&self, value: ValueId) -> BackendResult<Loc> {
+        self.allocation
+            .locations
+            .get(&value)
+            .copied()
+            .ok_or_else(|| {
+                BackendError::Internal(format!(
+                    "function '{}' uses undefined value %{}",
+                    self.function.name,
+                    value.index()
+                ))
+            })
+    }
+
+    fn value_type(&self, value: ValueId) -> BackendResult<&'a Type> {
+        let module = self.module;
+        self.types
+            .get(&value)
+            .map(|ty| module.type_of(*ty))
+            .ok_or_else(|| {
+                BackendError::Internal(format!(
+                    "function '{}' uses undefined value %{}",
+                    self.function.name,
+                    value.index()
+                ))
+            })
+    }
+
+    fn slot_disp(slot: u32) -> i32 {
+        -SAVED_BYTES - 8 * (slot as i32 + 1)
+    }
+
+    fn load_loc(&mut self, dst: Reg, loc: Loc) {
+        match loc {
+            Loc::Reg(src) => self.asm.mov(dst, src),
+            Loc::Slot(slot) => self.asm.load(dst, Reg::Rbp, Self::slot_disp(slot)),
+        }
+    }
+
+    fn store_loc(&mut self, loc: Loc, src: Reg) {
+        match loc {
+            Loc::Reg(dst) => self.asm.mov(dst, src),
+            Loc::Slot(slot) => self.asm.store(Reg::Rbp, Self::slot_disp(slot), src),
+        }
+    }
+
+    fn copy(&mut self, dst: Loc, src: Loc) {
+        match (dst, src) {
+            (Loc::Reg(dst), src) => self.load_loc(dst, src),
+            (dst, Loc::Reg(src)) => self.store_loc(dst, src),
+            (dst, src) => {
+                self.load_loc(Reg::Rax, src);
+                self.store_loc(dst, Reg::Rax);
+            }
+        }
+    }
+
+    fn load(&mut self, dst: Reg, value: ValueId) -> BackendResult<()> {
+        let loc = self.loc(value)?;
+        self.load_loc(dst, loc);
+        Ok(())
+    }
+
+    fn store(&mut self, value: ValueId, src: Reg) -> BackendResult<()> {
+        let loc = self.loc(value)?;
+        self.store_loc(loc, src);
+        Ok(())
+    }
+
+    fn call_routine(&mut self, routine: Routine) {
+        self.asm.call(routine_symbol(self.module, routine));
+    }
+
+    fn emit_block(&mut self, block: &ir::BasicBlock, next: Option<BlockId>) -> BackendResult<()> {
+        let tail_call = self.tail_call(block);
+        let body = match tail_call {
+            Some(_) => &block.instructions[..block.instructions.len() - 1],
+            None => &block.instructions[..],
+        };
+        for inst in body {
+            self.emit_instruction(inst)?;
+        }
+        if let Some((index, args)) = tail_call {
+            for (arg, reg) in args.iter().zip(ARG_REGS) {
+                self.load(reg, *arg)?;
+            }
+            self.unwind();
+            self.asm.jmp_symbol(Symbol::Code(index));
+            return Ok(());
+        }
+        match &block.terminator {
+            Terminator::Return(value) => {
+                match value {
+                    Some(value) => self.load(Reg::Rax, *value)?,
+                    None => self.asm.mov_imm(Reg::Rax, 0),
+                }
+                self.unwind();
+                self.asm.ret();
+            }
+            Terminator::Jump(target) => {
+                let moves = self.edge_moves(block.id, *target)?;
+                self.emit_moves(moves);
+                if next != Some(*target) {
+                    self.asm.jmp(self.label(*target)?);
+                }
+            }
+            Terminator::Branch {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                self.load(Reg::Rax, *condition)?;
+                self.asm.alu(Alu::Test, Reg::Rax, Reg::Rax);
+                let then_moves = self.edge_moves(block.id, *then_block)?;
+                let else_moves = self.edge_moves(block.id, *else_block)?;
+                let then_label = self.label(*then_block)?;
+                let else_label = self.label(*else_block)?;
+                if else_moves.is_empty() {
+                    self.asm.jcc(Cond::E, else_label);
+                    self.emit_moves(then_moves);
+                    if next != Some(*then_block) {
+                        self.asm.jmp(then_label);
+                    }
+                } else {
+                    let skip = self.asm.label();
+                    self.asm.jcc(Cond::E, skip);
+                    self.emit_moves(then_moves);
+                    self.asm.jmp(then_label);
+                    self.asm.bind(skip);
+                    self.emit_moves(else_moves);
+                    if next != Some(*else_block) {
+                        self.asm.jmp(else_label);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A block ending in `return f(args)` for a module function `f` jumps to
+    /// it instead, reusing the frame, so sibling recursion runs in constant
+    /// stack like the C backend's trampolines.
+    fn tail_call(&self, block: &'a ir::BasicBlock) -> Option<(usize, &'a [ValueId])> {
+        let Terminator::Return(Some(value)) = &block.terminator else {
+            return None;
+        };
+        let last = block.instructions.last()?;
+        let InstKind::Call { func, args } = &last.kind else {
+            return None;
+        };
+        if last.id != *value {
+            return None;
+        }
+        let name = match func {
+            ir::FuncRef::Function(path) => path.segments.join("::"),
+            ir::FuncRef::Method(name) if runtime_method(name) => return None,
+            ir::FuncRef::Method(name) => name.clone(),
+        };
+        let index = self.module.functions.iter().position(|f| f.name == name)?;
+        (self.module.functions[index].params.len() == args.len()).then_some((index, &args[..]))
+    }
+
+    fn label(&self, block: BlockId) -> BackendResult<Label> {
+        self.labels.get(&block).copied().ok_or_else(|| {
+            BackendError::Internal(format!(
+                "function '{}' branches to missing block {}",
+                self.function.name, block.0
+            ))
+        })
+    }
+
+    /// Phi assignments `(dst, src)` performed when control moves from `from`
+    /// to `to`.
+    fn edge_moves(&self, from: BlockId, to: BlockId) -> BackendResult<Vec<(Loc, Loc)>> {
+        let Some(block) = self.function.blocks.iter().find(|block| block.id == to) else {
+            return Err(BackendError::Internal(format!(
+                "function '{}' branches to missing block {}",
+                self.function.name, to.0
+            )));
+        };
+        let mut moves = Vec::new();
+        for inst in &block.instructions {
+            let InstKind::Phi { incomings } = &inst.kind else {
+                continue;
+            };
+            let Some((_, value)) = incomings.iter().find(|(pred, _)| *pred == from) else {
+                return Err(BackendError::Internal(format!(
+                    "phi %{} in '{}' has no incoming value from block {}",
+                    inst.id.index(),
+                    self.function.name,
+                    from.0
+                )));
+            };
+            let (dst, src) = (self.loc(inst.id)?, self.loc(*value)?);
+            if dst != src {
+                moves.push((dst, src));
+            }
+        }
+        Ok(moves)
+    }
+
+    /// Emits the parallel assignment `moves`, staging every source in the
+    /// scratch slots first when a destination is also a source.
+    fn emit_moves(&mut self, moves: Vec<(Loc, Loc)>) {
+        let conflicts = moves
+            .iter()
+            .any(|(dst, _)| moves.iter().any(|(_, src)| src == dst));
+        if !conflicts {
+            for (dst, src) in moves {
+                self.copy(dst, src);
+            }
+            return;
+        }
+        for (offset, (_, src)) in moves.iter().enumerate() {
+            self.copy(Loc::Slot(self.scratch_base + offset as u32), *src);
+        }
+        for (offset, (dst, _)) in moves.iter().enumerate() {
+            self.copy(*dst, Loc::Slot(self.scratch_base + offset as u32));
+        }
+    }
+
+    fn emit_instruction(&mut self, inst: &ir::Instruction) -> BackendResult<()> {
+        match &inst.kind {
+            InstKind::Literal(literal) => {
+                match literal {
+                    Literal::Int(value) => self.asm.mov_imm(Reg::Rax, *value),
+                    Literal::Bool(value) => self.asm.mov_imm(Reg::Rax, i64::from(*value)),
+                    Literal::Unit => self.asm.mov_imm(Reg::Rax, 0),
+                    Literal::String(value) => {
+                        let symbol = self.rodata.intern(value);
+                        self.asm.lea_symbol(Reg::Rax, symbol);
+                    }
+                    Literal::Float(_) => {
+                        return Err(BackendError::unsupported(
+                            "Float literals are not supported by the x86-64 backend",
+                        ));
+                    }
+                }
+                self.store(inst.id, Reg::Rax)
+            }
+            InstKind::Binary { op, lhs, rhs } => self.emit_binary(inst.id, *op, *lhs, *rhs),
+            InstKind::Call { func, args } => self.emit_call(inst.id, func, args),
+            InstKind::Record { fields, .. } => {
+                let Type::Record(record) = self.module.type_of(inst.ty) else {
+                    return Err(BackendError::Internal(
+                        "record literal lowered with non-record type".into(),
+                    ));
+                };
+                let mut slots = Vec::with_capacity(record.fields.len());
+                for field in &record.fields {
+                    let Some((_, value)) = fields.iter().find(|(name, _)| name == &field.name)
+                    else {
+                        let record_name = record.name.as_deref().unwrap_or("<anonymous record>");
+                        return Err(BackendError::Unsupported(format!(
+                            "record literal for '{record_name}' is missing field '{}'",
+                            field.name
+                        )));
+                    };
+                    slots.push(*value);
+                }
+                for (name, _) in fields {
+                    if record.field(name).is_none() {
+                        return Err(BackendError::Unsupported(format!(
+                            "record literal references unknown field '{}'",
+                            name
+                        )));
+                    }
+                }
+                self.asm.mov_imm(Reg::Rdi, 8 * slots.len().max(1) as i64);
+                self.call_routine(Routine::Alloc);
+                for (slot, value) in slots.into_iter().enumerate() {
+                    self.load(Reg::Rcx, value)?;
+                    self.asm.store(Reg::Rax, 8 * slot as i32, Reg::Rcx);
+                }
+                self.store(inst.id, Reg::Rax)
+            }
+            InstKind::Field { base, name } => {
+                let Type::Record(record) = self.value_type(*base)? else {
+                    return Err(BackendError::unsupported(format!(
+                        "field '{name}' read from a value that is not a record"
+                    )));
+                };
+                let Some(slot) = record.fields.iter().position(|field| &field.name == name) else {
+                    return Err(BackendError::unsupported(format!(
+                        "record has no field '{name}'"
+                    )));
+                };
+                self.load(Reg::Rax, *base)?;
+                self.asm.load(Reg::Rax, Reg::Rax, 8 * slot as i32);
+                self.store(inst.id, Reg::Rax)
+            }
+            // Every conversion involves a Float, which was rejected up front.
+            InstKind::Convert { .. } => Err(BackendError::unsupported(
+                "numeric conversions are not supported by the x86-64 backend",
+            )),
+            InstKind::Path(path) => Err(BackendError::Unsupported(format!(
+                "path expression '{}' cannot be lowered to x86-64",
+                path.segments.join("::")
+            ))),
+            // Phis are written by the moves on their incoming edges.
+            InstKind::Phi { .. } => Ok(()),
+            InstKind::List { elements } => {
+                self.asm.mov_imm(Reg::Rdi, elements.len() as i64);
+                self.call_routine(Routine::ListNew);
+                self.asm.load(Reg::Rcx, Reg::Rax, 16);
+                for (index, element) in elements.iter().enumerate() {
+                    self.load(Reg::Rdx, *element)?;
+                    self.asm.store(Reg::Rcx, 8 * index as i32, Reg::Rdx);
+                }
+                self.store(inst.id, Reg::Rax)
+            }
+            InstKind::ListLen { list } => {
+                self.load(Reg::Rax, *list)?;
+                self.asm.load(Reg::Rax, Reg::Rax, 0);
+                self.store(inst.id, Reg::Rax)
+            }
+            InstKind::ListIndex { list, index } => {
+                self.load(Reg::Rdi, *list)?;
+                self.load(Reg::Rsi, *index)?;
+                self.call_routine(Routine::ListIndex);
+                self.store(inst.id, Reg::Rax)
+            }
+            InstKind::ListPush { list, value } => {
+                self.load(Reg::Rdi, *list)?;
+                self.load(Reg::Rsi, *value)?;
+                self.call_routine(Routine::ListPush);
+                self.asm.mov_imm(Reg::Rax, 0);
+                self.store(inst.id, Reg::Rax)
+            }
+            // The built-in runtime never frees, so counts are moot.
+            InstKind::Retain { .. } | InstKind::Release { .. } => {
+                self.asm.mov_imm(Reg::Rax, 0);
+                self.store(inst.id, Reg::Rax)
+            }
+            InstKind::Variant { .. }
+            | InstKind::IsVariant { .. }
+            | InstKind::Payload { .. }
+            | InstKind::Trap { .. } => Err(BackendError::Unsupported(
+                "sum types cannot be lowered to x86-64".into(),
+            )),
+        }
+    }
+
+    fn emit_binary(
+        &mut self,
+        id: ValueId,
+        op: BinaryOp,
+        lhs: ValueId,
+        rhs: ValueId,
+    ) -> BackendResult<()> {
+        let lhs_ty = self.value_type(lhs)?;
+        let unsupported = || {
+            Err(BackendError::unsupported(format!(
+                "operator '{op}' on {} values",
+                type_label(lhs_ty)
+            )))
+        };
+        match (lhs_ty, op) {
+            (Type::String, BinaryOp::Eq | BinaryOp::Ne) => {
+                self.load(Reg::Rdi, lhs)?;
+                self.load(Reg::Rsi, rhs)?;
+                self.call_routine(Routine::StrEq);
+                if op == BinaryOp::Ne {
+                    self.asm.alu_imm(Alu::Xor, Reg::Rax, 1);
+                }
+                return self.store(id, Reg::Rax);
+            }
+            (Type::Bool, BinaryOp::Eq | BinaryOp::Ne | BinaryOp::And | BinaryOp::Or) => {}
+            (Type::Bool | Type::String | Type::Float, _) => return unsupported(),
+            (_, BinaryOp::And | BinaryOp::Or) => return unsupported(),
+            _ => {}
+        }
+        self.load(Reg::Rax, lhs)?;
+        self.load(Reg::Rcx, rhs)?;
+        match op {
+            BinaryOp::Add => self.asm.alu(Alu::Add, Reg::Rax, Reg::Rcx),
+            BinaryOp::Sub => self.asm.alu(Alu::Sub, Reg::Rax, Reg::Rcx),
+            BinaryOp::Mul => self.asm.imul(Reg::Rax, Reg::Rcx),
+            BinaryOp::And => self.asm.alu(Alu::And, Reg::Rax, Reg::Rcx),
+            BinaryOp::Or => self.asm.alu(Alu::Or, Reg::Rax, Reg::Rcx),
+            BinaryOp::Div | BinaryOp::Mod => {
+                let nonzero = self.asm.label();
+                self.asm.alu(Alu::Test, Reg::Rcx, Reg::Rcx);
+                self.asm.jcc(Cond::Ne, nonzero);
+                let message = self.rodata.intern(runtime::DIVISION_BY_ZERO);
+                self.asm.lea_symbol(Reg::Rdi, message);
+                self.call_routine(Routine::Trap);
+                self.asm.bind(nonzero);
+                self.asm.idiv(Reg::Rcx);
+                if op == BinaryOp::Mod {
+                    self.asm.mov(Reg::Rax, Reg::Rdx);
+                }
+            }
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => {
+                let cond = match op {
+                    BinaryOp::Eq => Cond::E,
+                    BinaryOp::Ne => Cond::Ne,
+                    BinaryOp::Lt => Cond::L,
+                    BinaryOp::Le => Cond::Le,
+                    BinaryOp::Gt => Cond::G,
+                    _ => Cond::Ge,
+                };
+                self.asm.alu(Alu::Cmp, Reg::Rax, Reg::Rcx);
+                self.asm.set_rax(cond);
+            }
+        }
+        self.store(id, Reg::Rax)
+    }
+
+    fn emit_call(
+        &mut self,
+        id: ValueId,
+        func: &ir::FuncRef,
+        args: &[ValueId],
+    ) -> BackendResult<()> {
+        if let ir::FuncRef::Method(name) = func
+            && runtime_method(name)
+        {
+            match name.as_str() {
+                // Method calls pass the capability token first; the
+                // runtime needs only the payload.
+                "println" | "write_line" => {
+                    match args.get(1) {
+                        Some(value) => {
+                            let ty = self.value_type(*value)?;
+                            if !matches!(ty, Type::String) {
+                                return Err(BackendError::unsupported(format!(
+                                    "'{name}' of {} values on x86-64",
+                                    type_label(ty)
+                                )));
+                            }
+                            self.load(Reg::Rdi, *value)?;
+                        }
+                        None => {
+                            let empty = self.rodata.intern("");
+                            self.asm.lea_symbol(Reg::Rdi, empty);
+                        }
+                    }
+                    self.call_routine(Routine::WriteLine);
+                    self.asm.mov_imm(Reg::Rax, 0);
+                    return self.store(id, Reg::Rax);
+                }
+                _ => {
+                    self.call_routine(Routine::NowMillis);
+                    return self.store(id, Reg::Rax);
+                }
+            }
+        }
+        let (index, _) = resolve_callee(self.module, func, args.len(), "to x86-64")?;
+        for (arg, reg) in args.iter().zip(ARG_REGS) {
+            self.load(reg, *arg)?;
+        }
+        self.asm.call(Symbol::Code(index));
+        self.store(id, Reg::Rax)
+    }
+}
@@ -0,0 +1,261 @@
+//! Linear-scan register allocation over SSA values.
+//!
+//! Blocks are numbered in layout order and each value gets one interval from
+//! its definition to its last use, stretched over every block it is live
+//! across. Intervals are assigned the callee-saved registers, so values
+//! survive calls without saving anything; whatever does not fit is spilled
+//! to a stack slot for its whole lifetime.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{self, BlockId, InstKind, Terminator, ValueId};
+
+use super::asm::Reg;
+
+/// Registers values live in. The code generator saves them in every
+/// prologue and uses only the caller-saved registers as scratch.
+pub const ALLOCATABLE: [Reg; 5] = [Reg::Rbx, Reg::R12, Reg::R13, Reg::R14, Reg::R15];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Loc {
+    Reg(Reg),
+    /// Index of a spill slot in the frame.
+    Slot(u32),
+}
+
+#[derive(Debug, Default)]
+pub struct Allocation {
+    pub locations: HashMap<ValueId, Loc>,
+    pub slots: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interval {
+    value: ValueId,
+    start: usize,
+    end: usize,
+}
+
+pub fn allocate(function: &ir::Function) -> Allocation {
+    let intervals = intervals(function);
+    let mut allocation = Allocation::default();
+    let mut free: Vec<Reg> = ALLOCATABLE.iter().rev().copied().collect();
+    let mut active: Vec<(Interval, Reg)> = Vec::new();
+
+    for interval in intervals {
+        active.retain(|(other, reg)| {
+            let live = other.end >= interval.start;
+            if !live {
+                free.push(*reg);
+            }
+            live
+        });
+        if let Some(reg) = free.pop() {
+            allocation.locations.insert(interval.value, Loc::Reg(reg));
+            active.push((interval, reg));
+            continue;
+        }
+        // Spill whichever interval lives longest, keeping registers for
+        // the values needed soonest.
+        let (index, (longest, reg)) = active
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (other, _))| other.end)
+            .map(|(index, entry)| (index, *entry))
+            .expect("no free register implies active intervals");
+        let slot = Loc::Slot(allocation.slots);
+        allocation.slots += 1;
+        if longest.end > interval.end {
+            allocation.locations.insert(longest.value, slot);
+            allocation.locations.insert(interval.value, Loc::Reg(reg));
+            active[index] = (interval, reg);
+        } else {
+            allocation.locations.insert(interval.value, slot);
+        }
+    }
+    allocation
+}
+
+fn successors(terminator: &Terminator) -> Vec<BlockId> {
+    match terminator {
+        Terminator::Return(_) => Vec::new(),
+        Terminator::Jump(target) => vec![*target],
+        Terminator::Branch {
+            then_block,
+            else_block,
+            ..
+        } => vec![*then_block, *else_block],
+    }
+}
+
+/// Values an instruction reads, other than phi incomings, which are read on
+/// the edge instead.
+pub fn operands(kind: &InstKind) -> Vec<ValueId> {
+    match kind {
+        InstKind::Literal(_) | InstKind::Path(_) | InstKind::Phi { .. } | InstKind::Trap { .. } => {
+            Vec::new()
+        }
+        InstKind::Binary { lhs, rhs, .. } => vec![*lhs, *rhs],
+        InstKind::Call { args, .. } => args.clone(),
+        InstKind::Record { fields, .. } => fields.iter().map(|(_, value)| *value).collect(),
+        InstKind::Field { base, .. } => vec![*base],
+        InstKind::List { elements } => elements.clone(),
+        InstKind::ListLen { list } => vec![*list],
+        InstKind::ListIndex { list, index } => vec![*list, *index],
+        InstKind::ListPush { list, value } => vec![*list, *value],
+        InstKind::Retain { value } | InstKind::Release { value } | InstKind::Convert { value } => {
+            vec![*value]
+        }
+        InstKind::Variant { payload, .. } => payload.clone(),
+        InstKind::IsVariant { value, .. } | InstKind::Payload { value, .. } => vec![*value],
+    }
+}
+
+fn terminator_operands(terminator: &Terminator) -> Vec<ValueId> {
+    match terminator {
+        Terminator::Return(value) => value.iter().copied().collect(),
+        Terminator::Branch { condition, .. } => vec![*condition],
+        Terminator::Jump(_) => Vec::new(),
+    }
+}
+
+/// Live intervals sorted by start. Block `i` starts at the position of its
+/// phis, each instruction takes the next position, and the terminator, where
+/// edge moves read phi inputs, ends the block.
+fn intervals(function: &ir::Function) -> Vec<Interval> {
+    let index_of: HashMap<BlockId, usize> = function
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(index, block)| (block.id, index))
+        .collect();
+
+    let mut ranges: HashMap<ValueId, (usize, usize)> = HashMap::new();
+    let mut touch = |value: ValueId, position: usize| {
+        let range = ranges.entry(value).or_insert((position, position));
+        range.0 = range.0.min(position);
+        range.1 = range.1.max(position);
+    };
+    for param in &function.params {
+        touch(param.value, 0);
+    }
+
+    let mut bounds = Vec::with_capacity(function.blocks.len());
+    let mut position = 0;
+    for block in &function.blocks {
+        let start = position;
+        for inst in &block.instructions {
+            if !matches!(inst.kind, InstKind::Phi { .. }) {
+                position += 1;
+            }
+            touch(inst.id, position);
+            for operand in operands(&inst.kind) {
+                touch(operand, position);
+            }
+        }
+        position += 1;
+        for operand in terminator_operands(&block.terminator) {
+            touch(operand, position);
+        }
+        bounds.push((start, position));
+        position += 1;
+    }
+
+    // Phi inputs are read, and the phi assigned, at the end of each
+    // predecessor.
+    for block in &function.blocks {
+        for inst in &block.instructions {
+            if let InstKind::Phi { incomings } = &inst.kind {
+                for (pred, value) in incomings {
+                    if let Some(pred) = index_of.get(pred) {
+                        touch(*value, bounds[*pred].1);
+                        touch(inst.id, bounds[*pred].1);
+                    }
+                }
+            }
+        }
+    }
+
+    let (live_in, live_out) = liveness(function, &index_of);
+    for (index, (start, end)) in bounds.iter().enumerate() {
+        for value in &live_in[index] {
+            touch(*value, *start);
+        }
+        for value in &live_out[index] {
+            touch(*value, *end);
+        }
+    }
+
+    let mut intervals: Vec<Interval> = ranges
+        .into_iter()
+        .map(|(value, (start, end))| Interval { value, start, end })
+        .collect();
+    intervals.sort_by_key(|interval| (interval.start, interval.value.index()));
+    intervals
+}
+
+type LiveSets = Vec<HashSet<ValueId>>;
+
+fn liveness(function: &ir::Function, index_of: &HashMap<BlockId, usize>) -> (LiveSets, LiveSets) {
+    let count = function.blocks.len();
+    let mut uses = vec![HashSet::new(); count];
+    let mut defs = vec![HashSet::new(); count];
+    let mut phi_defs = vec![HashSet::new(); count];
+    for (index, block) in function.blocks.iter().enumerate() {
+        for inst in &block.instructions {
+            for operand in operands(&inst.kind) {
+                if !defs[index].contains(&operand) {
+                    uses[index].insert(operand);
+                }
+            }
+            if matches!(inst.kind, InstKind::Phi { .. }) {
+                phi_defs[index].insert(inst.id);
+            }
+            defs[index].insert(inst.id);
+        }
+        for operand in terminator_operands(&block.terminator) {
+            if !defs[index].contains(&operand) {
+                uses[index].insert(operand);
+            }
+        }
+    }
+
+    let mut live_in: LiveSets = vec![HashSet::new(); count];
+    let mut live_out: LiveSets = vec![HashSet::new(); count];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, block) in function.blocks.iter().enumerate().rev() {
+            let mut out = HashSet::new();
+            for succ in successors(&block.terminator) {
+                let Some(&succ_index) = index_of.get(&succ) else {
+                    continue;
+                };
+                out.extend(
+                    live_in[succ_index]
+                        .iter()
+                        .filter(|value| !phi_defs[succ_index].contains(value)),
+                );
+                for inst in &function.blocks[succ_index].instructions {
+                    if let InstKind::Phi { incomings } = &inst.kind {
+                        out.extend(
+                            incomings
+                                .iter()
+                                .filter(|(pred, _)| *pred == block.id)
+                                .map(|(_, value)| *value),
+                        );
+                    }
+                }
+            }
+            let mut inn: HashSet<ValueId> = uses[index].clone();
+            inn.extend(out.iter().filter(|value| !defs[index].contains(value)));
+            inn.extend(phi_defs[index].iter().filter(|value| out.contains(value)));
+            if inn != live_in[index] || out != live_out[index] {
+                live_in[index] = inn;
+                live_out[index] = out;
+                changed = true;
+            }
+        }
+    }
+    (live_in, live_out)
+}
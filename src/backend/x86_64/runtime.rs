@@ -0,0 +1,455 @@
+//! Built-in runtime linked into every object: a bump allocator, list
+//! helpers, console output, the clock and traps, all on raw Linux system
+//! calls so executables need no C library.
+//!
+//! Routines take arguments in `rdi`/`rsi` and return in `rax` like SysV
+//! functions, and clobber only caller-saved registers.
+
+use super::asm::{Alu, Assembler, Cond, Reg, Symbol};
+
+/// Bytes reserved in `.bss` for the heap; allocation past it traps.
+pub const HEAP_SIZE: u32 = 256 << 20;
+
+/// `.bss` offset of the number of heap bytes handed out so far.
+const HEAP_USED: Symbol = Symbol::Bss(0);
+/// `.bss` offset of the first heap byte.
+const HEAP: Symbol = Symbol::Bss(16);
+
+/// Size of `.bss`: the heap cursor, padding, and the heap itself.
+pub const BSS_SIZE: u32 = 16 + HEAP_SIZE;
+
+/// Exit status of a program stopped by a trap, as in the C runtime.
+const TRAP_STATUS: i64 = 70;
+
+const SYS_WRITE: i64 = 1;
+const SYS_CLOCK_GETTIME: i64 = 228;
+const SYS_EXIT_GROUP: i64 = 231;
+
+/// Minimum capacity of a list's element buffer.
+const MIN_LIST_CAPACITY: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Routine {
+    /// `_start`: calls `main` and exits with its result.
+    Start,
+    /// `alloc(size) -> ptr`, 8-byte aligned.
+    Alloc,
+    /// `list_new(len) -> list` with `len` uninitialised elements.
+    ListNew,
+    /// `list_push(list, value)`.
+    ListPush,
+    /// `list_index(list, index) -> value`, trapping when out of bounds.
+    ListIndex,
+    /// `str_eq(a, b) -> 0 | 1`.
+    StrEq,
+    /// `write_line(str)` to stdout.
+    WriteLine,
+    /// `now_millis() -> Int`.
+    NowMillis,
+    /// `trap(str)`: prints `error: <str>` to stderr and exits.
+    Trap,
+    /// `trap_index(index, len)`.
+    TrapIndex,
+    /// `write_all(fd, ptr, len)`.
+    WriteAll,
+    /// `write_int(fd, value)` in decimal.
+    WriteInt,
+}
+
+impl Routine {
+    pub const ALL: [Routine; 12] = [
+        Routine::Start,
+        Routine::Alloc,
+        Routine::ListNew,
+        Routine::ListPush,
+        Routine::ListIndex,
+        Routine::StrEq,
+        Routine::WriteLine,
+        Routine::NowMillis,
+        Routine::Trap,
+        Routine::TrapIndex,
+        Routine::WriteAll,
+        Routine::WriteInt,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Routine::Start => "_start",
+            Routine::Alloc => "mica_rt_alloc",
+            Routine::ListNew => "mica_rt_list_new",
+            Routine::ListPush => "mica_rt_list_push",
+            Routine::ListIndex => "mica_rt_list_index",
+            Routine::StrEq => "mica_rt_str_eq",
+            Routine::WriteLine => "mica_rt_write_line",
+            Routine::NowMillis => "mica_rt_now_millis",
+            Routine::Trap => "mica_rt_trap",
+            Routine::TrapIndex => "mica_rt_trap_index",
+            Routine::WriteAll => "mica_rt_write_all",
+            Routine::WriteInt => "mica_rt_write_int",
+        }
+    }
+}
+
+/// Fixed strings the runtime prints, interned into `.rodata` by the caller.
+#[derive(Debug, Clone, Copy)]
+pub struct Messages {
+    pub newline: Symbol,
+    pub error_prefix: Symbol,
+    pub out_of_memory: Symbol,
+    pub index_prefix: Symbol,
+    pub index_middle: Symbol,
+}
+
+pub const NEWLINE: &str = "\n";
+pub const ERROR_PREFIX: &str = "error: ";
+pub const OUT_OF_MEMORY: &str = "out of memory";
+pub const INDEX_PREFIX: &str = "list index ";
+pub const INDEX_MIDDLE: &str = " out of bounds for length ";
+pub const DIVISION_BY_ZERO: &str = "division by zero";
+
+/// Where `_start` finds `main`.
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    pub main: Symbol,
+    pub arity: usize,
+    /// `main` returns an `Int` that becomes the exit status.
+    pub returns_status: bool,
+}
+
+pub struct RuntimeEmitter<'a> {
+    pub asm: &'a mut Assembler,
+    pub symbol: &'a dyn Fn(Routine) -> Symbol,
+    pub messages: Messages,
+}
+
+impl RuntimeEmitter<'_> {
+    fn call(&mut self, routine: Routine) {
+        let symbol = (self.symbol)(routine);
+        self.asm.call(symbol);
+    }
+
+    pub fn emit(&mut self, routine: Routine, entry: Option<Entry>) {
+        match routine {
+            Routine::Start => self.start(entry),
+            Routine::Alloc => self.alloc(),
+            Routine::ListNew => self.list_new(),
+            Routine::ListPush => self.list_push(),
+            Routine::ListIndex => self.list_index(),
+            Routine::StrEq => self.str_eq(),
+            Routine::WriteLine => self.write_line(),
+            Routine::NowMillis => self.now_millis(),
+            Routine::Trap => self.trap(),
+            Routine::TrapIndex => self.trap_index(),
+            Routine::WriteAll => self.write_all(),
+            Routine::WriteInt => self.write_int(),
+        }
+    }
+
+    fn exit(&mut self, status: Reg) {
+        self.asm.mov(Reg::Rdi, status);
+        self.asm.mov_imm(Reg::Rax, SYS_EXIT_GROUP);
+        self.asm.syscall();
+    }
+
+    fn start(&mut self, entry: Option<Entry>) {
+        let asm = &mut *self.asm;
+        let Some(entry) = entry else {
+            // Libraries have no `main`; running one exits cleanly.
+            asm.mov_imm(Reg::Rdi, 0);
+            self.exit(Reg::Rdi);
+            return;
+        };
+        // Capability parameters are tokens with no runtime representation.
+        for reg in [Reg::Rdi, Reg::Rsi, Reg::Rdx, Reg::Rcx, Reg::R8, Reg::R9]
+            .into_iter()
+            .take(entry.arity)
+        {
+            asm.mov_imm(reg, 0);
+        }
+        asm.call(entry.main);
+        if !entry.returns_status {
+            asm.mov_imm(Reg::Rax, 0);
+        }
+        self.exit(Reg::Rax);
+    }
+
+    fn alloc(&mut self) {
+        let oom = self.asm.label();
+        let asm = &mut *self.asm;
+        asm.load_symbol(Reg::Rax, HEAP_USED);
+        asm.alu_imm(Alu::Add, Reg::Rdi, 7);
+        asm.alu_imm(Alu::And, Reg::Rdi, -8);
+        asm.mov(Reg::Rcx, Reg::Rax);
+        asm.alu(Alu::Add, Reg::Rcx, Reg::Rdi);
+        asm.alu_imm(Alu::Cmp, Reg::Rcx, HEAP_SIZE as i32);
+        asm.jcc(Cond::A, oom);
+        asm.store_symbol(HEAP_USED, Reg::Rcx);
+        asm.lea_symbol(Reg::Rdx, HEAP);
+        asm.alu(Alu::Add, Reg::Rax, Reg::Rdx);
+        asm.ret();
+        asm.bind(oom);
+        asm.lea_symbol(Reg::Rdi, self.messages.out_of_memory);
+        let trap = (self.symbol)(Routine::Trap);
+        self.asm.jmp_symbol(trap);
+    }
+
+    /// Lists are a `{len, cap, data}` header so pushes that move the
+    /// elements stay visible through every alias.
+    fn list_new(&mut self) {
+        let big = self.asm.label();
+        let asm = &mut *self.asm;
+        asm.push(Reg::Rbx);
+        asm.push(Reg::R12);
+        asm.mov(Reg::Rbx, Reg::Rdi);
+        asm.mov(Reg::R12, Reg::Rdi);
+        asm.alu_imm(Alu::Cmp, Reg::R12, MIN_LIST_CAPACITY);
+        asm.jcc(Cond::Ge, big);
+        asm.mov_imm(Reg::R12, MIN_LIST_CAPACITY as i64);
+        asm.bind(big);
+        asm.mov(Reg::Rdi, Reg::R12);
+        asm.shl(Reg::Rdi, 3);
+        self.call(Routine::Alloc);
+        let asm = &mut *self.asm;
+        asm.push(Reg::Rax);
+        asm.mov_imm(Reg::Rdi, 24);
+        self.call(Routine::Alloc);
+        let asm = &mut *self.asm;
+        asm.pop(Reg::Rcx);
+        asm.store(Reg::Rax, 0, Reg::Rbx);
+        asm.store(Reg::Rax, 8, Reg::R12);
+        asm.store(Reg::Rax, 16, Reg::Rcx);
+        asm.pop(Reg::R12);
+        asm.pop(Reg::Rbx);
+        asm.ret();
+    }
+
+    fn list_push(&mut self) {
+        let append = self.asm.label();
+        let copy = self.asm.label();
+        let copied = self.asm.label();
+        let asm = &mut *self.asm;
+        asm.load(Reg::Rax, Reg::Rdi, 0);
+        asm.load(Reg::Rcx, Reg::Rdi, 8);
+        asm.alu(Alu::Cmp, Reg::Rax, Reg::Rcx);
+        asm.jcc(Cond::L, append);
+        // Full: move the elements into a buffer twice the size.
+        asm.push(Reg::Rdi);
+        asm.push(Reg::Rsi);
+        asm.mov(Reg::Rdi, Reg::Rcx);
+        asm.shl(Reg::Rdi, 4);
+        self.call(Routine::Alloc);
+        let asm = &mut *self.asm;
+        asm.pop(Reg::Rsi);
+        asm.pop(Reg::Rdi);
+        asm.load(Reg::R8, Reg::Rdi, 16);
+        asm.mov_imm(Reg::Rdx, 0);
+        asm.bind(copy);
+        asm.load(Reg::R9, Reg::Rdi, 0);
+        asm.alu(Alu::Cmp, Reg::Rdx, Reg::R9);
+        asm.jcc(Cond::Ge, copied);
+        asm.mov(Reg::R10, Reg::Rdx);
+        asm.shl(Reg::R10, 3);
+        asm.mov(Reg::R11, Reg::R8);
+        asm.alu(Alu::Add, Reg::R11, Reg::R10);
+        asm.load(Reg::R9, Reg::R11, 0);
+        asm.mov(Reg::R11, Reg::Rax);
+        asm.alu(Alu::Add, Reg::R11, Reg::R10);
+        asm.store(Reg::R11, 0, Reg::R9);
+        asm.alu_imm(Alu::Add, Reg::Rdx, 1);
+        asm.jmp(copy);
+        asm.bind(copied);
+        asm.store(Reg::Rdi, 16, Reg::Rax);
+        asm.load(Reg::Rcx, Reg::Rdi, 8);
+        asm.shl(Reg::Rcx, 1);
+        asm.store(Reg::Rdi, 8, Reg::Rcx);
+        asm.bind(append);
+        asm.load(Reg::Rax, Reg::Rdi, 0);
+        asm.mov(Reg::Rcx, Reg::Rax);
+        asm.shl(Reg::Rcx, 3);
+        asm.load(Reg::R8, Reg::Rdi, 16);
+        asm.alu(Alu::Add, Reg::Rcx, Reg::R8);
+        asm.store(Reg::Rcx, 0, Reg::Rsi);
+        asm.alu_imm(Alu::Add, Reg::Rax, 1);
+        asm.store(Reg::Rdi, 0, Reg::Rax);
+        asm.ret();
+    }
+
+    fn list_index(&mut self) {
+        let out_of_bounds = self.asm.label();
+        let asm = &mut *self.asm;
+        asm.load(Reg::Rax, Reg::Rdi, 0);
+        // Unsigned, so negative indices fail too.
+        asm.alu(Alu::Cmp, Reg::Rsi, Reg::Rax);
+        asm.jcc(Cond::Ae, out_of_bounds);
+        asm.load(Reg::Rax, Reg::Rdi, 16);
+        asm.shl(Reg::Rsi, 3);
+        asm.alu(Alu::Add, Reg::Rax, Reg::Rsi);
+        asm.load(Reg::Rax, Reg::Rax, 0);
+        asm.ret();
+        asm.bind(out_of_bounds);
+        asm.mov(Reg::Rdi, Reg::Rsi);
+        asm.mov(Reg::Rsi, Reg::Rax);
+        let trap = (self.symbol)(Routine::TrapIndex);
+        self.asm.jmp_symbol(trap);
+    }
+
+    /// Strings are a length followed by their bytes.
+    fn str_eq(&mut self) {
+        let differ = self.asm.label();
+        let equal = self.asm.label();
+        let compare = self.asm.label();
+        let asm = &mut *self.asm;
+        asm.load(Reg::Rcx, Reg::Rdi, 0);
+        asm.load(Reg::Rdx, Reg::Rsi, 0);
+        asm.alu(Alu::Cmp, Reg::Rcx, Reg::Rdx);
+        asm.jcc(Cond::Ne, differ);
+        asm.mov_imm(Reg::Rdx, 0);
+        asm.bind(compare);
+        asm.alu(Alu::Cmp, Reg::Rdx, Reg::Rcx);
+        asm.jcc(Cond::Ge, equal);
+        asm.mov(Reg::R9, Reg::Rdi);
+        asm.alu(Alu::Add, Reg::R9, Reg::Rdx);
+        asm.load_byte(Reg::R8, Reg::R9, 8);
+        asm.mov(Reg::R9, Reg::Rsi);
+        asm.alu(Alu::Add, Reg::R9, Reg::Rdx);
+        asm.load_byte(Reg::R10, Reg::R9, 8);
+        asm.alu(Alu::Cmp, Reg::R8, Reg::R10);
+        asm.jcc(Cond::Ne, differ);
+        asm.alu_imm(Alu::Add, Reg::Rdx, 1);
+        asm.jmp(compare);
+        asm.bind(equal);
+        asm.mov_imm(Reg::Rax, 1);
+        asm.ret();
+        asm.bind(differ);
+        asm.mov_imm(Reg::Rax, 0);
+        asm.ret();
+    }
+
+    /// Writes a length-prefixed string from `rsi` to the descriptor in `rdi`.
+    fn write_str(&mut self) {
+        let asm = &mut *self.asm;
+        asm.load(Reg::Rdx, Reg::Rsi, 0);
+        asm.alu_imm(Alu::Add, Reg::Rsi, 8);
+        self.call(Routine::WriteAll);
+    }
+
+    fn write_symbol(&mut self, fd: i64, symbol: Symbol) {
+        self.asm.mov_imm(Reg::Rdi, fd);
+        self.asm.lea_symbol(Reg::Rsi, symbol);
+        self.write_str();
+    }
+
+    fn write_line(&mut self) {
+        self.asm.mov(Reg::Rsi, Reg::Rdi);
+        self.asm.mov_imm(Reg::Rdi, 1);
+        self.write_str();
+        self.write_symbol(1, self.messages.newline);
+        self.asm.ret();
+    }
+
+    fn now_millis(&mut self) {
+        let asm = &mut *self.asm;
+        asm.alu_imm(Alu::Sub, Reg::Rsp, 24);
+        asm.mov_imm(Reg::Rax, SYS_CLOCK_GETTIME);
+        asm.mov_imm(Reg::Rdi, 0);
+        asm.mov(Reg::Rsi, Reg::Rsp);
+        asm.syscall();
+        asm.load(Reg::Rcx, Reg::Rsp, 0);
+        asm.mov_imm(Reg::Rdx, 1000);
+        asm.imul(Reg::Rcx, Reg::Rdx);
+        asm.load(Reg::Rax, Reg::Rsp, 8);
+        asm.mov_imm(Reg::R8, 1_000_000);
+        asm.idiv(Reg::R8);
+        asm.alu(Alu::Add, Reg::Rax, Reg::Rcx);
+        asm.alu_imm(Alu::Add, Reg::Rsp, 24);
+        asm.ret();
+    }
+
+    fn trap(&mut self) {
+        self.asm.push(Reg::Rdi);
+        self.write_symbol(2, self.messages.error_prefix);
+        self.asm.pop(Reg::Rsi);
+        self.asm.mov_imm(Reg::Rdi, 2);
+        self.write_str();
+        self.write_symbol(2, self.messages.newline);
+        self.asm.mov_imm(Reg::Rdi, TRAP_STATUS);
+        self.exit(Reg::Rdi);
+    }
+
+    fn trap_index(&mut self) {
+        self.asm.push(Reg::Rsi);
+        self.asm.push(Reg::Rdi);
+        self.write_symbol(2, self.messages.error_prefix);
+        self.write_symbol(2, self.messages.index_prefix);
+        self.asm.pop(Reg::Rsi);
+        self.asm.mov_imm(Reg::Rdi, 2);
+        self.call(Routine::WriteInt);
+        self.write_symbol(2, self.messages.index_middle);
+        self.asm.pop(Reg::Rsi);
+        self.asm.mov_imm(Reg::Rdi, 2);
+        self.call(Routine::WriteInt);
+        self.write_symbol(2, self.messages.newline);
+        self.asm.mov_imm(Reg::Rdi, TRAP_STATUS);
+        self.exit(Reg::Rdi);
+    }
+
+    /// Retries short writes; gives up on errors, since there is nowhere left
+    /// to report them.
+    fn write_all(&mut self) {
+        let again = self.asm.label();
+        let done = self.asm.label();
+        let asm = &mut *self.asm;
+        asm.bind(again);
+        asm.alu(Alu::Test, Reg::Rdx, Reg::Rdx);
+        asm.jcc(Cond::E, done);
+        asm.mov_imm(Reg::Rax, SYS_WRITE);
+        asm.syscall();
+        asm.alu(Alu::Test, Reg::Rax, Reg::Rax);
+        asm.jcc(Cond::Le, done);
+        asm.alu(Alu::Add, Reg::Rsi, Reg::Rax);
+        asm.alu(Alu::Sub, Reg::Rdx, Reg::Rax);
+        asm.jmp(again);
+        asm.bind(done);
+        asm.ret();
+    }
+
+    /// Formats `rsi` right to left into a stack buffer.
+    fn write_int(&mut self) {
+        let digits = self.asm.label();
+        let positive = self.asm.label();
+        let asm = &mut *self.asm;
+        asm.alu_imm(Alu::Sub, Reg::Rsp, 32);
+        asm.mov(Reg::Rax, Reg::Rsi);
+        // r8 = one past the buffer end, walking down; r10 = sign.
+        asm.lea(Reg::R8, Reg::Rsp, 32);
+        asm.mov(Reg::R10, Reg::Rax);
+        asm.alu_imm(Alu::Cmp, Reg::Rax, 0);
+        asm.jcc(Cond::Ge, positive);
+        asm.mov_imm(Reg::Rcx, 0);
+        asm.alu(Alu::Sub, Reg::Rcx, Reg::Rax);
+        asm.mov(Reg::Rax, Reg::Rcx);
+        asm.bind(positive);
+        asm.mov_imm(Reg::R9, 10);
+        asm.bind(digits);
+        asm.alu_imm(Alu::Sub, Reg::R8, 1);
+        asm.mov_imm(Reg::Rdx, 0);
+        // Unsigned division keeps i64::MIN, negated in place, correct.
+        asm.div(Reg::R9);
+        asm.alu_imm(Alu::Add, Reg::Rdx, b'0' as i32);
+        asm.store_byte(Reg::R8, 0, Reg::Rdx);
+        asm.alu(Alu::Test, Reg::Rax, Reg::Rax);
+        asm.jcc(Cond::Ne, digits);
+        let unsigned = asm.label();
+        asm.alu(Alu::Test, Reg::R10, Reg::R10);
+        asm.jcc(Cond::Ge, unsigned);
+        asm.alu_imm(Alu::Sub, Reg::R8, 1);
+        asm.store_byte_imm(Reg::R8, 0, b'-');
+        asm.bind(unsigned);
+        asm.mov(Reg::Rsi, Reg::R8);
+        asm.lea(Reg::Rdx, Reg::Rsp, 32);
+        asm.alu(Alu::Sub, Reg::Rdx, Reg::R8);
+        self.call(Routine::WriteAll);
+        self.asm.alu_imm(Alu::Add, Reg::Rsp, 32);
+        self.asm.ret();
+    }
+}
@@ -0,0 +1,234 @@
+//! Register bytecode compiled from IR modules.
+//!
+//! [`backend::bytecode::BytecodeBackend`](crate::backend::bytecode) compiles
+//! each IR function into a flat instruction list over numbered registers:
+//! every SSA value gets its own register and phis become moves on the
+//! incoming edges. [`vm::Vm`] executes a [`Program`] and routes capability
+//! methods through [`crate::runtime::Runtime`], and [`serialize`] stores
+//! programs as `.micab` files.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::syntax::ast::BinaryOp;
+
+pub mod serialize;
+pub mod vm;
+
+pub use serialize::{LoadError, from_bytes, load, save, to_bytes};
+pub use vm::{Execution, Value, Vm, VmError};
+
+/// Register index within the current call frame.
+pub type Reg = u32;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    pub name: String,
+    /// String literals and capability names, referenced by index.
+    pub strings: Vec<String>,
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Function {
+    pub name: String,
+    /// Arguments arrive in registers `0..arity`.
+    pub arity: u32,
+    pub registers: u32,
+    /// The function's effect row, which becomes the task's capabilities when
+    /// it is the entry point.
+    pub capabilities: Vec<String>,
+    pub code: Vec<Instr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Instr {
+    Unit {
+        dst: Reg,
+    },
+    Int {
+        dst: Reg,
+        value: i64,
+    },
+    Float {
+        dst: Reg,
+        value: f64,
+    },
+    Bool {
+        dst: Reg,
+        value: bool,
+    },
+    /// Loads `strings[index]`.
+    Str {
+        dst: Reg,
+        index: u32,
+    },
+    Move {
+        dst: Reg,
+        src: Reg,
+    },
+    Binary {
+        op: BinaryOp,
+        dst: Reg,
+        lhs: Reg,
+        rhs: Reg,
+    },
+    IntToFloat {
+        dst: Reg,
+        src: Reg,
+    },
+    /// Truncates toward zero.
+    FloatToInt {
+        dst: Reg,
+        src: Reg,
+    },
+    Call {
+        dst: Reg,
+        function: u32,
+        args: Vec<Reg>,
+    },
+    /// Runs `operation` of `capability` (both indices into `strings`)
+    /// through the runtime.
+    Invoke {
+        dst: Reg,
+        capability: u32,
+        operation: u32,
+        payload: Option<Reg>,
+    },
+    /// Builds a record from field values in declaration order.
+    Record {
+        dst: Reg,
+        fields: Vec<Reg>,
+    },
+    Field {
+        dst: Reg,
+        base: Reg,
+        slot: u32,
+    },
+    List {
+        dst: Reg,
+        elements: Vec<Reg>,
+    },
+    ListLen {
+        dst: Reg,
+        list: Reg,
+    },
+    ListIndex {
+        dst: Reg,
+        list: Reg,
+        index: Reg,
+    },
+    /// Appends `value` to `list` and stores unit in `dst`.
+    ListPush {
+        dst: Reg,
+        list: Reg,
+        value: Reg,
+    },
+    Jump {
+        target: u32,
+    },
+    JumpUnless {
+        cond: Reg,
+        target: u32,
+    },
+    Return {
+        src: Option<Reg>,
+    },
+}
+
+impl Program {
+    /// Index and body of the function called `name`.
+    pub fn function(&self, name: &str) -> Option<(u32, &Function)> {
+        self.functions
+            .iter()
+            .enumerate()
+            .find(|(_, function)| function.name == name)
+            .map(|(index, function)| (index as u32, function))
+    }
+}
+
+impl fmt::Display for Program {
+    /// Prints a disassembly listing.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "program {}", self.name)?;
+        for function in &self.functions {
+            write!(
+                f,
+                "\nfn {} (arity {}, registers {})",
+                function.name, function.arity, function.registers
+            )?;
+            if !function.capabilities.is_empty() {
+                write!(f, " !{{{}}}", function.capabilities.join(", "))?;
+            }
+            writeln!(f)?;
+            for (pc, instr) in function.code.iter().enumerate() {
+                write!(f, "  {pc:>4}: ")?;
+                self.fmt_instr(f, instr)?;
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Program {
+    fn fmt_instr(&self, f: &mut fmt::Formatter<'_>, instr: &Instr) -> fmt::Result {
+        let string = |index: &u32| {
+            self.strings
+                .get(*index as usize)
+                .map(String::as_str)
+                .unwrap_or("<missing>")
+        };
+        let function = |index: &u32| {
+            self.functions
+                .get(*index as usize)
+                .map(|function| function.name.as_str())
+                .unwrap_or("<missing>")
+        };
+        let regs = |regs: &[Reg]| {
+            regs.iter()
+                .map(|reg| format!("r{reg}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match instr {
+            Instr::Unit { dst } => write!(f, "r{dst} = ()"),
+            Instr::Int { dst, value } => write!(f, "r{dst} = {value}"),
+            Instr::Float { dst, value } => write!(f, "r{dst} = {value:?}"),
+            Instr::Bool { dst, value } => write!(f, "r{dst} = {value}"),
+            Instr::Str { dst, index } => write!(f, "r{dst} = {:?}", string(index)),
+            Instr::Move { dst, src } => write!(f, "r{dst} = r{src}"),
+            Instr::Binary { op, dst, lhs, rhs } => write!(f, "r{dst} = r{lhs} {op} r{rhs}"),
+            Instr::IntToFloat { dst, src } => write!(f, "r{dst} = float r{src}"),
+            Instr::FloatToInt { dst, src } => write!(f, "r{dst} = int r{src}"),
+            Instr::Call {
+                dst,
+                function: callee,
+                args,
+            } => write!(f, "r{dst} = call {}({})", function(callee), regs(args)),
+            Instr::Invoke {
+                dst,
+                capability,
+                operation,
+                payload,
+            } => write!(
+                f,
+                "r{dst} = invoke {}.{}({})",
+                string(capability),
+                string(operation),
+                regs(payload.as_slice())
+            ),
+            Instr::Record { dst, fields } => write!(f, "r{dst} = record {{{}}}", regs(fields)),
+            Instr::Field { dst, base, slot } => write!(f, "r{dst} = r{base}.{slot}"),
+            Instr::List { dst, elements } => write!(f, "r{dst} = list [{}]", regs(elements)),
+            Instr::ListLen { dst, list } => write!(f, "r{dst} = len r{list}"),
+            Instr::ListIndex { dst, list, index } => write!(f, "r{dst} = r{list}[r{index}]"),
+            Instr::ListPush { dst, list, value } => write!(f, "r{dst} = push r{list}, r{value}"),
+            Instr::Jump { target } => write!(f, "jump {target}"),
+            Instr::JumpUnless { cond, target } => write!(f, "jump {target} unless r{cond}"),
+            Instr::Return { src: Some(src) } => write!(f, "return r{src}"),
+            Instr::Return { src: None } => write!(f, "return"),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! The `.micab` file format.
+//!
+//! A file is the [`MAGIC`] bytes, [`FORMAT_VERSION`] as a little-endian
+//! `u32`, then the [`Program`] in bincode's varint encoding. Loading checks
+//! every register, string, function and jump target against the program, so
+//! the VM never indexes out of bounds on a corrupt file.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use bincode::Options;
+
+use super::{Instr, Program, Reg};
+
+/// Leading bytes of a `.micab` file.
+pub const MAGIC: [u8; 4] = *b"MICB";
+
+/// Version of the serialized program layout; bump it whenever `Program` or
+/// `Instr` change shape.
+pub const FORMAT_VERSION: u32 = 2;
+
+const HEADER_LEN: usize = MAGIC.len() + 4;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// The input is not a serialized program at all.
+    Format(String),
+    /// The input was written with a different format version.
+    Version {
+        found: u32,
+    },
+    /// The program decoded but refers to registers, strings, functions or
+    /// instructions that do not exist.
+    Invalid(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "failed to read bytecode: {}", err),
+            LoadError::Format(msg) => write!(f, "malformed bytecode: {}", msg),
+            LoadError::Version { found } => write!(
+                f,
+                "bytecode version {} is not supported (expected {})",
+                found, FORMAT_VERSION
+            ),
+            LoadError::Invalid(msg) => write!(f, "invalid bytecode: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+fn binary_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// Whether `bytes` start like a `.micab` file.
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+pub fn to_bytes(program: &Program) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    binary_options()
+        .serialize_into(&mut bytes, program)
+        .expect("bytecode programs serialize to bytes");
+    bytes
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<Program, LoadError> {
+    if bytes.len() < HEADER_LEN || !is_bytecode(bytes) {
+        return Err(LoadError::Format("missing bytecode header".to_string()));
+    }
+    let mut version = [0u8; 4];
+    version.copy_from_slice(&bytes[MAGIC.len()..HEADER_LEN]);
+    let found = u32::from_le_bytes(version);
+    if found != FORMAT_VERSION {
+        return Err(LoadError::Version { found });
+    }
+    let program: Program = binary_options()
+        .deserialize(&bytes[HEADER_LEN..])
+        .map_err(|err| LoadError::Format(err.to_string()))?;
+    validate(&program)?;
+    Ok(program)
+}
+
+/// Writes `program` to `path` as a `.micab` file.
+pub fn save(program: &Program, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, to_bytes(program))
+}
+
+/// Reads a program written by [`save`].
+pub fn load(path: impl AsRef<Path>) -> Result<Program, LoadError> {
+    from_bytes(&fs::read(path)?)
+}
+
+fn validate(program: &Program) -> Result<(), LoadError> {
+    for function in &program.functions {
+        let invalid = |what: String| {
+            Err(LoadError::Invalid(format!(
+                "function `{}` {}",
+                function.name, what
+            )))
+        };
+        if function.arity > function.registers {
+            return invalid(format!(
+                "takes {} arguments in {} registers",
+                function.arity, function.registers
+            ));
+        }
+        for (pc, instr) in function.code.iter().enumerate() {
+            if let Some(reg) = registers(instr).find(|reg| *reg >= function.registers) {
+                return invalid(format!("uses register r{reg} at {pc}"));
+            }
+            match instr {
+                Instr::Str { index, .. }
+                | Instr::Invoke {
+                    capability: index, ..
+                } if *index as usize >= program.strings.len() => {
+                    return invalid(format!("uses unknown string #{index} at {pc}"));
+                }
+                Instr::Invoke { operation, .. } if *operation as usize >= program.strings.len() => {
+                    return invalid(format!("uses unknown string #{operation} at {pc}"));
+                }
+                Instr::Call {
+                    function: callee,
+                    args,
+                    ..
+                } => match program.functions.get(*callee as usize) {
+                    None => return invalid(format!("calls unknown function #{callee} at {pc}")),
+                    Some(target) if target.arity as usize != args.len() => {
+                        return invalid(format!(
+                            "passes {} arguments to `{}` at {pc}",
+                            args.len(),
+                            target.name
+                        ));
+                    }
+                    Some(_) => {}
+                },
+                Instr::Jump { target } | Instr::JumpUnless { target, .. }
+                    if *target as usize >= function.code.len() =>
+                {
+                    return invalid(format!("jumps to {target} at {pc}"));
+                }
+                _ => {}
+            }
+        }
+        if !matches!(
+            function.code.last(),
+            Some(Instr::Return { .. } | Instr::Jump { .. })
+        ) {
+            return invalid("can run past its last instruction".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Every register `instr` reads or writes.
+fn registers(instr: &Instr) -> impl Iterator<Item = Reg> + '_ {
+    let (fixed, list): ([Option<Reg>; 3], &[Reg]) = match instr {
+        Instr::Unit { dst }
+        | Instr::Int { dst, .. }
+        | Instr::Float { dst, .. }
+        | Instr::Bool { dst, .. }
+        | Instr::Str { dst, .. } => ([Some(*dst), None, None], &[]),
+        Instr::Move { dst, src }
+        | Instr::IntToFloat { dst, src }
+        | Instr::FloatToInt { dst, src } => ([Some(*dst), Some(*src), None], &[]),
+        Instr::Binary { dst, lhs, rhs, .. } => ([Some(*dst), Some(*lhs), Some(*rhs)], &[]),
+        Instr::Call { dst, args, .. } => ([Some(*dst), None, None], args),
+        Instr::Invoke { dst, payload, .. } => ([Some(*dst), *payload, None], &[]),
+        Instr::Record { dst, fields } => ([Some(*dst), None, None], fields),
+        Instr::Field { dst, base, .. } => ([Some(*dst), Some(*base), None], &[]),
+        Instr::List { dst, elements } => ([Some(*dst), None, None], elements),
+        Instr::ListLen { dst, list } => ([Some(*dst), Some(*list), None], &[]),
+        Instr::ListIndex { dst, list, index } => ([Some(*dst), Some(*list), Some(*index)], &[]),
+        Instr::ListPush { dst, list, value } => ([Some(*dst), Some(*list), Some(*value)], &[]),
+        Instr::JumpUnless { cond, .. } => ([Some(*cond), None, None], &[]),
+        Instr::Return { src } => ([*src, None, None], &[]),
+        Instr::Jump { .. } => ([None, None, None], &[]),
+    };
+    fixed.into_iter().flatten().chain(list.iter().copied())
+}
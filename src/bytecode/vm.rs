@@ -0,0 +1,482 @@
+//! Register machine that executes bytecode [`Program`]s.
+//!
+//! Calls push frames onto an explicit stack instead of recursing on the host
+//! stack, and every frame's registers live in one shared vector. Capability
+//! instructions go through [`Runtime::invoke`] under a task built from the
+//! entry function's effect row, so a program can only reach the capabilities
+//! its `main` declares.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::runtime::{
+    CapabilityEvent, Runtime, RuntimeError, RuntimeEvent, RuntimeValue, TaskSpec,
+};
+use crate::syntax::ast::BinaryOp;
+
+use super::{Instr, Program, Reg};
+
+/// Calls nested deeper than this fail instead of exhausting memory.
+const MAX_CALL_DEPTH: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unit,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(Rc<str>),
+    /// Field values in declaration order.
+    Record(Rc<[Value]>),
+    /// Lists are shared handles, as in the interpreter.
+    List(Rc<RefCell<Vec<Value>>>),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Unit => "unit",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::String(_) => "string",
+            Value::Record(_) => "record",
+            Value::List(_) => "list",
+        }
+    }
+
+    fn to_runtime(&self) -> Option<RuntimeValue> {
+        match self {
+            Value::Unit => Some(RuntimeValue::Unit),
+            Value::Int(value) => Some(RuntimeValue::Int(*value)),
+            Value::Float(value) => Some(RuntimeValue::Float(*value)),
+            Value::Bool(value) => Some(RuntimeValue::Bool(*value)),
+            Value::String(value) => Some(RuntimeValue::String(value.to_string())),
+            Value::Record(_) | Value::List(_) => None,
+        }
+    }
+}
+
+impl From<RuntimeValue> for Value {
+    fn from(value: RuntimeValue) -> Self {
+        match value {
+            RuntimeValue::Unit => Value::Unit,
+            RuntimeValue::Int(value) => Value::Int(value),
+            RuntimeValue::Float(value) => Value::Float(value),
+            RuntimeValue::Bool(value) => Value::Bool(value),
+            RuntimeValue::String(value) => Value::String(value.into()),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, values: &[Value]| {
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{value}")?;
+            }
+            Ok(())
+        };
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Int(value) => write!(f, "{value}"),
+            Value::Float(value) => write!(f, "{value}"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::String(value) => write!(f, "{value:?}"),
+            Value::Record(fields) => {
+                write!(f, "{{ ")?;
+                join(f, fields)?;
+                write!(f, " }}")
+            }
+            Value::List(items) => {
+                write!(f, "[")?;
+                join(f, &items.borrow())?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum VmError {
+    /// A capability call was rejected by the runtime.
+    Runtime(RuntimeError),
+    /// The requested entry function does not exist or was called with the
+    /// wrong number of arguments.
+    Entry(String),
+    /// The program faulted, e.g. by dividing by zero.
+    Trap { function: String, message: String },
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VmError::Runtime(err) => write!(f, "{}", err),
+            VmError::Entry(msg) => write!(f, "{}", msg),
+            VmError::Trap { function, message } => write!(f, "{} (in '{}')", message, function),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+impl From<RuntimeError> for VmError {
+    fn from(err: RuntimeError) -> Self {
+        VmError::Runtime(err)
+    }
+}
+
+/// Result of running a program: the entry function's return value plus the
+/// runtime events its capability calls produced.
+#[derive(Debug, Clone)]
+pub struct Execution {
+    pub value: Value,
+    pub events: Vec<RuntimeEvent>,
+}
+
+impl Execution {
+    /// Lines written through the `io` capability, in order.
+    pub fn output(&self) -> Vec<&str> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                RuntimeEvent::CapabilityEvent {
+                    capability,
+                    event: CapabilityEvent::Message(line),
+                    ..
+                } if capability == "io" => Some(line.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+pub struct Vm<'p> {
+    program: &'p Program,
+    runtime: Runtime,
+    strings: Vec<Rc<str>>,
+}
+
+struct Frame {
+    function: usize,
+    pc: usize,
+    base: usize,
+    /// Caller register that receives the return value.
+    ret: Reg,
+}
+
+impl<'p> Vm<'p> {
+    pub fn new(program: &'p Program, runtime: Runtime) -> Self {
+        let strings = program
+            .strings
+            .iter()
+            .map(|s| Rc::from(s.as_str()))
+            .collect();
+        Vm {
+            program,
+            runtime,
+            strings,
+        }
+    }
+
+    /// Runs `main`, passing a unit capability token for each parameter.
+    pub fn run(&self) -> Result<Execution, VmError> {
+        let arity = self
+            .program
+            .function("main")
+            .map(|(_, main)| main.arity as usize)
+            .unwrap_or(0);
+        self.call("main", vec![Value::Unit; arity])
+    }
+
+    /// Runs the function called `name` with `args` as the entry task.
+    pub fn call(&self, name: &str, args: Vec<Value>) -> Result<Execution, VmError> {
+        let Some((index, function)) = self.program.function(name) else {
+            return Err(VmError::Entry(format!("program has no function '{name}'")));
+        };
+        if function.arity as usize != args.len() {
+            return Err(VmError::Entry(format!(
+                "'{name}' takes {} arguments but got {}",
+                function.arity,
+                args.len()
+            )));
+        }
+        let task = TaskSpec::new(format!("{}::{}", self.program.name, name))
+            .with_capabilities(function.capabilities.iter().cloned());
+        let mut events = Vec::new();
+        let value = self.execute(index as usize, args, &task, &mut events)?;
+        Ok(Execution { value, events })
+    }
+
+    fn trap(&self, function: &str, message: impl Into<String>) -> VmError {
+        VmError::Trap {
+            function: function.to_string(),
+            message: message.into(),
+        }
+    }
+
+    fn execute(
+        &self,
+        entry: usize,
+        args: Vec<Value>,
+        task: &TaskSpec,
+        events: &mut Vec<RuntimeEvent>,
+    ) -> Result<Value, VmError> {
+        let functions = &self.program.functions;
+        let mut registers = vec![Value::Unit; functions[entry].registers as usize];
+        for (slot, arg) in registers.iter_mut().zip(args) {
+            *slot = arg;
+        }
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut frame = Frame {
+            function: entry,
+            pc: 0,
+            base: 0,
+            ret: 0,
+        };
+
+        loop {
+            let function = &functions[frame.function];
+            let Some(instr) = function.code.get(frame.pc) else {
+                return Err(self.trap(&function.name, "ran past the end of the function"));
+            };
+            frame.pc += 1;
+            let base = frame.base;
+            let reg = |r: &Reg| base + *r as usize;
+            match instr {
+                Instr::Unit { dst } => registers[reg(dst)] = Value::Unit,
+                Instr::Int { dst, value } => registers[reg(dst)] = Value::Int(*value),
+                Instr::Float { dst, value } => registers[reg(dst)] = Value::Float(*value),
+                Instr::Bool { dst, value } => registers[reg(dst)] = Value::Bool(*value),
+                Instr::Str { dst, index } => {
+                    registers[reg(dst)] = Value::String(self.strings[*index as usize].clone())
+                }
+                Instr::Move { dst, src } => registers[reg(dst)] = registers[reg(src)].clone(),
+                Instr::Binary { op, dst, lhs, rhs } => {
+                    let value = binary(*op, &registers[reg(lhs)], &registers[reg(rhs)])
+                        .map_err(|message| self.trap(&function.name, message))?;
+                    registers[reg(dst)] = value;
+                }
+                Instr::IntToFloat { dst, src } => {
+                    let Value::Int(value) = registers[reg(src)] else {
+                        return Err(self.trap(
+                            &function.name,
+                            format!("int to float applied to a {}", registers[reg(src)].kind()),
+                        ));
+                    };
+                    registers[reg(dst)] = Value::Float(value as f64);
+                }
+                Instr::FloatToInt { dst, src } => {
+                    let Value::Float(value) = registers[reg(src)] else {
+                        return Err(self.trap(
+                            &function.name,
+                            format!("float to int applied to a {}", registers[reg(src)].kind()),
+                        ));
+                    };
+                    registers[reg(dst)] = Value::Int(value as i64);
+                }
+                Instr::Call {
+                    dst,
+                    function: callee,
+                    args,
+                } => {
+                    if frames.len() >= MAX_CALL_DEPTH {
+                        return Err(self.trap(&function.name, "call stack overflow"));
+                    }
+                    let callee = *callee as usize;
+                    let callee_base = registers.len();
+                    registers.resize(
+                        callee_base + functions[callee].registers as usize,
+                        Value::Unit,
+                    );
+                    for (slot, arg) in args.iter().enumerate() {
+                        registers[callee_base + slot] = registers[reg(arg)].clone();
+                    }
+                    let caller = std::mem::replace(
+                        &mut frame,
+                        Frame {
+                            function: callee,
+                            pc: 0,
+                            base: callee_base,
+                            ret: *dst,
+                        },
+                    );
+                    frames.push(caller);
+                }
+                Instr::Invoke {
+                    dst,
+                    capability,
+                    operation,
+                    payload,
+                } => {
+                    let capability = &self.program.strings[*capability as usize];
+                    let operation = &self.program.strings[*operation as usize];
+                    let payload = match payload {
+                        Some(payload) => {
+                            let value = &registers[reg(payload)];
+                            let Some(value) = value.to_runtime() else {
+                                return Err(self.trap(
+                                    &function.name,
+                                    format!(
+                                        "cannot pass a {} to {capability}.{operation}",
+                                        value.kind()
+                                    ),
+                                ));
+                            };
+                            Some(value)
+                        }
+                        None => None,
+                    };
+                    let response = self.runtime.invoke(task, capability, operation, payload)?;
+                    events.push(RuntimeEvent::CapabilityInvoked {
+                        task: task.name().to_string(),
+                        capability: capability.clone(),
+                        operation: operation.clone(),
+                    });
+                    events.extend(response.events.into_iter().map(|event| {
+                        RuntimeEvent::CapabilityEvent {
+                            task: task.name().to_string(),
+                            capability: capability.clone(),
+                            event,
+                        }
+                    }));
+                    registers[reg(dst)] = response.value.into();
+                }
+                Instr::Record { dst, fields } => {
+                    let fields: Rc<[Value]> =
+                        fields.iter().map(|f| registers[reg(f)].clone()).collect();
+                    registers[reg(dst)] = Value::Record(fields);
+                }
+                Instr::Field { dst, base, slot } => {
+                    let value = match &registers[reg(base)] {
+                        Value::Record(fields) => fields.get(*slot as usize).cloned(),
+                        _ => None,
+                    };
+                    let Some(value) = value else {
+                        return Err(self.trap(
+                            &function.name,
+                            format!("field {slot} read from a {}", registers[reg(base)].kind()),
+                        ));
+                    };
+                    registers[reg(dst)] = value;
+                }
+                Instr::List { dst, elements } => {
+                    let items = elements.iter().map(|e| registers[reg(e)].clone()).collect();
+                    registers[reg(dst)] = Value::List(Rc::new(RefCell::new(items)));
+                }
+                Instr::ListLen { dst, list } => {
+                    let items = self.list(&function.name, &registers[reg(list)])?;
+                    let len = items.borrow().len() as i64;
+                    registers[reg(dst)] = Value::Int(len);
+                }
+                Instr::ListIndex { dst, list, index } => {
+                    let items = self.list(&function.name, &registers[reg(list)])?;
+                    let Value::Int(index) = registers[reg(index)] else {
+                        return Err(self.trap(
+                            &function.name,
+                            format!("list index is a {}", registers[reg(index)].kind()),
+                        ));
+                    };
+                    let item = usize::try_from(index)
+                        .ok()
+                        .and_then(|i| items.borrow().get(i).cloned());
+                    let Some(item) = item else {
+                        return Err(self.trap(
+                            &function.name,
+                            format!(
+                                "list index {index} out of bounds for length {}",
+                                items.borrow().len()
+                            ),
+                        ));
+                    };
+                    registers[reg(dst)] = item;
+                }
+                Instr::ListPush { dst, list, value } => {
+                    let items = self.list(&function.name, &registers[reg(list)])?;
+                    items.borrow_mut().push(registers[reg(value)].clone());
+                    registers[reg(dst)] = Value::Unit;
+                }
+                Instr::Jump { target } => frame.pc = *target as usize,
+                Instr::JumpUnless { cond, target } => match registers[reg(cond)] {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => frame.pc = *target as usize,
+                    ref other => {
+                        return Err(self.trap(
+                            &function.name,
+                            format!("branch condition is a {}", other.kind()),
+                        ));
+                    }
+                },
+                Instr::Return { src } => {
+                    let value = match src {
+                        Some(src) => registers[reg(src)].clone(),
+                        None => Value::Unit,
+                    };
+                    registers.truncate(frame.base);
+                    let Some(caller) = frames.pop() else {
+                        return Ok(value);
+                    };
+                    let dst = caller.base + frame.ret as usize;
+                    registers[dst] = value;
+                    frame = caller;
+                }
+            }
+        }
+    }
+
+    fn list(&self, function: &str, value: &Value) -> Result<Rc<RefCell<Vec<Value>>>, VmError> {
+        match value {
+            Value::List(items) => Ok(items.clone()),
+            other => Err(self.trap(function, format!("expected a list, got a {}", other.kind()))),
+        }
+    }
+}
+
+/// Evaluates a binary operator with the interpreter's semantics; integer
+/// arithmetic wraps like the native backends.
+fn binary(op: BinaryOp, lhs: &Value, rhs: &Value) -> Result<Value, String> {
+    use BinaryOp::*;
+    let value = match (op, lhs, rhs) {
+        (Add, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_add(*b)),
+        (Sub, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_sub(*b)),
+        (Mul, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_mul(*b)),
+        (Div | Mod, Value::Int(_), Value::Int(0)) => return Err("division by zero".into()),
+        (Div, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_div(*b)),
+        (Mod, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_rem(*b)),
+        (Add, Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+        (Sub, Value::Float(a), Value::Float(b)) => Value::Float(a - b),
+        (Mul, Value::Float(a), Value::Float(b)) => Value::Float(a * b),
+        (Div, Value::Float(a), Value::Float(b)) => Value::Float(a / b),
+        (Eq, Value::Int(a), Value::Int(b)) => Value::Bool(a == b),
+        (Ne, Value::Int(a), Value::Int(b)) => Value::Bool(a != b),
+        (Lt, Value::Int(a), Value::Int(b)) => Value::Bool(a < b),
+        (Le, Value::Int(a), Value::Int(b)) => Value::Bool(a <= b),
+        (Gt, Value::Int(a), Value::Int(b)) => Value::Bool(a > b),
+        (Ge, Value::Int(a), Value::Int(b)) => Value::Bool(a >= b),
+        (Eq, Value::Float(a), Value::Float(b)) => Value::Bool(a == b),
+        (Ne, Value::Float(a), Value::Float(b)) => Value::Bool(a != b),
+        (Lt, Value::Float(a), Value::Float(b)) => Value::Bool(a < b),
+        (Le, Value::Float(a), Value::Float(b)) => Value::Bool(a <= b),
+        (Gt, Value::Float(a), Value::Float(b)) => Value::Bool(a > b),
+        (Ge, Value::Float(a), Value::Float(b)) => Value::Bool(a >= b),
+        (Eq, Value::Bool(a), Value::Bool(b)) => Value::Bool(a == b),
+        (Ne, Value::Bool(a), Value::Bool(b)) => Value::Bool(a != b),
+        (Eq, Value::String(a), Value::String(b)) => Value::Bool(a == b),
+        (Ne, Value::String(a), Value::String(b)) => Value::Bool(a != b),
+        (And, Value::Bool(a), Value::Bool(b)) => Value::Bool(*a && *b),
+        (Or, Value::Bool(a), Value::Bool(b)) => Value::Bool(*a || *b),
+        _ => {
+            return Err(format!(
+                "unsupported operation: {} {} {}",
+                lhs.kind(),
+                op,
+                rhs.kind()
+            ));
+        }
+    };
+    Ok(value)
+}
@@ -1,7 +1,11 @@
-use crate::ir::{BlockId, FuncRef, Function, InstKind, Instruction, Module, Terminator, ValueId};
+use crate::ir::{
+    BlockId, FuncRef, Function, InstKind, Instruction, Module, Terminator, Type, ValueId,
+};
 use crate::syntax::ast::{BinaryOp, Literal};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::Write as _;
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub enum Value {
@@ -11,6 +15,11 @@ pub enum Value {
     Bool(bool),
     String(String),
     Record(HashMap<String, Value>),
+    /// Lists are shared handles: copies alias the same storage, so a `push`
+    /// through one binding is visible through every other.
+    List(Rc<RefCell<Vec<Value>>>),
+    /// A sum value: the variant's name and its payload.
+    Variant(String, Vec<Value>),
 }
 
 impl Value {
@@ -34,6 +43,19 @@ impl Value {
                 s.push_str(" }");
                 s
             }
+            Value::List(items) => {
+                let items: Vec<String> = items
+                    .borrow()
+                    .iter()
+                    .map(Value::to_display_string)
+                    .collect();
+                format!("[{}]", items.join(", "))
+            }
+            Value::Variant(name, payload) if payload.is_empty() => name.clone(),
+            Value::Variant(name, payload) => {
+                let payload: Vec<String> = payload.iter().map(Value::to_display_string).collect();
+                format!("{}({})", name, payload.join(", "))
+            }
         }
     }
 
@@ -122,6 +144,7 @@ impl Interpreter {
 
         // Execute blocks starting from block 0
         let mut current_block_id = BlockId::from_index(0);
+        let mut previous_block_id: Option<BlockId> = None;
 
         loop {
             let block = func
@@ -130,12 +153,28 @@ impl Interpreter {
                 .find(|b| b.id == current_block_id)
                 .ok_or_else(|| format!("Block {:?} not found", current_block_id))?;
 
+            // Phis read the values flowing out of the predecessor, so they are
+            // all evaluated before any of them is written back.
+            let mut phi_values = Vec::new();
+            for inst in &block.instructions {
+                if let InstKind::Phi { incomings } = &inst.kind {
+                    let value = self.execute_phi(incomings, previous_block_id, &values)?;
+                    phi_values.push((inst.id, value));
+                }
+            }
+            values.extend(phi_values);
+
             // Execute instructions
             for inst in &block.instructions {
+                if matches!(inst.kind, InstKind::Phi { .. }) {
+                    continue;
+                }
                 let value = self.execute_instruction(inst, &values)?;
                 values.insert(inst.id, value);
             }
 
+            previous_block_id = Some(current_block_id);
+
             // Execute terminator
             match &block.terminator {
                 Terminator::Return(val_id) => {
@@ -208,24 +247,138 @@ impl Interpreter {
                 }
                 Ok(Value::Record(record))
             }
+            InstKind::Field { base, name } => match values.get(base) {
+                Some(Value::Record(fields)) => fields
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("Record has no field '{}'", name)),
+                Some(other) => Err(format!("Expected record, got {:?}", other)),
+                None => Err(format!("Field base {:?} not found", base)),
+            },
+            InstKind::Convert { value } => {
+                let value = values
+                    .get(value)
+                    .ok_or_else(|| format!("Converted value {:?} not found", value))?;
+                match (value, self.module.type_of(inst.ty)) {
+                    (Value::Int(n), Type::Float) => Ok(Value::Float(*n as f64)),
+                    (Value::Float(x), Type::Int) => Ok(Value::Int(*x as i64)),
+                    (Value::Int(_), Type::Int) | (Value::Float(_), Type::Float) => {
+                        Ok(value.clone())
+                    }
+                    (other, ty) => Err(format!("Cannot convert {:?} to {:?}", other, ty)),
+                }
+            }
             InstKind::Path(_path) => {
                 // For now, return a placeholder
                 Ok(Value::Unit)
             }
-            InstKind::Phi { incomings } => {
-                // For simple cases, just take the first value
-                if let Some((_, val_id)) = incomings.first() {
-                    values
-                        .get(val_id)
-                        .cloned()
-                        .ok_or_else(|| format!("Phi value {:?} not found", val_id))
-                } else {
-                    Ok(Value::Unit)
-                }
+            InstKind::Phi { .. } => Err("Phi evaluated outside block entry".to_string()),
+            InstKind::List { elements } => {
+                let items = elements
+                    .iter()
+                    .map(|id| {
+                        values
+                            .get(id)
+                            .cloned()
+                            .ok_or_else(|| format!("List element {:?} not found", id))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::List(Rc::new(RefCell::new(items))))
+            }
+            InstKind::ListLen { list } => {
+                let items = self.list_operand(list, values)?;
+                Ok(Value::Int(items.borrow().len() as i64))
+            }
+            InstKind::ListIndex { list, index } => {
+                let items = self.list_operand(list, values)?;
+                let index = values
+                    .get(index)
+                    .ok_or_else(|| format!("Index value {:?} not found", index))?
+                    .as_int()?;
+                let items = items.borrow();
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|i| items.get(i))
+                    .cloned()
+                    .ok_or_else(|| {
+                        format!(
+                            "List index {} out of bounds for length {}",
+                            index,
+                            items.len()
+                        )
+                    })
+            }
+            InstKind::ListPush { list, value } => {
+                let items = self.list_operand(list, values)?;
+                let value = values
+                    .get(value)
+                    .cloned()
+                    .ok_or_else(|| format!("Pushed value {:?} not found", value))?;
+                items.borrow_mut().push(value);
+                Ok(Value::Unit)
+            }
+            InstKind::Retain { .. } | InstKind::Release { .. } => Ok(Value::Unit),
+            InstKind::Variant { variant, payload } => {
+                let payload = payload
+                    .iter()
+                    .map(|id| {
+                        values
+                            .get(id)
+                            .cloned()
+                            .ok_or_else(|| format!("Payload value {:?} not found", id))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::Variant(variant.clone(), payload))
             }
+            InstKind::IsVariant { value, variant } => match values.get(value) {
+                Some(Value::Variant(name, _)) => Ok(Value::Bool(name == variant)),
+                Some(other) => Err(format!("Expected variant, got {:?}", other)),
+                None => Err(format!("Variant value {:?} not found", value)),
+            },
+            InstKind::Payload {
+                value,
+                variant,
+                index,
+            } => match values.get(value) {
+                Some(Value::Variant(name, payload)) if name == variant => payload
+                    .get(*index as usize)
+                    .cloned()
+                    .ok_or_else(|| format!("{} has no payload field {}", variant, index)),
+                Some(other) => Err(format!("Expected {}, got {:?}", variant, other)),
+                None => Err(format!("Variant value {:?} not found", value)),
+            },
+            InstKind::Trap { message } => Err(message.clone()),
+        }
+    }
+
+    fn list_operand(
+        &self,
+        id: &ValueId,
+        values: &HashMap<ValueId, Value>,
+    ) -> Result<Rc<RefCell<Vec<Value>>>, String> {
+        match values.get(id) {
+            Some(Value::List(items)) => Ok(Rc::clone(items)),
+            Some(other) => Err(format!("Expected list, got {:?}", other)),
+            None => Err(format!("List value {:?} not found", id)),
         }
     }
 
+    fn execute_phi(
+        &self,
+        incomings: &[(BlockId, ValueId)],
+        previous: Option<BlockId>,
+        values: &HashMap<ValueId, Value>,
+    ) -> Result<Value, String> {
+        let (_, val_id) = incomings
+            .iter()
+            .find(|(block, _)| Some(*block) == previous)
+            .ok_or_else(|| format!("Phi has no incoming value for {:?}", previous))?;
+        values
+            .get(val_id)
+            .cloned()
+            .ok_or_else(|| format!("Phi value {:?} not found", val_id))
+    }
+
     fn execute_literal(&self, lit: &Literal) -> Result<Value, String> {
         match lit {
             Literal::Unit => Ok(Value::Unit),
@@ -311,12 +464,15 @@ impl Interpreter {
                         Ok(Value::Unit)
                     }
                     _ => {
-                        // Try to find the function in the module
+                        // Try to find the function in the module; linked
+                        // modules name functions by their qualified path.
+                        let qualified = path.segments.join("::");
                         let target_func = self
                             .module
                             .functions
                             .iter()
-                            .find(|f| f.name == func_name)
+                            .find(|f| f.name == qualified)
+                            .or_else(|| self.module.functions.iter().find(|f| f.name == func_name))
                             .ok_or_else(|| format!("Function {} not found", func_name))?
                             .clone();
 
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::ir::{FuncRef, Function, InstKind, Module};
+
+use super::analyze_function_purity;
+
+/// Direct calls between the functions of a module, grouped into strongly
+/// connected components.
+///
+/// A `FuncRef::Function` call resolves when its path, joined with `::`, names
+/// a function of the module; linked modules name their functions that way.
+/// Every other call (methods, runtime paths, calls through parameters) is
+/// recorded as unresolved, since nothing is known about its target.
+#[derive(Debug, Clone)]
+pub struct CallGraph {
+    functions: Vec<String>,
+    index: HashMap<String, usize>,
+    callees: Vec<Vec<usize>>,
+    callers: Vec<Vec<usize>>,
+    unresolved: Vec<Vec<String>>,
+    /// Components with callees before their callers.
+    sccs: Vec<Vec<usize>>,
+    scc_of: Vec<usize>,
+}
+
+impl CallGraph {
+    pub fn new(module: &Module) -> Self {
+        let functions: Vec<String> = module
+            .functions
+            .iter()
+            .map(|function| function.name.clone())
+            .collect();
+        let mut index = HashMap::new();
+        for (position, name) in functions.iter().enumerate() {
+            index.entry(name.clone()).or_insert(position);
+        }
+
+        let mut callees: Vec<Vec<usize>> = vec![Vec::new(); functions.len()];
+        let mut callers: Vec<Vec<usize>> = vec![Vec::new(); functions.len()];
+        let mut unresolved: Vec<Vec<String>> = vec![Vec::new(); functions.len()];
+        for (caller, function) in module.functions.iter().enumerate() {
+            for func in called_functions(function) {
+                match resolve(func, &index) {
+                    Some(callee) => {
+                        if !callees[caller].contains(&callee) {
+                            callees[caller].push(callee);
+                            callers[callee].push(caller);
+                        }
+                    }
+                    None => {
+                        let name = describe(func);
+                        if !unresolved[caller].contains(&name) {
+                            unresolved[caller].push(name);
+                        }
+                    }
+                }
+            }
+        }
+
+        let sccs = strongly_connected_components(&callees);
+        let mut scc_of = vec![0; functions.len()];
+        for (scc, members) in sccs.iter().enumerate() {
+            for member in members {
+                scc_of[*member] = scc;
+            }
+        }
+
+        CallGraph {
+            functions,
+            index,
+            callees,
+            callers,
+            unresolved,
+            sccs,
+            scc_of,
+        }
+    }
+
+    pub fn functions(&self) -> &[String] {
+        &self.functions
+    }
+
+    /// Module functions `name` calls directly, in order of first call.
+    pub fn callees(&self, name: &str) -> Vec<&str> {
+        self.names(self.index.get(name).map(|node| &self.callees[*node]))
+    }
+
+    pub fn callers(&self, name: &str) -> Vec<&str> {
+        self.names(self.index.get(name).map(|node| &self.callers[*node]))
+    }
+
+    /// Calls in `name` whose target is not a module function.
+    pub fn unresolved_calls(&self, name: &str) -> &[String] {
+        self.index
+            .get(name)
+            .map(|node| self.unresolved[*node].as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Strongly connected components, callees before callers.
+    pub fn sccs(&self) -> Vec<Vec<&str>> {
+        self.sccs
+            .iter()
+            .map(|members| self.names(Some(members)))
+            .collect()
+    }
+
+    pub fn scc_of(&self, name: &str) -> Option<usize> {
+        self.index.get(name).map(|node| self.scc_of[*node])
+    }
+
+    /// Whether `name` can reach itself through direct calls.
+    pub fn is_recursive(&self, name: &str) -> bool {
+        let Some(node) = self.index.get(name).copied() else {
+            return false;
+        };
+        self.sccs[self.scc_of[node]].len() > 1 || self.callees[node].contains(&node)
+    }
+
+    fn names(&self, nodes: Option<&Vec<usize>>) -> Vec<&str> {
+        nodes
+            .into_iter()
+            .flatten()
+            .map(|node| self.functions[*node].as_str())
+            .collect()
+    }
+}
+
+fn called_functions(function: &Function) -> impl Iterator<Item = &FuncRef> {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter_map(|inst| match &inst.kind {
+            InstKind::Call { func, .. } => Some(func),
+            _ => None,
+        })
+}
+
+fn resolve(func: &FuncRef, index: &HashMap<String, usize>) -> Option<usize> {
+    match func {
+        FuncRef::Function(path) => index.get(&path.segments.join("::")).copied(),
+        FuncRef::Method(_) => None,
+    }
+}
+
+fn describe(func: &FuncRef) -> String {
+    match func {
+        FuncRef::Function(path) => path.segments.join("::"),
+        FuncRef::Method(name) => format!(".{}", name),
+    }
+}
+
+/// Tarjan's algorithm, iterative so deep call chains cannot overflow the
+/// stack. Components are emitted as they complete, which puts every
+/// component after the components it calls into.
+fn strongly_connected_components(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let count = edges.len();
+    let mut order = vec![UNVISITED; count];
+    let mut low = vec![0; count];
+    let mut on_stack = vec![false; count];
+    let mut stack = Vec::new();
+    let mut sccs = Vec::new();
+    let mut next_order = 0;
+
+    for root in 0..count {
+        if order[root] != UNVISITED {
+            continue;
+        }
+        let mut frames = vec![(root, 0usize)];
+        order[root] = next_order;
+        low[root] = next_order;
+        next_order += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some((node, next_edge)) = frames.pop() {
+            if let Some(&target) = edges[node].get(next_edge) {
+                frames.push((node, next_edge + 1));
+                if order[target] == UNVISITED {
+                    order[target] = next_order;
+                    low[target] = next_order;
+                    next_order += 1;
+                    stack.push(target);
+                    on_stack[target] = true;
+                    frames.push((target, 0));
+                } else if on_stack[target] {
+                    low[node] = low[node].min(order[target]);
+                }
+                continue;
+            }
+            if let Some(&(parent, _)) = frames.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+            if low[node] == order[node] {
+                let mut members = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    members.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                members.sort_unstable();
+                sccs.push(members);
+            }
+        }
+    }
+    sccs
+}
+
+/// What a caller can assume about a function once its callees are taken
+/// into account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FunctionSummary {
+    pub name: String,
+    pub callees: Vec<String>,
+    pub unresolved_calls: Vec<String>,
+    /// Declared and instruction effects of the function and everything it
+    /// calls, in effect-table order.
+    pub effects: Vec<String>,
+    /// Every block is pure according to [`analyze_function_purity`].
+    pub locally_pure: bool,
+    /// Locally pure, free of effects and unresolved calls, and calling only
+    /// functions that are themselves pure.
+    pub pure: bool,
+    pub recursive: bool,
+    /// Index of the function's component in [`EffectSummaries::sccs`].
+    pub scc: usize,
+}
+
+/// Effect and purity summaries for every function, propagated bottom-up
+/// over the call graph. Members of one component share their effects and
+/// are pure only together.
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectSummaries {
+    pub module: Vec<String>,
+    /// Summaries with callees before callers.
+    pub functions: Vec<FunctionSummary>,
+    pub sccs: Vec<Vec<String>>,
+}
+
+impl EffectSummaries {
+    pub fn new(module: &Module) -> Self {
+        Self::from_call_graph(module, &CallGraph::new(module))
+    }
+
+    pub fn from_call_graph(module: &Module, graph: &CallGraph) -> Self {
+        let count = graph.functions.len();
+        let mut effects: Vec<Vec<usize>> = vec![Vec::new(); count];
+        let mut pure = vec![false; count];
+        let locally_pure: Vec<bool> = module
+            .functions
+            .iter()
+            .map(|function| {
+                let report = analyze_function_purity(function);
+                report.pure_blocks.len() == function.blocks.len()
+            })
+            .collect();
+
+        for members in &graph.sccs {
+            let mut scc_effects: Vec<usize> = Vec::new();
+            let mut scc_pure = true;
+            for member in members {
+                let function = &module.functions[*member];
+                let own = function.effect_row.iter().chain(
+                    function
+                        .blocks
+                        .iter()
+                        .flat_map(|block| &block.instructions)
+                        .flat_map(|inst| &inst.effects),
+                );
+                scc_effects.extend(own.map(|effect| effect.index()));
+                scc_pure &= locally_pure[*member] && graph.unresolved[*member].is_empty();
+                for callee in &graph.callees[*member] {
+                    if graph.scc_of[*callee] != graph.scc_of[*member] {
+                        scc_effects.extend(effects[*callee].iter().copied());
+                        scc_pure &= pure[*callee];
+                    }
+                }
+            }
+            scc_effects.sort_unstable();
+            scc_effects.dedup();
+            scc_pure &= scc_effects.is_empty();
+            for member in members {
+                effects[*member] = scc_effects.clone();
+                pure[*member] = scc_pure;
+            }
+        }
+
+        let effect_names: Vec<&String> = module.effects.entries().map(|(_, name)| name).collect();
+        let functions = graph
+            .sccs
+            .iter()
+            .flatten()
+            .map(|node| {
+                let name = &graph.functions[*node];
+                FunctionSummary {
+                    name: name.clone(),
+                    callees: graph.callees(name).into_iter().map(String::from).collect(),
+                    unresolved_calls: graph.unresolved[*node].clone(),
+                    effects: effects[*node]
+                        .iter()
+                        .map(|effect| effect_names[*effect].clone())
+                        .collect(),
+                    locally_pure: locally_pure[*node],
+                    pure: pure[*node],
+                    recursive: graph.is_recursive(name),
+                    scc: graph.scc_of[*node],
+                }
+            })
+            .collect();
+
+        EffectSummaries {
+            module: module.name.clone(),
+            functions,
+            sccs: graph
+                .sccs()
+                .into_iter()
+                .map(|members| members.into_iter().map(String::from).collect())
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FunctionSummary> {
+        self.functions.iter().find(|summary| summary.name == name)
+    }
+
+    /// Names of the transitively pure functions, callees first.
+    pub fn pure_functions(&self) -> Vec<&str> {
+        self.functions
+            .iter()
+            .filter(|summary| summary.pure)
+            .map(|summary| summary.name.as_str())
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("summaries serialize to JSON")
+    }
+}
+
+impl fmt::Display for EffectSummaries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "module {}", self.module.join("."))?;
+        for summary in &self.functions {
+            write!(
+                f,
+                "fn {}: {}",
+                summary.name,
+                if summary.pure { "pure" } else { "impure" }
+            )?;
+            if !summary.effects.is_empty() {
+                write!(f, " !{{{}}}", summary.effects.join(", "))?;
+            }
+            if summary.recursive {
+                write!(f, ", recursive {{{}}}", self.sccs[summary.scc].join(", "))?;
+            }
+            writeln!(f)?;
+            if !summary.callees.is_empty() {
+                writeln!(f, "  calls {}", summary.callees.join(", "))?;
+            }
+            if !summary.unresolved_calls.is_empty() {
+                writeln!(f, "  unresolved {}", summary.unresolved_calls.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{BasicBlock, BlockId, Function};
+
+/// Successor and predecessor maps of a function body rooted at its first
+/// block, plus the reverse postorder of the blocks reachable from it.
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+    entry: BlockId,
+    order: Vec<BlockId>,
+    rpo_index: HashMap<BlockId, usize>,
+    successors: HashMap<BlockId, Vec<BlockId>>,
+    predecessors: HashMap<BlockId, Vec<BlockId>>,
+}
+
+impl ControlFlowGraph {
+    pub fn new(function: &Function) -> Self {
+        Self::from_blocks(&function.blocks)
+    }
+
+    /// Builds the graph for `blocks`, which must not be empty.
+    pub fn from_blocks(blocks: &[BasicBlock]) -> Self {
+        let entry = blocks[0].id;
+        let mut successors: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+        let mut predecessors: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+        for block in blocks {
+            let succs = block.terminator.successors();
+            for succ in &succs {
+                predecessors.entry(*succ).or_default().push(block.id);
+            }
+            successors.insert(block.id, succs);
+        }
+
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(entry, 0usize)];
+        visited.insert(entry);
+        while let Some((block, next)) = stack.pop() {
+            let succs = successors.get(&block).map(Vec::as_slice).unwrap_or(&[]);
+            if let Some(succ) = succs.get(next) {
+                stack.push((block, next + 1));
+                if visited.insert(*succ) {
+                    stack.push((*succ, 0));
+                }
+            } else {
+                order.push(block);
+            }
+        }
+        order.reverse();
+        let rpo_index = order
+            .iter()
+            .enumerate()
+            .map(|(index, block)| (*block, index))
+            .collect();
+
+        ControlFlowGraph {
+            entry,
+            order,
+            rpo_index,
+            successors,
+            predecessors,
+        }
+    }
+
+    pub fn entry(&self) -> BlockId {
+        self.entry
+    }
+
+    /// Reachable blocks in reverse postorder, so every block appears after
+    /// its dominators.
+    pub fn reverse_postorder(&self) -> &[BlockId] {
+        &self.order
+    }
+
+    /// Position of `block` in [`Self::reverse_postorder`], if reachable.
+    pub fn rpo_index(&self, block: BlockId) -> Option<usize> {
+        self.rpo_index.get(&block).copied()
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.rpo_index.contains_key(&block)
+    }
+
+    pub fn successors(&self, block: BlockId) -> &[BlockId] {
+        self.successors
+            .get(&block)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Every block that branches to `block`, reachable or not.
+    pub fn predecessors(&self, block: BlockId) -> &[BlockId] {
+        self.predecessors
+            .get(&block)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+}
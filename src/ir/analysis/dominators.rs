@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use crate::ir::BlockId;
+
+use super::ControlFlowGraph;
+
+/// Immediate dominators of the reachable blocks, computed with the
+/// Cooper–Harvey–Kennedy iterative algorithm over reverse postorder.
+#[derive(Debug, Clone)]
+pub struct DominatorTree {
+    entry: BlockId,
+    idom: HashMap<BlockId, BlockId>,
+    children: HashMap<BlockId, Vec<BlockId>>,
+}
+
+impl DominatorTree {
+    pub fn new(cfg: &ControlFlowGraph) -> Self {
+        let entry = cfg.entry();
+        let order = cfg.reverse_postorder();
+        let mut idom: HashMap<BlockId, BlockId> = HashMap::new();
+        idom.insert(entry, entry);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in order.iter().skip(1) {
+                let mut new_idom: Option<BlockId> = None;
+                for pred in cfg.predecessors(*block) {
+                    if !idom.contains_key(pred) {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => *pred,
+                        Some(current) => intersect(&idom, cfg, *pred, current),
+                    });
+                }
+                if let Some(new_idom) = new_idom
+                    && idom.get(block) != Some(&new_idom)
+                {
+                    idom.insert(*block, new_idom);
+                    changed = true;
+                }
+            }
+        }
+
+        // Children in reverse postorder keep tree walks deterministic.
+        let mut children: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+        for block in order.iter().skip(1) {
+            if let Some(parent) = idom.get(block) {
+                children.entry(*parent).or_default().push(*block);
+            }
+        }
+
+        DominatorTree {
+            entry,
+            idom,
+            children,
+        }
+    }
+
+    pub fn root(&self) -> BlockId {
+        self.entry
+    }
+
+    /// The closest strict dominator of `block`; `None` for the entry block
+    /// and for unreachable blocks.
+    pub fn immediate_dominator(&self, block: BlockId) -> Option<BlockId> {
+        self.idom
+            .get(&block)
+            .copied()
+            .filter(|parent| *parent != block)
+    }
+
+    /// Blocks whose immediate dominator is `block`.
+    pub fn children(&self, block: BlockId) -> &[BlockId] {
+        self.children.get(&block).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.idom.contains_key(&block)
+    }
+
+    /// Whether `lhs` dominates `rhs`; every reachable block dominates itself.
+    pub fn dominates(&self, lhs: BlockId, rhs: BlockId) -> bool {
+        let mut current = rhs;
+        loop {
+            if current == lhs {
+                return true;
+            }
+            match self.idom.get(&current) {
+                Some(parent) if *parent != current => current = *parent,
+                _ => return false,
+            }
+        }
+    }
+
+    pub fn strictly_dominates(&self, lhs: BlockId, rhs: BlockId) -> bool {
+        lhs != rhs && self.dominates(lhs, rhs)
+    }
+}
+
+fn intersect(
+    idom: &HashMap<BlockId, BlockId>,
+    cfg: &ControlFlowGraph,
+    mut lhs: BlockId,
+    mut rhs: BlockId,
+) -> BlockId {
+    let rpo_index = |block: BlockId| cfg.rpo_index(block).expect("reachable block");
+    while lhs != rhs {
+        while rpo_index(lhs) > rpo_index(rhs) {
+            lhs = idom[&lhs];
+        }
+        while rpo_index(rhs) > rpo_index(lhs) {
+            rhs = idom[&rhs];
+        }
+    }
+    lhs
+}
+
+/// For each block, the blocks where its dominance ends: the join points
+/// reached from it that it does not strictly dominate.
+#[derive(Debug, Clone, Default)]
+pub struct DominanceFrontiers {
+    frontiers: HashMap<BlockId, Vec<BlockId>>,
+}
+
+impl DominanceFrontiers {
+    pub fn new(cfg: &ControlFlowGraph, dominators: &DominatorTree) -> Self {
+        let mut frontiers: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+        for block in cfg.reverse_postorder() {
+            let preds: Vec<BlockId> = cfg
+                .predecessors(*block)
+                .iter()
+                .filter(|pred| dominators.is_reachable(**pred))
+                .copied()
+                .collect();
+            if preds.len() < 2 {
+                continue;
+            }
+            let Some(block_idom) = dominators.immediate_dominator(*block) else {
+                continue;
+            };
+            for pred in preds {
+                let mut runner = pred;
+                while runner != block_idom {
+                    let frontier = frontiers.entry(runner).or_default();
+                    if !frontier.contains(block) {
+                        frontier.push(*block);
+                    }
+                    match dominators.immediate_dominator(runner) {
+                        Some(next) => runner = next,
+                        None => break,
+                    }
+                }
+            }
+        }
+        DominanceFrontiers { frontiers }
+    }
+
+    pub fn frontier(&self, block: BlockId) -> &[BlockId] {
+        self.frontiers.get(&block).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{BlockId, Function, InstKind, ValueId};
+
+use super::ControlFlowGraph;
+
+/// Values live on entry to and exit from each reachable block.
+///
+/// Phis follow SSA edge semantics: a phi's result is defined at the top of
+/// its block, and each incoming value is used at the end of the matching
+/// predecessor rather than on entry to the phi's block. Parameters count as
+/// defined before the entry block, so a parameter appears in `live_in` of
+/// every block from which a use of it is reachable.
+#[derive(Debug, Clone, Default)]
+pub struct Liveness {
+    live_in: HashMap<BlockId, HashSet<ValueId>>,
+    live_out: HashMap<BlockId, HashSet<ValueId>>,
+    empty: HashSet<ValueId>,
+}
+
+impl Liveness {
+    pub fn new(function: &Function, cfg: &ControlFlowGraph) -> Self {
+        let mut uses: HashMap<BlockId, HashSet<ValueId>> = HashMap::new();
+        let mut defs: HashMap<BlockId, HashSet<ValueId>> = HashMap::new();
+        // Values each block feeds into a successor's phis, keyed by the
+        // predecessor that provides them.
+        let mut phi_uses: HashMap<BlockId, HashSet<ValueId>> = HashMap::new();
+        for block in &function.blocks {
+            if !cfg.is_reachable(block.id) {
+                continue;
+            }
+            let block_uses = uses.entry(block.id).or_default();
+            let block_defs = defs.entry(block.id).or_default();
+            for inst in &block.instructions {
+                if let InstKind::Phi { incomings } = &inst.kind {
+                    for (pred, value) in incomings {
+                        phi_uses.entry(*pred).or_default().insert(*value);
+                    }
+                } else {
+                    for operand in inst.kind.operands() {
+                        if !block_defs.contains(&operand) {
+                            block_uses.insert(operand);
+                        }
+                    }
+                }
+                block_defs.insert(inst.id);
+            }
+            if let Some(operand) = block.terminator.operand()
+                && !block_defs.contains(&operand)
+            {
+                block_uses.insert(operand);
+            }
+        }
+
+        let mut live_in: HashMap<BlockId, HashSet<ValueId>> = HashMap::new();
+        let mut live_out: HashMap<BlockId, HashSet<ValueId>> = HashMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in cfg.reverse_postorder().iter().rev() {
+                let mut out: HashSet<ValueId> = phi_uses.get(block).cloned().unwrap_or_default();
+                for succ in cfg.successors(*block) {
+                    if let Some(succ_in) = live_in.get(succ) {
+                        out.extend(succ_in.iter().copied());
+                    }
+                }
+                let mut entry: HashSet<ValueId> = out
+                    .iter()
+                    .filter(|value| !defs[block].contains(value))
+                    .copied()
+                    .collect();
+                entry.extend(uses[block].iter().copied());
+                if live_in.get(block) != Some(&entry) {
+                    live_in.insert(*block, entry);
+                    changed = true;
+                }
+                live_out.insert(*block, out);
+            }
+        }
+
+        Liveness {
+            live_in,
+            live_out,
+            empty: HashSet::new(),
+        }
+    }
+
+    pub fn live_in(&self, block: BlockId) -> &HashSet<ValueId> {
+        self.live_in.get(&block).unwrap_or(&self.empty)
+    }
+
+    pub fn live_out(&self, block: BlockId) -> &HashSet<ValueId> {
+        self.live_out.get(&block).unwrap_or(&self.empty)
+    }
+
+    pub fn is_live_in(&self, value: ValueId, block: BlockId) -> bool {
+        self.live_in(block).contains(&value)
+    }
+
+    pub fn is_live_out(&self, value: ValueId, block: BlockId) -> bool {
+        self.live_out(block).contains(&value)
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::BlockId;
+
+use super::{ControlFlowGraph, DominatorTree};
+
+/// A natural loop: the header plus every block that reaches one of its back
+/// edges without passing through the header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    pub header: BlockId,
+    /// Loop blocks in reverse postorder, header first.
+    pub blocks: Vec<BlockId>,
+    /// Sources of the back edges into `header`.
+    pub latches: Vec<BlockId>,
+    /// Blocks outside the loop that loop blocks branch to.
+    pub exits: Vec<BlockId>,
+    /// Index of the innermost enclosing loop in [`LoopInfo::loops`].
+    pub parent: Option<usize>,
+    /// 1 for outermost loops.
+    pub depth: u32,
+}
+
+impl Loop {
+    pub fn contains(&self, block: BlockId) -> bool {
+        self.blocks.contains(&block)
+    }
+}
+
+/// The loop nest of a function. Back edges sharing a header form one loop;
+/// loops are ordered outermost first, so a parent index is always lower than
+/// its children's.
+#[derive(Debug, Clone, Default)]
+pub struct LoopInfo {
+    loops: Vec<Loop>,
+    innermost: HashMap<BlockId, usize>,
+}
+
+impl LoopInfo {
+    pub fn new(cfg: &ControlFlowGraph, dominators: &DominatorTree) -> Self {
+        let mut latches_by_header: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+        for block in cfg.reverse_postorder() {
+            for succ in cfg.successors(*block) {
+                if dominators.dominates(*succ, *block) {
+                    latches_by_header.entry(*succ).or_default().push(*block);
+                }
+            }
+        }
+
+        let mut loops: Vec<Loop> = Vec::with_capacity(latches_by_header.len());
+        for header in cfg.reverse_postorder() {
+            let Some(latches) = latches_by_header.remove(header) else {
+                continue;
+            };
+            let mut body: HashSet<BlockId> = HashSet::from([*header]);
+            let mut worklist = latches.clone();
+            while let Some(block) = worklist.pop() {
+                if body.insert(block) {
+                    worklist.extend(
+                        cfg.predecessors(block)
+                            .iter()
+                            .filter(|pred| cfg.is_reachable(**pred)),
+                    );
+                }
+            }
+            let mut blocks: Vec<BlockId> = body.iter().copied().collect();
+            blocks.sort_by_key(|block| cfg.rpo_index(*block));
+            let mut exits = Vec::new();
+            for block in &blocks {
+                for succ in cfg.successors(*block) {
+                    if !body.contains(succ) && !exits.contains(succ) {
+                        exits.push(*succ);
+                    }
+                }
+            }
+            loops.push(Loop {
+                header: *header,
+                blocks,
+                latches,
+                exits,
+                parent: None,
+                depth: 1,
+            });
+        }
+
+        // Outer loops contain their inner loops' headers, so sorting by size
+        // puts every parent before its children.
+        loops.sort_by(|lhs, rhs| {
+            rhs.blocks
+                .len()
+                .cmp(&lhs.blocks.len())
+                .then_with(|| cfg.rpo_index(lhs.header).cmp(&cfg.rpo_index(rhs.header)))
+        });
+        for index in 0..loops.len() {
+            let header = loops[index].header;
+            let parent = (0..index)
+                .rev()
+                .find(|candidate| loops[*candidate].contains(header));
+            loops[index].parent = parent;
+            loops[index].depth = parent.map_or(1, |parent| loops[parent].depth + 1);
+        }
+
+        let mut innermost = HashMap::new();
+        for (index, lp) in loops.iter().enumerate() {
+            for block in &lp.blocks {
+                // Later loops are nested deeper than earlier ones containing
+                // the same block.
+                innermost.insert(*block, index);
+            }
+        }
+
+        LoopInfo { loops, innermost }
+    }
+
+    pub fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    /// The innermost loop containing `block`.
+    pub fn loop_for(&self, block: BlockId) -> Option<&Loop> {
+        self.innermost.get(&block).map(|index| &self.loops[*index])
+    }
+
+    /// Number of loops enclosing `block`; 0 outside any loop.
+    pub fn depth(&self, block: BlockId) -> u32 {
+        self.loop_for(block).map_or(0, |lp| lp.depth)
+    }
+
+    pub fn is_header(&self, block: BlockId) -> bool {
+        self.loops.iter().any(|lp| lp.header == block)
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::ir::Function;
+
+use super::{
+    ControlFlowGraph, DominanceFrontiers, DominatorTree, Liveness, LoopInfo, PurityReport,
+    analyze_function_purity,
+};
+
+/// Caches per-function analyses so passes share them instead of rebuilding
+/// CFG structure on every query.
+///
+/// Results are keyed by function name and stay valid until
+/// [`AnalysisManager::invalidate`] is called for that function, so a pass
+/// must invalidate every function it rewrites before anyone queries it
+/// again. Dependent analyses are computed through the cache: asking for
+/// loops builds (or reuses) the CFG and dominator tree first.
+#[derive(Debug, Default)]
+pub struct AnalysisManager {
+    functions: HashMap<String, FunctionAnalyses>,
+    stats: AnalysisStats,
+}
+
+/// How often queries were answered from the cache versus computed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnalysisStats {
+    pub computed: usize,
+    pub cached: usize,
+    pub invalidations: usize,
+}
+
+#[derive(Debug, Default)]
+struct FunctionAnalyses {
+    cfg: Option<Arc<ControlFlowGraph>>,
+    dominators: Option<Arc<DominatorTree>>,
+    frontiers: Option<Arc<DominanceFrontiers>>,
+    loops: Option<Arc<LoopInfo>>,
+    liveness: Option<Arc<Liveness>>,
+    purity: Option<Arc<PurityReport>>,
+}
+
+impl AnalysisManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The control-flow graph of `function`, which must have a block.
+    pub fn cfg(&mut self, function: &Function) -> Arc<ControlFlowGraph> {
+        if let Some(cfg) = self.entry(function).cfg.clone() {
+            self.stats.cached += 1;
+            return cfg;
+        }
+        let cfg = Arc::new(ControlFlowGraph::new(function));
+        self.stats.computed += 1;
+        self.entry(function).cfg = Some(cfg.clone());
+        cfg
+    }
+
+    pub fn dominators(&mut self, function: &Function) -> Arc<DominatorTree> {
+        if let Some(dominators) = self.entry(function).dominators.clone() {
+            self.stats.cached += 1;
+            return dominators;
+        }
+        let cfg = self.cfg(function);
+        let dominators = Arc::new(DominatorTree::new(&cfg));
+        self.stats.computed += 1;
+        self.entry(function).dominators = Some(dominators.clone());
+        dominators
+    }
+
+    pub fn dominance_frontiers(&mut self, function: &Function) -> Arc<DominanceFrontiers> {
+        if let Some(frontiers) = self.entry(function).frontiers.clone() {
+            self.stats.cached += 1;
+            return frontiers;
+        }
+        let cfg = self.cfg(function);
+        let dominators = self.dominators(function);
+        let frontiers = Arc::new(DominanceFrontiers::new(&cfg, &dominators));
+        self.stats.computed += 1;
+        self.entry(function).frontiers = Some(frontiers.clone());
+        frontiers
+    }
+
+    pub fn loops(&mut self, function: &Function) -> Arc<LoopInfo> {
+        if let Some(loops) = self.entry(function).loops.clone() {
+            self.stats.cached += 1;
+            return loops;
+        }
+        let cfg = self.cfg(function);
+        let dominators = self.dominators(function);
+        let loops = Arc::new(LoopInfo::new(&cfg, &dominators));
+        self.stats.computed += 1;
+        self.entry(function).loops = Some(loops.clone());
+        loops
+    }
+
+    pub fn liveness(&mut self, function: &Function) -> Arc<Liveness> {
+        if let Some(liveness) = self.entry(function).liveness.clone() {
+            self.stats.cached += 1;
+            return liveness;
+        }
+        let cfg = self.cfg(function);
+        let liveness = Arc::new(Liveness::new(function, &cfg));
+        self.stats.computed += 1;
+        self.entry(function).liveness = Some(liveness.clone());
+        liveness
+    }
+
+    pub fn purity(&mut self, function: &Function) -> Arc<PurityReport> {
+        if let Some(purity) = self.entry(function).purity.clone() {
+            self.stats.cached += 1;
+            return purity;
+        }
+        let purity = Arc::new(analyze_function_purity(function));
+        self.stats.computed += 1;
+        self.entry(function).purity = Some(purity.clone());
+        purity
+    }
+
+    /// Drops every cached analysis of the function named `name`.
+    pub fn invalidate(&mut self, name: &str) {
+        if self.functions.remove(name).is_some() {
+            self.stats.invalidations += 1;
+        }
+    }
+
+    pub fn invalidate_all(&mut self) {
+        self.stats.invalidations += self.functions.len();
+        self.functions.clear();
+    }
+
+    pub fn stats(&self) -> AnalysisStats {
+        self.stats
+    }
+
+    fn entry(&mut self, function: &Function) -> &mut FunctionAnalyses {
+        if !self.functions.contains_key(&function.name) {
+            self.functions
+                .insert(function.name.clone(), FunctionAnalyses::default());
+        }
+        self.functions
+            .get_mut(&function.name)
+            .expect("entry inserted above")
+    }
+}
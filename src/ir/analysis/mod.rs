@@ -0,0 +1,29 @@
+//! Reusable analyses over IR functions.
+//!
+//! [`ControlFlowGraph`] records successors, predecessors and reverse
+//! postorder; [`DominatorTree`] and [`DominanceFrontiers`] are built from it,
+//! [`LoopInfo`] finds natural loops and their nesting from the dominator tree,
+//! and [`Liveness`] computes the values live across each block boundary.
+//! [`analyze_function_purity`] classifies blocks by their effects. Passes
+//! query all of them through an [`AnalysisManager`], which caches results per
+//! function until the function is invalidated.
+//!
+//! At module level, [`CallGraph`] links functions through direct calls and
+//! groups recursion into strongly connected components, and
+//! [`EffectSummaries`] propagates effects and purity bottom-up over it.
+
+mod call_graph;
+mod cfg;
+mod dominators;
+mod liveness;
+mod loops;
+mod manager;
+mod purity;
+
+pub use call_graph::{CallGraph, EffectSummaries, FunctionSummary};
+pub use cfg::ControlFlowGraph;
+pub use dominators::{DominanceFrontiers, DominatorTree};
+pub use liveness::Liveness;
+pub use loops::{Loop, LoopInfo};
+pub use manager::{AnalysisManager, AnalysisStats};
+pub use purity::{BlockPurity, PurityReport, analyze_function_purity};
@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use super::{BlockId, Function, InstKind, Terminator, ValueId};
+use crate::ir::{BlockId, FuncRef, Function, InstKind, Terminator, ValueId};
 
 #[derive(Debug, Default, Clone)]
 pub struct PurityReport {
@@ -51,7 +51,18 @@ pub fn analyze_function_purity(function: &Function) -> PurityReport {
                 // they target another IR function. Method calls or unresolved
                 // references remain conservatively effectful so future lowering
                 // phases can attach metadata without breaking assumptions here.
-                effectful = matches!(func, super::FuncRef::Method(_));
+                effectful = matches!(func, FuncRef::Method(_));
+            }
+            // Pushing mutates a list that other values may alias, a release
+            // may free one, and a trap ends the program.
+            if matches!(
+                inst.kind,
+                InstKind::ListPush { .. }
+                    | InstKind::Retain { .. }
+                    | InstKind::Release { .. }
+                    | InstKind::Trap { .. }
+            ) {
+                effectful = true;
             }
             if effectful {
                 block_pure = false;
@@ -0,0 +1,279 @@
+//! Links the IR of several source modules into one program.
+//!
+//! The first module is the entry module and keeps its function names, so its
+//! `main` stays the program entry. Functions of the other modules are renamed
+//! to their qualified path (`math::add`), which is also how callers spell a
+//! cross-module call. Type and effect tables are merged and every ID is
+//! renumbered into the shared tables.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::syntax::ast::Path;
+
+use super::{EffectId, EffectTable, FuncRef, Function, InstKind, Module, Type, TypeId, TypeTable};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// Two inputs declare the same module path.
+    DuplicateModule(String),
+    /// A module defines two functions with the same name.
+    DuplicateFunction { module: String, name: String },
+    /// Two modules define a record of the same name with different fields.
+    ConflictingType {
+        name: String,
+        first: String,
+        second: String,
+    },
+    /// A qualified call names a function no linked module defines.
+    MissingFunction { caller: String, callee: String },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::DuplicateModule(module) => {
+                write!(f, "module `{}` is linked more than once", module)
+            }
+            LinkError::DuplicateFunction { module, name } => {
+                write!(f, "module `{}` defines `{}` more than once", module, name)
+            }
+            LinkError::ConflictingType {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "type `{}` is defined differently in `{}` and `{}`",
+                name, first, second
+            ),
+            LinkError::MissingFunction { caller, callee } => {
+                write!(f, "`{}` calls undefined function `{}`", caller, callee)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+/// Merges `modules` into one module named after the first, reporting every
+/// duplicate or missing symbol found.
+///
+/// Calls are resolved against the functions of the calling module and the
+/// qualified names of the other modules. Every multi-segment call must
+/// resolve, whether or not its module was linked; anything else (runtime
+/// functions, methods, parameters) is left for the backends.
+pub fn link_modules(modules: Vec<Module>) -> Result<Module, Vec<LinkError>> {
+    let mut errors = Vec::new();
+    let Some(entry) = modules.first() else {
+        return Ok(Module {
+            name: Vec::new(),
+            functions: Vec::new(),
+            types: TypeTable::new(),
+            effects: EffectTable::default(),
+        });
+    };
+    let name = entry.name.clone();
+
+    // Later copies of a module path are reported and otherwise ignored.
+    let mut module_paths: HashSet<&[String]> = HashSet::new();
+    let mut inputs: Vec<(usize, &Module)> = Vec::with_capacity(modules.len());
+    for (index, module) in modules.iter().enumerate() {
+        if module_paths.insert(&module.name) {
+            inputs.push((index, module));
+        } else {
+            errors.push(LinkError::DuplicateModule(module.name.join(".")));
+        }
+    }
+
+    // Qualified path of every function to its name in the linked module.
+    let mut symbols: HashMap<Vec<String>, String> = HashMap::new();
+    for &(index, module) in &inputs {
+        let mut seen = HashSet::new();
+        for function in &module.functions {
+            if !seen.insert(function.name.as_str()) {
+                errors.push(LinkError::DuplicateFunction {
+                    module: module.name.join("."),
+                    name: function.name.clone(),
+                });
+                continue;
+            }
+            let mut path = module.name.clone();
+            path.push(function.name.clone());
+            let linked = if index == 0 {
+                function.name.clone()
+            } else {
+                path.join("::")
+            };
+            symbols.entry(path).or_insert(linked);
+        }
+    }
+
+    let mut types = TypeTable::new();
+    let mut effects = EffectTable::default();
+    // Equal records intern to the same merged ID, so a name that maps to a
+    // second ID was defined with different fields.
+    let mut record_owners: HashMap<String, (String, TypeId)> = HashMap::new();
+    let mut linked: Vec<(usize, Function)> = Vec::new();
+    for &(index, module) in &inputs {
+        let module_name = module.name.join(".");
+        let type_remap = types.merge(&module.types);
+        for (id, ty) in module.types.entries() {
+            let Type::Record(record) = ty else {
+                continue;
+            };
+            let Some(record_name) = &record.name else {
+                continue;
+            };
+            match record_owners.get(record_name) {
+                Some((owner, merged)) if *merged != type_remap[&id] => {
+                    errors.push(LinkError::ConflictingType {
+                        name: record_name.clone(),
+                        first: owner.clone(),
+                        second: module_name.clone(),
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    record_owners
+                        .insert(record_name.clone(), (module_name.clone(), type_remap[&id]));
+                }
+            }
+        }
+        let effect_remap: HashMap<EffectId, EffectId> = module
+            .effects
+            .entries()
+            .map(|(id, effect)| (id, effects.intern(effect.clone())))
+            .collect();
+
+        let mut seen = HashSet::new();
+        for function in &module.functions {
+            if !seen.insert(function.name.as_str()) {
+                continue;
+            }
+            let mut function = function.clone();
+            function.remap_types(&type_remap);
+            remap_effects(&mut function, &effect_remap);
+            if index > 0 {
+                let prefix = module.name.join("::");
+                function.name = format!("{}::{}", prefix, function.name);
+                qualify_provenance(&mut function, &prefix);
+            }
+            linked.push((index, function));
+        }
+    }
+
+    let signatures: HashMap<String, (TypeId, Vec<EffectId>)> = linked
+        .iter()
+        .map(|(_, function)| {
+            (
+                function.name.clone(),
+                (function.ret_type, function.effect_row.clone()),
+            )
+        })
+        .collect();
+    let unknown = types.unknown();
+    let mut functions = Vec::with_capacity(linked.len());
+    for (index, mut function) in linked {
+        let module_path = &modules[index].name;
+        let caller = function.name.clone();
+        for block in &mut function.blocks {
+            for inst in &mut block.instructions {
+                let path = match &mut inst.kind {
+                    InstKind::Call {
+                        func: FuncRef::Function(path),
+                        ..
+                    } => path,
+                    InstKind::Path(path) => {
+                        if let Some(target) = resolve(path, module_path, &symbols) {
+                            *path = linked_path(target);
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                };
+                match resolve(path, module_path, &symbols) {
+                    Some(target) => {
+                        let (ret_type, callee_effects) = &signatures[target];
+                        *path = linked_path(target);
+                        if inst.ty == unknown {
+                            inst.ty = *ret_type;
+                        }
+                        for effect in callee_effects {
+                            if !inst.effects.contains(effect) {
+                                inst.effects.push(*effect);
+                            }
+                        }
+                    }
+                    None if path.segments.len() > 1 => {
+                        errors.push(LinkError::MissingFunction {
+                            caller: caller.clone(),
+                            callee: path.segments.join("::"),
+                        });
+                    }
+                    None => {}
+                }
+            }
+        }
+        functions.push(function);
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(Module {
+        name,
+        functions,
+        types,
+        effects,
+    })
+}
+
+/// The linked name `path` refers to from inside `module_path`: a local
+/// function by its bare name, or any function by its qualified path.
+fn resolve<'a>(
+    path: &Path,
+    module_path: &[String],
+    symbols: &'a HashMap<Vec<String>, String>,
+) -> Option<&'a String> {
+    if path.segments.len() == 1 {
+        let mut local = module_path.to_vec();
+        local.push(path.segments[0].clone());
+        return symbols.get(&local);
+    }
+    symbols.get(&path.segments)
+}
+
+fn linked_path(name: &str) -> Path {
+    Path {
+        segments: name.split("::").map(String::from).collect(),
+    }
+}
+
+/// Qualifies the originating function of everything in `function`, so
+/// provenance names match the linked function names.
+fn qualify_provenance(function: &mut Function, prefix: &str) {
+    for block in &mut function.blocks {
+        let provenances = block
+            .instructions
+            .iter_mut()
+            .filter_map(|inst| inst.provenance.as_mut())
+            .chain(block.terminator_provenance.as_mut());
+        for provenance in provenances {
+            provenance.function = format!("{}::{}", prefix, provenance.function);
+        }
+    }
+}
+
+fn remap_effects(function: &mut Function, remap: &HashMap<EffectId, EffectId>) {
+    for effect in &mut function.effect_row {
+        *effect = remap[effect];
+    }
+    for block in &mut function.blocks {
+        for inst in &mut block.instructions {
+            for effect in &mut inst.effects {
+                *effect = remap[effect];
+            }
+        }
+    }
+}
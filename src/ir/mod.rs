@@ -1,12 +1,25 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-pub mod analysis;
-
-use crate::lower::{HBlock, HExpr, HFuncRef, HFunction, HItem, HModule, HParam, HStmt, HTypeAlias};
-use crate::syntax::ast::{BinaryOp, Literal, Path, TypeExpr};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+pub mod analysis;
+pub mod link;
+pub mod parse;
+pub mod passes;
+pub mod serialize;
+mod ssa;
+pub mod verify;
+
+use crate::lower::{
+    HBlock, HExpr, HExprKind, HFuncRef, HFunction, HItem, HMatchArm, HModule, HParam, HStmt,
+    HTypeAlias,
+};
+use crate::syntax::ast::{BinaryOp, Literal, Path, Pattern, Span, TypeExpr, TypeVariant};
+
+use ssa::{SlotAccess, SlotId, SlotTable};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Module {
     pub name: Vec<String>,
     pub functions: Vec<Function>,
@@ -14,38 +27,82 @@ pub struct Module {
     pub effects: EffectTable,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Function {
     pub name: String,
     pub params: Vec<Param>,
     pub ret_type: TypeId,
     pub blocks: Vec<BasicBlock>,
     pub effect_row: Vec<EffectId>,
+    /// Declared `pub`; libraries keep these as entry points.
+    pub is_public: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Param {
     pub name: String,
     pub ty: TypeId,
     pub value: ValueId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BasicBlock {
     pub id: BlockId,
     pub instructions: Vec<Instruction>,
     pub terminator: Terminator,
+    pub terminator_provenance: Option<Provenance>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Instruction {
     pub id: ValueId,
     pub ty: TypeId,
     pub effects: Vec<EffectId>,
     pub kind: InstKind,
+    /// Where the instruction came from; `None` for ones passes synthesize,
+    /// such as phis.
+    pub provenance: Option<Provenance>,
 }
 
-#[derive(Debug, Clone)]
+/// The source an instruction or terminator was lowered from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Function whose body held the source; after inlining this is the
+    /// callee rather than the function the instruction now sits in.
+    pub function: String,
+    /// The innermost statement being lowered, or the function itself for
+    /// code outside any statement.
+    pub span: Span,
+    pub node: HirNode,
+}
+
+/// The kind of HIR node that produced an instruction or terminator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HirNode {
+    Function,
+    Let,
+    Return,
+    Break,
+    Continue,
+    Literal,
+    Var,
+    Path,
+    Call,
+    If,
+    Binary,
+    Block,
+    Record,
+    List,
+    Field,
+    Cast,
+    Assign,
+    While,
+    Loop,
+    For,
+    Match,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InstKind {
     Literal(Literal),
     Binary {
@@ -62,12 +119,72 @@ pub enum InstKind {
         fields: Vec<(String, ValueId)>,
     },
     Path(Path),
+    /// Reads field `name` of the record `base`.
+    Field {
+        base: ValueId,
+        name: String,
+    },
+    /// Converts the number `value` to the instruction's type: `Int` to
+    /// `Float`, or `Float` to `Int` truncating toward zero.
+    Convert {
+        value: ValueId,
+    },
     Phi {
         incomings: Vec<(BlockId, ValueId)>,
     },
+    /// Allocates a list holding `elements` in order.
+    List {
+        elements: Vec<ValueId>,
+    },
+    ListLen {
+        list: ValueId,
+    },
+    /// Bounds-checked element read; an out-of-range index is a runtime error.
+    ListIndex {
+        list: ValueId,
+        index: ValueId,
+    },
+    /// Appends `value` in place; lists are shared handles, so every alias
+    /// observes the new element.
+    ListPush {
+        list: ValueId,
+        value: ValueId,
+    },
+    /// Takes another reference to the strings and lists `value` holds.
+    /// Only the `refcount` pass inserts these, for backends that free memory.
+    Retain {
+        value: ValueId,
+    },
+    /// Drops a reference taken by a definition or a [`InstKind::Retain`],
+    /// freeing the data once none remain.
+    Release {
+        value: ValueId,
+    },
+    /// Builds variant `variant` of the instruction's sum type from `payload`.
+    Variant {
+        variant: String,
+        payload: Vec<ValueId>,
+    },
+    /// Whether the sum `value` holds variant `variant`.
+    IsVariant {
+        value: ValueId,
+        variant: String,
+    },
+    /// Reads payload field `index` of `value`, which must hold `variant`;
+    /// lowering only emits it where an [`InstKind::IsVariant`] test passed.
+    Payload {
+        value: ValueId,
+        variant: String,
+        index: u32,
+    },
+    /// Stops the program with a runtime error, as when no `match` arm
+    /// applies.
+    Trap {
+        message: String,
+    },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Terminator {
     Return(Option<ValueId>),
     Branch {
@@ -78,32 +195,32 @@ pub enum Terminator {
     Jump(BlockId),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FuncRef {
     Function(Path),
     Method(String),
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ValueId(u32);
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct BlockId(pub u32);
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TypeId(u32);
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct EffectId(u32);
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct RecordField {
     pub name: String,
     pub ty: TypeId,
     pub offset: u32,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct RecordType {
     pub name: Option<String>,
     pub fields: Vec<RecordField>,
@@ -117,7 +234,33 @@ impl RecordType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// One constructor of a sum type and the types of its payload fields.
+/// Type parameters are erased to `Unknown`, and a payload naming a sum that
+/// was not yet interned, such as the sum itself, stays `Named`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SumVariant {
+    pub name: String,
+    pub fields: Vec<TypeId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SumType {
+    pub name: String,
+    pub variants: Vec<SumVariant>,
+}
+
+impl SumType {
+    /// The tag and declaration of variant `name`; tags follow declaration
+    /// order.
+    pub fn variant(&self, name: &str) -> Option<(usize, &SumVariant)> {
+        self.variants
+            .iter()
+            .enumerate()
+            .find(|(_, variant)| variant.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Type {
     Unit,
     Int,
@@ -126,10 +269,17 @@ pub enum Type {
     String,
     Named(String),
     Record(RecordType),
+    /// A named tagged union; values are one of its variants.
+    Sum(SumType),
+    List(TypeId),
     Unknown,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    into = "serialize::TypeTableData",
+    try_from = "serialize::TypeTableData"
+)]
 pub struct TypeTable {
     inner: Arc<TypeTableInner>,
 }
@@ -142,7 +292,8 @@ struct TypeTableInner {
     unknown: TypeId,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(into = "Vec<String>", try_from = "Vec<String>")]
 pub struct EffectTable {
     inner: Arc<EffectTableInner>,
 }
@@ -160,13 +311,27 @@ pub fn lower_module(module: &HModule) -> Module {
             lowerer.push_type_alias(alias);
         }
     }
-    for func in module.items.iter().filter_map(|item| match item {
-        HItem::Function(func) => Some(func),
-        HItem::TypeAlias(_) => None,
-    }) {
+    let functions: Vec<&HFunction> = module
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            HItem::Function(func) => Some(func),
+            HItem::TypeAlias(_) => None,
+        })
+        .collect();
+    // Declared signatures first, so calls to functions defined further down
+    // still see their return types and effects.
+    for func in &functions {
+        lowerer.declare_function(func);
+    }
+    for func in functions {
         lowerer.push_function(func);
     }
-    lowerer.finish()
+    let module = lowerer.finish();
+    if cfg!(debug_assertions) {
+        verify::assert_well_formed(&module, "lowering");
+    }
+    module
 }
 
 #[derive(Debug, Clone)]
@@ -175,12 +340,21 @@ struct FunctionSignature {
     effects: Vec<EffectId>,
 }
 
+/// The declared variants of a generic sum, whose payloads are erased in the
+/// type table; patterns instantiate them with the scrutinee's type arguments.
+#[derive(Debug, Clone)]
+struct GenericSum {
+    params: Vec<String>,
+    variants: Vec<TypeVariant>,
+}
+
 struct ModuleLower {
     name: Vec<String>,
     functions: Vec<Function>,
     types: TypeTable,
     effects: EffectTable,
     function_signatures: HashMap<String, FunctionSignature>,
+    generic_sums: HashMap<String, GenericSum>,
 }
 
 impl ModuleLower {
@@ -191,10 +365,15 @@ impl ModuleLower {
             types: TypeTable::new(),
             effects: EffectTable::default(),
             function_signatures: HashMap::new(),
+            generic_sums: HashMap::new(),
         }
     }
 
     fn push_type_alias(&mut self, alias: &HTypeAlias) {
+        if let TypeExpr::Sum(variants) = &alias.value {
+            self.push_sum(&alias.name, &alias.params, variants);
+            return;
+        }
         if !alias.params.is_empty() {
             return;
         }
@@ -209,12 +388,58 @@ impl ModuleLower {
         }
     }
 
+    /// Interns a sum type. Payloads of a type parameter are erased to
+    /// `Unknown`, so every instantiation of a generic sum shares one type.
+    fn push_sum(&mut self, name: &str, params: &[String], variants: &[TypeVariant]) {
+        if !params.is_empty() {
+            self.generic_sums.insert(
+                name.to_string(),
+                GenericSum {
+                    params: params.to_vec(),
+                    variants: variants.to_vec(),
+                },
+            );
+        }
+        let unknown = self.types.unknown();
+        let variants = variants
+            .iter()
+            .map(|variant| SumVariant {
+                name: variant.name.clone(),
+                fields: variant
+                    .fields
+                    .iter()
+                    .map(|field| match field {
+                        TypeExpr::Name(param) if params.contains(param) => unknown,
+                        other => self.types.intern_type_expr(other),
+                    })
+                    .collect(),
+            })
+            .collect();
+        self.types.intern_sum(name, variants);
+    }
+
+    fn declare_function(&mut self, func: &HFunction) {
+        let ret_type = self.declared_return_type(func);
+        let effects = func
+            .effect_row
+            .iter()
+            .map(|name| self.effects.intern(name.clone()))
+            .collect();
+        self.function_signatures
+            .insert(func.name.clone(), FunctionSignature { ret_type, effects });
+    }
+
+    /// The declared return type of `func`; functions declared without one
+    /// return unit.
+    fn declared_return_type(&mut self, func: &HFunction) -> TypeId {
+        match &func.return_type {
+            Some(ty) => self.types.intern_type_expr(ty),
+            None => self.types.intern(Type::Unit),
+        }
+    }
+
     fn push_function(&mut self, func: &HFunction) {
-        let declared_ret_type = func
-            .return_type
-            .as_ref()
-            .map(|ty| self.types.intern_type_expr(ty));
-        let ret_type = declared_ret_type.unwrap_or_else(|| self.types.unknown());
+        let ret_type = self.declared_return_type(func);
         let effect_row: Vec<_> = func
             .effect_row
             .iter()
@@ -229,18 +454,20 @@ impl ModuleLower {
         );
         let mut lowerer = FunctionLower::new(
             func.name.clone(),
-            declared_ret_type,
+            func.span,
+            ret_type,
             effect_row,
             &mut self.types,
             self.function_signatures.clone(),
+            self.generic_sums.clone(),
         );
         for param in &func.params {
             lowerer.push_param(param);
         }
         lowerer.lower_block(&func.body);
-        let lowered = lowerer.finish();
+        let mut lowered = lowerer.finish();
+        lowered.is_public = func.is_public;
         if let Some(signature) = self.function_signatures.get_mut(&func.name) {
-            signature.ret_type = lowered.ret_type;
             signature.effects = lowered.effect_row.clone();
         }
         self.functions.push(lowered);
@@ -263,22 +490,44 @@ struct FunctionLower<'a> {
     next_block: u32,
     current_block: BlockBuilder,
     blocks: Vec<BasicBlock>,
-    scopes: Vec<HashMap<String, ValueId>>,
+    scopes: Vec<HashMap<String, Binding>>,
+    slots: SlotTable,
+    loops: Vec<LoopTargets>,
     value_types: HashMap<ValueId, TypeId>,
     ret_type: TypeId,
     effect_row: Vec<EffectId>,
     types: &'a mut TypeTable,
     unknown: TypeId,
     functions: HashMap<String, FunctionSignature>,
+    generic_sums: HashMap<String, GenericSum>,
+    /// Span and HIR node stamped on everything emitted.
+    span: Span,
+    node: HirNode,
+}
+
+/// What a local name refers to: an immutable SSA value, or a mutable slot
+/// that is promoted to SSA once the function body is complete.
+#[derive(Debug, Clone, Copy)]
+enum Binding {
+    Value(ValueId),
+    Slot(SlotId),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LoopTargets {
+    continue_block: BlockId,
+    break_block: BlockId,
 }
 
 impl<'a> FunctionLower<'a> {
     fn new(
         name: String,
-        ret_type: Option<TypeId>,
+        span: Span,
+        ret_type: TypeId,
         effect_row: Vec<EffectId>,
         types: &'a mut TypeTable,
         functions: HashMap<String, FunctionSignature>,
+        generic_sums: HashMap<String, GenericSum>,
     ) -> Self {
         let entry = BlockBuilder::new(BlockId(0));
         let unknown = types.unknown();
@@ -290,29 +539,35 @@ impl<'a> FunctionLower<'a> {
             current_block: entry,
             blocks: Vec::new(),
             scopes: vec![HashMap::new()],
+            slots: SlotTable::default(),
+            loops: Vec::new(),
             value_types: HashMap::new(),
-            ret_type: ret_type.unwrap_or(unknown),
+            ret_type,
             effect_row,
             types,
             unknown,
             functions,
+            generic_sums,
+            span,
+            node: HirNode::Function,
         }
     }
 
     fn finish(mut self) -> Function {
         if self.current_block.terminator.is_none() {
-            let (unit, unit_ty) = self.emit_literal(Literal::Unit);
-            self.current_block
-                .set_terminator(Terminator::Return(Some(unit)));
-            self.merge_return_type(unit_ty);
+            let (unit, _) = self.emit_literal(Literal::Unit);
+            self.terminate(Terminator::Return(Some(unit)));
         }
         self.blocks.push(self.current_block.finish());
+        remove_unreachable_blocks(&mut self.blocks);
+        ssa::promote_slots(&mut self.blocks, &self.slots, &mut self.next_value);
         Function {
             name: self.name,
             params: self.params,
             ret_type: self.ret_type,
             blocks: self.blocks,
             effect_row: self.effect_row,
+            is_public: false,
         }
     }
 
@@ -320,10 +575,14 @@ impl<'a> FunctionLower<'a> {
         let id = self.alloc_value();
         let ty = self.types.intern_type_expr(&param.ty);
         self.value_types.insert(id, ty);
-        self.scopes
-            .last_mut()
-            .expect("scope stack")
-            .insert(param.name.clone(), id);
+        if param.mutable {
+            self.define_mutable(param.name.clone(), id, ty);
+        } else {
+            self.scopes
+                .last_mut()
+                .expect("scope stack")
+                .insert(param.name.clone(), Binding::Value(id));
+        }
         self.params.push(Param {
             name: param.name.clone(),
             ty,
@@ -332,14 +591,22 @@ impl<'a> FunctionLower<'a> {
     }
 
     fn lower_block(&mut self, block: &HBlock) {
+        let outer_span = self.span;
         self.with_scope(|this| {
             for (index, stmt) in block.stmts.iter().enumerate() {
                 if this.current_block.terminator.is_some() {
                     break;
                 }
+                if let Some(span) = block.spans.get(index) {
+                    this.span = *span;
+                }
 
+                // A unit function discards the value of its final expression.
                 let is_last = index + 1 == block.stmts.len();
-                if is_last && let HStmt::Expr(expr) = stmt {
+                if is_last
+                    && !this.returns_unit()
+                    && let HStmt::Expr(expr) = stmt
+                {
                     this.lower_return(Some(expr));
                     break;
                 }
@@ -347,13 +614,30 @@ impl<'a> FunctionLower<'a> {
                 this.lower_stmt(stmt);
             }
         });
+        self.span = outer_span;
     }
 
     fn lower_stmt(&mut self, stmt: &HStmt) {
+        let outer_node = self.node;
+        self.node = match stmt {
+            HStmt::Let { .. } => HirNode::Let,
+            HStmt::Expr(_) => outer_node,
+            HStmt::Return(_) => HirNode::Return,
+            HStmt::Break => HirNode::Break,
+            HStmt::Continue => HirNode::Continue,
+        };
         match stmt {
-            HStmt::Let { name, value } => {
+            HStmt::Let {
+                name,
+                mutable,
+                value,
+            } => {
                 let (val, ty) = self.lower_expr(value);
-                self.define(name.clone(), val, ty);
+                if *mutable {
+                    self.define_mutable(name.clone(), val, ty);
+                } else {
+                    self.define(name.clone(), val, ty);
+                }
             }
             HStmt::Expr(expr) => {
                 let _ = self.lower_expr(expr);
@@ -361,6 +645,23 @@ impl<'a> FunctionLower<'a> {
             HStmt::Return(expr) => {
                 self.lower_return(expr.as_ref());
             }
+            HStmt::Break => {
+                if let Some(targets) = self.loops.last().copied() {
+                    self.lower_jump(targets.break_block);
+                }
+            }
+            HStmt::Continue => {
+                if let Some(targets) = self.loops.last().copied() {
+                    self.lower_jump(targets.continue_block);
+                }
+            }
+        }
+        self.node = outer_node;
+    }
+
+    fn lower_jump(&mut self, target: BlockId) {
+        if self.current_block.terminator.is_none() {
+            self.terminate(Terminator::Jump(target));
         }
     }
 
@@ -368,47 +669,112 @@ impl<'a> FunctionLower<'a> {
         if self.current_block.terminator.is_some() {
             return;
         }
-        let value = expr.map(|e| self.lower_expr(e));
-        let value_id = value.as_ref().map(|(id, _)| *id);
-        if let Some((_, ty)) = &value {
-            self.merge_return_type(*ty);
-        } else {
-            let unit = self.types.intern(Type::Unit);
-            self.merge_return_type(unit);
+        let value_id = expr.map(|e| self.lower_expr(e).0);
+        self.node = HirNode::Return;
+        self.terminate(Terminator::Return(value_id));
+    }
+
+    /// Ends the current block, recording the current provenance.
+    fn terminate(&mut self, terminator: Terminator) {
+        let provenance = self.provenance();
+        self.current_block.set_terminator(terminator, provenance);
+    }
+
+    fn provenance(&self) -> Provenance {
+        Provenance {
+            function: self.name.clone(),
+            span: self.span,
+            node: self.node,
         }
-        self.current_block
-            .set_terminator(Terminator::Return(value_id));
     }
 
     fn lower_expr(&mut self, expr: &HExpr) -> (ValueId, TypeId) {
-        match expr {
-            HExpr::Literal(lit) => self.emit_literal(lit.clone()),
-            HExpr::Var(name) => {
-                let id = self
+        let outer_node = self.node;
+        self.node = match &expr.kind {
+            HExprKind::Literal(_) => HirNode::Literal,
+            HExprKind::Var(_) => HirNode::Var,
+            HExprKind::Path(_) => HirNode::Path,
+            HExprKind::Call {
+                func: HFuncRef::Method(name),
+                ..
+            } if name == "if" => HirNode::If,
+            HExprKind::Call { .. } => HirNode::Call,
+            HExprKind::Binary { .. } => HirNode::Binary,
+            HExprKind::Block(_) => HirNode::Block,
+            HExprKind::Record { .. } => HirNode::Record,
+            HExprKind::List(_) => HirNode::List,
+            HExprKind::Field { .. } => HirNode::Field,
+            HExprKind::Cast { .. } => HirNode::Cast,
+            HExprKind::Assign { .. } => HirNode::Assign,
+            HExprKind::While { .. } => HirNode::While,
+            HExprKind::Loop { .. } => HirNode::Loop,
+            HExprKind::For { .. } => HirNode::For,
+            HExprKind::Match { .. } => HirNode::Match,
+        };
+        let lowered = self.lower_expr_kind(expr);
+        self.node = outer_node;
+        lowered
+    }
+
+    /// The type the checker resolved for `expr`, or `guess` when the
+    /// checker could not type it.
+    fn resolve_type(&mut self, expr: &HExpr, guess: TypeId) -> TypeId {
+        match &expr.ty {
+            Some(checked) => match self.types.intern_type_expr(checked) {
+                checked if checked == self.unknown => guess,
+                checked => checked,
+            },
+            None => guess,
+        }
+    }
+
+    fn returns_unit(&self) -> bool {
+        matches!(self.types.get(self.ret_type), Type::Unit)
+    }
+
+    fn lower_expr_kind(&mut self, expr: &HExpr) -> (ValueId, TypeId) {
+        match &expr.kind {
+            HExprKind::Literal(lit) => self.emit_literal(lit.clone()),
+            HExprKind::Var(name) => {
+                let binding = self
                     .lookup(name)
                     .unwrap_or_else(|| panic!("unknown variable {name}"));
-                let ty = self.value_types.get(&id).copied().unwrap_or(self.unknown);
-                (id, ty)
+                self.read_binding(binding)
             }
-            HExpr::Path(path) => {
+            HExprKind::Path(path) => {
                 if path.segments.len() == 1
-                    && let Some(id) = self.lookup(&path.segments[0])
+                    && let Some(binding) = self.lookup(&path.segments[0])
                 {
-                    let ty = self.value_types.get(&id).copied().unwrap_or(self.unknown);
-                    return (id, ty);
+                    return self.read_binding(binding);
+                }
+                if let Some(sum) = self.variant_type(path) {
+                    return self.lower_variant(path, sum, &[]);
                 }
-                self.emit_instruction(InstKind::Path(path.clone()), self.unknown, Vec::new())
+                let ty = self.resolve_type(expr, self.unknown);
+                self.emit_instruction(InstKind::Path(path.clone()), ty, Vec::new())
             }
-            HExpr::Call { func, args } => {
+            HExprKind::Call { func, args } => {
                 if let HFuncRef::Method(name) = func
                     && name == "if"
                 {
-                    return self.lower_if_call(args);
+                    return self.lower_if_call(expr, args);
+                }
+                if let HFuncRef::Function(path) = func
+                    && let Some(sum) = self.variant_type(path)
+                {
+                    return self.lower_variant(path, sum, args);
                 }
                 let mut lowered_args = Vec::with_capacity(args.len());
+                let mut arg_types = Vec::with_capacity(args.len());
                 for arg in args {
-                    let (id, _) = self.lower_expr(arg);
+                    let (id, ty) = self.lower_expr(arg);
                     lowered_args.push(id);
+                    arg_types.push(ty);
+                }
+                if let HFuncRef::Method(name) = func
+                    && let Some(lowered) = self.lower_list_method(name, &lowered_args, &arg_types)
+                {
+                    return lowered;
                 }
                 let func_ref = match func {
                     HFuncRef::Function(path) => FuncRef::Function(path.clone()),
@@ -416,6 +782,7 @@ impl<'a> FunctionLower<'a> {
                 };
                 let effects = self.lookup_effects(&func_ref);
                 let ret_ty = self.lookup_return_type(&func_ref);
+                let ret_ty = self.resolve_type(expr, ret_ty);
                 self.emit_instruction(
                     InstKind::Call {
                         func: func_ref,
@@ -425,14 +792,15 @@ impl<'a> FunctionLower<'a> {
                     effects,
                 )
             }
-            HExpr::Binary { lhs, op, rhs } => {
+            HExprKind::Binary { lhs, op, rhs } => {
                 let (lhs_id, lhs_ty) = self.lower_expr(lhs);
                 let (rhs_id, rhs_ty) = self.lower_expr(rhs);
-                let ty = if lhs_ty != self.unknown && lhs_ty == rhs_ty {
+                let guess = if lhs_ty != self.unknown && lhs_ty == rhs_ty {
                     lhs_ty
                 } else {
                     self.unknown
                 };
+                let ty = self.resolve_type(expr, guess);
                 self.emit_instruction(
                     InstKind::Binary {
                         op: *op,
@@ -443,17 +811,18 @@ impl<'a> FunctionLower<'a> {
                     Vec::new(),
                 )
             }
-            HExpr::Block(block) => self.lower_block_expr(block),
-            HExpr::Record { type_path, fields } => {
+            HExprKind::Block(block) => self.lower_block_expr(block),
+            HExprKind::Record { type_path, fields } => {
                 let mut lowered_fields = Vec::with_capacity(fields.len());
                 for (name, value) in fields {
                     let (id, _) = self.lower_expr(value);
                     lowered_fields.push((name.clone(), id));
                 }
-                let ty = type_path
+                let guess = type_path
                     .as_ref()
                     .and_then(|path| self.lookup_type(path))
                     .unwrap_or(self.unknown);
+                let ty = self.resolve_type(expr, guess);
                 let reordered = self.reorder_record_fields(ty, lowered_fields);
                 self.emit_instruction(
                     InstKind::Record {
@@ -464,7 +833,453 @@ impl<'a> FunctionLower<'a> {
                     Vec::new(),
                 )
             }
+            HExprKind::Assign { name, value } => match self.lookup(name) {
+                Some(Binding::Slot(slot)) => {
+                    let (value, _) = self.lower_expr(value);
+                    self.write_slot(slot, value);
+                    self.emit_literal(Literal::Unit)
+                }
+                _ => {
+                    let target = HExpr::untyped(HExprKind::Path(Path {
+                        segments: vec![name.clone()],
+                    }));
+                    self.lower_expr(&HExpr::untyped(HExprKind::Call {
+                        func: HFuncRef::Method("assign".into()),
+                        args: vec![target, (**value).clone()],
+                    }))
+                }
+            },
+            HExprKind::Field { expr: record, name } => {
+                let (base, base_ty) = self.lower_expr(record);
+                let guess = match self.types.get(base_ty) {
+                    Type::Record(record) => record
+                        .field(name)
+                        .map(|field| field.ty)
+                        .unwrap_or(self.unknown),
+                    _ => self.unknown,
+                };
+                let ty = self.resolve_type(expr, guess);
+                self.emit_instruction(
+                    InstKind::Field {
+                        base,
+                        name: name.clone(),
+                    },
+                    ty,
+                    Vec::new(),
+                )
+            }
+            HExprKind::Cast { expr: operand, ty } => {
+                let (value, from) = self.lower_expr(operand);
+                let to = self.types.intern_type_expr(ty);
+                match (self.types.get(from), self.types.get(to)) {
+                    (Type::Int, Type::Float) | (Type::Float, Type::Int) => {
+                        self.emit_instruction(InstKind::Convert { value }, to, Vec::new())
+                    }
+                    // Anything else is a no-op that keeps the operand's type.
+                    _ => (value, from),
+                }
+            }
+            HExprKind::List(elements) => {
+                let mut lowered = Vec::with_capacity(elements.len());
+                let mut element_ty = None;
+                for element in elements {
+                    let (id, ty) = self.lower_expr(element);
+                    lowered.push(id);
+                    element_ty = Some(match element_ty {
+                        None => ty,
+                        Some(existing) => self.join_types(existing, ty),
+                    });
+                }
+                let element_ty = element_ty.unwrap_or(self.unknown);
+                let ty = match &expr.ty {
+                    Some(checked) if element_ty == self.unknown => {
+                        self.types.intern_type_expr(checked)
+                    }
+                    _ => self.types.intern(Type::List(element_ty)),
+                };
+                self.emit_instruction(InstKind::List { elements: lowered }, ty, Vec::new())
+            }
+            HExprKind::While { condition, body } => self.lower_loop(Some(condition), body),
+            HExprKind::Loop { body } => self.lower_loop(None, body),
+            HExprKind::For {
+                binding,
+                iterable,
+                body,
+            } => self.lower_for(binding, iterable, body),
+            HExprKind::Match { scrutinee, arms } => self.lower_match(expr, scrutinee, arms),
+        }
+    }
+
+    /// The sum type declaring the variant `path` names, unless a function of
+    /// that name shadows it.
+    fn variant_type(&self, path: &Path) -> Option<TypeId> {
+        let (name, qualifier) = path.segments.split_last()?;
+        if qualifier.is_empty() && self.functions.contains_key(name) {
+            return None;
+        }
+        self.types
+            .variant_owner(name, qualifier.last().map(String::as_str))
+    }
+
+    fn lower_variant(&mut self, path: &Path, sum: TypeId, args: &[HExpr]) -> (ValueId, TypeId) {
+        let payload = args.iter().map(|arg| self.lower_expr(arg).0).collect();
+        let variant = path.segments.last().expect("variant path").clone();
+        self.emit_instruction(InstKind::Variant { variant, payload }, sum, Vec::new())
+    }
+
+    /// Lowers `match` to a chain of arm tests. Each arm gets its own failure
+    /// block, which tests the next arm; bodies jump to a shared merge block,
+    /// and falling off the last arm traps.
+    fn lower_match(
+        &mut self,
+        expr: &HExpr,
+        scrutinee: &HExpr,
+        arms: &[HMatchArm],
+    ) -> (ValueId, TypeId) {
+        let (value, value_ty) = self.lower_expr(scrutinee);
+        let merge_block = self.alloc_block();
+        let merge_block_id = merge_block.id();
+
+        let mut incomings = Vec::with_capacity(arms.len());
+        let mut joined: Option<TypeId> = None;
+        for arm in arms {
+            let fail = self.alloc_block();
+            let fail_id = fail.id();
+            let (arm_value, arm_ty) = self.with_scope(|this| {
+                let checked = scrutinee.ty.as_ref();
+                this.lower_pattern(&arm.pattern, value, value_ty, checked, fail_id);
+                if let Some(guard) = &arm.guard {
+                    let (condition, _) = this.lower_expr(guard);
+                    this.branch_or_fail(condition, fail_id);
+                }
+                this.lower_expr(&arm.body)
+            });
+            if !self.current_block.has_terminator() {
+                incomings.push((self.current_block.id(), arm_value));
+                self.terminate(Terminator::Jump(merge_block_id));
+                joined = Some(match joined {
+                    None => arm_ty,
+                    Some(existing) => self.join_types(existing, arm_ty),
+                });
+            }
+            let previous = self.switch_block(fail);
+            self.blocks.push(previous.finish());
+        }
+
+        let unit = self.types.intern(Type::Unit);
+        self.emit_instruction(
+            InstKind::Trap {
+                message: "no match arm applies".into(),
+            },
+            unit,
+            Vec::new(),
+        );
+        self.terminate(Terminator::Return(None));
+        let previous = self.switch_block(merge_block);
+        self.blocks.push(previous.finish());
+
+        let ty = self.resolve_type(expr, joined.unwrap_or(self.unknown));
+        self.emit_instruction(InstKind::Phi { incomings }, ty, Vec::new())
+    }
+
+    /// Emits the tests for `pattern` against `value`, continuing in the
+    /// current block when they pass and jumping to `fail` otherwise, and
+    /// binds the names the pattern introduces. `checked` is the checker's
+    /// type for `value`, which types the payloads of generic variants.
+    fn lower_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: ValueId,
+        ty: TypeId,
+        checked: Option<&TypeExpr>,
+        fail: BlockId,
+    ) {
+        match pattern {
+            Pattern::Wildcard | Pattern::Literal(Literal::Unit) => {}
+            Pattern::Binding(name) => self.define(name.clone(), value, ty),
+            Pattern::Literal(literal) => {
+                let (expected, _) = self.emit_literal(literal.clone());
+                let bool_ty = self.types.intern(Type::Bool);
+                let (equal, _) = self.emit_instruction(
+                    InstKind::Binary {
+                        op: BinaryOp::Eq,
+                        lhs: value,
+                        rhs: expected,
+                    },
+                    bool_ty,
+                    Vec::new(),
+                );
+                self.branch_or_fail(equal, fail);
+            }
+            // The unit value is the only tuple the IR can represent.
+            Pattern::Tuple(items) if items.is_empty() => {}
+            Pattern::Tuple(_) => self.lower_jump(fail),
+            Pattern::Record(fields) => {
+                for (name, sub_pattern) in fields {
+                    let field_ty = match self.types.get(ty) {
+                        Type::Record(record) => record
+                            .field(name)
+                            .map(|field| field.ty)
+                            .unwrap_or(self.unknown),
+                        _ => self.unknown,
+                    };
+                    let (field, _) = self.emit_instruction(
+                        InstKind::Field {
+                            base: value,
+                            name: name.clone(),
+                        },
+                        field_ty,
+                        Vec::new(),
+                    );
+                    self.lower_pattern(sub_pattern, field, field_ty, None, fail);
+                }
+            }
+            Pattern::EnumVariant { path, fields } => {
+                let name = path.segments.last().expect("variant path");
+                let field_types = self
+                    .types
+                    .sum(ty)
+                    .or_else(|| self.variant_type(path).and_then(|sum| self.types.sum(sum)))
+                    .and_then(|sum| sum.variant(name))
+                    .map(|(_, variant)| variant.fields.clone());
+                let Some(field_types) = field_types else {
+                    self.lower_jump(fail);
+                    return;
+                };
+                let bool_ty = self.types.intern(Type::Bool);
+                let (is_variant, _) = self.emit_instruction(
+                    InstKind::IsVariant {
+                        value,
+                        variant: name.clone(),
+                    },
+                    bool_ty,
+                    Vec::new(),
+                );
+                self.branch_or_fail(is_variant, fail);
+                let instantiated = self.instantiate_payloads(name, checked);
+                for (index, (sub_pattern, field_ty)) in fields.iter().zip(field_types).enumerate() {
+                    if matches!(sub_pattern, Pattern::Wildcard) {
+                        continue;
+                    }
+                    let field_checked = instantiated.as_ref().and_then(|fields| fields.get(index));
+                    let field_ty = match field_checked {
+                        Some(checked) if field_ty == self.unknown => {
+                            self.types.intern_type_expr(checked)
+                        }
+                        _ => field_ty,
+                    };
+                    let (field, _) = self.emit_instruction(
+                        InstKind::Payload {
+                            value,
+                            variant: name.clone(),
+                            index: index as u32,
+                        },
+                        field_ty,
+                        Vec::new(),
+                    );
+                    self.lower_pattern(sub_pattern, field, field_ty, field_checked, fail);
+                }
+            }
+        }
+    }
+
+    /// The payload types of `variant` when `checked` instantiates the generic
+    /// sum declaring it, such as `Int` for `Some` in an `Option[Int]`.
+    fn instantiate_payloads(
+        &self,
+        variant: &str,
+        checked: Option<&TypeExpr>,
+    ) -> Option<Vec<TypeExpr>> {
+        let Some(TypeExpr::Generic(name, args)) = checked else {
+            return None;
+        };
+        let sum = self.generic_sums.get(name)?;
+        let declared = sum
+            .variants
+            .iter()
+            .find(|candidate| candidate.name == variant)?;
+        Some(
+            declared
+                .fields
+                .iter()
+                .map(|field| substitute_params(field, &sum.params, args))
+                .collect(),
+        )
+    }
+
+    /// Continues in a fresh block when `condition` holds and jumps to `fail`
+    /// otherwise.
+    fn branch_or_fail(&mut self, condition: ValueId, fail: BlockId) {
+        if self.current_block.has_terminator() {
+            return;
+        }
+        let pass = self.alloc_block();
+        self.terminate(Terminator::Branch {
+            condition,
+            then_block: pass.id(),
+            else_block: fail,
+        });
+        let previous = self.switch_block(pass);
+        self.blocks.push(previous.finish());
+    }
+
+    /// Lowers the built-in `len`, `push` and `index` methods to list
+    /// instructions when the receiver is statically a list. Returns `None` for
+    /// anything else so the call stays an opaque method call.
+    fn lower_list_method(
+        &mut self,
+        name: &str,
+        args: &[ValueId],
+        arg_types: &[TypeId],
+    ) -> Option<(ValueId, TypeId)> {
+        let Type::List(element_ty) = *self.types.get(*arg_types.first()?) else {
+            return None;
+        };
+        let (kind, ty) = match (name, args) {
+            ("len", [list]) => (
+                InstKind::ListLen { list: *list },
+                self.types.intern(Type::Int),
+            ),
+            ("index", [list, index]) => (
+                InstKind::ListIndex {
+                    list: *list,
+                    index: *index,
+                },
+                element_ty,
+            ),
+            ("push", [list, value]) => (
+                InstKind::ListPush {
+                    list: *list,
+                    value: *value,
+                },
+                self.types.intern(Type::Unit),
+            ),
+            _ => return None,
+        };
+        Some(self.emit_instruction(kind, ty, Vec::new()))
+    }
+
+    /// Lowers `for binding in list body` to an index loop: the header compares
+    /// a hidden counter against the list length, the body binds the current
+    /// element, and a latch block (the `continue` target) advances the counter.
+    fn lower_for(&mut self, binding: &str, iterable: &HExpr, body: &HExpr) -> (ValueId, TypeId) {
+        let (list, list_ty) = self.lower_expr(iterable);
+        let element_ty = match self.types.get(list_ty) {
+            Type::List(element) => *element,
+            _ => self.unknown,
+        };
+        let (zero, int_ty) = self.emit_literal(Literal::Int(0));
+        let counter = self.slots.declare(int_ty);
+        self.write_slot(counter, zero);
+
+        let header = self.alloc_block();
+        let header_id = header.id();
+        let body_block = self.alloc_block();
+        let body_id = body_block.id();
+        let latch = self.alloc_block();
+        let latch_id = latch.id();
+        let exit = self.alloc_block();
+        let exit_id = exit.id();
+
+        self.terminate(Terminator::Jump(header_id));
+        let previous = self.switch_block(header);
+        self.blocks.push(previous.finish());
+
+        let (index, _) = self.read_binding(Binding::Slot(counter));
+        let (len, _) = self.emit_instruction(InstKind::ListLen { list }, int_ty, Vec::new());
+        let bool_ty = self.types.intern(Type::Bool);
+        let (in_bounds, _) = self.emit_instruction(
+            InstKind::Binary {
+                op: BinaryOp::Lt,
+                lhs: index,
+                rhs: len,
+            },
+            bool_ty,
+            Vec::new(),
+        );
+        self.terminate(Terminator::Branch {
+            condition: in_bounds,
+            then_block: body_id,
+            else_block: exit_id,
+        });
+        let previous = self.switch_block(body_block);
+        self.blocks.push(previous.finish());
+
+        self.loops.push(LoopTargets {
+            continue_block: latch_id,
+            break_block: exit_id,
+        });
+        self.with_scope(|this| {
+            let (index, _) = this.read_binding(Binding::Slot(counter));
+            let (element, _) =
+                this.emit_instruction(InstKind::ListIndex { list, index }, element_ty, Vec::new());
+            this.define(binding.to_string(), element, element_ty);
+            this.lower_expr(body)
+        });
+        self.loops.pop();
+        self.lower_jump(latch_id);
+
+        let previous = self.switch_block(latch);
+        self.blocks.push(previous.finish());
+        let (index, _) = self.read_binding(Binding::Slot(counter));
+        let (one, _) = self.emit_literal(Literal::Int(1));
+        let (next, _) = self.emit_instruction(
+            InstKind::Binary {
+                op: BinaryOp::Add,
+                lhs: index,
+                rhs: one,
+            },
+            int_ty,
+            Vec::new(),
+        );
+        self.write_slot(counter, next);
+        self.terminate(Terminator::Jump(header_id));
+
+        let previous = self.switch_block(exit);
+        self.blocks.push(previous.finish());
+        self.emit_literal(Literal::Unit)
+    }
+
+    /// Lowers `while` (with a condition) and `loop` (without one). The loop
+    /// header is the `continue` target and a fresh exit block is the `break`
+    /// target; the loop itself evaluates to unit.
+    fn lower_loop(&mut self, condition: Option<&HExpr>, body: &HExpr) -> (ValueId, TypeId) {
+        self.ensure_open_block();
+        let header = self.alloc_block();
+        let header_id = header.id();
+        let exit = self.alloc_block();
+        let exit_id = exit.id();
+
+        self.terminate(Terminator::Jump(header_id));
+        let previous = self.switch_block(header);
+        self.blocks.push(previous.finish());
+
+        if let Some(condition) = condition {
+            let (cond_value, _) = self.lower_expr(condition);
+            let body_block = self.alloc_block();
+            let body_id = body_block.id();
+            if !self.current_block.has_terminator() {
+                self.terminate(Terminator::Branch {
+                    condition: cond_value,
+                    then_block: body_id,
+                    else_block: exit_id,
+                });
+            }
+            let previous = self.switch_block(body_block);
+            self.blocks.push(previous.finish());
         }
+
+        self.loops.push(LoopTargets {
+            continue_block: header_id,
+            break_block: exit_id,
+        });
+        self.with_scope(|this| this.lower_expr(body));
+        self.loops.pop();
+        self.lower_jump(header_id);
+
+        let previous = self.switch_block(exit);
+        self.blocks.push(previous.finish());
+        self.emit_literal(Literal::Unit)
     }
 
     fn lower_block_expr(&mut self, block: &HBlock) -> (ValueId, TypeId) {
@@ -472,26 +1287,19 @@ impl<'a> FunctionLower<'a> {
         self.with_scope(|this| {
             for stmt in &block.stmts {
                 match stmt {
-                    HStmt::Let { name, value } => {
-                        let (val, ty) = this.lower_expr(value);
-                        this.define(name.clone(), val, ty);
-                    }
                     HStmt::Expr(expr) => {
                         result = Some(this.lower_expr(expr));
                     }
-                    HStmt::Return(expr) => {
-                        this.lower_return(expr.as_ref());
-                    }
+                    other => this.lower_stmt(other),
                 }
                 if this.current_block.terminator.is_some() {
                     break;
                 }
             }
         });
-        if let Some(res) = result {
-            res
-        } else {
-            self.emit_literal(Literal::Unit)
+        match result {
+            Some(res) if !self.current_block.has_terminator() => res,
+            _ => self.emit_literal(Literal::Unit),
         }
     }
 
@@ -506,9 +1314,7 @@ impl<'a> FunctionLower<'a> {
         ty: TypeId,
         effects: Vec<EffectId>,
     ) -> (ValueId, TypeId) {
-        if self.current_block.terminator.is_some() {
-            panic!("attempted to emit instruction after block was terminated");
-        }
+        self.ensure_open_block();
         let id = self.alloc_value();
         self.value_types.insert(id, ty);
         let instr = Instruction {
@@ -516,12 +1322,13 @@ impl<'a> FunctionLower<'a> {
             ty,
             effects,
             kind,
+            provenance: Some(self.provenance()),
         };
         self.current_block.push_instruction(instr);
         (id, ty)
     }
 
-    fn lower_if_call(&mut self, args: &[HExpr]) -> (ValueId, TypeId) {
+    fn lower_if_call(&mut self, expr: &HExpr, args: &[HExpr]) -> (ValueId, TypeId) {
         if args.len() < 2 {
             panic!("if call expected at least condition and then branch");
         }
@@ -538,7 +1345,7 @@ impl<'a> FunctionLower<'a> {
         let merge_block = self.alloc_block();
         let merge_block_id = merge_block.id();
 
-        self.current_block.set_terminator(Terminator::Branch {
+        self.terminate(Terminator::Branch {
             condition: cond_value,
             then_block: then_block_id,
             else_block: else_block_id,
@@ -547,10 +1354,14 @@ impl<'a> FunctionLower<'a> {
         let previous = self.switch_block(then_block);
         self.blocks.push(previous.finish());
 
+        // Branches may end in a different block than they started in (nested
+        // control flow), and branches that `return`, `break` or `continue`
+        // never reach the merge block at all.
+        let mut incomings = Vec::with_capacity(2);
         let (then_value, then_ty) = self.with_scope(|this| this.lower_expr(then_branch));
         if !self.current_block.has_terminator() {
-            self.current_block
-                .set_terminator(Terminator::Jump(merge_block_id));
+            incomings.push((self.current_block.id(), then_value));
+            self.terminate(Terminator::Jump(merge_block_id));
         }
         let previous_then = self.switch_block(else_block);
         self.blocks.push(previous_then.finish());
@@ -561,31 +1372,73 @@ impl<'a> FunctionLower<'a> {
             self.emit_literal(Literal::Unit)
         };
         if !self.current_block.has_terminator() {
-            self.current_block
-                .set_terminator(Terminator::Jump(merge_block_id));
+            incomings.push((self.current_block.id(), else_value));
+            self.terminate(Terminator::Jump(merge_block_id));
         }
         let previous_else = self.switch_block(merge_block);
         self.blocks.push(previous_else.finish());
 
-        let ty = self.join_types(then_ty, else_ty);
-        let (phi_value, phi_ty) = self.emit_instruction(
-            InstKind::Phi {
-                incomings: vec![(then_block_id, then_value), (else_block_id, else_value)],
-            },
-            ty,
-            Vec::new(),
-        );
+        let joined = self.join_types(then_ty, else_ty);
+        let ty = self.resolve_type(expr, joined);
+        let (phi_value, phi_ty) =
+            self.emit_instruction(InstKind::Phi { incomings }, ty, Vec::new());
         (phi_value, phi_ty)
     }
 
     fn define(&mut self, name: String, value: ValueId, ty: TypeId) {
         self.value_types.entry(value).or_insert(ty);
         if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name, value);
+            scope.insert(name, Binding::Value(value));
+        }
+    }
+
+    fn define_mutable(&mut self, name: String, value: ValueId, ty: TypeId) {
+        self.value_types.entry(value).or_insert(ty);
+        let slot = self.slots.declare(ty);
+        self.write_slot(slot, value);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, Binding::Slot(slot));
+        }
+    }
+
+    fn read_binding(&mut self, binding: Binding) -> (ValueId, TypeId) {
+        match binding {
+            Binding::Value(id) => {
+                let ty = self.value_types.get(&id).copied().unwrap_or(self.unknown);
+                (id, ty)
+            }
+            Binding::Slot(slot) => {
+                self.ensure_open_block();
+                let placeholder = self.alloc_value();
+                let ty = self.slots.ty(slot);
+                self.value_types.insert(placeholder, ty);
+                self.slots.record(
+                    self.current_block.id(),
+                    SlotAccess::Read { slot, placeholder },
+                );
+                (placeholder, ty)
+            }
+        }
+    }
+
+    fn write_slot(&mut self, slot: SlotId, value: ValueId) {
+        self.ensure_open_block();
+        self.slots
+            .record(self.current_block.id(), SlotAccess::Write { slot, value });
+    }
+
+    /// Code following a `return`, `break` or `continue` is unreachable; it is
+    /// lowered into a fresh block without predecessors that is discarded when
+    /// the function is finished.
+    fn ensure_open_block(&mut self) {
+        if self.current_block.has_terminator() {
+            let dead = self.alloc_block();
+            let previous = self.switch_block(dead);
+            self.blocks.push(previous.finish());
         }
     }
 
-    fn lookup(&self, name: &str) -> Option<ValueId> {
+    fn lookup(&self, name: &str) -> Option<Binding> {
         for scope in self.scopes.iter().rev() {
             if let Some(id) = scope.get(name) {
                 return Some(*id);
@@ -654,21 +1507,6 @@ impl<'a> FunctionLower<'a> {
         result
     }
 
-    fn merge_return_type(&mut self, ty: TypeId) {
-        if self.ret_type == self.unknown {
-            self.ret_type = ty;
-            return;
-        }
-        if ty == self.unknown {
-            return;
-        }
-        let existing = self.types.get(self.ret_type).clone();
-        let new = self.types.get(ty).clone();
-        if existing != new {
-            self.ret_type = self.unknown;
-        }
-    }
-
     fn alloc_value(&mut self) -> ValueId {
         let id = ValueId(self.next_value);
         self.next_value += 1;
@@ -702,11 +1540,60 @@ impl<'a> FunctionLower<'a> {
     }
 }
 
+/// Replaces the type parameters `params` in `ty` with `args`.
+fn substitute_params(ty: &TypeExpr, params: &[String], args: &[TypeExpr]) -> TypeExpr {
+    match ty {
+        TypeExpr::Name(name) => params
+            .iter()
+            .position(|param| param == name)
+            .and_then(|index| args.get(index))
+            .cloned()
+            .unwrap_or_else(|| ty.clone()),
+        TypeExpr::Generic(name, inner) => TypeExpr::Generic(
+            name.clone(),
+            inner
+                .iter()
+                .map(|item| substitute_params(item, params, args))
+                .collect(),
+        ),
+        TypeExpr::List(inner) => TypeExpr::List(Box::new(substitute_params(inner, params, args))),
+        other => other.clone(),
+    }
+}
+
+fn remove_unreachable_blocks(blocks: &mut Vec<BasicBlock>) {
+    let Some(entry) = blocks.first().map(|block| block.id) else {
+        return;
+    };
+    let successors: HashMap<BlockId, Vec<BlockId>> = blocks
+        .iter()
+        .map(|block| (block.id, block.terminator.successors()))
+        .collect();
+    let mut reachable = std::collections::HashSet::new();
+    let mut stack = vec![entry];
+    while let Some(block) = stack.pop() {
+        if reachable.insert(block)
+            && let Some(succs) = successors.get(&block)
+        {
+            stack.extend(succs.iter().copied());
+        }
+    }
+    blocks.retain(|block| reachable.contains(&block.id));
+    for block in blocks.iter_mut() {
+        for inst in &mut block.instructions {
+            if let InstKind::Phi { incomings } = &mut inst.kind {
+                incomings.retain(|(pred, _)| reachable.contains(pred));
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct BlockBuilder {
     id: BlockId,
     instructions: Vec<Instruction>,
     terminator: Option<Terminator>,
+    terminator_provenance: Option<Provenance>,
 }
 
 impl BlockBuilder {
@@ -715,6 +1602,7 @@ impl BlockBuilder {
             id,
             instructions: Vec::new(),
             terminator: None,
+            terminator_provenance: None,
         }
     }
 
@@ -726,8 +1614,9 @@ impl BlockBuilder {
         self.instructions.push(inst);
     }
 
-    fn set_terminator(&mut self, terminator: Terminator) {
+    fn set_terminator(&mut self, terminator: Terminator, provenance: Provenance) {
         self.terminator = Some(terminator);
+        self.terminator_provenance = Some(provenance);
     }
 
     fn has_terminator(&self) -> bool {
@@ -739,6 +1628,7 @@ impl BlockBuilder {
             id: self.id,
             instructions: self.instructions,
             terminator: self.terminator.unwrap_or(Terminator::Return(None)),
+            terminator_provenance: self.terminator_provenance,
         }
     }
 }
@@ -755,6 +1645,217 @@ impl Module {
     pub fn unknown_type(&self) -> TypeId {
         self.types.unknown()
     }
+
+    /// Drops the provenance of every instruction and terminator.
+    pub fn clear_provenance(&mut self) {
+        for block in self.functions.iter_mut().flat_map(|f| &mut f.blocks) {
+            block.terminator_provenance = None;
+            for inst in &mut block.instructions {
+                inst.provenance = None;
+            }
+        }
+    }
+
+    /// Drops every type no function mentions, directly or through a record
+    /// field or list element, and renumbers the rest. Builtin types and the
+    /// aliases of surviving types are kept. Reports whether any type was
+    /// dropped.
+    pub fn prune_types(&mut self) -> bool {
+        let used = self.functions.iter().flat_map(|function| {
+            function
+                .params
+                .iter()
+                .map(|param| param.ty)
+                .chain(std::iter::once(function.ret_type))
+                .chain(
+                    function
+                        .blocks
+                        .iter()
+                        .flat_map(|block| &block.instructions)
+                        .map(|inst| inst.ty),
+                )
+        });
+        let (types, remap) = self.types.retain_used(used);
+        if types.entries().count() == self.types.entries().count() {
+            return false;
+        }
+        for function in &mut self.functions {
+            function.remap_types(&remap);
+        }
+        self.types = types;
+        true
+    }
+}
+
+impl Function {
+    /// Rewrites every type ID through `remap`, which must cover them all.
+    fn remap_types(&mut self, remap: &HashMap<TypeId, TypeId>) {
+        self.ret_type = remap[&self.ret_type];
+        for param in &mut self.params {
+            param.ty = remap[&param.ty];
+        }
+        for block in &mut self.blocks {
+            for inst in &mut block.instructions {
+                inst.ty = remap[&inst.ty];
+            }
+        }
+    }
+}
+
+impl HirNode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HirNode::Function => "fn",
+            HirNode::Let => "let",
+            HirNode::Return => "return",
+            HirNode::Break => "break",
+            HirNode::Continue => "continue",
+            HirNode::Literal => "literal",
+            HirNode::Var => "var",
+            HirNode::Path => "path",
+            HirNode::Call => "call",
+            HirNode::If => "if",
+            HirNode::Binary => "binary",
+            HirNode::Block => "block",
+            HirNode::Record => "record",
+            HirNode::List => "list",
+            HirNode::Field => "field",
+            HirNode::Cast => "cast",
+            HirNode::Assign => "assign",
+            HirNode::While => "while",
+            HirNode::Loop => "loop",
+            HirNode::For => "for",
+            HirNode::Match => "match",
+        }
+    }
+}
+
+impl BasicBlock {
+    /// The callee path and arguments of the module-function call whose
+    /// result this block returns, when that call is its last instruction.
+    pub fn tail_call(&self) -> Option<(&Path, &[ValueId])> {
+        let Terminator::Return(Some(value)) = self.terminator else {
+            return None;
+        };
+        let last = self.instructions.last()?;
+        match &last.kind {
+            InstKind::Call {
+                func: FuncRef::Function(path),
+                args,
+            } if last.id == value => Some((path, args)),
+            _ => None,
+        }
+    }
+}
+
+impl Terminator {
+    /// Blocks control may transfer to from this terminator.
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Return(_) => Vec::new(),
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => {
+                if then_block == else_block {
+                    vec![*then_block]
+                } else {
+                    vec![*then_block, *else_block]
+                }
+            }
+        }
+    }
+
+    /// The value this terminator reads, if any.
+    pub fn operand(&self) -> Option<ValueId> {
+        match self {
+            Terminator::Return(value) => *value,
+            Terminator::Branch { condition, .. } => Some(*condition),
+            Terminator::Jump(_) => None,
+        }
+    }
+
+    pub(crate) fn map_operands(&mut self, f: impl Fn(ValueId) -> ValueId) {
+        match self {
+            Terminator::Return(Some(value)) => *value = f(*value),
+            Terminator::Return(None) | Terminator::Jump(_) => {}
+            Terminator::Branch { condition, .. } => *condition = f(*condition),
+        }
+    }
+}
+
+impl InstKind {
+    /// Values read by this instruction, in operand order.
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            InstKind::Literal(_) | InstKind::Path(_) | InstKind::Trap { .. } => Vec::new(),
+            InstKind::Field { base, .. } => vec![*base],
+            InstKind::Convert { value } => vec![*value],
+            InstKind::Binary { lhs, rhs, .. } => vec![*lhs, *rhs],
+            InstKind::Call { args, .. } => args.clone(),
+            InstKind::Record { fields, .. } => fields.iter().map(|(_, value)| *value).collect(),
+            InstKind::Phi { incomings } => incomings.iter().map(|(_, value)| *value).collect(),
+            InstKind::List { elements } => elements.clone(),
+            InstKind::ListLen { list } => vec![*list],
+            InstKind::ListIndex { list, index } => vec![*list, *index],
+            InstKind::ListPush { list, value } => vec![*list, *value],
+            InstKind::Retain { value } | InstKind::Release { value } => vec![*value],
+            InstKind::Variant { payload, .. } => payload.clone(),
+            InstKind::IsVariant { value, .. } | InstKind::Payload { value, .. } => vec![*value],
+        }
+    }
+
+    pub(crate) fn map_operands(&mut self, f: impl Fn(ValueId) -> ValueId) {
+        match self {
+            InstKind::Literal(_) | InstKind::Path(_) | InstKind::Trap { .. } => {}
+            InstKind::Field { base, .. } => *base = f(*base),
+            InstKind::Convert { value } => *value = f(*value),
+            InstKind::Binary { lhs, rhs, .. } => {
+                *lhs = f(*lhs);
+                *rhs = f(*rhs);
+            }
+            InstKind::Call { args, .. } => {
+                for arg in args {
+                    *arg = f(*arg);
+                }
+            }
+            InstKind::Record { fields, .. } => {
+                for (_, value) in fields {
+                    *value = f(*value);
+                }
+            }
+            InstKind::Phi { incomings } => {
+                for (_, value) in incomings {
+                    *value = f(*value);
+                }
+            }
+            InstKind::List { elements } => {
+                for element in elements {
+                    *element = f(*element);
+                }
+            }
+            InstKind::ListLen { list } => *list = f(*list),
+            InstKind::ListIndex { list, index } => {
+                *list = f(*list);
+                *index = f(*index);
+            }
+            InstKind::ListPush { list, value } => {
+                *list = f(*list);
+                *value = f(*value);
+            }
+            InstKind::Retain { value } | InstKind::Release { value } => *value = f(*value),
+            InstKind::Variant { payload, .. } => {
+                for value in payload {
+                    *value = f(*value);
+                }
+            }
+            InstKind::IsVariant { value, .. } | InstKind::Payload { value, .. } => {
+                *value = f(*value)
+            }
+        }
+    }
 }
 
 impl ValueId {
@@ -829,6 +1930,10 @@ impl TypeTable {
     pub fn intern_type_expr(&mut self, expr: &TypeExpr) -> TypeId {
         match expr {
             TypeExpr::Unit => self.intern(Type::Unit),
+            TypeExpr::Generic(name, args) if name == "List" && args.len() == 1 => {
+                let element = self.intern_type_expr(&args[0]);
+                self.intern(Type::List(element))
+            }
             TypeExpr::Name(name) | TypeExpr::Generic(name, _) => {
                 if let Some(id) = self.lookup_named(name) {
                     id
@@ -845,7 +1950,10 @@ impl TypeTable {
                 }
             }
             TypeExpr::Function { return_type, .. } => self.intern_type_expr(return_type),
-            TypeExpr::List(inner) => self.intern_type_expr(inner),
+            TypeExpr::List(inner) => {
+                let element = self.intern_type_expr(inner);
+                self.intern(Type::List(element))
+            }
             TypeExpr::Reference { .. } | TypeExpr::Sum(_) | TypeExpr::SelfType => {
                 self.intern(Type::Unknown)
             }
@@ -853,11 +1961,25 @@ impl TypeTable {
     }
 
     pub fn intern_record(&mut self, name: Option<&str>, fields: &[(String, TypeExpr)]) -> TypeId {
+        let fields: Vec<(String, TypeId)> = fields
+            .iter()
+            .map(|(field_name, field_ty)| (field_name.clone(), self.intern_type_expr(field_ty)))
+            .collect();
+        self.intern_record_fields(name, &fields)
+    }
+
+    /// Interns a record whose field types are already interned, laying the
+    /// fields out in declaration order.
+    pub fn intern_record_fields(
+        &mut self,
+        name: Option<&str>,
+        fields: &[(String, TypeId)],
+    ) -> TypeId {
         let mut layout = Vec::with_capacity(fields.len());
         let mut offset = 0u32;
         let mut align = 1u32;
         for (field_name, field_ty) in fields {
-            let field_ty = self.intern_type_expr(field_ty);
+            let field_ty = *field_ty;
             let field_align = self.align_of(field_ty);
             let field_size = self.size_of(field_ty);
             offset = align_to(offset, field_align);
@@ -880,6 +2002,40 @@ impl TypeTable {
         self.intern(Type::Record(record))
     }
 
+    /// Interns the sum type `name` with `variants` in declaration order.
+    pub fn intern_sum(&mut self, name: &str, variants: Vec<SumVariant>) -> TypeId {
+        self.intern(Type::Sum(SumType {
+            name: name.to_string(),
+            variants,
+        }))
+    }
+
+    /// The sum type with a variant `name`, restricted to the sum called
+    /// `qualifier` when one is given.
+    pub fn variant_owner(&self, name: &str, qualifier: Option<&str>) -> Option<TypeId> {
+        self.entries().find_map(|(id, ty)| match ty {
+            Type::Sum(sum)
+                if qualifier.is_none_or(|qualifier| qualifier == sum.name)
+                    && sum.variant(name).is_some() =>
+            {
+                Some(id)
+            }
+            _ => None,
+        })
+    }
+
+    /// The sum type `ty` denotes, looking a `Named` payload up by name.
+    pub fn sum(&self, ty: TypeId) -> Option<&SumType> {
+        match self.get(ty) {
+            Type::Sum(sum) => Some(sum),
+            Type::Named(name) => match self.get(self.lookup_named(name)?) {
+                Type::Sum(sum) => Some(sum),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     pub fn define_alias(&mut self, name: &str, ty: TypeId) {
         Arc::make_mut(&mut self.inner)
             .named
@@ -906,12 +2062,103 @@ impl TypeTable {
         self.inner.unknown
     }
 
+    /// A table holding the builtin types, `used`, and the types nested in
+    /// them, in their original order, plus the mapping from old to new IDs.
+    fn retain_used(
+        &self,
+        used: impl IntoIterator<Item = TypeId>,
+    ) -> (TypeTable, HashMap<TypeId, TypeId>) {
+        let mut keep = vec![false; self.inner.entries.len()];
+        let mut worklist: Vec<TypeId> = used.into_iter().collect();
+        while let Some(id) = worklist.pop() {
+            if std::mem::replace(&mut keep[id.index()], true) {
+                continue;
+            }
+            match self.get(id) {
+                Type::List(element) => worklist.push(*element),
+                Type::Record(record) => worklist.extend(record.fields.iter().map(|field| field.ty)),
+                Type::Sum(sum) => worklist.extend(
+                    sum.variants
+                        .iter()
+                        .flat_map(|variant| variant.fields.iter().copied()),
+                ),
+                _ => {}
+            }
+        }
+
+        let mut table = TypeTable::new();
+        let mut remap = HashMap::new();
+        for (id, ty) in self.entries() {
+            if let Some(new_id) = table.inner.index.get(ty) {
+                // Builtins are already in the fresh table.
+                remap.insert(id, *new_id);
+            } else if keep[id.index()] {
+                self.copy_into(id, &mut table, &mut remap);
+            }
+        }
+        for (name, id) in &self.inner.named {
+            if let Some(new_id) = remap.get(id)
+                && table.lookup_named(name).is_none()
+            {
+                table.define_alias(name, *new_id);
+            }
+        }
+        (table, remap)
+    }
+
+    /// Interns every type of `other` into this table, plus the aliases this
+    /// table does not define yet, and returns the mapping from `other`'s IDs.
+    fn merge(&mut self, other: &TypeTable) -> HashMap<TypeId, TypeId> {
+        let mut remap = HashMap::new();
+        for (id, _) in other.entries() {
+            other.copy_into(id, self, &mut remap);
+        }
+        for (name, id) in &other.inner.named {
+            if self.lookup_named(name).is_none() {
+                self.define_alias(name, remap[id]);
+            }
+        }
+        remap
+    }
+
+    fn copy_into(
+        &self,
+        id: TypeId,
+        table: &mut TypeTable,
+        remap: &mut HashMap<TypeId, TypeId>,
+    ) -> TypeId {
+        if let Some(new_id) = remap.get(&id) {
+            return *new_id;
+        }
+        let ty = match self.get(id) {
+            Type::List(element) => Type::List(self.copy_into(*element, table, remap)),
+            Type::Record(record) => {
+                let mut record = record.clone();
+                for field in &mut record.fields {
+                    field.ty = self.copy_into(field.ty, table, remap);
+                }
+                Type::Record(record)
+            }
+            Type::Sum(sum) => {
+                let mut sum = sum.clone();
+                for field in sum.variants.iter_mut().flat_map(|v| &mut v.fields) {
+                    *field = self.copy_into(*field, table, remap);
+                }
+                Type::Sum(sum)
+            }
+            other => other.clone(),
+        };
+        let new_id = table.intern(ty);
+        remap.insert(id, new_id);
+        new_id
+    }
+
     pub fn size_of(&self, ty: TypeId) -> u32 {
         match self.get(ty) {
             Type::Unit => 0,
             Type::Bool => 1,
             Type::Int | Type::Float => 8,
-            Type::String | Type::Named(_) | Type::Unknown => 8,
+            Type::String | Type::Named(_) | Type::List(_) | Type::Sum(_) | Type::Unknown => 8,
             Type::Record(record) => record.size,
         }
     }
@@ -920,7 +2167,13 @@ impl TypeTable {
         match self.get(ty) {
             Type::Unit => 1,
             Type::Bool => 1,
-            Type::Int | Type::Float | Type::String | Type::Named(_) | Type::Unknown => 8,
+            Type::Int
+            | Type::Float
+            | Type::String
+            | Type::Named(_)
+            | Type::List(_)
+            | Type::Sum(_)
+            | Type::Unknown => 8,
             Type::Record(record) => record.align,
         }
     }
@@ -941,6 +2194,9 @@ impl TypeTableInner {
                     self.named.insert(name.clone(), id);
                 }
             }
+            Type::Sum(sum) => {
+                self.named.insert(sum.name.clone(), id);
+            }
             _ => {}
         }
         self.index.insert(ty.clone(), id);
@@ -1001,6 +2257,7 @@ impl Type {
             "Float" => Type::Float,
             "Bool" => Type::Bool,
             "String" => Type::String,
+            "F64" => Type::Float,
             other => Type::Named(other.to_string()),
         }
     }
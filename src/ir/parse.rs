@@ -0,0 +1,908 @@
+//! Reader for the textual IR printed by `backend::text::render_module`.
+//!
+//! The format is line oriented: a `module` header, optional `type` lines
+//! declaring named record layouts and an `effects` line listing the effect
+//! table in order, then functions made of `block` headers, instructions and
+//! one terminator per block. Parameters are numbered `%0..%n` in declaration
+//! order, and lines starting with `;` are comments.
+
+use crate::diagnostics::error::{Error, Result};
+use crate::syntax::ast::{BinaryOp, Literal, Path};
+
+use super::{
+    BasicBlock, BlockId, EffectId, EffectTable, FuncRef, Function, InstKind, Instruction, Module,
+    Param, SumVariant, Terminator, Type, TypeId, TypeTable, ValueId,
+};
+
+/// Parses a module in the `--ir` text format.
+pub fn parse_module(text: &str) -> Result<Module> {
+    let mut parser = ModuleParser {
+        name: None,
+        functions: Vec::new(),
+        types: TypeTable::new(),
+        effects: EffectTable::default(),
+        current: None,
+    };
+    let mut offset = 0;
+    for (index, raw) in text.split('\n').enumerate() {
+        let line = Line {
+            number: index + 1,
+            start: offset,
+            text: raw.trim_end_matches('\r'),
+        };
+        offset += raw.len() + 1;
+        let trimmed = line.text.trim();
+        if trimmed.is_empty() || trimmed.starts_with(';') {
+            continue;
+        }
+        parser.parse_line(&line, trimmed)?;
+    }
+    parser.finish()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Line<'a> {
+    number: usize,
+    start: usize,
+    text: &'a str,
+}
+
+impl Line<'_> {
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::parse(
+            Some((self.start, self.start + self.text.len())),
+            format!("IR line {}: {}", self.number, message.into()),
+        )
+    }
+}
+
+struct FunctionInProgress {
+    function: Function,
+    block: Option<(BlockId, Vec<Instruction>)>,
+    line: usize,
+}
+
+struct ModuleParser {
+    name: Option<Vec<String>>,
+    functions: Vec<Function>,
+    types: TypeTable,
+    effects: EffectTable,
+    current: Option<FunctionInProgress>,
+}
+
+impl ModuleParser {
+    fn parse_line(&mut self, line: &Line<'_>, trimmed: &str) -> Result<()> {
+        let tokens = tokenize(trimmed).map_err(|message| line.error(message))?;
+        let mut cursor = Cursor {
+            tokens: &tokens,
+            position: 0,
+            line,
+        };
+        let keyword = match cursor.peek() {
+            Some(Token::Ident(word)) => word.as_str(),
+            _ => "",
+        };
+
+        if self.name.is_none() {
+            if keyword != "module" {
+                return Err(line.error("expected `module` header"));
+            }
+            cursor.next();
+            let mut segments = vec![cursor.expect_ident()?];
+            while cursor.eat(&Token::Dot) {
+                segments.push(cursor.expect_ident()?);
+            }
+            cursor.expect_end()?;
+            self.name = Some(segments);
+            return Ok(());
+        }
+
+        match keyword {
+            "type" if self.current.is_none() => {
+                cursor.next();
+                let name = cursor.expect_ident()?;
+                cursor.expect(&Token::Eq)?;
+                if matches!(cursor.peek(), Some(Token::LBrace)) {
+                    let fields = self.parse_record_fields(&mut cursor)?;
+                    cursor.expect_end()?;
+                    self.types.intern_record_fields(Some(&name), &fields);
+                } else {
+                    let variants = self.parse_sum_variants(&mut cursor)?;
+                    cursor.expect_end()?;
+                    self.types.intern_sum(&name, variants);
+                }
+                Ok(())
+            }
+            "effects" if self.current.is_none() => {
+                cursor.next();
+                loop {
+                    let name = cursor.expect_ident()?;
+                    self.effects.intern(name);
+                    if !cursor.eat(&Token::Comma) {
+                        break;
+                    }
+                }
+                cursor.expect_end()
+            }
+            "fn" | "pub" => {
+                self.finish_function()?;
+                let is_public = keyword == "pub";
+                cursor.next();
+                if is_public && !cursor.eat(&Token::Ident("fn".to_string())) {
+                    return Err(line.error("expected `fn` after `pub`"));
+                }
+                let mut function = self.parse_signature(&mut cursor)?;
+                function.is_public = is_public;
+                self.current = Some(FunctionInProgress {
+                    function,
+                    block: None,
+                    line: line.number,
+                });
+                Ok(())
+            }
+            "block" => {
+                cursor.next();
+                let id = match cursor.next() {
+                    Some(Token::Int(value)) => block_id(*value, line)?,
+                    _ => return Err(line.error("expected block number")),
+                };
+                cursor.expect(&Token::Colon)?;
+                cursor.expect_end()?;
+                let current = self
+                    .current
+                    .as_mut()
+                    .ok_or_else(|| line.error("block outside of a function"))?;
+                if current.block.is_some() {
+                    return Err(line.error("previous block has no terminator"));
+                }
+                current.block = Some((id, Vec::new()));
+                Ok(())
+            }
+            "return" | "jump" | "branch" => {
+                let terminator = parse_terminator(&mut cursor)?;
+                let current = self
+                    .current
+                    .as_mut()
+                    .ok_or_else(|| line.error("terminator outside of a function"))?;
+                let (id, instructions) = current
+                    .block
+                    .take()
+                    .ok_or_else(|| line.error("terminator outside of a block"))?;
+                current.function.blocks.push(BasicBlock {
+                    id,
+                    instructions,
+                    terminator,
+                    terminator_provenance: None,
+                });
+                Ok(())
+            }
+            _ if matches!(cursor.peek(), Some(Token::Value(_))) => {
+                let instruction = self.parse_instruction(&mut cursor)?;
+                let block = self
+                    .current
+                    .as_mut()
+                    .and_then(|current| current.block.as_mut())
+                    .ok_or_else(|| line.error("instruction outside of a block"))?;
+                block.1.push(instruction);
+                Ok(())
+            }
+            _ => Err(line.error(format!("unexpected line `{}`", trimmed))),
+        }
+    }
+
+    fn finish_function(&mut self) -> Result<()> {
+        if let Some(current) = self.current.take() {
+            if let Some((id, _)) = current.block {
+                return Err(Error::parse(
+                    None,
+                    format!(
+                        "IR: block bb{} of fn {} has no terminator",
+                        id.index(),
+                        current.function.name
+                    ),
+                ));
+            }
+            if current.function.blocks.is_empty() {
+                return Err(Error::parse(
+                    None,
+                    format!(
+                        "IR line {}: fn {} has no blocks",
+                        current.line, current.function.name
+                    ),
+                ));
+            }
+            self.functions.push(current.function);
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Module> {
+        self.finish_function()?;
+        let name = self
+            .name
+            .ok_or_else(|| Error::parse(None, "IR: missing `module` header"))?;
+        Ok(Module {
+            name,
+            functions: self.functions,
+            types: self.types,
+            effects: self.effects,
+        })
+    }
+
+    fn parse_signature(&mut self, cursor: &mut Cursor<'_>) -> Result<Function> {
+        let name = cursor.expect_ident()?;
+        cursor.expect(&Token::LParen)?;
+        let mut params = Vec::new();
+        if !cursor.eat(&Token::RParen) {
+            loop {
+                let param_name = cursor.expect_ident()?;
+                cursor.expect(&Token::Colon)?;
+                let ty = self.parse_type(cursor)?;
+                params.push(Param {
+                    name: param_name,
+                    ty,
+                    value: ValueId(params.len() as u32),
+                });
+                if cursor.eat(&Token::RParen) {
+                    break;
+                }
+                cursor.expect(&Token::Comma)?;
+            }
+        }
+        let ret_type = if cursor.eat(&Token::Arrow) {
+            self.parse_type(cursor)?
+        } else {
+            self.types.intern(Type::Unit)
+        };
+        let effect_row = self.parse_effects(cursor)?;
+        cursor.expect_end()?;
+        Ok(Function {
+            name,
+            params,
+            ret_type,
+            blocks: Vec::new(),
+            effect_row,
+            is_public: false,
+        })
+    }
+
+    fn parse_effects(&mut self, cursor: &mut Cursor<'_>) -> Result<Vec<EffectId>> {
+        let mut effects = Vec::new();
+        if cursor.eat(&Token::Bang) {
+            cursor.expect(&Token::LBrace)?;
+            if !cursor.eat(&Token::RBrace) {
+                loop {
+                    let name = cursor.expect_ident()?;
+                    effects.push(self.effects.intern(name));
+                    if cursor.eat(&Token::RBrace) {
+                        break;
+                    }
+                    cursor.expect(&Token::Comma)?;
+                }
+            }
+        }
+        Ok(effects)
+    }
+
+    fn parse_type(&mut self, cursor: &mut Cursor<'_>) -> Result<TypeId> {
+        match cursor.peek() {
+            Some(Token::Underscore) => {
+                cursor.next();
+                Ok(self.types.unknown())
+            }
+            Some(Token::LBracket) => {
+                cursor.next();
+                let element = self.parse_type(cursor)?;
+                cursor.expect(&Token::RBracket)?;
+                Ok(self.types.intern(Type::List(element)))
+            }
+            Some(Token::LBrace) => {
+                let fields = self.parse_record_fields(cursor)?;
+                Ok(self.types.intern_record_fields(None, &fields))
+            }
+            Some(Token::Ident(_)) => {
+                let name = cursor.expect_ident()?;
+                Ok(match self.types.lookup_named(&name) {
+                    Some(id) => id,
+                    None => self.types.intern(Type::Named(name)),
+                })
+            }
+            _ => Err(cursor.error("expected a type")),
+        }
+    }
+
+    /// `A | B(Int, _)`: variants separated by `|`, each with an optional
+    /// list of payload types.
+    fn parse_sum_variants(&mut self, cursor: &mut Cursor<'_>) -> Result<Vec<SumVariant>> {
+        let mut variants = Vec::new();
+        loop {
+            let name = cursor.expect_ident()?;
+            let mut fields = Vec::new();
+            if cursor.eat(&Token::LParen) {
+                loop {
+                    fields.push(self.parse_type(cursor)?);
+                    if cursor.eat(&Token::RParen) {
+                        break;
+                    }
+                    cursor.expect(&Token::Comma)?;
+                }
+            }
+            variants.push(SumVariant { name, fields });
+            if !cursor.eat(&Token::Pipe) {
+                return Ok(variants);
+            }
+        }
+    }
+
+    fn parse_record_fields(&mut self, cursor: &mut Cursor<'_>) -> Result<Vec<(String, TypeId)>> {
+        cursor.expect(&Token::LBrace)?;
+        let mut fields = Vec::new();
+        if cursor.eat(&Token::RBrace) {
+            return Ok(fields);
+        }
+        loop {
+            let name = cursor.expect_ident()?;
+            cursor.expect(&Token::Colon)?;
+            fields.push((name, self.parse_type(cursor)?));
+            if cursor.eat(&Token::RBrace) {
+                return Ok(fields);
+            }
+            cursor.expect(&Token::Comma)?;
+        }
+    }
+
+    fn parse_instruction(&mut self, cursor: &mut Cursor<'_>) -> Result<Instruction> {
+        let id = cursor.expect_value()?;
+        cursor.expect(&Token::Eq)?;
+        let kind = parse_inst_kind(cursor)?;
+        cursor.expect(&Token::Colon)?;
+        let ty = self.parse_type(cursor)?;
+        let effects = self.parse_effects(cursor)?;
+        cursor.expect_end()?;
+        Ok(Instruction {
+            id,
+            ty,
+            effects,
+            kind,
+            provenance: None,
+        })
+    }
+}
+
+fn block_id(value: i64, line: &Line<'_>) -> Result<BlockId> {
+    u32::try_from(value)
+        .map(BlockId)
+        .map_err(|_| line.error(format!("invalid block number {}", value)))
+}
+
+fn parse_inst_kind(cursor: &mut Cursor<'_>) -> Result<InstKind> {
+    let Some(token) = cursor.next().cloned() else {
+        return Err(cursor.error("expected an instruction"));
+    };
+    if let Some(op) = binary_op(&token) {
+        let lhs = cursor.expect_value()?;
+        cursor.expect(&Token::Comma)?;
+        let rhs = cursor.expect_value()?;
+        return Ok(InstKind::Binary { op, lhs, rhs });
+    }
+    let kind = match token {
+        Token::Int(value) => InstKind::Literal(Literal::Int(value)),
+        Token::Float(value) => InstKind::Literal(Literal::Float(value)),
+        Token::Str(value) => InstKind::Literal(Literal::String(value)),
+        Token::LParen => {
+            cursor.expect(&Token::RParen)?;
+            InstKind::Literal(Literal::Unit)
+        }
+        Token::Ident(word) => match word.as_str() {
+            "true" => InstKind::Literal(Literal::Bool(true)),
+            "false" => InstKind::Literal(Literal::Bool(false)),
+            "inf" => InstKind::Literal(Literal::Float(f64::INFINITY)),
+            "NaN" => InstKind::Literal(Literal::Float(f64::NAN)),
+            "call" => {
+                let func = if cursor.eat(&Token::Dot) {
+                    FuncRef::Method(cursor.expect_ident()?)
+                } else {
+                    FuncRef::Function(parse_path(cursor)?)
+                };
+                cursor.expect(&Token::LParen)?;
+                let args = parse_value_list(cursor, &Token::RParen)?;
+                InstKind::Call { func, args }
+            }
+            "record" => {
+                let type_path = if matches!(cursor.peek(), Some(Token::Ident(_))) {
+                    Some(parse_path(cursor)?)
+                } else {
+                    None
+                };
+                cursor.expect(&Token::LBrace)?;
+                let mut fields = Vec::new();
+                if !cursor.eat(&Token::RBrace) {
+                    loop {
+                        let name = cursor.expect_ident()?;
+                        cursor.expect(&Token::Colon)?;
+                        fields.push((name, cursor.expect_value()?));
+                        if cursor.eat(&Token::RBrace) {
+                            break;
+                        }
+                        cursor.expect(&Token::Comma)?;
+                    }
+                }
+                InstKind::Record { type_path, fields }
+            }
+            "path" => InstKind::Path(parse_path(cursor)?),
+            "field" => {
+                let base = cursor.expect_value()?;
+                cursor.expect(&Token::Dot)?;
+                InstKind::Field {
+                    base,
+                    name: cursor.expect_ident()?,
+                }
+            }
+            "convert" => InstKind::Convert {
+                value: cursor.expect_value()?,
+            },
+            "phi" => {
+                cursor.expect(&Token::LBrace)?;
+                let mut incomings = Vec::new();
+                if !cursor.eat(&Token::RBrace) {
+                    loop {
+                        let block = cursor.expect_block()?;
+                        cursor.expect(&Token::Colon)?;
+                        incomings.push((block, cursor.expect_value()?));
+                        if cursor.eat(&Token::RBrace) {
+                            break;
+                        }
+                        cursor.expect(&Token::Comma)?;
+                    }
+                }
+                InstKind::Phi { incomings }
+            }
+            "list" => {
+                cursor.expect(&Token::LBracket)?;
+                InstKind::List {
+                    elements: parse_value_list(cursor, &Token::RBracket)?,
+                }
+            }
+            "len" => InstKind::ListLen {
+                list: cursor.expect_value()?,
+            },
+            "index" => {
+                let list = cursor.expect_value()?;
+                cursor.expect(&Token::Comma)?;
+                InstKind::ListIndex {
+                    list,
+                    index: cursor.expect_value()?,
+                }
+            }
+            "push" => {
+                let list = cursor.expect_value()?;
+                cursor.expect(&Token::Comma)?;
+                InstKind::ListPush {
+                    list,
+                    value: cursor.expect_value()?,
+                }
+            }
+            "retain" => InstKind::Retain {
+                value: cursor.expect_value()?,
+            },
+            "release" => InstKind::Release {
+                value: cursor.expect_value()?,
+            },
+            "variant" => {
+                let variant = cursor.expect_ident()?;
+                let payload = if cursor.eat(&Token::LParen) {
+                    parse_value_list(cursor, &Token::RParen)?
+                } else {
+                    Vec::new()
+                };
+                InstKind::Variant { variant, payload }
+            }
+            "is" => {
+                let value = cursor.expect_value()?;
+                cursor.expect(&Token::Comma)?;
+                InstKind::IsVariant {
+                    value,
+                    variant: cursor.expect_ident()?,
+                }
+            }
+            "payload" => {
+                let value = cursor.expect_value()?;
+                cursor.expect(&Token::Comma)?;
+                let variant = cursor.expect_ident()?;
+                cursor.expect(&Token::Comma)?;
+                let index = match cursor.next() {
+                    Some(Token::Int(index)) => u32::try_from(*index)
+                        .map_err(|_| cursor.error("payload index out of range"))?,
+                    _ => return Err(cursor.error("expected a payload index")),
+                };
+                InstKind::Payload {
+                    value,
+                    variant,
+                    index,
+                }
+            }
+            "trap" => match cursor.next() {
+                Some(Token::Str(message)) => InstKind::Trap {
+                    message: message.clone(),
+                },
+                _ => return Err(cursor.error("expected a trap message")),
+            },
+            other => return Err(cursor.error(format!("unknown instruction `{}`", other))),
+        },
+        _ => return Err(cursor.error("expected an instruction")),
+    };
+    Ok(kind)
+}
+
+fn parse_terminator(cursor: &mut Cursor<'_>) -> Result<Terminator> {
+    let keyword = cursor.expect_ident()?;
+    let terminator = match keyword.as_str() {
+        "return" => {
+            if cursor.at_end() {
+                Terminator::Return(None)
+            } else {
+                Terminator::Return(Some(cursor.expect_value()?))
+            }
+        }
+        "jump" => Terminator::Jump(cursor.expect_block()?),
+        "branch" => {
+            let condition = cursor.expect_value()?;
+            cursor.expect(&Token::Arrow)?;
+            let then_block = cursor.expect_block()?;
+            cursor.expect(&Token::Comma)?;
+            Terminator::Branch {
+                condition,
+                then_block,
+                else_block: cursor.expect_block()?,
+            }
+        }
+        other => return Err(cursor.error(format!("unknown terminator `{}`", other))),
+    };
+    cursor.expect_end()?;
+    Ok(terminator)
+}
+
+fn parse_path(cursor: &mut Cursor<'_>) -> Result<Path> {
+    let mut segments = vec![cursor.expect_ident()?];
+    while cursor.eat(&Token::PathSep) {
+        segments.push(cursor.expect_ident()?);
+    }
+    Ok(Path { segments })
+}
+
+/// Parses comma-separated values up to and including `close`.
+fn parse_value_list(cursor: &mut Cursor<'_>, close: &Token) -> Result<Vec<ValueId>> {
+    let mut values = Vec::new();
+    if cursor.eat(close) {
+        return Ok(values);
+    }
+    loop {
+        values.push(cursor.expect_value()?);
+        if cursor.eat(close) {
+            return Ok(values);
+        }
+        cursor.expect(&Token::Comma)?;
+    }
+}
+
+fn binary_op(token: &Token) -> Option<BinaryOp> {
+    let Token::Op(symbol) = token else {
+        return None;
+    };
+    Some(match *symbol {
+        "+" => BinaryOp::Add,
+        "-" => BinaryOp::Sub,
+        "*" => BinaryOp::Mul,
+        "/" => BinaryOp::Div,
+        "%" => BinaryOp::Mod,
+        "==" => BinaryOp::Eq,
+        "!=" => BinaryOp::Ne,
+        "<" => BinaryOp::Lt,
+        "<=" => BinaryOp::Le,
+        ">" => BinaryOp::Gt,
+        ">=" => BinaryOp::Ge,
+        "&&" => BinaryOp::And,
+        "||" => BinaryOp::Or,
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Value(u32),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Op(&'static str),
+    Eq,
+    Colon,
+    PathSep,
+    Comma,
+    Dot,
+    Bang,
+    Arrow,
+    Pipe,
+    Underscore,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+}
+
+struct Cursor<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    line: &'a Line<'a>,
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        self.line.error(message)
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<()> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(format!(
+                "expected {}, found {}",
+                describe(Some(expected)),
+                describe(self.peek())
+            )))
+        }
+    }
+
+    fn expect_end(&self) -> Result<()> {
+        match self.peek() {
+            None => Ok(()),
+            found => Err(self.error(format!("unexpected {} at end of line", describe(found)))),
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                self.position += 1;
+                Ok(name)
+            }
+            found => Err(self.error(format!("expected a name, found {}", describe(found)))),
+        }
+    }
+
+    fn expect_value(&mut self) -> Result<ValueId> {
+        match self.peek() {
+            Some(Token::Value(id)) => {
+                let id = ValueId(*id);
+                self.position += 1;
+                Ok(id)
+            }
+            found => Err(self.error(format!("expected a value, found {}", describe(found)))),
+        }
+    }
+
+    /// Block references are written `bbN`.
+    fn expect_block(&mut self) -> Result<BlockId> {
+        let label = self.expect_ident()?;
+        label
+            .strip_prefix("bb")
+            .and_then(|digits| digits.parse().ok())
+            .map(BlockId)
+            .ok_or_else(|| self.error(format!("expected a block label, found `{}`", label)))
+    }
+}
+
+fn describe(token: Option<&Token>) -> String {
+    let symbol = match token {
+        None => return "end of line".to_string(),
+        Some(Token::Ident(name)) => return format!("`{}`", name),
+        Some(Token::Value(id)) => return format!("`%{}`", id),
+        Some(Token::Int(value)) => return format!("`{}`", value),
+        Some(Token::Float(value)) => return format!("`{:?}`", value),
+        Some(Token::Str(value)) => return format!("{:?}", value),
+        Some(Token::Op(symbol)) => symbol,
+        Some(Token::Eq) => "=",
+        Some(Token::Colon) => ":",
+        Some(Token::PathSep) => "::",
+        Some(Token::Comma) => ",",
+        Some(Token::Dot) => ".",
+        Some(Token::Bang) => "!",
+        Some(Token::Arrow) => "->",
+        Some(Token::Pipe) => "|",
+        Some(Token::Underscore) => "_",
+        Some(Token::LParen) => "(",
+        Some(Token::RParen) => ")",
+        Some(Token::LBrace) => "{",
+        Some(Token::RBrace) => "}",
+        Some(Token::LBracket) => "[",
+        Some(Token::RBracket) => "]",
+    };
+    format!("`{}`", symbol)
+}
+
+fn tokenize(text: &str) -> std::result::Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        let next = chars.get(index + 1).copied();
+        if c.is_whitespace() {
+            index += 1;
+            continue;
+        }
+        if c == '%' && next.is_some_and(|d| d.is_ascii_digit()) {
+            let start = index + 1;
+            index = start;
+            while index < chars.len() && chars[index].is_ascii_digit() {
+                index += 1;
+            }
+            let digits: String = chars[start..index].iter().collect();
+            let id = digits
+                .parse()
+                .map_err(|_| format!("invalid value `%{}`", digits))?;
+            tokens.push(Token::Value(id));
+            continue;
+        }
+        if c.is_ascii_digit() || (c == '-' && next.is_some_and(|d| d.is_ascii_digit())) {
+            let start = index;
+            index += 1;
+            while index < chars.len() {
+                let d = chars[index];
+                let exponent_sign = (d == '-' || d == '+') && matches!(chars[index - 1], 'e' | 'E');
+                if d.is_ascii_alphanumeric() || d == '.' || exponent_sign {
+                    index += 1;
+                } else {
+                    break;
+                }
+            }
+            let number: String = chars[start..index].iter().collect();
+            if let Ok(value) = number.parse::<i64>() {
+                tokens.push(Token::Int(value));
+            } else {
+                let value = number
+                    .parse()
+                    .map_err(|_| format!("invalid number `{}`", number))?;
+                tokens.push(Token::Float(value));
+            }
+            continue;
+        }
+        if c == '-' && next == Some('i') {
+            // `-inf`
+            let rest: String = chars[index..].iter().take(4).collect();
+            if rest == "-inf" {
+                tokens.push(Token::Float(f64::NEG_INFINITY));
+                index += 4;
+                continue;
+            }
+        }
+        if c.is_alphabetic() || (c == '_' && next.is_some_and(|d| d.is_alphanumeric() || d == '_'))
+        {
+            let start = index;
+            while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_') {
+                index += 1;
+            }
+            tokens.push(Token::Ident(chars[start..index].iter().collect()));
+            continue;
+        }
+        if c == '"' {
+            let (value, end) = read_string(&chars, index)?;
+            tokens.push(Token::Str(value));
+            index = end;
+            continue;
+        }
+        let two: String = chars[index..].iter().take(2).collect();
+        let (token, width) = match two.as_str() {
+            "::" => (Token::PathSep, 2),
+            "->" => (Token::Arrow, 2),
+            "==" => (Token::Op("=="), 2),
+            "!=" => (Token::Op("!="), 2),
+            "<=" => (Token::Op("<="), 2),
+            ">=" => (Token::Op(">="), 2),
+            "&&" => (Token::Op("&&"), 2),
+            "||" => (Token::Op("||"), 2),
+            _ => {
+                let token = match c {
+                    '+' => Token::Op("+"),
+                    '-' => Token::Op("-"),
+                    '*' => Token::Op("*"),
+                    '/' => Token::Op("/"),
+                    '%' => Token::Op("%"),
+                    '<' => Token::Op("<"),
+                    '>' => Token::Op(">"),
+                    '=' => Token::Eq,
+                    ':' => Token::Colon,
+                    ',' => Token::Comma,
+                    '.' => Token::Dot,
+                    '!' => Token::Bang,
+                    '|' => Token::Pipe,
+                    '_' => Token::Underscore,
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '{' => Token::LBrace,
+                    '}' => Token::RBrace,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    other => return Err(format!("unexpected character `{}`", other)),
+                };
+                (token, 1)
+            }
+        };
+        tokens.push(token);
+        index += width;
+    }
+    Ok(tokens)
+}
+
+/// Reads a string literal using the escapes Rust's `Debug` output produces.
+fn read_string(chars: &[char], start: usize) -> std::result::Result<(String, usize), String> {
+    let mut value = String::new();
+    let mut index = start + 1;
+    while let Some(&c) = chars.get(index) {
+        index += 1;
+        match c {
+            '"' => return Ok((value, index)),
+            '\\' => {
+                let escape = chars
+                    .get(index)
+                    .copied()
+                    .ok_or("unterminated escape in string literal")?;
+                index += 1;
+                match escape {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'r' => value.push('\r'),
+                    '0' => value.push('\0'),
+                    '\\' => value.push('\\'),
+                    '"' => value.push('"'),
+                    '\'' => value.push('\''),
+                    'u' => {
+                        if chars.get(index) != Some(&'{') {
+                            return Err("expected `{` after `\\u`".to_string());
+                        }
+                        let close = chars[index..]
+                            .iter()
+                            .position(|c| *c == '}')
+                            .ok_or("unterminated unicode escape")?;
+                        let hex: String = chars[index + 1..index + close].iter().collect();
+                        let code = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("invalid unicode escape `{}`", hex))?;
+                        value.push(code);
+                        index += close + 1;
+                    }
+                    other => return Err(format!("unknown escape `\\{}`", other)),
+                }
+            }
+            other => value.push(other),
+        }
+    }
+    Err("unterminated string literal".to_string())
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use crate::ir::analysis::AnalysisManager;
+use crate::ir::{Function, InstKind, Module, ValueId};
+use crate::syntax::ast::{BinaryOp, Literal};
+
+use super::{Pass, run_on_functions};
+
+/// Replaces binary operations on literal operands with their result.
+///
+/// Folding mirrors the interpreter: only operand combinations it evaluates
+/// are folded, and operations that fail at runtime (division by zero,
+/// integer overflow) are left in place so they still fail there.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConstantFolding;
+
+impl Pass for ConstantFolding {
+    fn name(&self) -> &'static str {
+        "const-fold"
+    }
+
+    fn run(&self, module: &mut Module, analyses: &mut AnalysisManager) -> bool {
+        run_on_functions(module, analyses, |function, _| fold_function(function))
+    }
+}
+
+fn fold_function(function: &mut Function) -> bool {
+    let mut constants: HashMap<ValueId, Literal> = HashMap::new();
+    for block in &function.blocks {
+        for inst in &block.instructions {
+            if let InstKind::Literal(lit) = &inst.kind {
+                constants.insert(inst.id, lit.clone());
+            }
+        }
+    }
+
+    // Blocks are not stored in dominance order, so repeat until folded
+    // results have reached every use.
+    let mut changed = false;
+    loop {
+        let mut progress = false;
+        for block in &mut function.blocks {
+            for inst in &mut block.instructions {
+                let InstKind::Binary { op, lhs, rhs } = &inst.kind else {
+                    continue;
+                };
+                let (Some(lhs), Some(rhs)) = (constants.get(lhs), constants.get(rhs)) else {
+                    continue;
+                };
+                if let Some(result) = fold_binary(*op, lhs, rhs) {
+                    constants.insert(inst.id, result.clone());
+                    inst.kind = InstKind::Literal(result);
+                    progress = true;
+                }
+            }
+        }
+        if !progress {
+            return changed;
+        }
+        changed = true;
+    }
+}
+
+fn fold_binary(op: BinaryOp, lhs: &Literal, rhs: &Literal) -> Option<Literal> {
+    match (lhs, rhs) {
+        (Literal::Int(a), Literal::Int(b)) => {
+            let (a, b) = (*a, *b);
+            let value = match op {
+                BinaryOp::Add => Literal::Int(a.checked_add(b)?),
+                BinaryOp::Sub => Literal::Int(a.checked_sub(b)?),
+                BinaryOp::Mul => Literal::Int(a.checked_mul(b)?),
+                BinaryOp::Div => Literal::Int(a.checked_div(b)?),
+                BinaryOp::Mod => Literal::Int(a.checked_rem(b)?),
+                BinaryOp::Eq => Literal::Bool(a == b),
+                BinaryOp::Ne => Literal::Bool(a != b),
+                BinaryOp::Lt => Literal::Bool(a < b),
+                BinaryOp::Le => Literal::Bool(a <= b),
+                BinaryOp::Gt => Literal::Bool(a > b),
+                BinaryOp::Ge => Literal::Bool(a >= b),
+                BinaryOp::And | BinaryOp::Or => return None,
+            };
+            Some(value)
+        }
+        (Literal::Float(a), Literal::Float(b)) => {
+            let value = match op {
+                BinaryOp::Add => a + b,
+                BinaryOp::Sub => a - b,
+                BinaryOp::Mul => a * b,
+                BinaryOp::Div => a / b,
+                _ => return None,
+            };
+            Some(Literal::Float(value))
+        }
+        (Literal::Bool(a), Literal::Bool(b)) => {
+            let value = match op {
+                BinaryOp::Eq => a == b,
+                BinaryOp::Ne => a != b,
+                BinaryOp::And => *a && *b,
+                BinaryOp::Or => *a || *b,
+                _ => return None,
+            };
+            Some(Literal::Bool(value))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::analysis::AnalysisManager;
+use crate::ir::{BlockId, Function, InstKind, Module, TypeId, ValueId};
+use crate::syntax::ast::{BinaryOp, Literal};
+
+use super::{Pass, replace_uses, run_on_functions};
+
+/// Reuses the result of an earlier, dominating computation of the same pure
+/// expression.
+///
+/// Candidates are instructions [`PurityReport`] does not mark effectful whose
+/// result depends only on their operands: literals, binary operations, paths,
+/// field reads and record construction. Calls, phis, list allocation and list
+/// reads stay untouched because their results depend on identity or on
+/// mutable list contents.
+///
+/// [`PurityReport`]: crate::ir::analysis::PurityReport
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CommonSubexpressionElimination;
+
+impl Pass for CommonSubexpressionElimination {
+    fn name(&self) -> &'static str {
+        "cse"
+    }
+
+    fn run(&self, module: &mut Module, analyses: &mut AnalysisManager) -> bool {
+        run_on_functions(module, analyses, eliminate_common_subexpressions)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ExprKey {
+    Literal(LiteralKey),
+    Binary(BinaryOp, ValueId, ValueId),
+    Path(Vec<String>),
+    Field(ValueId, String),
+    Record(Option<Vec<String>>, Vec<(String, ValueId)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum LiteralKey {
+    Int(i64),
+    Float(u64),
+    Bool(bool),
+    String(String),
+    Unit,
+}
+
+fn eliminate_common_subexpressions(
+    function: &mut Function,
+    analyses: &mut AnalysisManager,
+) -> bool {
+    if function.blocks.is_empty() {
+        return false;
+    }
+    let purity = analyses.purity(function);
+    let cfg = analyses.cfg(function);
+    let dominators = analyses.dominators(function);
+    let positions: HashMap<BlockId, usize> = function
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(index, block)| (block.id, index))
+        .collect();
+
+    let mut available: HashMap<(ExprKey, TypeId), Vec<(BlockId, ValueId)>> = HashMap::new();
+    let mut replacements: HashMap<ValueId, ValueId> = HashMap::new();
+    for block_id in cfg.reverse_postorder() {
+        let block = &function.blocks[positions[block_id]];
+        for inst in &block.instructions {
+            if purity.is_instruction_effectful(inst.id) {
+                continue;
+            }
+            // Key on canonical operands so chains of duplicates collapse in
+            // a single walk.
+            let mut kind = inst.kind.clone();
+            kind.map_operands(|value| replacements.get(&value).copied().unwrap_or(value));
+            let Some(key) = expr_key(&kind) else {
+                continue;
+            };
+            let entries = available.entry((key, inst.ty)).or_default();
+            // Reverse postorder visits dominators first, and earlier
+            // instructions of the same block were recorded before this one.
+            let existing = entries
+                .iter()
+                .find(|(defined_in, _)| dominators.dominates(*defined_in, *block_id));
+            match existing {
+                Some((_, value)) => {
+                    replacements.insert(inst.id, *value);
+                }
+                None => entries.push((*block_id, inst.id)),
+            }
+        }
+    }
+    if replacements.is_empty() {
+        return false;
+    }
+
+    let removed: HashSet<ValueId> = replacements.keys().copied().collect();
+    for block in &mut function.blocks {
+        block
+            .instructions
+            .retain(|inst| !removed.contains(&inst.id));
+    }
+    replace_uses(function, &replacements);
+    true
+}
+
+fn expr_key(kind: &InstKind) -> Option<ExprKey> {
+    let key = match kind {
+        InstKind::Literal(lit) => ExprKey::Literal(match lit {
+            Literal::Int(value) => LiteralKey::Int(*value),
+            Literal::Float(value) => LiteralKey::Float(value.to_bits()),
+            Literal::Bool(value) => LiteralKey::Bool(*value),
+            Literal::String(value) => LiteralKey::String(value.clone()),
+            Literal::Unit => LiteralKey::Unit,
+        }),
+        InstKind::Binary { op, lhs, rhs } => ExprKey::Binary(*op, *lhs, *rhs),
+        InstKind::Path(path) => ExprKey::Path(path.segments.clone()),
+        InstKind::Field { base, name } => ExprKey::Field(*base, name.clone()),
+        InstKind::Record { type_path, fields } => ExprKey::Record(
+            type_path.as_ref().map(|path| path.segments.clone()),
+            fields.clone(),
+        ),
+        InstKind::Call { .. }
+        | InstKind::Convert { .. }
+        | InstKind::Phi { .. }
+        | InstKind::List { .. }
+        | InstKind::ListLen { .. }
+        | InstKind::ListIndex { .. }
+        | InstKind::ListPush { .. }
+        | InstKind::Retain { .. }
+        | InstKind::Release { .. }
+        | InstKind::Variant { .. }
+        | InstKind::IsVariant { .. }
+        | InstKind::Payload { .. }
+        | InstKind::Trap { .. } => return None,
+    };
+    Some(key)
+}
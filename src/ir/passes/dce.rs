@@ -0,0 +1,98 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::analysis::{AnalysisManager, PurityReport};
+use crate::ir::{Function, InstKind, Instruction, Module, ValueId};
+use crate::syntax::ast::{BinaryOp, Literal};
+
+use super::{Pass, run_on_functions};
+
+/// Removes instructions whose results are never used and whose execution
+/// cannot be observed.
+///
+/// Liveness starts from terminator operands and from every instruction that
+/// must run: effectful ones according to [`PurityReport`], calls (effect rows
+/// do not capture list mutation or non-termination), list pushes, and
+/// operations that may fail at runtime. Everything those transitively use is
+/// kept, so dead phi cycles disappear along with straight-line code.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DeadCodeElimination;
+
+impl Pass for DeadCodeElimination {
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn run(&self, module: &mut Module, analyses: &mut AnalysisManager) -> bool {
+        run_on_functions(module, analyses, eliminate_dead_code)
+    }
+}
+
+fn eliminate_dead_code(function: &mut Function, analyses: &mut AnalysisManager) -> bool {
+    let purity = analyses.purity(function);
+    let constants: HashMap<ValueId, &Literal> = function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter_map(|inst| match &inst.kind {
+            InstKind::Literal(lit) => Some((inst.id, lit)),
+            _ => None,
+        })
+        .collect();
+    let definitions: HashMap<ValueId, &Instruction> = function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .map(|inst| (inst.id, inst))
+        .collect();
+
+    let mut live: HashSet<ValueId> = HashSet::new();
+    let mut worklist: Vec<ValueId> = Vec::new();
+    for block in &function.blocks {
+        for inst in &block.instructions {
+            if must_keep(inst, &purity, &constants) {
+                worklist.push(inst.id);
+            }
+        }
+        worklist.extend(block.terminator.operand());
+    }
+    while let Some(value) = worklist.pop() {
+        if !live.insert(value) {
+            continue;
+        }
+        if let Some(inst) = definitions.get(&value) {
+            worklist.extend(inst.kind.operands());
+        }
+    }
+
+    let mut changed = false;
+    for block in &mut function.blocks {
+        let before = block.instructions.len();
+        block.instructions.retain(|inst| live.contains(&inst.id));
+        changed |= block.instructions.len() != before;
+    }
+    changed
+}
+
+fn must_keep(
+    inst: &Instruction,
+    purity: &PurityReport,
+    constants: &HashMap<ValueId, &Literal>,
+) -> bool {
+    if purity.is_instruction_effectful(inst.id) {
+        return true;
+    }
+    match &inst.kind {
+        InstKind::Call { .. } | InstKind::ListPush { .. } | InstKind::ListIndex { .. } => true,
+        InstKind::Binary {
+            op: BinaryOp::Div | BinaryOp::Mod,
+            rhs,
+            ..
+        } => {
+            !matches!(
+                constants.get(rhs),
+                Some(Literal::Int(divisor)) if *divisor != 0 && *divisor != -1
+            ) && !matches!(constants.get(rhs), Some(Literal::Float(_)))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+
+use crate::ir::analysis::AnalysisManager;
+use crate::ir::{FuncRef, Function, InstKind, Module};
+
+use super::Pass;
+
+/// Removes functions unreachable from the module's entry points, then drops
+/// the types only the removed functions used.
+///
+/// Entry points are `main` and, with `keep_public`, every `pub` function. A
+/// function stays when a kept function calls it directly, names it in a
+/// path, or calls a method of the same name, since the C backend dispatches
+/// unknown methods to module functions by name. Modules without entry points
+/// keep all of their functions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeadFunctionElimination {
+    pub keep_public: bool,
+}
+
+impl DeadFunctionElimination {
+    /// Entry points of a library: `main` if present plus every `pub` function.
+    pub fn library() -> Self {
+        DeadFunctionElimination { keep_public: true }
+    }
+}
+
+impl Pass for DeadFunctionElimination {
+    fn name(&self) -> &'static str {
+        "dead-functions"
+    }
+
+    fn run(&self, module: &mut Module, analyses: &mut AnalysisManager) -> bool {
+        let mut live: HashSet<String> = module
+            .functions
+            .iter()
+            .filter(|function| function.name == "main" || (self.keep_public && function.is_public))
+            .map(|function| function.name.clone())
+            .collect();
+
+        let mut removed = false;
+        if !live.is_empty() {
+            let mut worklist: Vec<String> = live.iter().cloned().collect();
+            while let Some(name) = worklist.pop() {
+                let Some(function) = module.functions.iter().find(|f| f.name == name) else {
+                    continue;
+                };
+                for referenced in referenced_names(function) {
+                    if live.insert(referenced.clone()) {
+                        worklist.push(referenced);
+                    }
+                }
+            }
+            module.functions.retain(|function| {
+                let keep = live.contains(&function.name);
+                if !keep {
+                    analyses.invalidate(&function.name);
+                    removed = true;
+                }
+                keep
+            });
+        }
+
+        let pruned = module.prune_types();
+        removed || pruned
+    }
+}
+
+/// Names `function` may refer to another module function by.
+fn referenced_names(function: &Function) -> impl Iterator<Item = String> {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter_map(|inst| match &inst.kind {
+            InstKind::Call {
+                func: FuncRef::Function(path),
+                ..
+            }
+            | InstKind::Path(path) => Some(path.segments.join("::")),
+            InstKind::Call {
+                func: FuncRef::Method(name),
+                ..
+            } => Some(name.clone()),
+            _ => None,
+        })
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use crate::ir::analysis::{AnalysisManager, EffectSummaries};
+use crate::ir::{
+    BasicBlock, BlockId, FuncRef, Function, InstKind, Instruction, Module, Terminator, ValueId,
+};
+use crate::syntax::ast::Literal;
+
+use super::{Pass, replace_uses};
+
+/// Inlines calls to small, pure module functions.
+///
+/// A callee qualifies when its [`EffectSummaries`] entry is pure and not
+/// recursive, and its size (instructions plus blocks) stays within
+/// `max_callee_size`. The call site's block
+/// is split, the callee body is copied in with fresh value and block IDs,
+/// every return jumps to the continuation (joined by a phi when there are
+/// several), and the callee's effect row is merged into the caller's.
+#[derive(Debug, Clone, Copy)]
+pub struct Inliner {
+    pub max_callee_size: usize,
+    /// Upper bound on call sites inlined into one caller per run.
+    pub max_sites_per_caller: usize,
+}
+
+impl Default for Inliner {
+    fn default() -> Self {
+        Inliner {
+            max_callee_size: 24,
+            max_sites_per_caller: 64,
+        }
+    }
+}
+
+impl Inliner {
+    pub fn with_max_callee_size(max_callee_size: usize) -> Self {
+        Inliner {
+            max_callee_size,
+            ..Inliner::default()
+        }
+    }
+}
+
+impl Pass for Inliner {
+    fn name(&self) -> &'static str {
+        "inline"
+    }
+
+    fn run(&self, module: &mut Module, analyses: &mut AnalysisManager) -> bool {
+        let summaries = EffectSummaries::new(module);
+        let candidates: HashMap<String, Function> = module
+            .functions
+            .iter()
+            .filter(|function| {
+                summaries
+                    .get(&function.name)
+                    .is_some_and(|summary| summary.pure && !summary.recursive)
+            })
+            .filter(|function| self.is_inlinable(function))
+            .map(|function| (function.name.clone(), function.clone()))
+            .collect();
+        if candidates.is_empty() {
+            return false;
+        }
+
+        let mut changed = false;
+        for caller in &mut module.functions {
+            let mut inlined = 0;
+            while inlined < self.max_sites_per_caller {
+                let Some(site) = find_call_site(caller, &candidates) else {
+                    break;
+                };
+                let callee = &candidates[&site.callee];
+                inline_call(caller, site, callee);
+                inlined += 1;
+                changed = true;
+            }
+            if inlined > 0 {
+                analyses.invalidate(&caller.name);
+            }
+        }
+        changed
+    }
+}
+
+impl Inliner {
+    fn is_inlinable(&self, function: &Function) -> bool {
+        let Some(entry) = function.blocks.first() else {
+            return false;
+        };
+        // The copied entry block is only reached from the call site, so a
+        // callee that branches back to its entry cannot be spliced in as is.
+        let entry_has_preds = function
+            .blocks
+            .iter()
+            .any(|block| block.terminator.successors().contains(&entry.id));
+        !entry_has_preds && function_size(function) <= self.max_callee_size
+    }
+}
+
+fn function_size(function: &Function) -> usize {
+    function
+        .blocks
+        .iter()
+        .map(|block| block.instructions.len() + 1)
+        .sum()
+}
+
+fn callee_name(func: &FuncRef) -> Option<String> {
+    match func {
+        FuncRef::Function(path) => Some(path.segments.join("::")),
+        FuncRef::Method(_) => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CallSite {
+    block: usize,
+    position: usize,
+    callee: String,
+}
+
+fn find_call_site(caller: &Function, candidates: &HashMap<String, Function>) -> Option<CallSite> {
+    for (block_index, block) in caller.blocks.iter().enumerate() {
+        for (position, inst) in block.instructions.iter().enumerate() {
+            let InstKind::Call { func, args } = &inst.kind else {
+                continue;
+            };
+            let Some(name) = callee_name(func) else {
+                continue;
+            };
+            if name == caller.name {
+                continue;
+            }
+            if let Some(callee) = candidates.get(&name)
+                && callee.params.len() == args.len()
+            {
+                return Some(CallSite {
+                    block: block_index,
+                    position,
+                    callee: name,
+                });
+            }
+        }
+    }
+    None
+}
+
+fn inline_call(caller: &mut Function, site: CallSite, callee: &Function) {
+    let mut next_value = caller
+        .params
+        .iter()
+        .map(|param| param.value.index())
+        .chain(
+            caller
+                .blocks
+                .iter()
+                .flat_map(|block| &block.instructions)
+                .map(|inst| inst.id.index()),
+        )
+        .max()
+        .map_or(0, |max| max + 1);
+    let mut next_block = caller
+        .blocks
+        .iter()
+        .map(|block| block.id.index())
+        .max()
+        .map_or(0, |max| max + 1);
+
+    // Split the call's block: everything after the call moves to a
+    // continuation block that inherits the original terminator.
+    let block = &mut caller.blocks[site.block];
+    let split_id = block.id;
+    let tail = block.instructions.split_off(site.position + 1);
+    let call = block.instructions.pop().expect("call instruction");
+    let InstKind::Call { args, .. } = &call.kind else {
+        unreachable!("call site points at a call");
+    };
+    let continuation = BlockId(next_block);
+    next_block += 1;
+    let original_terminator = block.terminator.clone();
+    let original_provenance = block.terminator_provenance.clone();
+
+    let mut blocks_map: HashMap<BlockId, BlockId> = HashMap::new();
+    for callee_block in &callee.blocks {
+        blocks_map.insert(callee_block.id, BlockId(next_block));
+        next_block += 1;
+    }
+    let mut values_map: HashMap<ValueId, ValueId> = callee
+        .params
+        .iter()
+        .zip(args)
+        .map(|(param, arg)| (param.value, *arg))
+        .collect();
+    for inst in callee.blocks.iter().flat_map(|block| &block.instructions) {
+        values_map.insert(inst.id, ValueId(next_value));
+        next_value += 1;
+    }
+    block.terminator = Terminator::Jump(blocks_map[&callee.blocks[0].id]);
+    block.terminator_provenance = call.provenance.clone();
+
+    let mut returns: Vec<(BlockId, Option<ValueId>)> = Vec::new();
+    let mut copied = Vec::with_capacity(callee.blocks.len());
+    for callee_block in &callee.blocks {
+        let id = blocks_map[&callee_block.id];
+        let instructions = callee_block
+            .instructions
+            .iter()
+            .map(|inst| {
+                let mut kind = inst.kind.clone();
+                kind.map_operands(|value| values_map.get(&value).copied().unwrap_or(value));
+                if let InstKind::Phi { incomings } = &mut kind {
+                    for (pred, _) in incomings {
+                        *pred = blocks_map[pred];
+                    }
+                }
+                Instruction {
+                    id: values_map[&inst.id],
+                    ty: inst.ty,
+                    effects: inst.effects.clone(),
+                    kind,
+                    provenance: inst.provenance.clone(),
+                }
+            })
+            .collect();
+        let terminator = match &callee_block.terminator {
+            Terminator::Return(value) => {
+                let value = value.map(|value| values_map.get(&value).copied().unwrap_or(value));
+                returns.push((id, value));
+                Terminator::Jump(continuation)
+            }
+            Terminator::Jump(target) => Terminator::Jump(blocks_map[target]),
+            Terminator::Branch {
+                condition,
+                then_block,
+                else_block,
+            } => Terminator::Branch {
+                condition: values_map.get(condition).copied().unwrap_or(*condition),
+                then_block: blocks_map[then_block],
+                else_block: blocks_map[else_block],
+            },
+        };
+        copied.push(BasicBlock {
+            id,
+            instructions,
+            terminator,
+            terminator_provenance: callee_block.terminator_provenance.clone(),
+        });
+    }
+
+    // The call's value becomes the returned value: directly when there is a
+    // single return, through a phi when several returns meet, or a unit
+    // literal for callees returning nothing.
+    let mut continuation_insts = Vec::with_capacity(tail.len() + 1);
+    let mut replacements = HashMap::new();
+    match returns.as_slice() {
+        [(_, Some(value))] => {
+            replacements.insert(call.id, *value);
+        }
+        sites if sites.iter().all(|(_, value)| value.is_some()) && !sites.is_empty() => {
+            continuation_insts.push(Instruction {
+                id: call.id,
+                ty: call.ty,
+                effects: Vec::new(),
+                kind: InstKind::Phi {
+                    incomings: sites
+                        .iter()
+                        .map(|(block, value)| (*block, value.expect("checked above")))
+                        .collect(),
+                },
+                provenance: call.provenance.clone(),
+            });
+        }
+        _ => continuation_insts.push(Instruction {
+            id: call.id,
+            ty: call.ty,
+            effects: Vec::new(),
+            kind: InstKind::Literal(Literal::Unit),
+            provenance: call.provenance.clone(),
+        }),
+    }
+    continuation_insts.extend(tail);
+
+    let successors = original_terminator.successors();
+    let insert_at = site.block + 1;
+    let continuation_block = BasicBlock {
+        id: continuation,
+        instructions: continuation_insts,
+        terminator: original_terminator,
+        terminator_provenance: original_provenance,
+    };
+    caller.blocks.splice(
+        insert_at..insert_at,
+        copied.into_iter().chain([continuation_block]),
+    );
+
+    // Successors of the split block now see the continuation as predecessor.
+    for block in &mut caller.blocks {
+        if !successors.contains(&block.id) {
+            continue;
+        }
+        for inst in &mut block.instructions {
+            if let InstKind::Phi { incomings } = &mut inst.kind {
+                for (pred, _) in incomings {
+                    if *pred == split_id {
+                        *pred = continuation;
+                    }
+                }
+            }
+        }
+    }
+    replace_uses(caller, &replacements);
+
+    for effect in &callee.effect_row {
+        if !caller.effect_row.contains(effect) {
+            caller.effect_row.push(*effect);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Optimisation passes over lowered IR.
+//!
+//! A [`PassManager`] runs an ordered list of [`Pass`]es over a module and
+//! repeats the list until no pass reports a change (bounded by
+//! [`PassManager::max_rounds`]). [`PassManager::optimization_pipeline`] is the
+//! pipeline backends apply when `BackendOptions::optimize` is set. When dumps
+//! are requested the manager snapshots the module around every pass that
+//! changed it, which the CLI renders for `--ir -O`.
+
+use std::collections::HashMap;
+
+use super::analysis::{AnalysisManager, AnalysisStats};
+use super::{Function, Module, ValueId, verify};
+
+mod const_fold;
+mod cse;
+mod dce;
+mod dead_functions;
+mod inline;
+mod refcount;
+mod simplify_cfg;
+mod tail_calls;
+
+pub use const_fold::ConstantFolding;
+pub use cse::CommonSubexpressionElimination;
+pub use dce::DeadCodeElimination;
+pub use dead_functions::DeadFunctionElimination;
+pub use inline::Inliner;
+pub use refcount::{ReferenceCounting, is_managed};
+pub use simplify_cfg::SimplifyCfg;
+pub use tail_calls::TailCallElimination;
+
+/// A transformation over a whole module.
+pub trait Pass {
+    /// Short kebab-case name used in reports and dumps.
+    fn name(&self) -> &'static str;
+
+    /// Rewrites `module` in place and reports whether anything changed.
+    ///
+    /// Analyses come from `analyses`; a pass must invalidate every function
+    /// it rewrites there before querying that function again or returning.
+    fn run(&self, module: &mut Module, analyses: &mut AnalysisManager) -> bool;
+}
+
+/// One execution of a pass within a round of the pipeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassRun {
+    pub pass: &'static str,
+    pub round: usize,
+    pub changed: bool,
+}
+
+/// Snapshots of the module around a pass execution that changed it.
+#[derive(Debug, Clone)]
+pub struct PassDump {
+    pub pass: &'static str,
+    pub round: usize,
+    pub before: Module,
+    pub after: Module,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PassReport {
+    pub runs: Vec<PassRun>,
+    pub dumps: Vec<PassDump>,
+    /// Cache behaviour of the analyses the passes queried.
+    pub analyses: AnalysisStats,
+}
+
+impl PassReport {
+    pub fn changed(&self) -> bool {
+        self.runs.iter().any(|run| run.changed)
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.runs.iter().map(|run| run.round).max().unwrap_or(0)
+    }
+}
+
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+    max_rounds: usize,
+    record_dumps: bool,
+}
+
+impl Default for PassManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        PassManager {
+            passes: Vec::new(),
+            max_rounds: 8,
+            record_dumps: false,
+        }
+    }
+
+    /// The pipeline enabled by `-O`: inline small pure callees, turn self
+    /// tail calls into loops, fold constants, simplify the CFG the folding
+    /// exposed, share repeated pure computations, then drop whatever became
+    /// dead.
+    pub fn optimization_pipeline() -> Self {
+        Self::new()
+            .with_pass(Inliner::default())
+            .with_pass(TailCallElimination)
+            .with_pass(ConstantFolding)
+            .with_pass(SimplifyCfg)
+            .with_pass(CommonSubexpressionElimination)
+            .with_pass(DeadCodeElimination)
+    }
+
+    pub fn with_pass(mut self, pass: impl Pass + 'static) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    pub fn max_rounds(mut self, rounds: usize) -> Self {
+        self.max_rounds = rounds.max(1);
+        self
+    }
+
+    pub fn record_dumps(mut self, enabled: bool) -> Self {
+        self.record_dumps = enabled;
+        self
+    }
+
+    pub fn pass_names(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    pub fn run(&self, module: &mut Module) -> PassReport {
+        self.run_with_analyses(module, &mut AnalysisManager::new())
+    }
+
+    /// Runs the pipeline, sharing `analyses` with the caller so results that
+    /// survive the passes can be reused afterwards.
+    pub fn run_with_analyses(
+        &self,
+        module: &mut Module,
+        analyses: &mut AnalysisManager,
+    ) -> PassReport {
+        let mut report = PassReport::default();
+        let start = analyses.stats();
+        for round in 1..=self.max_rounds {
+            let mut round_changed = false;
+            for pass in &self.passes {
+                let before = self.record_dumps.then(|| module.clone());
+                let changed = pass.run(module, analyses);
+                if changed && cfg!(debug_assertions) {
+                    verify::assert_well_formed(module, pass.name());
+                }
+                if let (true, Some(before)) = (changed, before) {
+                    report.dumps.push(PassDump {
+                        pass: pass.name(),
+                        round,
+                        before,
+                        after: module.clone(),
+                    });
+                }
+                report.runs.push(PassRun {
+                    pass: pass.name(),
+                    round,
+                    changed,
+                });
+                round_changed |= changed;
+            }
+            if !round_changed {
+                break;
+            }
+        }
+        let end = analyses.stats();
+        report.analyses = AnalysisStats {
+            computed: end.computed - start.computed,
+            cached: end.cached - start.cached,
+            invalidations: end.invalidations - start.invalidations,
+        };
+        report
+    }
+}
+
+/// Runs the default optimisation pipeline over `module`.
+pub fn optimize(module: &mut Module) -> PassReport {
+    PassManager::optimization_pipeline().run(module)
+}
+
+/// Applies `pass` to every function, invalidating the analyses of each one
+/// it changes, and reports whether any changed.
+fn run_on_functions(
+    module: &mut Module,
+    analyses: &mut AnalysisManager,
+    mut pass: impl FnMut(&mut Function, &mut AnalysisManager) -> bool,
+) -> bool {
+    let mut changed = false;
+    for function in &mut module.functions {
+        if pass(function, analyses) {
+            analyses.invalidate(&function.name);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Rewrites every use of a key in `replacements` to the value it maps to,
+/// following chains so `a -> b -> c` rewrites `a` to `c`.
+fn replace_uses(function: &mut Function, replacements: &HashMap<ValueId, ValueId>) {
+    if replacements.is_empty() {
+        return;
+    }
+    let resolve = |mut value: ValueId| {
+        // Bounded so a replacement cycle cannot hang the pass.
+        for _ in 0..=replacements.len() {
+            match replacements.get(&value) {
+                Some(next) if *next != value => value = *next,
+                _ => break,
+            }
+        }
+        value
+    };
+    for block in &mut function.blocks {
+        for inst in &mut block.instructions {
+            inst.kind.map_operands(resolve);
+        }
+        block.terminator.map_operands(resolve);
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::analysis::AnalysisManager;
+use crate::ir::{
+    BasicBlock, BlockId, Function, InstKind, Instruction, Module, Terminator, Type, TypeId,
+    TypeTable, ValueId,
+};
+
+use super::{Pass, run_on_functions};
+
+/// Inserts the `retain`/`release` instructions that let a backend free
+/// strings, lists, sum values and the records holding them.
+///
+/// Each managed value a function defines owns one reference: calls and
+/// constructors return a fresh one, and field or element reads retain what
+/// they copy out. Parameters and string literals are borrowed and never
+/// released. Storing a value in a record or list, pushing it, passing it to a
+/// phi or returning it consumes a reference; that is the value's own when the
+/// use is its last, and a retained one otherwise. Owned values are released
+/// right after their last use in a block, or on the CFG edges where they die,
+/// which splits critical edges when needed.
+///
+/// The pass is not idempotent, so backends run it once, after every other
+/// pass, on their own copy of the module.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReferenceCounting;
+
+impl Pass for ReferenceCounting {
+    fn name(&self) -> &'static str {
+        "refcount"
+    }
+
+    fn run(&self, module: &mut Module, analyses: &mut AnalysisManager) -> bool {
+        let types = module.types.clone();
+        let Some(unit) = types.lookup_named("Unit") else {
+            return false;
+        };
+        run_on_functions(module, analyses, |function, analyses| {
+            count_references(function, &types, unit, analyses)
+        })
+    }
+}
+
+/// Whether values of `ty` hold counted references: strings, lists, sum
+/// values, and records with a field that does.
+pub fn is_managed(types: &TypeTable, ty: TypeId) -> bool {
+    if types.sum(ty).is_some() {
+        return true;
+    }
+    match types.get(ty) {
+        Type::String | Type::List(_) => true,
+        Type::Record(record) => record
+            .fields
+            .iter()
+            .any(|field| is_managed(types, field.ty)),
+        _ => false,
+    }
+}
+
+/// The managed and owned values of the function being rewritten, and a
+/// source of IDs for the instructions added to it.
+struct Counter {
+    managed: HashSet<ValueId>,
+    owned: HashSet<ValueId>,
+    unit: TypeId,
+    next_value: u32,
+}
+
+impl Counter {
+    fn instruction(&mut self, kind: InstKind) -> Instruction {
+        let id = ValueId(self.next_value);
+        self.next_value += 1;
+        Instruction {
+            id,
+            ty: self.unit,
+            effects: Vec::new(),
+            kind,
+            provenance: None,
+        }
+    }
+
+    fn retain(&mut self, value: ValueId) -> Instruction {
+        self.instruction(InstKind::Retain { value })
+    }
+
+    fn release(&mut self, value: ValueId) -> Instruction {
+        self.instruction(InstKind::Release { value })
+    }
+}
+
+fn count_references(
+    function: &mut Function,
+    types: &TypeTable,
+    unit: TypeId,
+    analyses: &mut AnalysisManager,
+) -> bool {
+    let managed: HashSet<ValueId> = function
+        .params
+        .iter()
+        .map(|param| (param.value, param.ty))
+        .chain(
+            function
+                .blocks
+                .iter()
+                .flat_map(|block| &block.instructions)
+                .map(|inst| (inst.id, inst.ty)),
+        )
+        .filter(|(_, ty)| is_managed(types, *ty))
+        .map(|(value, _)| value)
+        .collect();
+    if managed.is_empty() {
+        return false;
+    }
+    let owned: HashSet<ValueId> = function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter(|inst| managed.contains(&inst.id) && !matches!(inst.kind, InstKind::Literal(_)))
+        .map(|inst| inst.id)
+        .collect();
+
+    let cfg = analyses.cfg(function);
+    let liveness = analyses.liveness(function);
+    let next_value = function
+        .params
+        .iter()
+        .map(|param| param.value.index())
+        .chain(
+            function
+                .blocks
+                .iter()
+                .flat_map(|block| &block.instructions)
+                .map(|inst| inst.id.index()),
+        )
+        .max()
+        .map_or(0, |max| max + 1);
+    let mut counter = Counter {
+        managed,
+        owned,
+        unit,
+        next_value,
+    };
+
+    // Edge code is computed against the original blocks, then placed.
+    let mut edges: Vec<(BlockId, BlockId, Vec<Instruction>)> = Vec::new();
+    for block in &function.blocks {
+        if !cfg.is_reachable(block.id) {
+            continue;
+        }
+        for succ in block.terminator.successors() {
+            let Some(target) = function.blocks.iter().find(|b| b.id == succ) else {
+                continue;
+            };
+            let code = edge_code(
+                &mut counter,
+                block.id,
+                target,
+                liveness.live_out(block.id),
+                |v| liveness.is_live_in(v, succ),
+            );
+            if !code.is_empty() {
+                edges.push((block.id, succ, code));
+            }
+        }
+    }
+
+    for block in &mut function.blocks {
+        if !cfg.is_reachable(block.id) {
+            continue;
+        }
+        let live_out = liveness.live_out(block.id);
+        count_block(&mut counter, block, |v| live_out.contains(&v));
+    }
+
+    let mut next_block = function
+        .blocks
+        .iter()
+        .map(|block| block.id.index())
+        .max()
+        .map_or(0, |max| max + 1);
+    for (from, to, code) in edges {
+        let single_exit = cfg.successors(from).len() == 1;
+        if single_exit {
+            let block = function
+                .blocks
+                .iter_mut()
+                .find(|block| block.id == from)
+                .expect("edge source");
+            block.instructions.extend(code);
+            continue;
+        }
+        if cfg.predecessors(to).len() == 1 {
+            let block = function
+                .blocks
+                .iter_mut()
+                .find(|block| block.id == to)
+                .expect("edge target");
+            let phis = block
+                .instructions
+                .iter()
+                .take_while(|inst| matches!(inst.kind, InstKind::Phi { .. }))
+                .count();
+            block.instructions.splice(phis..phis, code);
+            continue;
+        }
+        let split = BlockId(next_block);
+        next_block += 1;
+        let source = function
+            .blocks
+            .iter_mut()
+            .find(|block| block.id == from)
+            .expect("edge source");
+        if let Terminator::Branch {
+            then_block,
+            else_block,
+            ..
+        } = &mut source.terminator
+        {
+            for target in [then_block, else_block] {
+                if *target == to {
+                    *target = split;
+                }
+            }
+        }
+        let provenance = source.terminator_provenance.clone();
+        for block in &mut function.blocks {
+            if block.id != to {
+                continue;
+            }
+            for inst in &mut block.instructions {
+                if let InstKind::Phi { incomings } = &mut inst.kind {
+                    for (pred, _) in incomings {
+                        if *pred == from {
+                            *pred = split;
+                        }
+                    }
+                }
+            }
+        }
+        function.blocks.push(BasicBlock {
+            id: split,
+            instructions: code,
+            terminator: Terminator::Jump(to),
+            terminator_provenance: provenance,
+        });
+    }
+    true
+}
+
+/// Retains and transfers for `target`'s phis, then releases of the values
+/// that die on the edge from `from`.
+fn edge_code(
+    counter: &mut Counter,
+    from: BlockId,
+    target: &BasicBlock,
+    live_out: &HashSet<ValueId>,
+    live_in: impl Fn(ValueId) -> bool,
+) -> Vec<Instruction> {
+    let incomings: Vec<ValueId> = target
+        .instructions
+        .iter()
+        .filter_map(|inst| match &inst.kind {
+            InstKind::Phi { incomings } => incomings
+                .iter()
+                .find(|(pred, _)| *pred == from)
+                .map(|(_, value)| *value),
+            _ => None,
+        })
+        .filter(|value| counter.managed.contains(value))
+        .collect();
+
+    let mut code = Vec::new();
+    let mut transferred = HashSet::new();
+    for &value in &incomings {
+        let single = incomings.iter().filter(|v| **v == value).count() == 1;
+        if counter.owned.contains(&value) && !live_in(value) && single {
+            transferred.insert(value);
+        } else {
+            code.push(counter.retain(value));
+        }
+    }
+    let mut dying: Vec<ValueId> = live_out
+        .iter()
+        .filter(|value| counter.owned.contains(value))
+        .filter(|value| !live_in(**value) && !transferred.contains(*value))
+        .copied()
+        .collect();
+    dying.sort_by_key(|value| value.index());
+    for value in dying {
+        code.push(counter.release(value));
+    }
+    code
+}
+
+/// Values whose reference a use of `kind` takes over.
+fn consumed(kind: &InstKind) -> Vec<ValueId> {
+    match kind {
+        InstKind::Record { fields, .. } => fields.iter().map(|(_, value)| *value).collect(),
+        InstKind::List { elements } => elements.clone(),
+        InstKind::ListPush { value, .. } => vec![*value],
+        InstKind::Variant { payload, .. } => payload.clone(),
+        _ => Vec::new(),
+    }
+}
+
+/// Rewrites one block: retains around consuming uses and reads, releases
+/// after last uses. Values still live on exit are left to the edges.
+fn count_block(counter: &mut Counter, block: &mut BasicBlock, live_out: impl Fn(ValueId) -> bool) {
+    let instructions = std::mem::take(&mut block.instructions);
+    let end = instructions.len();
+    let phis = instructions
+        .iter()
+        .take_while(|inst| matches!(inst.kind, InstKind::Phi { .. }))
+        .count();
+
+    // Where each owned value handled here dies: its last use, or its
+    // definition when unused. Phi results die after the last phi at the
+    // earliest.
+    let mut dies_at: HashMap<ValueId, usize> = HashMap::new();
+    for (pos, inst) in instructions.iter().enumerate() {
+        if counter.owned.contains(&inst.id) {
+            dies_at.insert(inst.id, pos.max(phis.saturating_sub(1)));
+        }
+        if matches!(inst.kind, InstKind::Phi { .. }) {
+            continue;
+        }
+        for operand in inst.kind.operands() {
+            if counter.owned.contains(&operand) {
+                dies_at.insert(operand, pos);
+            }
+        }
+    }
+    if let Some(operand) = block.terminator.operand()
+        && counter.owned.contains(&operand)
+    {
+        dies_at.insert(operand, end);
+    }
+    dies_at.retain(|value, _| !live_out(*value));
+
+    let mut transferred: HashSet<ValueId> = HashSet::new();
+    let mut rewritten = Vec::with_capacity(end);
+    for (pos, inst) in instructions.into_iter().enumerate() {
+        if pos >= phis {
+            let operands = consumed(&inst.kind);
+            let read = inst.kind.operands();
+            for &value in &operands {
+                if !counter.managed.contains(&value) {
+                    continue;
+                }
+                let once = read.iter().filter(|v| **v == value).count() == 1;
+                if counter.owned.contains(&value)
+                    && dies_at.get(&value) == Some(&pos)
+                    && once
+                    && transferred.insert(value)
+                {
+                    continue;
+                }
+                rewritten.push(counter.retain(value));
+            }
+        }
+        let copies = counter.owned.contains(&inst.id)
+            && matches!(
+                inst.kind,
+                InstKind::Field { .. } | InstKind::ListIndex { .. } | InstKind::Payload { .. }
+            );
+        let id = inst.id;
+        rewritten.push(inst);
+        if copies {
+            rewritten.push(counter.retain(id));
+        }
+        if pos + 1 >= phis {
+            release_dying(counter, &mut rewritten, &dies_at, &transferred, pos);
+        }
+    }
+
+    if let Terminator::Return(Some(value)) = block.terminator
+        && counter.managed.contains(&value)
+    {
+        if counter.owned.contains(&value) && dies_at.get(&value) == Some(&end) {
+            transferred.insert(value);
+        } else {
+            rewritten.push(counter.retain(value));
+        }
+    }
+    release_dying(counter, &mut rewritten, &dies_at, &transferred, end);
+    block.instructions = rewritten;
+}
+
+/// Releases, in ID order, the values that die at `pos` and were not
+/// handed on.
+fn release_dying(
+    counter: &mut Counter,
+    rewritten: &mut Vec<Instruction>,
+    dies_at: &HashMap<ValueId, usize>,
+    transferred: &HashSet<ValueId>,
+    pos: usize,
+) {
+    let mut dying: Vec<ValueId> = dies_at
+        .iter()
+        .filter(|(value, at)| **at == pos && !transferred.contains(*value))
+        .map(|(value, _)| *value)
+        .collect();
+    dying.sort_by_key(|value| value.index());
+    for value in dying {
+        rewritten.push(counter.release(value));
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::analysis::AnalysisManager;
+use crate::ir::{BlockId, Function, InstKind, Module, Terminator, ValueId};
+use crate::syntax::ast::Literal;
+
+use super::{Pass, replace_uses, run_on_functions};
+
+/// Cleans up the control-flow graph.
+///
+/// Branches on constant conditions (or to the same block twice) become
+/// jumps, unreachable blocks are dropped, empty blocks that only jump
+/// elsewhere are bypassed, a block whose single predecessor jumps straight
+/// to it is merged into that predecessor, and phis left with one distinct
+/// incoming value are replaced by it. Phi incomings are kept in step with
+/// every edge that changes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SimplifyCfg;
+
+impl Pass for SimplifyCfg {
+    fn name(&self) -> &'static str {
+        "simplify-cfg"
+    }
+
+    fn run(&self, module: &mut Module, analyses: &mut AnalysisManager) -> bool {
+        run_on_functions(module, analyses, |function, _| simplify_function(function))
+    }
+}
+
+fn simplify_function(function: &mut Function) -> bool {
+    if function.blocks.is_empty() {
+        return false;
+    }
+    let mut changed = false;
+    loop {
+        let progress = fold_branches(function)
+            | remove_unreachable_blocks(function)
+            | bypass_empty_block(function)
+            | merge_into_predecessor(function)
+            | remove_trivial_phis(function);
+        if !progress {
+            return changed;
+        }
+        changed = true;
+    }
+}
+
+fn predecessors(function: &Function) -> HashMap<BlockId, Vec<BlockId>> {
+    let mut preds: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+    for block in &function.blocks {
+        for succ in block.terminator.successors() {
+            preds.entry(succ).or_default().push(block.id);
+        }
+    }
+    preds
+}
+
+fn block_index(function: &Function, id: BlockId) -> Option<usize> {
+    function.blocks.iter().position(|block| block.id == id)
+}
+
+fn has_phis(function: &Function, id: BlockId) -> bool {
+    block_index(function, id).is_some_and(|index| {
+        function.blocks[index]
+            .instructions
+            .iter()
+            .any(|inst| matches!(inst.kind, InstKind::Phi { .. }))
+    })
+}
+
+/// Applies `f` to the incoming list of every phi in block `id`.
+fn edit_phis(
+    function: &mut Function,
+    id: BlockId,
+    mut f: impl FnMut(&mut Vec<(BlockId, ValueId)>),
+) {
+    let Some(index) = block_index(function, id) else {
+        return;
+    };
+    for inst in &mut function.blocks[index].instructions {
+        if let InstKind::Phi { incomings } = &mut inst.kind {
+            f(incomings);
+        }
+    }
+}
+
+fn fold_branches(function: &mut Function) -> bool {
+    let constants: HashMap<ValueId, bool> = function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter_map(|inst| match inst.kind {
+            InstKind::Literal(Literal::Bool(value)) => Some((inst.id, value)),
+            _ => None,
+        })
+        .collect();
+
+    let mut folded = false;
+    let mut dropped_edges = Vec::new();
+    for block in &mut function.blocks {
+        let Terminator::Branch {
+            condition,
+            then_block,
+            else_block,
+        } = block.terminator
+        else {
+            continue;
+        };
+        let (taken, dropped) = if then_block == else_block {
+            (then_block, None)
+        } else {
+            match constants.get(&condition) {
+                Some(true) => (then_block, Some(else_block)),
+                Some(false) => (else_block, Some(then_block)),
+                None => continue,
+            }
+        };
+        block.terminator = Terminator::Jump(taken);
+        folded = true;
+        if let Some(dropped) = dropped {
+            dropped_edges.push((block.id, dropped));
+        }
+    }
+
+    for (pred, target) in dropped_edges {
+        edit_phis(function, target, |incomings| {
+            incomings.retain(|(block, _)| *block != pred)
+        });
+    }
+    folded
+}
+
+fn remove_unreachable_blocks(function: &mut Function) -> bool {
+    let successors: HashMap<BlockId, Vec<BlockId>> = function
+        .blocks
+        .iter()
+        .map(|block| (block.id, block.terminator.successors()))
+        .collect();
+    let mut reachable = HashSet::new();
+    let mut stack = vec![function.blocks[0].id];
+    while let Some(block) = stack.pop() {
+        if reachable.insert(block) {
+            stack.extend(successors.get(&block).into_iter().flatten().copied());
+        }
+    }
+    if reachable.len() == function.blocks.len() {
+        return false;
+    }
+
+    function
+        .blocks
+        .retain(|block| reachable.contains(&block.id));
+    for block in &mut function.blocks {
+        for inst in &mut block.instructions {
+            if let InstKind::Phi { incomings } = &mut inst.kind {
+                incomings.retain(|(pred, _)| reachable.contains(pred));
+            }
+        }
+    }
+    true
+}
+
+/// Redirects the predecessors of one empty, jump-only block straight to its
+/// target. The bypassed block becomes unreachable and is dropped on the next
+/// sweep.
+fn bypass_empty_block(function: &mut Function) -> bool {
+    let preds = predecessors(function);
+    let candidate = function.blocks.iter().skip(1).find_map(|block| {
+        let Terminator::Jump(target) = block.terminator else {
+            return None;
+        };
+        if !block.instructions.is_empty() || target == block.id {
+            return None;
+        }
+        let sources = preds.get(&block.id)?;
+        // A predecessor already reaching the target would need two phi
+        // incomings for the same edge.
+        let target_preds = preds.get(&target).map(Vec::as_slice).unwrap_or(&[]);
+        if has_phis(function, target) && sources.iter().any(|pred| target_preds.contains(pred)) {
+            return None;
+        }
+        Some((block.id, target, sources.clone()))
+    });
+    let Some((empty, target, sources)) = candidate else {
+        return false;
+    };
+
+    for block in &mut function.blocks {
+        if sources.contains(&block.id) {
+            retarget(&mut block.terminator, empty, target);
+        }
+    }
+    edit_phis(function, target, |incomings| {
+        let mut rewritten = Vec::with_capacity(incomings.len() + sources.len());
+        for (pred, value) in incomings.drain(..) {
+            if pred == empty {
+                rewritten.extend(sources.iter().map(|source| (*source, value)));
+            } else {
+                rewritten.push((pred, value));
+            }
+        }
+        *incomings = rewritten;
+    });
+    true
+}
+
+fn retarget(terminator: &mut Terminator, from: BlockId, to: BlockId) {
+    match terminator {
+        Terminator::Jump(target) if *target == from => *target = to,
+        Terminator::Branch {
+            then_block,
+            else_block,
+            ..
+        } => {
+            if *then_block == from {
+                *then_block = to;
+            }
+            if *else_block == from {
+                *else_block = to;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Appends one block to its sole predecessor when that predecessor ends in an
+/// unconditional jump to it.
+fn merge_into_predecessor(function: &mut Function) -> bool {
+    let preds = predecessors(function);
+    let candidate = function.blocks.iter().skip(1).find_map(|block| {
+        let [pred] = preds.get(&block.id)?.as_slice() else {
+            return None;
+        };
+        let pred_index = block_index(function, *pred)?;
+        let jumps_here = matches!(
+            function.blocks[pred_index].terminator,
+            Terminator::Jump(target) if target == block.id
+        );
+        (jumps_here && *pred != block.id).then_some((block.id, *pred))
+    });
+    let Some((merged, pred)) = candidate else {
+        return false;
+    };
+
+    let index = block_index(function, merged).expect("merged block exists");
+    let block = function.blocks.remove(index);
+    let mut replacements = HashMap::new();
+    let mut instructions = Vec::with_capacity(block.instructions.len());
+    for inst in block.instructions {
+        match &inst.kind {
+            InstKind::Phi { incomings } => {
+                if let Some((_, value)) = incomings.first() {
+                    replacements.insert(inst.id, *value);
+                }
+            }
+            _ => instructions.push(inst),
+        }
+    }
+    let successors = block.terminator.successors();
+    let pred_index = block_index(function, pred).expect("predecessor exists");
+    function.blocks[pred_index]
+        .instructions
+        .extend(instructions);
+    function.blocks[pred_index].terminator = block.terminator;
+    function.blocks[pred_index].terminator_provenance = block.terminator_provenance;
+    for succ in successors {
+        edit_phis(function, succ, |incomings| {
+            for (block, _) in incomings.iter_mut() {
+                if *block == merged {
+                    *block = pred;
+                }
+            }
+        });
+    }
+    replace_uses(function, &replacements);
+    true
+}
+
+fn remove_trivial_phis(function: &mut Function) -> bool {
+    let mut replacements = HashMap::new();
+    for block in &function.blocks {
+        for inst in &block.instructions {
+            let InstKind::Phi { incomings } = &inst.kind else {
+                continue;
+            };
+            let mut distinct = incomings
+                .iter()
+                .map(|(_, value)| *value)
+                .filter(|value| *value != inst.id);
+            let Some(first) = distinct.next() else {
+                continue;
+            };
+            if distinct.all(|value| value == first) {
+                replacements.insert(inst.id, first);
+            }
+        }
+    }
+    if replacements.is_empty() {
+        return false;
+    }
+    for block in &mut function.blocks {
+        block
+            .instructions
+            .retain(|inst| !replacements.contains_key(&inst.id));
+    }
+    replace_uses(function, &replacements);
+    true
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use crate::ir::analysis::AnalysisManager;
+use crate::ir::{
+    BasicBlock, BlockId, Function, InstKind, Instruction, Module, Terminator, ValueId,
+};
+
+use super::{Pass, replace_uses, run_on_functions};
+
+/// Turns self tail calls into loops.
+///
+/// Lowering routes the value of a trailing call through a shared return
+/// block, so calls that jump to a block which only returns a phi of their
+/// result are first rewritten to return it directly. Each `return f(..)` in
+/// `f` then becomes a jump back to the old entry block, whose parameters are
+/// replaced by phis over the initial arguments and those of every call site.
+/// Functions whose entry block already has predecessors are left alone.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TailCallElimination;
+
+impl Pass for TailCallElimination {
+    fn name(&self) -> &'static str {
+        "tail-calls"
+    }
+
+    fn run(&self, module: &mut Module, analyses: &mut AnalysisManager) -> bool {
+        run_on_functions(module, analyses, |function, _| {
+            let returned = return_tail_calls(function);
+            loop_self_calls(function) || returned
+        })
+    }
+}
+
+/// Rewrites `call; jump R` into `return call` when `R` only returns a phi
+/// whose incoming value from this block is the call, dropping `R` once no
+/// block jumps to it any more.
+fn return_tail_calls(function: &mut Function) -> bool {
+    let Some(entry) = function.blocks.first().map(|block| block.id) else {
+        return false;
+    };
+    let mut rewrites: Vec<(usize, BlockId, ValueId)> = Vec::new();
+    for (index, block) in function.blocks.iter().enumerate() {
+        let Terminator::Jump(target) = block.terminator else {
+            continue;
+        };
+        let Some(call) = block.instructions.last() else {
+            continue;
+        };
+        if !matches!(call.kind, InstKind::Call { .. }) || target == entry {
+            continue;
+        }
+        let Some(target_block) = function.blocks.iter().find(|b| b.id == target) else {
+            continue;
+        };
+        if returns_incoming(target_block, block.id, call.id) {
+            rewrites.push((index, target, call.id));
+        }
+    }
+    if rewrites.is_empty() {
+        return false;
+    }
+
+    for (index, target, call) in &rewrites {
+        let from = function.blocks[*index].id;
+        function.blocks[*index].terminator = Terminator::Return(Some(*call));
+        let target_block = function
+            .blocks
+            .iter_mut()
+            .find(|block| block.id == *target)
+            .expect("rewritten jump target");
+        for inst in &mut target_block.instructions {
+            if let InstKind::Phi { incomings } = &mut inst.kind {
+                incomings.retain(|(block, _)| *block != from);
+            }
+        }
+    }
+    let mut targets: Vec<BlockId> = rewrites.iter().map(|(_, target, _)| *target).collect();
+    targets.sort_by_key(|block| block.index());
+    targets.dedup();
+    function.blocks.retain(|block| {
+        !targets.contains(&block.id)
+            || block.instructions.iter().any(|inst| match &inst.kind {
+                InstKind::Phi { incomings } => !incomings.is_empty(),
+                _ => true,
+            })
+    });
+    true
+}
+
+/// Whether `block` holds nothing but phis and returns the value flowing in
+/// from `from` as `value`.
+fn returns_incoming(block: &BasicBlock, from: BlockId, value: ValueId) -> bool {
+    let Terminator::Return(Some(returned)) = block.terminator else {
+        return false;
+    };
+    if returned == value && block.instructions.is_empty() {
+        return true;
+    }
+    let mut returns_value = false;
+    for inst in &block.instructions {
+        let InstKind::Phi { incomings } = &inst.kind else {
+            return false;
+        };
+        if inst.id == returned {
+            returns_value = incomings.contains(&(from, value));
+        }
+    }
+    returns_value
+}
+
+fn loop_self_calls(function: &mut Function) -> bool {
+    let Some(entry) = function.blocks.first().map(|block| block.id) else {
+        return false;
+    };
+    let arity = function.params.len();
+    let sites: Vec<usize> = function
+        .blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, block)| {
+            block.tail_call().is_some_and(|(path, args)| {
+                path.segments.join("::") == function.name && args.len() == arity
+            })
+        })
+        .map(|(index, _)| index)
+        .collect();
+    let entry_has_preds = function
+        .blocks
+        .iter()
+        .any(|block| block.terminator.successors().contains(&entry));
+    if sites.is_empty() || entry_has_preds {
+        return false;
+    }
+
+    let mut next_value = function
+        .params
+        .iter()
+        .map(|param| param.value.index())
+        .chain(
+            function
+                .blocks
+                .iter()
+                .flat_map(|block| &block.instructions)
+                .map(|inst| inst.id.index()),
+        )
+        .max()
+        .map_or(0, |max| max + 1);
+    let header = BlockId(
+        function
+            .blocks
+            .iter()
+            .map(|block| block.id.index())
+            .max()
+            .map_or(0, |max| max + 1),
+    );
+
+    // The old entry becomes the loop header; a fresh empty block takes over
+    // its ID so the entry keeps its place.
+    function.blocks[0].id = header;
+    for block in &mut function.blocks {
+        for inst in &mut block.instructions {
+            if let InstKind::Phi { incomings } = &mut inst.kind {
+                for (pred, _) in incomings {
+                    if *pred == entry {
+                        *pred = header;
+                    }
+                }
+            }
+        }
+    }
+
+    let phis: Vec<ValueId> = function
+        .params
+        .iter()
+        .map(|_| {
+            let id = ValueId(next_value);
+            next_value += 1;
+            id
+        })
+        .collect();
+    let replacements: HashMap<ValueId, ValueId> = function
+        .params
+        .iter()
+        .zip(&phis)
+        .map(|(param, phi)| (param.value, *phi))
+        .collect();
+    replace_uses(function, &replacements);
+
+    let mut incomings: Vec<Vec<(BlockId, ValueId)>> = function
+        .params
+        .iter()
+        .map(|param| vec![(entry, param.value)])
+        .collect();
+    for &index in &sites {
+        let block = &mut function.blocks[index];
+        let call = block.instructions.pop().expect("tail call");
+        let InstKind::Call { args, .. } = call.kind else {
+            unreachable!("tail call site ends in a call");
+        };
+        for (incoming, arg) in incomings.iter_mut().zip(args) {
+            incoming.push((block.id, arg));
+        }
+        block.terminator = Terminator::Jump(header);
+        block.terminator_provenance = call.provenance;
+    }
+
+    let header_phis: Vec<Instruction> = function
+        .params
+        .iter()
+        .zip(phis)
+        .zip(incomings)
+        .map(|((param, id), incomings)| Instruction {
+            id,
+            ty: param.ty,
+            effects: Vec::new(),
+            kind: InstKind::Phi { incomings },
+            provenance: None,
+        })
+        .collect();
+    function.blocks[0].instructions.splice(0..0, header_phis);
+    function.blocks.insert(
+        0,
+        BasicBlock {
+            id: entry,
+            instructions: Vec::new(),
+            terminator: Terminator::Jump(header),
+            terminator_provenance: None,
+        },
+    );
+    true
+}
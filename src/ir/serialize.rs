@@ -0,0 +1,289 @@
+//! Versioned serialization of IR modules.
+//!
+//! Two encodings share one schema, derived with serde from the IR types:
+//!
+//! - JSON ([`to_json`]/[`from_json`]) wraps the module in an envelope
+//!   `{"schema_version": N, "module": {...}}` and backs `--ir-json`.
+//! - The binary format ([`to_bytes`]/[`from_bytes`], [`save`]/[`load`]) is
+//!   the [`BINARY_MAGIC`] bytes, the schema version as a little-endian `u32`,
+//!   then the module in bincode's varint encoding. It is meant for caching
+//!   compiled modules on disk and round-trips byte for byte, including
+//!   non-finite float literals that JSON cannot represent.
+//!
+//! Loading rejects other schema versions and modules whose type, effect or
+//! alias IDs point outside their tables, so a stale or corrupt cache entry
+//! surfaces as a [`LoadError`] instead of a panic in a later stage.
+//! [`SCHEMA_VERSION`] must be bumped whenever a serialized IR type changes
+//! shape.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    EffectId, EffectTable, EffectTableInner, Module, Type, TypeId, TypeTable, TypeTableInner,
+};
+
+/// Version of the serialized module layout.
+pub const SCHEMA_VERSION: u32 = 5;
+
+/// Leading bytes of the binary format.
+pub const BINARY_MAGIC: [u8; 4] = *b"MIRB";
+
+const HEADER_LEN: usize = BINARY_MAGIC.len() + 4;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// The input is not a serialized module at all.
+    Format(String),
+    /// The input was written with a different schema.
+    SchemaVersion {
+        found: u32,
+    },
+    /// The module decoded but refers to table entries that do not exist.
+    Invalid(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "failed to read IR module: {}", err),
+            LoadError::Format(msg) => write!(f, "malformed IR module: {}", msg),
+            LoadError::SchemaVersion { found } => write!(
+                f,
+                "IR schema version {} is not supported (expected {})",
+                found, SCHEMA_VERSION
+            ),
+            LoadError::Invalid(msg) => write!(f, "invalid IR module: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a> {
+    schema_version: u32,
+    module: &'a Module,
+}
+
+/// The version is read separately by [`VersionProbe`] so a mismatch is
+/// reported before the module body is decoded.
+#[derive(Deserialize)]
+struct Envelope {
+    module: Module,
+}
+
+#[derive(Deserialize)]
+struct VersionProbe {
+    schema_version: u32,
+}
+
+/// Serializes `module` as a single-line JSON envelope.
+pub fn to_json(module: &Module) -> String {
+    serde_json::to_string(&EnvelopeRef {
+        schema_version: SCHEMA_VERSION,
+        module,
+    })
+    .expect("IR modules serialize to JSON")
+}
+
+pub fn from_json(text: &str) -> Result<Module, LoadError> {
+    let probe: VersionProbe =
+        serde_json::from_str(text).map_err(|err| LoadError::Format(err.to_string()))?;
+    if probe.schema_version != SCHEMA_VERSION {
+        return Err(LoadError::SchemaVersion {
+            found: probe.schema_version,
+        });
+    }
+    let envelope: Envelope =
+        serde_json::from_str(text).map_err(|err| LoadError::Format(err.to_string()))?;
+    validate(&envelope.module)?;
+    Ok(envelope.module)
+}
+
+fn binary_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// Encodes `module` in the binary format.
+pub fn to_bytes(module: &Module) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.extend_from_slice(&BINARY_MAGIC);
+    bytes.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+    binary_options()
+        .serialize_into(&mut bytes, module)
+        .expect("IR modules serialize to bytes");
+    bytes
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<Module, LoadError> {
+    if bytes.len() < HEADER_LEN || bytes[..BINARY_MAGIC.len()] != BINARY_MAGIC {
+        return Err(LoadError::Format("missing binary IR header".to_string()));
+    }
+    let mut version = [0u8; 4];
+    version.copy_from_slice(&bytes[BINARY_MAGIC.len()..HEADER_LEN]);
+    let found = u32::from_le_bytes(version);
+    if found != SCHEMA_VERSION {
+        return Err(LoadError::SchemaVersion { found });
+    }
+    let module: Module = binary_options()
+        .deserialize(&bytes[HEADER_LEN..])
+        .map_err(|err| LoadError::Format(err.to_string()))?;
+    validate(&module)?;
+    Ok(module)
+}
+
+/// Writes `module` to `path` in the binary format.
+pub fn save(module: &Module, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, to_bytes(module))
+}
+
+/// Reads a module written by [`save`].
+pub fn load(path: impl AsRef<Path>) -> Result<Module, LoadError> {
+    from_bytes(&fs::read(path)?)
+}
+
+/// Checks that every type and effect ID used by the functions exists.
+fn validate(module: &Module) -> Result<(), LoadError> {
+    let type_count = module.types.entries().count();
+    let effect_count = module.effects.entries().count();
+    for function in &module.functions {
+        let check_type = |ty: TypeId| {
+            if ty.index() < type_count {
+                Ok(())
+            } else {
+                Err(LoadError::Invalid(format!(
+                    "function `{}` uses unknown type #{}",
+                    function.name,
+                    ty.index()
+                )))
+            }
+        };
+        let check_effects = |effects: &[EffectId]| match effects
+            .iter()
+            .find(|effect| effect.index() >= effect_count)
+        {
+            Some(effect) => Err(LoadError::Invalid(format!(
+                "function `{}` uses unknown effect #{}",
+                function.name,
+                effect.index()
+            ))),
+            None => Ok(()),
+        };
+        check_type(function.ret_type)?;
+        check_effects(&function.effect_row)?;
+        for param in &function.params {
+            check_type(param.ty)?;
+        }
+        for inst in function.blocks.iter().flat_map(|block| &block.instructions) {
+            check_type(inst.ty)?;
+            check_effects(&inst.effects)?;
+        }
+    }
+    Ok(())
+}
+
+/// Serialized form of a [`TypeTable`]: the entries in ID order plus the
+/// alias names, sorted so equal tables encode identically. The hash indexes
+/// are rebuilt on load.
+#[derive(Serialize, Deserialize)]
+pub(super) struct TypeTableData {
+    entries: Vec<Type>,
+    aliases: Vec<(String, TypeId)>,
+    unknown: TypeId,
+}
+
+impl From<TypeTable> for TypeTableData {
+    fn from(table: TypeTable) -> Self {
+        let mut aliases: Vec<(String, TypeId)> = table
+            .inner
+            .named
+            .iter()
+            .map(|(name, id)| (name.clone(), *id))
+            .collect();
+        aliases.sort_by(|a, b| a.0.cmp(&b.0));
+        TypeTableData {
+            entries: table.inner.entries.clone(),
+            aliases,
+            unknown: table.inner.unknown,
+        }
+    }
+}
+
+impl TryFrom<TypeTableData> for TypeTable {
+    type Error = String;
+
+    fn try_from(data: TypeTableData) -> Result<Self, String> {
+        let count = data.entries.len();
+        let in_range = |id: TypeId| id.index() < count;
+        let mut index = HashMap::with_capacity(count);
+        for (position, ty) in data.entries.iter().enumerate() {
+            let nested_ok = match ty {
+                Type::List(element) => in_range(*element),
+                Type::Record(record) => record.fields.iter().all(|field| in_range(field.ty)),
+                Type::Sum(sum) => sum
+                    .variants
+                    .iter()
+                    .all(|variant| variant.fields.iter().all(|field| in_range(*field))),
+                _ => true,
+            };
+            if !nested_ok {
+                return Err(format!("type #{} refers to a missing type", position));
+            }
+            if index.insert(ty.clone(), TypeId(position as u32)).is_some() {
+                return Err(format!("type #{} is interned twice", position));
+            }
+        }
+        if !in_range(data.unknown) {
+            return Err("the unknown type is missing".to_string());
+        }
+        if let Some((name, _)) = data.aliases.iter().find(|(_, id)| !in_range(*id)) {
+            return Err(format!("type alias `{}` refers to a missing type", name));
+        }
+        Ok(TypeTable {
+            inner: Arc::new(TypeTableInner {
+                entries: data.entries,
+                index,
+                named: data.aliases.into_iter().collect(),
+                unknown: data.unknown,
+            }),
+        })
+    }
+}
+
+impl From<EffectTable> for Vec<String> {
+    fn from(table: EffectTable) -> Self {
+        table.inner.entries.clone()
+    }
+}
+
+impl TryFrom<Vec<String>> for EffectTable {
+    type Error = String;
+
+    fn try_from(entries: Vec<String>) -> Result<Self, String> {
+        let mut inner = EffectTableInner::default();
+        for name in entries {
+            if inner.index.contains_key(&name) {
+                return Err(format!("effect `{}` is listed twice", name));
+            }
+            inner.insert(name);
+        }
+        Ok(EffectTable {
+            inner: Arc::new(inner),
+        })
+    }
+}
@@ -0,0 +1,232 @@
+//! Promotion of mutable locals into SSA form.
+//!
+//! While lowering a function, `let mut` bindings are tracked as slots: every
+//! read allocates a placeholder `ValueId` and every write records the value
+//! being stored. Once the CFG is complete, [`promote_slots`] places phi nodes
+//! at the iterated dominance frontier of each slot's definitions (pruned by
+//! liveness), renames reads along the dominator tree, and rewrites every
+//! placeholder to the value that reaches it.
+
+use std::collections::{HashMap, HashSet};
+
+use super::analysis::{ControlFlowGraph, DominanceFrontiers, DominatorTree};
+use super::{BasicBlock, BlockId, InstKind, Instruction, TypeId, ValueId};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(super) struct SlotId(u32);
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum SlotAccess {
+    Read { slot: SlotId, placeholder: ValueId },
+    Write { slot: SlotId, value: ValueId },
+}
+
+/// Mutable locals of one function together with the ordered reads and writes
+/// recorded in each block.
+#[derive(Debug, Default)]
+pub(super) struct SlotTable {
+    types: Vec<TypeId>,
+    accesses: HashMap<BlockId, Vec<SlotAccess>>,
+}
+
+impl SlotTable {
+    pub(super) fn declare(&mut self, ty: TypeId) -> SlotId {
+        let id = SlotId(self.types.len() as u32);
+        self.types.push(ty);
+        id
+    }
+
+    pub(super) fn ty(&self, slot: SlotId) -> TypeId {
+        self.types[slot.0 as usize]
+    }
+
+    pub(super) fn record(&mut self, block: BlockId, access: SlotAccess) {
+        self.accesses.entry(block).or_default().push(access);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    fn accesses(&self, block: BlockId) -> &[SlotAccess] {
+        self.accesses.get(&block).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+/// Rewrites `blocks` so that every slot read refers to the SSA value reaching
+/// it, inserting phi instructions where control flow merges differing
+/// definitions. `next_value` is advanced for each phi created.
+pub(super) fn promote_slots(blocks: &mut [BasicBlock], slots: &SlotTable, next_value: &mut u32) {
+    if slots.is_empty() || blocks.is_empty() {
+        return;
+    }
+
+    let cfg = ControlFlowGraph::from_blocks(blocks);
+    let dominators = DominatorTree::new(&cfg);
+    let frontiers = DominanceFrontiers::new(&cfg, &dominators);
+    let live_in = slot_liveness(&cfg, slots);
+
+    // Phi placement at the iterated dominance frontier of each slot's writes.
+    let mut phis: HashMap<BlockId, Vec<(SlotId, ValueId)>> = HashMap::new();
+    for index in 0..slots.types.len() {
+        let slot = SlotId(index as u32);
+        let mut worklist: Vec<BlockId> =
+            cfg.reverse_postorder()
+                .iter()
+                .copied()
+                .filter(|block| {
+                    slots.accesses(*block).iter().any(
+                        |access| matches!(access, SlotAccess::Write { slot: s, .. } if *s == slot),
+                    )
+                })
+                .collect();
+        let mut placed = HashSet::new();
+        while let Some(block) = worklist.pop() {
+            for target in frontiers.frontier(block) {
+                let live = live_in.get(target).is_some_and(|live| live.contains(&slot));
+                if live && placed.insert(*target) {
+                    let value = ValueId(*next_value);
+                    *next_value += 1;
+                    phis.entry(*target).or_default().push((slot, value));
+                    worklist.push(*target);
+                }
+            }
+        }
+    }
+
+    // Rename along the dominator tree.
+    let mut aliases: HashMap<ValueId, ValueId> = HashMap::new();
+    let mut incomings: HashMap<ValueId, Vec<(BlockId, ValueId)>> = HashMap::new();
+    let mut stacks: HashMap<SlotId, Vec<ValueId>> = HashMap::new();
+    let mut walk = vec![Visit::Enter(dominators.root())];
+    while let Some(visit) = walk.pop() {
+        match visit {
+            Visit::Enter(block) => {
+                let mut pushed = Vec::new();
+                for (slot, value) in phis.get(&block).into_iter().flatten() {
+                    stacks.entry(*slot).or_default().push(*value);
+                    pushed.push(*slot);
+                }
+                for access in slots.accesses(block) {
+                    match *access {
+                        SlotAccess::Read { slot, placeholder } => {
+                            if let Some(current) = stacks.get(&slot).and_then(|stack| stack.last())
+                            {
+                                aliases.insert(placeholder, *current);
+                            }
+                        }
+                        SlotAccess::Write { slot, value } => {
+                            stacks.entry(slot).or_default().push(value);
+                            pushed.push(slot);
+                        }
+                    }
+                }
+                for succ in cfg.successors(block) {
+                    for (slot, phi) in phis.get(succ).into_iter().flatten() {
+                        if let Some(current) = stacks.get(slot).and_then(|stack| stack.last()) {
+                            incomings.entry(*phi).or_default().push((block, *current));
+                        }
+                    }
+                }
+                walk.push(Visit::Exit(pushed));
+                walk.extend(
+                    dominators
+                        .children(block)
+                        .iter()
+                        .rev()
+                        .map(|kid| Visit::Enter(*kid)),
+                );
+            }
+            Visit::Exit(pushed) => {
+                for slot in pushed {
+                    if let Some(stack) = stacks.get_mut(&slot) {
+                        stack.pop();
+                    }
+                }
+            }
+        }
+    }
+
+    let resolve = |value: ValueId| -> ValueId {
+        let mut current = value;
+        let mut steps = 0;
+        while let Some(next) = aliases.get(&current) {
+            current = *next;
+            steps += 1;
+            if steps > aliases.len() {
+                break;
+            }
+        }
+        current
+    };
+
+    for block in blocks.iter_mut() {
+        if let Some(block_phis) = phis.get(&block.id) {
+            let mut inserted = Vec::with_capacity(block_phis.len());
+            for (slot, value) in block_phis {
+                inserted.push(Instruction {
+                    id: *value,
+                    ty: slots.ty(*slot),
+                    effects: Vec::new(),
+                    kind: InstKind::Phi {
+                        incomings: incomings.remove(value).unwrap_or_default(),
+                    },
+                    provenance: None,
+                });
+            }
+            block.instructions.splice(0..0, inserted);
+        }
+        for inst in &mut block.instructions {
+            inst.kind.map_operands(resolve);
+        }
+        block.terminator.map_operands(resolve);
+    }
+}
+
+enum Visit {
+    Enter(BlockId),
+    Exit(Vec<SlotId>),
+}
+
+/// Slots whose current value may be read on entry to each block.
+fn slot_liveness(cfg: &ControlFlowGraph, slots: &SlotTable) -> HashMap<BlockId, HashSet<SlotId>> {
+    let mut upward_exposed: HashMap<BlockId, HashSet<SlotId>> = HashMap::new();
+    let mut killed: HashMap<BlockId, HashSet<SlotId>> = HashMap::new();
+    for block in cfg.reverse_postorder() {
+        let uses = upward_exposed.entry(*block).or_default();
+        let kills = killed.entry(*block).or_default();
+        for access in slots.accesses(*block) {
+            match *access {
+                SlotAccess::Read { slot, .. } => {
+                    if !kills.contains(&slot) {
+                        uses.insert(slot);
+                    }
+                }
+                SlotAccess::Write { slot, .. } => {
+                    kills.insert(slot);
+                }
+            }
+        }
+    }
+
+    let mut live_in: HashMap<BlockId, HashSet<SlotId>> = HashMap::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in cfg.reverse_postorder().iter().rev() {
+            let mut live: HashSet<SlotId> = HashSet::new();
+            for succ in cfg.successors(*block) {
+                if let Some(succ_live) = live_in.get(succ) {
+                    live.extend(succ_live.iter().copied());
+                }
+            }
+            live.retain(|slot| !killed[block].contains(slot));
+            live.extend(upward_exposed[block].iter().copied());
+            if live_in.get(block) != Some(&live) {
+                live_in.insert(*block, live);
+                changed = true;
+            }
+        }
+    }
+    live_in
+}
@@ -0,0 +1,471 @@
+//! Structural checks for lowered modules.
+//!
+//! [`verify_module`] walks every function and reports violations of the
+//! invariants later stages rely on: block and value identifiers are unique,
+//! terminators target existing blocks, every use is dominated by its
+//! definition, phis list exactly one incoming value per predecessor, binary
+//! operands agree on their type, and direct calls pass as many arguments as
+//! the callee declares.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::analysis::{ControlFlowGraph, DominatorTree};
+use super::{BlockId, FuncRef, Function, InstKind, Module, Type, TypeId, ValueId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    DuplicateBlock,
+    DuplicateValue,
+    UnknownBlock,
+    UndefinedValue,
+    UseNotDominated,
+    MisplacedPhi,
+    PhiIncomings,
+    OperandTypeMismatch,
+    CallArity,
+}
+
+impl ViolationKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            ViolationKind::DuplicateBlock => "duplicate-block",
+            ViolationKind::DuplicateValue => "duplicate-value",
+            ViolationKind::UnknownBlock => "unknown-block",
+            ViolationKind::UndefinedValue => "undefined-value",
+            ViolationKind::UseNotDominated => "use-not-dominated",
+            ViolationKind::MisplacedPhi => "misplaced-phi",
+            ViolationKind::PhiIncomings => "phi-incomings",
+            ViolationKind::OperandTypeMismatch => "operand-type-mismatch",
+            ViolationKind::CallArity => "call-arity",
+        }
+    }
+
+    /// Structural violations always indicate a lowering or pass bug. Type and
+    /// arity disagreements can also stem from ill-typed source programs, which
+    /// the checker only warns about.
+    pub fn is_structural(self) -> bool {
+        !matches!(
+            self,
+            ViolationKind::OperandTypeMismatch | ViolationKind::CallArity
+        )
+    }
+}
+
+/// A single broken invariant, located by function, block and (when the
+/// violation concerns an instruction) the value it defines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub function: String,
+    pub block: Option<BlockId>,
+    pub value: Option<ValueId>,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] fn {}", self.kind.code(), self.function)?;
+        if let Some(block) = self.block {
+            write!(f, ", bb{}", block.index())?;
+        }
+        if let Some(value) = self.value {
+            write!(f, ", %{}", value.index())?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct VerifyReport {
+    pub violations: Vec<Violation>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn structural(&self) -> impl Iterator<Item = &Violation> {
+        self.violations
+            .iter()
+            .filter(|violation| violation.kind.is_structural())
+    }
+}
+
+pub fn verify_module(module: &Module) -> VerifyReport {
+    let arities: HashMap<&str, usize> = module
+        .functions
+        .iter()
+        .map(|function| (function.name.as_str(), function.params.len()))
+        .collect();
+    let mut report = VerifyReport::default();
+    for function in &module.functions {
+        FunctionVerifier::new(module, function, &arities).run(&mut report.violations);
+    }
+    report
+}
+
+/// Panics when `module` breaks a structural invariant. `stage` names the
+/// producer of the module in the panic message.
+pub(crate) fn assert_well_formed(module: &Module, stage: &str) {
+    let report = verify_module(module);
+    let broken: Vec<String> = report.structural().map(|v| v.to_string()).collect();
+    assert!(
+        broken.is_empty(),
+        "{stage} produced malformed IR:\n{}",
+        broken.join("\n")
+    );
+}
+
+/// Where a value is defined: a parameter (available everywhere) or an
+/// instruction at `position` within `block`.
+#[derive(Debug, Clone, Copy)]
+enum Definition {
+    Param,
+    Instruction { block: BlockId, position: usize },
+}
+
+struct FunctionVerifier<'a> {
+    module: &'a Module,
+    function: &'a Function,
+    arities: &'a HashMap<&'a str, usize>,
+    definitions: HashMap<ValueId, Definition>,
+    types: HashMap<ValueId, TypeId>,
+    blocks: HashSet<BlockId>,
+    violations: Vec<Violation>,
+}
+
+impl<'a> FunctionVerifier<'a> {
+    fn new(
+        module: &'a Module,
+        function: &'a Function,
+        arities: &'a HashMap<&'a str, usize>,
+    ) -> Self {
+        FunctionVerifier {
+            module,
+            function,
+            arities,
+            definitions: HashMap::new(),
+            types: HashMap::new(),
+            blocks: HashSet::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    fn run(mut self, out: &mut Vec<Violation>) {
+        self.collect_definitions();
+        self.check_terminator_targets();
+        // Dominance and predecessor checks need a well-formed CFG.
+        if self.violations.is_empty() && !self.function.blocks.is_empty() {
+            let cfg = ControlFlowGraph::new(self.function);
+            let dominators = DominatorTree::new(&cfg);
+            self.check_blocks(&cfg, &dominators);
+        }
+        out.append(&mut self.violations);
+    }
+
+    fn collect_definitions(&mut self) {
+        for param in &self.function.params {
+            if self
+                .definitions
+                .insert(param.value, Definition::Param)
+                .is_some()
+            {
+                self.report(
+                    ViolationKind::DuplicateValue,
+                    None,
+                    Some(param.value),
+                    format!("parameter '{}' reuses an existing value id", param.name),
+                );
+            }
+            self.types.insert(param.value, param.ty);
+        }
+        for block in &self.function.blocks {
+            if !self.blocks.insert(block.id) {
+                self.report(
+                    ViolationKind::DuplicateBlock,
+                    Some(block.id),
+                    None,
+                    "block id is defined more than once".to_string(),
+                );
+            }
+            for (position, inst) in block.instructions.iter().enumerate() {
+                let definition = Definition::Instruction {
+                    block: block.id,
+                    position,
+                };
+                if self.definitions.insert(inst.id, definition).is_some() {
+                    self.report(
+                        ViolationKind::DuplicateValue,
+                        Some(block.id),
+                        Some(inst.id),
+                        "value is defined more than once".to_string(),
+                    );
+                }
+                self.types.insert(inst.id, inst.ty);
+            }
+        }
+    }
+
+    fn check_terminator_targets(&mut self) {
+        for block in &self.function.blocks {
+            for target in block.terminator.successors() {
+                if !self.blocks.contains(&target) {
+                    self.report(
+                        ViolationKind::UnknownBlock,
+                        Some(block.id),
+                        None,
+                        format!("terminator targets missing block bb{}", target.index()),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_blocks(&mut self, cfg: &ControlFlowGraph, dominators: &DominatorTree) {
+        for block in &self.function.blocks {
+            if !cfg.is_reachable(block.id) {
+                continue;
+            }
+            let mut seen_non_phi = false;
+            for (position, inst) in block.instructions.iter().enumerate() {
+                if let InstKind::Phi { incomings } = &inst.kind {
+                    if seen_non_phi {
+                        self.report(
+                            ViolationKind::MisplacedPhi,
+                            Some(block.id),
+                            Some(inst.id),
+                            "phi follows a non-phi instruction".to_string(),
+                        );
+                    }
+                    self.check_phi(cfg, dominators, block.id, inst.id, incomings);
+                    continue;
+                }
+                seen_non_phi = true;
+                for operand in inst.kind.operands() {
+                    self.check_use(dominators, block.id, Some(position), inst.id, operand);
+                }
+                self.check_operand_types(block.id, inst.id, &inst.kind);
+                self.check_call_arity(block.id, inst.id, &inst.kind);
+            }
+            if let Some(operand) = block.terminator.operand() {
+                self.check_terminator_use(dominators, block.id, operand);
+            }
+        }
+    }
+
+    /// `position` is the index of the using instruction within `block`, or
+    /// `None` when the use sits at the very end of the block.
+    fn check_use(
+        &mut self,
+        dominators: &DominatorTree,
+        block: BlockId,
+        position: Option<usize>,
+        user: ValueId,
+        operand: ValueId,
+    ) {
+        match self.definitions.get(&operand).copied() {
+            None => self.report(
+                ViolationKind::UndefinedValue,
+                Some(block),
+                Some(user),
+                format!("uses undefined value %{}", operand.index()),
+            ),
+            Some(Definition::Param) => {}
+            Some(Definition::Instruction {
+                block: def_block,
+                position: def_position,
+            }) => {
+                let dominated = if def_block == block {
+                    position.is_none_or(|position| def_position < position)
+                } else {
+                    dominators.dominates(def_block, block)
+                };
+                if !dominated {
+                    self.report(
+                        ViolationKind::UseNotDominated,
+                        Some(block),
+                        Some(user),
+                        format!(
+                            "use of %{} is not dominated by its definition in bb{}",
+                            operand.index(),
+                            def_block.index()
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_terminator_use(
+        &mut self,
+        dominators: &DominatorTree,
+        block: BlockId,
+        operand: ValueId,
+    ) {
+        match self.definitions.get(&operand).copied() {
+            None => self.report(
+                ViolationKind::UndefinedValue,
+                Some(block),
+                None,
+                format!("terminator uses undefined value %{}", operand.index()),
+            ),
+            Some(Definition::Param) => {}
+            Some(Definition::Instruction {
+                block: def_block, ..
+            }) => {
+                if !dominators.dominates(def_block, block) {
+                    self.report(
+                        ViolationKind::UseNotDominated,
+                        Some(block),
+                        None,
+                        format!(
+                            "terminator use of %{} is not dominated by its definition in bb{}",
+                            operand.index(),
+                            def_block.index()
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    /// A phi needs exactly one incoming value per reachable predecessor, and
+    /// each incoming value must be available at the end of its predecessor.
+    fn check_phi(
+        &mut self,
+        cfg: &ControlFlowGraph,
+        dominators: &DominatorTree,
+        block: BlockId,
+        phi: ValueId,
+        incomings: &[(BlockId, ValueId)],
+    ) {
+        let predecessors: HashSet<BlockId> = cfg
+            .predecessors(block)
+            .iter()
+            .copied()
+            .filter(|pred| cfg.is_reachable(*pred))
+            .collect();
+        let mut covered = HashSet::new();
+        for (pred, value) in incomings {
+            if !predecessors.contains(pred) {
+                self.report(
+                    ViolationKind::PhiIncomings,
+                    Some(block),
+                    Some(phi),
+                    format!(
+                        "incoming from bb{} which is not a predecessor",
+                        pred.index()
+                    ),
+                );
+                continue;
+            }
+            if !covered.insert(*pred) {
+                self.report(
+                    ViolationKind::PhiIncomings,
+                    Some(block),
+                    Some(phi),
+                    format!("more than one incoming from bb{}", pred.index()),
+                );
+                continue;
+            }
+            self.check_use(dominators, *pred, None, phi, *value);
+        }
+        let mut missing: Vec<_> = predecessors.difference(&covered).copied().collect();
+        missing.sort_by_key(|block| block.index());
+        for pred in missing {
+            self.report(
+                ViolationKind::PhiIncomings,
+                Some(block),
+                Some(phi),
+                format!("no incoming value for predecessor bb{}", pred.index()),
+            );
+        }
+    }
+
+    fn check_operand_types(&mut self, block: BlockId, id: ValueId, kind: &InstKind) {
+        let InstKind::Binary { op, lhs, rhs } = kind else {
+            return;
+        };
+        let (Some(lhs_ty), Some(rhs_ty)) = (self.types.get(lhs), self.types.get(rhs)) else {
+            return;
+        };
+        let lhs_ty = self.module.type_of(*lhs_ty);
+        let rhs_ty = self.module.type_of(*rhs_ty);
+        if matches!(lhs_ty, Type::Unknown) || matches!(rhs_ty, Type::Unknown) || lhs_ty == rhs_ty {
+            return;
+        }
+        self.report(
+            ViolationKind::OperandTypeMismatch,
+            Some(block),
+            Some(id),
+            format!(
+                "operands of '{}' have different types {} and {}",
+                op,
+                describe(self.module, lhs_ty),
+                describe(self.module, rhs_ty)
+            ),
+        );
+    }
+
+    fn check_call_arity(&mut self, block: BlockId, id: ValueId, kind: &InstKind) {
+        let InstKind::Call {
+            func: FuncRef::Function(path),
+            args,
+        } = kind
+        else {
+            return;
+        };
+        let name = path.segments.join("::");
+        if let Some(expected) = self.arities.get(name.as_str())
+            && *expected != args.len()
+        {
+            self.report(
+                ViolationKind::CallArity,
+                Some(block),
+                Some(id),
+                format!(
+                    "call to '{}' passes {} arguments but it declares {}",
+                    name,
+                    args.len(),
+                    expected
+                ),
+            );
+        }
+    }
+
+    fn report(
+        &mut self,
+        kind: ViolationKind,
+        block: Option<BlockId>,
+        value: Option<ValueId>,
+        message: String,
+    ) {
+        self.violations.push(Violation {
+            kind,
+            function: self.function.name.clone(),
+            block,
+            value,
+            message,
+        });
+    }
+}
+
+fn describe(module: &Module, ty: &Type) -> String {
+    match ty {
+        Type::Unit => "Unit".to_string(),
+        Type::Int => "Int".to_string(),
+        Type::Float => "Float".to_string(),
+        Type::Bool => "Bool".to_string(),
+        Type::String => "String".to_string(),
+        Type::Named(name) => name.clone(),
+        Type::Record(record) => record
+            .name
+            .clone()
+            .unwrap_or_else(|| "anonymous record".to_string()),
+        Type::Sum(sum) => sum.name.clone(),
+        Type::List(element) => format!("[{}]", describe(module, module.type_of(*element))),
+        Type::Unknown => "_".to_string(),
+    }
+}
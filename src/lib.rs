@@ -1,4 +1,5 @@
 pub mod backend;
+pub mod bytecode;
 pub mod diagnostics;
 pub mod interpreter;
 pub mod ir;
@@ -1,3 +1,4 @@
+use crate::semantics::check::{ExprIds, ExprTypes};
 use crate::syntax::ast::*;
 
 #[derive(Debug, Clone)]
@@ -19,6 +20,8 @@ pub struct HFunction {
     pub return_type: Option<TypeExpr>,
     pub effect_row: Vec<String>,
     pub body: HBlock,
+    pub is_public: bool,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -32,22 +35,44 @@ pub struct HTypeAlias {
 pub struct HParam {
     pub name: String,
     pub ty: TypeExpr,
+    pub mutable: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct HBlock {
     pub stmts: Vec<HStmt>,
+    /// Source span of each statement, in step with `stmts` when known.
+    pub spans: Vec<Span>,
 }
 
 #[derive(Debug, Clone)]
 pub enum HStmt {
-    Let { name: String, value: HExpr },
+    Let {
+        name: String,
+        mutable: bool,
+        value: HExpr,
+    },
     Expr(HExpr),
     Return(Option<HExpr>),
+    Break,
+    Continue,
+}
+
+/// An expression with the type the checker resolved for it, when known.
+#[derive(Debug, Clone)]
+pub struct HExpr {
+    pub kind: HExprKind,
+    pub ty: Option<TypeExpr>,
+}
+
+impl HExpr {
+    pub fn untyped(kind: HExprKind) -> Self {
+        HExpr { kind, ty: None }
+    }
 }
 
 #[derive(Debug, Clone)]
-pub enum HExpr {
+pub enum HExprKind {
     Literal(Literal),
     Var(String),
     Path(Path),
@@ -65,6 +90,46 @@ pub enum HExpr {
         type_path: Option<Path>,
         fields: Vec<(String, HExpr)>,
     },
+    List(Vec<HExpr>),
+    Field {
+        expr: Box<HExpr>,
+        name: String,
+    },
+    Cast {
+        expr: Box<HExpr>,
+        ty: TypeExpr,
+    },
+    /// Assignment to a local binding; other assignment targets stay opaque
+    /// `assign` method calls.
+    Assign {
+        name: String,
+        value: Box<HExpr>,
+    },
+    While {
+        condition: Box<HExpr>,
+        body: Box<HExpr>,
+    },
+    Loop {
+        body: Box<HExpr>,
+    },
+    For {
+        binding: String,
+        iterable: Box<HExpr>,
+        body: Box<HExpr>,
+    },
+    Match {
+        scrutinee: Box<HExpr>,
+        arms: Vec<HMatchArm>,
+    },
+}
+
+/// A `match` arm; patterns are kept as written and resolved during IR
+/// lowering, where the variant types are known.
+#[derive(Debug, Clone)]
+pub struct HMatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<HExpr>,
+    pub body: HExpr,
 }
 
 #[derive(Debug, Clone)]
@@ -73,11 +138,18 @@ pub enum HFuncRef {
     Method(String), // desugared method name, receiver passed as first arg
 }
 
-pub fn lower_module(m: &Module) -> HModule {
+/// Lowers `m`, typing every expression with `types` from a
+/// `check::check_module` run over the same module (or a clone of it).
+pub fn lower_module(m: &Module, types: &ExprTypes) -> HModule {
+    let types = Types {
+        ids: ExprIds::new(m),
+        checked: types,
+    };
+    let types = &types;
     let mut items = Vec::new();
     for it in &m.items {
         match it {
-            Item::Function(f) => items.push(HItem::Function(lower_function(f))),
+            Item::Function(f) => items.push(HItem::Function(lower_function(f, types))),
             Item::TypeAlias(alias) => items.push(HItem::TypeAlias(lower_type_alias(alias))),
             _ => {}
         }
@@ -88,13 +160,29 @@ pub fn lower_module(m: &Module) -> HModule {
     }
 }
 
-fn lower_function(f: &Function) -> HFunction {
+/// The checker's expression types for the module being lowered.
+struct Types<'m> {
+    ids: ExprIds<'m>,
+    checked: &'m ExprTypes,
+}
+
+impl Types<'_> {
+    fn of(&self, expr: &Expr) -> Option<TypeExpr> {
+        self.ids
+            .get(expr)
+            .and_then(|id| self.checked.get(id))
+            .cloned()
+    }
+}
+
+fn lower_function(f: &Function, types: &Types<'_>) -> HFunction {
     let params = f
         .params
         .iter()
         .map(|p| HParam {
             name: p.name.clone(),
             ty: p.ty.clone(),
+            mutable: p.mutable,
         })
         .collect();
     HFunction {
@@ -102,7 +190,9 @@ fn lower_function(f: &Function) -> HFunction {
         params,
         return_type: f.return_type.clone(),
         effect_row: f.effect_row.clone(),
-        body: lower_block(&f.body),
+        body: lower_block(&f.body, types),
+        is_public: f.is_public,
+        span: f.span,
     }
 }
 
@@ -114,80 +204,91 @@ fn lower_type_alias(alias: &TypeAlias) -> HTypeAlias {
     }
 }
 
-fn lower_block(b: &Block) -> HBlock {
+fn lower_block(b: &Block, types: &Types<'_>) -> HBlock {
     let mut stmts = Vec::new();
     for s in &b.statements {
         match s {
             Stmt::Let(l) => stmts.push(HStmt::Let {
                 name: l.name.clone(),
-                value: lower_expr(&l.value),
+                mutable: l.mutable,
+                value: lower_expr(&l.value, types),
             }),
-            Stmt::Expr(e) => stmts.push(HStmt::Expr(lower_expr(e))),
-            Stmt::Return(e) => stmts.push(HStmt::Return(e.as_ref().map(lower_expr))),
-            Stmt::Break | Stmt::Continue => {}
+            Stmt::Expr(e) => stmts.push(HStmt::Expr(lower_expr(e, types))),
+            Stmt::Return(e) => stmts.push(HStmt::Return(e.as_ref().map(|e| lower_expr(e, types)))),
+            Stmt::Break => stmts.push(HStmt::Break),
+            Stmt::Continue => stmts.push(HStmt::Continue),
         }
     }
-    HBlock { stmts }
+    HBlock {
+        stmts,
+        spans: b.spans.clone(),
+    }
 }
 
-fn lower_expr(e: &Expr) -> HExpr {
+fn lower_expr(e: &Expr, types: &Types<'_>) -> HExpr {
+    HExpr {
+        kind: lower_expr_kind(e, types),
+        ty: types.of(e),
+    }
+}
+
+fn lower_expr_kind(e: &Expr, types: &Types<'_>) -> HExprKind {
     match e {
-        Expr::Literal(l) => HExpr::Literal(l.clone()),
-        Expr::Path(p) => HExpr::Path(p.clone()),
-        Expr::Block(b) => HExpr::Block(lower_block(b)),
-        Expr::Binary { lhs, op, rhs } => HExpr::Binary {
-            lhs: Box::new(lower_expr(lhs)),
+        Expr::Literal(l) => HExprKind::Literal(l.clone()),
+        Expr::Path(p) => HExprKind::Path(p.clone()),
+        Expr::Block(b) => HExprKind::Block(lower_block(b, types)),
+        Expr::Binary { lhs, op, rhs } => HExprKind::Binary {
+            lhs: Box::new(lower_expr(lhs, types)),
             op: *op,
-            rhs: Box::new(lower_expr(rhs)),
+            rhs: Box::new(lower_expr(rhs, types)),
         },
         Expr::Call { callee, args } => {
             // Detect method call: (Field { expr: recv, name })
             if let Expr::Field { expr: recv, name } = &**callee {
                 let mut largs = Vec::with_capacity(args.len() + 1);
-                largs.push(lower_expr(recv));
+                largs.push(lower_expr(recv, types));
                 for a in args {
-                    largs.push(lower_expr(a));
+                    largs.push(lower_expr(a, types));
                 }
-                HExpr::Call {
+                HExprKind::Call {
                     func: HFuncRef::Method(name.clone()),
                     args: largs,
                 }
             } else if let Expr::Path(p) = &**callee {
-                HExpr::Call {
+                HExprKind::Call {
                     func: HFuncRef::Function(p.clone()),
-                    args: args.iter().map(lower_expr).collect(),
+                    args: args.iter().map(|e| lower_expr(e, types)).collect(),
                 }
             } else {
                 // Fallback: try to stringify callee by lowering then discarding
-                HExpr::Call {
+                HExprKind::Call {
                     func: HFuncRef::Method("<expr>".into()),
-                    args: args.iter().map(lower_expr).collect(),
+                    args: args.iter().map(|e| lower_expr(e, types)).collect(),
                 }
             }
         }
         Expr::Record { type_path, fields } => {
             let lowered_fields = fields
                 .iter()
-                .map(|(name, value)| (name.clone(), lower_expr(value)))
+                .map(|(name, value)| (name.clone(), lower_expr(value, types)))
                 .collect();
-            HExpr::Record {
+            HExprKind::Record {
                 type_path: type_path.clone(),
                 fields: lowered_fields,
             }
         }
-        Expr::Field { expr, name: _ } => {
-            // As value: keep as Path if simple, else ignore. Encode as a call-ready method
-            // reference if needed; for now just return the lowered receiver.
-            match &**expr {
-                Expr::Path(p) => HExpr::Path(p.clone()),
-                _ => lower_expr(expr),
-            }
+        Expr::List(elements) => {
+            HExprKind::List(elements.iter().map(|e| lower_expr(e, types)).collect())
         }
+        Expr::Field { expr, name } => HExprKind::Field {
+            expr: Box::new(lower_expr(expr, types)),
+            name: name.clone(),
+        },
         Expr::Index { expr, index } => {
             // Desugar index as method call: index(expr, idx)
-            HExpr::Call {
+            HExprKind::Call {
                 func: HFuncRef::Method("index".into()),
-                args: vec![lower_expr(expr), lower_expr(index)],
+                args: vec![lower_expr(expr, types), lower_expr(index, types)],
             }
         }
         Expr::If {
@@ -196,67 +297,89 @@ fn lower_expr(e: &Expr) -> HExpr {
             else_branch,
         } => {
             // Desugar to ternary-like call for demo
-            let mut args = vec![lower_expr(condition), lower_expr(then_branch)];
+            let mut args = vec![lower_expr(condition, types), lower_expr(then_branch, types)];
             if let Some(e) = else_branch {
-                args.push(lower_expr(e));
+                args.push(lower_expr(e, types));
             }
-            HExpr::Call {
+            HExprKind::Call {
                 func: HFuncRef::Method("if".into()),
                 args,
             }
         }
-        Expr::Assignment { target, value } => HExpr::Call {
-            func: HFuncRef::Method("assign".into()),
-            args: vec![lower_expr(target), lower_expr(value)],
+        Expr::Assignment { target, value } => match &**target {
+            Expr::Path(p) if p.segments.len() == 1 => HExprKind::Assign {
+                name: p.segments[0].clone(),
+                value: Box::new(lower_expr(value, types)),
+            },
+            _ => HExprKind::Call {
+                func: HFuncRef::Method("assign".into()),
+                args: vec![lower_expr(target, types), lower_expr(value, types)],
+            },
         },
-        Expr::Await(e) => HExpr::Call {
+        Expr::Await(e) => HExprKind::Call {
             func: HFuncRef::Method("await".into()),
-            args: vec![lower_expr(e)],
+            args: vec![lower_expr(e, types)],
         },
-        Expr::Spawn(e) => HExpr::Call {
+        Expr::Spawn(e) => HExprKind::Call {
             func: HFuncRef::Method("spawn".into()),
-            args: vec![lower_expr(e)],
+            args: vec![lower_expr(e, types)],
         },
         Expr::Chan { ty: _, capacity } => {
             let mut args = Vec::new();
             if let Some(c) = capacity {
-                args.push(lower_expr(c));
+                args.push(lower_expr(c, types));
             }
-            HExpr::Call {
+            HExprKind::Call {
                 func: HFuncRef::Method("chan".into()),
                 args,
             }
         }
-        Expr::Using { expr, body, .. } => HExpr::Call {
+        Expr::Using { expr, body, .. } => HExprKind::Call {
             func: HFuncRef::Method("using".into()),
-            args: vec![lower_expr(expr), HExpr::Block(lower_block(body))],
+            args: vec![
+                lower_expr(expr, types),
+                HExpr::untyped(HExprKind::Block(lower_block(body, types))),
+            ],
         },
-        Expr::Try(e) => HExpr::Call {
+        Expr::Try(e) => HExprKind::Call {
             func: HFuncRef::Method("try".into()),
-            args: vec![lower_expr(e)],
+            args: vec![lower_expr(e, types)],
         },
-        Expr::Ctor { path, args } => HExpr::Call {
+        Expr::Ctor { path, args } => HExprKind::Call {
             func: HFuncRef::Function(path.clone()),
-            args: args.iter().map(lower_expr).collect(),
+            args: args.iter().map(|e| lower_expr(e, types)).collect(),
+        },
+        Expr::For {
+            binding,
+            iterable,
+            body,
+        } => HExprKind::For {
+            binding: binding.clone(),
+            iterable: Box::new(lower_expr(iterable, types)),
+            body: Box::new(lower_expr(body, types)),
         },
-        // Unhandled: For/While/Loop/Match/Cast; reduce to placeholders for this demo
-        Expr::For { .. } => HExpr::Call {
-            func: HFuncRef::Method("for".into()),
-            args: vec![],
+        Expr::While { condition, body } => HExprKind::While {
+            condition: Box::new(lower_expr(condition, types)),
+            body: Box::new(lower_expr(body, types)),
         },
-        Expr::While { .. } => HExpr::Call {
-            func: HFuncRef::Method("while".into()),
-            args: vec![],
+        Expr::Loop { body } => HExprKind::Loop {
+            body: Box::new(lower_expr(body, types)),
         },
-        Expr::Loop { .. } => HExpr::Call {
-            func: HFuncRef::Method("loop".into()),
-            args: vec![],
+        Expr::Match { scrutinee, arms } => HExprKind::Match {
+            scrutinee: Box::new(lower_expr(scrutinee, types)),
+            arms: arms
+                .iter()
+                .map(|arm| HMatchArm {
+                    pattern: arm.pattern.clone(),
+                    guard: arm.guard.as_ref().map(|guard| lower_expr(guard, types)),
+                    body: lower_expr(&arm.body, types),
+                })
+                .collect(),
         },
-        Expr::Match { .. } => HExpr::Call {
-            func: HFuncRef::Method("match".into()),
-            args: vec![],
+        Expr::Cast { expr, ty } => HExprKind::Cast {
+            expr: Box::new(lower_expr(expr, types)),
+            ty: ty.clone(),
         },
-        Expr::Cast { expr, .. } => lower_expr(expr),
         Expr::Unary { op, expr } => {
             // Desugar unary as call for demo
             let name = match op {
@@ -265,9 +388,9 @@ fn lower_expr(e: &Expr) -> HExpr {
                 UnaryOp::Ref => "ref",
                 UnaryOp::RefMut => "ref_mut",
             };
-            HExpr::Call {
+            HExprKind::Call {
                 func: HFuncRef::Method(name.into()),
-                args: vec![lower_expr(expr)],
+                args: vec![lower_expr(expr, types)],
             }
         }
     }
@@ -292,16 +415,7 @@ pub fn hir_to_string(m: &HModule) -> String {
                 };
                 out.push_str(&format!("fn {}({}){}\n", f.name, params, effect_row));
                 for s in &f.body.stmts {
-                    match s {
-                        HStmt::Let { name, value } => {
-                            out.push_str(&format!("  let {} = {}\n", name, fmt_expr(value)))
-                        }
-                        HStmt::Expr(e) => out.push_str(&format!("  {}\n", fmt_expr(e))),
-                        HStmt::Return(Some(e)) => {
-                            out.push_str(&format!("  return {}\n", fmt_expr(e)))
-                        }
-                        HStmt::Return(None) => out.push_str("  return\n"),
-                    }
+                    out.push_str(&format!("  {}\n", fmt_stmt(s)));
                 }
             }
             HItem::TypeAlias(alias) => {
@@ -313,16 +427,18 @@ pub fn hir_to_string(m: &HModule) -> String {
 }
 
 fn fmt_expr(e: &HExpr) -> String {
-    match e {
-        HExpr::Literal(Literal::Int(i)) => format!("{}", i),
-        HExpr::Literal(Literal::Float(x)) => format!("{}", x),
-        HExpr::Literal(Literal::Bool(b)) => format!("{}", b),
-        HExpr::Literal(Literal::String(s)) => format!("\"{}\"", s),
-        HExpr::Literal(Literal::Unit) => "()".into(),
-        HExpr::Var(v) => v.clone(),
-        HExpr::Path(p) => p.segments.join("::"),
-        HExpr::Binary { lhs, op, rhs } => format!("({} {} {})", fmt_expr(lhs), op, fmt_expr(rhs)),
-        HExpr::Block(b) => {
+    match &e.kind {
+        HExprKind::Literal(Literal::Int(i)) => format!("{}", i),
+        HExprKind::Literal(Literal::Float(x)) => format!("{}", x),
+        HExprKind::Literal(Literal::Bool(b)) => format!("{}", b),
+        HExprKind::Literal(Literal::String(s)) => format!("\"{}\"", s),
+        HExprKind::Literal(Literal::Unit) => "()".into(),
+        HExprKind::Var(v) => v.clone(),
+        HExprKind::Path(p) => p.segments.join("::"),
+        HExprKind::Binary { lhs, op, rhs } => {
+            format!("({} {} {})", fmt_expr(lhs), op, fmt_expr(rhs))
+        }
+        HExprKind::Block(b) => {
             let mut s = String::from("{");
             for st in &b.stmts {
                 s.push_str(&format!(" {};", &fmt_stmt(st)));
@@ -330,7 +446,7 @@ fn fmt_expr(e: &HExpr) -> String {
             s.push_str(" }");
             s
         }
-        HExpr::Record { type_path, fields } => {
+        HExprKind::Record { type_path, fields } => {
             let mut s = String::new();
             if let Some(path) = type_path {
                 s.push_str(&path.segments.join("::"));
@@ -354,7 +470,13 @@ fn fmt_expr(e: &HExpr) -> String {
             s.push('}');
             s
         }
-        HExpr::Call { func, args } => {
+        HExprKind::List(elements) => {
+            let items = elements.iter().map(fmt_expr).collect::<Vec<_>>().join(", ");
+            format!("[{}]", items)
+        }
+        HExprKind::Field { expr, name } => format!("{}.{}", fmt_expr(expr), name),
+        HExprKind::Cast { expr, ty } => format!("({} as {:?})", fmt_expr(expr), ty),
+        HExprKind::Call { func, args } => {
             let fname = match func {
                 HFuncRef::Function(p) => p.segments.join("::"),
                 HFuncRef::Method(n) => n.clone(),
@@ -362,14 +484,83 @@ fn fmt_expr(e: &HExpr) -> String {
             let astr = args.iter().map(fmt_expr).collect::<Vec<_>>().join(", ");
             format!("{}({})", fname, astr)
         }
+        HExprKind::Assign { name, value } => format!("{} = {}", name, fmt_expr(value)),
+        HExprKind::While { condition, body } => {
+            format!("while {} {}", fmt_expr(condition), fmt_expr(body))
+        }
+        HExprKind::Loop { body } => format!("loop {}", fmt_expr(body)),
+        HExprKind::For {
+            binding,
+            iterable,
+            body,
+        } => format!(
+            "for {} in {} {}",
+            binding,
+            fmt_expr(iterable),
+            fmt_expr(body)
+        ),
+        HExprKind::Match { scrutinee, arms } => {
+            let arms = arms
+                .iter()
+                .map(|arm| match &arm.guard {
+                    Some(guard) => format!(
+                        "{} if {} => {}",
+                        fmt_pattern(&arm.pattern),
+                        fmt_expr(guard),
+                        fmt_expr(&arm.body)
+                    ),
+                    None => format!("{} => {}", fmt_pattern(&arm.pattern), fmt_expr(&arm.body)),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("match {} {{ {} }}", fmt_expr(scrutinee), arms)
+        }
+    }
+}
+
+fn fmt_pattern(p: &Pattern) -> String {
+    match p {
+        Pattern::Wildcard => "_".into(),
+        Pattern::Binding(name) => name.clone(),
+        Pattern::Literal(literal) => fmt_expr(&HExpr::untyped(HExprKind::Literal(literal.clone()))),
+        Pattern::Tuple(items) => {
+            let items = items.iter().map(fmt_pattern).collect::<Vec<_>>().join(", ");
+            format!("({})", items)
+        }
+        Pattern::Record(fields) => {
+            let fields = fields
+                .iter()
+                .map(|(name, pattern)| format!("{}: {}", name, fmt_pattern(pattern)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{{ {} }}", fields)
+        }
+        Pattern::EnumVariant { path, fields } if fields.is_empty() => path.segments.join("::"),
+        Pattern::EnumVariant { path, fields } => {
+            let fields = fields
+                .iter()
+                .map(fmt_pattern)
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{}({})", path.segments.join("::"), fields)
+        }
     }
 }
 
 fn fmt_stmt(s: &HStmt) -> String {
     match s {
-        HStmt::Let { name, value } => format!("let {} = {}", name, fmt_expr(value)),
+        HStmt::Let {
+            name,
+            mutable,
+            value,
+        } => {
+            let keyword = if *mutable { "let mut" } else { "let" };
+            format!("{} {} = {}", keyword, name, fmt_expr(value))
+        }
         HStmt::Expr(e) => fmt_expr(e),
         HStmt::Return(Some(e)) => format!("return {}", fmt_expr(e)),
         HStmt::Return(None) => "return".into(),
+        HStmt::Break => "break".into(),
+        HStmt::Continue => "continue".into(),
     }
 }
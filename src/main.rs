@@ -1,11 +1,12 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use mica::{
-    Result, backend, check, error, ir, lexer, lower, parser, pretty,
+    Result, backend, bytecode, check, error, interpreter, ir, lexer, lower, parser, pretty,
     resolve::{self, CapabilityScope, PathKind, SymbolCategory, SymbolScope},
     runtime,
     syntax::ast,
@@ -21,16 +22,57 @@ fn main() {
 
 fn run() -> Result<()> {
     let cli_args = CliArgs::parse(env::args().skip(1))?;
-    let source = fs::read_to_string(&cli_args.input_path)
-        .map_err(|e| error::Error::lex(None, e.to_string()))?;
-    let ctx = CommandContext::new(cli_args.input_path.clone(), source, cli_args.pretty);
+    let bytes =
+        fs::read(&cli_args.input_path).map_err(|e| error::Error::lex(None, e.to_string()))?;
+    // `--run-bytecode` also takes a `.micab` file written by `--emit-bytecode`.
+    let (source, compiled) = if matches!(cli_args.command, CommandKind::RunBytecode)
+        && bytecode::serialize::is_bytecode(&bytes)
+    {
+        (String::new(), Some(bytes))
+    } else {
+        let source = String::from_utf8(bytes)
+            .map_err(|_| error::Error::lex(None, "stream did not contain valid UTF-8"))?;
+        (source, None)
+    };
+    let mut linked_inputs = Vec::with_capacity(cli_args.linked_paths.len());
+    for path in &cli_args.linked_paths {
+        let source =
+            fs::read_to_string(path).map_err(|e| error::Error::lex(None, e.to_string()))?;
+        linked_inputs.push((path.clone(), source));
+    }
+    let ctx = CommandContext::new(
+        cli_args.input_path.clone(),
+        source,
+        cli_args.pretty,
+        cli_args.verify_ir,
+        cli_args.optimize,
+        cli_args.from_ir,
+    )
+    .with_linked_inputs(linked_inputs)
+    .with_provenance(cli_args.provenance)
+    .with_bytecode(compiled)
+    .with_elf(cli_args.elf)
+    .with_debug_info(cli_args.debug_info)
+    .with_keep_c(cli_args.keep_c)
+    .with_cache_dir(cli_args.cache_dir);
 
     cli_args.command.execute(ctx)
 }
 
 struct CliArgs {
     input_path: PathBuf,
+    /// Further modules linked after the entry module; only `--build` and
+    /// `--run` take more than one input.
+    linked_paths: Vec<PathBuf>,
     pretty: bool,
+    verify_ir: bool,
+    optimize: bool,
+    from_ir: bool,
+    provenance: bool,
+    elf: bool,
+    debug_info: bool,
+    keep_c: bool,
+    cache_dir: Option<PathBuf>,
     command: CommandKind,
 }
 
@@ -39,31 +81,58 @@ impl CliArgs {
     where
         I: Iterator<Item = String>,
     {
-        let mut command = CommandKind::Ast;
+        let mut command: Option<CommandKind> = None;
         let mut pretty = false;
+        let mut verify_ir = false;
+        let mut optimize = false;
+        let mut from_ir = false;
+        let mut provenance = false;
+        let mut elf = false;
+        let mut debug_info = false;
+        let mut keep_c = false;
         let mut output_path: Option<PathBuf> = None;
+        let mut cache_dir: Option<PathBuf> = None;
         let mut trace: Option<TraceTarget> = None;
+        let mut linked_paths = Vec::new();
         let mut input_path: Option<PathBuf> = None;
 
         while let Some(arg) = args.next() {
             match arg.as_str() {
-                "--tokens" => command = CommandKind::Tokens,
-                "--ast" => command = CommandKind::Ast,
-                "--check" => command = CommandKind::Check,
+                "--tokens" => command = Some(CommandKind::Tokens),
+                "--ast" => command = Some(CommandKind::Ast),
+                "--check" => command = Some(CommandKind::Check),
                 "--pretty" => pretty = true,
-                "--resolve" => command = CommandKind::Resolve,
-                "--resolve-json" => command = CommandKind::ResolveJson,
-                "--lower" => command = CommandKind::Lower,
-                "--ir" => command = CommandKind::Ir,
-                "--ir-json" => command = CommandKind::IrJson,
-                "--pipeline-json" => command = CommandKind::PipelineJson,
-                "--llvm" | "--emit-llvm" => command = CommandKind::Llvm,
-                "--build" => command = CommandKind::Build { output: None },
+                "--verify-ir" => verify_ir = true,
+                "-O" => optimize = true,
+                "--from-ir" => from_ir = true,
+                "--provenance" => provenance = true,
+                "--elf" => elf = true,
+                "-g" => debug_info = true,
+                "--keep-c" => keep_c = true,
+                "--resolve" => command = Some(CommandKind::Resolve),
+                "--resolve-json" => command = Some(CommandKind::ResolveJson),
+                "--lower" => command = Some(CommandKind::Lower),
+                "--ir" => command = Some(CommandKind::Ir),
+                "--ir-json" => command = Some(CommandKind::IrJson),
+                "--ir-dot" => command = Some(CommandKind::IrDot),
+                "--interpret" => command = Some(CommandKind::Interpret),
+                "--call-graph" => command = Some(CommandKind::CallGraph),
+                "--call-graph-json" => command = Some(CommandKind::CallGraphJson),
+                "--call-graph-dot" => command = Some(CommandKind::CallGraphDot),
+                "--pipeline-json" => command = Some(CommandKind::PipelineJson),
+                "--llvm" | "--emit-llvm" => command = Some(CommandKind::Llvm),
+                "--wat" => command = Some(CommandKind::Wat),
+                "--wasm" => command = Some(CommandKind::Wasm { output: None }),
+                "--emit-bytecode" => command = Some(CommandKind::EmitBytecode { output: None }),
+                "--run-bytecode" => command = Some(CommandKind::RunBytecode),
+                "--emit-obj" => command = Some(CommandKind::EmitObject { output: None }),
+                "--build" => command = Some(CommandKind::Build { output: None }),
+                "--build-lib" => command = Some(CommandKind::BuildLib { output: None }),
                 "--run" => {
-                    command = CommandKind::Run {
+                    command = Some(CommandKind::Run {
                         output: None,
                         trace: None,
-                    }
+                    })
                 }
                 "--trace-json" => {
                     let target = args.next().ok_or_else(|| {
@@ -80,15 +149,22 @@ impl CliArgs {
                     })?;
                     output_path = Some(PathBuf::from(value));
                 }
+                "--cache-dir" => {
+                    let value = args.next().ok_or_else(|| {
+                        error::Error::parse(None, "expected directory after --cache-dir")
+                    })?;
+                    cache_dir = Some(PathBuf::from(value));
+                }
                 _ => {
                     input_path = Some(PathBuf::from(arg));
-                    for extra in args {
-                        if input_path.is_some() {
+                    for extra in args.by_ref() {
+                        if extra.starts_with('-') {
                             return Err(error::Error::parse(
                                 None,
                                 format!("unexpected extra argument '{}'", extra),
                             ));
                         }
+                        linked_paths.push(PathBuf::from(extra));
                     }
                     break;
                 }
@@ -98,6 +174,71 @@ impl CliArgs {
         let input_path =
             input_path.ok_or_else(|| error::Error::parse(None, "missing input file"))?;
 
+        // `--verify-ir` on its own only lowers and verifies the module, and
+        // `--from-ir` on its own reprints the module it read.
+        let command = command.unwrap_or(if verify_ir {
+            CommandKind::VerifyIr
+        } else if from_ir {
+            CommandKind::Ir
+        } else {
+            CommandKind::Ast
+        });
+
+        if from_ir && !command.accepts_ir_input() {
+            return Err(error::Error::parse(
+                None,
+                "--from-ir only supports --ir, --ir-json, --ir-dot, --verify-ir, --interpret, --call-graph, --call-graph-json, --call-graph-dot, --llvm, --wat, --wasm, --emit-bytecode, --run-bytecode, --emit-obj, --build, --build-lib, and --run",
+            ));
+        }
+
+        if !linked_paths.is_empty() && !command.builds_native() {
+            return Err(error::Error::parse(
+                None,
+                "multiple input files are only supported with --build, --build-lib and --run",
+            ));
+        }
+
+        if provenance && !matches!(command, CommandKind::Ir | CommandKind::IrJson) {
+            return Err(error::Error::parse(
+                None,
+                "--provenance is only supported with --ir and --ir-json",
+            ));
+        }
+
+        if elf && !matches!(command, CommandKind::Build { .. } | CommandKind::Run { .. }) {
+            return Err(error::Error::parse(
+                None,
+                "--elf is only supported with --build and --run",
+            ));
+        }
+
+        if debug_info && (elf || !(matches!(command, CommandKind::Llvm) || command.builds_native()))
+        {
+            return Err(error::Error::parse(
+                None,
+                "-g is only supported with --llvm, and with --build, --build-lib and --run without --elf",
+            ));
+        }
+
+        if keep_c && (elf || !command.builds_native()) {
+            return Err(error::Error::parse(
+                None,
+                "--keep-c is only supported with --build, --build-lib and --run without --elf",
+            ));
+        }
+
+        if cache_dir.is_some()
+            && !matches!(
+                command,
+                CommandKind::Build { .. } | CommandKind::Run { .. } | CommandKind::PipelineJson
+            )
+        {
+            return Err(error::Error::parse(
+                None,
+                "--cache-dir is only supported with --build, --run and --pipeline-json",
+            ));
+        }
+
         if trace.is_some() && !matches!(command, CommandKind::Run { .. }) {
             return Err(error::Error::parse(
                 None,
@@ -111,7 +252,16 @@ impl CliArgs {
 
         Ok(Self {
             input_path,
+            linked_paths,
             pretty,
+            verify_ir,
+            optimize,
+            from_ir,
+            provenance,
+            elf,
+            debug_info,
+            keep_c,
+            cache_dir,
             command,
         })
     }
@@ -122,14 +272,108 @@ struct CommandContext {
     input_path: PathBuf,
     source: String,
     pretty: bool,
+    verify_ir: bool,
+    optimize: bool,
+    /// The input is textual IR rather than Mica source.
+    from_ir: bool,
+    /// Paths and sources of the modules linked after the entry module.
+    linked_inputs: Vec<(PathBuf, String)>,
+    /// Print instruction provenance with `--ir` and `--ir-json`.
+    provenance: bool,
+    /// Contents of a `.micab` input for `--run-bytecode`.
+    bytecode: Option<Vec<u8>>,
+    /// Build executables with the x86-64 backend instead of the C one.
+    elf: bool,
+    /// Emit line information mapping generated code back to the source.
+    debug_info: bool,
+    /// Write the C source of `--build` and `--run` next to the executable.
+    keep_c: bool,
+    /// Directory of the native build cache, if not the default one.
+    cache_dir: Option<PathBuf>,
 }
 
 impl CommandContext {
-    fn new(input_path: PathBuf, source: String, pretty: bool) -> Self {
+    fn new(
+        input_path: PathBuf,
+        source: String,
+        pretty: bool,
+        verify_ir: bool,
+        optimize: bool,
+        from_ir: bool,
+    ) -> Self {
         Self {
             input_path,
             source,
             pretty,
+            verify_ir,
+            optimize,
+            from_ir,
+            linked_inputs: Vec::new(),
+            provenance: false,
+            bytecode: None,
+            elf: false,
+            debug_info: false,
+            keep_c: false,
+            cache_dir: None,
+        }
+    }
+
+    fn with_linked_inputs(mut self, linked_inputs: Vec<(PathBuf, String)>) -> Self {
+        self.linked_inputs = linked_inputs;
+        self
+    }
+
+    fn with_provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    fn with_bytecode(mut self, bytecode: Option<Vec<u8>>) -> Self {
+        self.bytecode = bytecode;
+        self
+    }
+
+    fn with_elf(mut self, elf: bool) -> Self {
+        self.elf = elf;
+        self
+    }
+
+    fn with_debug_info(mut self, debug_info: bool) -> Self {
+        self.debug_info = debug_info;
+        self
+    }
+
+    fn with_keep_c(mut self, keep_c: bool) -> Self {
+        self.keep_c = keep_c;
+        self
+    }
+
+    fn with_cache_dir(mut self, cache_dir: Option<PathBuf>) -> Self {
+        self.cache_dir = cache_dir;
+        self
+    }
+
+    fn build_cache(&self) -> backend::cache::BuildCache {
+        let dir = self
+            .cache_dir
+            .clone()
+            .unwrap_or_else(backend::cache::BuildCache::default_dir);
+        backend::cache::BuildCache::new(dir)
+    }
+
+    fn backend_options(&self) -> backend::BackendOptions {
+        backend::BackendOptions {
+            optimize: self.optimize,
+            debug_info: self.debug_info,
+            ..backend::BackendOptions::default()
+        }
+    }
+
+    /// Records the file `module` was lowered from for debug info. Textual IR
+    /// inputs name no Mica source, so their modules keep the default name.
+    fn record_source(&self, module: &ir::Module, files: &mut BTreeMap<String, PathBuf>) {
+        if !self.from_ir {
+            files.insert(module.name.join("."), self.input_path.clone());
         }
     }
 }
@@ -144,11 +388,33 @@ enum CommandKind {
     Lower,
     Ir,
     IrJson,
+    IrDot,
+    VerifyIr,
+    Interpret,
+    CallGraph,
+    CallGraphJson,
+    CallGraphDot,
     PipelineJson,
     Llvm,
+    Wat,
+    Wasm {
+        output: Option<PathBuf>,
+    },
+    EmitBytecode {
+        output: Option<PathBuf>,
+    },
+    RunBytecode,
+    EmitObject {
+        output: Option<PathBuf>,
+    },
     Build {
         output: Option<PathBuf>,
     },
+    /// A shared and a static library plus their header, written to the
+    /// `output` directory.
+    BuildLib {
+        output: Option<PathBuf>,
+    },
     Run {
         output: Option<PathBuf>,
         trace: Option<TraceTarget>,
@@ -158,7 +424,11 @@ enum CommandKind {
 impl CommandKind {
     fn with_output_path(self, output: Option<PathBuf>) -> Self {
         match self {
+            CommandKind::Wasm { .. } => CommandKind::Wasm { output },
+            CommandKind::EmitBytecode { .. } => CommandKind::EmitBytecode { output },
+            CommandKind::EmitObject { .. } => CommandKind::EmitObject { output },
             CommandKind::Build { .. } => CommandKind::Build { output },
+            CommandKind::BuildLib { .. } => CommandKind::BuildLib { output },
             CommandKind::Run { trace, .. } => CommandKind::Run { output, trace },
             other => other,
         }
@@ -171,6 +441,40 @@ impl CommandKind {
         }
     }
 
+    /// Modes that start from an IR module and can therefore read `--from-ir`
+    /// input.
+    fn accepts_ir_input(&self) -> bool {
+        matches!(
+            self,
+            CommandKind::Ir
+                | CommandKind::IrJson
+                | CommandKind::IrDot
+                | CommandKind::VerifyIr
+                | CommandKind::Interpret
+                | CommandKind::CallGraph
+                | CommandKind::CallGraphJson
+                | CommandKind::CallGraphDot
+                | CommandKind::Llvm
+                | CommandKind::Wat
+                | CommandKind::Wasm { .. }
+                | CommandKind::EmitBytecode { .. }
+                | CommandKind::RunBytecode
+                | CommandKind::EmitObject { .. }
+                | CommandKind::Build { .. }
+                | CommandKind::BuildLib { .. }
+                | CommandKind::Run { .. }
+        )
+    }
+
+    /// Modes that compile and link the program natively, which accept
+    /// several input files.
+    fn builds_native(&self) -> bool {
+        matches!(
+            self,
+            CommandKind::Build { .. } | CommandKind::BuildLib { .. } | CommandKind::Run { .. }
+        )
+    }
+
     fn execute(self, ctx: CommandContext) -> Result<()> {
         match self {
             CommandKind::Tokens => run_tokens(&ctx),
@@ -181,9 +485,21 @@ impl CommandKind {
             CommandKind::Lower => run_lower(&ctx),
             CommandKind::Ir => run_ir(&ctx),
             CommandKind::IrJson => run_ir_json(&ctx),
+            CommandKind::IrDot => run_ir_dot(&ctx),
+            CommandKind::VerifyIr => run_verify_ir(&ctx),
+            CommandKind::Interpret => run_interpret(&ctx),
+            CommandKind::CallGraph => run_call_graph(&ctx, false),
+            CommandKind::CallGraphJson => run_call_graph(&ctx, true),
+            CommandKind::CallGraphDot => run_call_graph_dot(&ctx),
             CommandKind::PipelineJson => run_pipeline_json(&ctx),
             CommandKind::Llvm => run_llvm(&ctx),
+            CommandKind::Wat => run_wat(&ctx),
+            CommandKind::Wasm { output } => run_wasm(&ctx, output),
+            CommandKind::EmitBytecode { output } => run_emit_bytecode(&ctx, output),
+            CommandKind::RunBytecode => run_bytecode(&ctx),
+            CommandKind::EmitObject { output } => run_emit_object(&ctx, output),
             CommandKind::Build { output } => run_build(&ctx, output),
+            CommandKind::BuildLib { output } => run_build_lib(&ctx, output),
             CommandKind::Run { output, trace } => run_executable(&ctx, output, trace),
         }
     }
@@ -213,9 +529,16 @@ fn run_ast(ctx: &CommandContext) -> Result<()> {
     Ok(())
 }
 
-fn run_check(ctx: &CommandContext) -> Result<()> {
+/// Parses and type checks the input. The check result carries the
+/// expression types that lowering needs.
+fn parse_and_check(ctx: &CommandContext) -> Result<(ast::Module, check::CheckResult)> {
     let module = parser::parse_module(&ctx.source)?;
     let result = check::check_module(&module);
+    Ok((module, result))
+}
+
+fn run_check(ctx: &CommandContext) -> Result<()> {
+    let (_, result) = parse_and_check(ctx)?;
     if result.diagnostics.is_empty() {
         println!("ok");
     } else {
@@ -340,74 +663,422 @@ fn run_resolve_json(ctx: &CommandContext) -> Result<()> {
 }
 
 fn run_lower(ctx: &CommandContext) -> Result<()> {
-    let module = parser::parse_module(&ctx.source)?;
-    let h = lower::lower_module(&module);
+    let (module, checked) = parse_and_check(ctx)?;
+    let h = lower::lower_module(&module, &checked.types);
     println!("{}", lower::hir_to_string(&h));
     Ok(())
 }
 
+/// Parses and lowers the input to IR, verifying the module first when
+/// `--verify-ir` was given. With `--from-ir` the input is read as textual IR
+/// and rejected if it is structurally malformed.
+fn lower_to_ir(ctx: &CommandContext) -> Result<ir::Module> {
+    let typed = if ctx.from_ir {
+        // `--ir-json` output starts with `{`; anything else is textual IR.
+        let typed = if ctx.source.trim_start().starts_with('{') {
+            ir::serialize::from_json(&ctx.source)
+                .map_err(|err| error::Error::parse(None, err.to_string()))?
+        } else {
+            ir::parse::parse_module(&ctx.source)?
+        };
+        let report = ir::verify::verify_module(&typed);
+        report_violations(report.structural().collect())?;
+        typed
+    } else {
+        let (module, checked) = parse_and_check(ctx)?;
+        let hir = lower::lower_module(&module, &checked.types);
+        ir::lower_module(&hir)
+    };
+    if ctx.verify_ir {
+        verify_ir_module(&typed)?;
+    }
+    Ok(typed)
+}
+
+/// Lowers the entry input and every linked input, then links them into one
+/// program with the entry module first. A single input is just lowered.
+/// Also returns the source file of each module.
+fn lower_program(ctx: &CommandContext) -> Result<(ir::Module, BTreeMap<String, PathBuf>)> {
+    let mut files = BTreeMap::new();
+    let entry = lower_to_ir(ctx)?;
+    ctx.record_source(&entry, &mut files);
+    if ctx.linked_inputs.is_empty() {
+        return Ok((entry, files));
+    }
+    let mut modules = vec![entry];
+    for (path, source) in &ctx.linked_inputs {
+        let input = CommandContext {
+            input_path: path.clone(),
+            source: source.clone(),
+            linked_inputs: Vec::new(),
+            ..ctx.clone()
+        };
+        let module = lower_to_ir(&input)?;
+        input.record_source(&module, &mut files);
+        modules.push(module);
+    }
+    let linked = match ir::link::link_modules(modules) {
+        Ok(linked) => linked,
+        Err(errors) => {
+            for err in &errors {
+                eprintln!("error[link]: {}", err);
+            }
+            return Err(error::Error::parse(
+                None,
+                format!("linking failed with {} error(s)", errors.len()),
+            ));
+        }
+    };
+    if ctx.verify_ir {
+        verify_ir_module(&linked)?;
+    }
+    Ok((linked, files))
+}
+
+fn verify_ir_module(module: &ir::Module) -> Result<()> {
+    let report = ir::verify::verify_module(module);
+    report_violations(report.violations.iter().collect())
+}
+
+fn report_violations(violations: Vec<&ir::verify::Violation>) -> Result<()> {
+    if violations.is_empty() {
+        return Ok(());
+    }
+    for violation in &violations {
+        eprintln!("error[ir-verify]: {}", violation);
+    }
+    Err(error::Error::parse(
+        None,
+        format!(
+            "IR verification failed with {} violation(s)",
+            violations.len()
+        ),
+    ))
+}
+
+fn run_verify_ir(ctx: &CommandContext) -> Result<()> {
+    lower_to_ir(ctx)?;
+    println!("ok");
+    Ok(())
+}
+
+fn run_interpret(ctx: &CommandContext) -> Result<()> {
+    let mut typed = lower_to_ir(ctx)?;
+    if ctx.optimize {
+        ir::passes::optimize(&mut typed);
+    }
+    let output = interpreter::Interpreter::new(typed)
+        .run()
+        .map_err(|err| error::Error::parse(None, format!("runtime error: {}", err)))?;
+    print!("{}", output);
+    Ok(())
+}
+
 fn run_ir(ctx: &CommandContext) -> Result<()> {
-    let module = parser::parse_module(&ctx.source)?;
-    let hir = lower::lower_module(&module);
-    let typed = ir::lower_module(&hir);
-    let backend = backend::text::TextBackend;
-    let output = backend::run(&backend, &typed, &backend::BackendOptions::default())
-        .map_err(|err| error::Error::parse(None, err.to_string()))?;
-    println!("{}", output);
+    let mut typed = lower_to_ir(ctx)?;
+    let render = if ctx.provenance {
+        backend::text::render_module_with_provenance
+    } else {
+        backend::text::render_module
+    };
+    if ctx.optimize {
+        // Show each pass that changed the module before the final result.
+        let report = ir::passes::PassManager::optimization_pipeline()
+            .record_dumps(true)
+            .run(&mut typed);
+        for dump in &report.dumps {
+            println!("; before {} (round {})", dump.pass, dump.round);
+            println!("{}", render(&dump.before));
+            println!("; after {} (round {})", dump.pass, dump.round);
+            println!("{}", render(&dump.after));
+        }
+        println!("; optimized");
+    }
+    println!("{}", render(&typed));
     Ok(())
 }
 
 fn run_ir_json(ctx: &CommandContext) -> Result<()> {
-    let module = parser::parse_module(&ctx.source)?;
-    let hir = lower::lower_module(&module);
-    let typed = ir::lower_module(&hir);
-    let json = ir_module_to_json(&typed);
-    println!("{}", json);
+    let mut typed = lower_to_ir(ctx)?;
+    if ctx.optimize {
+        ir::passes::optimize(&mut typed);
+    }
+    if !ctx.provenance {
+        typed.clear_provenance();
+    }
+    println!("{}", ir::serialize::to_json(&typed));
+    Ok(())
+}
+
+fn run_ir_dot(ctx: &CommandContext) -> Result<()> {
+    let mut typed = lower_to_ir(ctx)?;
+    if ctx.optimize {
+        ir::passes::optimize(&mut typed);
+    }
+    print!("{}", backend::dot::render_module(&typed));
+    Ok(())
+}
+
+fn run_call_graph(ctx: &CommandContext, json: bool) -> Result<()> {
+    let mut typed = lower_to_ir(ctx)?;
+    if ctx.optimize {
+        ir::passes::optimize(&mut typed);
+    }
+    let summaries = ir::analysis::EffectSummaries::new(&typed);
+    if json {
+        println!("{}", summaries.to_json());
+    } else {
+        print!("{}", summaries);
+    }
+    Ok(())
+}
+
+fn run_call_graph_dot(ctx: &CommandContext) -> Result<()> {
+    let mut typed = lower_to_ir(ctx)?;
+    if ctx.optimize {
+        ir::passes::optimize(&mut typed);
+    }
+    print!("{}", backend::dot::render_call_graph(&typed));
     Ok(())
 }
 
 fn run_pipeline_json(ctx: &CommandContext) -> Result<()> {
-    let snapshot = tooling::PipelineSnapshot::capture(&ctx.source);
+    let mut snapshot = tooling::PipelineSnapshot::capture(&ctx.source);
+    // The entry `--build` and `--run` would look up for this program.
+    if let Ok((typed, source_files)) = lower_program(ctx) {
+        let options = backend::BackendOptions {
+            source_files,
+            ..ctx.backend_options()
+        };
+        let key = backend::cache::CacheKey::new(&typed, &options, "executable");
+        snapshot = snapshot.with_native_cache(&ctx.build_cache(), &key);
+    }
     println!("{}", snapshot.to_json_string());
     Ok(())
 }
 
 fn run_llvm(ctx: &CommandContext) -> Result<()> {
-    let module = parser::parse_module(&ctx.source)?;
-    let hir = lower::lower_module(&module);
-    let typed = ir::lower_module(&hir);
+    let typed = lower_to_ir(ctx)?;
     let backend = backend::llvm::LlvmBackend::default();
-    let output = backend::run(&backend, &typed, &backend::BackendOptions::default())
+    let mut options = ctx.backend_options();
+    ctx.record_source(&typed, &mut options.source_files);
+    let output = backend::run(&backend, &typed, &options)
         .map_err(|err| error::Error::parse(None, err.to_string()))?;
     println!("{}", output.as_str());
     Ok(())
 }
 
-fn run_build(ctx: &CommandContext, output: Option<PathBuf>) -> Result<()> {
-    let module = parser::parse_module(&ctx.source)?;
-    let hir = lower::lower_module(&module);
-    let typed = ir::lower_module(&hir);
-    let backend = backend::native::NativeBackend;
-    let artifact = backend::run(&backend, &typed, &backend::BackendOptions::default())
+fn run_wat(ctx: &CommandContext) -> Result<()> {
+    let typed = lower_to_ir(ctx)?;
+    let module = backend::run(&backend::wasm::WasmBackend, &typed, &ctx.backend_options())
+        .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    print!("{}", module.wat);
+    Ok(())
+}
+
+fn run_wasm(ctx: &CommandContext, output: Option<PathBuf>) -> Result<()> {
+    let typed = lower_to_ir(ctx)?;
+    let module = backend::run(&backend::wasm::WasmBackend, &typed, &ctx.backend_options())
+        .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    let target_path = output.unwrap_or_else(|| ctx.input_path.with_extension("wasm"));
+    module
+        .write(&target_path)
+        .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    println!("wrote {}", target_path.display());
+    Ok(())
+}
+
+fn compile_bytecode(ctx: &CommandContext) -> Result<bytecode::Program> {
+    let typed = lower_to_ir(ctx)?;
+    backend::run(
+        &backend::bytecode::BytecodeBackend,
+        &typed,
+        &ctx.backend_options(),
+    )
+    .map_err(|err| error::Error::parse(None, err.to_string()))
+}
+
+fn run_emit_bytecode(ctx: &CommandContext, output: Option<PathBuf>) -> Result<()> {
+    let program = compile_bytecode(ctx)?;
+    let target_path = output.unwrap_or_else(|| ctx.input_path.with_extension("micab"));
+    bytecode::save(&program, &target_path)
+        .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    println!("wrote {}", target_path.display());
+    Ok(())
+}
+
+fn run_bytecode(ctx: &CommandContext) -> Result<()> {
+    let program = match &ctx.bytecode {
+        Some(bytes) => {
+            bytecode::from_bytes(bytes).map_err(|err| error::Error::parse(None, err.to_string()))?
+        }
+        None => compile_bytecode(ctx)?,
+    };
+    let runtime = runtime::Runtime::with_default_shims()
         .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    let execution = bytecode::Vm::new(&program, runtime)
+        .run()
+        .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    for line in execution.output() {
+        println!("{}", line);
+    }
+    // Like `--run`, an integer returned from `main` is the exit status.
+    match execution.value {
+        bytecode::Value::Int(status) if status != 0 => Err(error::Error::parse(
+            None,
+            format!("program exited with status {}", status),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn run_emit_object(ctx: &CommandContext, output: Option<PathBuf>) -> Result<()> {
+    let typed = lower_to_ir(ctx)?;
+    let artifact = backend::run(
+        &backend::x86_64::X86_64Backend,
+        &typed,
+        &ctx.backend_options(),
+    )
+    .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    let target_path = output.unwrap_or_else(|| ctx.input_path.with_extension("o"));
+    artifact
+        .write_object(&target_path)
+        .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    println!("wrote {}", target_path.display());
+    Ok(())
+}
+
+/// Compiles the program into an executable at `path`, through the C backend
+/// or, with `--elf`, the self-contained x86-64 backend. C builds are copied
+/// out of the build cache.
+fn link_program(ctx: &CommandContext, path: &Path, c_path: &Path) -> Result<()> {
+    if !ctx.elf {
+        let cached = cached_executable(ctx, c_path)?;
+        // Another `mica` may evict the entry between the lookup and the
+        // copy; looking it up again rebuilds it.
+        let copied = match fs::copy(&cached, path) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                fs::copy(cached_executable(ctx, c_path)?, path)
+            }
+            copied => copied,
+        };
+        copied.map_err(|err| error::Error::parse(None, err.to_string()))?;
+        return Ok(());
+    }
+    let (typed, source_files) = lower_program(ctx)?;
+    let options = backend::BackendOptions {
+        source_files,
+        ..ctx.backend_options()
+    };
+    backend::run(&backend::x86_64::X86_64Backend, &typed, &options)
+        .and_then(|artifact| artifact.link_executable(path))
+        .map(|_| ())
+        .map_err(|err| error::Error::parse(None, err.to_string()))
+}
+
+/// The program's executable in the build cache, which the C backend and
+/// `cc` only build when the module or options changed. With `--keep-c` the
+/// C source is also written to `c_path`, before `cc` runs so that it is
+/// there to inspect when compilation fails.
+fn cached_executable(ctx: &CommandContext, c_path: &Path) -> Result<PathBuf> {
+    let (typed, source_files) = lower_program(ctx)?;
+    let options = backend::BackendOptions {
+        source_files,
+        ..ctx.backend_options()
+    };
+    let key = backend::cache::CacheKey::new(&typed, &options, "executable");
+    let generate = || backend::run(&backend::native::NativeBackend, &typed, &options);
+    let lookup = ctx.build_cache().get_or_build(&key, |path| {
+        let artifact = generate()?;
+        if ctx.keep_c {
+            artifact.write_source(c_path)?;
+        }
+        artifact.link_executable(path).map(|_| ())
+    });
+    let lookup = lookup.map_err(|err| error::Error::parse(None, err.to_string()))?;
+    if lookup.hit && ctx.keep_c {
+        generate()
+            .and_then(|artifact| artifact.write_source(c_path))
+            .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    }
+    Ok(lookup.path)
+}
+
+fn run_build(ctx: &CommandContext, output: Option<PathBuf>) -> Result<()> {
     let mut default_path = ctx.input_path.clone();
     default_path.set_extension("bin");
     let target_path = output.unwrap_or(default_path);
-    artifact
-        .link_executable(&target_path)
-        .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    let c_path = target_path.with_extension("c");
+    link_program(ctx, &target_path, &c_path)?;
+    if ctx.keep_c {
+        println!("wrote {}", c_path.display());
+    }
     println!("built {}", target_path.display());
     Ok(())
 }
 
+/// Builds `lib<name>.so`, `lib<name>.a` and `mica.h` into `output`, by
+/// default the directory of the input, exporting the `pub` functions with
+/// C linkage.
+fn run_build_lib(ctx: &CommandContext, output: Option<PathBuf>) -> Result<()> {
+    let dir = output.unwrap_or_else(|| {
+        ctx.input_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    });
+    let stem = ctx
+        .input_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "mica".to_string());
+    let (typed, source_files) = lower_program(ctx)?;
+    let options = backend::BackendOptions {
+        library: true,
+        source_files,
+        ..ctx.backend_options()
+    };
+    let backend_error = |err: backend::BackendError| error::Error::parse(None, err.to_string());
+    let artifact =
+        backend::run(&backend::native::NativeBackend, &typed, &options).map_err(backend_error)?;
+    fs::create_dir_all(&dir).map_err(|err| error::Error::parse(None, err.to_string()))?;
+
+    let header_path = dir.join("mica.h");
+    let shared_path = dir.join(format!("lib{stem}.so"));
+    let static_path = dir.join(format!("lib{stem}.a"));
+    let c_path = dir.join(format!("lib{stem}.c"));
+    artifact.write_header(&header_path).map_err(backend_error)?;
+    if ctx.keep_c {
+        artifact.write_source(&c_path).map_err(backend_error)?;
+    }
+    artifact
+        .link_shared_library(&shared_path)
+        .map_err(backend_error)?;
+    artifact
+        .link_static_library(&static_path)
+        .map_err(backend_error)?;
+    println!("wrote {}", header_path.display());
+    if ctx.keep_c {
+        println!("wrote {}", c_path.display());
+    }
+    println!("built {}", shared_path.display());
+    println!("built {}", static_path.display());
+    Ok(())
+}
+
 fn run_executable(
     ctx: &CommandContext,
     output: Option<PathBuf>,
     trace: Option<TraceTarget>,
 ) -> Result<()> {
-    let module = parser::parse_module(&ctx.source)?;
-    let resolved = resolve::resolve_module(&module);
-    let entry_spec = entry_task_spec(&module, &resolved);
+    // Capability checks need the source AST; IR input skips them.
+    let entry_spec = if ctx.from_ir {
+        None
+    } else {
+        let module = parser::parse_module(&ctx.source)?;
+        let resolved = resolve::resolve_module(&module);
+        entry_task_spec(&module, &resolved)
+    };
     if let Some(spec) = &entry_spec {
         let runtime = runtime::Runtime::with_default_shims()
             .map_err(|err| error::Error::parse(None, err.to_string()))?;
@@ -415,35 +1086,31 @@ fn run_executable(
             .ensure_capabilities(spec)
             .map_err(|err| error::Error::parse(None, err.to_string()))?;
     }
-    let hir = lower::lower_module(&module);
-    let typed = ir::lower_module(&hir);
-    let backend = backend::native::NativeBackend;
-    let artifact = backend::run(&backend, &typed, &backend::BackendOptions::default())
-        .map_err(|err| error::Error::parse(None, err.to_string()))?;
     let (exe_path, cleanup_path);
     if let Some(path) = output {
-        artifact
-            .link_executable(&path)
-            .map_err(|err| error::Error::parse(None, err.to_string()))?;
+        link_program(ctx, &path, &path.with_extension("c"))?;
         exe_path = path.clone();
         cleanup_path = None;
     } else {
+        // C builds come out of the build cache as a private copy, which
+        // another `mica` evicting the entry cannot delete while it runs.
+        // Kept C goes next to the input.
         let mut path_buf = env::temp_dir();
         let nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
             .as_nanos();
         path_buf.push(format!("mica-run-{nanos}"));
-        artifact
-            .link_executable(&path_buf)
-            .map_err(|err| error::Error::parse(None, err.to_string()))?;
+        link_program(ctx, &path_buf, &ctx.input_path.with_extension("c"))?;
         exe_path = path_buf;
         cleanup_path = Some(exe_path.clone());
     }
 
-    let output = Command::new(&exe_path)
-        .output()
-        .map_err(|err| error::Error::parse(None, err.to_string()))?;
+    let output = Command::new(&exe_path).output();
+    if let Some(path) = cleanup_path {
+        fs::remove_file(&path).ok();
+    }
+    let output = output.map_err(|err| error::Error::parse(None, err.to_string()))?;
     if !output.status.success() {
         let stdout = String::from_utf8_lossy(&output.stdout);
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -484,9 +1151,6 @@ fn run_executable(
         }
     }
 
-    if let Some(path) = cleanup_path {
-        fs::remove_file(&path).ok();
-    }
     Ok(())
 }
 
@@ -676,277 +1340,6 @@ fn resolved_to_json(resolved: &resolve::Resolved) -> String {
     json_object(fields)
 }
 
-fn ir_module_to_json(module: &ir::Module) -> String {
-    let functions = module
-        .functions
-        .iter()
-        .map(|function| ir_function_json(module, function))
-        .collect::<Vec<_>>();
-    let types = module
-        .types
-        .entries()
-        .map(|(id, ty)| ir_type_entry_json(id, ty))
-        .collect::<Vec<_>>();
-    let effects = module
-        .effects
-        .entries()
-        .map(|(id, name)| {
-            json_object(vec![
-                ("id", id.index().to_string()),
-                ("name", json_string(name)),
-            ])
-        })
-        .collect::<Vec<_>>();
-
-    json_object(vec![
-        ("name", json_string_array(&module.name)),
-        ("functions", json_array(functions)),
-        ("types", json_array(types)),
-        ("effects", json_array(effects)),
-    ])
-}
-
-fn ir_function_json(module: &ir::Module, function: &ir::Function) -> String {
-    let params = function
-        .params
-        .iter()
-        .map(|param| {
-            json_object(vec![
-                ("name", json_string(&param.name)),
-                ("type", param.ty.index().to_string()),
-                ("value", param.value.index().to_string()),
-            ])
-        })
-        .collect::<Vec<_>>();
-
-    let blocks = function
-        .blocks
-        .iter()
-        .map(|block| ir_block_json(module, block))
-        .collect::<Vec<_>>();
-
-    let effects = function
-        .effect_row
-        .iter()
-        .map(|effect| module.effects.name(*effect).to_string())
-        .collect::<Vec<_>>();
-
-    json_object(vec![
-        ("name", json_string(&function.name)),
-        ("ret_type", function.ret_type.index().to_string()),
-        ("effect_row", json_string_array(&effects)),
-        ("params", json_array(params)),
-        ("blocks", json_array(blocks)),
-    ])
-}
-
-fn ir_block_json(module: &ir::Module, block: &ir::BasicBlock) -> String {
-    let instructions = block
-        .instructions
-        .iter()
-        .map(|inst| ir_instruction_json(module, inst))
-        .collect::<Vec<_>>();
-
-    json_object(vec![
-        ("id", block.id.index().to_string()),
-        ("instructions", json_array(instructions)),
-        ("terminator", ir_terminator_json(module, &block.terminator)),
-    ])
-}
-
-fn ir_instruction_json(module: &ir::Module, inst: &ir::Instruction) -> String {
-    let effects = inst
-        .effects
-        .iter()
-        .map(|effect| module.effects.name(*effect).to_string())
-        .collect::<Vec<_>>();
-
-    json_object(vec![
-        ("id", inst.id.index().to_string()),
-        ("type", inst.ty.index().to_string()),
-        ("effects", json_string_array(&effects)),
-        ("kind", ir_instruction_kind_json(module, &inst.kind)),
-    ])
-}
-
-fn ir_instruction_kind_json(_module: &ir::Module, kind: &ir::InstKind) -> String {
-    match kind {
-        ir::InstKind::Literal(lit) => json_object(vec![
-            ("kind", json_string("Literal")),
-            ("value", literal_json(lit)),
-        ]),
-        ir::InstKind::Binary { op, lhs, rhs } => json_object(vec![
-            ("kind", json_string("Binary")),
-            ("op", json_string(&op.to_string())),
-            ("lhs", lhs.index().to_string()),
-            ("rhs", rhs.index().to_string()),
-        ]),
-        ir::InstKind::Call { func, args } => {
-            let function_json = match func {
-                ir::FuncRef::Function(path) => json_object(vec![
-                    ("type", json_string("Function")),
-                    ("path", json_string_array(&path.segments)),
-                ]),
-                ir::FuncRef::Method(name) => json_object(vec![
-                    ("type", json_string("Method")),
-                    ("name", json_string(name)),
-                ]),
-            };
-            let args_json = json_array(
-                args.iter()
-                    .map(|arg| arg.index().to_string())
-                    .collect::<Vec<_>>(),
-            );
-            json_object(vec![
-                ("kind", json_string("Call")),
-                ("function", function_json),
-                ("args", args_json),
-            ])
-        }
-        ir::InstKind::Record { type_path, fields } => {
-            let type_json = type_path
-                .as_ref()
-                .map(|path| json_string_array(&path.segments))
-                .unwrap_or_else(|| "null".to_string());
-            let field_json = json_array(
-                fields
-                    .iter()
-                    .map(|(name, value)| {
-                        json_object(vec![
-                            ("name", json_string(name)),
-                            ("value", value.index().to_string()),
-                        ])
-                    })
-                    .collect::<Vec<_>>(),
-            );
-            json_object(vec![
-                ("kind", json_string("Record")),
-                ("type_path", type_json),
-                ("fields", field_json),
-            ])
-        }
-        ir::InstKind::Path(path) => json_object(vec![
-            ("kind", json_string("Path")),
-            ("segments", json_string_array(&path.segments)),
-        ]),
-        ir::InstKind::Phi { incomings } => {
-            let incomings_json = json_array(
-                incomings
-                    .iter()
-                    .map(|(block, value)| {
-                        json_object(vec![
-                            ("block", block.index().to_string()),
-                            ("value", value.index().to_string()),
-                        ])
-                    })
-                    .collect::<Vec<_>>(),
-            );
-            json_object(vec![
-                ("kind", json_string("Phi")),
-                ("incomings", incomings_json),
-            ])
-        }
-    }
-}
-
-fn ir_terminator_json(_module: &ir::Module, terminator: &ir::Terminator) -> String {
-    match terminator {
-        ir::Terminator::Return(Some(value)) => json_object(vec![
-            ("kind", json_string("Return")),
-            ("value", value.index().to_string()),
-        ]),
-        ir::Terminator::Return(None) => json_object(vec![
-            ("kind", json_string("Return")),
-            ("value", "null".to_string()),
-        ]),
-        ir::Terminator::Branch {
-            condition,
-            then_block,
-            else_block,
-        } => json_object(vec![
-            ("kind", json_string("Branch")),
-            ("condition", condition.index().to_string()),
-            ("then", then_block.index().to_string()),
-            ("else", else_block.index().to_string()),
-        ]),
-        ir::Terminator::Jump(target) => json_object(vec![
-            ("kind", json_string("Jump")),
-            ("target", target.index().to_string()),
-        ]),
-    }
-}
-
-fn ir_type_entry_json(id: ir::TypeId, ty: &ir::Type) -> String {
-    json_object(vec![
-        ("id", id.index().to_string()),
-        ("type", ir_type_json(ty)),
-    ])
-}
-
-fn ir_type_json(ty: &ir::Type) -> String {
-    match ty {
-        ir::Type::Unit => json_object(vec![("kind", json_string("Unit"))]),
-        ir::Type::Int => json_object(vec![("kind", json_string("Int"))]),
-        ir::Type::Float => json_object(vec![("kind", json_string("Float"))]),
-        ir::Type::Bool => json_object(vec![("kind", json_string("Bool"))]),
-        ir::Type::String => json_object(vec![("kind", json_string("String"))]),
-        ir::Type::Named(name) => json_object(vec![
-            ("kind", json_string("Named")),
-            ("name", json_string(name)),
-        ]),
-        ir::Type::Record(record) => {
-            let fields = record
-                .fields
-                .iter()
-                .map(|field| {
-                    json_object(vec![
-                        ("name", json_string(&field.name)),
-                        ("type", field.ty.index().to_string()),
-                        ("offset", field.offset.to_string()),
-                    ])
-                })
-                .collect::<Vec<_>>();
-            json_object(vec![
-                ("kind", json_string("Record")),
-                (
-                    "name",
-                    record
-                        .name
-                        .as_ref()
-                        .map(|name| json_string(name))
-                        .unwrap_or_else(|| "null".to_string()),
-                ),
-                ("fields", json_array(fields)),
-                ("size", record.size.to_string()),
-                ("align", record.align.to_string()),
-            ])
-        }
-        ir::Type::Unknown => json_object(vec![("kind", json_string("Unknown"))]),
-    }
-}
-
-fn literal_json(literal: &ast::Literal) -> String {
-    match literal {
-        ast::Literal::Int(value) => json_object(vec![
-            ("type", json_string("Int")),
-            ("value", value.to_string()),
-        ]),
-        ast::Literal::Float(value) => json_object(vec![
-            ("type", json_string("Float")),
-            ("value", value.to_string()),
-        ]),
-        ast::Literal::Bool(value) => json_object(vec![
-            ("type", json_string("Bool")),
-            ("value", bool_string(*value)),
-        ]),
-        ast::Literal::String(value) => json_object(vec![
-            ("type", json_string("String")),
-            ("value", json_string(value)),
-        ]),
-        ast::Literal::Unit => json_object(vec![("type", json_string("Unit"))]),
-    }
-}
-
 fn resolved_symbol_json(symbol: &resolve::SymbolInfo) -> String {
     json_object(vec![
         ("name", json_string(&symbol.name)),
@@ -1153,7 +1546,9 @@ mod cli_tests {
                 effect_row: vec!["io".into()],
                 body: ast::Block {
                     statements: Vec::new(),
+                    spans: Vec::new(),
                 },
+                span: ast::Span::default(),
             })],
         };
 
@@ -1174,6 +1569,212 @@ mod cli_tests {
         assert_eq!(spec.capabilities(), &[String::from("io")]);
     }
 
+    #[test]
+    fn verify_ir_flag_defaults_to_verification_only() {
+        let args = ["--verify-ir", "demo.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(parsed.verify_ir);
+        assert!(matches!(parsed.command, CommandKind::VerifyIr));
+
+        let args = ["--verify-ir", "--llvm", "demo.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(parsed.verify_ir);
+        assert!(matches!(parsed.command, CommandKind::Llvm));
+    }
+
+    #[test]
+    fn extra_inputs_are_linked_only_for_build_and_run() {
+        let args = ["--run", "app.mica", "math.mica", "util.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert_eq!(parsed.input_path, PathBuf::from("app.mica"));
+        assert_eq!(
+            parsed.linked_paths,
+            vec![PathBuf::from("math.mica"), PathBuf::from("util.mica")]
+        );
+
+        let args = ["--ir", "app.mica", "math.mica"].map(String::from);
+        assert!(CliArgs::parse(args.into_iter()).is_err());
+        let args = ["--build", "app.mica", "-O"].map(String::from);
+        assert!(CliArgs::parse(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn provenance_flag_is_limited_to_ir_output() {
+        let args = ["--ir-json", "--provenance", "demo.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(parsed.provenance);
+        assert!(matches!(parsed.command, CommandKind::IrJson));
+
+        let args = ["--provenance", "--llvm", "demo.mica"].map(String::from);
+        let err = CliArgs::parse(args.into_iter())
+            .err()
+            .expect("--provenance needs an IR mode");
+        assert!(err.message.contains("--provenance is only supported"));
+    }
+
+    #[test]
+    fn optimize_flag_applies_to_backend_options() {
+        let args = ["-O", "--llvm", "demo.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(parsed.optimize);
+        assert!(matches!(parsed.command, CommandKind::Llvm));
+
+        let ctx = CommandContext::new(
+            parsed.input_path,
+            String::new(),
+            parsed.pretty,
+            parsed.verify_ir,
+            parsed.optimize,
+            parsed.from_ir,
+        );
+        assert!(ctx.backend_options().optimize);
+    }
+
+    #[test]
+    fn bytecode_flags_select_emit_and_run_modes() {
+        let args = ["--emit-bytecode", "--out", "app.micab", "demo.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(matches!(
+            parsed.command,
+            CommandKind::EmitBytecode { output: Some(ref path) } if path == &PathBuf::from("app.micab")
+        ));
+
+        let args = ["--from-ir", "--run-bytecode", "demo.ir"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(parsed.from_ir);
+        assert!(matches!(parsed.command, CommandKind::RunBytecode));
+    }
+
+    #[test]
+    fn elf_flags_select_object_output_and_the_x86_64_backend() {
+        let args = ["--emit-obj", "demo.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(matches!(
+            parsed.command,
+            CommandKind::EmitObject { output: None }
+        ));
+
+        let args = ["--run", "--elf", "demo.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(parsed.elf);
+        assert!(matches!(parsed.command, CommandKind::Run { .. }));
+
+        let args = ["--wat", "--elf", "demo.mica"].map(String::from);
+        let err = CliArgs::parse(args.into_iter())
+            .err()
+            .expect("--elf needs --build or --run");
+        assert!(
+            err.to_string()
+                .contains("--elf is only supported with --build and --run")
+        );
+    }
+
+    #[test]
+    fn build_lib_writes_into_an_output_directory() {
+        let args = [
+            "--build-lib",
+            "--out",
+            "dist",
+            "-g",
+            "geom.mica",
+            "util.mica",
+        ]
+        .map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(parsed.debug_info);
+        assert_eq!(parsed.linked_paths, vec![PathBuf::from("util.mica")]);
+        assert!(matches!(
+            parsed.command,
+            CommandKind::BuildLib { output: Some(ref path) } if path == &PathBuf::from("dist")
+        ));
+
+        let args = ["--build-lib", "--elf", "geom.mica"].map(String::from);
+        assert!(CliArgs::parse(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn cache_dir_applies_to_native_builds_and_pipeline_json() {
+        let args = ["--run", "--cache-dir", "build/cache", "demo.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert_eq!(parsed.cache_dir, Some(PathBuf::from("build/cache")));
+
+        let args = ["--pipeline-json", "--cache-dir", "cache", "demo.mica"].map(String::from);
+        assert!(CliArgs::parse(args.into_iter()).is_ok());
+        let args = ["--ir", "--cache-dir", "cache", "demo.mica"].map(String::from);
+        assert!(CliArgs::parse(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn debug_flags_enable_line_info_and_keep_the_c_source() {
+        let args = ["-g", "--build", "--keep-c", "demo.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(parsed.debug_info && parsed.keep_c);
+        let ctx = CommandContext::new(
+            PathBuf::from("demo.mica"),
+            String::new(),
+            false,
+            false,
+            false,
+            false,
+        )
+        .with_debug_info(parsed.debug_info);
+        assert!(ctx.backend_options().debug_info);
+
+        let args = ["-g", "--llvm", "demo.mica"].map(String::from);
+        assert!(CliArgs::parse(args.into_iter()).is_ok());
+
+        let rejected: [&[&str]; 2] = [
+            &["-g", "--wat", "demo.mica"],
+            &["-g", "--run", "--elf", "demo.mica"],
+        ];
+        for args in rejected {
+            let err = CliArgs::parse(args.iter().map(|arg| arg.to_string()))
+                .err()
+                .expect("-g needs a backend with line info");
+            assert!(err.to_string().contains("-g is only supported"), "{err}");
+        }
+
+        let args = ["--keep-c", "--run", "--elf", "demo.mica"].map(String::from);
+        let err = CliArgs::parse(args.into_iter())
+            .err()
+            .expect("--keep-c needs the C backend");
+        assert!(err.to_string().contains(
+            "--keep-c is only supported with --build, --build-lib and --run without --elf"
+        ));
+    }
+
+    #[test]
+    fn from_ir_flag_limits_modes_to_ir_consumers() {
+        let args = ["--from-ir", "demo.ir"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(parsed.from_ir);
+        assert!(matches!(parsed.command, CommandKind::Ir));
+
+        let args = ["--from-ir", "--interpret", "demo.ir"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(matches!(parsed.command, CommandKind::Interpret));
+
+        let args = ["--from-ir", "--check", "demo.ir"].map(String::from);
+        let err = CliArgs::parse(args.into_iter())
+            .err()
+            .expect("--check reads source");
+        assert!(err.message.contains("--from-ir only supports"));
+    }
+
+    #[test]
+    fn wasm_flag_takes_an_output_path() {
+        let args = ["--wasm", "--out", "app.wasm", "demo.mica"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(matches!(
+            parsed.command,
+            CommandKind::Wasm { output: Some(ref path) } if path == &PathBuf::from("app.wasm")
+        ));
+
+        let args = ["--from-ir", "--wat", "demo.ir"].map(String::from);
+        let parsed = CliArgs::parse(args.into_iter()).expect("parse args");
+        assert!(matches!(parsed.command, CommandKind::Wat));
+    }
+
     #[test]
     fn entry_task_spec_ignores_modules_without_main() {
         let module = ast::Module {
@@ -228,6 +228,27 @@ impl Runtime {
         Ok(())
     }
 
+    /// Performs a single capability operation on behalf of `spec` outside of
+    /// the scheduler. Executors that run code directly, such as the bytecode
+    /// VM, use this so capability checks match planned tasks.
+    pub fn invoke(
+        &self,
+        spec: &TaskSpec,
+        capability: &str,
+        operation: &str,
+        payload: Option<RuntimeValue>,
+    ) -> Result<ProviderResponse, RuntimeError> {
+        if !spec.has_capability(capability) {
+            return Err(RuntimeError::missing_capability(&spec.name, capability));
+        }
+        self.lookup_provider(capability)?
+            .handle(&CapabilityInvocation {
+                capability: capability.to_string(),
+                operation: operation.to_string(),
+                payload,
+            })
+    }
+
     fn execute_task(
         &self,
         spec: &TaskSpec,
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
 
 use crate::syntax::ast::*;
 
@@ -10,6 +11,155 @@ pub struct Diagnostic {
 #[derive(Debug, Default)]
 pub struct CheckResult {
     pub diagnostics: Vec<Diagnostic>,
+    pub types: ExprTypes,
+}
+
+/// Names an expression by its position in a pre-order walk of the module's
+/// functions, so a node keeps its ID in every clone of the module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(u32);
+
+/// The [`ExprId`] of every expression in a module. Nodes are looked up by
+/// address, which the borrow keeps valid for as long as the map lives.
+#[derive(Debug)]
+pub struct ExprIds<'m> {
+    ids: HashMap<*const Expr, ExprId>,
+    module: PhantomData<&'m Module>,
+}
+
+impl<'m> ExprIds<'m> {
+    pub fn new(module: &'m Module) -> Self {
+        let mut ids = Self {
+            ids: HashMap::new(),
+            module: PhantomData,
+        };
+        for item in &module.items {
+            match item {
+                Item::Function(func) => ids.number_block(&func.body),
+                Item::Impl(block) => {
+                    for ImplItem::Function(func) in &block.items {
+                        ids.number_block(&func.body);
+                    }
+                }
+                Item::TypeAlias(_) | Item::Use(_) => {}
+            }
+        }
+        ids
+    }
+
+    /// The ID of `expr`, or `None` for nodes outside the module.
+    pub fn get(&self, expr: &Expr) -> Option<ExprId> {
+        self.ids.get(&std::ptr::from_ref(expr)).copied()
+    }
+
+    fn number_block(&mut self, block: &'m Block) {
+        for stmt in &block.statements {
+            match stmt {
+                Stmt::Let(binding) => self.number(&binding.value),
+                Stmt::Expr(expr) | Stmt::Return(Some(expr)) => self.number(expr),
+                Stmt::Return(None) | Stmt::Break | Stmt::Continue => {}
+            }
+        }
+    }
+
+    fn number(&mut self, expr: &'m Expr) {
+        let id = ExprId(self.ids.len() as u32);
+        self.ids.insert(std::ptr::from_ref(expr), id);
+        match expr {
+            Expr::Block(block) => self.number_block(block),
+            Expr::Literal(_) | Expr::Path(_) | Expr::Chan { capacity: None, .. } => {}
+            Expr::Binary { lhs, rhs, .. } => {
+                self.number(lhs);
+                self.number(rhs);
+            }
+            Expr::Unary { expr, .. }
+            | Expr::Field { expr, .. }
+            | Expr::Cast { expr, .. }
+            | Expr::Spawn(expr)
+            | Expr::Await(expr)
+            | Expr::Try(expr)
+            | Expr::Loop { body: expr }
+            | Expr::Chan {
+                capacity: Some(expr),
+                ..
+            } => self.number(expr),
+            Expr::Call { callee, args } => {
+                self.number(callee);
+                args.iter().for_each(|arg| self.number(arg));
+            }
+            Expr::Ctor { args, .. } | Expr::List(args) => {
+                args.iter().for_each(|arg| self.number(arg));
+            }
+            Expr::Record { fields, .. } => {
+                fields.iter().for_each(|(_, value)| self.number(value));
+            }
+            Expr::Index { expr, index } => {
+                self.number(expr);
+                self.number(index);
+            }
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.number(condition);
+                self.number(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.number(else_branch);
+                }
+            }
+            Expr::Match { scrutinee, arms } => {
+                self.number(scrutinee);
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        self.number(guard);
+                    }
+                    self.number(&arm.body);
+                }
+            }
+            Expr::For { iterable, body, .. } => {
+                self.number(iterable);
+                self.number(body);
+            }
+            Expr::While { condition, body } => {
+                self.number(condition);
+                self.number(body);
+            }
+            Expr::Assignment { target, value } => {
+                self.number(target);
+                self.number(value);
+            }
+            Expr::Using { expr, body, .. } => {
+                self.number(expr);
+                self.number_block(body);
+            }
+        }
+    }
+}
+
+/// The resolved type of every expression the checker could type, keyed by
+/// [`ExprId`].
+#[derive(Debug, Default)]
+pub struct ExprTypes {
+    types: HashMap<ExprId, TypeExpr>,
+}
+
+impl ExprTypes {
+    pub fn get(&self, id: ExprId) -> Option<&TypeExpr> {
+        self.types.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    fn record(&mut self, id: ExprId, ty: TypeExpr) {
+        self.types.insert(id, ty);
+    }
 }
 
 pub fn check_module(module: &Module) -> CheckResult {
@@ -17,6 +167,7 @@ pub fn check_module(module: &Module) -> CheckResult {
     checker.run();
     CheckResult {
         diagnostics: checker.diagnostics,
+        types: checker.types,
     }
 }
 
@@ -121,6 +272,11 @@ fn visit_expr(expr: &Expr, adts: &[(String, Vec<String>)], diags: &mut Vec<Diagn
             visit_expr(expr, adts, diags);
             visit_expr(index, adts, diags)
         }
+        Expr::List(elements) => {
+            for element in elements {
+                visit_expr(element, adts, diags)
+            }
+        }
         Expr::If {
             condition,
             then_branch,
@@ -165,8 +321,11 @@ fn visit_expr(expr: &Expr, adts: &[(String, Vec<String>)], diags: &mut Vec<Diagn
 struct Checker<'a> {
     module: &'a Module,
     diagnostics: Vec<Diagnostic>,
+    ids: ExprIds<'a>,
+    types: ExprTypes,
     functions: HashMap<String, FunctionSig>,
     variants: HashMap<Vec<String>, VariantInfo>,
+    records: HashMap<String, Vec<(String, TypeRepr)>>,
 }
 
 impl<'a> Checker<'a> {
@@ -174,11 +333,15 @@ impl<'a> Checker<'a> {
         let mut checker = Self {
             module,
             diagnostics: Vec::new(),
+            ids: ExprIds::new(module),
+            types: ExprTypes::default(),
             functions: HashMap::new(),
             variants: HashMap::new(),
+            records: HashMap::new(),
         };
         checker.collect_signatures();
         checker.collect_variants();
+        checker.collect_records();
         checker
     }
 
@@ -213,6 +376,21 @@ impl<'a> Checker<'a> {
         }
     }
 
+    fn collect_records(&mut self) {
+        for item in &self.module.items {
+            if let Item::TypeAlias(alias) = item
+                && alias.params.is_empty()
+                && let TypeExpr::Record(fields) = &alias.value
+            {
+                let fields = fields
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), parse_type_expr(ty, &HashSet::new())))
+                    .collect();
+                self.records.insert(alias.name.clone(), fields);
+            }
+        }
+    }
+
     fn register_variants(&mut self, alias: &TypeAlias, variants: &[TypeVariant]) {
         let generics: HashSet<String> = alias.params.iter().cloned().collect();
         let mut module_parent = self.module.name.clone();
@@ -245,14 +423,20 @@ impl<'a> Checker<'a> {
     }
 
     fn check_function(&mut self, func: &Function, sig: FunctionSig) {
-        let checker = FunctionChecker::new(
-            &func.name,
-            sig,
-            &self.module.name,
-            &self.functions,
-            &self.variants,
-            &mut self.diagnostics,
-        );
+        let checker = FunctionChecker {
+            name: &func.name,
+            params: sig.params,
+            return_type: sig.return_type,
+            effects: sig.effects,
+            module_path: &self.module.name,
+            functions: &self.functions,
+            variants: &self.variants,
+            records: &self.records,
+            diagnostics: &mut self.diagnostics,
+            ids: &self.ids,
+            types: &mut self.types,
+            scopes: vec![HashMap::new()],
+        };
         checker.check(func);
     }
 }
@@ -308,32 +492,14 @@ struct FunctionChecker<'a, 'm> {
     module_path: &'m [String],
     functions: &'a HashMap<String, FunctionSig>,
     variants: &'a HashMap<Vec<String>, VariantInfo>,
+    records: &'a HashMap<String, Vec<(String, TypeRepr)>>,
     diagnostics: &'a mut Vec<Diagnostic>,
+    ids: &'a ExprIds<'m>,
+    types: &'a mut ExprTypes,
     scopes: Vec<HashMap<String, TypeRepr>>,
 }
 
 impl<'a, 'm> FunctionChecker<'a, 'm> {
-    fn new(
-        name: &'a str,
-        sig: FunctionSig,
-        module_path: &'m [String],
-        functions: &'a HashMap<String, FunctionSig>,
-        variants: &'a HashMap<Vec<String>, VariantInfo>,
-        diagnostics: &'a mut Vec<Diagnostic>,
-    ) -> Self {
-        Self {
-            name,
-            params: sig.params,
-            return_type: sig.return_type,
-            effects: sig.effects,
-            module_path,
-            functions,
-            variants,
-            diagnostics,
-            scopes: vec![HashMap::new()],
-        }
-    }
-
     fn check(mut self, func: &Function) {
         let mut seen_caps = HashSet::new();
         for cap in &self.effects {
@@ -467,7 +633,18 @@ impl<'a, 'm> FunctionChecker<'a, 'm> {
         Some(last_type)
     }
 
+    /// Checks `expr` and records its type when it is fully known.
     fn check_expr(&mut self, expr: &Expr) -> Option<TypeRepr> {
+        let ty = self.check_expr_kind(expr);
+        if let Some(id) = self.ids.get(expr)
+            && let Some(resolved) = ty.as_ref().and_then(TypeRepr::to_type_expr)
+        {
+            self.types.record(id, resolved);
+        }
+        ty
+    }
+
+    fn check_expr_kind(&mut self, expr: &Expr) -> Option<TypeRepr> {
         match expr {
             Expr::Block(block) => self.check_block(block),
             Expr::Literal(lit) => Some(match lit {
@@ -489,16 +666,62 @@ impl<'a, 'm> FunctionChecker<'a, 'm> {
             }
             Expr::Call { callee, args } => self.check_call(callee, args),
             Expr::Ctor { path, args } => self.check_ctor(path, args),
-            Expr::Record { fields, .. } => {
+            Expr::Record { type_path, fields } => {
                 let mut record_fields = Vec::new();
                 for (name, expr) in fields {
                     let ty = self.check_expr(expr).unwrap_or(TypeRepr::Unknown);
                     record_fields.push((name.clone(), ty));
                 }
-                Some(TypeRepr::Record(record_fields))
+                match type_path.as_ref().and_then(|path| path.segments.last()) {
+                    Some(name) => Some(TypeRepr::Named(vec![name.clone()], Vec::new())),
+                    None => Some(TypeRepr::Record(record_fields)),
+                }
+            }
+            Expr::Field { expr, name } => {
+                let base = self.check_expr(expr);
+                Some(self.field_type(base.as_ref(), name))
+            }
+            Expr::Index { expr, index } => {
+                let container = self.check_expr(expr);
+                if let Some(index_ty) = self.check_expr(index)
+                    && !types_compatible(&TypeRepr::Primitive(PrimitiveType::Int), &index_ty)
+                {
+                    self.diagnostics.push(Diagnostic {
+                        message: format!(
+                            "index in '{}' has type '{}' but must be Int",
+                            self.name,
+                            index_ty.describe()
+                        ),
+                    });
+                }
+                match container {
+                    Some(TypeRepr::List(inner)) => Some(*inner),
+                    other => other,
+                }
+            }
+            Expr::List(elements) => {
+                let mut element_ty: Option<TypeRepr> = None;
+                for element in elements {
+                    let ty = self.check_expr(element).unwrap_or(TypeRepr::Unknown);
+                    if let Some(existing) = &element_ty {
+                        if !types_compatible(existing, &ty) {
+                            self.diagnostics.push(Diagnostic {
+                                message: format!(
+                                    "list elements in '{}' have incompatible types '{}' and '{}'",
+                                    self.name,
+                                    existing.describe(),
+                                    ty.describe()
+                                ),
+                            });
+                        }
+                    } else {
+                        element_ty = Some(ty);
+                    }
+                }
+                Some(TypeRepr::List(Box::new(
+                    element_ty.unwrap_or(TypeRepr::Unknown),
+                )))
             }
-            Expr::Field { expr, .. } => self.check_expr(expr),
-            Expr::Index { expr, .. } => self.check_expr(expr),
             Expr::Cast { expr, ty } => {
                 self.check_expr(expr);
                 let parsed = parse_type_expr(ty, &HashSet::new());
@@ -573,7 +796,37 @@ impl<'a, 'm> FunctionChecker<'a, 'm> {
                 }
                 arm_type
             }
-            Expr::For { body, .. } | Expr::While { body, .. } | Expr::Loop { body } => {
+            Expr::For {
+                binding,
+                iterable,
+                body,
+            } => {
+                let element_ty = match self.check_expr(iterable) {
+                    Some(TypeRepr::List(inner)) => *inner,
+                    _ => TypeRepr::Unknown,
+                };
+                self.push_scope();
+                self.bind(binding.clone(), element_ty);
+                self.check_expr(body);
+                self.pop_scope();
+                Some(TypeRepr::Unit)
+            }
+            Expr::While { condition, body } => {
+                if let Some(cond_ty) = self.check_expr(condition)
+                    && !types_compatible(&TypeRepr::Primitive(PrimitiveType::Bool), &cond_ty)
+                {
+                    self.diagnostics.push(Diagnostic {
+                        message: format!(
+                            "while condition in '{}' is '{}' but must be Bool",
+                            self.name,
+                            cond_ty.describe()
+                        ),
+                    });
+                }
+                self.check_expr(body);
+                Some(TypeRepr::Unit)
+            }
+            Expr::Loop { body } => {
                 self.check_expr(body);
                 Some(TypeRepr::Unit)
             }
@@ -593,10 +846,20 @@ impl<'a, 'm> FunctionChecker<'a, 'm> {
                 }
                 Some(target_ty)
             }
-            Expr::Spawn(expr) | Expr::Await(expr) | Expr::Try(expr) => {
+            Expr::Spawn(expr) | Expr::Await(expr) => {
                 self.check_expr(expr);
                 Some(TypeRepr::Unknown)
             }
+            // `?` unwraps the success payload of a `Result` or `Option`.
+            Expr::Try(expr) => match self.check_expr(expr) {
+                Some(TypeRepr::Named(path, args))
+                    if matches!(path.last().map(String::as_str), Some("Result" | "Option"))
+                        && !args.is_empty() =>
+                {
+                    Some(args[0].clone())
+                }
+                _ => Some(TypeRepr::Unknown),
+            },
             Expr::Chan { ty, capacity } => {
                 if let Some(cap) = capacity {
                     self.check_expr(cap);
@@ -625,6 +888,68 @@ impl<'a, 'm> FunctionChecker<'a, 'm> {
         }
     }
 
+    /// Types the built-in list methods `len` and `push`; returns `None` when
+    /// `receiver` is not a list so the call is checked as an ordinary one.
+    fn check_list_method(
+        &mut self,
+        receiver: &Expr,
+        name: &str,
+        args: &[Expr],
+    ) -> Option<TypeRepr> {
+        if name != "len" && name != "push" {
+            return None;
+        }
+        let Some(TypeRepr::List(element_ty)) = self.check_expr(receiver) else {
+            return None;
+        };
+        let expected = if name == "len" { 0 } else { 1 };
+        if args.len() != expected {
+            self.diagnostics.push(Diagnostic {
+                message: format!(
+                    "list method '{}' in '{}' expected {} arguments but found {}",
+                    name,
+                    self.name,
+                    expected,
+                    args.len()
+                ),
+            });
+        }
+        for arg in args {
+            if let Some(actual) = self.check_expr(arg)
+                && !types_compatible(&element_ty, &actual)
+            {
+                self.diagnostics.push(Diagnostic {
+                    message: format!(
+                        "cannot push '{}' onto '[{}]' in '{}'",
+                        actual.describe(),
+                        element_ty.describe(),
+                        self.name
+                    ),
+                });
+            }
+        }
+        Some(if name == "len" {
+            TypeRepr::Primitive(PrimitiveType::Int)
+        } else {
+            TypeRepr::Unit
+        })
+    }
+
+    /// The type of field `name` on a record, or on a named record alias.
+    fn field_type(&self, base: Option<&TypeRepr>, name: &str) -> TypeRepr {
+        let fields = match base {
+            Some(TypeRepr::Record(fields)) => Some(fields),
+            Some(TypeRepr::Named(path, args)) if args.is_empty() => {
+                path.last().and_then(|alias| self.records.get(alias))
+            }
+            _ => None,
+        };
+        fields
+            .and_then(|fields| fields.iter().find(|(field, _)| field == name))
+            .map(|(_, ty)| ty.clone())
+            .unwrap_or(TypeRepr::Unknown)
+    }
+
     fn check_path(&mut self, path: &Path) -> Option<TypeRepr> {
         if path.segments.len() == 1 {
             let name = &path.segments[0];
@@ -725,6 +1050,23 @@ impl<'a, 'm> FunctionChecker<'a, 'm> {
     }
 
     fn check_call(&mut self, callee: &Expr, args: &[Expr]) -> Option<TypeRepr> {
+        if let Expr::Field { expr, name } = callee
+            && let Some(ty) = self.check_list_method(expr, name, args)
+        {
+            return Some(ty);
+        }
+        if let Expr::Field { expr, name } = callee
+            && matches!(**expr, Expr::Path(_))
+            && let Some(TypeRepr::Named(path, _)) = self.check_expr(expr)
+            && let Some(ty) = path
+                .last()
+                .and_then(|capability| capability_method_type(capability, name))
+        {
+            for arg in args {
+                self.check_expr(arg);
+            }
+            return Some(ty);
+        }
         let callee_ty = self.check_expr(callee);
         let Some(TypeRepr::Function {
             params,
@@ -806,10 +1148,12 @@ impl<'a, 'm> FunctionChecker<'a, 'm> {
                 });
             }
 
+            let mut bindings = HashMap::new();
             for (expected, expr) in info.fields.iter().zip(args.iter()) {
-                if let Some(actual) = self.check_expr(expr)
-                    && !types_compatible(expected, &actual)
-                {
+                let Some(actual) = self.check_expr(expr) else {
+                    continue;
+                };
+                if !types_compatible(expected, &actual) {
                     self.diagnostics.push(Diagnostic {
                         message: format!(
                             "constructor '{}' field expected '{}' but found '{}'",
@@ -819,9 +1163,10 @@ impl<'a, 'm> FunctionChecker<'a, 'm> {
                         ),
                     });
                 }
+                expected.bind_generics(&actual, &mut bindings);
             }
 
-            Some(info.ty)
+            Some(info.ty.instantiate(&bindings))
         } else {
             self.diagnostics.push(Diagnostic {
                 message: format!(
@@ -885,8 +1230,12 @@ impl<'a, 'm> FunctionChecker<'a, 'm> {
             }
             Pattern::EnumVariant { path, fields } => {
                 if let Some(info) = self.lookup_variant(&path.segments).cloned() {
+                    let mut bindings = HashMap::new();
+                    if let Some(ty) = ty {
+                        info.ty.bind_generics(ty, &mut bindings);
+                    }
                     for (pat, field_ty) in fields.iter().zip(info.fields.iter()) {
-                        self.bind_pattern(pat, Some(field_ty));
+                        self.bind_pattern(pat, Some(&field_ty.instantiate(&bindings)));
                     }
                 } else {
                     self.diagnostics.push(Diagnostic {
@@ -995,6 +1344,135 @@ impl TypeRepr {
     }
 }
 
+impl TypeRepr {
+    /// The type as a type expression for later stages, or `None` while any
+    /// part of it is unknown, generic, or a function.
+    fn to_type_expr(&self) -> Option<TypeExpr> {
+        Some(match self {
+            TypeRepr::Unit => TypeExpr::Unit,
+            TypeRepr::Primitive(p) => TypeExpr::Name(
+                match p {
+                    PrimitiveType::Int => "Int",
+                    PrimitiveType::Float => "Float",
+                    PrimitiveType::Bool => "Bool",
+                    PrimitiveType::String => "String",
+                }
+                .into(),
+            ),
+            TypeRepr::Named(path, args) => {
+                let name = path.last()?.clone();
+                if name == "<sum>" {
+                    return None;
+                }
+                if args.is_empty() {
+                    TypeExpr::Name(name)
+                } else {
+                    let args = args
+                        .iter()
+                        .map(TypeRepr::to_type_expr)
+                        .collect::<Option<_>>()?;
+                    TypeExpr::Generic(name, args)
+                }
+            }
+            TypeRepr::Tuple(items) => TypeExpr::Tuple(
+                items
+                    .iter()
+                    .map(TypeRepr::to_type_expr)
+                    .collect::<Option<_>>()?,
+            ),
+            TypeRepr::List(inner) => TypeExpr::List(Box::new(inner.to_type_expr()?)),
+            TypeRepr::Record(fields) => TypeExpr::Record(
+                fields
+                    .iter()
+                    .map(|(name, ty)| Some((name.clone(), ty.to_type_expr()?)))
+                    .collect::<Option<_>>()?,
+            ),
+            TypeRepr::Function { .. } | TypeRepr::Generic(_) | TypeRepr::Unknown => return None,
+        })
+    }
+}
+
+impl TypeRepr {
+    /// Records what each generic parameter in `self` stands for in `actual`.
+    fn bind_generics(&self, actual: &TypeRepr, bindings: &mut HashMap<String, TypeRepr>) {
+        match (self, actual) {
+            (_, TypeRepr::Unknown) => {}
+            (TypeRepr::Generic(name), _) => {
+                bindings
+                    .entry(name.clone())
+                    .or_insert_with(|| actual.clone());
+            }
+            (TypeRepr::Named(_, a_args), TypeRepr::Named(_, b_args))
+            | (TypeRepr::Tuple(a_args), TypeRepr::Tuple(b_args)) => {
+                for (a, b) in a_args.iter().zip(b_args.iter()) {
+                    a.bind_generics(b, bindings);
+                }
+            }
+            (TypeRepr::List(a_inner), TypeRepr::List(b_inner)) => {
+                a_inner.bind_generics(b_inner, bindings)
+            }
+            (TypeRepr::Record(a_fields), TypeRepr::Record(b_fields)) => {
+                for ((_, a), (_, b)) in a_fields.iter().zip(b_fields.iter()) {
+                    a.bind_generics(b, bindings);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces generic parameters with their bindings; parameters nothing
+    /// was bound to become unknown.
+    fn instantiate(&self, bindings: &HashMap<String, TypeRepr>) -> TypeRepr {
+        match self {
+            TypeRepr::Generic(name) => bindings.get(name).cloned().unwrap_or(TypeRepr::Unknown),
+            TypeRepr::Named(path, args) => TypeRepr::Named(
+                path.clone(),
+                args.iter().map(|arg| arg.instantiate(bindings)).collect(),
+            ),
+            TypeRepr::Tuple(items) => TypeRepr::Tuple(
+                items
+                    .iter()
+                    .map(|item| item.instantiate(bindings))
+                    .collect(),
+            ),
+            TypeRepr::List(inner) => TypeRepr::List(Box::new(inner.instantiate(bindings))),
+            TypeRepr::Record(fields) => TypeRepr::Record(
+                fields
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), ty.instantiate(bindings)))
+                    .collect(),
+            ),
+            TypeRepr::Function {
+                params,
+                return_type,
+                effects,
+            } => TypeRepr::Function {
+                params: params
+                    .iter()
+                    .map(|param| param.instantiate(bindings))
+                    .collect(),
+                return_type: Box::new(return_type.instantiate(bindings)),
+                effects: effects.clone(),
+            },
+            TypeRepr::Unit | TypeRepr::Primitive(_) | TypeRepr::Unknown => self.clone(),
+        }
+    }
+}
+
+/// The result of a runtime operation on a capability parameter, such as
+/// `io.println(..)` on an `IO`.
+fn capability_method_type(capability: &str, method: &str) -> Option<TypeRepr> {
+    let string = TypeRepr::Primitive(PrimitiveType::String);
+    Some(match (capability.to_ascii_lowercase().as_str(), method) {
+        ("io", "println" | "write_line") => TypeRepr::Unit,
+        ("time", "now_millis") => TypeRepr::Primitive(PrimitiveType::Int),
+        ("fs", "read_to_string") | ("env", "get") | ("net", "fetch") => string,
+        ("fs", "write_string") | ("env", "set" | "unset") => TypeRepr::Unit,
+        ("process", "spawn") => TypeRepr::Primitive(PrimitiveType::Int),
+        _ => return None,
+    })
+}
+
 fn types_compatible(expected: &TypeRepr, actual: &TypeRepr) -> bool {
     match (expected, actual) {
         (_, TypeRepr::Unknown) | (TypeRepr::Unknown, _) => true,
@@ -213,6 +213,11 @@ impl<'a, 'g> Resolver<'a, 'g> {
                 self.resolve_expr(expr);
                 self.resolve_expr(index);
             }
+            Expr::List(elements) => {
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            }
             Expr::Cast { expr, ty } => {
                 self.resolve_expr(expr);
                 self.resolve_type_expr(ty);
@@ -2,6 +2,8 @@
 
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 /// A complete Mica module produced by the parser.
 #[derive(Debug, Clone)]
 pub struct Module {
@@ -67,6 +69,7 @@ pub struct Function {
     pub return_type: Option<TypeExpr>,
     pub effect_row: Vec<String>,
     pub body: Block,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -85,6 +88,9 @@ pub struct Param {
 #[derive(Debug, Clone)]
 pub struct Block {
     pub statements: Vec<Stmt>,
+    /// Source span of each statement, in step with `statements`. Blocks
+    /// built outside the parser may leave it empty.
+    pub spans: Vec<Span>,
 }
 
 #[derive(Debug, Clone)]
@@ -137,6 +143,7 @@ pub enum Expr {
         expr: Box<Expr>,
         index: Box<Expr>,
     },
+    List(Vec<Expr>),
     Cast {
         expr: Box<Expr>,
         ty: TypeExpr,
@@ -180,7 +187,23 @@ pub enum Expr {
     Try(Box<Expr>),
 }
 
-#[derive(Debug, Clone)]
+/// A byte range of the source, with the 1-based line and byte column of its
+/// start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Path {
     pub segments: Vec<String>,
 }
@@ -214,7 +237,7 @@ pub enum Pattern {
     EnumVariant { path: Path, fields: Vec<Pattern> },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Literal {
     Int(i64),
     Float(f64),
@@ -223,7 +246,7 @@ pub enum Literal {
     Unit,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BinaryOp {
     Add,
     Sub,
@@ -5,17 +5,26 @@ use crate::syntax::token::{Token, TokenKind};
 
 pub fn parse_module(source: &str) -> Result<Module> {
     let tokens = lexer::lex(source)?;
-    Parser::new(tokens).parse_module()
+    Parser::new(tokens, source).parse_module()
 }
 
 struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    /// Byte offset at which each source line starts.
+    line_starts: Vec<usize>,
 }
 
 impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+    fn new(tokens: Vec<Token>, source: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self {
+            tokens,
+            pos: 0,
+            line_starts,
+        }
     }
 
     fn parse_module(&mut self) -> Result<Module> {
@@ -183,6 +192,8 @@ impl Parser {
     }
 
     fn parse_function(&mut self, is_public: bool) -> Result<Function> {
+        // The `fn` keyword has already been consumed.
+        let start = self.pos - 1;
         let name = self.expect_identifier()?;
         let generics = if self.match_symbol(TokenKind::LBracket) {
             let mut params = Vec::new();
@@ -307,17 +318,21 @@ impl Parser {
             return_type,
             effect_row,
             body,
+            span: self.span_from(start),
         })
     }
 
     fn parse_block(&mut self) -> Result<Block> {
         self.expect_symbol(TokenKind::LBrace, "expected '{' to start block")?;
         let mut statements = Vec::new();
+        let mut spans = Vec::new();
         while !self.check(TokenKind::RBrace) && !self.check(TokenKind::Eof) {
+            let start = self.pos;
             statements.push(self.parse_statement()?);
+            spans.push(self.span_from(start));
         }
         self.expect_symbol(TokenKind::RBrace, "expected '}' to close block")?;
-        Ok(Block { statements })
+        Ok(Block { statements, spans })
     }
 
     fn parse_statement(&mut self) -> Result<Stmt> {
@@ -622,7 +637,7 @@ impl Parser {
                     break;
                 }
             } else if self.match_symbol(TokenKind::Dot) {
-                let name = self.expect_identifier()?;
+                let name = self.expect_member_name()?;
                 expr = Expr::Field {
                     expr: Box::new(expr),
                     name,
@@ -746,6 +761,25 @@ impl Parser {
                     body,
                 })
             }
+            TokenKind::LBracket => {
+                // list literal: [a, b, c]
+                self.advance();
+                let mut elements = Vec::new();
+                if !self.check(TokenKind::RBracket) {
+                    loop {
+                        elements.push(self.parse_expression()?);
+                        if self.match_symbol(TokenKind::Comma) {
+                            if self.check(TokenKind::RBracket) {
+                                break;
+                            }
+                            continue;
+                        }
+                        break;
+                    }
+                }
+                self.expect_symbol(TokenKind::RBracket, "expected ']' after list elements")?;
+                Ok(Expr::List(elements))
+            }
             TokenKind::LBrace => {
                 let block = self.parse_block()?;
                 Ok(Expr::Block(block))
@@ -1130,6 +1164,16 @@ impl Parser {
         }
     }
 
+    /// Member names after `.` may reuse the `spawn` keyword so that
+    /// `process.spawn(...)` reads like every other capability call.
+    fn expect_member_name(&mut self) -> Result<String> {
+        if self.check(TokenKind::Spawn) {
+            self.advance();
+            return Ok("spawn".to_string());
+        }
+        self.expect_identifier()
+    }
+
     fn parse_module_path(&mut self) -> Result<Vec<String>> {
         let mut segments = Vec::new();
         segments.push(self.expect_identifier()?);
@@ -1157,6 +1201,22 @@ impl Parser {
         token
     }
 
+    /// Span from the token at index `start` through the last consumed token.
+    fn span_from(&self, start: usize) -> Span {
+        let begin = self.tokens[start].span.0;
+        let end = self.tokens[..self.pos]
+            .last()
+            .map_or(begin, |token| token.span.1)
+            .max(begin);
+        let line = self.line_starts.partition_point(|&offset| offset <= begin);
+        Span {
+            start: begin,
+            end,
+            line: line as u32,
+            column: (begin - self.line_starts[line - 1] + 1) as u32,
+        }
+    }
+
     fn error_here<S: Into<String>>(&self, message: S) -> Error {
         Error::parse(Some(self.current_span()), message)
     }
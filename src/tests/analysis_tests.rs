@@ -0,0 +1,325 @@
+use super::*;
+
+use ir::BlockId;
+use ir::analysis::{
+    AnalysisManager, ControlFlowGraph, DominanceFrontiers, DominatorTree, Liveness, LoopInfo,
+};
+
+fn parse_ir(text: &str) -> ir::Module {
+    ir::parse::parse_module(text).expect("parse IR")
+}
+
+fn bb(index: u32) -> BlockId {
+    BlockId(index)
+}
+
+fn sorted(blocks: &[BlockId]) -> Vec<u32> {
+    let mut indices: Vec<u32> = blocks.iter().map(|block| block.index()).collect();
+    indices.sort();
+    indices
+}
+
+fn value_indices<'a>(values: impl IntoIterator<Item = &'a ir::ValueId>) -> Vec<u32> {
+    let mut indices: Vec<u32> = values.into_iter().map(|value| value.index()).collect();
+    indices.sort();
+    indices
+}
+
+const NESTED_LOOPS: &str = r#"
+module demo
+
+fn main(c: Bool, n: Int) -> Int
+  block 0:
+    jump bb1
+  block 1:
+    branch %0 -> bb2, bb5
+  block 2:
+    branch %0 -> bb3, bb4
+  block 3:
+    jump bb2
+  block 4:
+    jump bb1
+  block 5:
+    return %1
+  block 6:
+    jump bb5
+"#;
+
+#[test]
+fn cfg_and_dominators_describe_nested_loops() {
+    let module = parse_ir(NESTED_LOOPS);
+    let function = &module.functions[0];
+    let cfg = ControlFlowGraph::new(function);
+
+    assert_eq!(cfg.entry(), bb(0));
+    assert_eq!(sorted(cfg.successors(bb(1))), vec![2, 5]);
+    assert_eq!(sorted(cfg.predecessors(bb(5))), vec![1, 6]);
+    assert!(!cfg.is_reachable(bb(6)));
+    assert_eq!(cfg.reverse_postorder().len(), 6);
+    assert_eq!(cfg.reverse_postorder()[0], bb(0));
+
+    let dominators = DominatorTree::new(&cfg);
+    assert_eq!(dominators.immediate_dominator(bb(0)), None);
+    assert_eq!(dominators.immediate_dominator(bb(4)), Some(bb(2)));
+    assert_eq!(dominators.immediate_dominator(bb(5)), Some(bb(1)));
+    assert_eq!(dominators.immediate_dominator(bb(6)), None);
+    assert_eq!(sorted(dominators.children(bb(1))), vec![2, 5]);
+    assert!(dominators.dominates(bb(1), bb(3)));
+    assert!(dominators.dominates(bb(3), bb(3)));
+    assert!(!dominators.strictly_dominates(bb(3), bb(3)));
+    assert!(!dominators.dominates(bb(2), bb(5)));
+
+    let frontiers = DominanceFrontiers::new(&cfg, &dominators);
+    assert_eq!(sorted(frontiers.frontier(bb(3))), vec![2]);
+    assert_eq!(sorted(frontiers.frontier(bb(2))), vec![1, 2]);
+    assert_eq!(sorted(frontiers.frontier(bb(4))), vec![1]);
+    assert!(frontiers.frontier(bb(0)).is_empty());
+}
+
+#[test]
+fn loop_info_finds_nesting_latches_and_exits() {
+    let module = parse_ir(NESTED_LOOPS);
+    let function = &module.functions[0];
+    let cfg = ControlFlowGraph::new(function);
+    let loops = LoopInfo::new(&cfg, &DominatorTree::new(&cfg));
+
+    assert_eq!(loops.loops().len(), 2);
+    let outer = &loops.loops()[0];
+    assert_eq!(outer.header, bb(1));
+    assert_eq!(sorted(&outer.blocks), vec![1, 2, 3, 4]);
+    assert_eq!(outer.blocks[0], bb(1), "header comes first");
+    assert_eq!(outer.latches, vec![bb(4)]);
+    assert_eq!(outer.exits, vec![bb(5)]);
+    assert_eq!((outer.parent, outer.depth), (None, 1));
+
+    let inner = &loops.loops()[1];
+    assert_eq!(inner.header, bb(2));
+    assert_eq!(sorted(&inner.blocks), vec![2, 3]);
+    assert_eq!(inner.exits, vec![bb(4)]);
+    assert_eq!((inner.parent, inner.depth), (Some(0), 2));
+
+    assert_eq!(loops.depth(bb(3)), 2);
+    assert_eq!(loops.depth(bb(4)), 1);
+    assert_eq!(loops.depth(bb(5)), 0);
+    assert_eq!(loops.loop_for(bb(3)).map(|lp| lp.header), Some(bb(2)));
+    assert!(loops.is_header(bb(1)) && !loops.is_header(bb(3)));
+}
+
+#[test]
+fn loop_info_matches_lowered_while_loops() {
+    let src = r#"
+module demo
+
+fn main() -> Int {
+  let mut total = 0
+  let mut i = 0
+  while i < 3 {
+    let mut j = 0
+    while j < 3 {
+      total = total + j
+      j = j + 1
+    }
+    i = i + 1
+  }
+  total
+}
+"#;
+    let module = ir::lower_module(&helpers::lower_checked(&helpers::parse(src)));
+    let function = &module.functions[0];
+    let cfg = ControlFlowGraph::new(function);
+    let loops = LoopInfo::new(&cfg, &DominatorTree::new(&cfg));
+
+    let depths: Vec<u32> = loops.loops().iter().map(|lp| lp.depth).collect();
+    assert_eq!(depths, vec![1, 2]);
+    assert_eq!(loops.depth(function.blocks[0].id), 0);
+    // Both loop headers carry the phis SSA promotion placed for the
+    // variables updated inside them.
+    for lp in loops.loops() {
+        let header = function
+            .blocks
+            .iter()
+            .find(|block| block.id == lp.header)
+            .expect("header block");
+        assert!(matches!(
+            header.instructions.first().map(|inst| &inst.kind),
+            Some(ir::InstKind::Phi { .. })
+        ));
+    }
+}
+
+#[test]
+fn liveness_treats_phi_operands_as_edge_uses() {
+    let module = parse_ir(
+        r#"
+module demo
+
+fn pick(c: Bool, a: Int) -> Int
+  block 0:
+    %2 = 1 : Int
+    branch %0 -> bb1, bb2
+  block 1:
+    %3 = + %1, %2 : Int
+    jump bb3
+  block 2:
+    jump bb3
+  block 3:
+    %4 = phi { bb1: %3, bb2: %2 } : Int
+    %5 = + %4, %1 : Int
+    return %5
+"#,
+    );
+    let function = &module.functions[0];
+    let cfg = ControlFlowGraph::new(function);
+    let liveness = Liveness::new(function, &cfg);
+
+    assert_eq!(value_indices(liveness.live_in(bb(0))), vec![0, 1]);
+    assert_eq!(value_indices(liveness.live_in(bb(1))), vec![1, 2]);
+    assert_eq!(value_indices(liveness.live_out(bb(1))), vec![1, 3]);
+    assert_eq!(value_indices(liveness.live_out(bb(2))), vec![1, 2]);
+    assert_eq!(value_indices(liveness.live_in(bb(3))), vec![1]);
+    assert!(liveness.live_out(bb(3)).is_empty());
+    assert!(!liveness.is_live_in(function.blocks[3].instructions[0].id, bb(3)));
+}
+
+#[test]
+fn analysis_manager_caches_until_invalidated() {
+    let module = parse_ir(NESTED_LOOPS);
+    let function = &module.functions[0];
+    let mut analyses = AnalysisManager::new();
+
+    let loops = analyses.loops(function);
+    // Loops need the CFG and dominator tree, which are now cached too.
+    assert_eq!(analyses.stats().computed, 3);
+    let dominators = analyses.dominators(function);
+    assert!(std::sync::Arc::ptr_eq(&loops, &analyses.loops(function)));
+    assert_eq!(analyses.stats().computed, 3);
+    assert_eq!(analyses.stats().cached, 3);
+
+    analyses.invalidate(&function.name);
+    let rebuilt = analyses.dominators(function);
+    assert!(!std::sync::Arc::ptr_eq(&dominators, &rebuilt));
+    assert_eq!(analyses.stats().computed, 5);
+    assert_eq!(analyses.stats().invalidations, 1);
+
+    // The pass manager shares its cache across passes, so functions that a
+    // pass leaves untouched keep their analyses.
+    let src = r#"
+module demo
+
+fn helper(x: Int) -> Int {
+  x + 1
+}
+
+fn main() -> Int {
+  helper(3) * 2
+}
+"#;
+    let mut module = ir::lower_module(&helpers::lower_checked(&helpers::parse(src)));
+    let report = ir::passes::PassManager::new()
+        .with_pass(ir::passes::CommonSubexpressionElimination)
+        .with_pass(ir::passes::DeadCodeElimination)
+        .run(&mut module);
+    assert!(!report.changed());
+    assert!(report.analyses.cached > 0, "{:?}", report.analyses);
+}
+
+const CALL_GRAPH_SRC: &str = r#"
+module demo
+
+fn fact(n: Int) -> Int {
+  if n <= 1 { 1 } else { n * fact(n - 1) }
+}
+
+fn is_even(n: Int) -> Bool {
+  if n == 0 { true } else { is_odd(n - 1) }
+}
+
+fn is_odd(n: Int) -> Bool {
+  if n == 0 { false } else { is_even(n - 1) }
+}
+
+fn shout(io: IO) !{io} {
+  io.println("value")
+}
+
+fn relay(io: IO) {
+  shout(io)
+}
+
+fn report(io: IO, n: Int) -> Int {
+  relay(io)
+  n
+}
+
+fn main(io: IO) -> Int {
+  let parity = if is_even(4) { 1 } else { 0 }
+  fact(5) + report(io, parity)
+}
+"#;
+
+#[test]
+fn call_graph_groups_recursion_into_bottom_up_sccs() {
+    let module = ir::lower_module(&helpers::lower_checked(&helpers::parse(CALL_GRAPH_SRC)));
+    let graph = ir::analysis::CallGraph::new(&module);
+
+    assert_eq!(graph.callees("main"), vec!["is_even", "fact", "report"]);
+    assert_eq!(graph.callers("shout"), vec!["relay"]);
+    assert_eq!(graph.unresolved_calls("shout"), [".println".to_string()]);
+    assert!(graph.is_recursive("fact"));
+    assert!(graph.is_recursive("is_odd"));
+    assert!(!graph.is_recursive("report"));
+    assert_eq!(graph.scc_of("is_even"), graph.scc_of("is_odd"));
+
+    let sccs = graph.sccs();
+    assert!(sccs.contains(&vec!["is_even", "is_odd"]));
+    for name in graph.functions() {
+        for callee in graph.callees(name) {
+            assert!(
+                graph.scc_of(callee) <= graph.scc_of(name),
+                "{callee} must come before its caller {name}"
+            );
+        }
+    }
+}
+
+#[test]
+fn effect_summaries_propagate_effects_and_purity_to_callers() {
+    let module = ir::lower_module(&helpers::lower_checked(&helpers::parse(CALL_GRAPH_SRC)));
+    let summaries = ir::analysis::EffectSummaries::new(&module);
+
+    let report = summaries.get("report").expect("report summary");
+    // `relay` declares no effects, so the call in `report` carries none and
+    // only the summary sees the `io` effect two calls down.
+    assert!(report.locally_pure);
+    assert!(!report.pure);
+    assert_eq!(report.effects, vec!["io".to_string()]);
+    let main_fn = summaries.get("main").expect("main summary");
+    assert_eq!(main_fn.effects, vec!["io".to_string()]);
+    assert!(!main_fn.pure);
+
+    let even = summaries.get("is_even").expect("is_even summary");
+    assert!(even.pure && even.recursive);
+    assert_eq!(summaries.sccs[even.scc], vec!["is_even", "is_odd"]);
+    assert_eq!(
+        summaries.pure_functions(),
+        vec!["fact", "is_even", "is_odd"]
+    );
+
+    let text = summaries.to_string();
+    assert!(text.starts_with("module demo\n"), "{text}");
+    assert!(
+        text.contains("fn is_even: pure, recursive {is_even, is_odd}\n  calls is_odd\n"),
+        "{text}"
+    );
+    assert!(
+        text.contains("fn shout: impure !{io}\n  unresolved .println\n"),
+        "{text}"
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_str(&summaries.to_json()).expect("summaries JSON");
+    let functions = json["functions"].as_array().expect("functions array");
+    assert_eq!(functions.len(), module.functions.len());
+    assert_eq!(functions.last().unwrap()["name"], "main");
+    assert_eq!(functions.last().unwrap()["effects"][0], "io");
+}
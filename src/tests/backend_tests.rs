@@ -18,7 +18,7 @@ fn transform(x: Int, io: IO) -> Int !{io} {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let text_backend = backend::text::TextBackend;
     let output = backend::run(
@@ -57,7 +57,7 @@ fn build(flag: Bool) {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let text_backend = backend::text::TextBackend;
     let output = backend::run(
@@ -82,6 +82,63 @@ fn build(flag: Bool) {
     );
 }
 
+#[test]
+fn dot_backend_renders_cfgs_and_call_graph() {
+    let src = r#"
+module backend.dot
+
+fn shout(io: IO) !{io} {
+  io.println("say \"hi\"")
+}
+
+fn pick(flag: Bool) -> Int {
+  if flag { 1 } else { 2 }
+}
+
+fn main(io: IO) -> Int {
+  shout(io)
+  pick(true)
+}
+"#;
+
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+    let output = backend::run(
+        &backend::dot::DotBackend,
+        &ir_module,
+        &backend::BackendOptions::default(),
+    )
+    .expect("backend output");
+
+    assert!(output.starts_with("digraph \"backend.dot\" {"), "{output}");
+    assert!(output.contains("subgraph \"cluster_pick\""), "{output}");
+    assert!(output.contains("\"pick.bb0\" -> \"pick.bb1\" [label=\"true\"];"));
+    assert!(output.contains("\"pick.bb0\" -> \"pick.bb2\" [label=\"false\"];"));
+    // The string literal's quotes are escaped inside the label.
+    assert!(output.contains(r#"\"say \\\"hi\\\"\""#), "{output}");
+    let shout_block = output
+        .lines()
+        .find(|line| line.contains("\"shout.bb0\" ["))
+        .expect("shout block node");
+    assert!(
+        shout_block.contains("fillcolor=\"#f9d6d5\""),
+        "{shout_block}"
+    );
+    let pick_entry = output
+        .lines()
+        .find(|line| line.contains("\"pick.bb0\" ["))
+        .expect("pick entry node");
+    assert!(pick_entry.contains("fillcolor=\"#d9f2d9\""), "{pick_entry}");
+
+    let graph = backend::dot::render_call_graph(&ir_module);
+    assert!(graph.contains("\"main\" -> \"shout\";"), "{graph}");
+    assert!(graph.contains("\"main\" -> \"pick\";"), "{graph}");
+    assert!(graph.contains("\"shout\" -> \"?.println\" [style=dashed];"));
+    assert!(graph.contains("\"pick\" [label=\"pick\", fillcolor=\"#d9f2d9\"];"));
+    assert!(graph.contains("\"main\" [label=\"main\\n!{io}\", fillcolor=\"#f9d6d5\"];"));
+}
+
 #[test]
 fn llvm_backend_renders_integer_arithmetic() {
     let src = r#"
@@ -94,7 +151,7 @@ fn transform(x: Int, io: IO) -> Int !{io} {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let llvm_backend = backend::llvm::LlvmBackend::default();
     let output = backend::run(
@@ -109,9 +166,9 @@ fn transform(x: Int, io: IO) -> Int !{io} {
     assert!(ir.contains("define i64 @transform(i64 %x, ptr %io)"));
     assert!(ir.contains("; effects: io"));
     assert!(ir.contains("bb0:"));
-    assert!(ir.contains("%2 = add i64 0, 1"));
-    assert!(ir.contains("%3 = add i64 %0, %2"));
-    assert!(ir.contains("ret i64 %3"));
+    assert!(ir.contains("%0 = add i64 0, 1"));
+    assert!(ir.contains("%1 = add i64 %x, %0"));
+    assert!(ir.contains("ret i64 %1"));
 }
 
 #[test]
@@ -129,7 +186,7 @@ fn choose(flag: Bool) -> Int {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let llvm_backend = backend::llvm::LlvmBackend::default();
     let output = backend::run(
@@ -140,9 +197,9 @@ fn choose(flag: Bool) -> Int {
     .expect("backend output");
 
     let ir = output.as_str();
-    assert!(ir.contains("br i1 %0, label %bb1, label %bb2"));
-    assert!(ir.contains("%3 = phi i64 [ %1, %bb1 ], [ %2, %bb2 ]"));
-    assert!(ir.contains("ret i64 %3"));
+    assert!(ir.contains("br i1 %flag, label %bb1, label %bb2"));
+    assert!(ir.contains("%2 = phi i64 [ %0, %bb1 ], [ %1, %bb2 ]"));
+    assert!(ir.contains("ret i64 %2"));
 }
 
 #[test]
@@ -156,7 +213,7 @@ fn message() -> String {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let llvm_backend = backend::llvm::LlvmBackend::default();
     let output = backend::run(
@@ -168,7 +225,7 @@ fn message() -> String {
 
     let ir = output.as_str();
     assert!(ir.contains("@.str0 = private constant [3 x i8] c\"hi\\00\""));
-    assert!(ir.contains("%0 = getelementptr inbounds ([3 x i8], ptr @.str0, i32 0, i32 0)"));
+    assert!(ir.contains("%0 = getelementptr inbounds [3 x i8], ptr @.str0, i32 0, i32 0"));
     assert!(ir.contains("ret ptr %0"));
 }
 
@@ -183,7 +240,7 @@ fn noop() {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let llvm_backend = backend::llvm::LlvmBackend::default();
     let output = backend::run(
@@ -212,7 +269,7 @@ fn build() -> Data {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let llvm_backend = backend::llvm::LlvmBackend::default();
     let output = backend::run(
@@ -246,7 +303,7 @@ fn use_call() -> Vec2 {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let llvm_backend = backend::llvm::LlvmBackend::default();
     let output = backend::run(
@@ -275,7 +332,7 @@ fn build() -> Data {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let llvm_backend = backend::llvm::LlvmBackend::default();
     let err = backend::run(
@@ -308,7 +365,7 @@ fn make() {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let llvm_backend = backend::llvm::LlvmBackend::default();
     let err = backend::run(
@@ -343,7 +400,7 @@ fn main() -> Int {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let backend = backend::native::NativeBackend;
     let artifact = backend::run(&backend, &ir_module, &backend::BackendOptions::default())
@@ -380,7 +437,7 @@ fn caller() {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let backend = backend::native::NativeBackend;
     let artifact = backend::run(&backend, &ir_module, &backend::BackendOptions::default())
@@ -413,6 +470,796 @@ fn caller() {
     );
 }
 
+#[test]
+fn native_backend_runs_loops_over_mutable_locals() {
+    let src = r#"
+module backend.native_loops
+
+fn fib(n: Int) -> Int {
+  let mut a = 0
+  let mut b = 1
+  let mut k = 0
+  while k < n {
+    let t = a
+    a = b
+    b = t + b
+    k = k + 1
+  }
+  a
+}
+
+fn main() -> Int {
+  fib(10) - 55
+}
+"#;
+
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+    let backend = backend::native::NativeBackend;
+    let artifact = backend::run(&backend, &ir_module, &backend::BackendOptions::default())
+        .expect("native backend artifact");
+
+    let mut exe_path = std::env::temp_dir();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    exe_path.push(format!("mica-native-loops-{nanos}"));
+
+    artifact
+        .link_executable(&exe_path)
+        .expect("link executable");
+
+    let status = Command::new(&exe_path).status().expect("execute binary");
+    fs::remove_file(&exe_path).ok();
+    assert!(
+        status.success(),
+        "fib(10) should equal 55, process exit: {status}\n{}",
+        artifact.c_source
+    );
+}
+
+fn link_and_run(source: &str, name: &str) -> (std::process::Output, String) {
+    let module = parse(source);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+    let backend = backend::native::NativeBackend;
+    let artifact = backend::run(&backend, &ir_module, &backend::BackendOptions::default())
+        .expect("native backend artifact");
+
+    let mut exe_path = std::env::temp_dir();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    exe_path.push(format!("mica-{name}-{nanos}"));
+
+    artifact
+        .link_executable(&exe_path)
+        .expect("link executable");
+
+    let output = Command::new(&exe_path).output().expect("execute binary");
+    fs::remove_file(&exe_path).ok();
+    (output, artifact.c_source)
+}
+
+fn run_checking_leaks(
+    artifact: &backend::native::NativeArtifact,
+    name: &str,
+) -> std::process::Output {
+    let mut exe_path = std::env::temp_dir();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    exe_path.push(format!("mica-{name}-{nanos}"));
+    artifact
+        .link_executable(&exe_path)
+        .expect("link executable");
+    let output = Command::new(&exe_path)
+        .env("MICA_LEAK_CHECK", "1")
+        .output()
+        .expect("execute binary");
+    fs::remove_file(&exe_path).ok();
+    output
+}
+
+#[test]
+fn native_backend_frees_strings_lists_and_records() {
+    let src = r#"
+module backend.native_refcount
+
+type Tag = { label: String, weight: Int }
+type Item = { name: String, tag: Tag, parts: [String] }
+
+fn label(n: Int) -> String {
+  if n % 2 == 0 { "even" } else { "odd" }
+}
+
+fn build(n: Int) -> Item {
+  let mut name = "item"
+  let mut i = 0
+  while i < n {
+    name = name + "-" + label(i)
+    i = i + 1
+  }
+  Item { name: name, tag: Tag { label: label(n), weight: n }, parts: [name, label(n)] }
+}
+
+fn pick(flag: Bool, a: String, b: String) -> String {
+  if flag { a } else { b + "!" }
+}
+
+fn longest(items: [Item]) -> String {
+  let mut best = ""
+  for item in items {
+    if item.name == best { best = best } else { best = item.name }
+  }
+  best
+}
+
+fn main(io: IO, env: Env) -> Int !{io, env} {
+  let items = [build(1), build(2)]
+  items.push(build(3))
+  let first = items[0]
+  io.println(first.tag.label)
+  io.println(pick(true, first.name, "x"))
+  io.println(pick(false, first.name, "y"))
+  io.println(longest(items))
+  let nested = [["a", "b"], ["c"]]
+  let inner = nested[1]
+  inner.push("d")
+  io.println(inner[1])
+  env.set("MICA_REFCOUNT=counted")
+  io.println(env.get("MICA_REFCOUNT"))
+  items.len() + nested.len() + inner.len() - 7
+}
+"#;
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+    let mut artifact = backend::run(
+        &backend::native::NativeBackend,
+        &ir_module,
+        &backend::BackendOptions::default(),
+    )
+    .expect("native backend artifact");
+
+    let output = run_checking_leaks(&artifact, "native-refcount");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "odd\nitem-even\ny!\nitem-even-odd-even\nd\ncounted\n"
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(output.status.success(), "{}", artifact.c_source);
+
+    // Without its releases the same program trips the leak check.
+    artifact.c_source = artifact
+        .c_source
+        .replace("mica_string_release(v", "(void)(v");
+    let output = run_checking_leaks(&artifact, "native-refcount-leak");
+    assert_eq!(output.status.code(), Some(70));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("heap objects leaked"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn native_backend_builds_tagged_unions_for_sum_types() {
+    let src = r#"
+module backend.native_sums
+
+type Shape = Circle(Int) | Rect(Int, Int) | Empty
+type Named = { label: String, shape: Shape }
+type Chain = Link(String, Chain) | End
+
+fn area(s: Shape) -> Int {
+  match s {
+    Circle(r) => 3 * r * r,
+    Rect(w, h) if w == h => w * w + 1000,
+    Rect(w, h) => w * h,
+    Empty => 0,
+  }
+}
+
+fn describe(s: Shape) -> String {
+  match s {
+    Circle(_) => "circle",
+    Rect(_, _) => "rect",
+    _ => "empty",
+  }
+}
+
+fn join(c: Chain) -> String {
+  match c {
+    Link(head, rest) => head + join(rest),
+    End => "",
+  }
+}
+
+fn main(io: IO) -> Int !{io} {
+  let shapes = [Circle(1), Rect(2, 3), Empty, Rect(2, 2)]
+  let mut total = 0
+  for s in shapes {
+    io.println(describe(s))
+    total = total + area(s)
+  }
+  let named = Named { label: "boxed", shape: Rect(1, 1) }
+  io.println(named.label + " " + describe(named.shape))
+  io.println(join(Link("a", Link("b" + "c", End))))
+  total - 1013
+}
+"#;
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+    let artifact = backend::run(
+        &backend::native::NativeBackend,
+        &ir_module,
+        &backend::BackendOptions::default(),
+    )
+    .expect("native backend artifact");
+    assert!(artifact.c_source.contains("switch (value->tag) {"));
+
+    let output = run_checking_leaks(&artifact, "native-sums");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "circle\nrect\nempty\nrect\nboxed rect\nabc\n"
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(output.status.success(), "{}", artifact.c_source);
+
+    let partial = r#"
+module backend.native_partial_match
+
+type Light = Red | Green
+
+fn go(l: Light) -> Bool {
+  match l {
+    Green => true,
+  }
+}
+
+fn main() -> Int {
+  if go(Red) { 0 } else { 1 }
+}
+"#;
+    let (output, c_source) = link_and_run(partial, "native-partial-match");
+    assert_eq!(output.status.code(), Some(70), "{c_source}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: no match arm applies\n"
+    );
+}
+
+#[test]
+fn native_backend_builds_libraries_matching_record_layouts() {
+    let src = r#"
+module backend.native_lib
+
+type Small = { a: Bool, b: Bool }
+
+type Mixed = { flag: Bool, count: Int, ratio: Float, done: Bool }
+
+type Nested = { tag: Bool, inner: Small, name: String, items: [Int], shape: Shape }
+
+type Shape = Circle(Int) | Dot
+
+pub fn add(a: Int, b: Int) -> Int {
+  twice(a) + b
+}
+
+fn twice(a: Int) -> Int {
+  a * 2
+}
+
+pub fn mixed(count: Int) -> Mixed {
+  Mixed { flag: true, count: count, ratio: 0.5, done: false }
+}
+
+pub fn nested() -> Nested {
+  Nested { tag: true, inner: Small { a: false, b: true }, name: "n", items: [1, 2], shape: Shape::Dot }
+}
+
+pub fn ident(s: String) -> String {
+  s
+}
+
+pub fn greet(name: String) -> String {
+  "hi " + ident(name)
+}
+
+pub fn items(n: Int) -> [Int] {
+  [n, n + 1, n + 2]
+}
+
+pub fn total(xs: [Int], label: String) -> Int {
+  let mut sum = 0
+  for x in xs {
+    sum = sum + x
+  }
+  sum
+}
+"#;
+
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+    let options = backend::BackendOptions {
+        library: true,
+        ..backend::BackendOptions::default()
+    };
+    let artifact = backend::run(&backend::native::NativeBackend, &ir_module, &options)
+        .expect("native backend artifact");
+    assert!(
+        artifact
+            .c_source
+            .contains("static int64_t twice(int64_t arg0)"),
+        "private functions should not be exported: {}",
+        artifact.c_source
+    );
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("mica-lib-{nanos}"));
+    fs::create_dir_all(&dir).expect("create library directory");
+    artifact
+        .write_header(dir.join("mica.h"))
+        .expect("write header");
+    artifact
+        .link_shared_library(dir.join("libdemo.so"))
+        .expect("link shared library");
+    artifact
+        .link_static_library(dir.join("libdemo.a"))
+        .expect("link static library");
+
+    // The C compiler's view of each record, next to the calls it exports.
+    let mut expected = String::new();
+    // Strings from C are plain `char *`; results belong to the caller. The
+    // exchange runs a few times, since LeakSanitizer may still find the
+    // pointers of the last one and miss what it did not release.
+    let mut program = String::from(
+        "#include <stddef.h>\n#include <stdio.h>\n#include <stdlib.h>\n#include <string.h>\n\
+         #include \"mica.h\"\n\n\
+         static void exchange(void) {\n  \
+         char *bob = malloc(4);\n  strcpy(bob, \"bob\");\n  \
+         const char *same = ident(bob);\n  const char *hello = greet(bob);\n  free(bob);\n  \
+         mica_list *xs = items(5);\n  \
+         printf(\"%s %s %lld\\n\", same, hello, (long long)total(xs, \"sum\"));\n  \
+         backend_native_lib_string_release(same);\n  \
+         backend_native_lib_string_release(hello);\n  backend_native_lib_list_release(xs);\n}\n\n\
+         int main(void) {\n  \
+         for (int i = 0; i < 3; ++i) {\n    exchange();\n  }\n  \
+         record_Mixed m = mixed(7);\n  \
+         printf(\"%lld %lld %d\\n\", (long long)add(20, 2), (long long)m.count, m.flag);\n",
+    );
+    expected.push_str(&"bob hi bob 18\n".repeat(3));
+    expected.push_str("42 7 1\n");
+    for name in ["Small", "Mixed", "Nested"] {
+        let id = ir_module.types.lookup_named(name).expect("record type");
+        let ir::Type::Record(record) = ir_module.type_of(id) else {
+            panic!("{name} should be a record");
+        };
+        program.push_str(&format!(
+            "  printf(\"%zu %zu\\n\", sizeof(record_{name}), _Alignof(record_{name}));\n"
+        ));
+        expected.push_str(&format!("{} {}\n", record.size, record.align));
+        for field in &record.fields {
+            program.push_str(&format!(
+                "  printf(\"%zu\\n\", offsetof(record_{name}, {}));\n",
+                field.name
+            ));
+            expected.push_str(&format!("{}\n", field.offset));
+        }
+    }
+    program.push_str("  return 0;\n}\n");
+    let c_path = dir.join("main.c");
+    fs::write(&c_path, program).expect("write C program");
+
+    let shared_exe = dir.join("shared");
+    let static_exe = dir.join("static");
+    let linked = Command::new("cc")
+        .arg(&c_path)
+        .arg("-std=c11")
+        .arg("-I")
+        .arg(&dir)
+        .arg("-L")
+        .arg(&dir)
+        .arg(format!("-Wl,-rpath,{}", dir.display()))
+        .arg("-ldemo")
+        .arg("-o")
+        .arg(&shared_exe)
+        .status()
+        .expect("invoke cc");
+    assert!(
+        linked.success(),
+        "linking against the shared library failed"
+    );
+    let linked = Command::new("cc")
+        .arg(&c_path)
+        .arg("-std=c11")
+        .arg("-I")
+        .arg(&dir)
+        .arg(dir.join("libdemo.a"))
+        .arg("-o")
+        .arg(&static_exe)
+        .status()
+        .expect("invoke cc");
+    assert!(
+        linked.success(),
+        "linking against the static library failed"
+    );
+
+    for exe in [&shared_exe, &static_exe] {
+        let output = Command::new(exe).output().expect("execute binary");
+        assert!(output.status.success(), "process exit: {}", output.status);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    }
+
+    // Under AddressSanitizer, crossing the ABI must neither read outside the
+    // C strings nor leak what the library returns.
+    let library_source = artifact
+        .write_source(dir.join("libdemo.c"))
+        .expect("write library source");
+    let asan_exe = dir.join("asan");
+    let sanitized = Command::new("cc")
+        .arg(&c_path)
+        .arg(&library_source)
+        .args(["-std=c11", "-g", "-fsanitize=address"])
+        .arg("-I")
+        .arg(&dir)
+        .arg("-o")
+        .arg(&asan_exe)
+        .output()
+        .expect("invoke cc");
+    // Without the sanitizer, the plain builds above still pass C strings
+    // across, which reads before them when they are not copied.
+    if !sanitized.status.success() {
+        eprintln!(
+            "skipping the AddressSanitizer check, `cc -fsanitize=address` failed: {}",
+            String::from_utf8_lossy(&sanitized.stderr).trim()
+        );
+    } else {
+        let output = Command::new(&asan_exe)
+            .env("ASAN_OPTIONS", "detect_leaks=1")
+            .output()
+            .expect("execute sanitized binary");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // LeakSanitizer refuses to run under ptrace; only its errors count.
+        if stderr.contains("LeakSanitizer does not work under ptrace") {
+            eprintln!("skipping the LeakSanitizer check, which cannot run under ptrace");
+        } else {
+            assert!(output.status.success(), "{stderr}\n{}", artifact.c_source);
+            assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+        }
+    }
+
+    // Each library prefixes its exported release functions, so two of them
+    // link into one program.
+    let other = r#"
+module backend.other_lib
+
+pub fn shout(s: String) -> String {
+  s + "!"
+}
+"#;
+    let other = backend::run(
+        &backend::native::NativeBackend,
+        &ir::lower_module(&lower_checked(&parse(other))),
+        &options,
+    )
+    .expect("second library");
+    other
+        .link_static_library(dir.join("libother.a"))
+        .expect("link second static library");
+    let both_path = dir.join("both.c");
+    fs::write(
+        &both_path,
+        "#include <stdio.h>\n#include \"mica.h\"\n\n\
+         const char *shout(const char *s);\n\
+         void backend_other_lib_string_release(const char *value);\n\n\
+         int main(void) {\n  \
+         const char *same = ident(\"both\");\n  const char *loud = shout(same);\n  \
+         printf(\"%s\\n\", loud);\n  \
+         backend_native_lib_string_release(same);\n  backend_other_lib_string_release(loud);\n  \
+         return 0;\n}\n",
+    )
+    .expect("write C program");
+    let both_exe = dir.join("both");
+    let linked = Command::new("cc")
+        .arg(&both_path)
+        .arg("-std=c11")
+        .arg("-I")
+        .arg(&dir)
+        .arg(dir.join("libdemo.a"))
+        .arg(dir.join("libother.a"))
+        .arg("-o")
+        .arg(&both_exe)
+        .output()
+        .expect("invoke cc");
+    assert!(
+        linked.status.success(),
+        "linking two libraries failed: {}",
+        String::from_utf8_lossy(&linked.stderr)
+    );
+    let output = Command::new(&both_exe).output().expect("execute binary");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "both!\n");
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn native_build_cache_skips_unchanged_programs() {
+    use backend::cache::{BuildCache, CacheKey, CacheStats};
+
+    let src = r#"
+module backend.native_cache
+
+fn main() -> Int {
+  40 + 2 - 42
+}
+"#;
+    let lower_source = |src: &str| ir::lower_module(&lower_checked(&parse(src)));
+    let options = backend::BackendOptions::default();
+    let key = CacheKey::new(&lower_source(src), &options, "executable");
+    assert_eq!(
+        key,
+        CacheKey::new(&lower_source(src), &options, "executable"),
+        "lowering the same source twice should give the same key"
+    );
+    let optimized = backend::BackendOptions {
+        optimize: true,
+        ..backend::BackendOptions::default()
+    };
+    assert_ne!(
+        key,
+        CacheKey::new(&lower_source(src), &optimized, "executable")
+    );
+    let changed = src.replace("40 + 2", "41 + 1");
+    assert_ne!(
+        key,
+        CacheKey::new(&lower_source(&changed), &options, "executable")
+    );
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let cache = BuildCache::new(std::env::temp_dir().join(format!("mica-cache-{nanos}")));
+    assert!(!cache.contains(&key));
+    let builds = AtomicUsize::new(0);
+    let build = |path: &std::path::Path| {
+        builds.fetch_add(1, Ordering::SeqCst);
+        backend::run(
+            &backend::native::NativeBackend,
+            &lower_source(src),
+            &options,
+        )?
+        .link_executable(path)
+        .map(|_| ())
+    };
+    let first = cache.get_or_build(&key, build).expect("build on a miss");
+    let second = cache.get_or_build(&key, build).expect("reuse on a hit");
+    assert!(!first.hit && second.hit);
+    assert_eq!(first.path, second.path);
+    assert_eq!(builds.load(Ordering::SeqCst), 1, "a hit should not compile");
+    assert_eq!(
+        cache.stats(),
+        CacheStats {
+            hits: 1,
+            misses: 1,
+            entries: 1
+        }
+    );
+
+    let status = Command::new(&second.path).status().expect("execute binary");
+    assert!(status.success(), "process exit: {status}");
+
+    // A failed build leaves nothing behind.
+    let failed = cache.get_or_build(
+        &CacheKey::new(&lower_source(src), &optimized, "executable"),
+        |_| Err(backend::BackendError::Internal("cc failed".to_string())),
+    );
+    assert!(failed.is_err());
+    assert_eq!(cache.stats().entries, 1);
+    fs::remove_dir_all(cache.dir()).ok();
+}
+
+#[test]
+fn native_build_cache_evicts_by_age_and_size() {
+    use backend::cache::{BuildCache, CacheKey, CacheLimits};
+    use std::time::Duration;
+
+    let module = ir::lower_module(&lower_checked(&parse("module backend.evict\n")));
+    let options = backend::BackendOptions::default();
+    let key = |kind: &str| CacheKey::new(&module, &options, kind);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let cache = BuildCache::new(std::env::temp_dir().join(format!("mica-evict-{nanos}")))
+        .with_limits(CacheLimits {
+            max_bytes: 10,
+            max_age: Duration::from_secs(60 * 60),
+        });
+    let store = |kind: &str| {
+        cache
+            .get_or_build(&key(kind), |path| {
+                fs::write(path, "six b.")
+                    .map_err(|err| backend::BackendError::Internal(err.to_string()))
+            })
+            .expect("store entry")
+    };
+    let backdate = |path: &std::path::Path, secs: u64| {
+        fs::File::open(path)
+            .and_then(|file| file.set_modified(SystemTime::now() - Duration::from_secs(secs)))
+            .expect("set modification time");
+    };
+
+    // A hit makes an entry the most recently used one.
+    let older = store("older");
+    let newer = store("newer");
+    assert!(!older.path.exists(), "12 bytes exceed the 10 byte cap");
+    backdate(&newer.path, 30 * 60);
+    assert!(store("newer").hit);
+    let modified = fs::metadata(&newer.path)
+        .and_then(|m| m.modified())
+        .expect("mtime");
+    assert!(
+        SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default()
+            < Duration::from_secs(60)
+    );
+
+    // Expired entries and abandoned temporary files go regardless of size.
+    let cache = cache.with_limits(CacheLimits {
+        max_bytes: 1024,
+        max_age: Duration::from_secs(60 * 60),
+    });
+    let abandoned = cache.dir().join("abandoned.tmp");
+    fs::write(&abandoned, "").expect("write temporary file");
+    backdate(&abandoned, 2 * 60 * 60);
+    backdate(&newer.path, 2 * 60 * 60);
+    let fresh = cache
+        .get_or_build(&key("fresh"), |path| {
+            fs::write(path, "fresh").map_err(|err| backend::BackendError::Internal(err.to_string()))
+        })
+        .expect("store entry");
+    assert!(fresh.path.exists());
+    assert!(!newer.path.exists() && !abandoned.exists());
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 3, 1));
+    assert_eq!(
+        fs::read_to_string(cache.dir().join("stats")).expect("counters"),
+        "1 3\n"
+    );
+    fs::remove_dir_all(cache.dir()).ok();
+}
+
+#[test]
+fn native_backend_grows_and_iterates_lists() {
+    let src = r#"
+module backend.native_lists
+
+fn total(xs: [Int]) -> Int {
+  let mut sum = 0
+  for x in xs {
+    sum = sum + x
+  }
+  sum
+}
+
+fn main() -> Int {
+  let xs = [1, 2, 3]
+  let mut i = 0
+  while i < 20 {
+    xs.push(i)
+    i = i + 1
+  }
+  total(xs) + xs.len() - xs[22] - 200
+}
+"#;
+
+    let (output, c_source) = link_and_run(src, "native-lists");
+    assert!(
+        output.status.success(),
+        "196 + 23 - 19 should equal 200, process exit: {}\n{}",
+        output.status,
+        c_source
+    );
+}
+
+#[test]
+fn native_backend_aborts_on_out_of_bounds_index() {
+    let src = r#"
+module backend.native_bounds
+
+fn main() -> Int {
+  let xs = [1, 2]
+  xs[5]
+}
+"#;
+
+    let (output, _) = link_and_run(src, "native-bounds");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("list index 5 out of bounds for length 2"),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+const DEEP_TAIL_CALLS: &str = r#"
+module backend.tail_calls
+
+fn count(n: Int, acc: Int) -> Int {
+  if n == 0 { acc } else { count(n - 1, acc + 1) }
+}
+
+fn is_even(n: Int) -> Bool {
+  if n == 0 { true } else { is_odd(n - 1) }
+}
+
+fn is_odd(n: Int) -> Bool {
+  if n == 0 { false } else { is_even(n - 1) }
+}
+
+fn main() -> Int {
+  let total = count(1000000, 0)
+  if is_even(1000001) { 1 } else { total - 1000000 }
+}
+"#;
+
+#[test]
+fn native_backend_trampolines_deep_tail_recursion() {
+    let (output, c_source) = link_and_run(DEEP_TAIL_CALLS, "native-tail-calls");
+    assert!(
+        output.status.success(),
+        "a million tail calls should not overflow, process exit: {}\n{}",
+        output.status,
+        c_source
+    );
+    assert!(c_source.contains("typedef struct mica_tail0 {"));
+    assert!(c_source.contains("tail->next = "));
+    let count_body = c_source
+        .split("int64_t count(int64_t arg0, int64_t arg1) {")
+        .nth(1)
+        .and_then(|rest| rest.split("\n}\n").next())
+        .expect("count definition");
+    assert!(
+        !count_body.contains("count("),
+        "self tail call should be a loop:\n{c_source}"
+    );
+}
+
+#[test]
+fn llvm_backend_marks_sibling_tail_calls_musttail() {
+    let module = parse(DEEP_TAIL_CALLS);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+    let output = backend::run(
+        &backend::llvm::LlvmBackend::default(),
+        &ir_module,
+        &backend::BackendOptions::default(),
+    )
+    .expect("llvm output");
+    assert!(output.ir.contains("musttail call i1 @is_odd(i64"));
+    assert!(output.ir.contains("musttail call i1 @is_even(i64"));
+    let count_body = output
+        .ir
+        .split("define i64 @count(")
+        .nth(1)
+        .and_then(|rest| rest.split("\n}\n").next())
+        .expect("count definition");
+    assert!(!count_body.contains("@count"), "{}", output.ir);
+}
+
 #[derive(Clone)]
 struct CountingBackend {
     counter: Arc<AtomicUsize>,
@@ -452,7 +1299,7 @@ fn parallel_backend_reports_worker_metrics() {
         .map(|name| {
             let src = format!("module parallel.{name}\n\nfn entry() -> Int {{ 1 }}\n");
             let module = parse(&src);
-            let hir = lower::lower_module(&module);
+            let hir = lower_checked(&module);
             ir::lower_module(&hir)
         })
         .collect::<Vec<_>>();
@@ -495,7 +1342,7 @@ fn log(io: IO) !{io} {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let backend = backend::native::NativeBackend;
     let artifact = backend::run(&backend, &ir_module, &backend::BackendOptions::default())
@@ -520,6 +1367,87 @@ fn log(io: IO) !{io} {
     );
 }
 
+#[test]
+fn native_backend_runs_fs_env_and_process_capabilities() {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("mica-native-fs-{nanos}.txt"));
+    let path = path.display();
+    let src = format!(
+        r#"
+module backend.native_providers
+
+fn roundtrip(fs: Fs, io: IO) !{{fs, io}} {{
+  fs.write_string("{path}=written natively")
+  io.println(fs.read_to_string("{path}"))
+}}
+
+fn main(io: IO, fs: Fs, env: Env, process: Process) -> Int !{{io, fs, env, process}} {{
+  roundtrip(fs, io)
+  env.set("MICA_NATIVE_GREETING = hello from env")
+  io.println(env.get("MICA_NATIVE_GREETING"))
+  env.unset("MICA_NATIVE_GREETING")
+  process.spawn("sh -c 'echo hidden; exit 3'")
+}}
+"#
+    );
+
+    let (output, c_source) = link_and_run(&src, "native-providers");
+    fs::remove_file(path.to_string()).ok();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "written natively\n hello from env\n",
+        "{c_source}"
+    );
+    assert_eq!(output.status.code(), Some(3), "{c_source}");
+}
+
+#[test]
+fn native_backend_reports_provider_failures_like_the_runtime() {
+    let cases = [
+        (
+            r#"fs.read_to_string("/nonexistent/mica")"#,
+            "capability provider 'fs' reported an error: failed to read '/nonexistent/mica': No such file or directory (os error 2)",
+        ),
+        (
+            r#"env.set("KEY")"#,
+            "capability provider 'env' reported an error: set payload must include a value",
+        ),
+        (
+            r#"process.spawn("echo 'open")"#,
+            "capability provider 'process' reported an error: unterminated quote in process payload",
+        ),
+        (
+            r#"net.fetch("service")"#,
+            "capability provider 'net' reported an error: no fixture registered for 'service'",
+        ),
+        (
+            r#"fs.remove("data")"#,
+            "capability provider 'fs' reported an error: unsupported operation 'remove'",
+        ),
+    ];
+    for (index, (call, expected)) in cases.into_iter().enumerate() {
+        let src = format!(
+            r#"
+module backend.native_provider_errors
+
+fn main(fs: Fs, env: Env, process: Process, net: Net) -> Int !{{fs, env, process, net}} {{
+  {call}
+  0
+}}
+"#
+        );
+        let (output, _) = link_and_run(&src, &format!("native-provider-error-{index}"));
+        assert_eq!(output.status.code(), Some(74), "{call}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            format!("error: {expected}\n")
+        );
+    }
+}
+
 #[test]
 fn recommended_worker_count_reserves_headroom() {
     assert_eq!(backend::recommended_worker_count_with_available(1, 8), 1);
@@ -543,7 +1471,7 @@ fn build(x: Int, y: Int) -> Vec2 {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
     let backend = backend::native::NativeBackend;
     let artifact = backend::run(&backend, &ir_module, &backend::BackendOptions::default())
@@ -571,6 +1499,65 @@ fn build(x: Int, y: Int) -> Vec2 {
     );
 }
 
+#[test]
+fn native_backend_maps_lines_back_to_linked_sources() {
+    let app = r#"
+module app
+
+fn main() -> Int {
+  let half = util::halve(8)
+  half - 4
+}
+"#;
+    let util = r#"
+module util
+
+pub fn halve(n: Int) -> Int {
+  n / 2
+}
+"#;
+
+    let lower_src = |src: &str| ir::lower_module(&lower_checked(&parse(src)));
+    let linked = ir::link::link_modules(vec![lower_src(app), lower_src(util)]).expect("link");
+    let mut options = backend::BackendOptions {
+        debug_info: true,
+        ..backend::BackendOptions::default()
+    };
+    options
+        .source_files
+        .insert("app".to_string(), "src/app.mica".into());
+    let artifact =
+        backend::run(&backend::native::NativeBackend, &linked, &options).expect("artifact");
+
+    let source = &artifact.c_source;
+    assert!(source.contains("#line 5 \"src/app.mica\"\nint main(void) {"));
+    assert!(source.contains("#line 6 \"src/app.mica\"\n  return"));
+    // Modules without a registered file fall back to their module path.
+    assert!(source.contains("#line 5 \"util.mica\"\n  int64_t v2 = v0 / v1;"));
+    assert!(artifact.debug_info);
+
+    let mut exe_path = std::env::temp_dir();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    exe_path.push(format!("mica-native-debug-{nanos}"));
+    artifact
+        .link_executable(&exe_path)
+        .expect("link executable");
+    let status = Command::new(&exe_path).status().expect("execute binary");
+    fs::remove_file(&exe_path).ok();
+    assert!(status.success(), "process exit: {status}");
+
+    let plain = backend::run(
+        &backend::native::NativeBackend,
+        &linked,
+        &backend::BackendOptions::default(),
+    )
+    .expect("artifact");
+    assert!(!plain.c_source.contains("#line"));
+}
+
 #[test]
 fn parallel_backend_reports_metrics_for_many_modules() {
     use std::sync::{Arc, Mutex};
@@ -615,7 +1602,7 @@ fn parallel_backend_reports_metrics_for_many_modules() {
         .iter()
         .map(|src| {
             let module = parse(src);
-            let hir = lower::lower_module(&module);
+            let hir = lower_checked(&module);
             ir::lower_module(&hir)
         })
         .collect::<Vec<_>>();
@@ -669,3 +1656,260 @@ fn parallel_backend_reports_metrics_for_many_modules() {
         assert!(log.contains(&name));
     }
 }
+
+const WASM_SAMPLE: &str = r#"
+module backend.wasm_sample
+
+type Point = { x: Int, y: Int }
+
+fn sum(points: [Point]) -> Int {
+  let mut total = 0
+  for p in points {
+    total = total + p.x + p.y
+  }
+  total
+}
+
+pub fn main(io: IO) -> Int !{io} {
+  io.println("hello")
+  let points = [Point { x: 1, y: 2 }]
+  points.push(Point { x: 3, y: 4 })
+  sum(points)
+}
+"#;
+
+fn read_leb(bytes: &[u8], pos: &mut usize) -> u32 {
+    let mut result = 0u32;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*pos];
+        *pos += 1;
+        result |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return result;
+        }
+        shift += 7;
+    }
+}
+
+fn read_name(bytes: &[u8], pos: &mut usize) -> String {
+    let len = read_leb(bytes, pos) as usize;
+    let name = String::from_utf8(bytes[*pos..*pos + len].to_vec()).expect("utf-8 name");
+    *pos += len;
+    name
+}
+
+/// Splits a binary module into `(section id, contents)` pairs.
+fn wasm_sections(bytes: &[u8]) -> Vec<(u8, &[u8])> {
+    assert_eq!(&bytes[..4], b"\0asm", "magic");
+    assert_eq!(&bytes[4..8], &[1, 0, 0, 0], "version");
+    let mut sections = Vec::new();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let id = bytes[pos];
+        pos += 1;
+        let size = read_leb(bytes, &mut pos) as usize;
+        sections.push((id, &bytes[pos..pos + size]));
+        pos += size;
+    }
+    assert_eq!(pos, bytes.len(), "sections must cover the module exactly");
+    sections
+}
+
+#[test]
+fn wasm_backend_encodes_sections_imports_and_exports() {
+    let module = ir::lower_module(&lower_checked(&parse(WASM_SAMPLE)));
+    let output = backend::run(
+        &backend::wasm::WasmBackend,
+        &module,
+        &backend::BackendOptions::default(),
+    )
+    .expect("wasm output");
+    let sections = wasm_sections(&output.bytes);
+
+    let ids: Vec<u8> = sections.iter().map(|(id, _)| *id).collect();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]), "{ids:?}");
+    let section = |wanted: u8| {
+        sections
+            .iter()
+            .find(|(id, _)| *id == wanted)
+            .map(|(_, contents)| *contents)
+            .unwrap_or_else(|| panic!("missing section {wanted}"))
+    };
+
+    let imports = section(2);
+    let mut pos = 0;
+    assert_eq!(read_leb(imports, &mut pos), 1);
+    assert_eq!(read_name(imports, &mut pos), "mica_runtime");
+    assert_eq!(read_name(imports, &mut pos), "io_write_line");
+
+    let mut pos = 0;
+    let declared = read_leb(section(3), &mut pos);
+    let mut pos = 0;
+    let bodies = read_leb(section(10), &mut pos);
+    assert_eq!(declared, bodies);
+    assert!(declared >= 2, "module functions plus runtime helpers");
+
+    let exports = section(7);
+    let mut pos = 0;
+    let count = read_leb(exports, &mut pos);
+    let mut names = Vec::new();
+    for _ in 0..count {
+        names.push(read_name(exports, &mut pos));
+        pos += 1;
+        read_leb(exports, &mut pos);
+    }
+    assert_eq!(names, ["memory", "main"]);
+
+    let data = section(11);
+    assert!(
+        data.windows(5).any(|window| window == b"hello"),
+        "string literal stored in a data segment"
+    );
+}
+
+#[test]
+fn wasm_backend_prints_text_form() {
+    let module = ir::lower_module(&lower_checked(&parse(WASM_SAMPLE)));
+    let output = backend::run(
+        &backend::wasm::WasmBackend,
+        &module,
+        &backend::BackendOptions::default(),
+    )
+    .expect("wasm output");
+
+    let wat = &output.wat;
+    assert!(wat.starts_with("(module $backend::wasm_sample"), "{wat}");
+    assert!(wat.contains(
+        "(import \"mica_runtime\" \"io_write_line\" (func $mica_runtime.io_write_line (param i32 i32)))"
+    ));
+    assert!(wat.contains("(memory (export \"memory\") 1)"));
+    assert!(wat.contains("(func $main (export \"main\") (param $v0 i64) (result i64)"));
+    assert!(wat.contains("(func $sum (param $v0 i32) (result i64)"));
+    assert!(wat.contains("call $mica_list_slot"));
+    assert!(wat.contains("br_table"));
+    assert!(
+        wat.contains("i64.store offset=8"),
+        "record fields use 8-byte slots"
+    );
+    assert_eq!(output.module_name, "backend_wasm_sample");
+}
+
+#[test]
+fn wasm_backend_calls_helpers_ending_in_capability_calls() {
+    let src = r#"
+module backend.wasm_helper
+
+fn log(io: IO) !{io} {
+  io.println("value")
+}
+
+pub fn main(io: IO) -> Int !{io} {
+  log(io)
+  0
+}
+"#;
+    let module = ir::lower_module(&lower_checked(&parse(src)));
+    let output = backend::run(
+        &backend::wasm::WasmBackend,
+        &module,
+        &backend::BackendOptions::default(),
+    )
+    .expect("wasm output");
+
+    let wat = &output.wat;
+    assert!(wat.contains("(func $log (param $v0 i64)"), "{wat}");
+    assert!(wat.contains("call $log"), "{wat}");
+    assert!(wat.contains("call $mica_runtime.io_write_line"), "{wat}");
+    wasm_sections(&output.bytes);
+
+    // A call the caller types as unit discards whatever the callee returns.
+    let module = ir::parse::parse_module(
+        "module backend.wasm_helper\n\
+         fn helper() -> Int\n  block 0:\n    %0 = 1 : Int\n    return %0\n\
+         pub fn main() -> Int\n  block 0:\n    %0 = call helper() : Unit\n    \
+         %1 = 0 : Int\n    return %1\n",
+    )
+    .expect("parse ir");
+    let output = backend::run(
+        &backend::wasm::WasmBackend,
+        &module,
+        &backend::BackendOptions::default(),
+    )
+    .expect("wasm output");
+    assert!(
+        output.wat.contains("call $helper\n    drop"),
+        "{}",
+        output.wat
+    );
+}
+
+#[test]
+fn numeric_casts_convert_on_every_backend() {
+    let src = r#"
+module backend.casts
+
+fn halve(n: Int) -> Float {
+  (n as Float) / 2.0
+}
+
+fn main() -> Int {
+  let back = halve(7) as Int
+  let whole = 9.9 as Int
+  back * 10 + whole
+}
+"#;
+    let module = ir::lower_module(&lower_checked(&parse(src)));
+    let options = backend::BackendOptions::default();
+
+    let output = crate::interpreter::Interpreter::new(module.clone())
+        .run()
+        .expect("interpreter run");
+    assert!(output.ends_with("39\n"), "unexpected output: {output}");
+
+    let program = backend::run(&backend::bytecode::BytecodeBackend, &module, &options)
+        .expect("bytecode compilation");
+    let runtime = crate::runtime::Runtime::with_default_shims().expect("runtime setup");
+    let execution = crate::bytecode::Vm::new(&program, runtime)
+        .run()
+        .expect("program runs");
+    assert_eq!(execution.value, crate::bytecode::Value::Int(39));
+
+    let (output, c_source) = link_and_run(src, "native-casts");
+    assert_eq!(output.status.code(), Some(39), "{c_source}");
+
+    let llvm_backend = backend::llvm::LlvmBackend::default();
+    let output = backend::run(&llvm_backend, &module, &options).expect("llvm ir");
+    let llvm = output.as_str();
+    assert!(llvm.contains("sitofp i64 %n to double"), "{llvm}");
+    assert!(llvm.contains("fptosi double"), "{llvm}");
+
+    let wasm = backend::run(&backend::wasm::WasmBackend, &module, &options).expect("wasm output");
+    assert!(wasm.wat.contains("f64.convert_i64_s"), "{}", wasm.wat);
+    assert!(wasm.wat.contains("i64.trunc_f64_s"), "{}", wasm.wat);
+    wasm_sections(&wasm.bytes);
+
+    let err = backend::run(&backend::x86_64::X86_64Backend, &module, &options)
+        .expect_err("the x86-64 backend has no floats");
+    assert!(err.to_string().contains("Float"), "{err}");
+}
+
+#[test]
+fn wasm_backend_rejects_unprintable_values() {
+    let src = r#"
+module backend.wasm_print
+
+fn main(io: IO) !{io} {
+  io.println(1.5)
+}
+"#;
+    let module = ir::lower_module(&lower_checked(&parse(src)));
+    let err = backend::run(
+        &backend::wasm::WasmBackend,
+        &module,
+        &backend::BackendOptions::default(),
+    )
+    .expect_err("float printing is not imported");
+    assert!(matches!(err, backend::BackendError::Unsupported(_)));
+    assert!(err.to_string().contains("can only print strings"), "{err}");
+}
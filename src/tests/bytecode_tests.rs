@@ -0,0 +1,249 @@
+use super::helpers::*;
+use super::*;
+use crate::backend::bytecode::BytecodeBackend;
+use crate::bytecode::{self, Instr, LoadError, Value, Vm, VmError};
+use crate::runtime::{Runtime, RuntimeErrorKind};
+
+fn compile(src: &str) -> bytecode::Program {
+    let typed = ir::lower_module(&lower_checked(&parse(src)));
+    backend::run(
+        &BytecodeBackend,
+        &typed,
+        &backend::BackendOptions::default(),
+    )
+    .expect("bytecode compilation")
+}
+
+fn run(program: &bytecode::Program) -> Result<bytecode::Execution, VmError> {
+    let runtime = Runtime::with_default_shims().expect("runtime setup");
+    Vm::new(program, runtime).run()
+}
+
+#[test]
+fn vm_runs_loops_recursion_and_phi_swaps() {
+    let src = r#"
+module demo.vm
+
+fn fact(n: Int) -> Int {
+  if n <= 1 { 1 } else { n * fact(n - 1) }
+}
+
+fn swap(n: Int) -> Int {
+  let mut a = 1
+  let mut b = 2
+  let mut i = 0
+  while i < n {
+    let t = a
+    a = b
+    b = t
+    i = i + 1
+  }
+  a * 10 + b
+}
+
+fn main() -> Int {
+  fact(5) + swap(3)
+}
+"#;
+    let program = compile(src);
+    assert_eq!(program.name, "demo::vm");
+    // Moving the swapped phis one at a time would leave both at 2.
+    let execution = run(&program).expect("program runs");
+    assert_eq!(execution.value, Value::Int(120 + 21));
+
+    let vm = Vm::new(
+        &program,
+        Runtime::with_default_shims().expect("runtime setup"),
+    );
+    let swapped = vm.call("swap", vec![Value::Int(2)]).expect("swap runs");
+    assert_eq!(swapped.value, Value::Int(12));
+}
+
+#[test]
+fn vm_builds_records_and_shared_lists() {
+    let src = r#"
+module demo.data
+
+type Pair = { left: Int, right: Int }
+
+fn total(xs: List[Int]) -> Int {
+  let mut sum = 0
+  let mut i = 0
+  while i < xs.len() {
+    sum = sum + xs[i]
+    i = i + 1
+  }
+  sum
+}
+
+fn main() -> Int {
+  let pair = Pair { right: 2, left: 40 }
+  let xs = [pair.left, pair.right]
+  let alias = xs
+  alias.push(100)
+  total(xs)
+}
+"#;
+    let program = compile(src);
+    let (_, main) = program.function("main").expect("main compiled");
+    assert!(
+        main.code
+            .iter()
+            .any(|instr| matches!(instr, Instr::Record { fields, .. } if fields.len() == 2))
+    );
+    let execution = run(&program).expect("program runs");
+    assert_eq!(execution.value, Value::Int(142));
+}
+
+#[test]
+fn vm_routes_capabilities_through_the_runtime() {
+    let src = r#"
+module demo.io
+
+fn greet(io: IO, name: String) !{io} {
+  io.println(name)
+}
+
+fn main(io: IO) !{io} {
+  greet(io, "hello")
+  io.println("bye")
+}
+"#;
+    let program = compile(src);
+    let bundle = Runtime::with_deterministic_shims().expect("runtime setup");
+    let execution = Vm::new(&program, bundle.runtime())
+        .run()
+        .expect("program runs");
+    assert_eq!(execution.output(), vec!["hello", "bye"]);
+    assert_eq!(bundle.console.writes(), vec!["hello", "bye"]);
+
+    let denied = r#"
+module demo.denied
+
+fn main(io: IO) {
+  io.println("sneaky")
+}
+"#;
+    let err = run(&compile(denied)).expect_err("main declares no effects");
+    let VmError::Runtime(err) = err else {
+        panic!("expected a runtime error, got {err:?}");
+    };
+    assert!(matches!(
+        err.kind(),
+        RuntimeErrorKind::MissingCapability { capability, .. } if capability == "io"
+    ));
+}
+
+#[test]
+fn vm_round_trips_fs_and_env_through_the_default_runtime() {
+    let path = std::env::temp_dir().join(format!("mica-bytecode-fs-{}.txt", std::process::id()));
+    let path = path.display();
+    let src = format!(
+        r#"
+module demo.providers
+
+fn roundtrip(fs: Fs, io: IO) !{{fs, io}} {{
+  fs.write_string("{path}=written by the vm")
+  io.println(fs.read_to_string("{path}"))
+}}
+
+fn main(io: IO, fs: Fs, env: Env) !{{io, fs, env}} {{
+  roundtrip(fs, io)
+  env.set("MICA_BYTECODE_GREETING=hello from env")
+  io.println(env.get("MICA_BYTECODE_GREETING"))
+  env.unset("MICA_BYTECODE_GREETING")
+}}
+"#
+    );
+    let program = compile(&src);
+    // `--run-bytecode` runs programs against the default providers.
+    let execution = run(&program).expect("program runs");
+    std::fs::remove_file(path.to_string()).ok();
+    assert_eq!(
+        execution.output(),
+        vec!["written by the vm", "hello from env"]
+    );
+    assert!(std::env::var("MICA_BYTECODE_GREETING").is_err());
+}
+
+#[test]
+fn vm_traps_on_runtime_faults() {
+    let src = r#"
+module demo.faults
+
+pub fn divide(a: Int, b: Int) -> Int {
+  a / b
+}
+
+fn main() -> Int {
+  let xs = [1]
+  xs[3]
+}
+"#;
+    let program = compile(src);
+    let err = run(&program).expect_err("index out of bounds");
+    assert_eq!(
+        err.to_string(),
+        "list index 3 out of bounds for length 1 (in 'main')"
+    );
+
+    let vm = Vm::new(
+        &program,
+        Runtime::with_default_shims().expect("runtime setup"),
+    );
+    let err = vm
+        .call("divide", vec![Value::Int(1), Value::Int(0)])
+        .expect_err("division by zero");
+    assert!(err.to_string().starts_with("division by zero"));
+    assert!(matches!(
+        vm.call("missing", Vec::new()),
+        Err(VmError::Entry(_))
+    ));
+}
+
+#[test]
+fn micab_files_round_trip_and_reject_corruption() {
+    let src = r#"
+module demo.file
+
+fn main(io: IO) -> Int !{io} {
+  io.println("from disk")
+  7
+}
+"#;
+    let program = compile(src);
+    let bytes = bytecode::to_bytes(&program);
+    assert!(bytes.starts_with(&bytecode::serialize::MAGIC));
+    assert_eq!(bytecode::from_bytes(&bytes).expect("round trip"), program);
+
+    let path = std::env::temp_dir().join(format!("mica-bytecode-{}.micab", std::process::id()));
+    bytecode::save(&program, &path).expect("save");
+    let loaded = bytecode::load(&path).expect("load");
+    let _ = std::fs::remove_file(&path);
+    let execution = run(&loaded).expect("loaded program runs");
+    assert_eq!(execution.value, Value::Int(7));
+    assert_eq!(execution.output(), vec!["from disk"]);
+
+    assert!(matches!(
+        bytecode::from_bytes(b"MIRB\x01\x00\x00\x00"),
+        Err(LoadError::Format(_))
+    ));
+    let mut future = bytes.clone();
+    future[4] = 99;
+    assert!(matches!(
+        bytecode::from_bytes(&future),
+        Err(LoadError::Version { found: 99 })
+    ));
+
+    let mut broken = program.clone();
+    let registers = broken.functions[0].registers;
+    broken.functions[0].code.insert(
+        0,
+        Instr::Move {
+            dst: 0,
+            src: registers,
+        },
+    );
+    let err = bytecode::from_bytes(&bytecode::to_bytes(&broken)).expect_err("bad register");
+    assert!(matches!(err, LoadError::Invalid(_)), "{err}");
+}
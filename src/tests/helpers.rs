@@ -17,3 +17,12 @@ pub fn literal_bool(value: bool) -> Expr {
 pub fn parse(src: &str) -> Module {
     parser::parse_module(src).expect("parse ok")
 }
+
+pub fn lower_src(src: &str) -> ir::Module {
+    ir::lower_module(&lower_checked(&parse(src)))
+}
+
+/// Checks `module` and lowers it to HIR with the checker's types.
+pub fn lower_checked(module: &Module) -> lower::HModule {
+    lower::lower_module(module, &check::check_module(module).types)
+}
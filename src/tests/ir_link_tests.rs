@@ -0,0 +1,165 @@
+use super::helpers::*;
+use super::*;
+
+use ir::link::{LinkError, link_modules};
+
+const APP: &str = r#"
+module app
+
+type Point = { x: Int, y: Int }
+
+fn main() -> Int {
+  let p = Point { x: 2, y: 3 }
+  println("linking")
+  geometry::shapes::scale(p.x, 4) + offset()
+}
+
+fn offset() -> Int {
+  1
+}
+"#;
+
+const SHAPES: &str = r#"
+module geometry.shapes
+
+type Point = { x: Int, y: Int }
+
+pub fn scale(v: Int, by: Int) -> Int !{trace} {
+  offset(v) * by
+}
+
+fn offset(v: Int) -> Int {
+  v + 10
+}
+"#;
+
+#[test]
+fn linker_merges_tables_and_resolves_cross_module_calls() {
+    let linked = link_modules(vec![lower_src(APP), lower_src(SHAPES)]).expect("link");
+
+    assert_eq!(linked.name, vec!["app".to_string()]);
+    let names: Vec<&str> = linked
+        .functions
+        .iter()
+        .map(|function| function.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "main",
+            "offset",
+            "geometry::shapes::scale",
+            "geometry::shapes::offset"
+        ]
+    );
+    // Identical records from both modules share one entry.
+    let points = linked
+        .types
+        .entries()
+        .filter(|(_, ty)| matches!(ty, ir::Type::Record(record) if record.name.as_deref() == Some("Point")))
+        .count();
+    assert_eq!(points, 1);
+
+    let text = backend::text::render_module(&linked);
+    assert!(text.contains("call geometry::shapes::scale(%"), "{text}");
+    assert!(text.contains("call geometry::shapes::offset(%"), "{text}");
+    assert!(text.contains("call offset()"), "{text}");
+    // The call picks up the callee's return type and effects.
+    assert!(
+        text.lines()
+            .any(|line| line.contains("call geometry::shapes::scale(")
+                && line.ends_with(": Int !{trace}")),
+        "{text}"
+    );
+
+    assert!(ir::verify::verify_module(&linked).is_ok());
+    let output = crate::interpreter::Interpreter::new(linked)
+        .run()
+        .expect("interpreter run");
+    assert!(output.contains("linking"), "{output}");
+    assert!(output.contains("49"), "{output}");
+}
+
+#[test]
+fn linker_reports_duplicate_conflicting_and_missing_symbols() {
+    let other = r#"
+module geometry.shapes
+
+type Point = { x: Float }
+
+fn area() -> Int {
+  1
+}
+"#;
+    let caller = r#"
+module app
+
+fn main() -> Int {
+  geometry::shapes::volume() + File::size()
+}
+"#;
+    let errors = link_modules(vec![lower_src(caller), lower_src(SHAPES), lower_src(other)])
+        .expect_err("link errors");
+
+    assert!(errors.contains(&LinkError::DuplicateModule("geometry.shapes".into())));
+    assert!(errors.contains(&LinkError::MissingFunction {
+        caller: "main".into(),
+        callee: "geometry::shapes::volume".into(),
+    }));
+    assert!(errors.contains(&LinkError::MissingFunction {
+        caller: "main".into(),
+        callee: "File::size".into(),
+    }));
+    assert_eq!(errors.len(), 3, "{errors:?}");
+
+    let errors = link_modules(vec![
+        lower_src(APP),
+        lower_src(other.replace("geometry.shapes", "geometry.solids").as_str()),
+    ])
+    .expect_err("conflicting records");
+    assert_eq!(
+        errors,
+        vec![
+            LinkError::ConflictingType {
+                name: "Point".into(),
+                first: "app".into(),
+                second: "geometry.solids".into(),
+            },
+            LinkError::MissingFunction {
+                caller: "main".into(),
+                callee: "geometry::shapes::scale".into(),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "type `Point` is defined differently in `app` and `geometry.solids`"
+    );
+}
+
+#[test]
+fn linker_reports_calls_into_modules_that_were_not_linked() {
+    let app = r#"
+module app
+
+fn main() -> Int {
+  geo::make(1) + local()
+}
+
+fn local() -> Int {
+  2
+}
+"#;
+    let errors = link_modules(vec![lower_src(app)]).expect_err("missing module");
+    assert_eq!(
+        errors,
+        vec![LinkError::MissingFunction {
+            caller: "main".into(),
+            callee: "geo::make".into(),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "`main` calls undefined function `geo::make`"
+    );
+}
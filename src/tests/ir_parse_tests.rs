@@ -0,0 +1,148 @@
+use super::*;
+use std::fs;
+
+fn render(module: &ir::Module) -> String {
+    backend::text::render_module(module)
+}
+
+#[test]
+fn textual_ir_round_trips_for_every_example() {
+    let mut checked = 0;
+    for entry in fs::read_dir("examples").expect("examples directory") {
+        let path = entry.expect("directory entry").path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("mica") {
+            continue;
+        }
+        let source = fs::read_to_string(&path).expect("read example");
+        let Ok(ast) = parser::parse_module(&source) else {
+            continue;
+        };
+        let module = ir::lower_module(&helpers::lower_checked(&ast));
+        let text = render(&module);
+        let reparsed = ir::parse::parse_module(&text)
+            .unwrap_or_else(|err| panic!("{} failed to parse back: {err}\n{text}", path.display()));
+        assert_eq!(
+            render(&reparsed),
+            text,
+            "{} changed on round trip",
+            path.display()
+        );
+        checked += 1;
+    }
+    assert!(checked > 10, "expected to round-trip the example corpus");
+}
+
+#[test]
+fn textual_ir_restores_type_and_effect_tables() {
+    let text = r#"
+module demo.shapes
+type Point = { flag: Bool, x: Int }
+effects io, net
+
+; comments are ignored
+fn area(p: Point, scale: Float, tags: [String]) -> { w: Int, h: Float } !{net}
+  block 0:
+    %3 = field %0.x : Int
+    %4 = 2.0 : Float
+    %5 = * %1, %4 : Float
+    %6 = "a \"quoted\"\n\u{e9}" : String
+    %7 = call .log(%6) : Unit !{io}
+    %8 = record { w: %3, h: %5 } : { w: Int, h: Float }
+    return %8
+"#;
+    let module = ir::parse::parse_module(text).expect("parse IR");
+
+    assert_eq!(module.name, vec!["demo".to_string(), "shapes".to_string()]);
+    let effects: Vec<&str> = module
+        .effects
+        .entries()
+        .map(|(_, name)| name.as_str())
+        .collect();
+    assert_eq!(effects, vec!["io", "net"]);
+
+    let point = module
+        .types
+        .lookup_named("Point")
+        .expect("Point is declared");
+    let ir::Type::Record(record) = module.type_of(point) else {
+        panic!("Point should be a record");
+    };
+    let x = record.field("x").expect("field x");
+    assert_eq!((x.offset, record.size, record.align), (8, 16, 8));
+
+    let function = &module.functions[0];
+    assert_eq!(function.params.len(), 3);
+    assert_eq!(function.params[2].value.index(), 2);
+    assert!(matches!(
+        module.type_of(function.params[2].ty),
+        ir::Type::List(_)
+    ));
+    assert_eq!(module.effect_name(function.effect_row[0]), "net");
+
+    let insts = &function.blocks[0].instructions;
+    assert!(matches!(insts[1].kind, ir::InstKind::Literal(Literal::Float(value)) if value == 2.0));
+    assert!(matches!(
+        &insts[3].kind,
+        ir::InstKind::Literal(Literal::String(value)) if value == "a \"quoted\"\n\u{e9}"
+    ));
+    assert!(matches!(
+        &insts[4].kind,
+        ir::InstKind::Call { func: ir::FuncRef::Method(name), .. } if name == "log"
+    ));
+    assert_eq!(module.effect_name(insts[4].effects[0]), "io");
+    assert!(ir::verify::verify_module(&module).is_ok());
+
+    let reparsed = ir::parse::parse_module(&render(&module)).expect("reparse");
+    assert_eq!(render(&reparsed), render(&module));
+}
+
+#[test]
+fn hand_written_ir_drives_passes_and_the_interpreter() {
+    let text = r#"
+module demo
+
+fn main() -> Int
+  block 0:
+    %0 = true : Bool
+    branch %0 -> bb1, bb2
+  block 1:
+    %1 = 40 : Int
+    jump bb3
+  block 2:
+    %2 = 0 : Int
+    jump bb3
+  block 3:
+    %3 = phi { bb1: %1, bb2: %2 } : Int
+    %4 = 2 : Int
+    %5 = + %3, %4 : Int
+    return %5
+"#;
+    let module = ir::parse::parse_module(text).expect("parse IR");
+    let mut simplified = module.clone();
+    ir::passes::PassManager::new()
+        .with_pass(ir::passes::SimplifyCfg)
+        .run(&mut simplified);
+
+    assert_eq!(simplified.functions[0].blocks.len(), 1);
+    let output = crate::interpreter::Interpreter::new(simplified)
+        .run()
+        .expect("interpreter run");
+    assert!(output.ends_with("42\n"), "unexpected output: {output}");
+}
+
+#[test]
+fn textual_ir_errors_name_the_offending_line() {
+    let missing_terminator = "module demo\nfn main()\n  block 0:\n    %0 = 1 : Int\n";
+    let err = ir::parse::parse_module(missing_terminator).expect_err("missing terminator");
+    assert!(err.message.contains("has no terminator"), "{}", err.message);
+
+    let bad_instruction = "module demo\nfn main()\n  block 0:\n    %0 = frob %1 : Int\n";
+    let err = ir::parse::parse_module(bad_instruction).expect_err("unknown instruction");
+    assert_eq!(err.kind, ErrorKind::Parse);
+    assert!(err.message.starts_with("IR line 4:"), "{}", err.message);
+    let (start, end) = err.span.expect("span");
+    assert_eq!(&bad_instruction[start..end], "    %0 = frob %1 : Int");
+
+    let err = ir::parse::parse_module("fn main()\n").expect_err("missing header");
+    assert!(err.message.contains("module"), "{}", err.message);
+}
@@ -0,0 +1,148 @@
+use super::*;
+use std::fs;
+
+use ir::serialize::{self, LoadError};
+
+fn render(module: &ir::Module) -> String {
+    backend::text::render_module(module)
+}
+
+fn example_modules() -> Vec<(String, ir::Module)> {
+    let mut modules = Vec::new();
+    for entry in fs::read_dir("examples").expect("examples directory") {
+        let path = entry.expect("directory entry").path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("mica") {
+            continue;
+        }
+        let source = fs::read_to_string(&path).expect("read example");
+        let Ok(ast) = parser::parse_module(&source) else {
+            continue;
+        };
+        let module = ir::lower_module(&helpers::lower_checked(&ast));
+        modules.push((path.display().to_string(), module));
+    }
+    modules
+}
+
+#[test]
+fn binary_and_json_round_trip_every_example() {
+    let modules = example_modules();
+    assert!(modules.len() > 10, "expected the example corpus");
+    for (path, module) in modules {
+        let bytes = serialize::to_bytes(&module);
+        let decoded = serialize::from_bytes(&bytes)
+            .unwrap_or_else(|err| panic!("{path} failed to decode: {err}"));
+        assert_eq!(
+            serialize::to_bytes(&decoded),
+            bytes,
+            "{path} binary drifted"
+        );
+        assert_eq!(render(&decoded), render(&module), "{path} changed");
+
+        let json = serialize::to_json(&module);
+        let decoded =
+            serialize::from_json(&json).unwrap_or_else(|err| panic!("{path} JSON: {err}"));
+        assert_eq!(serialize::to_json(&decoded), json, "{path} JSON drifted");
+        assert!(bytes.len() < json.len(), "{path} binary should be compact");
+    }
+}
+
+#[test]
+fn loaded_modules_keep_working_tables_and_run() {
+    let src = r#"
+module demo
+
+type Point = { x: Int, y: Int }
+
+fn shift(p: Point) -> Int {
+  p.x + p.y
+}
+
+fn main() -> Float {
+  let nan = 0.0 / 0.0
+  let p = Point { x: 40, y: 2 }
+  let total = shift(p)
+  if total > 40 { nan } else { 2.5 }
+}
+"#;
+    let module = ir::lower_module(&helpers::lower_checked(&helpers::parse(src)));
+
+    let unique = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("mica_ir_cache_{unique}.mirb"));
+    serialize::save(&module, &path).expect("save module");
+    let mut loaded = serialize::load(&path).expect("load module");
+    let _ = fs::remove_file(&path);
+
+    // Interning after a load must reuse the rebuilt indexes.
+    let point = loaded.types.lookup_named("Point").expect("alias restored");
+    let int = loaded.types.intern(ir::Type::Int);
+    assert_eq!(loaded.types.lookup_named("Int"), Some(int));
+    let count = loaded.types.entries().count();
+    let record = loaded.type_of(point).clone();
+    assert_eq!(loaded.types.intern(record), point);
+    assert_eq!(loaded.types.entries().count(), count);
+    assert_eq!(
+        loaded.effects.entries().count(),
+        module.effects.entries().count()
+    );
+
+    let expected = crate::interpreter::Interpreter::new(module.clone())
+        .run()
+        .expect("original run");
+    let actual = crate::interpreter::Interpreter::new(loaded)
+        .run()
+        .expect("loaded run");
+    assert_eq!(actual, expected);
+
+    let mut folded = module;
+    ir::passes::optimize(&mut folded);
+    assert!(render(&folded).contains("NaN"), "{}", render(&folded));
+    let reloaded = serialize::from_bytes(&serialize::to_bytes(&folded)).expect("reload");
+    assert_eq!(render(&reloaded), render(&folded));
+}
+
+#[test]
+fn loading_rejects_foreign_versions_and_corrupt_input() {
+    let src = "module demo\n\nfn main() -> Int {\n  1 + 2\n}\n";
+    let module = ir::lower_module(&helpers::lower_checked(&helpers::parse(src)));
+
+    let mut bytes = serialize::to_bytes(&module);
+    assert!(matches!(
+        serialize::from_bytes(&bytes[..3]),
+        Err(LoadError::Format(_))
+    ));
+    assert!(matches!(
+        serialize::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(LoadError::Format(_))
+    ));
+    bytes[4] = bytes[4].wrapping_add(1);
+    assert!(matches!(
+        serialize::from_bytes(&bytes),
+        Err(LoadError::SchemaVersion { found }) if found == serialize::SCHEMA_VERSION + 1
+    ));
+
+    let json = serialize::to_json(&module);
+    let stale = json.replacen(
+        &format!("\"schema_version\":{}", serialize::SCHEMA_VERSION),
+        "\"schema_version\":0",
+        1,
+    );
+    let err = serialize::from_json(&stale).expect_err("old schema");
+    assert!(err.to_string().contains("schema version 0"), "{err}");
+
+    let dangling = json.replacen("\"ret_type\":2", "\"ret_type\":999", 1);
+    assert_ne!(dangling, json);
+    assert!(matches!(
+        serialize::from_json(&dangling),
+        Err(LoadError::Invalid(message)) if message.contains("#999")
+    ));
+    let duplicate = json.replacen("\"Unknown\",\"Unit\"", "\"Unit\",\"Unit\"", 1);
+    assert_ne!(duplicate, json);
+    assert!(matches!(
+        serialize::from_json(&duplicate),
+        Err(LoadError::Format(message)) if message.contains("interned twice")
+    ));
+}
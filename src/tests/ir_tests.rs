@@ -13,7 +13,7 @@ fn add(x: Int, y: Int) -> Int {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
 
     assert_eq!(ir_module.name, vec!["demo".to_string()]);
@@ -52,7 +52,7 @@ fn forty_two() -> Int {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
 
     let func = ir_module
@@ -91,7 +91,7 @@ fn process(count: Int, data: Data) -> Unit !{io} {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
 
     let func = ir_module
@@ -150,7 +150,7 @@ fn make(flag: Bool) -> Point {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
 
     let point_id = ir_module
@@ -189,7 +189,7 @@ fn main(io: IO) -> Int !{io} {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
 
     let main_fn = ir_module
@@ -230,7 +230,7 @@ fn use_call() -> Int {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
 
     let call_inst = ir_module
@@ -259,7 +259,7 @@ fn identity(x: Int) -> Int {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
 
     let func = ir_module
@@ -298,7 +298,7 @@ fn pick(flag: Bool) -> Int {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
 
     let func = ir_module
@@ -392,7 +392,7 @@ fn choice(flag: Bool) -> Int {
 "#;
 
     let module = parse(src);
-    let hir = lower::lower_module(&module);
+    let hir = lower_checked(&module);
     let ir_module = ir::lower_module(&hir);
 
     let pure_fn = ir_module
@@ -470,3 +470,584 @@ fn choice(flag: Bool) -> Int {
         );
     }
 }
+
+#[test]
+fn mutable_locals_are_promoted_to_ssa_phis() {
+    let src = r#"
+module demo
+
+fn countdown(start: Int) -> Int {
+  let mut value = start
+  while value > 0 {
+    value = value - 1
+  }
+  value
+}
+"#;
+
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+
+    let func = ir_module
+        .functions
+        .iter()
+        .find(|f| f.name == "countdown")
+        .expect("function");
+
+    let calls_assign = func
+        .blocks
+        .iter()
+        .flat_map(|block| block.instructions.iter())
+        .any(|inst| matches!(&inst.kind, ir::InstKind::Call { func: ir::FuncRef::Method(name), .. } if name == "assign"));
+    assert!(
+        !calls_assign,
+        "assignments should not lower to method calls"
+    );
+
+    let header = func
+        .blocks
+        .iter()
+        .find(|block| matches!(block.terminator, ir::Terminator::Branch { .. }))
+        .expect("loop header");
+    let phi = header
+        .instructions
+        .iter()
+        .find_map(|inst| match &inst.kind {
+            ir::InstKind::Phi { incomings } => Some((inst, incomings)),
+            _ => None,
+        })
+        .expect("loop header phi");
+    assert_eq!(ir_module.type_of(phi.0.ty), &ir::Type::Int);
+    assert_eq!(phi.1.len(), 2, "entry and back-edge incomings");
+    assert!(
+        phi.1
+            .iter()
+            .any(|(block, value)| block.index() == 0 && *value == func.params[0].value),
+        "entry incoming should be the parameter: {:?}",
+        phi.1
+    );
+
+    let exit = func
+        .blocks
+        .iter()
+        .find(|block| matches!(block.terminator, ir::Terminator::Return(Some(_))))
+        .expect("exit block");
+    match &exit.terminator {
+        ir::Terminator::Return(Some(value)) => assert_eq!(*value, phi.0.id),
+        other => panic!("expected return of loop-carried value, got {other:?}"),
+    }
+}
+
+#[test]
+fn branches_assigning_mutable_locals_merge_through_phi() {
+    let src = r#"
+module demo
+
+fn clamp(x: Int) -> Int {
+  let mut result = x
+  if x > 10 {
+    result = 10
+  }
+  result
+}
+"#;
+
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+    let func = &ir_module.functions[0];
+
+    let merge = func.blocks.last().expect("merge block");
+    let phis: Vec<_> = merge
+        .instructions
+        .iter()
+        .filter_map(|inst| match &inst.kind {
+            ir::InstKind::Phi { incomings } => Some((inst.id, incomings.clone())),
+            _ => None,
+        })
+        .collect();
+    let (result_phi, incomings) = phis
+        .iter()
+        .find(|(_, incomings)| {
+            incomings
+                .iter()
+                .any(|(_, value)| *value == func.params[0].value)
+        })
+        .expect("phi merging the parameter with the assigned literal");
+    assert_eq!(incomings.len(), 2);
+    match &merge.terminator {
+        ir::Terminator::Return(Some(value)) => assert_eq!(value, result_phi),
+        other => panic!("expected return of merged value, got {other:?}"),
+    }
+}
+
+#[test]
+fn interpreter_evaluates_loop_carried_values() {
+    let src = r#"
+module demo
+
+fn fib(n: Int) -> Int {
+  let mut a = 0
+  let mut b = 1
+  let mut k = 0
+  while k < n {
+    let t = a
+    a = b
+    b = t + b
+    k = k + 1
+  }
+  a
+}
+
+fn factorial(n: Int) -> Int {
+  let mut acc = 1
+  let mut i = 0
+  loop {
+    if i == 0 {
+      i = i + 1
+      continue
+    }
+    if i > n {
+      break
+    }
+    acc = acc * i
+    i = i + 1
+  }
+  acc
+}
+
+fn main() -> Int {
+  fib(10) + factorial(5)
+}
+"#;
+
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+    let output = crate::interpreter::Interpreter::new(ir_module)
+        .run()
+        .expect("interpreter run");
+    assert!(output.ends_with("175\n"), "unexpected output: {output}");
+}
+
+#[test]
+fn list_operations_lower_to_list_instructions() {
+    let src = r#"
+module demo
+
+fn total(xs: [Int]) -> Int {
+  let mut sum = 0
+  for x in xs {
+    sum = sum + x
+  }
+  sum
+}
+
+fn main() -> Int {
+  let xs = [1, 2]
+  xs.push(3)
+  total(xs) + xs.len() + xs[0]
+}
+"#;
+
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+
+    let main_fn = ir_module
+        .functions
+        .iter()
+        .find(|f| f.name == "main")
+        .expect("main");
+    let kinds: Vec<_> = main_fn
+        .blocks
+        .iter()
+        .flat_map(|block| block.instructions.iter())
+        .collect();
+    let literal = kinds
+        .iter()
+        .find(|inst| matches!(inst.kind, ir::InstKind::List { .. }))
+        .expect("list literal");
+    match ir_module.type_of(literal.ty) {
+        ir::Type::List(element) => assert_eq!(ir_module.type_of(*element), &ir::Type::Int),
+        other => panic!("expected list type, got {other:?}"),
+    }
+    assert!(
+        kinds
+            .iter()
+            .any(|inst| matches!(inst.kind, ir::InstKind::ListPush { .. }))
+    );
+    assert!(
+        kinds
+            .iter()
+            .any(|inst| matches!(inst.kind, ir::InstKind::ListLen { .. }))
+    );
+    let index = kinds
+        .iter()
+        .find(|inst| matches!(inst.kind, ir::InstKind::ListIndex { .. }))
+        .expect("index");
+    assert_eq!(ir_module.type_of(index.ty), &ir::Type::Int);
+
+    let total_fn = ir_module
+        .functions
+        .iter()
+        .find(|f| f.name == "total")
+        .expect("total");
+    let header = total_fn
+        .blocks
+        .iter()
+        .find(|block| matches!(block.terminator, ir::Terminator::Branch { .. }))
+        .expect("loop header");
+    assert!(
+        header
+            .instructions
+            .iter()
+            .any(|inst| matches!(inst.kind, ir::InstKind::ListLen { .. })),
+        "for loops compare the counter against the list length"
+    );
+
+    let output = crate::interpreter::Interpreter::new(ir_module)
+        .run()
+        .expect("interpreter run");
+    assert!(output.ends_with("10\n"), "unexpected output: {output}");
+}
+
+#[test]
+fn interpreter_reports_out_of_bounds_list_index() {
+    let src = r#"
+module demo
+
+fn main() -> Int {
+  let xs = [1, 2]
+  xs[2]
+}
+"#;
+
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    let ir_module = ir::lower_module(&hir);
+    let err = crate::interpreter::Interpreter::new(ir_module)
+        .run()
+        .expect_err("index past the end");
+    assert!(err.contains("out of bounds"), "unexpected error: {err}");
+}
+
+fn verify_src(src: &str) -> ir::Module {
+    let module = parse(src);
+    let hir = lower_checked(&module);
+    ir::lower_module(&hir)
+}
+
+fn violation_kinds(module: &ir::Module) -> Vec<ir::verify::ViolationKind> {
+    ir::verify::verify_module(module)
+        .violations
+        .iter()
+        .map(|violation| violation.kind)
+        .collect()
+}
+
+#[test]
+fn verifier_accepts_lowered_control_flow() {
+    let module = verify_src(
+        r#"
+module demo
+
+fn sum(xs: [Int]) -> Int {
+  let mut total = 0
+  for x in xs {
+    if x > 10 { break }
+    total = total + x
+  }
+  total
+}
+
+fn main() -> Int {
+  sum([1, 2, 3])
+}
+"#,
+    );
+    let report = ir::verify::verify_module(&module);
+    assert!(
+        report.is_ok(),
+        "unexpected violations: {:?}",
+        report.violations
+    );
+}
+
+#[test]
+fn verifier_reports_broken_dominance_targets_and_phis() {
+    let src = r#"
+module demo
+
+fn pick(flag: Bool, x: Int) -> Int {
+  let y = if flag { x + 1 } else { x }
+  y * 2
+}
+"#;
+
+    // Moving the literal after its use breaks dominance within the block.
+    let mut module = verify_src(src);
+    let merge = module.functions[0]
+        .blocks
+        .iter_mut()
+        .find(|block| {
+            block
+                .instructions
+                .iter()
+                .any(|inst| matches!(inst.kind, ir::InstKind::Phi { .. }))
+        })
+        .expect("merge block");
+    let len = merge.instructions.len();
+    merge.instructions.swap(len - 2, len - 1);
+    assert!(violation_kinds(&module).contains(&ir::verify::ViolationKind::UseNotDominated));
+
+    // Dropping a phi incoming leaves one predecessor uncovered.
+    let mut module = verify_src(src);
+    for block in &mut module.functions[0].blocks {
+        for inst in &mut block.instructions {
+            if let ir::InstKind::Phi { incomings } = &mut inst.kind {
+                incomings.pop();
+            }
+        }
+    }
+    assert!(violation_kinds(&module).contains(&ir::verify::ViolationKind::PhiIncomings));
+
+    // Jumping to a block that does not exist.
+    let mut module = verify_src(src);
+    for block in &mut module.functions[0].blocks {
+        if let ir::Terminator::Jump(target) = &mut block.terminator {
+            *target = ir::BlockId(99);
+        }
+    }
+    assert!(violation_kinds(&module).contains(&ir::verify::ViolationKind::UnknownBlock));
+}
+
+#[test]
+fn verifier_reports_call_arity_and_operand_types() {
+    let src = r#"
+module demo
+
+fn add(a: Int, b: Int) -> Int {
+  a + b
+}
+
+fn main() -> Int {
+  add(1, 2) + 1.5
+}
+"#;
+    let mut module = verify_src(src);
+    let kinds = violation_kinds(&module);
+    assert_eq!(
+        kinds,
+        vec![ir::verify::ViolationKind::OperandTypeMismatch],
+        "Int + Float should be the only violation"
+    );
+
+    let main_fn = module
+        .functions
+        .iter_mut()
+        .find(|f| f.name == "main")
+        .expect("main");
+    for block in &mut main_fn.blocks {
+        for inst in &mut block.instructions {
+            if let ir::InstKind::Call { args, .. } = &mut inst.kind {
+                args.pop();
+            }
+        }
+    }
+    let report = ir::verify::verify_module(&module);
+    let arity = report
+        .violations
+        .iter()
+        .find(|violation| violation.kind == ir::verify::ViolationKind::CallArity)
+        .expect("arity violation");
+    assert_eq!(arity.function, "main");
+    assert!(
+        arity
+            .to_string()
+            .contains("passes 1 arguments but it declares 2"),
+        "{arity}"
+    );
+}
+
+#[test]
+fn lowering_records_source_provenance() {
+    let src = r#"
+module demo
+
+fn add(x: Int, y: Int) -> Int {
+  let sum = x + y
+  return sum
+}
+"#;
+
+    let module = ir::lower_module(&lower_checked(&parse(src)));
+    let block = &module.functions[0].blocks[0];
+
+    let binary = block.instructions[0]
+        .provenance
+        .as_ref()
+        .expect("instruction provenance");
+    assert_eq!(binary.function, "add");
+    assert_eq!(binary.span.line, 5);
+    assert_eq!(binary.node, ir::HirNode::Binary);
+
+    let ret = block
+        .terminator_provenance
+        .as_ref()
+        .expect("terminator provenance");
+    assert_eq!(ret.span.line, 6);
+    assert_eq!(ret.node, ir::HirNode::Return);
+
+    let text = backend::text::render_module_with_provenance(&module);
+    assert!(text.contains("    ; add 5:3 binary"), "{text}");
+    assert!(text.contains("    ; add 6:3 return"), "{text}");
+    assert!(!backend::text::render_module(&module).contains("; add"));
+
+    let reparsed = ir::parse::parse_module(&text).expect("comments are ignored");
+    assert_eq!(
+        backend::text::render_module(&reparsed),
+        backend::text::render_module(&module)
+    );
+}
+
+#[test]
+fn lowering_types_expressions_from_the_checker() {
+    let src = r#"
+module demo
+
+type Row = { qty: Int, price: F64 }
+
+fn apply(f: fn(Int) -> Int, x: Int) -> Int {
+  f(x)
+}
+
+fn total(rows: List[Row]) -> F64 {
+  let mut sum = 0.0
+  for r in rows {
+    sum = sum + r.price
+  }
+  sum
+}
+"#;
+
+    let ast = parse(src);
+    assert!(check::check_module(&ast).diagnostics.is_empty());
+    let module = ir::lower_module(&lower_checked(&ast));
+
+    for func in &module.functions {
+        assert_ne!(module.type_of(func.ret_type), &ir::Type::Unknown);
+        for inst in func.blocks.iter().flat_map(|block| &block.instructions) {
+            assert_ne!(
+                module.type_of(inst.ty),
+                &ir::Type::Unknown,
+                "{}",
+                backend::text::render_module(&module)
+            );
+        }
+    }
+}
+
+#[test]
+fn lowering_types_comparisons_as_bool() {
+    let src = r#"
+module demo
+
+fn below(a: Int, b: Int) -> Bool {
+  a < b
+}
+"#;
+
+    let module = ir::lower_module(&lower_checked(&parse(src)));
+    let compare = module.functions[0].blocks[0]
+        .instructions
+        .iter()
+        .find(|inst| matches!(inst.kind, ir::InstKind::Binary { .. }))
+        .expect("comparison");
+    assert_eq!(module.type_of(compare.ty), &ir::Type::Bool);
+}
+
+#[test]
+fn lowering_keeps_checked_types_over_guesses() {
+    let src = r#"
+module demo
+
+type Result[T, E] = Ok(T) | Err(E)
+type Option[T] = Some(T) | None
+
+fn log(io: IO) !{io} {
+  io.println("value")
+}
+
+fn halve(n: Int) -> Result[Int, String] {
+  if n > 0 { Ok(n / 2) } else { Err("negative") }
+}
+
+fn quarter(n: Int) -> Result[Int, String] {
+  let half = halve(n)?
+  halve(half)
+}
+
+fn to_option(r: Result[Int, String]) -> Option[Int] {
+  match r {
+    Ok(v) => Some(v + 1),
+    Err(_) => None,
+  }
+}
+"#;
+
+    let ast = parse(src);
+    assert!(check::check_module(&ast).diagnostics.is_empty());
+    let module = ir::lower_module(&lower_checked(&ast));
+    let text = backend::text::render_module(&module);
+
+    let log = module
+        .functions
+        .iter()
+        .find(|func| func.name == "log")
+        .expect("log");
+    assert_eq!(module.type_of(log.ret_type), &ir::Type::Unit, "{text}");
+    for func in &module.functions {
+        for inst in func.blocks.iter().flat_map(|block| &block.instructions) {
+            assert_ne!(module.type_of(inst.ty), &ir::Type::Unknown, "{text}");
+        }
+    }
+    assert!(text.contains("payload %0, Ok, 0 : Int"), "{text}");
+}
+
+#[test]
+fn lowering_turns_matches_into_variant_tests() {
+    let src = r#"
+module demo
+
+type Shape = Circle(Float) | Rect(Float, Float) | Empty
+
+fn width(s: Shape) -> Float {
+  match s {
+    Rect(w, _) => w,
+    _ => 0.0,
+  }
+}
+
+fn main() -> Float {
+  width(Rect(2.5, 1.0)) + width(Empty)
+}
+"#;
+
+    let module = ir::lower_module(&lower_checked(&parse(src)));
+    let text = backend::text::render_module(&module);
+    assert!(text.contains("type Shape = Circle(Float) | Rect(Float, Float) | Empty\n"));
+    assert!(text.contains(
+        "  block 0:\n    %1 = is %0, Rect : Bool\n    branch %1 -> bb3, bb2\n  block 3:\n    %2 = payload %0, Rect, 0 : Float\n    jump bb1\n"
+    ));
+    assert!(text.contains("%2 = variant Rect(%0, %1) : Shape\n"));
+    assert!(text.contains("%4 = variant Empty : Shape\n"));
+    // The wildcard arm leaves no way to fall off the end.
+    assert!(!text.contains("trap"), "{text}");
+
+    let output = crate::interpreter::Interpreter::new(module)
+        .run()
+        .expect("interpreter run");
+    assert!(output.ends_with("2.5\n"), "unexpected output: {output}");
+}
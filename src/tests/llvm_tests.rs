@@ -0,0 +1,561 @@
+use std::collections::{HashMap, HashSet};
+
+use super::helpers::*;
+use super::*;
+use crate::backend::llvm::{LlvmBackend, LlvmModule};
+use crate::backend::{BackendOptions, BackendResult};
+
+fn compile(src: &str, options: &BackendOptions) -> BackendResult<LlvmModule> {
+    let typed = ir::lower_module(&lower_checked(&parse(src)));
+    backend::run(&LlvmBackend::default(), &typed, options)
+}
+
+/// Structural checks on emitted LLVM text: every global symbol and metadata
+/// node is defined, locals are defined once with unnamed values numbered
+/// from zero, branch targets exist, and operands have the types the
+/// instructions using them expect.
+fn validate(ir: &str) -> Result<(), String> {
+    let mut globals = HashSet::new();
+    let mut signatures: HashMap<String, (String, Vec<String>)> = HashMap::new();
+    let mut metadata = HashSet::new();
+    let mut records = HashMap::new();
+    for line in ir.lines() {
+        if let Some(rest) = line.strip_prefix('%')
+            && let Some((name, body)) = rest.split_once(" = type ")
+        {
+            records.insert(format!("%{name}"), body.to_string());
+        } else if let Some(rest) = line.strip_prefix('@') {
+            globals.insert(rest.split(' ').next().unwrap().to_string());
+        } else if let Some(rest) = line.strip_prefix('!')
+            && let Some((id, _)) = rest.split_once(" = ")
+        {
+            metadata.insert(id.to_string());
+        } else if line.starts_with("declare ") || line.starts_with("define ") {
+            let (name, ret, params) = parse_signature(line)?;
+            let params = params.into_iter().map(|(ty, _)| ty).collect();
+            globals.insert(name.clone());
+            if signatures.insert(name.clone(), (ret, params)).is_some() {
+                return Err(format!("@{name} is defined twice"));
+            }
+        }
+    }
+
+    for line in ir.lines() {
+        for symbol in references(line, '@') {
+            if !globals.contains(&symbol) {
+                return Err(format!("@{symbol} is not declared: {line}"));
+            }
+        }
+        for node in references(line, '!') {
+            let numbered = node.chars().all(|ch| ch.is_ascii_digit());
+            if numbered && !metadata.contains(&node) {
+                return Err(format!("!{node} is not defined: {line}"));
+            }
+        }
+    }
+
+    let mut lines = ir.lines();
+    while let Some(line) = lines.next() {
+        if !line.starts_with("define ") {
+            continue;
+        }
+        let body: Vec<&str> = lines.by_ref().take_while(|line| *line != "}").collect();
+        validate_function(line, &body, &signatures, &records)?;
+    }
+    Ok(())
+}
+
+fn validate_function(
+    header: &str,
+    body: &[&str],
+    signatures: &HashMap<String, (String, Vec<String>)>,
+    records: &HashMap<String, String>,
+) -> Result<(), String> {
+    let (name, ret, params) = parse_signature(header)?;
+    let mut locals: HashMap<String, String> = HashMap::new();
+    for (ty, local) in params {
+        if locals.insert(local.clone(), ty).is_some() {
+            return Err(format!("@{name}: parameter %{local} is repeated"));
+        }
+    }
+    let labels: HashSet<String> = body
+        .iter()
+        .filter_map(|line| line.strip_suffix(':'))
+        .map(str::to_string)
+        .collect();
+
+    // Definitions first, since phis may refer to values defined later.
+    let mut next = 0;
+    for line in body {
+        let Some((local, rhs)) = definition(line) else {
+            continue;
+        };
+        if local.chars().all(|ch| ch.is_ascii_digit()) {
+            if local != next.to_string() {
+                return Err(format!("@{name}: expected %{next}, found %{local}"));
+            }
+            next += 1;
+        }
+        let ty = result_type(rhs, signatures, records)?;
+        if locals.insert(local.clone(), ty).is_some() {
+            return Err(format!("@{name}: %{local} is defined twice"));
+        }
+    }
+
+    let expect = |ty: &str, operand: &str, line: &str| -> Result<(), String> {
+        let Some(local) = operand.strip_prefix('%') else {
+            return Ok(());
+        };
+        match locals.get(local) {
+            Some(found) if found == ty => Ok(()),
+            Some(found) => Err(format!(
+                "@{name}: %{local} is {found}, expected {ty}: {line}"
+            )),
+            None => Err(format!("@{name}: %{local} is not defined: {line}")),
+        }
+    };
+
+    for line in body {
+        let code = instruction(line);
+        for local in references(code, '%') {
+            let is_type = local.starts_with("record.");
+            if !is_type && !labels.contains(&local) && !locals.contains_key(&local) {
+                return Err(format!("@{name}: %{local} is not defined: {line}"));
+            }
+        }
+        for (index, _) in code.match_indices("label %") {
+            let target: String = code[index + 7..]
+                .chars()
+                .take_while(|ch| ch.is_ascii_alphanumeric())
+                .collect();
+            if !labels.contains(&target) {
+                return Err(format!("@{name}: no block {target}: {line}"));
+            }
+        }
+
+        let rhs = definition(line).map_or(code, |(_, rhs)| rhs);
+        let words: Vec<&str> = rhs.split_whitespace().collect();
+        match words.as_slice() {
+            ["ret", "void"] if ret != "void" => {
+                return Err(format!("@{name}: ret void in a {ret} function"));
+            }
+            ["ret", ty, value] => {
+                if *ty != ret {
+                    return Err(format!("@{name}: ret {ty} in a {ret} function"));
+                }
+                expect(ty, value, line)?;
+            }
+            ["br", "i1", condition, ..] => {
+                expect("i1", condition.trim_end_matches(','), line)?;
+            }
+            ["store", ty, value, "ptr", slot] => {
+                expect(ty, value.trim_end_matches(','), line)?;
+                expect("ptr", slot, line)?;
+            }
+            [op, ty, lhs, rhs] if is_binary(op) => {
+                expect(ty, lhs.trim_end_matches(','), line)?;
+                expect(ty, rhs, line)?;
+            }
+            [_, _, ty, lhs, rhs] if words[0] == "icmp" || words[0] == "fcmp" => {
+                expect(ty, lhs.trim_end_matches(','), line)?;
+                expect(ty, rhs, line)?;
+            }
+            ["phi", ty, ..] => {
+                for incoming in rhs.split('[').skip(1) {
+                    let value = incoming.trim().split(',').next().unwrap_or_default();
+                    expect(ty, value, line)?;
+                }
+            }
+            _ => {}
+        }
+        if let Some(call) = rhs.split("call ").nth(1) {
+            let (callee, declared_ret, args) = parse_call(call)?;
+            let (ret, params) = signatures
+                .get(&callee)
+                .ok_or_else(|| format!("@{name}: call to undeclared @{callee}"))?;
+            if *ret != declared_ret || params.len() != args.len() {
+                return Err(format!("@{name}: call does not match @{callee}: {line}"));
+            }
+            for ((ty, value), param) in args.iter().zip(params) {
+                if ty != param {
+                    return Err(format!("@{name}: argument {ty} for {param}: {line}"));
+                }
+                expect(ty, value, line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The instruction on `line` without its debug location and comment.
+fn instruction(line: &str) -> &str {
+    let line = line.split(';').next().unwrap_or_default();
+    line.split(", !dbg").next().unwrap_or_default().trim()
+}
+
+fn definition(line: &str) -> Option<(String, &str)> {
+    let code = instruction(line);
+    let rest = code.strip_prefix('%')?;
+    let (local, rhs) = rest.split_once(" = ")?;
+    Some((local.to_string(), rhs))
+}
+
+fn is_binary(op: &str) -> bool {
+    [
+        "add", "sub", "mul", "sdiv", "srem", "and", "or", "fadd", "fsub", "fmul", "fdiv", "frem",
+    ]
+    .contains(&op)
+}
+
+fn result_type(
+    rhs: &str,
+    signatures: &HashMap<String, (String, Vec<String>)>,
+    records: &HashMap<String, String>,
+) -> Result<String, String> {
+    let words: Vec<&str> = rhs.split_whitespace().collect();
+    let after = |keyword: &str| -> String {
+        let rest = rhs.split_once(keyword).map_or("", |(_, rest)| rest).trim();
+        leading_type(rest).to_string()
+    };
+    Ok(match words.first().copied().unwrap_or_default() {
+        "icmp" | "fcmp" => "i1".to_string(),
+        "getelementptr" | "alloca" => "ptr".to_string(),
+        "bitcast" => after(" to "),
+        "load" | "phi" | "insertvalue" => after(words[0]).trim_end_matches(',').to_string(),
+        "select" => after("i1 true,"),
+        "extractvalue" => {
+            let aggregate = after("extractvalue");
+            let body = records.get(&aggregate).unwrap_or(&aggregate);
+            let fields = body.trim_start_matches('{').trim_end_matches('}');
+            let index: usize = words
+                .last()
+                .and_then(|index| index.parse().ok())
+                .unwrap_or(0);
+            split_list(fields)
+                .get(index)
+                .map(|field| field.to_string())
+                .ok_or_else(|| format!("no field {index}: {rhs}"))?
+        }
+        "call" | "tail" | "musttail" => {
+            let call = rhs.split("call ").nth(1).unwrap_or_default();
+            let (callee, ret, _) = parse_call(call)?;
+            signatures
+                .get(&callee)
+                .map_or(ret, |(declared, _)| declared.clone())
+        }
+        op if is_binary(op) => words[1].to_string(),
+        other => return Err(format!("unexpected instruction '{other}': {rhs}")),
+    })
+}
+
+/// The type at the start of `text`, which may be a braced struct.
+fn leading_type(text: &str) -> &str {
+    if text.starts_with('{') {
+        let end = text.find('}').map_or(text.len(), |end| end + 1);
+        &text[..end]
+    } else {
+        text.split([' ', ',']).next().unwrap_or_default()
+    }
+}
+
+/// A symbol, its return type, and `(type, operand)` pairs for its
+/// parameters or arguments.
+type Signature = (String, String, Vec<(String, String)>);
+
+/// Splits `ty @name(args)` after a `call` into the callee, its return type
+/// and the typed arguments.
+fn parse_call(call: &str) -> Result<Signature, String> {
+    let (ret, rest) = call
+        .split_once(" @")
+        .ok_or_else(|| format!("malformed call: {call}"))?;
+    let (callee, args) = rest
+        .split_once('(')
+        .ok_or_else(|| format!("malformed call: {call}"))?;
+    let args = args.rsplit_once(')').map_or("", |(args, _)| args);
+    let args = split_list(args)
+        .into_iter()
+        .map(|arg| {
+            let (ty, value) = arg.rsplit_once(' ').unwrap_or((arg, ""));
+            (ty.to_string(), value.to_string())
+        })
+        .collect();
+    Ok((callee.to_string(), ret.to_string(), args))
+}
+
+/// Parses a `define` or `declare` line into its symbol, return type and
+/// parameters as `(type, name)` pairs; declarations have empty names.
+fn parse_signature(line: &str) -> Result<Signature, String> {
+    let rest = line
+        .strip_prefix("define ")
+        .or_else(|| line.strip_prefix("declare "))
+        .unwrap_or(line);
+    let (ret, rest) = rest
+        .split_once(" @")
+        .ok_or_else(|| format!("malformed signature: {line}"))?;
+    let (name, rest) = rest
+        .split_once('(')
+        .ok_or_else(|| format!("malformed signature: {line}"))?;
+    let params = rest.rsplit_once(')').map_or("", |(params, _)| params);
+    let params = split_list(params)
+        .into_iter()
+        .map(|param| match param.rsplit_once(" %") {
+            Some((ty, local)) => (ty.to_string(), local.to_string()),
+            None => (param.to_string(), String::new()),
+        })
+        .collect();
+    Ok((name.to_string(), ret.to_string(), params))
+}
+
+/// Splits a comma separated list, keeping braced struct types together.
+fn split_list(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (index, ch) in list.char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(list[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    if !list[start..].trim().is_empty() {
+        parts.push(list[start..].trim());
+    }
+    parts
+}
+
+/// Names following `sigil` on `line`, outside string constants.
+fn references(line: &str, sigil: char) -> Vec<String> {
+    let line = match line.find(" c\"") {
+        Some(index) => &line[..index],
+        None => line,
+    };
+    let line = line.split(';').next().unwrap_or_default();
+    let mut names = Vec::new();
+    let mut rest = line;
+    while let Some(index) = rest.find(sigil) {
+        rest = &rest[index + 1..];
+        let name: String = rest
+            .chars()
+            .take_while(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '.'))
+            .collect();
+        if !name.is_empty() {
+            names.push(name);
+        }
+    }
+    names
+}
+
+const EVERY_CONSTRUCT: &str = r#"
+module demo.llvm_all
+
+type Point = { x: Int, y: Float }
+type Empty = {}
+
+fn ratio(a: Float, b: Float) -> Bool {
+  a / b > 1.5
+}
+
+fn same(a: String, b: String) -> Bool {
+  a == b
+}
+
+fn build(n: Int) -> Point {
+  Point { x: n, y: 2.25 }
+}
+
+fn empty() -> Empty {
+  Empty {}
+}
+
+fn total(xs: [Int]) -> Int {
+  let mut sum = 0
+  for x in xs {
+    sum = sum + x
+  }
+  sum
+}
+
+fn greet(io: IO, name: String) !{io} {
+  if same(name, "w\"orld") {
+    io.println("hello")
+  } else {
+    io.println("who?")
+  }
+}
+
+fn is_even(n: Int) -> Bool {
+  if n == 0 { true } else { is_odd(n - 1) }
+}
+
+fn is_odd(n: Int) -> Bool {
+  if n == 0 { false } else { is_even(n - 1) }
+}
+
+fn main(io: IO) -> Int {
+  let xs = [1, 2, 3]
+  xs.push(4)
+  greet(io, "w")
+  let p = build(3)
+  let e = empty()
+  let elapsed = io.now_millis()
+  if ratio(p.y, 1.0) && is_even(4) { total(xs) + xs.len() + xs[0] + p.x } else { elapsed }
+}
+"#;
+
+#[test]
+fn llvm_validator_rejects_malformed_modules() {
+    let gap = "define i64 @f() {\nbb0:\n  %1 = add i64 0, 1\n  ret i64 %1\n}\n";
+    assert!(validate(gap).unwrap_err().contains("expected %0"));
+    let undeclared = "define void @f() {\nbb0:\n  call void @g()\n  ret void\n}\n";
+    assert!(validate(undeclared).unwrap_err().contains("@g"));
+    let mistyped = "define i64 @f(i1 %b) {\nbb0:\n  %0 = add i64 %b, 1\n  ret i64 %0\n}\n";
+    assert!(validate(mistyped).unwrap_err().contains("%b is i1"));
+    let missing_block = "define void @f() {\nbb0:\n  br label %bb3\n}\n";
+    assert!(validate(missing_block).unwrap_err().contains("bb3"));
+}
+
+#[test]
+fn llvm_lowers_every_construct_to_a_well_formed_module() {
+    let output = compile(EVERY_CONSTRUCT, &BackendOptions::default()).expect("llvm output");
+    let ir = output.as_str();
+    validate(ir).unwrap_or_else(|err| panic!("{err}\n{ir}"));
+
+    assert!(ir.contains("%record.Empty = type {}"));
+    assert!(ir.contains("c\"w\\22orld\\00\""));
+    assert!(ir.contains("fadd double 0.0, 0x4002000000000000"));
+    assert!(ir.contains("call i32 @strcmp(ptr %a, ptr %b)"));
+    assert!(ir.contains("declare void @mica_runtime_io_write_line(ptr)"));
+    assert!(ir.contains("call void @mica_runtime_io_write_line(ptr"));
+    assert!(ir.contains("declare i64 @mica_runtime_time_now_millis()"));
+    assert!(ir.contains("call i64 @mica_runtime_time_now_millis()"));
+    assert!(ir.contains("insertvalue %record.Point %t"));
+    assert!(ir.contains("select i1 true, %record.Empty zeroinitializer"));
+    assert!(ir.contains("extractvalue %record.Point"));
+    assert!(ir.contains("call ptr @mica_list_new(i64 8, i64 3)"));
+    assert!(ir.contains("call i64 @mica_list_len(ptr"));
+    assert!(ir.contains("call ptr @mica_list_at(ptr"));
+    assert!(ir.contains("musttail call i1 @is_odd(i64"));
+    // A single entry-block slot serves every push.
+    assert_eq!(ir.matches("alloca").count(), 1);
+}
+
+#[test]
+fn llvm_declares_external_functions_and_globals() {
+    let src = r#"
+module demo.llvm_external
+
+fn double(x: Int) -> Int {
+  x * 2
+}
+
+fn run(x: Int) -> Int {
+  let f = double
+  let limit = limits::max
+  let u = ()
+  let clamped = clamp(x, 2)
+  x.double() + x
+}
+"#;
+    let output = compile(src, &BackendOptions::default()).expect("llvm output");
+    let ir = output.as_str();
+    validate(ir).unwrap_or_else(|err| panic!("{err}\n{ir}"));
+    assert!(ir.contains("bitcast ptr @double to ptr"));
+    assert!(ir.contains("@limits__max = external global ptr"));
+    assert!(ir.contains("declare ptr @clamp(i64, i64)"));
+    assert!(ir.contains("call i64 @double(i64 %x)"));
+}
+
+#[test]
+fn llvm_debug_info_attaches_source_locations() {
+    let options = BackendOptions {
+        debug_info: true,
+        ..BackendOptions::default()
+    };
+    let output = compile(EVERY_CONSTRUCT, &options).expect("llvm output");
+    let ir = output.as_str();
+    validate(ir).unwrap_or_else(|err| panic!("{err}\n{ir}"));
+
+    assert!(ir.contains("!llvm.dbg.cu = !{!0}"));
+    assert!(ir.contains("!DIFile(filename: \"demo/llvm_all.mica\", directory: \".\")"));
+    assert!(ir.contains("!\"Debug Info Version\", i32 3"));
+    assert!(ir.contains("distinct !DISubprogram(name: \"ratio\""));
+    assert!(ir.contains("!DILocation(line: 8, column: 3, scope: !"));
+    let header = ir
+        .lines()
+        .find(|line| line.starts_with("define i1 @ratio"))
+        .unwrap();
+    assert!(header.ends_with('{') && header.contains(" !dbg !"));
+    // Every instruction and terminator carries a location.
+    for line in ir.lines().filter(|line| line.starts_with("  ")) {
+        let code = line.trim_start();
+        if !code.starts_with(';') {
+            assert!(line.contains(", !dbg !"), "no location: {line}");
+        }
+    }
+
+    let plain = compile(EVERY_CONSTRUCT, &BackendOptions::default()).expect("llvm output");
+    assert!(!plain.as_str().contains("!dbg"));
+
+    let mut options = options;
+    options
+        .source_files
+        .insert("demo.llvm_all".to_string(), "examples/all.mica".into());
+    let output = compile(EVERY_CONSTRUCT, &options).expect("llvm output");
+    assert!(
+        output
+            .as_str()
+            .contains("!1 = !DIFile(filename: \"examples/all.mica\", directory: \".\")")
+    );
+}
+
+#[test]
+fn llvm_honours_the_target_triple() {
+    let options = BackendOptions {
+        target_triple: Some("aarch64-unknown-linux-gnu".to_string()),
+        ..BackendOptions::default()
+    };
+    let output = compile(EVERY_CONSTRUCT, &options).expect("llvm output");
+    assert_eq!(
+        output.target_triple.as_deref(),
+        Some("aarch64-unknown-linux-gnu")
+    );
+    assert!(
+        output
+            .as_str()
+            .contains("target triple = \"aarch64-unknown-linux-gnu\"")
+    );
+    assert!(
+        output.as_str().contains(
+            "target datalayout = \"e-m:e-i8:8:32-i16:16:32-i64:64-i128:128-n32:64-S128\""
+        )
+    );
+
+    let backend = LlvmBackend {
+        target_triple: Some("x86_64-pc-linux-gnu".to_string()),
+    };
+    let typed = ir::lower_module(&lower_checked(&parse(EVERY_CONSTRUCT)));
+    let output = backend::run(&backend, &typed, &options).expect("llvm output");
+    assert!(
+        output
+            .as_str()
+            .contains("target triple = \"x86_64-pc-linux-gnu\"")
+    );
+    assert!(output.as_str().contains("-f80:128-"));
+}
+
+#[test]
+fn llvm_rejects_arithmetic_on_untyped_values() {
+    let src = r#"
+module demo.llvm_untyped
+
+fn run(x: Int) -> Int {
+  missing(x) * 2
+}
+"#;
+    let err = compile(src, &BackendOptions::default()).expect_err("untyped arithmetic");
+    assert!(err.to_string().contains("'*'"), "unexpected error: {err}");
+}
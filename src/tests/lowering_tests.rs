@@ -9,7 +9,7 @@ fn lower_method_call() {
       fn f(a: V, b: V) -> V { a.add(b) }
     "#;
     let m = parse(src);
-    let h = lower::lower_module(&m);
+    let h = lower_checked(&m);
     let s = lower::hir_to_string(&h);
     assert!(s.contains("add(a, b)"));
 }
@@ -18,6 +18,7 @@ fn lower_method_call() {
 fn lower_covers_all_expression_kinds() {
     let using_body = Block {
         statements: vec![Stmt::Expr(literal_bool(true))],
+        spans: Vec::new(),
     };
 
     let complex_block = Block {
@@ -141,6 +142,7 @@ fn lower_covers_all_expression_kinds() {
             Stmt::Return(Some(literal_int(18))),
             Stmt::Return(None),
         ],
+        spans: Vec::new(),
     };
 
     let function = Function {
@@ -155,13 +157,14 @@ fn lower_covers_all_expression_kinds() {
         return_type: Some(TypeExpr::Name("Int".into())),
         effect_row: vec![],
         body: complex_block,
+        span: Default::default(),
     };
 
     let module = Module {
         name: vec!["demo".into()],
         items: vec![Item::Function(function)],
     };
-    let lowered = lower::lower_module(&module);
+    let lowered = lower_checked(&module);
     let dump = lower::hir_to_string(&lowered);
 
     assert!(dump.contains("fn complex(value)"));
@@ -170,7 +173,7 @@ fn lower_covers_all_expression_kinds() {
     assert!(dump.contains("<expr>(8)"));
     assert!(dump.contains("index(array, 0)"));
     assert!(dump.contains("if(true, 1, 2)"));
-    assert!(dump.contains("assign(value, 11)"));
+    assert!(dump.contains("value = 11"));
     assert!(dump.contains("await(task)"));
     assert!(dump.contains("spawn(task)"));
     assert!(dump.contains("chan(1)"));
@@ -178,10 +181,10 @@ fn lower_covers_all_expression_kinds() {
     assert!(dump.contains("using(try(File::open), { true; })"));
     assert!(dump.contains("Option::Some(12)"));
     assert!(dump.contains("Row { value: 19 }"));
-    assert!(dump.contains("match()"));
-    assert!(dump.contains("for()"));
-    assert!(dump.contains("while()"));
-    assert!(dump.contains("loop()"));
+    assert!(dump.contains("match value { Option::Some(x) => 13 }"));
+    assert!(dump.contains("for item in items 14"));
+    assert!(dump.contains("while true 15"));
+    assert!(dump.contains("loop 16"));
     assert!(dump.contains("neg(3)"));
     assert!(dump.contains("not(true)"));
     assert!(dump.contains("ref(4)"));
@@ -3,15 +3,24 @@ use crate::semantics::{check, resolve};
 use crate::syntax::{ast::*, lexer, parser, token::TokenKind};
 use crate::{backend, ir, lower, pretty};
 
+mod analysis_tests;
 mod backend_tests;
+mod bytecode_tests;
 mod display_tests;
 mod helpers;
+mod ir_link_tests;
+mod ir_parse_tests;
+mod ir_serialize_tests;
 mod ir_tests;
 mod lexer_tests;
+mod llvm_tests;
 mod lowering_tests;
 mod parser_tests;
+mod passes_tests;
 mod pipeline_tests;
 mod pretty_tests;
 mod resolve_and_check_tests;
 mod runtime_tests;
 mod tooling_tests;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+mod x86_64_tests;
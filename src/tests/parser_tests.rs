@@ -76,6 +76,7 @@ fn parse_using_try_and_chan() {
 }
 
 #[test]
+#[allow(clippy::collapsible_match)]
 fn parse_cast_and_patterns() {
     let src = r#"
       module demo
@@ -167,3 +168,38 @@ fn list_type_parses() {
         _ => panic!(),
     }
 }
+
+#[test]
+fn list_literal_parses() {
+    let m = parse("module m\nfn g() { [1, 2, 3,] }");
+    let Item::Function(f) = &m.items[0] else {
+        panic!();
+    };
+    let Stmt::Expr(Expr::List(elements)) = &f.body.statements[0] else {
+        panic!("expected list literal, got {:?}", f.body.statements[0]);
+    };
+    assert_eq!(elements.len(), 3);
+    assert!(matches!(elements[2], Expr::Literal(Literal::Int(3))));
+}
+
+#[test]
+fn spawn_parses_as_a_member_name() {
+    let m = parse("module m\nfn run(process: Process) { process.spawn(\"ls\") }");
+    let Item::Function(f) = &m.items[0] else {
+        panic!();
+    };
+    let Stmt::Expr(Expr::Call { callee, args }) = &f.body.statements[0] else {
+        panic!("expected call, got {:?}", f.body.statements[0]);
+    };
+    match &**callee {
+        Expr::Field { expr, name } => {
+            assert_eq!(name, "spawn");
+            assert!(matches!(&**expr, Expr::Path(p) if p.segments == vec!["process"]));
+        }
+        other => panic!("expected member access, got {other:?}"),
+    }
+    assert_eq!(args.len(), 1);
+
+    // Outside member position `spawn` is still a keyword.
+    assert!(parser::parse_module("module m\nfn g() { let spawn = 1 }").is_err());
+}
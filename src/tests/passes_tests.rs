@@ -0,0 +1,570 @@
+use super::helpers::*;
+use super::*;
+
+use ir::analysis::AnalysisManager;
+use ir::passes::{
+    CommonSubexpressionElimination, ConstantFolding, DeadCodeElimination, DeadFunctionElimination,
+    Inliner, Pass, PassManager, ReferenceCounting, SimplifyCfg, TailCallElimination,
+};
+
+fn function<'a>(module: &'a ir::Module, name: &str) -> &'a ir::Function {
+    module
+        .functions
+        .iter()
+        .find(|function| function.name == name)
+        .expect("function exists")
+}
+
+fn count_insts(function: &ir::Function, pred: impl Fn(&ir::InstKind) -> bool) -> usize {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter(|inst| pred(&inst.kind))
+        .count()
+}
+
+fn run_main(module: ir::Module) -> String {
+    crate::interpreter::Interpreter::new(module)
+        .run()
+        .expect("interpreter run")
+}
+
+#[test]
+fn optimization_pipeline_folds_constant_branches_to_one_block() {
+    let src = r#"
+module demo
+
+fn main() -> Int {
+  let a = 2 * 3
+  let mut total = 0
+  if a > 5 {
+    total = a + 1
+  } else {
+    total = 1
+  }
+  total
+}
+"#;
+    let original = lower_src(src);
+    let mut optimized = original.clone();
+    let report = ir::passes::optimize(&mut optimized);
+
+    assert!(report.changed());
+    let main_fn = function(&optimized, "main");
+    assert_eq!(main_fn.blocks.len(), 1, "{main_fn:#?}");
+    assert_eq!(main_fn.blocks[0].instructions.len(), 1);
+    assert!(matches!(
+        main_fn.blocks[0].instructions[0].kind,
+        ir::InstKind::Literal(Literal::Int(7))
+    ));
+    assert!(ir::verify::verify_module(&optimized).is_ok());
+    assert_eq!(run_main(original), run_main(optimized));
+}
+
+#[test]
+fn constant_folding_leaves_runtime_failures_in_place() {
+    let src = r#"
+module demo
+
+fn main() -> Int {
+  let unused = 1 / 0
+  9223372036854775807 + 1
+}
+"#;
+    let mut module = lower_src(src);
+    PassManager::new()
+        .with_pass(ConstantFolding)
+        .with_pass(DeadCodeElimination)
+        .run(&mut module);
+
+    let main_fn = function(&module, "main");
+    let binaries = count_insts(main_fn, |kind| matches!(kind, ir::InstKind::Binary { .. }));
+    assert_eq!(binaries, 2, "division by zero and overflow must survive");
+}
+
+#[test]
+fn cse_reuses_dominating_values_but_not_list_reads_across_pushes() {
+    let src = r#"
+module demo
+
+fn main(x: Int) -> Int {
+  let xs = [1]
+  let before = xs.len()
+  xs.push(2)
+  let after = xs.len()
+  let a = x * 4
+  if x > 0 {
+    a + x * 4 + before + after
+  } else {
+    x * 4
+  }
+}
+"#;
+    let mut module = lower_src(src);
+    let changed = PassManager::new()
+        .with_pass(CommonSubexpressionElimination)
+        .run(&mut module)
+        .changed();
+    assert!(changed);
+
+    let main_fn = function(&module, "main");
+    let multiplies = count_insts(main_fn, |kind| {
+        matches!(
+            kind,
+            ir::InstKind::Binary {
+                op: BinaryOp::Mul,
+                ..
+            }
+        )
+    });
+    assert_eq!(multiplies, 1);
+    let lens = count_insts(main_fn, |kind| matches!(kind, ir::InstKind::ListLen { .. }));
+    assert_eq!(lens, 2, "len before and after push must stay distinct");
+    assert!(ir::verify::verify_module(&module).is_ok());
+}
+
+#[test]
+fn dce_removes_unused_pure_values_but_keeps_calls() {
+    let src = r#"
+module demo
+
+fn log(x: Int) -> Int {
+  x
+}
+
+fn main() -> Int {
+  let unused = 40 + 2
+  let also_unused = [unused]
+  log(1)
+  0
+}
+"#;
+    let mut module = lower_src(src);
+    PassManager::new()
+        .with_pass(DeadCodeElimination)
+        .run(&mut module);
+
+    let main_fn = function(&module, "main");
+    assert_eq!(
+        count_insts(main_fn, |kind| matches!(kind, ir::InstKind::Binary { .. })),
+        0
+    );
+    assert_eq!(
+        count_insts(main_fn, |kind| matches!(kind, ir::InstKind::List { .. })),
+        0
+    );
+    assert_eq!(
+        count_insts(main_fn, |kind| matches!(kind, ir::InstKind::Call { .. })),
+        1
+    );
+}
+
+#[test]
+fn simplify_cfg_keeps_loops_and_their_phis_consistent() {
+    let src = r#"
+module demo
+
+fn main() -> Int {
+  let mut i = 0
+  let mut acc = 0
+  while i < 5 {
+    if i == 2 {
+      i = i + 1
+      continue
+    }
+    acc = acc + i
+    i = i + 1
+  }
+  acc
+}
+"#;
+    let original = lower_src(src);
+    let mut module = original.clone();
+    PassManager::new().with_pass(SimplifyCfg).run(&mut module);
+
+    assert!(function(&module, "main").blocks.len() <= function(&original, "main").blocks.len());
+    assert!(ir::verify::verify_module(&module).is_ok());
+    assert_eq!(run_main(module), run_main(original));
+}
+
+#[test]
+fn pass_manager_records_dumps_only_for_changing_passes() {
+    let src = r#"
+module demo
+
+fn main() -> Int {
+  1 + 2
+}
+"#;
+    let mut module = lower_src(src);
+    let report = PassManager::optimization_pipeline()
+        .record_dumps(true)
+        .run(&mut module);
+
+    let dumped: Vec<&str> = report.dumps.iter().map(|dump| dump.pass).collect();
+    assert_eq!(dumped, vec!["const-fold", "dce"]);
+    assert_eq!(report.rounds(), 2, "the second round confirms the fixpoint");
+    let fold = &report.dumps[0];
+    let before = backend::text::render_module(&fold.before);
+    let after = backend::text::render_module(&fold.after);
+    assert!(before.contains("+ %0, %1"), "{before}");
+    assert!(after.contains("= 3 : Int"), "{after}");
+}
+
+#[test]
+fn backend_options_optimize_runs_the_pipeline() {
+    let src = r#"
+module demo
+
+fn main() -> Int {
+  2 * 21
+}
+"#;
+    let module = lower_src(src);
+    let backend = backend::text::TextBackend;
+    let plain =
+        backend::run(&backend, &module, &backend::BackendOptions::default()).expect("text backend");
+    let optimized = backend::run(
+        &backend,
+        &module,
+        &backend::BackendOptions {
+            optimize: true,
+            ..backend::BackendOptions::default()
+        },
+    )
+    .expect("text backend");
+
+    assert!(plain.contains("* %0, %1"), "{plain}");
+    assert!(!optimized.contains('*'), "{optimized}");
+    assert!(optimized.contains("= 42 : Int"), "{optimized}");
+}
+
+fn call_count(module: &ir::Module) -> usize {
+    backend::text::render_module(module)
+        .matches("= call ")
+        .count()
+}
+
+#[test]
+fn inliner_reduces_calls_and_preserves_behaviour() {
+    let src = r#"
+module demo
+
+fn square(x: Int) -> Int {
+  x * x
+}
+
+fn clamp(x: Int) -> Int {
+  if x > 100 {
+    return 100
+  }
+  if x < 0 {
+    return 0
+  }
+  x
+}
+
+fn main() -> Int {
+  let mut total = 0
+  let mut i = 0
+  while i < 12 {
+    total = total + clamp(square(i) - 20)
+    i = i + 1
+  }
+  total
+}
+"#;
+    let original = lower_src(src);
+    let mut inlined = original.clone();
+    let report = PassManager::new()
+        .with_pass(Inliner::default())
+        .run(&mut inlined);
+
+    assert!(report.changed());
+    assert_eq!(call_count(&original), 2);
+    assert_eq!(
+        call_count(&inlined),
+        0,
+        "{}",
+        backend::text::render_module(&inlined)
+    );
+    assert!(ir::verify::verify_module(&inlined).is_ok());
+    assert_eq!(run_main(original.clone()), run_main(inlined));
+
+    let mut optimized = original.clone();
+    ir::passes::optimize(&mut optimized);
+    assert_eq!(call_count(&optimized), 0);
+    assert_eq!(run_main(original), run_main(optimized));
+}
+
+#[test]
+fn inliner_skips_recursive_effectful_and_large_callees() {
+    let src = r#"
+module demo
+
+fn fact(n: Int) -> Int {
+  if n <= 1 { 1 } else { n * fact(n - 1) }
+}
+
+fn is_even(n: Int) -> Bool {
+  if n == 0 { true } else { is_odd(n - 1) }
+}
+
+fn is_odd(n: Int) -> Bool {
+  if n == 0 { false } else { is_even(n - 1) }
+}
+
+fn record(xs: [Int], x: Int) -> Int {
+  xs.push(x)
+  x
+}
+
+fn double(x: Int) -> Int {
+  x + x
+}
+
+fn main() -> Int {
+  let xs = [1]
+  let parity = if is_even(4) { 1 } else { 0 }
+  fact(5) + record(xs, 2) + parity + double(3) + xs.len()
+}
+"#;
+    let original = lower_src(src);
+
+    let mut module = original.clone();
+    PassManager::new()
+        .with_pass(Inliner::default())
+        .run(&mut module);
+    let main_calls = backend::text::render_module(&ir::Module {
+        functions: vec![function(&module, "main").clone()],
+        ..module.clone()
+    });
+    assert!(main_calls.contains("call fact("), "{main_calls}");
+    assert!(main_calls.contains("call is_even("), "{main_calls}");
+    assert!(main_calls.contains("call record("), "{main_calls}");
+    assert!(!main_calls.contains("call double("), "{main_calls}");
+    assert_eq!(run_main(original.clone()), run_main(module));
+
+    let mut module = original;
+    let changed = PassManager::new()
+        .with_pass(Inliner::with_max_callee_size(1))
+        .run(&mut module)
+        .changed();
+    assert!(!changed, "no callee fits in a single instruction");
+}
+
+#[test]
+fn inlined_instructions_keep_callee_provenance() {
+    let src = r#"
+module demo
+
+fn double(x: Int) -> Int {
+  x * 2
+}
+
+fn main() -> Int {
+  double(3) + 1
+}
+"#;
+    let mut module = lower_src(src);
+    PassManager::new()
+        .with_pass(Inliner::default())
+        .run(&mut module);
+
+    let main = function(&module, "main");
+    let multiply = main
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .find(|inst| {
+            matches!(
+                inst.kind,
+                ir::InstKind::Binary {
+                    op: BinaryOp::Mul,
+                    ..
+                }
+            )
+        })
+        .expect("inlined multiply");
+    let provenance = multiply.provenance.as_ref().expect("provenance");
+    assert_eq!(provenance.function, "double");
+    assert_eq!(provenance.span.line, 5);
+}
+
+#[test]
+fn dead_function_elimination_keeps_entry_points_and_used_types() {
+    let src = r#"
+module demo
+
+type Point = { x: Int, y: Int }
+type Unused = { flag: Bool, scale: Float }
+
+fn origin() -> Point {
+  Point { x: 0, y: 0 }
+}
+
+fn stale() -> Unused {
+  Unused { flag: true, scale: 1.5 }
+}
+
+pub fn api(n: Int) -> Int {
+  n + 1
+}
+
+fn main() -> Int {
+  let p = origin()
+  p.x + 2
+}
+"#;
+    let original = lower_src(src);
+    let names = |module: &ir::Module| -> Vec<String> {
+        module
+            .functions
+            .iter()
+            .map(|function| function.name.clone())
+            .collect()
+    };
+
+    let mut executable = original.clone();
+    let report = PassManager::new()
+        .with_pass(DeadFunctionElimination::default())
+        .run(&mut executable);
+    assert!(report.changed());
+    assert_eq!(names(&executable), vec!["origin", "main"]);
+    assert!(executable.types.lookup_named("Point").is_some());
+    assert!(executable.types.lookup_named("Unused").is_none());
+    assert!(
+        executable.types.entries().count() < original.types.entries().count(),
+        "the unused record and its field types are dropped"
+    );
+    assert!(ir::verify::verify_module(&executable).is_ok());
+    assert_eq!(run_main(original.clone()), run_main(executable));
+
+    let mut library = original.clone();
+    PassManager::new()
+        .with_pass(DeadFunctionElimination::library())
+        .run(&mut library);
+    assert_eq!(names(&library), vec!["origin", "api", "main"]);
+    assert!(function(&library, "api").is_public);
+
+    let artifact = backend::run(
+        &backend::native::NativeBackend,
+        &original,
+        &backend::BackendOptions::default(),
+    )
+    .expect("native backend");
+    assert!(
+        !artifact.c_source.contains("stale"),
+        "{}",
+        artifact.c_source
+    );
+    assert!(
+        artifact.c_source.contains("origin"),
+        "{}",
+        artifact.c_source
+    );
+}
+
+#[test]
+fn tail_call_elimination_turns_self_recursion_into_a_loop() {
+    let src = r#"
+module demo
+
+fn count(n: Int, acc: Int) -> Int {
+  if n == 0 { acc } else { count(n - 1, acc + 1) }
+}
+
+fn is_even(n: Int) -> Bool {
+  if n == 0 { true } else { is_odd(n - 1) }
+}
+
+fn is_odd(n: Int) -> Bool {
+  if n == 0 { false } else { is_even(n - 1) }
+}
+
+fn main() -> Int {
+  let parity = if is_even(10) { 1 } else { 0 }
+  count(1000000, parity)
+}
+"#;
+    let mut module = lower_src(src);
+    let report = PassManager::new()
+        .with_pass(TailCallElimination)
+        .run(&mut module);
+    assert!(report.changed());
+    assert!(ir::verify::verify_module(&module).is_ok());
+
+    let count = function(&module, "count");
+    let calls = |function: &ir::Function| {
+        count_insts(function, |kind| matches!(kind, ir::InstKind::Call { .. }))
+    };
+    assert_eq!(calls(count), 0);
+    assert_eq!(count.blocks[0].id, ir::BlockId(0), "entry keeps its ID");
+    let cfg = ir::analysis::ControlFlowGraph::new(count);
+    let loops = ir::analysis::LoopInfo::new(&cfg, &ir::analysis::DominatorTree::new(&cfg));
+    assert_eq!(loops.loops().len(), 1);
+    assert_eq!(
+        count_insts(count, |kind| matches!(kind, ir::InstKind::Phi { .. })),
+        3,
+        "one phi per parameter plus the returned value"
+    );
+
+    // Sibling calls now return directly but stay calls for the backends.
+    let is_even = function(&module, "is_even");
+    assert_eq!(calls(is_even), 1);
+    assert!(
+        is_even
+            .blocks
+            .iter()
+            .any(|block| block.tail_call().is_some())
+    );
+
+    let rerun = PassManager::new()
+        .with_pass(TailCallElimination)
+        .run(&mut module.clone());
+    assert!(
+        !rerun.changed(),
+        "the pass reaches a fixed point in one run"
+    );
+    assert!(run_main(module).contains("1000001"));
+}
+
+#[test]
+fn reference_counting_releases_strings_where_they_die() {
+    let src = r#"
+module demo
+
+fn pick(flag: Bool, a: String) -> String {
+  let b = a + "!"
+  if flag { a } else { b }
+}
+
+fn main() -> Int {
+  0
+}
+"#;
+    let mut module = lower_src(src);
+    let changed = ReferenceCounting.run(&mut module, &mut AnalysisManager::new());
+    assert!(changed);
+    assert!(ir::verify::verify_module(&module).is_ok());
+
+    // `b` dies on the edge that returns `a`, which needs its own reference
+    // for the phi; the other edge hands `b`'s reference to the phi.
+    let pick = function(&module, "pick");
+    let kinds = |block: usize| -> Vec<String> {
+        pick.blocks[block]
+            .instructions
+            .iter()
+            .map(|inst| match inst.kind {
+                ir::InstKind::Retain { value } => format!("retain {}", value.index()),
+                ir::InstKind::Release { value } => format!("release {}", value.index()),
+                _ => "other".to_string(),
+            })
+            .collect()
+    };
+    assert_eq!(kinds(0), ["other", "other"]);
+    assert_eq!(kinds(1), ["release 3", "retain 1"]);
+    assert!(kinds(2).is_empty());
+    assert_eq!(count_insts(function(&module, "main"), |_| true), 1);
+}
@@ -46,7 +46,7 @@ fn classify(result: TaskResult) -> Int {
     );
     assert!(pretty.contains("fn classify(result: TaskResult) -> Int"));
 
-    let lowered = lower::lower_module(&module);
+    let lowered = lower_checked(&module);
     let dump = lower::hir_to_string(&lowered);
     assert!(dump.contains("spawn(network::fetch(job_id, net))"));
     assert!(dump.contains("await(pending)"));
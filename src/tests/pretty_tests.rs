@@ -60,7 +60,11 @@ fn pretty_print_handles_every_type_form() {
                 ],
                 return_type: Some(TypeExpr::Tuple(vec![TypeExpr::Unit, TypeExpr::SelfType])),
                 effect_row: vec!["io".into(), "net".into()],
-                body: Block { statements: vec![] },
+                body: Block {
+                    statements: vec![],
+                    spans: Vec::new(),
+                },
+                span: Default::default(),
             }),
             Item::Impl(ImplBlock {
                 trait_path: path(["Display"]),
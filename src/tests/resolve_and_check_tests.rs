@@ -27,6 +27,7 @@ fn exhaustive_module() -> Module {
             Stmt::Expr(match_expr),
             Stmt::Expr(Expr::Block(Block {
                 statements: vec![Stmt::Expr(literal_int(3))],
+                spans: Vec::new(),
             })),
             Stmt::Expr(Expr::Ctor {
                 path: path(["State", "Busy"]),
@@ -77,9 +78,11 @@ fn exhaustive_module() -> Module {
                         name: "v".into(),
                         value: literal_int(11),
                     })],
+                    spans: Vec::new(),
                 },
             }),
         ],
+        spans: Vec::new(),
     };
 
     Module {
@@ -112,6 +115,7 @@ fn exhaustive_module() -> Module {
                 return_type: Some(TypeExpr::Name("Int".into())),
                 effect_row: vec![],
                 body,
+                span: Default::default(),
             }),
         ],
     }
@@ -132,6 +136,35 @@ fn diagnostics_require_effect_row_for_calls() {
     );
 }
 
+#[test]
+fn check_records_resolved_expression_types() {
+    let module = parse(
+        "module m\ntype Row = { qty: Int, price: F64 }\nfn cost(row: Row) -> F64 { row.price }",
+    );
+    let result = check::check_module(&module);
+    assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+
+    // IDs are positional, so they name the same nodes in a copy.
+    let copy = module.clone();
+    let ids = check::ExprIds::new(&copy);
+    let Item::Function(cost) = &copy.items[1] else {
+        panic!("expected function");
+    };
+    let Stmt::Expr(field @ Expr::Field { expr: base, .. }) = &cost.body.statements[0] else {
+        panic!("expected field access");
+    };
+    let type_of = |expr: &Expr| ids.get(expr).and_then(|id| result.types.get(id));
+    assert!(matches!(
+        type_of(field),
+        Some(TypeExpr::Name(name)) if name == "Float"
+    ));
+    assert!(matches!(
+        type_of(base),
+        Some(TypeExpr::Name(name)) if name == "Row"
+    ));
+    assert!(type_of(&literal_int(1)).is_none());
+}
+
 #[test]
 fn diagnostics_detect_duplicate_capabilities() {
     let module = parse("module m\nfn writer(io: IO) -> Unit !{io, io} { () }");
@@ -260,7 +293,9 @@ fn resolve_collects_symbols_and_paths() {
                             segments: vec!["first".into()],
                         })),
                     ],
+                    spans: Vec::new(),
                 },
+                span: Default::default(),
             }),
         ],
     };
@@ -328,7 +363,9 @@ fn resolve_reports_unresolved_paths() {
                         args: vec![],
                     }),
                 ],
+                spans: Vec::new(),
             },
+            span: Default::default(),
         })],
     };
 
@@ -589,7 +626,9 @@ fn resolve_cross_module_paths() {
             effect_row: vec![],
             body: Block {
                 statements: vec![Stmt::Expr(Expr::Path(path(["lhs"])))],
+                spans: Vec::new(),
             },
+            span: Default::default(),
         })],
     };
 
@@ -636,7 +675,9 @@ fn resolve_cross_module_paths() {
                         args: vec![Expr::Path(path(["total"]))],
                     }),
                 ],
+                spans: Vec::new(),
             },
+            span: Default::default(),
         })],
     };
 
@@ -778,6 +819,34 @@ fn type_checker_validates_capabilities_and_calls() {
     );
 }
 
+#[test]
+fn type_checker_instantiates_generic_constructors() {
+    let module = parse(
+        "module demo\n\
+         type Result[T, E] = Ok(T) | Err(E)\n\
+         type Option[T] = Some(T) | None\n\
+         fn safe_div(a: Int, b: Int) -> Result[Int, Int] {\n\
+           if b == 0 { Err(1) } else { Ok(a / b) }\n\
+         }\n\
+         fn unwrap(x: Option[Int]) -> Int {\n\
+           match x { Some(v) => v, None => 0 }\n\
+         }\n\
+         fn mislabeled() -> Option[Int] {\n\
+           Some(\"text\")\n\
+         }\n",
+    );
+
+    let result = check::check_module(&module);
+    assert_eq!(
+        result
+            .diagnostics
+            .iter()
+            .map(|diag| diag.message.as_str())
+            .collect::<Vec<_>>(),
+        vec!["function 'mislabeled' returns 'Option[String]' but expected 'Option[Int]'"]
+    );
+}
+
 #[test]
 fn diagnostics_report_capability_misuse() {
     let duplicate_cap = parse("module m\nfn bad(io: IO, other: IO) -> Unit !{io, io} { other }");
@@ -512,6 +512,7 @@ fn runtime_trace_summary_accumulates_metrics() {
 }
 
 #[test]
+#[allow(clippy::collapsible_match)]
 fn runtime_deterministic_shims_capture_state() {
     let bundle = Runtime::with_deterministic_shims().expect("runtime setup");
     let runtime = bundle.runtime();
@@ -1,4 +1,6 @@
+use crate::backend::{self, cache::BuildCache, cache::CacheKey};
 use crate::tooling::{MetricValue, PipelineSnapshot, StageStatus};
+use crate::{check, ir, lower, parser};
 
 #[test]
 fn pipeline_snapshot_reports_stage_metrics() {
@@ -100,3 +102,32 @@ fn identity(x: Int) -> Int {
     assert!(json.contains("\"diagnostics\""));
     assert!(json.contains("\"ok\":true"));
 }
+
+#[test]
+fn pipeline_snapshot_reports_native_cache_state() {
+    let src = r#"
+module tooling.cache
+
+fn main() -> Int {
+  0
+}
+"#;
+
+    let module = parser::parse_module(src).expect("parse module");
+    let typed = ir::lower_module(&lower::lower_module(
+        &module,
+        &check::check_module(&module).types,
+    ));
+    let key = CacheKey::new(&typed, &backend::BackendOptions::default(), "executable");
+    // Probing never creates the cache directory.
+    let cache =
+        BuildCache::new(std::env::temp_dir().join(format!("mica-empty-cache-{}", key.hex())));
+    let json = PipelineSnapshot::capture(src)
+        .with_native_cache(&cache, &key)
+        .to_json_string();
+
+    assert!(json.contains(&format!("\"key\":\"{}\"", key.hex())));
+    assert!(json.contains("\"cached\":false"));
+    assert!(json.contains("\"total_hits\":0"));
+    assert!(json.contains("\"total_misses\":0"));
+}
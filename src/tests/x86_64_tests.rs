@@ -0,0 +1,263 @@
+use std::fs;
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::helpers::*;
+use super::*;
+use crate::backend::x86_64::{ElfArtifact, X86_64Backend};
+use crate::backend::{BackendError, BackendResult};
+
+fn compile(src: &str) -> BackendResult<ElfArtifact> {
+    let typed = ir::lower_module(&lower_checked(&parse(src)));
+    backend::run(&X86_64Backend, &typed, &backend::BackendOptions::default())
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    std::env::temp_dir().join(format!("mica-x86-{name}-{nanos}"))
+}
+
+fn build_and_run(src: &str, name: &str) -> Output {
+    let artifact = compile(src).expect("x86-64 artifact");
+    let exe_path = temp_path(name);
+    artifact
+        .link_executable(&exe_path)
+        .expect("write executable");
+    let output = Command::new(&exe_path).output().expect("execute binary");
+    fs::remove_file(&exe_path).ok();
+    output
+}
+
+#[test]
+fn x86_64_executables_run_loops_recursion_and_spills() {
+    let src = r#"
+module demo.elf
+
+fn fib(n: Int) -> Int {
+  if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+}
+
+fn swap(n: Int) -> Int {
+  let mut a = 1
+  let mut b = 2
+  let mut i = 0
+  while i < n {
+    let t = a
+    a = b
+    b = t
+    i = i + 1
+  }
+  a * 10 + b
+}
+
+fn mix(a: Int, b: Int, c: Int, d: Int, e: Int, f: Int) -> Int {
+  let g = a * 2
+  let h = b * 3
+  let i = c * 5
+  let j = d * 7
+  let k = e * 11
+  let l = f * 13
+  a + b + c + d + e + f + g + h + i + j + k + l + (g + h) + (i + j) + (k + l)
+}
+
+fn main() -> Int {
+  fib(10) + swap(3) + mix(1, 2, 3, 4, 5, 6) - 389 + 17 % 5 + 17 / 5 - 60
+}
+"#;
+
+    let output = build_and_run(src, "arith");
+    // 55 + 21 + 389 - 389 + 2 + 3 - 60
+    assert_eq!(output.status.code(), Some(21));
+}
+
+#[test]
+fn x86_64_executables_print_strings_and_use_records_and_lists() {
+    let src = r#"
+module demo.elf_data
+
+type Point = { x: Int, y: Int }
+
+fn total(xs: [Int]) -> Int {
+  let mut sum = 0
+  for x in xs {
+    sum = sum + x
+  }
+  sum
+}
+
+fn greet(io: IO, name: String) !{io} {
+  if name == "world" {
+    io.println("hello, world")
+  } else {
+    io.println("who?")
+  }
+}
+
+fn main(io: IO) -> Int {
+  let xs = [1, 2, 3]
+  let mut i = 0
+  while i < 20 {
+    xs.push(i)
+    i = i + 1
+  }
+  greet(io, "world")
+  greet(io, "nobody")
+  let p = Point { x: 3, y: 4 }
+  total(xs) + xs.len() - xs[22] + p.x * p.y - 200
+}
+"#;
+
+    let output = build_and_run(src, "data");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "hello, world\nwho?\n"
+    );
+    // 196 + 23 - 19 + 12 - 200
+    assert_eq!(output.status.code(), Some(12));
+}
+
+#[test]
+fn x86_64_executables_trap_like_the_c_runtime() {
+    let bounds = r#"
+module demo.elf_bounds
+
+fn main() -> Int {
+  let xs = [1, 2]
+  xs[5]
+}
+"#;
+    let output = build_and_run(bounds, "bounds");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: list index 5 out of bounds for length 2\n"
+    );
+
+    let division = r#"
+module demo.elf_division
+
+fn divide(a: Int, b: Int) -> Int {
+  a / b
+}
+
+fn main() -> Int {
+  divide(1, 0)
+}
+"#;
+    let output = build_and_run(division, "division");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: division by zero\n"
+    );
+}
+
+#[test]
+fn x86_64_sibling_tail_calls_run_in_constant_stack() {
+    let src = r#"
+module demo.elf_tail
+
+fn is_even(n: Int) -> Bool {
+  if n == 0 { true } else { is_odd(n - 1) }
+}
+
+fn is_odd(n: Int) -> Bool {
+  if n == 0 { false } else { is_even(n - 1) }
+}
+
+fn main() -> Int {
+  if is_even(10000001) { 1 } else { 0 }
+}
+"#;
+
+    let output = build_and_run(src, "tail");
+    assert!(output.status.success(), "exit: {}", output.status);
+}
+
+#[test]
+fn x86_64_objects_are_relocatable_and_link_with_ld() {
+    let src = r#"
+module demo.elf_object
+
+pub fn answer() -> Int {
+  42
+}
+
+fn main(io: IO) -> Int {
+  io.println("linked")
+  answer() - 42
+}
+"#;
+
+    let artifact = compile(src).expect("x86-64 artifact");
+    let object = &artifact.object;
+    assert_eq!(&object[..4], b"\x7fELF");
+    assert_eq!(u16::from_le_bytes([object[16], object[17]]), 1, "ET_REL");
+    assert_eq!(
+        u16::from_le_bytes([object[18], object[19]]),
+        62,
+        "EM_X86_64"
+    );
+    let executable = artifact.executable.as_ref().expect("module has main");
+    assert_eq!(u16::from_le_bytes([executable[16], executable[17]]), 2);
+
+    let obj_path = temp_path("object").with_extension("o");
+    let exe_path = temp_path("object-linked");
+    artifact.write_object(&obj_path).expect("write object");
+    // The system linker is optional; the built-in one is tested above.
+    let Ok(linked) = Command::new("ld")
+        .arg(&obj_path)
+        .arg("-o")
+        .arg(&exe_path)
+        .output()
+    else {
+        fs::remove_file(&obj_path).ok();
+        return;
+    };
+    fs::remove_file(&obj_path).ok();
+    assert!(
+        linked.status.success(),
+        "ld failed: {}",
+        String::from_utf8_lossy(&linked.stderr)
+    );
+    let output = Command::new(&exe_path).output().expect("execute binary");
+    fs::remove_file(&exe_path).ok();
+    assert!(output.status.success(), "exit: {}", output.status);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "linked\n");
+}
+
+#[test]
+fn x86_64_backend_rejects_floats_and_libraries_have_no_executable() {
+    let src = r#"
+module demo.elf_float
+
+fn main() -> Float {
+  1.5
+}
+"#;
+    let err = compile(src).expect_err("floats are unsupported");
+    assert!(
+        matches!(&err, BackendError::Unsupported(msg) if msg.contains("Float")),
+        "unexpected error: {err}"
+    );
+
+    let library = r#"
+module demo.elf_library
+
+pub fn double(x: Int) -> Int {
+  x * 2
+}
+"#;
+    let artifact = compile(library).expect("x86-64 artifact");
+    assert!(artifact.executable.is_none());
+    let err = artifact
+        .link_executable(temp_path("library"))
+        .expect_err("nothing to run");
+    assert!(
+        err.to_string().contains("no 'main'"),
+        "unexpected error: {err}"
+    );
+}
@@ -1,4 +1,5 @@
 use crate::{
+    backend::cache::{BuildCache, CacheKey},
     check, ir, lexer,
     lower::{self, HItem},
     parser, resolve,
@@ -105,7 +106,7 @@ impl PipelineSnapshot {
                 .with_metric("ok", MetricValue::Bool(check_result.diagnostics.is_empty())),
         );
 
-        let lowered = lower::lower_module(&module);
+        let lowered = lower::lower_module(&module, &check_result.types);
         let function_count = lowered
             .items
             .iter()
@@ -137,6 +138,22 @@ impl PipelineSnapshot {
         }
     }
 
+    /// Adds a `native-cache` stage: the build cache key of the program,
+    /// whether this build would hit (`cached`), and the totals of `cache`
+    /// across all builds (`total_hits`, `total_misses`, `entries`).
+    pub fn with_native_cache(mut self, cache: &BuildCache, key: &CacheKey) -> Self {
+        let stats = cache.stats();
+        self.stages.push(
+            PipelineStage::success("native-cache")
+                .with_metric("key", MetricValue::Text(key.hex()))
+                .with_metric("cached", MetricValue::Bool(cache.contains(key)))
+                .with_metric("total_hits", MetricValue::Integer(stats.hits))
+                .with_metric("total_misses", MetricValue::Integer(stats.misses))
+                .with_metric("entries", MetricValue::Integer(stats.entries)),
+        );
+        self
+    }
+
     pub fn module_path(&self) -> &[String] {
         &self.module_path
     }
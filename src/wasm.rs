@@ -212,7 +212,8 @@ pub fn lower_code(source: &str) -> String {
 
 fn lower_internal(source: &str) -> Result<String, String> {
     let ast = parser::parse_module(source).map_err(|e| format!("Parser error: {}", e))?;
-    let hir = lower::lower_module(&ast);
+    let checked = check::check_module(&ast);
+    let hir = lower::lower_module(&ast, &checked.types);
 
     Ok(lower::hir_to_string(&hir))
 }
@@ -248,7 +249,8 @@ pub fn generate_ir(source: &str) -> String {
 
 fn generate_ir_internal(source: &str) -> Result<String, String> {
     let ast = parser::parse_module(source).map_err(|e| format!("Parser error: {}", e))?;
-    let hir = lower::lower_module(&ast);
+    let checked = check::check_module(&ast);
+    let hir = lower::lower_module(&ast, &checked.types);
     let ir_module = ir::lower_module(&hir);
 
     Ok(format!("{:#?}", ir_module))
@@ -285,7 +287,8 @@ pub fn run_code(source: &str) -> String {
 
 fn run_internal(source: &str) -> Result<String, String> {
     let ast = parser::parse_module(source).map_err(|e| format!("Parser error: {}", e))?;
-    let hir = lower::lower_module(&ast);
+    let checked = check::check_module(&ast);
+    let hir = lower::lower_module(&ast, &checked.types);
     let ir_module = ir::lower_module(&hir);
 
     let mut interp = interpreter::Interpreter::new(ir_module);